@@ -52,6 +52,11 @@ where
         res1
     }
 
+    /// Number of elements in the heap.
+    pub fn len(&self) -> usize {
+        self.key_to_order_and_value.len()
+    }
+
     /// Insert element.
     ///
     /// If the element (compared by `K`) already exists, it will be returned.
@@ -0,0 +1,745 @@
+//! Implements a [`CacheBackend`] that stores entries as files on local disk.
+use std::{
+    any::Any,
+    collections::HashSet,
+    fmt::Debug,
+    fs::{self, File},
+    hash::Hash,
+    io::{self, Read},
+    marker::PhantomData,
+    path::{Path, PathBuf},
+};
+
+use bytes::Bytes;
+use observability_deps::tracing::{info, warn};
+use parking_lot::Mutex;
+
+use crate::addressable_heap::AddressableHeap;
+
+use super::CacheBackend;
+
+/// Magic bytes at the start of every cache file.
+const MAGIC: &[u8; 4] = b"IOXC";
+
+/// Extension of cache files.
+const FILE_EXTENSION: &str = "cache";
+
+/// Extension of files that are currently being written.
+const TMP_EXTENSION: &str = "tmp";
+
+/// Size of magic bytes and key length (u32).
+const HEADER_LEN: usize = 8;
+
+/// Size of the checksum.
+const TRAILER_LEN: usize = 4;
+
+/// Types that can be stored in a [`DiskBackend`], either as key or as value.
+pub trait DiskEncode: Sized {
+    /// Append the serialized form of `self` to `buf`.
+    ///
+    /// For keys, the encoding MUST be stable across process restarts.
+    fn encode(&self, buf: &mut Vec<u8>);
+
+    /// Deserialize data that was produced by [`encode`](Self::encode).
+    ///
+    /// Returns `None` if `data` is invalid.
+    fn decode(data: Bytes) -> Option<Self>;
+}
+
+impl DiskEncode for u8 {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        buf.push(*self);
+    }
+
+    fn decode(data: Bytes) -> Option<Self> {
+        match data.as_ref() {
+            [v] => Some(*v),
+            _ => None,
+        }
+    }
+}
+
+impl DiskEncode for String {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(self.as_bytes());
+    }
+
+    fn decode(data: Bytes) -> Option<Self> {
+        String::from_utf8(data.to_vec()).ok()
+    }
+}
+
+impl DiskEncode for Bytes {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(self);
+    }
+
+    fn decode(data: Bytes) -> Option<Self> {
+        Some(data)
+    }
+}
+
+/// [`CacheBackend`] that stores every entry as a file within a local directory.
+///
+/// The backend is bounded by the total size of its files. When this limit is exceeded, the least recently used
+/// entries are evicted.
+///
+/// Every file contains the encoded key and a checksum, which is validated on every read. Entries that cannot be read
+/// or fail validation are removed and reported as missing. File names are derived from a hash of the key; if two keys
+/// hash to the same name, the later one gets a different file, so entries never overwrite each other.
+///
+/// Files written by a previous process are indexed (but not read) when the backend is [created](Self::new), so the
+/// backend is NOT necessarily empty and can therefore not be wrapped into a
+/// [`PolicyBackend`](crate::backend::policy::PolicyBackend).
+///
+/// All [`CacheBackend`] operations perform blocking file system IO. Backends that are shared between threads should
+/// use [`get_shared`](Self::get_shared) and [`set_shared`](Self::set_shared) instead, which only hold the lock to
+/// update the in-memory index and perform the file system IO without it.
+#[derive(Debug)]
+pub struct DiskBackend<K, V>
+where
+    K: DiskEncode + Clone + Eq + Hash + Ord + Debug + Send + 'static,
+    V: DiskEncode + Clone + Debug + Send + 'static,
+{
+    /// Directory that holds the cache files.
+    dir: PathBuf,
+
+    /// Maximum total size of all cache files in bytes.
+    limit: u64,
+
+    /// Current total size of all cache files in bytes.
+    used: u64,
+
+    /// Known entries, ordered by last usage.
+    entries: AddressableHeap<K, Entry, u64>,
+
+    /// Names of all files that are in use, either by an entry, by a write that is in progress or by a removal that is
+    /// in progress.
+    ///
+    /// A name is only released once its file is gone, so it cannot be handed out to another key while the file still
+    /// exists.
+    files: HashSet<String>,
+
+    /// Logical clock used to order entries by last usage.
+    clock: u64,
+
+    _phantom: PhantomData<V>,
+}
+
+/// Index data of a single cache file.
+#[derive(Debug)]
+struct Entry {
+    /// File size in bytes.
+    size: u64,
+
+    /// File name within the cache directory.
+    file: String,
+}
+
+impl<K, V> DiskBackend<K, V>
+where
+    K: DiskEncode + Clone + Eq + Hash + Ord + Debug + Send + 'static,
+    V: DiskEncode + Clone + Debug + Send + 'static,
+{
+    /// Create a backend that stores its files in `dir` and uses at most `limit` bytes of disk space.
+    ///
+    /// The directory is created if it does not exist. Valid cache files already present in the directory are indexed
+    /// (oldest files are considered least recently used), so a restarted process does not start cold. Their content is
+    /// only read on first access. Leftovers of interrupted writes and invalid files are removed.
+    pub fn new(dir: impl Into<PathBuf>, limit: u64) -> io::Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+
+        let mut found = vec![];
+        for dir_entry in fs::read_dir(&dir)? {
+            let dir_entry = dir_entry?;
+            let path = dir_entry.path();
+
+            match path.extension().and_then(|ext| ext.to_str()) {
+                Some(FILE_EXTENSION) => {}
+                Some(TMP_EXTENSION) => {
+                    remove_file(&path);
+                    continue;
+                }
+                _ => continue,
+            }
+            let Some(file) = dir_entry.file_name().to_str().map(ToOwned::to_owned) else {
+                continue;
+            };
+
+            match read_key::<K>(&path) {
+                Ok(k) => {
+                    let metadata = dir_entry.metadata()?;
+                    found.push((metadata.modified().ok(), k, metadata.len(), file));
+                }
+                Err(e) => {
+                    warn!(path=%path.display(), %e, "removing invalid disk cache file");
+                    remove_file(&path);
+                }
+            }
+        }
+
+        // least recently written first
+        found.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let mut backend = Self {
+            dir,
+            limit,
+            used: 0,
+            entries: AddressableHeap::new(),
+            files: HashSet::new(),
+            clock: 0,
+            _phantom: PhantomData::default(),
+        };
+        let n_found = found.len();
+        let mut obsolete = vec![];
+        for (_modified, k, size, file) in found {
+            backend.files.insert(file.clone());
+            // the same key may be stored twice if a process crashed while replacing an entry, keep the newer file
+            obsolete.extend(backend.insert_entry(k, size, file));
+        }
+        obsolete.extend(backend.evict());
+        backend.remove_files(obsolete);
+
+        info!(
+            dir=%backend.dir.display(),
+            n_found,
+            n_entries=backend.n_entries(),
+            used_bytes=backend.used,
+            "initialized disk cache",
+        );
+
+        Ok(backend)
+    }
+
+    /// Number of entries.
+    pub fn n_entries(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Total size of all cache files in bytes.
+    pub fn used_bytes(&self) -> u64 {
+        self.used
+    }
+
+    /// Get entry from a backend that is shared between threads.
+    ///
+    /// The lock is NOT held while the file is read.
+    pub fn get_shared(backend: &Mutex<Self>, k: &K) -> Option<V> {
+        let (dir, file) = {
+            let mut guard = backend.lock();
+            let file = guard.lookup(k)?;
+            (guard.dir.clone(), file)
+        };
+
+        match read_file(&dir.join(&file), &encode_key(k)) {
+            Ok(v) => Some(v),
+            Err(e) => {
+                // the entry might have been evicted or replaced in the meantime, which is NOT an error
+                let unreadable = backend.lock().take_if_file(k, &file);
+                if let Some(file) = unreadable {
+                    warn!(key=?k, %e, "dropping unreadable disk cache entry");
+                    Self::remove_files_shared(backend, &dir, vec![file]);
+                }
+                None
+            }
+        }
+    }
+
+    /// Set entry in a backend that is shared between threads.
+    ///
+    /// The lock is NOT held while the file is written or while evicted files are removed.
+    pub fn set_shared(backend: &Mutex<Self>, k: K, v: V) {
+        let key = encode_key(&k);
+        let data = encode_file(&key, &v);
+        let size = data.len() as u64;
+
+        let mut guard = backend.lock();
+        let dir = guard.dir.clone();
+        if size > guard.limit {
+            // oversized values are not stored, but the old value is outdated
+            let outdated = guard.take(&k);
+            drop(guard);
+            Self::remove_files_shared(backend, &dir, outdated.into_iter().collect());
+            return;
+        }
+        let file = guard.reserve(&k, &key);
+        drop(guard);
+
+        if let Err(e) = write_file(&dir.join(&file), &data) {
+            warn!(key=?k, %e, "cannot write disk cache entry");
+            Self::remove_files_shared(backend, &dir, vec![file]);
+            return;
+        }
+
+        let obsolete = backend.lock().commit(k, size, file);
+        Self::remove_files_shared(backend, &dir, obsolete);
+    }
+
+    /// Remove files without holding the lock and release their names afterwards.
+    fn remove_files_shared(backend: &Mutex<Self>, dir: &Path, files: Vec<String>) {
+        if files.is_empty() {
+            return;
+        }
+        for file in &files {
+            remove_file(&dir.join(file));
+        }
+        backend.lock().release(&files);
+    }
+
+    /// Look up the file of an entry and mark the entry as used.
+    fn lookup(&mut self, k: &K) -> Option<String> {
+        let file = self.entries.get(k)?.0.file.clone();
+        self.clock += 1;
+        self.entries.update_order(k, self.clock);
+        Some(file)
+    }
+
+    /// Remove entry from the index.
+    ///
+    /// Returns the file of the entry, which must be removed and then [released](Self::release) by the caller.
+    fn take(&mut self, k: &K) -> Option<String> {
+        let (entry, _) = self.entries.remove(k)?;
+        self.used -= entry.size;
+        Some(entry.file)
+    }
+
+    /// Same as [`take`](Self::take) but only if the entry is still backed by the given file.
+    fn take_if_file(&mut self, k: &K, file: &str) -> Option<String> {
+        match self.entries.get(k) {
+            Some((entry, _)) if entry.file == file => self.take(k),
+            _ => None,
+        }
+    }
+
+    /// Reserve a file that the new value for `k` can be written to.
+    ///
+    /// An existing entry for `k` is removed from the index and its file is reused. Otherwise the name is derived from
+    /// the encoded key, falling back to a numbered variant if the name is already used by another key.
+    ///
+    /// The reservation must either be [committed](Self::commit) or [released](Self::release).
+    fn reserve(&mut self, k: &K, key: &[u8]) -> String {
+        if let Some(file) = self.take(k) {
+            return file;
+        }
+
+        let hash = key_hash(key);
+        let mut file = format!("{hash:016x}.{FILE_EXTENSION}");
+        let mut n = 0u64;
+        while self.files.contains(&file) {
+            n += 1;
+            file = format!("{hash:016x}-{n}.{FILE_EXTENSION}");
+        }
+        self.files.insert(file.clone());
+        file
+    }
+
+    /// Add a written file to the index and evict entries if required.
+    ///
+    /// Returns files that must be removed and then [released](Self::release) by the caller.
+    fn commit(&mut self, k: K, size: u64, file: String) -> Vec<String> {
+        let mut obsolete: Vec<_> = self.insert_entry(k, size, file).into_iter().collect();
+        obsolete.extend(self.evict());
+        obsolete
+    }
+
+    /// Release file names after their files were removed.
+    fn release(&mut self, files: &[String]) {
+        for file in files {
+            self.files.remove(file);
+        }
+    }
+
+    /// Remove files and release their names.
+    fn remove_files(&mut self, files: Vec<String>) {
+        for file in &files {
+            remove_file(&self.dir.join(file));
+        }
+        self.release(&files);
+    }
+
+    /// Insert entry into the index.
+    ///
+    /// Returns the file of the entry that was replaced, if it differs from the new file.
+    fn insert_entry(&mut self, k: K, size: u64, file: String) -> Option<String> {
+        self.clock += 1;
+        let replaced = self.entries.insert(
+            k,
+            Entry {
+                size,
+                file: file.clone(),
+            },
+            self.clock,
+        );
+        self.used += size;
+
+        let (old, _) = replaced?;
+        self.used -= old.size;
+        (old.file != file).then_some(old.file)
+    }
+
+    /// Evict least recently used entries until the size limit is met.
+    ///
+    /// Returns the files of the evicted entries.
+    fn evict(&mut self) -> Vec<String> {
+        let mut evicted = vec![];
+        while self.used > self.limit {
+            let Some((_k, entry, _)) = self.entries.pop() else {
+                break;
+            };
+            self.used -= entry.size;
+            evicted.push(entry.file);
+        }
+        evicted
+    }
+}
+
+impl<K, V> CacheBackend for DiskBackend<K, V>
+where
+    K: DiskEncode + Clone + Eq + Hash + Ord + Debug + Send + 'static,
+    V: DiskEncode + Clone + Debug + Send + 'static,
+{
+    type K = K;
+    type V = V;
+
+    fn get(&mut self, k: &Self::K) -> Option<Self::V> {
+        let file = self.lookup(k)?;
+
+        match read_file(&self.dir.join(&file), &encode_key(k)) {
+            Ok(v) => Some(v),
+            Err(e) => {
+                warn!(key=?k, %e, "dropping unreadable disk cache entry");
+                self.remove(k);
+                None
+            }
+        }
+    }
+
+    fn set(&mut self, k: Self::K, v: Self::V) {
+        let key = encode_key(&k);
+        let data = encode_file(&key, &v);
+        let size = data.len() as u64;
+        if size > self.limit {
+            self.remove(&k);
+            return;
+        }
+
+        let file = self.reserve(&k, &key);
+        let path = self.dir.join(&file);
+        if let Err(e) = write_file(&path, &data) {
+            warn!(key=?k, %e, "cannot write disk cache entry");
+            self.remove_files(vec![file]);
+            return;
+        }
+
+        let obsolete = self.commit(k, size, file);
+        self.remove_files(obsolete);
+    }
+
+    fn remove(&mut self, k: &Self::K) {
+        if let Some(file) = self.take(k) {
+            self.remove_files(vec![file]);
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self as &dyn Any
+    }
+}
+
+fn encode_key<K: DiskEncode>(k: &K) -> Vec<u8> {
+    let mut buf = vec![];
+    k.encode(&mut buf);
+    buf
+}
+
+/// Encode the full content of a cache file.
+fn encode_file<V: DiskEncode>(key: &[u8], v: &V) -> Vec<u8> {
+    let mut data = Vec::with_capacity(HEADER_LEN + key.len());
+    data.extend_from_slice(MAGIC);
+    data.extend_from_slice(&(key.len() as u32).to_le_bytes());
+    data.extend_from_slice(key);
+    v.encode(&mut data);
+    let checksum = crc32fast::hash(&data);
+    data.extend_from_slice(&checksum.to_le_bytes());
+    data
+}
+
+/// Hash of the encoded key, used to derive file names.
+///
+/// Uses FNV-1a, which (in contrast to [`DefaultHasher`](std::collections::hash_map::DefaultHasher)) is stable across
+/// restarts and Rust versions.
+fn key_hash(key: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for b in key {
+        hash ^= u64::from(*b);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash
+}
+
+/// Validate the magic bytes of a cache file header and return the length of the encoded key.
+fn parse_key_len(header: &[u8]) -> io::Result<usize> {
+    if header.len() < HEADER_LEN || &header[..MAGIC.len()] != MAGIC {
+        return Err(invalid_data("invalid magic bytes"));
+    }
+
+    let key_len = u32::from_le_bytes(
+        header[MAGIC.len()..HEADER_LEN]
+            .try_into()
+            .expect("checked length"),
+    );
+    Ok(key_len as usize)
+}
+
+/// Parse the header of a cache file, returning the encoded key and the offset of the value.
+fn parse_header(data: &[u8]) -> io::Result<(&[u8], usize)> {
+    let value_start = HEADER_LEN + parse_key_len(data)?;
+    if data.len() < value_start {
+        return Err(invalid_data("file too short"));
+    }
+
+    Ok((&data[HEADER_LEN..value_start], value_start))
+}
+
+/// Read the key of a cache file without reading the value.
+fn read_key<K: DiskEncode>(path: &Path) -> io::Result<K> {
+    let mut file = File::open(path)?;
+
+    let mut header = [0u8; HEADER_LEN];
+    file.read_exact(&mut header)?;
+
+    let mut key = vec![0u8; parse_key_len(&header)?];
+    file.read_exact(&mut key)?;
+    K::decode(Bytes::from(key)).ok_or_else(|| invalid_data("cannot decode key"))
+}
+
+/// Read and validate a cache file that is expected to contain the given encoded key.
+fn read_file<V: DiskEncode>(path: &Path, key: &[u8]) -> io::Result<V> {
+    let data = Bytes::from(fs::read(path)?);
+
+    if data.len() < HEADER_LEN + TRAILER_LEN {
+        return Err(invalid_data("file too short"));
+    }
+
+    let (content, checksum) = data.split_at(data.len() - TRAILER_LEN);
+    let checksum = u32::from_le_bytes(checksum.try_into().expect("checked length"));
+    if crc32fast::hash(content) != checksum {
+        return Err(invalid_data("checksum mismatch"));
+    }
+
+    let (header_key, value_start) = parse_header(content)?;
+    if header_key != key {
+        return Err(invalid_data("key mismatch"));
+    }
+
+    V::decode(data.slice(value_start..data.len() - TRAILER_LEN))
+        .ok_or_else(|| invalid_data("cannot decode value"))
+}
+
+/// Write file so that readers never observe partial content.
+fn write_file(path: &Path, data: &[u8]) -> io::Result<()> {
+    let tmp = path.with_extension(TMP_EXTENSION);
+    let res = fs::write(&tmp, data).and_then(|_| fs::rename(&tmp, path));
+    if res.is_err() {
+        remove_file(&tmp);
+    }
+    res
+}
+
+fn remove_file(path: &Path) {
+    if let Err(e) = fs::remove_file(path) {
+        if e.kind() != io::ErrorKind::NotFound {
+            warn!(path=%path.display(), %e, "cannot remove disk cache file");
+        }
+    }
+}
+
+fn invalid_data(msg: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, fs::OpenOptions, io::Write};
+
+    use tempfile::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn test_generic() {
+        use crate::backend::test_util::test_generic;
+
+        let dirs = RefCell::new(vec![]);
+        test_generic(|| {
+            let dir = TempDir::new().unwrap();
+            let backend = DiskBackend::new(dir.path(), u64::MAX).unwrap();
+            dirs.borrow_mut().push(dir);
+            backend
+        });
+    }
+
+    #[test]
+    fn test_lru_eviction() {
+        let dir = TempDir::new().unwrap();
+        let file_size = file_size(1, "aaaa");
+        let mut backend = DiskBackend::<u8, String>::new(dir.path(), 2 * file_size).unwrap();
+
+        backend.set(1, String::from("aaaa"));
+        backend.set(2, String::from("bbbb"));
+        assert_eq!(backend.used_bytes(), 2 * file_size);
+
+        // use 1, so 2 is evicted
+        assert_eq!(backend.get(&1), Some(String::from("aaaa")));
+        backend.set(3, String::from("cccc"));
+
+        assert_eq!(backend.get(&1), Some(String::from("aaaa")));
+        assert_eq!(backend.get(&2), None);
+        assert_eq!(backend.get(&3), Some(String::from("cccc")));
+        assert_eq!(backend.n_entries(), 2);
+        assert_eq!(n_files(&dir), 2);
+
+        // oversized entries are not stored
+        backend.set(4, "x".repeat(2 * file_size as usize));
+        assert_eq!(backend.get(&4), None);
+        assert_eq!(backend.n_entries(), 2);
+    }
+
+    #[test]
+    fn test_checksum_validation() {
+        let dir = TempDir::new().unwrap();
+        let mut backend = DiskBackend::<u8, String>::new(dir.path(), u64::MAX).unwrap();
+
+        backend.set(1, String::from("aaaa"));
+        backend.set(2, String::from("bbbb"));
+
+        // corrupt the value of the first entry
+        let path = dir
+            .path()
+            .join(format!("{:016x}.{FILE_EXTENSION}", key_hash(&[1])));
+        let mut data = fs::read(&path).unwrap();
+        data[HEADER_LEN + 1] = b'x';
+        fs::write(&path, data).unwrap();
+
+        assert_eq!(backend.get(&1), None);
+        assert!(!path.exists());
+        assert_eq!(backend.get(&2), Some(String::from("bbbb")));
+        assert_eq!(backend.n_entries(), 1);
+        assert_eq!(backend.used_bytes(), file_size(1, "bbbb"));
+    }
+
+    #[test]
+    fn test_reopen() {
+        let dir = TempDir::new().unwrap();
+
+        let mut backend = DiskBackend::<u8, String>::new(dir.path(), u64::MAX).unwrap();
+        backend.set(1, String::from("aaaa"));
+        backend.set(2, String::from("bbbb"));
+        drop(backend);
+
+        // leftovers and garbage are cleaned up
+        fs::write(dir.path().join("foo.tmp"), b"partial").unwrap();
+        fs::write(dir.path().join("bar.cache"), b"garbage").unwrap();
+        let mut truncated = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .open(dir.path().join("baz.cache"))
+            .unwrap();
+        truncated.write_all(MAGIC).unwrap();
+        drop(truncated);
+
+        let mut backend = DiskBackend::<u8, String>::new(dir.path(), u64::MAX).unwrap();
+        assert_eq!(backend.n_entries(), 2);
+        assert_eq!(n_files(&dir), 2);
+        assert_eq!(backend.used_bytes(), 2 * file_size(1, "aaaa"));
+        assert_eq!(backend.get(&1), Some(String::from("aaaa")));
+        assert_eq!(backend.get(&2), Some(String::from("bbbb")));
+
+        // a smaller limit evicts entries while indexing
+        drop(backend);
+        let backend = DiskBackend::<u8, String>::new(dir.path(), file_size(1, "aaaa")).unwrap();
+        assert_eq!(backend.n_entries(), 1);
+        assert_eq!(n_files(&dir), 1);
+    }
+
+    #[test]
+    fn test_file_name_collision() {
+        let dir = TempDir::new().unwrap();
+        let mut backend = DiskBackend::<u8, String>::new(dir.path(), u64::MAX).unwrap();
+
+        // pretend that another key already uses the file name of key 1
+        let name = format!("{:016x}.{FILE_EXTENSION}", key_hash(&[1]));
+        backend.files.insert(name.clone());
+        backend.set(1, String::from("aaaa"));
+        backend.set(2, String::from("bbbb"));
+
+        let file = backend.entries.get(&1).unwrap().0.file.clone();
+        assert_ne!(file, name);
+        assert_eq!(backend.get(&1), Some(String::from("aaaa")));
+        assert_eq!(backend.get(&2), Some(String::from("bbbb")));
+
+        // replacing the value keeps the file
+        backend.set(1, String::from("cccc"));
+        assert_eq!(backend.entries.get(&1).unwrap().0.file, file);
+        assert_eq!(backend.used_bytes(), 2 * file_size(1, "aaaa"));
+        assert_eq!(n_files(&dir), 2);
+
+        // removal only affects the removed entry
+        backend.remove(&1);
+        assert_eq!(backend.get(&2), Some(String::from("bbbb")));
+        assert_eq!(n_files(&dir), 1);
+
+        // entries in numbered files are indexed as well
+        backend.set(1, String::from("aaaa"));
+        drop(backend);
+        let mut backend = DiskBackend::<u8, String>::new(dir.path(), u64::MAX).unwrap();
+        assert_eq!(backend.get(&1), Some(String::from("aaaa")));
+        assert_eq!(backend.get(&2), Some(String::from("bbbb")));
+    }
+
+    #[test]
+    fn test_shared() {
+        let dir = TempDir::new().unwrap();
+        let file_size = file_size(1, "aaaa");
+        let backend =
+            Mutex::new(DiskBackend::<u8, String>::new(dir.path(), 2 * file_size).unwrap());
+
+        DiskBackend::set_shared(&backend, 1, String::from("aaaa"));
+        DiskBackend::set_shared(&backend, 2, String::from("bbbb"));
+        assert_eq!(
+            DiskBackend::get_shared(&backend, &1),
+            Some(String::from("aaaa"))
+        );
+
+        // evicts 2
+        DiskBackend::set_shared(&backend, 3, String::from("cccc"));
+        assert_eq!(DiskBackend::get_shared(&backend, &2), None);
+        assert_eq!(n_files(&dir), 2);
+
+        // oversized values remove the old value
+        DiskBackend::set_shared(&backend, 3, "x".repeat(2 * file_size as usize));
+        assert_eq!(DiskBackend::get_shared(&backend, &3), None);
+        assert_eq!(n_files(&dir), 1);
+
+        // unreadable files are dropped
+        let file = backend.lock().entries.get(&1).unwrap().0.file.clone();
+        fs::write(dir.path().join(file), b"garbage").unwrap();
+        assert_eq!(DiskBackend::get_shared(&backend, &1), None);
+        assert_eq!(n_files(&dir), 0);
+        assert!(backend.lock().files.is_empty());
+        assert_eq!(backend.lock().used_bytes(), 0);
+    }
+
+    fn file_size(key: u8, value: &str) -> u64 {
+        (HEADER_LEN + encode_key(&key).len() + value.len() + TRAILER_LEN) as u64
+    }
+
+    fn n_files(dir: &TempDir) -> usize {
+        fs::read_dir(dir.path()).unwrap().count()
+    }
+}
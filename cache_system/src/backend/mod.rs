@@ -1,6 +1,7 @@
 //! Storage backends to keep and manage cached entries.
 use std::{any::Any, fmt::Debug, hash::Hash};
 
+pub mod disk;
 pub mod hash_map;
 pub mod policy;
 
@@ -20,6 +20,19 @@ pub struct Ingester2Config {
     )]
     pub wal_rotation_period_seconds: u64,
 
+    /// Skip unreadable entries at the end of a WAL segment during replay instead of failing to
+    /// start.
+    ///
+    /// A segment may end in a partially written entry if the ingester crashed mid-write. When
+    /// set, all entries before the first unreadable one are replayed and the number of skipped
+    /// bytes is logged - the writes they contained are lost.
+    #[clap(
+        long = "wal-replay-skip-corrupt-tail",
+        env = "INFLUXDB_IOX_WAL_REPLAY_SKIP_CORRUPT_TAIL",
+        action
+    )]
+    pub wal_replay_skip_corrupt_tail: bool,
+
     /// Sets how many queries the ingester will handle simultaneously before
     /// rejecting further incoming requests.
     #[clap(
@@ -60,4 +73,27 @@ pub struct Ingester2Config {
         action
     )]
     pub persist_hot_partition_cost: usize,
+
+    /// The estimated number of bytes of buffered data (across all partitions)
+    /// above which the largest partitions are persisted to reduce memory usage.
+    ///
+    /// Disabled if not set.
+    #[clap(
+        long = "buffer-memory-high-watermark-bytes",
+        env = "INFLUXDB_IOX_BUFFER_MEMORY_HIGH_WATERMARK_BYTES",
+        action
+    )]
+    pub buffer_memory_high_watermark_bytes: Option<usize>,
+
+    /// The estimated number of bytes of buffered data (across all partitions)
+    /// above which writes are rejected with a retryable error until memory
+    /// usage falls below the high watermark.
+    ///
+    /// Must not be less than the high watermark. Disabled if not set.
+    #[clap(
+        long = "buffer-memory-hard-limit-bytes",
+        env = "INFLUXDB_IOX_BUFFER_MEMORY_HARD_LIMIT_BYTES",
+        action
+    )]
+    pub buffer_memory_hard_limit_bytes: Option<usize>,
 }
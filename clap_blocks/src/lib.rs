@@ -19,6 +19,7 @@ pub mod ingest_replica;
 pub mod ingester;
 pub mod ingester2;
 pub mod object_store;
+pub mod parquet_bloom_filter;
 pub mod querier;
 pub mod router;
 pub mod router2;
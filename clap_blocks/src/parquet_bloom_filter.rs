@@ -0,0 +1,38 @@
+//! CLI config for the bloom filters written into parquet files.
+
+/// CLI config for the bloom filters written for the tag columns of parquet
+/// files at persist and compaction time.
+#[derive(Debug, Clone, Copy, clap::Parser)]
+pub struct ParquetBloomFilterConfig {
+    /// Write a bloom filter for each tag column of the parquet files created
+    /// by this service, allowing queries with equality / `IN` predicates on
+    /// tags to skip files and row groups that cannot contain a match.
+    ///
+    /// Set to false to disable writing bloom filters.
+    #[clap(
+        long = "parquet-bloom-filters-enabled",
+        env = "INFLUXDB_IOX_PARQUET_BLOOM_FILTERS_ENABLED",
+        default_value = "true",
+        action
+    )]
+    pub enabled: bool,
+
+    /// The target false positive probability of each bloom filter.
+    #[clap(
+        long = "parquet-bloom-filter-fpp",
+        env = "INFLUXDB_IOX_PARQUET_BLOOM_FILTER_FPP",
+        default_value = "0.01",
+        action
+    )]
+    pub fpp: f64,
+
+    /// The expected number of distinct values of a tag within a single row
+    /// group, used to size each bloom filter.
+    #[clap(
+        long = "parquet-bloom-filter-ndv",
+        env = "INFLUXDB_IOX_PARQUET_BLOOM_FILTER_NDV",
+        default_value = "10000",
+        action
+    )]
+    pub ndv: u64,
+}
@@ -2,7 +2,13 @@
 use data_types::{IngesterMapping, ShardIndex};
 use serde::Deserialize;
 use snafu::{ResultExt, Snafu};
-use std::{collections::HashMap, fs, io, path::PathBuf, sync::Arc};
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
 
 #[derive(Debug, Snafu)]
 #[allow(missing_docs)]
@@ -63,6 +69,16 @@ pub struct QuerierConfig {
     )]
     pub exec_mem_pool_bytes: usize,
 
+    /// Directory for temporary files of queries that exceed their share of the memory pool.
+    ///
+    /// If set, sorts, deduplication and stream splits spill intermediate data to files in this
+    /// directory instead of failing with "ResourcesExhausted", and each query may only use a fair
+    /// share of the memory pool while others are running. Files are deleted once the query is
+    /// done. Aggregations (e.g. GROUP BY) can not spill and still fail once they exceed the
+    /// share of their query.
+    #[clap(long = "exec-spill-dir", env = "INFLUXDB_IOX_EXEC_SPILL_DIR", action)]
+    pub exec_spill_dir: Option<PathBuf>,
+
     /// Path to a JSON file containing a Shard index to ingesters gRPC mapping. For example:
     ///
     /// ```json
@@ -125,6 +141,9 @@ pub struct QuerierConfig {
     ///   }
     /// }
     /// ```
+    ///
+    /// The file is re-read every `--ingester-discovery-interval`, so ingesters can be added,
+    /// removed or replaced without restarting the querier.
     #[clap(
         long = "shard-to-ingesters-file",
         env = "INFLUXDB_IOX_SHARD_TO_INGESTERS_FILE",
@@ -214,6 +233,31 @@ pub struct QuerierConfig {
     #[clap(long = "ingester-addresses", env = "INFLUXDB_IOX_INGESTER_ADDRESSES")]
     pub ingester_addresses: Vec<String>,
 
+    /// DNS name and gRPC port (`host:port`) that resolves to the addresses of all ingesters, for
+    /// example a headless Kubernetes service:
+    ///
+    /// "ingester.iox.svc.cluster.local:8083"
+    ///
+    /// The name is resolved every `--ingester-discovery-interval`, so ingesters can be added,
+    /// removed or replaced without restarting the querier. Only supported for the RPC write path.
+    #[clap(
+        long = "ingester-dns-name",
+        env = "INFLUXDB_IOX_INGESTER_DNS_NAME",
+        conflicts_with = "ingester_addresses",
+        action
+    )]
+    pub ingester_dns_name: Option<String>,
+
+    /// How often the querier refreshes the set of ingesters from `--shard-to-ingesters-file` or
+    /// `--ingester-dns-name`.
+    #[clap(
+        long = "ingester-discovery-interval",
+        env = "INFLUXDB_IOX_INGESTER_DISCOVERY_INTERVAL",
+        default_value = "10s",
+        value_parser = humantime::parse_duration,
+    )]
+    pub ingester_discovery_interval: Duration,
+
     /// Size of the RAM cache used to store catalog metadata information in bytes.
     #[clap(
         long = "ram-pool-metadata-bytes",
@@ -232,6 +276,42 @@ pub struct QuerierConfig {
     )]
     pub ram_pool_data_bytes: usize,
 
+    /// Directory of the optional on-disk cache for parquet files.
+    ///
+    /// If set, parquet files that are loaded into the RAM cache are also stored in this directory, so they can be
+    /// served without contacting the object store after they were evicted from RAM or after a restart. Files that
+    /// already exist in this directory are picked up on startup.
+    #[clap(
+        long = "parquet-disk-cache-dir",
+        env = "INFLUXDB_IOX_PARQUET_DISK_CACHE_DIR",
+        action
+    )]
+    pub parquet_disk_cache_dir: Option<PathBuf>,
+
+    /// Size of the on-disk cache for parquet files in bytes.
+    ///
+    /// Only used if `--parquet-disk-cache-dir` is set.
+    #[clap(
+        long = "parquet-disk-cache-bytes",
+        env = "INFLUXDB_IOX_PARQUET_DISK_CACHE_BYTES",
+        default_value = "10737418240",  // 10GB
+        action
+    )]
+    pub parquet_disk_cache_bytes: u64,
+
+    /// Size of the RAM cache used to store query results in bytes.
+    ///
+    /// Results are reused when the same query (ignoring whitespace) is run again and the data it
+    /// reads has not changed since. Queries using functions like `now()` are never cached. A size
+    /// of 0 disables the cache.
+    #[clap(
+        long = "query-result-cache-bytes",
+        env = "INFLUXDB_IOX_QUERY_RESULT_CACHE_BYTES",
+        default_value = "0",
+        action
+    )]
+    pub query_result_cache_bytes: usize,
+
     /// Limit the number of concurrent queries.
     #[clap(
         long = "max-concurrent-queries",
@@ -262,6 +342,52 @@ pub struct QuerierConfig {
         action
     )]
     pub ingester_circuit_breaker_threshold: u64,
+
+    /// Tables whose last values are kept in memory, each given as `<namespace>/<table>`,
+    /// separated by `,`.
+    ///
+    /// Queries that only need the last value of each field of each series of such a table (e.g.
+    /// `ORDER BY time DESC LIMIT 1` or `selector_last` grouped by tags) are answered from memory
+    /// instead of scanning the table.
+    #[clap(
+        long = "last-value-cache-tables",
+        env = "INFLUXDB_IOX_LAST_VALUE_CACHE_TABLES",
+        value_delimiter = ','
+    )]
+    pub last_value_cache_tables: Vec<String>,
+
+    /// gRPC address of the router the results of downsampling tasks are written through, e.g.
+    /// `http://router:8081`.
+    ///
+    /// The querier only runs the windows of the downsampling tasks in the catalog when this is
+    /// set.
+    #[clap(
+        long = "downsampling-router-address",
+        env = "INFLUXDB_IOX_DOWNSAMPLING_ROUTER_ADDRESS",
+        action
+    )]
+    pub downsampling_router_address: Option<String>,
+
+    /// How often the querier checks for windows of downsampling tasks that are due.
+    #[clap(
+        long = "downsampling-interval",
+        env = "INFLUXDB_IOX_DOWNSAMPLING_INTERVAL",
+        default_value = "10s",
+        value_parser = humantime::parse_duration,
+    )]
+    pub downsampling_interval: Duration,
+
+    /// The longest time between refreshes of a cached namespace schema, e.g. `5m`.
+    ///
+    /// This bounds how long schema changes made by other nodes that queries do not detect, such as a deleted table,
+    /// remain visible to this querier. If unset, namespaces that are queried often are refreshed ever less
+    /// frequently to reduce catalog load.
+    #[clap(
+        long = "namespace-cache-max-refresh-interval",
+        env = "INFLUXDB_IOX_NAMESPACE_CACHE_MAX_REFRESH_INTERVAL",
+        value_parser = humantime::parse_duration,
+    )]
+    pub namespace_cache_max_refresh_interval: Option<Duration>,
 }
 
 impl QuerierConfig {
@@ -284,9 +410,7 @@ impl QuerierConfig {
     // `INFLUXDB_IOX_RPC_MODE` and setting ingester addresses, will panic.
     pub fn ingester_addresses(&self) -> Result<IngesterAddresses, Error> {
         if let Some(file) = &self.shard_to_ingesters_file {
-            let contents =
-                fs::read_to_string(file).context(ShardToIngesterFileReadingSnafu { file })?;
-            let map = deserialize_shard_ingester_map(&contents)?;
+            let map = read_shard_to_ingesters_file(file)?;
             if map.is_empty() {
                 Ok(IngesterAddresses::None)
             } else {
@@ -299,6 +423,8 @@ impl QuerierConfig {
             } else {
                 Ok(IngesterAddresses::ByShardIndex(map))
             }
+        } else if let Some(name) = &self.ingester_dns_name {
+            Ok(IngesterAddresses::Dns(name.clone()))
         } else if !self.ingester_addresses.is_empty() {
             Ok(IngesterAddresses::List(
                 self.ingester_addresses
@@ -311,6 +437,11 @@ impl QuerierConfig {
         }
     }
 
+    /// How often the set of ingesters is refreshed at runtime.
+    pub fn ingester_discovery_interval(&self) -> Duration {
+        self.ingester_discovery_interval
+    }
+
     /// Size of the RAM cache pool for metadata in bytes.
     pub fn ram_pool_metadata_bytes(&self) -> usize {
         self.ram_pool_metadata_bytes
@@ -321,12 +452,35 @@ impl QuerierConfig {
         self.ram_pool_data_bytes
     }
 
+    /// Size of the RAM cache for query results in bytes, 0 if disabled.
+    pub fn query_result_cache_bytes(&self) -> usize {
+        self.query_result_cache_bytes
+    }
+
+    /// Directory of the on-disk cache for parquet files, if enabled.
+    pub fn parquet_disk_cache_dir(&self) -> Option<&PathBuf> {
+        self.parquet_disk_cache_dir.as_ref()
+    }
+
+    /// Size of the on-disk cache for parquet files in bytes.
+    pub fn parquet_disk_cache_bytes(&self) -> u64 {
+        self.parquet_disk_cache_bytes
+    }
+
     /// Number of queries allowed to run concurrently
     pub fn max_concurrent_queries(&self) -> usize {
         self.max_concurrent_queries
     }
 }
 
+/// Read and interpret a shard to ingesters JSON file as accepted by `--shard-to-ingesters-file`.
+pub fn read_shard_to_ingesters_file(
+    file: &Path,
+) -> Result<HashMap<ShardIndex, IngesterMapping>, Error> {
+    let contents = fs::read_to_string(file).context(ShardToIngesterFileReadingSnafu { file })?;
+    deserialize_shard_ingester_map(&contents)
+}
+
 fn deserialize_shard_ingester_map(
     contents: &str,
 ) -> Result<HashMap<ShardIndex, IngesterMapping>, Error> {
@@ -406,6 +560,9 @@ pub enum IngesterAddresses {
     /// A list of ingester2 addresses.
     List(Vec<Arc<str>>),
 
+    /// A DNS name (`host:port`) that resolves to all ingester2 addresses.
+    Dns(String),
+
     /// No connections, meaning only persisted data should be used.
     None,
 }
@@ -466,6 +623,102 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_ingester_dns_name() {
+        let actual = QuerierConfig::try_parse_from([
+            "my_binary",
+            "--ingester-dns-name",
+            "ingester:8083",
+            "--ingester-discovery-interval",
+            "1m",
+        ])
+        .unwrap();
+
+        assert_eq!(
+            actual.ingester_addresses().unwrap(),
+            IngesterAddresses::Dns(String::from("ingester:8083")),
+        );
+        assert_eq!(
+            actual.ingester_discovery_interval(),
+            Duration::from_secs(60)
+        );
+
+        QuerierConfig::try_parse_from([
+            "my_binary",
+            "--ingester-dns-name",
+            "ingester:8083",
+            "--ingester-addresses",
+            "http://ingester:8083",
+        ])
+        .unwrap_err();
+    }
+
+    #[test]
+    fn test_query_result_cache_bytes() {
+        let actual = QuerierConfig::try_parse_from(["my_binary"]).unwrap();
+        assert_eq!(actual.query_result_cache_bytes(), 0);
+
+        let actual =
+            QuerierConfig::try_parse_from(["my_binary", "--query-result-cache-bytes", "1024"])
+                .unwrap();
+        assert_eq!(actual.query_result_cache_bytes(), 1024);
+    }
+
+    #[test]
+    fn test_last_value_cache_tables() {
+        let actual = QuerierConfig::try_parse_from(["my_binary"]).unwrap();
+        assert!(actual.last_value_cache_tables.is_empty());
+
+        let actual = QuerierConfig::try_parse_from([
+            "my_binary",
+            "--last-value-cache-tables",
+            "bananas/cpu,bananas/mem",
+        ])
+        .unwrap();
+        assert_eq!(
+            actual.last_value_cache_tables,
+            ["bananas/cpu", "bananas/mem"]
+        );
+    }
+
+    #[test]
+    fn test_downsampling() {
+        let actual = QuerierConfig::try_parse_from(["my_binary"]).unwrap();
+        assert_eq!(actual.downsampling_router_address, None);
+        assert_eq!(actual.downsampling_interval, Duration::from_secs(10));
+
+        let actual = QuerierConfig::try_parse_from([
+            "my_binary",
+            "--downsampling-router-address",
+            "http://router:8081",
+            "--downsampling-interval",
+            "1m",
+        ])
+        .unwrap();
+        assert_eq!(
+            actual.downsampling_router_address.as_deref(),
+            Some("http://router:8081")
+        );
+        assert_eq!(actual.downsampling_interval, Duration::from_secs(60));
+    }
+
+    #[test]
+    fn test_namespace_cache_max_refresh_interval() {
+        let actual = QuerierConfig::try_parse_from(["my_binary"]).unwrap();
+        assert_eq!(actual.namespace_cache_max_refresh_interval, None);
+
+        let actual = QuerierConfig::try_parse_from([
+            "my_binary",
+            "--namespace-cache-max-refresh-interval",
+            "5m",
+        ])
+        .unwrap();
+        assert_eq!(
+            actual.namespace_cache_max_refresh_interval,
+            Some(Duration::from_secs(300))
+        );
+    }
+
     #[test]
     fn supply_json_value() {
         let actual = QuerierConfig::try_parse_from([
@@ -50,4 +50,69 @@ pub struct RouterConfig {
         action
     )]
     pub namespace_autocreation_enabled: bool,
+
+    /// The maximum number of namespace schemas held in the router's cache.
+    ///
+    /// Once full, the least recently used namespace is evicted and its schema
+    /// is loaded from the catalog when next used.
+    #[clap(
+        long = "namespace-cache-max-entries",
+        env = "INFLUXDB_IOX_NAMESPACE_CACHE_MAX_ENTRIES",
+        default_value = "10000",
+        action
+    )]
+    pub namespace_cache_max_entries: usize,
+
+    /// The number of seconds between refreshes of the cached namespaces from
+    /// the catalog.
+    ///
+    /// This bounds the time taken for namespace changes made elsewhere (such as
+    /// a retention period update or a deleted namespace) to be observed by this
+    /// router.
+    #[clap(
+        long = "namespace-cache-refresh-interval-seconds",
+        env = "INFLUXDB_IOX_NAMESPACE_CACHE_REFRESH_INTERVAL_SECONDS",
+        default_value = "60",
+        action
+    )]
+    pub namespace_cache_refresh_interval_seconds: u64,
+
+    /// Periodically write this router's own metrics into an IOx namespace, so the
+    /// history of the metrics can be queried with IOx itself.
+    ///
+    /// Each metric is written as a table in the namespace set by
+    /// `--self-monitoring-namespace`, with the metric attributes as tags.
+    ///
+    /// Only the metrics of this router are written. Other services do not
+    /// self-monitor, their metrics are available from their `/metrics`
+    /// endpoint.
+    #[clap(
+        long = "self-monitoring-enabled",
+        env = "INFLUXDB_IOX_SELF_MONITORING_ENABLED",
+        default_value = "false",
+        action
+    )]
+    pub self_monitoring_enabled: bool,
+
+    /// The namespace this router's own metrics are written to when
+    /// `--self-monitoring-enabled` is set.
+    ///
+    /// The namespace is auto-created if namespace auto-creation is enabled.
+    #[clap(
+        long = "self-monitoring-namespace",
+        env = "INFLUXDB_IOX_SELF_MONITORING_NAMESPACE",
+        default_value = "_monitoring",
+        action
+    )]
+    pub self_monitoring_namespace: String,
+
+    /// The number of seconds between writes of this router's own metrics when
+    /// `--self-monitoring-enabled` is set.
+    #[clap(
+        long = "self-monitoring-interval-seconds",
+        env = "INFLUXDB_IOX_SELF_MONITORING_INTERVAL_SECONDS",
+        default_value = "10",
+        action
+    )]
+    pub self_monitoring_interval_seconds: u64,
 }
@@ -354,6 +354,7 @@ impl CompactPlanBuilder {
         let plan = if total_size <= small_cutoff_bytes {
             // Compact everything into one file
             ReorgPlanner::new(ctx.child_ctx("ReorgPlanner"))
+                .with_dedup_policy(partition.table.dedup_policy)
                 .compact_plan(
                     Arc::from(partition.table.name.clone()),
                     &merged_schema,
@@ -382,6 +383,7 @@ impl CompactPlanBuilder {
                 // The split times might not have actually split anything, so in this case, compact
                 // everything into one file
                 ReorgPlanner::new(ctx.child_ctx("ReorgPlanner"))
+                    .with_dedup_policy(partition.table.dedup_policy)
                     .compact_plan(
                         Arc::from(partition.table.name.clone()),
                         &merged_schema,
@@ -392,6 +394,7 @@ impl CompactPlanBuilder {
             } else {
                 // split compact query plan
                 ReorgPlanner::new(ctx.child_ctx("ReorgPlanner"))
+                    .with_dedup_policy(partition.table.dedup_policy)
                     .split_plan(
                         Arc::from(partition.table.name.clone()),
                         &merged_schema,
@@ -535,6 +538,7 @@ impl CompactPlanBuilder {
         let ctx = exec.new_context(ExecutorType::Reorg);
         // Compact everything into one file
         let plan = ReorgPlanner::new(ctx.child_ctx("ReorgPlanner"))
+            .with_dedup_policy(partition.table.dedup_policy)
             .compact_plan(
                 Arc::from(partition.table.name.clone()),
                 &merged_schema,
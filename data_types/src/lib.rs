@@ -153,6 +153,27 @@ impl std::fmt::Display for TableId {
     }
 }
 
+/// Unique ID for a `DownsamplingTask`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, sqlx::Type)]
+#[sqlx(transparent)]
+pub struct DownsamplingTaskId(i64);
+
+#[allow(missing_docs)]
+impl DownsamplingTaskId {
+    pub const fn new(v: i64) -> Self {
+        Self(v)
+    }
+    pub fn get(&self) -> i64 {
+        self.0
+    }
+}
+
+impl std::fmt::Display for DownsamplingTaskId {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
 /// Unique ID for a `Column`
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, sqlx::Type)]
 #[sqlx(transparent)]
@@ -502,6 +523,55 @@ impl NamespaceSchema {
     }
 }
 
+/// How rows of a table with the same primary key (the tag columns and time)
+/// are deduplicated when the table is queried, compacted or persisted.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, Hash, sqlx::Type)]
+#[repr(i16)]
+pub enum DeduplicationPolicy {
+    /// The most recently written non-null value of each field wins.
+    #[default]
+    Upsert = 0,
+    /// The first written row wins, later writes of the same primary key are
+    /// discarded.
+    KeepFirst = 1,
+    /// Rows are never deduplicated, for tables of events or logs in which
+    /// primary keys are either unique or intentionally repeated.
+    AppendOnly = 2,
+}
+
+impl DeduplicationPolicy {
+    /// The short string description of the policy
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Upsert => "upsert",
+            Self::KeepFirst => "keep_first",
+            Self::AppendOnly => "append_only",
+        }
+    }
+}
+
+impl std::fmt::Display for DeduplicationPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for DeduplicationPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "upsert" => Ok(Self::Upsert),
+            "keep_first" => Ok(Self::KeepFirst),
+            "append_only" => Ok(Self::AppendOnly),
+            _ => Err(format!(
+                "invalid deduplication policy '{}', expected one of upsert, keep_first, append_only",
+                s
+            )),
+        }
+    }
+}
+
 /// Data object for a table
 #[derive(Debug, Clone, sqlx::FromRow, Eq, PartialEq)]
 pub struct Table {
@@ -511,6 +581,8 @@ pub struct Table {
     pub namespace_id: NamespaceId,
     /// The name of the table, which is unique within the associated namespace
     pub name: String,
+    /// How rows with the same primary key are deduplicated
+    pub dedup_policy: DeduplicationPolicy,
 }
 
 /// Column definitions for a table
@@ -520,14 +592,27 @@ pub struct TableSchema {
     pub id: TableId,
     /// the table's columns by their name
     pub columns: BTreeMap<String, ColumnSchema>,
+    /// How rows with the same primary key are deduplicated
+    pub dedup_policy: DeduplicationPolicy,
 }
 
 impl TableSchema {
-    /// Initialize new `TableSchema`
+    /// Initialize new `TableSchema`, with the default [`DeduplicationPolicy`].
     pub fn new(id: TableId) -> Self {
         Self {
             id,
             columns: BTreeMap::new(),
+            dedup_policy: DeduplicationPolicy::default(),
+        }
+    }
+
+    /// Initialize an empty `TableSchema` for `table`, carrying over its
+    /// [`DeduplicationPolicy`].
+    pub fn new_for_table(table: &Table) -> Self {
+        Self {
+            id: table.id,
+            columns: BTreeMap::new(),
+            dedup_policy: table.dedup_policy,
         }
     }
 
@@ -957,6 +1042,108 @@ pub struct SkippedCompaction {
     pub limit_num_files_first_in_partition: i64,
 }
 
+/// The language of the query of a [`DownsamplingTask`].
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, sqlx::Type)]
+#[repr(i16)]
+pub enum QueryLanguage {
+    /// SQL
+    Sql = 1,
+    /// InfluxQL
+    InfluxQl = 2,
+}
+
+impl QueryLanguage {
+    /// The short string description of the language
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Sql => "sql",
+            Self::InfluxQl => "influxql",
+        }
+    }
+}
+
+impl std::fmt::Display for QueryLanguage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for QueryLanguage {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sql" => Ok(Self::Sql),
+            "influxql" => Ok(Self::InfluxQl),
+            _ => Err(format!(
+                "invalid query language '{}', expected one of sql, influxql",
+                s
+            )),
+        }
+    }
+}
+
+/// The placeholder for the start (inclusive) of the window in the query of a
+/// [`DownsamplingTask`].
+pub const DOWNSAMPLING_WINDOW_START: &str = "$window_start";
+
+/// The placeholder for the end (exclusive) of the window in the query of a
+/// [`DownsamplingTask`].
+pub const DOWNSAMPLING_WINDOW_END: &str = "$window_end";
+
+/// Data object for a downsampling task: a query over a namespace that is run
+/// for consecutive, closed time windows, with its results written into a
+/// table of another namespace.
+#[derive(Debug, Clone, PartialEq, Eq, sqlx::FromRow)]
+pub struct DownsamplingTask {
+    /// the id of the task
+    pub id: DownsamplingTaskId,
+    /// the unique name of the task
+    pub name: String,
+    /// the namespace the query runs against
+    pub namespace_id: NamespaceId,
+    /// the query, which must refer to the bounds of the window it is run for
+    /// as [`DOWNSAMPLING_WINDOW_START`] and [`DOWNSAMPLING_WINDOW_END`]
+    pub query: String,
+    /// the language of the query
+    pub query_language: QueryLanguage,
+    /// the namespace the results are written to
+    pub target_namespace: String,
+    /// the table the results are written to
+    pub target_table: String,
+    /// the length of the windows in nanoseconds
+    pub every_ns: i64,
+    /// how long to wait after the end of a window before it is run, for late
+    /// writes to arrive, in nanoseconds
+    pub delay_ns: i64,
+    /// the end (exclusive) of the last window whose results were written, or
+    /// the start of the first window if none was run yet
+    pub watermark: Timestamp,
+}
+
+/// Data for a downsampling task to be created in the catalog.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DownsamplingTaskParams {
+    /// the unique name of the task
+    pub name: String,
+    /// the namespace the query runs against
+    pub namespace_id: NamespaceId,
+    /// the query, see [`DownsamplingTask::query`]
+    pub query: String,
+    /// the language of the query
+    pub query_language: QueryLanguage,
+    /// the namespace the results are written to
+    pub target_namespace: String,
+    /// the table the results are written to
+    pub target_table: String,
+    /// the length of the windows in nanoseconds
+    pub every_ns: i64,
+    /// how long to wait after the end of a window before it is run
+    pub delay_ns: i64,
+    /// the start of the first window to run
+    pub watermark: Timestamp,
+}
+
 /// Data object for a tombstone.
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, sqlx::FromRow)]
 pub struct Tombstone {
@@ -3373,6 +3560,7 @@ mod tests {
         let schema1 = TableSchema {
             id: TableId::new(1),
             columns: BTreeMap::from([]),
+            dedup_policy: DeduplicationPolicy::Upsert,
         };
         let schema2 = TableSchema {
             id: TableId::new(2),
@@ -3383,6 +3571,7 @@ mod tests {
                     column_type: ColumnType::Bool,
                 },
             )]),
+            dedup_policy: DeduplicationPolicy::Upsert,
         };
         assert!(schema1.size() < schema2.size());
     }
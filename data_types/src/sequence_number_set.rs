@@ -46,6 +46,12 @@ impl SequenceNumberSet {
         self.0.contains(n.get() as _)
     }
 
+    /// Return `true` if `self` and `other` have at least one
+    /// [`SequenceNumber`] in common.
+    pub fn intersects(&self, other: &Self) -> bool {
+        self.0.intersect(&other.0)
+    }
+
     /// Returns the number of [`SequenceNumber`] in this set.
     pub fn len(&self) -> u64 {
         self.0.cardinality()
@@ -96,7 +102,9 @@ mod tests {
 
         // Merging a non-empty set should add the new elements
         b.add(SequenceNumber::new(2));
+        assert!(!a.intersects(&b));
         a.add_set(&b);
+        assert!(a.intersects(&b));
         assert_eq!(a.len(), 2);
         assert!(a.contains(SequenceNumber::new(1)));
         assert!(a.contains(SequenceNumber::new(2)));
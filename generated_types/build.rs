@@ -21,6 +21,7 @@ fn main() -> Result<()> {
 /// - `influxdata.iox.catalog.v1.rs`
 /// - `influxdata.iox.compactor.v1.rs`
 /// - `influxdata.iox.delete.v1.rs`
+/// - `influxdata.iox.downsampling.v1.rs`
 /// - `influxdata.iox.ingester.v1.rs`
 /// - `influxdata.iox.namespace.v1.rs`
 /// - `influxdata.iox.object_store.v1.rs`
@@ -28,14 +29,23 @@ fn main() -> Result<()> {
 /// - `influxdata.iox.querier.v1.rs`
 /// - `influxdata.iox.schema.v1.rs`
 /// - `influxdata.iox.sharder.v1.rs`
+/// - `influxdata.iox.table.v1.rs`
 /// - `influxdata.iox.wal.v1.rs`
 /// - `influxdata.iox.write.v1.rs`
 /// - `influxdata.iox.write_buffer.v1.rs`
 /// - `influxdata.platform.storage.rs`
+/// - `opentelemetry.proto.collector.metrics.v1.rs`
+/// - `opentelemetry.proto.collector.trace.v1.rs`
+/// - `opentelemetry.proto.common.v1.rs`
+/// - `opentelemetry.proto.metrics.v1.rs`
+/// - `opentelemetry.proto.resource.v1.rs`
+/// - `opentelemetry.proto.trace.v1.rs`
+/// - `prometheus.rs`
 fn generate_grpc_types(root: &Path) -> Result<()> {
     let catalog_path = root.join("influxdata/iox/catalog/v1");
     let compactor_path = root.join("influxdata/iox/compactor/v1");
     let delete_path = root.join("influxdata/iox/delete/v1");
+    let downsampling_path = root.join("influxdata/iox/downsampling/v1");
     let ingester_path = root.join("influxdata/iox/ingester/v1");
     let namespace_path = root.join("influxdata/iox/namespace/v1");
     let object_store_path = root.join("influxdata/iox/object_store/v1");
@@ -43,17 +53,21 @@ fn generate_grpc_types(root: &Path) -> Result<()> {
     let querier_path = root.join("influxdata/iox/querier/v1");
     let schema_path = root.join("influxdata/iox/schema/v1");
     let sharder_path = root.join("influxdata/iox/sharder/v1");
+    let table_path = root.join("influxdata/iox/table/v1");
     let wal_path = root.join("influxdata/iox/wal/v1");
     let write_buffer_path = root.join("influxdata/iox/write_buffer/v1");
     let write_summary_path = root.join("influxdata/iox/write_summary/v1");
     let storage_path = root.join("influxdata/platform/storage");
     let storage_errors_path = root.join("influxdata/platform/errors");
+    let otlp_path = root.join("opentelemetry/proto");
+    let prometheus_path = root.join("prometheus");
 
     let proto_files = vec![
         catalog_path.join("parquet_file.proto"),
         catalog_path.join("service.proto"),
         compactor_path.join("service.proto"),
         delete_path.join("service.proto"),
+        downsampling_path.join("service.proto"),
         ingester_path.join("parquet_metadata.proto"),
         ingester_path.join("query.proto"),
         ingester_path.join("write_info.proto"),
@@ -70,6 +84,7 @@ fn generate_grpc_types(root: &Path) -> Result<()> {
         root.join("influxdata/pbdata/v1/influxdb_pb_data_protocol.proto"),
         schema_path.join("service.proto"),
         sharder_path.join("sharder.proto"),
+        table_path.join("service.proto"),
         wal_path.join("wal.proto"),
         write_buffer_path.join("write_buffer.proto"),
         write_summary_path.join("write_summary.proto"),
@@ -79,6 +94,14 @@ fn generate_grpc_types(root: &Path) -> Result<()> {
         storage_path.join("storage_common.proto"),
         storage_path.join("test.proto"),
         storage_errors_path.join("errors.proto"),
+        otlp_path.join("collector/metrics/v1/metrics_service.proto"),
+        otlp_path.join("collector/trace/v1/trace_service.proto"),
+        otlp_path.join("common/v1/common.proto"),
+        otlp_path.join("metrics/v1/metrics.proto"),
+        otlp_path.join("resource/v1/resource.proto"),
+        otlp_path.join("trace/v1/trace.proto"),
+        prometheus_path.join("remote.proto"),
+        prometheus_path.join("types.proto"),
     ];
 
     // Tell cargo to recompile if any of these proto files are changed
@@ -75,6 +75,39 @@ pub mod influxdata {
             }
         }
 
+        pub mod downsampling {
+            pub mod v1 {
+                include!(concat!(
+                    env!("OUT_DIR"),
+                    "/influxdata.iox.downsampling.v1.rs"
+                ));
+                include!(concat!(
+                    env!("OUT_DIR"),
+                    "/influxdata.iox.downsampling.v1.serde.rs"
+                ));
+
+                impl From<QueryLanguage> for data_types::QueryLanguage {
+                    fn from(value: QueryLanguage) -> Self {
+                        match value {
+                            QueryLanguage::Unspecified | QueryLanguage::Sql => {
+                                data_types::QueryLanguage::Sql
+                            }
+                            QueryLanguage::Influxql => data_types::QueryLanguage::InfluxQl,
+                        }
+                    }
+                }
+
+                impl From<data_types::QueryLanguage> for QueryLanguage {
+                    fn from(value: data_types::QueryLanguage) -> Self {
+                        match value {
+                            data_types::QueryLanguage::Sql => Self::Sql,
+                            data_types::QueryLanguage::InfluxQl => Self::Influxql,
+                        }
+                    }
+                }
+            }
+        }
+
         pub mod ingester {
             pub mod v1 {
                 include!(concat!(env!("OUT_DIR"), "/influxdata.iox.ingester.v1.rs"));
@@ -167,6 +200,42 @@ pub mod influxdata {
             }
         }
 
+        pub mod table {
+            pub mod v1 {
+                include!(concat!(env!("OUT_DIR"), "/influxdata.iox.table.v1.rs"));
+                include!(concat!(
+                    env!("OUT_DIR"),
+                    "/influxdata.iox.table.v1.serde.rs"
+                ));
+
+                impl From<DeduplicationPolicy> for data_types::DeduplicationPolicy {
+                    fn from(value: DeduplicationPolicy) -> Self {
+                        match value {
+                            DeduplicationPolicy::Unspecified | DeduplicationPolicy::Upsert => {
+                                data_types::DeduplicationPolicy::Upsert
+                            }
+                            DeduplicationPolicy::KeepFirst => {
+                                data_types::DeduplicationPolicy::KeepFirst
+                            }
+                            DeduplicationPolicy::AppendOnly => {
+                                data_types::DeduplicationPolicy::AppendOnly
+                            }
+                        }
+                    }
+                }
+
+                impl From<data_types::DeduplicationPolicy> for DeduplicationPolicy {
+                    fn from(value: data_types::DeduplicationPolicy) -> Self {
+                        match value {
+                            data_types::DeduplicationPolicy::Upsert => Self::Upsert,
+                            data_types::DeduplicationPolicy::KeepFirst => Self::KeepFirst,
+                            data_types::DeduplicationPolicy::AppendOnly => Self::AppendOnly,
+                        }
+                    }
+                }
+            }
+        }
+
         pub mod wal {
             pub mod v1 {
                 include!(concat!(env!("OUT_DIR"), "/influxdata.iox.wal.v1.rs"));
@@ -218,6 +287,69 @@ pub mod grpc {
     }
 }
 
+/// The OpenTelemetry protocol (OTLP) types.
+pub mod opentelemetry {
+    pub mod proto {
+        pub mod common {
+            pub mod v1 {
+                include!(concat!(
+                    env!("OUT_DIR"),
+                    "/opentelemetry.proto.common.v1.rs"
+                ));
+            }
+        }
+
+        pub mod metrics {
+            pub mod v1 {
+                include!(concat!(
+                    env!("OUT_DIR"),
+                    "/opentelemetry.proto.metrics.v1.rs"
+                ));
+            }
+        }
+
+        pub mod resource {
+            pub mod v1 {
+                include!(concat!(
+                    env!("OUT_DIR"),
+                    "/opentelemetry.proto.resource.v1.rs"
+                ));
+            }
+        }
+
+        pub mod trace {
+            pub mod v1 {
+                include!(concat!(env!("OUT_DIR"), "/opentelemetry.proto.trace.v1.rs"));
+            }
+        }
+
+        pub mod collector {
+            pub mod metrics {
+                pub mod v1 {
+                    include!(concat!(
+                        env!("OUT_DIR"),
+                        "/opentelemetry.proto.collector.metrics.v1.rs"
+                    ));
+                }
+            }
+
+            pub mod trace {
+                pub mod v1 {
+                    include!(concat!(
+                        env!("OUT_DIR"),
+                        "/opentelemetry.proto.collector.trace.v1.rs"
+                    ));
+                }
+            }
+        }
+    }
+}
+
+/// The Prometheus remote write types.
+pub mod prometheus {
+    include!(concat!(env!("OUT_DIR"), "/prometheus.rs"));
+}
+
 /// gRPC Storage Service
 pub const STORAGE_SERVICE: &str = "influxdata.platform.storage.Storage";
 
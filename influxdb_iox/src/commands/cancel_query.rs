@@ -0,0 +1,28 @@
+use influxdb_iox_client::{connection::Connection, flight};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Error cancelling query: {0}")]
+    Cancel(#[from] influxdb_iox_client::flight::Error),
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Cancel a running query
+#[derive(Debug, clap::Parser)]
+pub struct Config {
+    /// The ID of the query to cancel, as listed in the `system.queries` table
+    #[clap(action)]
+    query_id: String,
+}
+
+pub async fn command(connection: Connection, config: Config) -> Result<()> {
+    let mut client = flight::Client::new(connection);
+
+    client.cancel_query(config.query_id.clone()).await?;
+
+    println!("Cancelled query {}", config.query_id);
+
+    Ok(())
+}
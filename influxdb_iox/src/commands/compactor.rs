@@ -105,6 +105,7 @@ pub async fn command(config: Config) -> Result<()> {
                     Arc::clone(parquet_store.object_store()),
                 )]),
                 mem_pool_size: exec_mem_pool_bytes,
+                spill_dir: None,
             }));
             let time_provider = Arc::new(SystemProvider::new());
 
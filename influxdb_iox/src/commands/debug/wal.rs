@@ -0,0 +1,366 @@
+//! This module implements the `debug wal` CLI command
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use comfy_table::{Cell, Table};
+use generated_types::influxdata::iox::wal::v1::{
+    sequenced_wal_op::Op, SequencedWalOp as ProtoSequencedWalOp,
+};
+use mutable_batch_pb::decode::decode_database_batch;
+use schema::Projection;
+use thiserror::Error;
+use wal::{ClosedSegmentFileReader, SequencedWalOp};
+
+/// File extension of WAL segment files.
+const SEGMENT_FILE_EXTENSION: &str = "dat";
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Cannot read {path:?}: {source}")]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
+    #[error("Cannot write output: {0}")]
+    Output(#[source] std::io::Error),
+
+    #[error("WAL error: {0}")]
+    Wal(#[from] wal::Error),
+
+    #[error("JSON Serialization error: {0}")]
+    Serde(#[from] serde_json::Error),
+
+    #[error("Cannot decode write: {0}")]
+    Decode(#[from] mutable_batch_pb::decode::Error),
+
+    #[error("Cannot convert write to line protocol: {0}")]
+    LineProtocol(String),
+
+    #[error("{0} corrupt segment file(s) found")]
+    Corrupt(usize),
+}
+
+/// Inspect and repair the write-ahead log files of an ingester.
+///
+/// These commands read segment files directly and must not be used on the WAL
+/// directory of a running ingester, except for read-only inspection.
+#[derive(Debug, clap::Parser)]
+pub struct Config {
+    #[clap(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+enum DumpFormat {
+    /// One JSON object per op.
+    Json,
+    /// Line protocol, with a comment line giving the sequence number,
+    /// namespace ID and table ID of each write. Tables are named by their ID.
+    Lp,
+}
+
+/// All possible subcommands for the WAL
+#[derive(Debug, clap::Parser)]
+enum Command {
+    /// List the segment files in a WAL directory
+    List {
+        /// The WAL directory
+        #[clap(value_parser)]
+        directory: PathBuf,
+    },
+
+    /// Print all the ops in a segment file
+    Dump {
+        /// The segment file
+        #[clap(value_parser)]
+        file: PathBuf,
+
+        /// The output format
+        #[clap(long, value_enum, default_value = "json")]
+        format: DumpFormat,
+    },
+
+    /// Verify the checksums of all entries in segment files, failing if any
+    /// are corrupt
+    Verify {
+        /// Segment files, or WAL directories to verify all segments of
+        #[clap(value_parser, required = true)]
+        paths: Vec<PathBuf>,
+    },
+
+    /// Truncate a corrupt segment file after its last readable entry
+    Truncate {
+        /// The segment file
+        #[clap(value_parser)]
+        file: PathBuf,
+
+        /// Report what would be removed without modifying the file
+        #[clap(long, action)]
+        dry_run: bool,
+    },
+}
+
+pub fn command(config: Config) -> Result<(), Error> {
+    match config.command {
+        Command::List { directory } => {
+            let mut table = Table::new();
+            table.load_preset("||--+-++|    ++++++");
+            table.set_header(vec![
+                Cell::new("segment_id"),
+                Cell::new("size"),
+                Cell::new("entries"),
+                Cell::new("ops"),
+                Cell::new("min_sequence_number"),
+                Cell::new("max_sequence_number"),
+                Cell::new("status"),
+            ]);
+
+            for path in segment_files(&directory)? {
+                let scan = scan(&path)?;
+                table.add_row(vec![
+                    Cell::new(scan.segment_id),
+                    Cell::new(scan.size),
+                    Cell::new(scan.entries),
+                    Cell::new(scan.ops),
+                    Cell::new(display_opt(scan.min_sequence_number)),
+                    Cell::new(display_opt(scan.max_sequence_number)),
+                    Cell::new(scan.status()),
+                ]);
+            }
+
+            println!("{table}");
+        }
+
+        Command::Dump { file, format } => {
+            let mut reader = ClosedSegmentFileReader::from_path(&file)?;
+            let mut out = std::io::stdout().lock();
+            while let Some(ops) = reader.next_batch()? {
+                for op in ops {
+                    match format {
+                        DumpFormat::Json => write_json(&mut out, op)?,
+                        DumpFormat::Lp => write_lp(&mut out, op)?,
+                    }
+                }
+            }
+        }
+
+        Command::Verify { paths } => {
+            let mut corrupt = 0;
+            for path in paths {
+                let files = if path.is_dir() {
+                    segment_files(&path)?
+                } else {
+                    vec![path]
+                };
+
+                for file in files {
+                    let scan = scan(&file)?;
+                    if scan.error.is_some() {
+                        corrupt += 1;
+                    }
+                    println!("{}: {}", file.display(), scan.status());
+                }
+            }
+
+            if corrupt > 0 {
+                return Err(Error::Corrupt(corrupt));
+            }
+        }
+
+        Command::Truncate { file, dry_run } => {
+            let scan = scan(&file)?;
+            let Some(error) = &scan.error else {
+                println!("{}: no corruption found, not modified", file.display());
+                return Ok(());
+            };
+
+            let removed = scan.size - scan.valid_bytes;
+            if dry_run {
+                println!(
+                    "{}: would remove {removed} bytes after the last good entry at offset {} ({error})",
+                    file.display(),
+                    scan.valid_bytes,
+                );
+            } else {
+                wal::truncate_segment(&file, scan.valid_bytes)?;
+                println!(
+                    "{}: removed {removed} bytes after the last good entry at offset {} ({error})",
+                    file.display(),
+                    scan.valid_bytes,
+                );
+            }
+        } // Deliberately not adding _ => so the compiler will direct people here to impl new
+          // commands
+    }
+
+    Ok(())
+}
+
+/// The result of reading a segment file from start to end.
+#[derive(Debug)]
+struct Scan {
+    segment_id: wal::SegmentId,
+    size: u64,
+    entries: usize,
+    ops: usize,
+    min_sequence_number: Option<u64>,
+    max_sequence_number: Option<u64>,
+    /// The length of the header and all entries before the first corrupt one.
+    valid_bytes: u64,
+    /// The error that stopped reading the file, if any.
+    error: Option<wal::Error>,
+}
+
+impl Scan {
+    fn status(&self) -> String {
+        match &self.error {
+            None => "ok".to_string(),
+            Some(e) => format!(
+                "corrupt at offset {} ({} bytes affected): {e}",
+                self.valid_bytes,
+                self.size - self.valid_bytes
+            ),
+        }
+    }
+}
+
+/// Read all entries in the segment file at `path`, stopping at the first
+/// corrupt entry.
+fn scan(path: &Path) -> Result<Scan, Error> {
+    let size = std::fs::metadata(path)
+        .map_err(|source| Error::Io {
+            path: path.to_owned(),
+            source,
+        })?
+        .len();
+    let mut reader = ClosedSegmentFileReader::from_path(path)?;
+
+    let mut scan = Scan {
+        segment_id: reader.id(),
+        size,
+        entries: 0,
+        ops: 0,
+        min_sequence_number: None,
+        max_sequence_number: None,
+        valid_bytes: 0,
+        error: None,
+    };
+
+    loop {
+        match reader.next_batch() {
+            Ok(Some(ops)) => {
+                scan.entries += 1;
+                scan.ops += ops.len();
+                for op in ops {
+                    let n = Some(op.sequence_number);
+                    scan.min_sequence_number = scan.min_sequence_number.min(n).or(n);
+                    scan.max_sequence_number = scan.max_sequence_number.max(n);
+                }
+            }
+            Ok(None) => break,
+            Err(e) => {
+                scan.error = Some(e);
+                break;
+            }
+        }
+    }
+
+    scan.valid_bytes = reader.valid_bytes();
+    Ok(scan)
+}
+
+/// Return the segment files in `directory`, ordered by segment ID.
+fn segment_files(directory: &Path) -> Result<Vec<PathBuf>, Error> {
+    let io_err = |source| Error::Io {
+        path: directory.to_owned(),
+        source,
+    };
+
+    let mut files = std::fs::read_dir(directory)
+        .map_err(io_err)?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(io_err)?
+        .into_iter()
+        .filter_map(|path| {
+            if path.extension()? != SEGMENT_FILE_EXTENSION {
+                return None;
+            }
+            let id = path.file_stem()?.to_str()?.parse::<u64>().ok()?;
+            Some((id, path))
+        })
+        .collect::<Vec<_>>();
+
+    files.sort_unstable_by_key(|(id, _)| *id);
+    Ok(files.into_iter().map(|(_, path)| path).collect())
+}
+
+fn write_json(out: &mut impl Write, op: SequencedWalOp) -> Result<(), Error> {
+    let json = serde_json::to_string(&ProtoSequencedWalOp::from(op))?;
+    writeln!(out, "{json}").map_err(Error::Output)
+}
+
+fn write_lp(out: &mut impl Write, op: SequencedWalOp) -> Result<(), Error> {
+    let SequencedWalOp {
+        sequence_number,
+        op,
+    } = op;
+
+    let write = match op {
+        Op::Write(w) => w,
+        Op::Delete(d) => {
+            return writeln!(
+                out,
+                "# sequence_number={sequence_number} namespace_id={} delete (not representable as line protocol)",
+                d.database_id
+            )
+            .map_err(Error::Output);
+        }
+        Op::Persist(p) => {
+            return writeln!(
+                out,
+                "# sequence_number={sequence_number} namespace_id={} table_id={} persist",
+                p.namespace_id, p.table_id
+            )
+            .map_err(Error::Output);
+        }
+    };
+
+    let mut batches = decode_database_batch(&write)?
+        .into_iter()
+        .collect::<Vec<_>>();
+    batches.sort_unstable_by_key(|(table_id, _)| *table_id);
+
+    for (table_id, batch) in batches {
+        writeln!(
+            out,
+            "# sequence_number={sequence_number} namespace_id={} table_id={table_id} partition_key={}",
+            write.database_id, write.partition_key
+        )
+        .map_err(Error::Output)?;
+
+        let schema = batch
+            .schema(Projection::All)
+            .map_err(|e| Error::LineProtocol(e.to_string()))?;
+        let record_batch = batch
+            .to_arrow(Projection::All)
+            .map_err(|e| Error::LineProtocol(e.to_string()))?;
+        let lines = parquet_to_line_protocol::convert_to_lines(
+            &table_id.to_string(),
+            &schema,
+            &record_batch,
+        )
+        .map_err(Error::LineProtocol)?;
+
+        out.write_all(&lines).map_err(Error::Output)?;
+    }
+
+    Ok(())
+}
+
+fn display_opt(v: Option<u64>) -> String {
+    v.map(|v| v.to_string()).unwrap_or_default()
+}
@@ -0,0 +1,91 @@
+use std::time::Duration;
+
+use influxdb_iox_client::{
+    connection::Connection,
+    downsampling::generated_types::{CreateDownsamplingTaskRequest, QueryLanguage},
+};
+use iox_time::Time;
+
+/// Create a new downsampling task
+#[derive(Debug, clap::Parser)]
+pub struct Config {
+    /// The unique name of the task
+    #[clap(action)]
+    name: String,
+
+    /// The namespace the query runs against
+    #[clap(action)]
+    namespace: String,
+
+    /// The query, run once per window. It refers to the bounds of the window
+    /// as `$window_start` (inclusive) and `$window_end` (exclusive).
+    #[clap(action)]
+    query: String,
+
+    /// The namespace the results are written to
+    #[clap(action, long = "target-namespace")]
+    target_namespace: String,
+
+    /// The table the results are written to
+    #[clap(action, long = "target-table")]
+    target_table: String,
+
+    /// The length of the windows, e.g. `1h`
+    #[clap(action, long = "every", value_parser = humantime::parse_duration)]
+    every: Duration,
+
+    /// How long to wait after the end of a window before it is run, for late
+    /// writes to arrive
+    #[clap(action, long = "delay", default_value = "0s", value_parser = humantime::parse_duration)]
+    delay: Duration,
+
+    /// The language of the query, one of `sql` or `influxql`
+    #[clap(action, long = "lang", default_value = "sql", value_parser = parse_query_language)]
+    query_language: QueryLanguage,
+
+    /// The RFC3339 start of the first window, rounded down to a window
+    /// boundary. Defaults to the current window.
+    #[clap(action, long = "start", value_parser = super::parse_time)]
+    start: Option<Time>,
+}
+
+fn parse_query_language(s: &str) -> Result<QueryLanguage, String> {
+    let lang: data_types::QueryLanguage = s.parse()?;
+    Ok(lang.into())
+}
+
+pub async fn command(
+    connection: Connection,
+    config: Config,
+) -> Result<(), crate::commands::downsampling::Error> {
+    let Config {
+        name,
+        namespace,
+        query,
+        target_namespace,
+        target_table,
+        every,
+        delay,
+        query_language,
+        start,
+    } = config;
+
+    let mut client = influxdb_iox_client::downsampling::Client::new(connection);
+
+    let task = client
+        .create_downsampling_task(CreateDownsamplingTaskRequest {
+            name,
+            namespace_name: namespace,
+            query,
+            query_language: query_language as i32,
+            target_namespace,
+            target_table,
+            every_ns: every.as_nanos() as i64,
+            delay_ns: delay.as_nanos() as i64,
+            start_ns: start.map(|t| t.timestamp_nanos()),
+        })
+        .await?;
+    println!("{}", serde_json::to_string_pretty(&task)?);
+
+    Ok(())
+}
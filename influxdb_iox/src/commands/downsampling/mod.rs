@@ -0,0 +1,92 @@
+//! This module implements the `downsampling` CLI command
+
+use influxdb_iox_client::{connection::Connection, downsampling};
+use iox_time::Time;
+use thiserror::Error;
+
+mod create;
+
+#[allow(clippy::enum_variant_names)]
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("JSON Serialization error: {0}")]
+    Serde(#[from] serde_json::Error),
+
+    #[error("Client error: {0}")]
+    ClientError(#[from] influxdb_iox_client::error::Error),
+}
+
+/// Various commands for managing downsampling tasks
+#[derive(Debug, clap::Parser)]
+pub struct Config {
+    #[clap(subcommand)]
+    command: Command,
+}
+
+/// Delete a downsampling task, leaving the rollups it wrote in place
+#[derive(Debug, clap::Parser)]
+struct Delete {
+    /// The task to be deleted
+    #[clap(action)]
+    name: String,
+}
+
+/// Run the windows of a downsampling task again from an earlier time
+#[derive(Debug, clap::Parser)]
+struct Backfill {
+    /// The task to be backfilled
+    #[clap(action)]
+    name: String,
+
+    /// The RFC3339 start of the first window to run again, rounded down to a
+    /// window boundary
+    #[clap(action, value_parser = parse_time)]
+    start: Time,
+}
+
+/// All possible subcommands for downsampling
+#[derive(Debug, clap::Parser)]
+enum Command {
+    /// Create a new downsampling task
+    Create(create::Config),
+
+    /// Fetch all downsampling tasks
+    List,
+
+    /// Delete a downsampling task
+    Delete(Delete),
+
+    /// Run the windows of a downsampling task again from an earlier time
+    Backfill(Backfill),
+}
+
+pub(super) fn parse_time(s: &str) -> Result<Time, String> {
+    Time::from_rfc3339(s).map_err(|e| format!("invalid RFC3339 timestamp '{s}': {e}"))
+}
+
+pub async fn command(connection: Connection, config: Config) -> Result<(), Error> {
+    match config.command {
+        Command::Create(config) => {
+            create::command(connection, config).await?;
+        }
+        Command::List => {
+            let mut client = downsampling::Client::new(connection);
+            let tasks = client.get_downsampling_tasks().await?;
+            println!("{}", serde_json::to_string_pretty(&tasks)?);
+        }
+        Command::Delete(Delete { name }) => {
+            let mut client = downsampling::Client::new(connection);
+            client.delete_downsampling_task(&name).await?;
+            println!("Deleted downsampling task {name}");
+        }
+        Command::Backfill(Backfill { name, start }) => {
+            let mut client = downsampling::Client::new(connection);
+            let task = client
+                .backfill_downsampling_task(&name, start.timestamp_nanos())
+                .await?;
+            println!("{}", serde_json::to_string_pretty(&task)?);
+        } // Deliberately not adding _ => so the compiler will direct people here to impl new
+          // commands
+    }
+    Ok(())
+}
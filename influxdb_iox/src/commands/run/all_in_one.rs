@@ -27,8 +27,11 @@ use ioxd_querier::{create_querier_server_type, QuerierServerTypeArgs};
 use ioxd_router::create_router_server_type;
 use object_store::DynObjectStore;
 use observability_deps::tracing::*;
-use parquet_file::storage::{ParquetStorage, StorageId};
-use std::{collections::HashMap, sync::Arc};
+use parquet_file::{
+    serialize::BloomFilterConfig,
+    storage::{ParquetStorage, StorageId},
+};
+use std::{collections::HashMap, path::PathBuf, sync::Arc, time::Duration};
 use thiserror::Error;
 use trace_exporters::TracingConfig;
 use trogging::cli::LoggingConfig;
@@ -343,6 +346,10 @@ pub struct Config {
         action
     )]
     pub exec_mem_pool_bytes: usize,
+
+    /// Directory for temporary files of queries that exceed their share of the memory pool.
+    #[clap(long = "exec-spill-dir", env = "INFLUXDB_IOX_EXEC_SPILL_DIR", action)]
+    pub exec_spill_dir: Option<PathBuf>,
 }
 
 impl Config {
@@ -369,6 +376,7 @@ impl Config {
             querier_ram_pool_data_bytes,
             querier_max_concurrent_queries,
             exec_mem_pool_bytes,
+            exec_spill_dir,
         } = self;
 
         let database_directory = object_store_config.database_directory.clone();
@@ -436,6 +444,11 @@ impl Config {
             http_request_limit: 1_000,
             new_namespace_retention_hours: None, // infinite retention
             namespace_autocreation_enabled: true,
+            namespace_cache_max_entries: 10_000,
+            namespace_cache_refresh_interval_seconds: 60,
+            self_monitoring_enabled: false,
+            self_monitoring_namespace: "_monitoring".to_string(),
+            self_monitoring_interval_seconds: 10,
         };
 
         // create a CompactorConfig for the all in one server based on
@@ -467,15 +480,25 @@ impl Config {
         };
 
         let querier_config = QuerierConfig {
-            num_query_threads: None,       // will be ignored
-            shard_to_ingesters_file: None, // will be ignored
-            shard_to_ingesters: None,      // will be ignored
-            ingester_addresses: vec![],    // will be ignored
+            num_query_threads: None,                              // will be ignored
+            shard_to_ingesters_file: None,                        // will be ignored
+            shard_to_ingesters: None,                             // will be ignored
+            ingester_addresses: vec![],                           // will be ignored
+            ingester_dns_name: None,                              // will be ignored
+            ingester_discovery_interval: Duration::from_secs(10), // will be ignored
             ram_pool_metadata_bytes: querier_ram_pool_metadata_bytes,
             ram_pool_data_bytes: querier_ram_pool_data_bytes,
+            parquet_disk_cache_dir: None,
+            parquet_disk_cache_bytes: 0,
+            query_result_cache_bytes: 0,
             max_concurrent_queries: querier_max_concurrent_queries,
             exec_mem_pool_bytes,
+            exec_spill_dir,
             ingester_circuit_breaker_threshold: u64::MAX, // never for all-in-one-mode
+            last_value_cache_tables: vec![],
+            downsampling_router_address: Some(format!("http://{router_grpc_bind_address}")),
+            downsampling_interval: Duration::from_secs(10),
+            namespace_cache_max_refresh_interval: None,
         };
 
         SpecializedConfig {
@@ -558,7 +581,8 @@ pub async fn command(config: Config) -> Result<()> {
     let num_threads = num_cpus::get();
     info!(%num_threads, "Creating shared query executor");
 
-    let parquet_store = ParquetStorage::new(Arc::clone(&object_store), StorageId::from("iox"));
+    let parquet_store = ParquetStorage::new(Arc::clone(&object_store), StorageId::from("iox"))
+        .with_bloom_filters(BloomFilterConfig::default());
     let exec = Arc::new(Executor::new_with_config(ExecutorConfig {
         num_threads,
         target_query_partitions: num_threads,
@@ -567,6 +591,7 @@ pub async fn command(config: Config) -> Result<()> {
             Arc::clone(parquet_store.object_store()),
         )]),
         mem_pool_size: querier_config.exec_mem_pool_bytes,
+        spill_dir: querier_config.exec_spill_dir.clone(),
     }));
 
     info!("starting router");
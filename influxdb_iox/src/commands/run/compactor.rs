@@ -5,14 +5,14 @@ use iox_time::{SystemProvider, TimeProvider};
 use object_store::DynObjectStore;
 use object_store_metrics::ObjectStoreMetrics;
 use observability_deps::tracing::*;
-use parquet_file::storage::{ParquetStorage, StorageId};
 use std::collections::HashMap;
 use std::sync::Arc;
 use thiserror::Error;
 
 use clap_blocks::object_store::make_object_store;
 use clap_blocks::{
-    catalog_dsn::CatalogDsnConfig, compactor::CompactorConfig, run_config::RunConfig,
+    catalog_dsn::CatalogDsnConfig, compactor::CompactorConfig,
+    parquet_bloom_filter::ParquetBloomFilterConfig, run_config::RunConfig,
 };
 use ioxd_common::server_type::{CommonServerState, CommonServerStateError};
 use ioxd_common::Service;
@@ -68,6 +68,9 @@ pub struct Config {
     #[clap(flatten)]
     pub(crate) compactor_config: CompactorConfig,
 
+    #[clap(flatten)]
+    pub(crate) bloom_filter_config: ParquetBloomFilterConfig,
+
     /// Number of threads to use for the compactor query execution, compaction and persistence.
     #[clap(
         long = "query-exec-thread-count",
@@ -114,7 +117,7 @@ pub async fn command(config: Config) -> Result<(), Error> {
         &metric_registry,
     ));
 
-    let parquet_store = ParquetStorage::new(object_store, StorageId::from("iox"));
+    let parquet_store = super::parquet_storage(object_store, &config.bloom_filter_config);
 
     let exec = Arc::new(Executor::new_with_config(ExecutorConfig {
         num_threads: config.query_exec_thread_count,
@@ -124,6 +127,7 @@ pub async fn command(config: Config) -> Result<(), Error> {
             Arc::clone(parquet_store.object_store()),
         )]),
         mem_pool_size: config.exec_mem_pool_bytes,
+        spill_dir: None,
     }));
     let time_provider = Arc::new(SystemProvider::new());
 
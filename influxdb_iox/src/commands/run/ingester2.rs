@@ -4,7 +4,7 @@ use super::main;
 use crate::process_info::{setup_metric_registry, USIZE_MAX};
 use clap_blocks::{
     catalog_dsn::CatalogDsnConfig, ingester2::Ingester2Config, object_store::make_object_store,
-    run_config::RunConfig,
+    parquet_bloom_filter::ParquetBloomFilterConfig, run_config::RunConfig,
 };
 use iox_query::exec::Executor;
 use ioxd_common::{
@@ -13,7 +13,6 @@ use ioxd_common::{
 };
 use ioxd_ingester2::create_ingester_server_type;
 use observability_deps::tracing::*;
-use parquet_file::storage::{ParquetStorage, StorageId};
 use std::sync::Arc;
 use thiserror::Error;
 
@@ -61,6 +60,9 @@ pub struct Config {
     #[clap(flatten)]
     pub(crate) ingester_config: Ingester2Config,
 
+    #[clap(flatten)]
+    pub(crate) bloom_filter_config: ParquetBloomFilterConfig,
+
     /// Specify the size of the thread-pool for query execution, and the
     /// separate compaction thread-pool.
     #[clap(
@@ -110,7 +112,7 @@ pub async fn command(config: Config) -> Result<()> {
         Arc::clone(&metric_registry),
         &config.ingester_config,
         exec,
-        ParquetStorage::new(object_store, StorageId::from("iox")),
+        super::parquet_storage(object_store, &config.bloom_filter_config),
     )
     .await?;
 
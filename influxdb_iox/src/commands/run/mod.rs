@@ -1,4 +1,11 @@
+use clap_blocks::parquet_bloom_filter::ParquetBloomFilterConfig;
+use object_store::DynObjectStore;
+use parquet_file::{
+    serialize::BloomFilterConfig,
+    storage::{ParquetStorage, StorageId},
+};
 use snafu::{ResultExt, Snafu};
+use std::sync::Arc;
 use trogging::cli::LoggingConfig;
 
 pub(crate) mod all_in_one;
@@ -146,3 +153,20 @@ pub async fn command(config: Config) -> Result<()> {
         Some(Command::Test(config)) => test::command(config).await.context(TestSnafu),
     }
 }
+
+/// Initialise the [`ParquetStorage`] used by services that create parquet
+/// files, writing bloom filters as configured by `bloom_filters`.
+fn parquet_storage(
+    object_store: Arc<DynObjectStore>,
+    bloom_filters: &ParquetBloomFilterConfig,
+) -> ParquetStorage {
+    let store = ParquetStorage::new(object_store, StorageId::from("iox"));
+    if !bloom_filters.enabled {
+        return store;
+    }
+
+    store.with_bloom_filters(BloomFilterConfig {
+        fpp: bloom_filters.fpp,
+        ndv: bloom_filters.ndv,
+    })
+}
@@ -0,0 +1,76 @@
+use std::collections::HashMap;
+
+use influxdb_iox_client::{
+    connection::Connection, schema::generated_types::column_schema::ColumnType,
+    table::generated_types::DeduplicationPolicy,
+};
+
+/// Create a new table with an explicitly declared set of columns
+#[derive(Debug, clap::Parser)]
+pub struct Config {
+    /// The namespace to create the table in
+    #[clap(action)]
+    namespace: String,
+
+    /// The table to be created
+    #[clap(action)]
+    table: String,
+
+    /// A column to create, in the form `name:type`, where type is one of
+    /// `tag`, `i64`, `u64`, `f64`, `bool` or `string`.
+    ///
+    /// May be specified multiple times. A `time` column is always created.
+    #[clap(action, long = "column", short = 'c', value_parser = parse_column)]
+    columns: Vec<(String, ColumnType)>,
+
+    /// How rows with the same primary key are resolved, one of `upsert`,
+    /// `keep_first` or `append_only`.
+    #[clap(action, long = "dedup-policy", default_value = "upsert", value_parser = parse_dedup_policy)]
+    dedup_policy: DeduplicationPolicy,
+}
+
+pub(super) fn parse_dedup_policy(s: &str) -> Result<DeduplicationPolicy, String> {
+    let policy: data_types::DeduplicationPolicy = s.parse()?;
+    Ok(policy.into())
+}
+
+fn parse_column(s: &str) -> Result<(String, ColumnType), String> {
+    let (name, column_type) = s
+        .rsplit_once(':')
+        .ok_or_else(|| format!("invalid column '{s}', expected 'name:type'"))?;
+
+    let column_type = match column_type {
+        "tag" => ColumnType::Tag,
+        "i64" => ColumnType::I64,
+        "u64" => ColumnType::U64,
+        "f64" => ColumnType::F64,
+        "bool" => ColumnType::Bool,
+        "string" => ColumnType::String,
+        "time" => ColumnType::Time,
+        v => return Err(format!("unknown column type '{v}' for column '{name}'")),
+    };
+
+    Ok((name.to_string(), column_type))
+}
+
+pub async fn command(
+    connection: Connection,
+    config: Config,
+) -> Result<(), crate::commands::table::Error> {
+    let Config {
+        namespace,
+        table,
+        columns,
+        dedup_policy,
+    } = config;
+
+    let mut client = influxdb_iox_client::table::Client::new(connection);
+
+    let columns: HashMap<_, _> = columns.into_iter().collect();
+    let table = client
+        .create_table(&namespace, &table, columns, dedup_policy)
+        .await?;
+    println!("{}", serde_json::to_string_pretty(&table)?);
+
+    Ok(())
+}
@@ -0,0 +1,109 @@
+//! This module implements the `table` CLI command
+
+use influxdb_iox_client::{
+    connection::Connection,
+    table::{self, generated_types::DeduplicationPolicy},
+};
+use thiserror::Error;
+
+mod create;
+
+#[allow(clippy::enum_variant_names)]
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("JSON Serialization error: {0}")]
+    Serde(#[from] serde_json::Error),
+
+    #[error("Client error: {0}")]
+    ClientError(#[from] influxdb_iox_client::error::Error),
+}
+
+/// Various commands for table inspection and manipulation
+#[derive(Debug, clap::Parser)]
+pub struct Config {
+    #[clap(subcommand)]
+    command: Command,
+}
+
+/// Fetch the tables of a namespace
+#[derive(Debug, clap::Parser)]
+struct List {
+    /// The namespace to list the tables of
+    #[clap(action)]
+    namespace: String,
+}
+
+/// Delete a table and all of the data within it
+#[derive(Debug, clap::Parser)]
+struct Delete {
+    /// The namespace the table belongs to
+    #[clap(action)]
+    namespace: String,
+
+    /// The table to be deleted
+    #[clap(action)]
+    table: String,
+}
+
+/// Change how rows with the same primary key are resolved for a table
+#[derive(Debug, clap::Parser)]
+struct UpdateDedupPolicy {
+    /// The namespace the table belongs to
+    #[clap(action)]
+    namespace: String,
+
+    /// The table to be updated
+    #[clap(action)]
+    table: String,
+
+    /// The new policy, one of `upsert`, `keep_first` or `append_only`
+    #[clap(action, value_parser = create::parse_dedup_policy)]
+    dedup_policy: DeduplicationPolicy,
+}
+
+/// All possible subcommands for table
+#[derive(Debug, clap::Parser)]
+enum Command {
+    /// Create a new table with an explicit schema
+    Create(create::Config),
+
+    /// Fetch the tables of a namespace
+    List(List),
+
+    /// Delete a table and all of the data within it
+    Delete(Delete),
+
+    /// Change how rows with the same primary key are resolved for a table
+    UpdateDedupPolicy(UpdateDedupPolicy),
+}
+
+pub async fn command(connection: Connection, config: Config) -> Result<(), Error> {
+    match config.command {
+        Command::Create(config) => {
+            create::command(connection, config).await?;
+        }
+        Command::List(List { namespace }) => {
+            let mut client = table::Client::new(connection);
+            let tables = client.get_tables(&namespace).await?;
+            println!("{}", serde_json::to_string_pretty(&tables)?);
+        }
+        Command::Delete(Delete { namespace, table }) => {
+            let mut client = table::Client::new(connection);
+            client.delete_table(&namespace, &table).await?;
+            println!("Deleted table {table} from namespace {namespace}");
+        }
+        Command::UpdateDedupPolicy(UpdateDedupPolicy {
+            namespace,
+            table,
+            dedup_policy,
+        }) => {
+            let mut client = table::Client::new(connection);
+            let table = client
+                .update_table_dedup_policy(&namespace, &table, dedup_policy)
+                .await?;
+            println!("{}", serde_json::to_string_pretty(&table)?);
+        } // Deliberately not adding _ => so the compiler will direct people here to impl new
+          // commands
+    }
+    Ok(())
+}
@@ -33,9 +33,11 @@ use std::{
 use tokio::runtime::Runtime;
 
 mod commands {
+    pub mod cancel_query;
     pub mod catalog;
     pub mod compactor;
     pub mod debug;
+    pub mod downsampling;
     pub mod import;
     pub mod namespace;
     pub mod query;
@@ -44,6 +46,7 @@ mod commands {
     pub mod run;
     pub mod sql;
     pub mod storage;
+    pub mod table;
     pub mod tracing;
     pub mod write;
 }
@@ -190,11 +193,20 @@ enum Command {
     /// Query the ingester only
     QueryIngester(commands::query_ingester::Config),
 
+    /// Cancel a running query
+    CancelQuery(commands::cancel_query::Config),
+
     /// Commands related to the bulk ingest of data
     Import(commands::import::Config),
 
     /// Various commands for namespace manipulation
     Namespace(commands::namespace::Config),
+
+    /// Various commands for table manipulation
+    Table(commands::table::Config),
+
+    /// Various commands for managing downsampling tasks
+    Downsampling(commands::downsampling::Config),
 }
 
 fn main() -> Result<(), std::io::Error> {
@@ -346,6 +358,14 @@ fn main() -> Result<(), std::io::Error> {
                     std::process::exit(ReturnCode::Failure as _)
                 }
             }
+            Some(Command::CancelQuery(config)) => {
+                let _tracing_guard = handle_init_logs(init_simple_logs(log_verbose_count));
+                let connection = connection().await;
+                if let Err(e) = commands::cancel_query::command(connection, config).await {
+                    eprintln!("{}", e);
+                    std::process::exit(ReturnCode::Failure as _)
+                }
+            }
             Some(Command::Import(config)) => {
                 let _tracing_guard = handle_init_logs(init_simple_logs(log_verbose_count));
                 let connection = connection().await;
@@ -362,6 +382,22 @@ fn main() -> Result<(), std::io::Error> {
                     std::process::exit(ReturnCode::Failure as _)
                 }
             }
+            Some(Command::Table(config)) => {
+                let _tracing_guard = handle_init_logs(init_simple_logs(log_verbose_count));
+                let connection = connection().await;
+                if let Err(e) = commands::table::command(connection, config).await {
+                    eprintln!("{}", e);
+                    std::process::exit(ReturnCode::Failure as _)
+                }
+            }
+            Some(Command::Downsampling(config)) => {
+                let _tracing_guard = handle_init_logs(init_simple_logs(log_verbose_count));
+                let connection = connection().await;
+                if let Err(e) = commands::downsampling::command(connection, config).await {
+                    eprintln!("{}", e);
+                    std::process::exit(ReturnCode::Failure as _)
+                }
+            }
         }
     });
 
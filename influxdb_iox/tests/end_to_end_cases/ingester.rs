@@ -57,6 +57,7 @@ mod with_kafka {
                 }),
                 ingester_uuid: String::new(),
                 completed_persistence_count: 0,
+                buffer_version: 0,
             },
         );
 
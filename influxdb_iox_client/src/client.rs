@@ -7,6 +7,9 @@ pub mod compactor;
 /// Client for delete API
 pub mod delete;
 
+/// Client for downsampling task API
+pub mod downsampling;
+
 /// Errors for the client
 pub mod error;
 
@@ -29,6 +32,9 @@ pub mod schema;
 /// Client for interacting with a remote object store
 pub mod store;
 
+/// Client for table API
+pub mod table;
+
 /// Client for testing purposes.
 pub mod test;
 
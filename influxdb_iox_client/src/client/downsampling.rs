@@ -0,0 +1,99 @@
+use client_util::connection::GrpcConnection;
+
+use self::generated_types::{
+    downsampling_service_client::DownsamplingServiceClient,
+    downsampling_write_service_client::DownsamplingWriteServiceClient, *,
+};
+use crate::connection::Connection;
+use crate::error::Error;
+use ::generated_types::{google::OptionalField, influxdata::pbdata::v1::TableBatch};
+
+/// Re-export generated_types
+pub mod generated_types {
+    pub use generated_types::influxdata::iox::downsampling::v1::*;
+}
+
+/// A basic client for working with downsampling tasks.
+#[derive(Debug, Clone)]
+pub struct Client {
+    inner: DownsamplingServiceClient<GrpcConnection>,
+    write_inner: DownsamplingWriteServiceClient<GrpcConnection>,
+}
+
+impl Client {
+    /// Creates a new client with the provided connection
+    pub fn new(connection: Connection) -> Self {
+        Self {
+            inner: DownsamplingServiceClient::new(connection.clone().into_grpc_connection()),
+            write_inner: DownsamplingWriteServiceClient::new(connection.into_grpc_connection()),
+        }
+    }
+
+    /// Get all downsampling tasks
+    pub async fn get_downsampling_tasks(&mut self) -> Result<Vec<DownsamplingTask>, Error> {
+        let response = self
+            .inner
+            .get_downsampling_tasks(GetDownsamplingTasksRequest {})
+            .await?;
+
+        Ok(response.into_inner().tasks)
+    }
+
+    /// Create a downsampling task
+    pub async fn create_downsampling_task(
+        &mut self,
+        request: CreateDownsamplingTaskRequest,
+    ) -> Result<DownsamplingTask, Error> {
+        let response = self.inner.create_downsampling_task(request).await?;
+
+        Ok(response.into_inner().task.unwrap_field("task")?)
+    }
+
+    /// Delete a downsampling task, leaving the rollups it wrote in place
+    pub async fn delete_downsampling_task(&mut self, name: &str) -> Result<(), Error> {
+        self.inner
+            .delete_downsampling_task(DeleteDownsamplingTaskRequest {
+                name: name.to_string(),
+            })
+            .await?;
+
+        Ok(())
+    }
+
+    /// Run the windows of a downsampling task again, starting at the window
+    /// containing `start_ns`
+    pub async fn backfill_downsampling_task(
+        &mut self,
+        name: &str,
+        start_ns: i64,
+    ) -> Result<DownsamplingTask, Error> {
+        let response = self
+            .inner
+            .backfill_downsampling_task(BackfillDownsamplingTaskRequest {
+                name: name.to_string(),
+                start_ns,
+            })
+            .await?;
+
+        Ok(response.into_inner().task.unwrap_field("task")?)
+    }
+
+    /// Write the results of a window of a downsampling task into `table` of
+    /// `namespace`
+    pub async fn write_window(
+        &mut self,
+        namespace: &str,
+        table: &str,
+        batch: TableBatch,
+    ) -> Result<(), Error> {
+        self.write_inner
+            .write_window(WriteWindowRequest {
+                namespace: namespace.to_string(),
+                table: table.to_string(),
+                batch: Some(batch),
+            })
+            .await?;
+
+        Ok(())
+    }
+}
@@ -14,10 +14,13 @@ use arrow::{
 
 use rand::Rng;
 
-use iox_arrow_flight::{FlightClient, FlightError, FlightRecordBatchStream};
+use iox_arrow_flight::{Action, FlightClient, FlightError, FlightRecordBatchStream};
 
 use crate::connection::Connection;
 
+/// The Flight `DoAction` type used to cancel a running query.
+const CANCEL_QUERY_ACTION: &str = "cancel_query";
+
 /// Re-export generated_types
 pub mod generated_types {
     pub use generated_types::influxdata::iox::{
@@ -201,6 +204,18 @@ impl Client {
         self.do_get_with_read_info(request).await
     }
 
+    /// Cancel the running query with the given ID, as listed in the
+    /// `system.queries` table of the querier running it.
+    pub async fn cancel_query(&mut self, query_id: String) -> Result<(), Error> {
+        let action = Action {
+            r#type: CANCEL_QUERY_ACTION.to_string(),
+            body: query_id.into_bytes().into(),
+        };
+
+        self.inner.do_action(action).await?;
+        Ok(())
+    }
+
     /// Perform a lower level client read with the `ReadInfo`
     async fn do_get_with_read_info(
         &mut self,
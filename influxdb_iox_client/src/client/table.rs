@@ -0,0 +1,99 @@
+use std::collections::HashMap;
+
+use client_util::connection::GrpcConnection;
+
+use self::generated_types::{table_service_client::TableServiceClient, *};
+use crate::connection::Connection;
+use crate::error::Error;
+use ::generated_types::google::OptionalField;
+use ::generated_types::influxdata::iox::schema::v1::column_schema::ColumnType;
+
+/// Re-export generated_types
+pub mod generated_types {
+    pub use generated_types::influxdata::iox::table::v1::*;
+}
+
+/// A basic client for working with Tables.
+#[derive(Debug, Clone)]
+pub struct Client {
+    inner: TableServiceClient<GrpcConnection>,
+}
+
+impl Client {
+    /// Creates a new client with the provided connection
+    pub fn new(connection: Connection) -> Self {
+        Self {
+            inner: TableServiceClient::new(connection.into_grpc_connection()),
+        }
+    }
+
+    /// Get the tables of a namespace
+    pub async fn get_tables(&mut self, namespace: &str) -> Result<Vec<Table>, Error> {
+        let response = self
+            .inner
+            .get_tables(GetTablesRequest {
+                namespace_name: namespace.to_string(),
+            })
+            .await?;
+
+        Ok(response.into_inner().tables)
+    }
+
+    /// Create a table in `namespace` with the given columns and
+    /// deduplication policy.
+    ///
+    /// A `time` column is always created, and does not need to be specified.
+    pub async fn create_table(
+        &mut self,
+        namespace: &str,
+        table: &str,
+        columns: HashMap<String, ColumnType>,
+        dedup_policy: DeduplicationPolicy,
+    ) -> Result<Table, Error> {
+        let response = self
+            .inner
+            .create_table(CreateTableRequest {
+                namespace_name: namespace.to_string(),
+                name: table.to_string(),
+                columns: columns
+                    .into_iter()
+                    .map(|(name, column_type)| (name, column_type as i32))
+                    .collect(),
+                dedup_policy: dedup_policy as i32,
+            })
+            .await?;
+
+        Ok(response.into_inner().table.unwrap_field("table")?)
+    }
+
+    /// Delete a table, and all the data within it
+    pub async fn delete_table(&mut self, namespace: &str, table: &str) -> Result<(), Error> {
+        self.inner
+            .delete_table(DeleteTableRequest {
+                namespace_name: namespace.to_string(),
+                name: table.to_string(),
+            })
+            .await?;
+
+        Ok(())
+    }
+
+    /// Change how rows with the same primary key are resolved for a table
+    pub async fn update_table_dedup_policy(
+        &mut self,
+        namespace: &str,
+        table: &str,
+        dedup_policy: DeduplicationPolicy,
+    ) -> Result<Table, Error> {
+        let response = self
+            .inner
+            .update_table_dedup_policy(UpdateTableDedupPolicyRequest {
+                namespace_name: namespace.to_string(),
+                name: table.to_string(),
+                dedup_policy: dedup_policy as i32,
+            })
+            .await?;
+
+        Ok(response.into_inner().table.unwrap_field("table")?)
+    }
+}
@@ -430,6 +430,8 @@ impl Stream for FlightFrameCodec {
                         }),
                         ingester_uuid: this.ingester_uuid.to_string(),
                         completed_persistence_count,
+                        // The replica does not track versions of its buffer.
+                        buffer_version: 0,
                     };
                     prost::Message::encode(&app_metadata, &mut bytes).map_err(Error::from)?;
 
@@ -525,6 +527,7 @@ mod tests {
                         }),
                         completed_persistence_count: 0,
                         ingester_uuid: ingester_uuid.to_string(),
+                        buffer_version: 0,
                     },
                 }),
                 Ok(DecodedFlightData {
@@ -572,6 +575,7 @@ mod tests {
                         }),
                         completed_persistence_count: 0,
                         ingester_uuid: ingester_uuid.to_string(),
+                        buffer_version: 0,
                     },
                 }),
                 Err(tonic::Code::Internal),
@@ -2,6 +2,7 @@
 
 use std::sync::Arc;
 
+use data_types::DeduplicationPolicy;
 use datafusion::{error::DataFusionError, physical_plan::SendableRecordBatchStream};
 use iox_query::{
     exec::{Executor, ExecutorType},
@@ -87,10 +88,13 @@ impl std::fmt::Debug for CompactedStream {
 
 /// Compact a given batch into a [`CompactedStream`] or `None` if there is no
 /// data to compact, returning an updated sort key, if any.
+///
+/// Duplicate rows are resolved according to `dedup_policy`.
 pub(crate) async fn compact_persisting_batch(
     executor: &Executor,
     sort_key: Option<SortKey>,
     table_name: TableName,
+    dedup_policy: DeduplicationPolicy,
     batch: QueryAdaptor,
 ) -> Result<CompactedStream> {
     assert!(!batch.record_batches().is_empty());
@@ -119,7 +123,14 @@ pub(crate) async fn compact_persisting_batch(
     };
 
     // Compact
-    let stream = compact(executor, table_name, Arc::new(batch), data_sort_key.clone()).await?;
+    let stream = compact(
+        executor,
+        table_name,
+        dedup_policy,
+        Arc::new(batch),
+        data_sort_key.clone(),
+    )
+    .await?;
 
     Ok(CompactedStream {
         stream,
@@ -132,12 +143,14 @@ pub(crate) async fn compact_persisting_batch(
 pub(crate) async fn compact(
     executor: &Executor,
     table_name: TableName,
+    dedup_policy: DeduplicationPolicy,
     data: Arc<QueryAdaptor>,
     sort_key: SortKey,
 ) -> Result<SendableRecordBatchStream> {
     // Build logical plan for compaction
     let ctx = executor.new_context(ExecutorType::Reorg);
     let logical_plan = ReorgPlanner::new(ctx.child_ctx("ReorgPlanner"))
+        .with_dedup_policy(dedup_policy)
         .compact_plan(
             table_name.into(),
             data.schema(),
@@ -202,10 +215,15 @@ mod tests {
 
         // compact
         let exc = Executor::new_testing();
-        let CompactedStream { stream, .. } =
-            compact_persisting_batch(&exc, Some(SortKey::empty()), "test_table".into(), batch)
-                .await
-                .unwrap();
+        let CompactedStream { stream, .. } = compact_persisting_batch(
+            &exc,
+            Some(SortKey::empty()),
+            "test_table".into(),
+            DeduplicationPolicy::Upsert,
+            batch,
+        )
+        .await
+        .unwrap();
 
         let output_batches = datafusion::physical_plan::common::collect(stream)
             .await
@@ -243,9 +261,15 @@ mod tests {
             stream,
             data_sort_key,
             catalog_sort_key_update,
-        } = compact_persisting_batch(&exc, Some(SortKey::empty()), "test_table".into(), batch)
-            .await
-            .unwrap();
+        } = compact_persisting_batch(
+            &exc,
+            Some(SortKey::empty()),
+            "test_table".into(),
+            DeduplicationPolicy::Upsert,
+            batch,
+        )
+        .await
+        .unwrap();
 
         let output_batches = datafusion::physical_plan::common::collect(stream)
             .await
@@ -293,9 +317,15 @@ mod tests {
             stream,
             data_sort_key,
             catalog_sort_key_update,
-        } = compact_persisting_batch(&exc, Some(SortKey::empty()), "test_table".into(), batch)
-            .await
-            .unwrap();
+        } = compact_persisting_batch(
+            &exc,
+            Some(SortKey::empty()),
+            "test_table".into(),
+            DeduplicationPolicy::Upsert,
+            batch,
+        )
+        .await
+        .unwrap();
 
         let output_batches = datafusion::physical_plan::common::collect(stream)
             .await
@@ -352,6 +382,7 @@ mod tests {
             &exc,
             Some(SortKey::from_columns(["tag3", "tag1", "time"])),
             "test_table".into(),
+            DeduplicationPolicy::Upsert,
             batch,
         )
         .await
@@ -412,6 +443,7 @@ mod tests {
             &exc,
             Some(SortKey::from_columns(["tag3", "time"])),
             "test_table".into(),
+            DeduplicationPolicy::Upsert,
             batch,
         )
         .await
@@ -475,6 +507,7 @@ mod tests {
             &exc,
             Some(SortKey::from_columns(["tag3", "tag1", "tag4", "time"])),
             "test_table".into(),
+            DeduplicationPolicy::Upsert,
             batch,
         )
         .await
@@ -530,9 +563,15 @@ mod tests {
 
         // compact
         let exc = Executor::new_testing();
-        let stream = compact(&exc, "test_table".into(), Arc::new(batch), sort_key)
-            .await
-            .unwrap();
+        let stream = compact(
+            &exc,
+            "test_table".into(),
+            DeduplicationPolicy::Upsert,
+            Arc::new(batch),
+            sort_key,
+        )
+        .await
+        .unwrap();
         let output_batches = datafusion::physical_plan::common::collect(stream)
             .await
             .unwrap();
@@ -571,9 +610,15 @@ mod tests {
 
         // compact
         let exc = Executor::new_testing();
-        let stream = compact(&exc, "test_table".into(), Arc::new(batch), sort_key)
-            .await
-            .unwrap();
+        let stream = compact(
+            &exc,
+            "test_table".into(),
+            DeduplicationPolicy::Upsert,
+            Arc::new(batch),
+            sort_key,
+        )
+        .await
+        .unwrap();
         let output_batches = datafusion::physical_plan::common::collect(stream)
             .await
             .unwrap();
@@ -617,9 +662,15 @@ mod tests {
 
         // compact
         let exc = Executor::new_testing();
-        let stream = compact(&exc, "test_table".into(), Arc::new(batch), sort_key)
-            .await
-            .unwrap();
+        let stream = compact(
+            &exc,
+            "test_table".into(),
+            DeduplicationPolicy::Upsert,
+            Arc::new(batch),
+            sort_key,
+        )
+        .await
+        .unwrap();
         let output_batches = datafusion::physical_plan::common::collect(stream)
             .await
             .unwrap();
@@ -663,9 +714,15 @@ mod tests {
 
         // compact
         let exc = Executor::new_testing();
-        let stream = compact(&exc, "test_table".into(), Arc::new(batch), sort_key)
-            .await
-            .unwrap();
+        let stream = compact(
+            &exc,
+            "test_table".into(),
+            DeduplicationPolicy::Upsert,
+            Arc::new(batch),
+            sort_key,
+        )
+        .await
+        .unwrap();
         let output_batches = datafusion::physical_plan::common::collect(stream)
             .await
             .unwrap();
@@ -713,9 +770,15 @@ mod tests {
 
         // compact
         let exc = Executor::new_testing();
-        let stream = compact(&exc, "test_table".into(), Arc::new(batch), sort_key)
-            .await
-            .unwrap();
+        let stream = compact(
+            &exc,
+            "test_table".into(),
+            DeduplicationPolicy::Upsert,
+            Arc::new(batch),
+            sort_key,
+        )
+        .await
+        .unwrap();
         let output_batches = datafusion::physical_plan::common::collect(stream)
             .await
             .unwrap();
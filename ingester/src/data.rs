@@ -436,14 +436,36 @@ impl Persister for IngesterData {
         // if it is not yet available.
         let table_name = table_name.get().await;
 
+        // Duplicate rows are resolved according to the table's policy in the
+        // catalog.
+        let dedup_policy = Backoff::new(&self.backoff_config)
+            .retry_all_errors("get table dedup policy", || async {
+                self.catalog
+                    .repositories()
+                    .await
+                    .tables()
+                    .get_by_id(table_id)
+                    .await
+            })
+            .await
+            .expect("retry forever")
+            .map(|t| t.dedup_policy)
+            .unwrap_or_default();
+
         // Prepare the plan for CPU intensive work of compaction, de-duplication and sorting
         let CompactedStream {
             stream: record_stream,
             catalog_sort_key_update,
             data_sort_key,
-        } = compact_persisting_batch(&self.exec, sort_key.clone(), table_name.clone(), batch)
-            .await
-            .expect("unable to compact persisting batch");
+        } = compact_persisting_batch(
+            &self.exec,
+            sort_key.clone(),
+            table_name.clone(),
+            dedup_policy,
+            batch,
+        )
+        .await
+        .expect("unable to compact persisting batch");
 
         // Generate a UUID to uniquely identify this parquet file in object
         // storage.
@@ -326,6 +326,7 @@ impl Stream for GetStream {
                         // These fields are only used in ingester2.
                         ingester_uuid: String::new(),
                         completed_persistence_count: 0,
+                        buffer_version: 0,
                     };
                     prost::Message::encode(&app_metadata, &mut bytes)
                         .context(SerializationSnafu)?;
@@ -418,6 +419,7 @@ mod tests {
                         // These fields are only used in ingester2.
                         ingester_uuid: String::new(),
                         completed_persistence_count: 0,
+                        buffer_version: 0,
                     },
                 }),
                 Ok(DecodedFlightData {
@@ -462,6 +464,7 @@ mod tests {
                         // These fields are only used in ingester2.
                         ingester_uuid: String::new(),
                         completed_persistence_count: 0,
+                        buffer_version: 0,
                     },
                 }),
                 Err(tonic::Code::Internal),
@@ -9,6 +9,7 @@ use data_types::{NamespaceId, ShardId, TableId};
 use dml::DmlOperation;
 use metric::U64Counter;
 use observability_deps::tracing::warn;
+use predicate::Predicate;
 use trace::span::Span;
 
 use super::{
@@ -205,6 +206,7 @@ where
         namespace_id: NamespaceId,
         table_id: TableId,
         columns: Vec<String>,
+        predicate: Predicate,
         span: Option<Span>,
     ) -> Result<Self::Response, QueryError> {
         assert_eq!(
@@ -221,7 +223,7 @@ where
         // a tracing delegate to emit a child span.
         Ok(QueryResponse::new(
             QueryExecTracing::new(inner, "table")
-                .query_exec(namespace_id, table_id, columns, span)
+                .query_exec(namespace_id, table_id, columns, predicate, span)
                 .await?,
         ))
     }
@@ -93,6 +93,11 @@ pub struct PartitionData {
     /// [`PartitionData`].
     completed_persistence_count: u64,
 
+    /// Incremented whenever the data returned by queries against this
+    /// [`PartitionData`] changes, i.e. when a write is buffered or data is
+    /// persisted.
+    buffer_version: u64,
+
     transition_shard_id: ShardId,
 }
 
@@ -121,6 +126,7 @@ impl PartitionData {
             persisting: VecDeque::with_capacity(1),
             started_persistence_count: BatchIdent::default(),
             completed_persistence_count: 0,
+            buffer_version: 0,
             transition_shard_id,
         }
     }
@@ -131,7 +137,9 @@ impl PartitionData {
         mb: MutableBatch,
         sequence_number: SequenceNumber,
     ) -> Result<(), mutable_batch::Error> {
-        // Buffer the write.
+        // Buffer the write, changing the buffered data even if it fails
+        // part way through.
+        self.buffer_version += 1;
         self.buffer.buffer_write(mb, sequence_number)?;
 
         trace!(
@@ -150,6 +158,36 @@ impl PartitionData {
         self.buffer.persist_cost_estimate()
     }
 
+    /// Return an estimate of the number of bytes of memory held by this
+    /// partition - both the buffered data, and the data marked as persisting
+    /// that has not yet been released by a call to
+    /// [`Self::mark_persisted()`].
+    pub(crate) fn memory_usage(&self) -> usize {
+        self.persist_cost_estimate() + self.persisting_memory_usage()
+    }
+
+    /// Return an estimate of the number of bytes of memory held by the
+    /// persisting data of this partition.
+    ///
+    /// This memory is released once the outstanding persist operations
+    /// complete.
+    pub(crate) fn persisting_memory_usage(&self) -> usize {
+        self.persisting
+            .iter()
+            .flat_map(|(_, b)| b.get_query_data())
+            .map(|b| b.get_array_memory_size())
+            .sum()
+    }
+
+    /// Return the set of [`SequenceNumber`] of the writes buffered in this
+    /// partition that have not been marked as persisting.
+    ///
+    /// Intersecting this set with the writes of a WAL segment identifies the
+    /// partitions that must persist before the segment can be deleted.
+    pub(crate) fn buffered_sequence_numbers(&self) -> &SequenceNumberSet {
+        self.buffer.sequence_number_set()
+    }
+
     /// Return all data for this partition, ordered by the calls to
     /// [`PartitionData::buffer_write()`].
     pub(crate) fn get_query_data(&mut self) -> Option<QueryAdaptor> {
@@ -261,6 +299,7 @@ impl PartitionData {
         assert_eq!(old_ident, batch.batch_ident());
 
         self.completed_persistence_count += 1;
+        self.buffer_version += 1;
 
         debug!(
             batch_ident = %old_ident,
@@ -287,6 +326,12 @@ impl PartitionData {
         self.completed_persistence_count
     }
 
+    /// Return the version of the data buffered in this [`PartitionData`]
+    /// instance, which changes whenever the data returned by queries does.
+    pub(crate) fn buffer_version(&self) -> u64 {
+        self.buffer_version
+    }
+
     /// Return the name of the table this [`PartitionData`] is buffering writes
     /// for.
     pub(crate) fn table_name(&self) -> &Arc<DeferredLoad<TableName>> {
@@ -479,6 +524,7 @@ mod tests {
         // Ensure the batch ident hasn't been increased yet.
         assert_eq!(p.started_persistence_count.get(), 0);
         assert_eq!(p.completed_persistence_count, 0);
+        assert_eq!(p.buffer_version(), 1);
 
         // Begin persisting the partition.
         let persisting_data = p.mark_persisting().expect("must contain existing data");
@@ -506,6 +552,8 @@ mod tests {
         // batch ident.
         assert_eq!(p.started_persistence_count.get(), 1);
         assert_eq!(p.completed_persistence_count, 0);
+        // The queryable data did not change.
+        assert_eq!(p.buffer_version(), 1);
         // And the batch is correctly identified
         assert_eq!(persisting_data.batch_ident().get(), 1);
 
@@ -547,6 +595,7 @@ mod tests {
         // completed count is increased.
         assert_eq!(p.started_persistence_count.get(), 1);
         assert_eq!(p.completed_persistence_count, 1);
+        assert_eq!(p.buffer_version(), 3);
 
         // Querying the buffer should now return only the second write.
         {
@@ -1156,6 +1205,46 @@ mod tests {
         assert!(p.mark_persisting().is_none());
     }
 
+    // Ensure the memory usage estimate covers both buffered and persisting
+    // data, and is released once the persist completes.
+    #[tokio::test]
+    async fn test_memory_usage() {
+        let mut p = PartitionData::new(
+            PARTITION_ID,
+            PARTITION_KEY.clone(),
+            NamespaceId::new(3),
+            Arc::new(DeferredLoad::new(Duration::from_secs(1), async {
+                NAMESPACE_NAME.clone()
+            })),
+            TableId::new(4),
+            Arc::new(DeferredLoad::new(Duration::from_secs(1), async {
+                TABLE_NAME.clone()
+            })),
+            SortKeyState::Provided(None),
+            TRANSITION_SHARD_ID,
+        );
+        assert_eq!(p.memory_usage(), 0);
+
+        let mb = lp_to_mutable_batch(r#"bananas,city=London people=2,pigeons="millions" 10"#).1;
+        p.buffer_write(mb, SequenceNumber::new(1))
+            .expect("write should succeed");
+
+        let buffered = p.memory_usage();
+        assert!(buffered > 0);
+        assert_eq!(buffered, p.persist_cost_estimate());
+        assert_eq!(p.persisting_memory_usage(), 0);
+
+        // Persisting data is still held in memory.
+        let data = p.mark_persisting().unwrap();
+        assert_eq!(p.persist_cost_estimate(), 0);
+        assert!(p.persisting_memory_usage() > 0);
+        assert_eq!(p.memory_usage(), p.persisting_memory_usage());
+
+        // And released once persisted.
+        p.mark_persisted(data);
+        assert_eq!(p.memory_usage(), 0);
+    }
+
     // Ensure an empty PartitionData does not panic due to constructing an empty
     // QueryAdaptor.
     #[tokio::test]
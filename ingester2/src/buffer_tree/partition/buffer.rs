@@ -1,7 +1,7 @@
 use std::sync::Arc;
 
 use arrow::record_batch::RecordBatch;
-use data_types::SequenceNumber;
+use data_types::{sequence_number_set::SequenceNumberSet, SequenceNumber};
 use mutable_batch::MutableBatch;
 
 mod always_some;
@@ -61,6 +61,13 @@ impl DataBuffer {
         }
     }
 
+    /// Return the set of [`SequenceNumber`] buffered in this [`DataBuffer`].
+    pub(crate) fn sequence_number_set(&self) -> &SequenceNumberSet {
+        match self.0.get() {
+            FsmState::Buffering(b) => b.sequence_number_set(),
+        }
+    }
+
     /// Return all data for this buffer, ordered by the [`SequenceNumber`] from
     /// which it was buffered with.
     pub(crate) fn get_query_data(&mut self) -> Vec<Arc<RecordBatch>> {
@@ -1,5 +1,6 @@
 use std::sync::Arc;
 
+use data_types::SequenceNumber;
 use parking_lot::Mutex;
 
 use crate::buffer_tree::partition::PartitionData;
@@ -11,11 +12,18 @@ pub(crate) struct MockPostWriteObserver {
     saw: Mutex<Vec<Arc<Mutex<PartitionData>>>>,
 }
 
+impl MockPostWriteObserver {
+    pub(crate) fn calls(&self) -> Vec<Arc<Mutex<PartitionData>>> {
+        self.saw.lock().clone()
+    }
+}
+
 impl PostWriteObserver for MockPostWriteObserver {
     fn observe(
         &self,
         partition: Arc<Mutex<PartitionData>>,
         _guard: parking_lot::MutexGuard<'_, PartitionData>,
+        _sequence_number: SequenceNumber,
     ) {
         self.saw.lock().push(partition);
     }
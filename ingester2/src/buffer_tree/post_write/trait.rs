@@ -1,9 +1,17 @@
 use std::{fmt::Debug, sync::Arc};
 
+use data_types::SequenceNumber;
 use parking_lot::{Mutex, MutexGuard};
 
 use crate::buffer_tree::partition::PartitionData;
 
 pub(crate) trait PostWriteObserver: Send + Sync + Debug {
-    fn observe(&self, partition: Arc<Mutex<PartitionData>>, guard: MutexGuard<'_, PartitionData>);
+    /// Observe `partition` after the write identified by `sequence_number` was
+    /// successfully buffered into it, while its lock (`guard`) is still held.
+    fn observe(
+        &self,
+        partition: Arc<Mutex<PartitionData>>,
+        guard: MutexGuard<'_, PartitionData>,
+        sequence_number: SequenceNumber,
+    );
 }
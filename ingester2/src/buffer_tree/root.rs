@@ -5,6 +5,7 @@ use data_types::{NamespaceId, ShardId, TableId};
 use dml::DmlOperation;
 use metric::U64Counter;
 use parking_lot::Mutex;
+use predicate::Predicate;
 use trace::span::Span;
 
 use super::{
@@ -200,6 +201,7 @@ where
         namespace_id: NamespaceId,
         table_id: TableId,
         columns: Vec<String>,
+        predicate: Predicate,
         span: Option<Span>,
     ) -> Result<Self::Response, QueryError> {
         // Extract the namespace if it exists.
@@ -210,7 +212,7 @@ where
         // Delegate query execution to the namespace, wrapping the execution in
         // a tracing delegate to emit a child span.
         QueryExecTracing::new(inner, "namespace")
-            .query_exec(namespace_id, table_id, columns, span)
+            .query_exec(namespace_id, table_id, columns, predicate, span)
             .await
     }
 }
@@ -230,7 +232,10 @@ mod tests {
 
     use assert_matches::assert_matches;
     use data_types::{PartitionId, PartitionKey};
-    use datafusion::{assert_batches_eq, assert_batches_sorted_eq};
+    use datafusion::{
+        assert_batches_eq, assert_batches_sorted_eq,
+        prelude::{col, lit},
+    };
     use futures::{StreamExt, TryStreamExt};
     use metric::{Attributes, Metric};
 
@@ -337,6 +342,21 @@ mod tests {
             partitions = [$($partition:expr), +], // The set of PartitionData for the mock partition provider
             writes = [$($write:expr), *],         // The set of DmlWrite to apply()
             want = $want:expr                     // The expected results of querying NAMESPACE_ID and TABLE_ID
+        ) => {
+            test_write_query!(
+                $name,
+                partitions = [$($partition), +],
+                writes = [$($write), *],
+                predicate = Predicate::default(),
+                want = $want
+            );
+        };
+        (
+            $name:ident,
+            partitions = [$($partition:expr), +], // The set of PartitionData for the mock partition provider
+            writes = [$($write:expr), *],         // The set of DmlWrite to apply()
+            predicate = $predicate:expr,          // The predicate of the query
+            want = $want:expr                     // The expected results of querying NAMESPACE_ID and TABLE_ID
         ) => {
             paste::paste! {
                 #[tokio::test]
@@ -368,7 +388,7 @@ mod tests {
 
                     // Execute the query against NAMESPACE_ID and TABLE_ID
                     let batches = buf
-                        .query_exec(NAMESPACE_ID, TABLE_ID, vec![], None)
+                        .query_exec(NAMESPACE_ID, TABLE_ID, vec![], $predicate, None)
                         .await
                         .expect("query should succeed")
                         .into_record_batches()
@@ -420,6 +440,122 @@ mod tests {
         ]
     );
 
+    // A query with a predicate only returns the matching rows, and skips
+    // partitions whose buffered time range does not overlap the query.
+    test_write_query!(
+        predicate,
+        partitions = [
+            PartitionData::new(
+                PartitionId::new(0),
+                PartitionKey::from("p1"),
+                NAMESPACE_ID,
+                Arc::new(DeferredLoad::new(Duration::from_secs(1), async {
+                    NamespaceName::from(NAMESPACE_NAME)
+                })),
+                TABLE_ID,
+                Arc::new(DeferredLoad::new(Duration::from_secs(1), async {
+                    TableName::from(TABLE_NAME)
+                })),
+                SortKeyState::Provided(None),
+                TRANSITION_SHARD_ID,
+            ),
+            PartitionData::new(
+                PartitionId::new(1),
+                PartitionKey::from("p2"),
+                NAMESPACE_ID,
+                Arc::new(DeferredLoad::new(Duration::from_secs(1), async {
+                    NamespaceName::from(NAMESPACE_NAME)
+                })),
+                TABLE_ID,
+                Arc::new(DeferredLoad::new(Duration::from_secs(1), async {
+                    TableName::from(TABLE_NAME)
+                })),
+                SortKeyState::Provided(None),
+                TRANSITION_SHARD_ID,
+            )
+        ],
+        writes = [
+            make_write_op(
+                &PartitionKey::from("p1"),
+                NAMESPACE_ID,
+                TABLE_NAME,
+                TABLE_ID,
+                0,
+                "bananas,region=Madrid temp=35 4242424242\n\
+                bananas,region=Asturias temp=25 4242424243\n\
+                bananas,region=Madrid temp=36 4242424244\n\
+                bananas,region=Madrid temp=37 9999999999",
+            ),
+            make_write_op(
+                &PartitionKey::from("p2"),
+                NAMESPACE_ID,
+                TABLE_NAME,
+                TABLE_ID,
+                1,
+                r#"bananas,region=Madrid temp=20 1"#,
+            )
+        ],
+        predicate = Predicate::default()
+            .with_range(4242424242, 5000000000)
+            .with_expr(col("region").eq(lit("Madrid"))),
+        want = [
+            "+--------+------+-------------------------------+",
+            "| region | temp | time                          |",
+            "+--------+------+-------------------------------+",
+            "| Madrid | 35   | 1970-01-01T00:00:04.242424242 |",
+            "| Madrid | 36   | 1970-01-01T00:00:04.242424244 |",
+            "+--------+------+-------------------------------+",
+        ]
+    );
+
+    // Field predicates are not applied to the buffered data, as they could
+    // remove the newest row for a primary key and let an older one through
+    // de-duplication.
+    test_write_query!(
+        predicate_not_pushed_through_dedup,
+        partitions = [PartitionData::new(
+            PartitionId::new(0),
+            PartitionKey::from("p1"),
+            NAMESPACE_ID,
+            Arc::new(DeferredLoad::new(Duration::from_secs(1), async {
+                NamespaceName::from(NAMESPACE_NAME)
+            })),
+            TABLE_ID,
+            Arc::new(DeferredLoad::new(Duration::from_secs(1), async {
+                TableName::from(TABLE_NAME)
+            })),
+            SortKeyState::Provided(None),
+            TRANSITION_SHARD_ID,
+        )],
+        writes = [
+            make_write_op(
+                &PartitionKey::from("p1"),
+                NAMESPACE_ID,
+                TABLE_NAME,
+                TABLE_ID,
+                0,
+                r#"bananas,region=Madrid temp=35 4242424242"#,
+            ),
+            make_write_op(
+                &PartitionKey::from("p1"),
+                NAMESPACE_ID,
+                TABLE_NAME,
+                TABLE_ID,
+                1,
+                r#"bananas,region=Madrid temp=20 4242424242"#,
+            )
+        ],
+        predicate = Predicate::default().with_expr(col("temp").gt(lit(30.0))),
+        want = [
+            "+--------+------+-------------------------------+",
+            "| region | temp | time                          |",
+            "+--------+------+-------------------------------+",
+            "| Madrid | 20   | 1970-01-01T00:00:04.242424242 |",
+            "| Madrid | 35   | 1970-01-01T00:00:04.242424242 |",
+            "+--------+------+-------------------------------+",
+        ]
+    );
+
     // A query that ensures the data across multiple partitions within a single
     // table are returned.
     test_write_query!(
@@ -898,7 +1034,7 @@ mod tests {
 
         // Query the empty tree
         let err = buf
-            .query_exec(NAMESPACE_ID, TABLE_ID, vec![], None)
+            .query_exec(NAMESPACE_ID, TABLE_ID, vec![], Predicate::default(), None)
             .await
             .expect_err("query should fail");
         assert_matches!(err, QueryError::NamespaceNotFound(ns) => {
@@ -919,7 +1055,13 @@ mod tests {
 
         // Ensure an unknown table errors
         let err = buf
-            .query_exec(NAMESPACE_ID, TableId::new(1234), vec![], None)
+            .query_exec(
+                NAMESPACE_ID,
+                TableId::new(1234),
+                vec![],
+                Predicate::default(),
+                None,
+            )
             .await
             .expect_err("query should fail");
         assert_matches!(err, QueryError::TableNotFound(ns, t) => {
@@ -928,7 +1070,7 @@ mod tests {
         });
 
         // Ensure a valid namespace / table does not error
-        buf.query_exec(NAMESPACE_ID, TABLE_ID, vec![], None)
+        buf.query_exec(NAMESPACE_ID, TABLE_ID, vec![], Predicate::default(), None)
             .await
             .expect("namespace / table should exist");
     }
@@ -1010,7 +1152,7 @@ mod tests {
         // Execute a query of the buffer tree, generating the result stream, but
         // DO NOT consume it.
         let stream = buf
-            .query_exec(NAMESPACE_ID, TABLE_ID, vec![], None)
+            .query_exec(NAMESPACE_ID, TABLE_ID, vec![], Predicate::default(), None)
             .await
             .expect("query should succeed")
             .into_partition_stream();
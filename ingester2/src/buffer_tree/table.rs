@@ -9,6 +9,7 @@ use data_types::{NamespaceId, PartitionKey, SequenceNumber, ShardId, TableId};
 use datafusion_util::MemoryStream;
 use mutable_batch::MutableBatch;
 use parking_lot::Mutex;
+use predicate::Predicate;
 use schema::Projection;
 use trace::span::{Span, SpanRecorder};
 
@@ -196,7 +197,7 @@ where
 
         // If successful, allow the observer to inspect the partition.
         self.post_write_observer
-            .observe(Arc::clone(&partition_data), p);
+            .observe(Arc::clone(&partition_data), p, sequence_number);
 
         Ok(())
     }
@@ -214,6 +215,7 @@ where
         namespace_id: NamespaceId,
         table_id: TableId,
         columns: Vec<String>,
+        predicate: Predicate,
         span: Option<Span>,
     ) -> Result<Self::Response, QueryError> {
         assert_eq!(self.table_id, table_id, "buffer tree index inconsistency");
@@ -226,15 +228,27 @@ where
         let partitions = self.partitions().into_iter().map(move |p| {
             let mut span = SpanRecorder::new(span.clone().map(|s| s.child("partition read")));
 
-            let (id, completed_persistence_count, data) = {
+            let (id, completed_persistence_count, buffer_version, data) = {
                 let mut p = p.lock();
                 (
                     p.partition_id(),
                     p.completed_persistence_count(),
+                    p.buffer_version(),
                     p.get_query_data(),
                 )
             };
 
+            // Apply the predicate to the buffered data, skipping the
+            // partition data entirely if no row can match.
+            //
+            // The buffered data is not de-duplicated yet, so only the parts of
+            // the predicate that cannot remove a newer row while keeping an
+            // older row with the same primary key are applied.
+            let data = data.and_then(|data| {
+                let predicate = predicate.clone().push_through_dedup(data.schema());
+                data.filter(&predicate)
+            });
+
             let ret = match data {
                 Some(data) => {
                     assert_eq!(id, data.partition_id());
@@ -250,9 +264,16 @@ where
                     let data = Box::pin(MemoryStream::new(
                         data.project_selection(selection).into_iter().collect(),
                     ));
-                    PartitionResponse::new(Some(data), id, completed_persistence_count)
+                    PartitionResponse::new(
+                        Some(data),
+                        id,
+                        completed_persistence_count,
+                        buffer_version,
+                    )
+                }
+                None => {
+                    PartitionResponse::new(None, id, completed_persistence_count, buffer_version)
                 }
-                None => PartitionResponse::new(None, id, completed_persistence_count),
             };
 
             span.ok("read partition data");
@@ -14,6 +14,9 @@ pub(crate) enum IngestStateError {
 
     #[error("ingester is shutting down")]
     GracefulStop = 1 << 1,
+
+    #[error("ingester overloaded - buffered data exceeds memory limit")]
+    MemoryLimit = 1 << 2,
 }
 
 impl IngestStateError {
@@ -128,6 +131,7 @@ impl IngestState {
     ///
     ///   1. [`IngestStateError::GracefulStop`]
     ///   2. [`IngestStateError::PersistSaturated`].
+    ///   3. [`IngestStateError::MemoryLimit`].
     ///
     pub(crate) fn read(&self) -> Result<(), IngestStateError> {
         let current = self.state.load(Ordering::Relaxed);
@@ -157,6 +161,10 @@ fn to_err(state: usize) -> Result<(), IngestStateError> {
         return Err(IngestStateError::PersistSaturated);
     }
 
+    if state & IngestStateError::MemoryLimit.to_bits() != 0 {
+        return Err(IngestStateError::MemoryLimit);
+    }
+
     unreachable!()
 }
 
@@ -174,10 +182,21 @@ mod tests {
         assert!(IngestStateError::GracefulStop.to_bits() < usize::BITS as usize);
         assert_eq!(IngestStateError::PersistSaturated.to_bits().count_ones(), 1);
 
+        assert!(IngestStateError::MemoryLimit.to_bits() < usize::BITS as usize);
+        assert_eq!(IngestStateError::MemoryLimit.to_bits().count_ones(), 1);
+
         assert_ne!(
             IngestStateError::PersistSaturated.to_bits(),
             IngestStateError::GracefulStop.to_bits()
         );
+        assert_ne!(
+            IngestStateError::MemoryLimit.to_bits(),
+            IngestStateError::PersistSaturated.to_bits()
+        );
+        assert_ne!(
+            IngestStateError::MemoryLimit.to_bits(),
+            IngestStateError::GracefulStop.to_bits()
+        );
     }
 
     #[test]
@@ -224,6 +243,12 @@ mod tests {
         // Un-setting the shutdown state shows the persist state.
         state.unset(IngestStateError::GracefulStop);
         assert_matches!(state.read(), Err(IngestStateError::PersistSaturated));
+
+        // The memory limit state has the lowest precedence.
+        state.set(IngestStateError::MemoryLimit);
+        assert_matches!(state.read(), Err(IngestStateError::PersistSaturated));
+        state.unset(IngestStateError::PersistSaturated);
+        assert_matches!(state.read(), Err(IngestStateError::MemoryLimit));
     }
 
     #[test]
@@ -35,10 +35,17 @@ use crate::{
     },
     ingest_state::IngestState,
     ingester_id::IngesterId,
-    persist::{handle::PersistHandle, hot_partitions::HotPartitionPersister},
+    persist::{
+        handle::PersistHandle,
+        hot_partitions::HotPartitionPersister,
+        memory_pressure::{MemoryLimits, MemoryPressureMonitor},
+    },
     server::grpc::GrpcDelegate,
     timestamp_oracle::TimestampOracle,
-    wal::{rotate_task::periodic_rotation, wal_sink::WalSink},
+    wal::{
+        reference_observer::WalReferenceObserver, reference_tracker::WalReferenceHandle,
+        rotate_task::periodic_rotation, wal_sink::WalSink,
+    },
     TRANSITION_SHARD_INDEX,
 };
 
@@ -95,6 +102,18 @@ pub struct IngesterGuard<T> {
     /// Aborted on drop.
     rotation_task: tokio::task::JoinHandle<()>,
 
+    /// The handle of the task deleting WAL segments once all their writes are
+    /// persisted.
+    ///
+    /// Aborted on drop.
+    wal_reference_task: tokio::task::JoinHandle<()>,
+
+    /// The handle of the task enforcing the buffer memory limits, if any are
+    /// configured.
+    ///
+    /// Aborted on drop.
+    memory_pressure_task: Option<tokio::task::JoinHandle<()>>,
+
     /// The task handle executing the graceful shutdown once triggered.
     graceful_shutdown_handler: tokio::task::JoinHandle<()>,
     shutdown_complete: Shared<oneshot::Receiver<()>>,
@@ -121,6 +140,10 @@ where
 impl<T> Drop for IngesterGuard<T> {
     fn drop(&mut self) {
         self.rotation_task.abort();
+        self.wal_reference_task.abort();
+        if let Some(h) = &self.memory_pressure_task {
+            h.abort();
+        }
         self.graceful_shutdown_handler.abort();
     }
 }
@@ -155,7 +178,19 @@ pub enum InitError {
 ///
 /// These files are read and replayed fully before this function returns.
 ///
-/// Any error during replay is fatal.
+/// Any error during replay is fatal, unless `wal_replay_skip_corrupt_tail` is
+/// set, in which case an unreadable entry (such as a partially written entry
+/// left by a crash) and everything after it in the same segment is skipped and
+/// the amount of skipped data is logged.
+///
+/// ## Buffer Memory Limits
+///
+/// The memory used by buffered (and persisting) data across all partitions is
+/// periodically estimated. Once it exceeds `buffer_memory_high_watermark`
+/// bytes, the largest partitions are persisted until the buffer is projected to
+/// fall below the watermark. While it exceeds `buffer_memory_hard_limit` bytes,
+/// writes are rejected with a retryable error. Either limit is disabled when
+/// [`None`].
 ///
 /// ## Deferred Loading for Persist Operations
 ///
@@ -201,10 +236,13 @@ pub async fn new<F>(
     persist_background_fetch_time: Duration,
     wal_directory: PathBuf,
     wal_rotation_period: Duration,
+    wal_replay_skip_corrupt_tail: bool,
     persist_executor: Arc<Executor>,
     persist_workers: usize,
     persist_queue_depth: usize,
     persist_hot_partition_cost: usize,
+    buffer_memory_high_watermark: Option<usize>,
+    buffer_memory_hard_limit: Option<usize>,
     object_store: ParquetStorage,
     shutdown: F,
 ) -> Result<IngesterGuard<impl IngesterRpcInterface>, InitError>
@@ -276,6 +314,10 @@ where
     // write path.
     let ingest_state = Arc::new(IngestState::default());
 
+    // Initialise the WAL segment reference tracker, which is notified of the
+    // writes released by each completed persist job.
+    let (wal_reference_handle, wal_reference_actor) = WalReferenceHandle::new();
+
     // Spawn the persist workers to compact partition data, convert it into
     // Parquet files, and upload them to object storage.
     let persist_handle = PersistHandle::new(
@@ -285,6 +327,7 @@ where
         persist_executor,
         object_store,
         Arc::clone(&catalog),
+        Arc::new(wal_reference_handle.clone()),
         &metrics,
     );
     let persist_handle = Arc::new(persist_handle);
@@ -298,8 +341,13 @@ where
     // runs, such as if the configuration of the ingester was changed to persist
     // smaller partitions in-between executions because it was OOMing during WAL
     // replay (and the configuration was changed to mitigate it).
-    let hot_partition_persister =
-        HotPartitionPersister::new(Arc::clone(&persist_handle), persist_hot_partition_cost);
+    //
+    // Each partition a write is buffered into references the WAL segment
+    // containing it until that partition is persisted.
+    let hot_partition_persister = WalReferenceObserver::new(
+        HotPartitionPersister::new(Arc::clone(&persist_handle), persist_hot_partition_cost),
+        wal_reference_handle.clone(),
+    );
 
     let buffer = Arc::new(BufferTree::new(
         namespace_name_provider,
@@ -310,16 +358,50 @@ where
         transition_shard.id,
     ));
 
+    // Spawn a background task to enforce the buffer memory limits, if any.
+    //
+    // As with hot partition persistence, this is enabled before replaying the
+    // WAL so that replaying a large WAL does not exhaust the available memory.
+    let memory_limits = MemoryLimits {
+        high_watermark_bytes: buffer_memory_high_watermark,
+        hard_limit_bytes: buffer_memory_hard_limit,
+    };
+    let memory_pressure_task = memory_limits.is_enabled().then(|| {
+        tokio::spawn(
+            MemoryPressureMonitor::new(
+                Arc::clone(&buffer),
+                Arc::clone(&persist_handle),
+                Arc::clone(&ingest_state),
+                memory_limits,
+                &metrics,
+            )
+            .run(),
+        )
+    });
+
     // Initialise the WAL
     let wal = Wal::new(wal_directory).await.map_err(InitError::WalInit)?;
 
     // Replay the WAL log files, if any.
-    let max_sequence_number = wal_replay::replay(&wal, &buffer, Arc::clone(&persist_handle))
-        .await
-        .map_err(|e| InitError::WalReplay(e.into()))?;
+    let max_sequence_number = wal_replay::replay(
+        &wal,
+        &buffer,
+        Arc::clone(&persist_handle),
+        wal_replay_skip_corrupt_tail,
+    )
+    .await
+    .map_err(|e| InitError::WalReplay(e.into()))?;
+
+    // Spawn a background task to delete WAL segments once all the writes
+    // within them are persisted.
+    let wal_reference_task = tokio::spawn(wal_reference_actor.run(Arc::clone(&wal)));
 
     // Build the chain of DmlSink that forms the write path.
-    let write_path = WalSink::new(Arc::clone(&buffer), Arc::clone(&wal));
+    let write_path = WalSink::new(
+        Arc::clone(&buffer),
+        Arc::clone(&wal),
+        wal_reference_handle.clone(),
+    );
 
     // Spawn a background thread to periodically rotate the WAL segment file.
     let rotation_task = tokio::spawn(periodic_rotation(
@@ -327,6 +409,7 @@ where
         wal_rotation_period,
         Arc::clone(&buffer),
         Arc::clone(&persist_handle),
+        wal_reference_handle,
     ));
 
     // Restore the highest sequence number from the WAL files, and default to 0
@@ -364,6 +447,8 @@ where
             persist_handle,
         ),
         rotation_task,
+        wal_reference_task,
+        memory_pressure_task,
         graceful_shutdown_handler: shutdown_task,
         shutdown_complete: shutdown_rx.shared(),
     })
@@ -43,10 +43,16 @@ pub enum WalReplayError {
 
 /// Replay all the entries in `wal` to `sink`, returning the maximum observed
 /// [`SequenceNumber`].
+///
+/// If `skip_corrupt_tail` is true, an entry that cannot be read (for example,
+/// because of a checksum mismatch or a truncated write) ends the replay of its
+/// segment instead of failing the replay - all entries before it are applied,
+/// and the number of bytes skipped is logged.
 pub async fn replay<T, P>(
     wal: &Wal,
     sink: &T,
     persist: P,
+    skip_corrupt_tail: bool,
 ) -> Result<Option<SequenceNumber>, WalReplayError>
 where
     T: DmlSink + PartitionIter,
@@ -70,6 +76,7 @@ where
     // Applying writes to the buffer can only happen monotonically and this is
     // enforced within the buffer.
     let mut max_sequence = None;
+    let mut skipped_bytes = 0;
     for (index, file) in files.into_iter().enumerate() {
         // Map 0-based iter index to 1 based file count
         let file_number = index + 1;
@@ -90,7 +97,10 @@ where
         );
 
         // Replay this segment file
-        match replay_file(reader, sink).await? {
+        let (file_max_sequence, file_skipped_bytes) =
+            replay_file(reader, &file, sink, skip_corrupt_tail).await?;
+        skipped_bytes += file_skipped_bytes;
+        match file_max_sequence {
             v @ Some(_) => max_sequence = max_sequence.max(v),
             None => {
                 // This file was empty and should be deleted.
@@ -146,20 +156,34 @@ where
         );
     }
 
+    if skipped_bytes > 0 {
+        warn!(
+            skipped_bytes,
+            "wal replay skipped corrupt segment data, writes may have been lost"
+        );
+    }
+
     info!(
         max_sequence_number = ?max_sequence,
+        skipped_bytes,
         "wal replay complete"
     );
 
     Ok(max_sequence)
 }
 
-/// Replay the entries in `file`, applying them to `buffer`. Returns the highest
-/// sequence number observed in the file, or [`None`] if the file was empty.
+/// Replay the entries read from `reader` for the segment `file`, applying them
+/// to `buffer`.
+///
+/// Returns the highest sequence number observed in the file, or [`None`] if the
+/// file was empty, and the number of corrupt bytes skipped at the end of the
+/// file (always 0 unless `skip_corrupt_tail` is true).
 async fn replay_file<T>(
-    mut file: wal::ClosedSegmentFileReader,
+    mut reader: wal::ClosedSegmentFileReader,
+    file: &wal::ClosedSegment,
     sink: &T,
-) -> Result<Option<SequenceNumber>, WalReplayError>
+    skip_corrupt_tail: bool,
+) -> Result<(Option<SequenceNumber>, u64), WalReplayError>
 where
     T: DmlSink,
 {
@@ -167,13 +191,26 @@ where
     let start = Instant::now();
 
     loop {
-        let ops = match file.next_batch() {
+        let ops = match reader.next_batch() {
             Ok(Some(v)) => v,
             Ok(None) => {
                 // This file is complete, return the last observed sequence
                 // number.
                 debug!("wal file replayed in {:?}", start.elapsed());
-                return Ok(max_sequence);
+                return Ok((max_sequence, 0));
+            }
+            Err(e) if skip_corrupt_tail => {
+                let skipped_bytes = file.size().saturating_sub(reader.valid_bytes());
+                warn!(
+                    error = %e,
+                    file_id = %file.id(),
+                    size = file.size(),
+                    valid_bytes = reader.valid_bytes(),
+                    skipped_bytes,
+                    max_sequence_number = ?max_sequence,
+                    "skipping corrupt wal segment tail"
+                );
+                return Ok((max_sequence, skipped_bytes));
             }
             Err(e) => return Err(WalReplayError::ReadEntry(e)),
         };
@@ -247,7 +284,7 @@ mod tests {
         dml_sink::mock_sink::MockDmlSink,
         persist::queue::mock::MockPersistQueue,
         test_util::{assert_dml_writes_eq, make_write_op},
-        wal::wal_sink::WalSink,
+        wal::{reference_tracker::WalReferenceHandle, wal_sink::WalSink},
     };
 
     use super::*;
@@ -319,7 +356,8 @@ mod tests {
                 .await
                 .expect("failed to initialise WAL");
 
-            let wal_sink = WalSink::new(Arc::clone(&inner), Arc::clone(&wal));
+            let (handle, _actor) = WalReferenceHandle::new();
+            let wal_sink = WalSink::new(Arc::clone(&inner), Arc::clone(&wal), handle);
 
             // Apply the first op through the decorator
             wal_sink
@@ -384,7 +422,7 @@ mod tests {
             partitions: vec![Arc::new(Mutex::new(partition))],
         };
 
-        let max_sequence_number = replay(&wal, &mock_iter, Arc::clone(&persist))
+        let max_sequence_number = replay(&wal, &mock_iter, Arc::clone(&persist), false)
             .await
             .expect("failed to replay WAL");
 
@@ -417,4 +455,76 @@ mod tests {
 
         assert_eq!(wal.closed_segments().len(), 1);
     }
+
+    #[tokio::test]
+    async fn test_replay_skip_corrupt_tail() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let op1 = make_write_op(
+            &PartitionKey::from("p1"),
+            NAMESPACE_ID,
+            TABLE_NAME,
+            TABLE_ID,
+            24,
+            r#"bananas,region=Madrid temp=35 4242424242"#,
+        );
+
+        // Write one op and rotate the segment.
+        let segment_id = {
+            let inner = Arc::new(MockDmlSink::default().with_apply_return(vec![Ok(())]));
+            let wal = Wal::new(dir.path())
+                .await
+                .expect("failed to initialise WAL");
+
+            let (handle, _actor) = WalReferenceHandle::new();
+            let wal_sink = WalSink::new(Arc::clone(&inner), Arc::clone(&wal), handle);
+            wal_sink
+                .apply(DmlOperation::Write(op1.clone()))
+                .await
+                .expect("wal should not error");
+
+            wal.rotate().expect("failed to rotate WAL file").id()
+        };
+
+        // Append a partially written entry to the closed segment.
+        {
+            use std::io::Write;
+            let mut f = std::fs::OpenOptions::new()
+                .append(true)
+                .open(dir.path().join(format!("{segment_id}.dat")))
+                .unwrap();
+            f.write_all(&[0, 1, 2, 3, 0, 0, 0, 42, 7]).unwrap();
+        }
+
+        let wal = Wal::new(dir.path())
+            .await
+            .expect("failed to initialise WAL");
+        let persist = Arc::new(MockPersistQueue::default());
+
+        // By default, the corrupt entry fails the replay.
+        let mock_iter = MockIter {
+            sink: MockDmlSink::default().with_apply_return(vec![Ok(())]),
+            partitions: vec![],
+        };
+        let got = replay(&wal, &mock_iter, Arc::clone(&persist), false).await;
+        assert_matches!(got, Err(WalReplayError::ReadEntry(_)));
+
+        // When skipping the corrupt tail, the ops before it are replayed.
+        let mock_iter = MockIter {
+            sink: MockDmlSink::default().with_apply_return(vec![Ok(())]),
+            partitions: vec![],
+        };
+        let max_sequence_number = replay(&wal, &mock_iter, Arc::clone(&persist), true)
+            .await
+            .expect("failed to replay WAL");
+        assert_eq!(max_sequence_number, Some(SequenceNumber::new(24)));
+
+        let ops = mock_iter.sink.get_calls();
+        assert_matches!(&*ops, &[DmlOperation::Write(ref w1)] => {
+            assert_dml_writes_eq(w1.clone(), op1);
+        });
+
+        // The replayed segment was dropped.
+        assert!(wal.closed_segments().iter().all(|s| s.id() != segment_id));
+    }
 }
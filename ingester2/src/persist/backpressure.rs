@@ -41,6 +41,12 @@ const EVALUATE_SATURATION_INTERVAL: Duration = Duration::from_secs(1);
 ///
 /// These conditions are evaluated periodically, at the interval specified in
 /// [`EVALUATE_SATURATION_INTERVAL`].
+///
+/// Exceeding the buffer memory hard limit is signalled through the same
+/// [`IngestState`] with [`IngestStateError::MemoryLimit`], managed by the
+/// [`MemoryPressureMonitor`].
+///
+/// [`MemoryPressureMonitor`]: super::memory_pressure::MemoryPressureMonitor
 #[derive(Debug)]
 pub(super) struct PersistState {
     /// The ingest state the persister configures.
@@ -1,5 +1,6 @@
 use std::sync::Arc;
 
+use data_types::DeduplicationPolicy;
 use datafusion::physical_plan::SendableRecordBatchStream;
 use iox_query::{
     exec::{Executor, ExecutorType},
@@ -46,10 +47,13 @@ impl std::fmt::Debug for CompactedStream {
 
 /// Compact a given batch into a [`CompactedStream`] or `None` if there is no
 /// data to compact, returning an updated sort key, if any.
+///
+/// Duplicate rows are resolved according to `dedup_policy`.
 pub(super) async fn compact_persisting_batch(
     executor: &Executor,
     sort_key: Option<SortKey>,
     table_name: TableName,
+    dedup_policy: DeduplicationPolicy,
     batch: QueryAdaptor,
 ) -> Result<CompactedStream, ()> {
     assert!(!batch.record_batches().is_empty());
@@ -82,6 +86,7 @@ pub(super) async fn compact_persisting_batch(
     // Build logical plan for compaction
     let ctx = executor.new_context(ExecutorType::Reorg);
     let logical_plan = ReorgPlanner::new(ctx.child_ctx("ReorgPlanner"))
+        .with_dedup_policy(dedup_policy)
         .compact_plan(
             table_name.into(),
             batch.schema(),
@@ -106,7 +111,7 @@ pub(super) async fn compact_persisting_batch(
 #[cfg(test)]
 mod tests {
     use arrow::record_batch::RecordBatch;
-    use arrow_util::assert_batches_eq;
+    use arrow_util::{assert_batches_eq, assert_batches_sorted_eq};
     use data_types::PartitionId;
     use iox_query::test::{raw_data, TestChunk};
     use mutable_batch_lp::lines_to_batches;
@@ -137,10 +142,15 @@ mod tests {
 
         // compact
         let exc = Executor::new_testing();
-        let CompactedStream { stream, .. } =
-            compact_persisting_batch(&exc, Some(SortKey::empty()), "test_table".into(), batch)
-                .await
-                .unwrap();
+        let CompactedStream { stream, .. } = compact_persisting_batch(
+            &exc,
+            Some(SortKey::empty()),
+            "test_table".into(),
+            DeduplicationPolicy::Upsert,
+            batch,
+        )
+        .await
+        .unwrap();
 
         let output_batches = datafusion::physical_plan::common::collect(stream)
             .await
@@ -178,9 +188,15 @@ mod tests {
             stream,
             data_sort_key,
             catalog_sort_key_update,
-        } = compact_persisting_batch(&exc, Some(SortKey::empty()), "test_table".into(), batch)
-            .await
-            .unwrap();
+        } = compact_persisting_batch(
+            &exc,
+            Some(SortKey::empty()),
+            "test_table".into(),
+            DeduplicationPolicy::Upsert,
+            batch,
+        )
+        .await
+        .unwrap();
 
         let output_batches = datafusion::physical_plan::common::collect(stream)
             .await
@@ -228,9 +244,15 @@ mod tests {
             stream,
             data_sort_key,
             catalog_sort_key_update,
-        } = compact_persisting_batch(&exc, Some(SortKey::empty()), "test_table".into(), batch)
-            .await
-            .unwrap();
+        } = compact_persisting_batch(
+            &exc,
+            Some(SortKey::empty()),
+            "test_table".into(),
+            DeduplicationPolicy::Upsert,
+            batch,
+        )
+        .await
+        .unwrap();
 
         let output_batches = datafusion::physical_plan::common::collect(stream)
             .await
@@ -287,6 +309,7 @@ mod tests {
             &exc,
             Some(SortKey::from_columns(["tag3", "tag1", "time"])),
             "test_table".into(),
+            DeduplicationPolicy::Upsert,
             batch,
         )
         .await
@@ -347,6 +370,7 @@ mod tests {
             &exc,
             Some(SortKey::from_columns(["tag3", "time"])),
             "test_table".into(),
+            DeduplicationPolicy::Upsert,
             batch,
         )
         .await
@@ -410,6 +434,7 @@ mod tests {
             &exc,
             Some(SortKey::from_columns(["tag3", "tag1", "tag4", "time"])),
             "test_table".into(),
+            DeduplicationPolicy::Upsert,
             batch,
         )
         .await
@@ -465,9 +490,15 @@ mod tests {
 
         // compact
         let exc = Executor::new_testing();
-        let stream = compact_persisting_batch(&exc, Some(sort_key), "test_table".into(), batch)
-            .await
-            .unwrap();
+        let stream = compact_persisting_batch(
+            &exc,
+            Some(sort_key),
+            "test_table".into(),
+            DeduplicationPolicy::Upsert,
+            batch,
+        )
+        .await
+        .unwrap();
         let output_batches = datafusion::physical_plan::common::collect(stream.stream)
             .await
             .unwrap();
@@ -506,9 +537,15 @@ mod tests {
 
         // compact
         let exc = Executor::new_testing();
-        let stream = compact_persisting_batch(&exc, Some(sort_key), "test_table".into(), batch)
-            .await
-            .unwrap();
+        let stream = compact_persisting_batch(
+            &exc,
+            Some(sort_key),
+            "test_table".into(),
+            DeduplicationPolicy::Upsert,
+            batch,
+        )
+        .await
+        .unwrap();
         let output_batches = datafusion::physical_plan::common::collect(stream.stream)
             .await
             .unwrap();
@@ -535,6 +572,52 @@ mod tests {
         assert_batches_eq!(&expected, &output_batches);
     }
 
+    #[tokio::test]
+    async fn test_compact_one_batch_with_duplicates_append_only() {
+        // create input data
+        let batch = QueryAdaptor::new(
+            PartitionId::new(1),
+            create_one_record_batch_with_influxtype_duplicates().await,
+        );
+
+        let sort_key = SortKey::from_columns(["tag1", "time"]);
+
+        // compact
+        let exc = Executor::new_testing();
+        let stream = compact_persisting_batch(
+            &exc,
+            Some(sort_key),
+            "test_table".into(),
+            DeduplicationPolicy::AppendOnly,
+            batch,
+        )
+        .await
+        .unwrap();
+        let output_batches = datafusion::physical_plan::common::collect(stream.stream)
+            .await
+            .unwrap();
+
+        // verify compacted data
+        //  data is sorted but none of the duplicates are removed
+        let expected = vec![
+            "+-----------+------+--------------------------------+",
+            "| field_int | tag1 | time                           |",
+            "+-----------+------+--------------------------------+",
+            "| 10        | AL   | 1970-01-01T00:00:00.000000050Z |",
+            "| 100       | AL   | 1970-01-01T00:00:00.000000050Z |",
+            "| 70        | CT   | 1970-01-01T00:00:00.000000100Z |",
+            "| 70        | CT   | 1970-01-01T00:00:00.000000500Z |",
+            "| 30        | MT   | 1970-01-01T00:00:00.000000005Z |",
+            "| 5         | MT   | 1970-01-01T00:00:00.000000005Z |",
+            "| 1000      | MT   | 1970-01-01T00:00:00.000001Z    |",
+            "| 1000      | MT   | 1970-01-01T00:00:00.000002Z    |",
+            "| 10        | MT   | 1970-01-01T00:00:00.000007Z    |",
+            "| 20        | MT   | 1970-01-01T00:00:00.000007Z    |",
+            "+-----------+------+--------------------------------+",
+        ];
+        assert_batches_sorted_eq!(&expected, &output_batches);
+    }
+
     #[tokio::test]
     async fn test_compact_many_batches_same_columns_with_duplicates() {
         // create many-batches input data
@@ -552,10 +635,16 @@ mod tests {
 
         // compact
         let exc = Executor::new_testing();
-        let stream = compact_persisting_batch(&exc, Some(sort_key), "test_table".into(), batch)
-            .await
-            .unwrap()
-            .stream;
+        let stream = compact_persisting_batch(
+            &exc,
+            Some(sort_key),
+            "test_table".into(),
+            DeduplicationPolicy::Upsert,
+            batch,
+        )
+        .await
+        .unwrap()
+        .stream;
         let output_batches = datafusion::physical_plan::common::collect(stream)
             .await
             .unwrap();
@@ -599,10 +688,16 @@ mod tests {
 
         // compact
         let exc = Executor::new_testing();
-        let stream = compact_persisting_batch(&exc, Some(sort_key), "test_table".into(), batch)
-            .await
-            .unwrap()
-            .stream;
+        let stream = compact_persisting_batch(
+            &exc,
+            Some(sort_key),
+            "test_table".into(),
+            DeduplicationPolicy::Upsert,
+            batch,
+        )
+        .await
+        .unwrap()
+        .stream;
         let output_batches = datafusion::physical_plan::common::collect(stream)
             .await
             .unwrap();
@@ -650,10 +745,16 @@ mod tests {
 
         // compact
         let exc = Executor::new_testing();
-        let stream = compact_persisting_batch(&exc, Some(sort_key), "test_table".into(), batch)
-            .await
-            .unwrap()
-            .stream;
+        let stream = compact_persisting_batch(
+            &exc,
+            Some(sort_key),
+            "test_table".into(),
+            DeduplicationPolicy::Upsert,
+            batch,
+        )
+        .await
+        .unwrap()
+        .stream;
         let output_batches = datafusion::physical_plan::common::collect(stream)
             .await
             .unwrap();
@@ -0,0 +1,46 @@
+use std::fmt::Debug;
+
+use data_types::sequence_number_set::SequenceNumberSet;
+
+/// An abstract observer of persist completion events.
+///
+/// Observers are notified once the data of a persist job is no longer held in
+/// the buffer tree - either because it was persisted, or because it was
+/// discarded (as the table it belongs to no longer exists).
+pub(crate) trait PersistCompletionObserver: Send + Sync + Debug {
+    /// Observe the persistence of all writes in `sequence_numbers`.
+    fn persist_complete(&self, sequence_numbers: &SequenceNumberSet);
+}
+
+/// A no-op implementation of [`PersistCompletionObserver`].
+#[derive(Debug, Default)]
+pub(crate) struct NopObserver;
+
+impl PersistCompletionObserver for NopObserver {
+    fn persist_complete(&self, _sequence_numbers: &SequenceNumberSet) {}
+}
+
+#[cfg(test)]
+pub(crate) mod mock {
+    use parking_lot::Mutex;
+
+    use super::*;
+
+    /// A [`PersistCompletionObserver`] that records every observed set.
+    #[derive(Debug, Default)]
+    pub(crate) struct MockCompletionObserver {
+        calls: Mutex<Vec<SequenceNumberSet>>,
+    }
+
+    impl MockCompletionObserver {
+        pub(crate) fn calls(&self) -> Vec<SequenceNumberSet> {
+            self.calls.lock().clone()
+        }
+    }
+
+    impl PersistCompletionObserver for MockCompletionObserver {
+        fn persist_complete(&self, sequence_numbers: &SequenceNumberSet) {
+            self.calls.lock().push(sequence_numbers.clone());
+        }
+    }
+}
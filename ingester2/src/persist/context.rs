@@ -20,6 +20,8 @@ use crate::{
     deferred_load::DeferredLoad,
 };
 
+use super::completion_observer::PersistCompletionObserver;
+
 /// Errors a persist can experience.
 #[derive(Debug, Error)]
 pub(super) enum PersistError {
@@ -66,6 +68,40 @@ impl PersistRequest {
     pub(super) fn partition_id(&self) -> PartitionId {
         self.data.partition_id()
     }
+
+    /// Return the table ID of the persisting data.
+    pub(super) fn table_id(&self) -> TableId {
+        self.partition.lock().table_id()
+    }
+
+    /// Discard the persisting data without persisting it, marking the
+    /// partition as having completed persistence and notifying the observer of
+    /// this persistence task, if any.
+    ///
+    /// The discarded writes are reported to `completion_observer` as they no
+    /// longer need to be retained anywhere.
+    ///
+    /// This is used when the table the data belongs to has been deleted from
+    /// the catalog, leaving nowhere to persist the data to.
+    pub(super) fn discard(self, completion_observer: &dyn PersistCompletionObserver) {
+        let table_id = self.table_id();
+        let partition_id = self.partition_id();
+        let sequence_numbers = self.partition.lock().mark_persisted(self.data);
+
+        warn!(
+            %table_id,
+            %partition_id,
+            n_writes = sequence_numbers.len(),
+            "discarded persist data for deleted table"
+        );
+
+        completion_observer.persist_complete(&sequence_numbers);
+
+        // As in Context::mark_complete(), release the permit before notifying
+        // the caller.
+        drop(self.permit);
+        let _ = self.complete.send(());
+    }
 }
 
 /// The context of a persist job, containing the data to be persisted and
@@ -215,9 +251,14 @@ impl Context {
     }
 
     // Call [`PartitionData::mark_complete`] to finalise the persistence job,
-    // emit a log for the user, and notify the observer of this persistence
-    // task, if any.
-    pub(super) fn mark_complete(self, object_store_id: Uuid) {
+    // emit a log for the user, report the persisted writes to the
+    // `completion_observer` and notify the observer of this persistence task,
+    // if any.
+    pub(super) fn mark_complete(
+        self,
+        object_store_id: Uuid,
+        completion_observer: &dyn PersistCompletionObserver,
+    ) {
         // Mark the partition as having completed persistence, causing it to
         // release the reference to the in-flight persistence data it is
         // holding.
@@ -244,6 +285,8 @@ impl Context {
             "persisted partition"
         );
 
+        completion_observer.persist_complete(&sequence_numbers);
+
         // Explicitly drop the permit before notifying the caller, so that if
         // there's no headroom in the queue, the caller that is woken by the
         // notification is able to push into the queue immediately.
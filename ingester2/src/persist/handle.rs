@@ -14,8 +14,8 @@ use tokio::{
 };
 
 use super::{
-    backpressure::PersistState, context::PersistRequest, queue::PersistQueue,
-    worker::SharedWorkerState,
+    backpressure::PersistState, completion_observer::PersistCompletionObserver,
+    context::PersistRequest, queue::PersistQueue, worker::SharedWorkerState,
 };
 use crate::{
     buffer_tree::partition::{persisting::PersistingData, PartitionData, SortKeyState},
@@ -161,6 +161,10 @@ pub(crate) struct PersistHandle {
 
 impl PersistHandle {
     /// Initialise a new persist actor & obtain the first handle.
+    ///
+    /// The `completion_observer` is notified of the writes released by every
+    /// completed persist job.
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn new(
         n_workers: usize,
         persist_queue_depth: usize,
@@ -168,6 +172,7 @@ impl PersistHandle {
         exec: Arc<Executor>,
         store: ParquetStorage,
         catalog: Arc<dyn Catalog>,
+        completion_observer: Arc<dyn PersistCompletionObserver>,
         metrics: &metric::Registry,
     ) -> Self {
         assert_ne!(n_workers, 0, "must run at least 1 persist worker");
@@ -183,6 +188,7 @@ impl PersistHandle {
             exec,
             store,
             catalog,
+            completion_observer,
         });
 
         // Initialise the global queue.
@@ -415,6 +421,7 @@ mod tests {
         },
         deferred_load::DeferredLoad,
         dml_sink::DmlSink,
+        persist::completion_observer::NopObserver,
         test_util::make_write_op,
     };
 
@@ -495,6 +502,7 @@ mod tests {
             Arc::clone(&EXEC),
             storage,
             catalog,
+            Arc::new(NopObserver),
             &metrics,
         );
 
@@ -570,6 +578,7 @@ mod tests {
             Arc::clone(&EXEC),
             storage,
             catalog,
+            Arc::new(NopObserver),
             &metrics,
         );
 
@@ -657,6 +666,7 @@ mod tests {
             Arc::clone(&EXEC),
             storage,
             catalog,
+            Arc::new(NopObserver),
             &metrics,
         );
 
@@ -744,6 +754,7 @@ mod tests {
             Arc::clone(&EXEC),
             storage,
             catalog,
+            Arc::new(NopObserver),
             &metrics,
         );
 
@@ -1,5 +1,6 @@
 use std::{fmt::Debug, sync::Arc};
 
+use data_types::SequenceNumber;
 use observability_deps::tracing::info;
 use parking_lot::{Mutex, MutexGuard};
 
@@ -55,7 +56,12 @@ where
     P: PersistQueue + Clone + Sync + 'static,
 {
     #[inline(always)]
-    fn observe(&self, partition: Arc<Mutex<PartitionData>>, guard: MutexGuard<'_, PartitionData>) {
+    fn observe(
+        &self,
+        partition: Arc<Mutex<PartitionData>>,
+        guard: MutexGuard<'_, PartitionData>,
+        _sequence_number: SequenceNumber,
+    ) {
         // Without releasing the lock, obtain the new persist cost estimate.
         let cost_estimate = guard.persist_cost_estimate();
 
@@ -0,0 +1,465 @@
+//! Global buffer memory accounting, and persistence under memory pressure.
+//!
+//! The [`HotPartitionPersister`] bounds the size of any single partition, but
+//! not the total amount of data buffered across all partitions - a large number
+//! of warm partitions can exhaust the memory available to the ingester without
+//! any single partition reaching the hot partition limit.
+//!
+//! The [`MemoryPressureMonitor`] periodically sums the memory usage of all
+//! partitions in the buffer tree and:
+//!
+//!   * Persists the largest partitions (oldest first for equal sizes) once the
+//!     buffered data exceeds the high watermark, until the buffer is projected
+//!     to fall below it once the persist jobs complete.
+//!
+//!   * Sets [`IngestStateError::MemoryLimit`] while the buffered data exceeds
+//!     the hard limit, rejecting writes with a retryable error. This state is
+//!     cleared once memory usage falls below the high watermark (or the hard
+//!     limit, if no high watermark is configured).
+//!
+//! [`HotPartitionPersister`]: super::hot_partitions::HotPartitionPersister
+
+use std::{cmp::Reverse, sync::Arc, time::Duration};
+
+use data_types::SequenceNumber;
+use metric::{U64Counter, U64Gauge};
+use observability_deps::tracing::*;
+use parking_lot::Mutex;
+use tokio::time::MissedTickBehavior;
+
+use crate::{
+    buffer_tree::partition::PartitionData,
+    ingest_state::{IngestState, IngestStateError},
+    partition_iter::PartitionIter,
+};
+
+use super::queue::PersistQueue;
+
+/// The interval of time between evaluations of the buffer memory usage.
+///
+/// The hard limit should leave enough headroom for the data ingested in one
+/// interval.
+const EVALUATE_MEMORY_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The buffer memory limits enforced by a [`MemoryPressureMonitor`].
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct MemoryLimits {
+    /// Persist partitions once the estimated buffer memory usage exceeds this
+    /// number of bytes.
+    pub(crate) high_watermark_bytes: Option<usize>,
+
+    /// Reject writes while the estimated buffer memory usage exceeds this
+    /// number of bytes.
+    pub(crate) hard_limit_bytes: Option<usize>,
+}
+
+impl MemoryLimits {
+    /// Returns true if at least one limit is configured.
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.high_watermark_bytes.is_some() || self.hard_limit_bytes.is_some()
+    }
+}
+
+/// A partition that can be persisted to relieve memory pressure.
+#[derive(Debug)]
+struct Candidate {
+    buffered_bytes: usize,
+    oldest_write: Option<SequenceNumber>,
+    partition: Arc<Mutex<PartitionData>>,
+}
+
+/// Enforces the [`MemoryLimits`] over all partitions in a buffer tree.
+#[derive(Debug)]
+pub(crate) struct MemoryPressureMonitor<T, P> {
+    buffer: T,
+    persist: P,
+    ingest_state: Arc<IngestState>,
+    limits: MemoryLimits,
+
+    /// The estimated memory usage of all buffered and persisting data.
+    memory_usage: U64Gauge,
+
+    /// The number of partitions persisted due to memory pressure.
+    persist_count: U64Counter,
+}
+
+impl<T, P> MemoryPressureMonitor<T, P>
+where
+    T: PartitionIter + Sync,
+    P: PersistQueue + Clone,
+{
+    /// Initialise a [`MemoryPressureMonitor`] for the partitions in `buffer`,
+    /// persisting them with `persist` and signalling the hard limit through
+    /// `ingest_state`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the high watermark is greater than the hard limit.
+    pub(crate) fn new(
+        buffer: T,
+        persist: P,
+        ingest_state: Arc<IngestState>,
+        limits: MemoryLimits,
+        metrics: &metric::Registry,
+    ) -> Self {
+        if let (Some(high), Some(hard)) = (limits.high_watermark_bytes, limits.hard_limit_bytes) {
+            assert!(
+                high <= hard,
+                "buffer memory high watermark must not exceed the hard limit"
+            );
+        }
+
+        let memory_usage = metrics
+            .register_metric::<U64Gauge>(
+                "ingester_buffer_memory_bytes",
+                "the estimated number of bytes of buffered and persisting data",
+            )
+            .recorder(&[]);
+        let persist_count = metrics
+            .register_metric::<U64Counter>(
+                "ingester_memory_pressure_persist",
+                "the number of partitions persisted due to buffer memory pressure",
+            )
+            .recorder(&[]);
+
+        Self {
+            buffer,
+            persist,
+            ingest_state,
+            limits,
+            memory_usage,
+            persist_count,
+        }
+    }
+
+    /// Evaluate the buffer memory usage every [`EVALUATE_MEMORY_INTERVAL`]
+    /// duration of time, forever.
+    pub(crate) async fn run(self) {
+        let mut interval = tokio::time::interval(EVALUATE_MEMORY_INTERVAL);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            interval.tick().await;
+            self.evaluate().await;
+        }
+    }
+
+    /// Sum the memory usage of all partitions, persisting partitions and
+    /// setting the ingest state as necessary.
+    async fn evaluate(&self) {
+        let mut buffered_total = 0;
+        let mut persisting_total = 0;
+        let mut candidates = vec![];
+
+        for partition in self.buffer.partition_iter() {
+            let guard = partition.lock();
+            let buffered_bytes = guard.persist_cost_estimate();
+            let persisting_bytes = guard.persisting_memory_usage();
+            let oldest_write = guard.buffered_sequence_numbers().iter().next();
+            drop(guard);
+
+            buffered_total += buffered_bytes;
+            persisting_total += persisting_bytes;
+
+            if buffered_bytes > 0 {
+                candidates.push(Candidate {
+                    buffered_bytes,
+                    oldest_write,
+                    partition,
+                });
+            }
+        }
+
+        let total = buffered_total + persisting_total;
+        self.memory_usage.set(total as u64);
+        self.update_ingest_state(total);
+
+        let Some(high_watermark) = self.limits.high_watermark_bytes else {
+            return;
+        };
+
+        // The memory held by persisting data is released once the in-flight
+        // persist jobs complete - only the buffered data in excess of the high
+        // watermark must be reclaimed by persisting more partitions.
+        let mut excess = buffered_total.saturating_sub(high_watermark);
+        if excess == 0 {
+            return;
+        }
+
+        info!(
+            total_bytes = total,
+            buffered_bytes = buffered_total,
+            persisting_bytes = persisting_total,
+            high_watermark,
+            "buffer memory over high watermark, persisting partitions"
+        );
+
+        // Persist the largest partitions first to reclaim the most memory with
+        // the fewest persist jobs, preferring the oldest data for equal sizes.
+        candidates.sort_unstable_by_key(|c| (Reverse(c.buffered_bytes), c.oldest_write));
+
+        for c in candidates {
+            if excess == 0 {
+                break;
+            }
+
+            // Writes may have been buffered, or the partition persisted, since
+            // the memory usage was read.
+            let Some(data) = c.partition.lock().mark_persisting() else {
+                continue;
+            };
+
+            debug!(
+                partition_id = data.partition_id().get(),
+                buffered_bytes = c.buffered_bytes,
+                "persisting partition due to memory pressure"
+            );
+
+            excess = excess.saturating_sub(c.buffered_bytes);
+            self.persist_count.inc(1);
+
+            // There is no need to await on the completion handle - the memory
+            // is accounted for as persisting until the job completes.
+            let _ = self.persist.enqueue(c.partition, data).await;
+        }
+    }
+
+    /// Set or clear [`IngestStateError::MemoryLimit`] for the `total` memory
+    /// usage.
+    fn update_ingest_state(&self, total: usize) {
+        let Some(hard_limit) = self.limits.hard_limit_bytes else {
+            return;
+        };
+
+        if total >= hard_limit {
+            if self.ingest_state.set(IngestStateError::MemoryLimit) {
+                warn!(
+                    total_bytes = total,
+                    hard_limit, "buffer memory over hard limit, rejecting writes"
+                );
+            }
+            return;
+        }
+
+        // Clear the state only once the memory usage is below the high
+        // watermark, to avoid flip-flopping around the hard limit.
+        let resume_at = self.limits.high_watermark_bytes.unwrap_or(hard_limit);
+        if total < resume_at && self.ingest_state.unset(IngestStateError::MemoryLimit) {
+            info!(
+                total_bytes = total,
+                "buffer memory below limit, accepting writes"
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_matches::assert_matches;
+    use data_types::{NamespaceId, PartitionId, PartitionKey, ShardId, TableId};
+    use metric::{Attributes, Metric};
+    use mutable_batch_lp::test_helpers::lp_to_mutable_batch;
+    use test_helpers::timeout::FutureTimeout;
+
+    use super::*;
+    use crate::{
+        buffer_tree::partition::SortKeyState, deferred_load::DeferredLoad,
+        persist::queue::mock::MockPersistQueue,
+    };
+
+    /// Initialise a partition with `n` rows buffered, using sequence numbers
+    /// starting at `seq`.
+    fn partition(id: i64, n: usize, seq: i64) -> Arc<Mutex<PartitionData>> {
+        let mut p = PartitionData::new(
+            PartitionId::new(id),
+            PartitionKey::from("bananas"),
+            NamespaceId::new(1),
+            Arc::new(DeferredLoad::new(Duration::from_secs(1), async {
+                "platanos".into()
+            })),
+            TableId::new(2),
+            Arc::new(DeferredLoad::new(Duration::from_secs(1), async {
+                "bananas".into()
+            })),
+            SortKeyState::Provided(None),
+            ShardId::new(3),
+        );
+
+        for i in 0..n {
+            let mb = lp_to_mutable_batch(&format!("bananas,city=London people={i} {i}")).1;
+            p.buffer_write(mb, SequenceNumber::new(seq + i as i64))
+                .unwrap();
+        }
+
+        Arc::new(Mutex::new(p))
+    }
+
+    fn usage(partitions: &[Arc<Mutex<PartitionData>>]) -> usize {
+        partitions.iter().map(|p| p.lock().memory_usage()).sum()
+    }
+
+    fn persisted_ids(persist: &MockPersistQueue) -> Vec<PartitionId> {
+        persist
+            .calls()
+            .iter()
+            .map(|p| p.lock().partition_id())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_persist_over_high_watermark() {
+        let small = partition(1, 1, 1);
+        let large = partition(2, 10, 10);
+        let old = partition(3, 10, 0);
+        let partitions = vec![Arc::clone(&small), Arc::clone(&large), Arc::clone(&old)];
+
+        // Equal row counts are assumed to have an equal size.
+        assert_eq!(
+            large.lock().persist_cost_estimate(),
+            old.lock().persist_cost_estimate()
+        );
+        let large_bytes = large.lock().persist_cost_estimate();
+        let total = usage(&partitions);
+
+        let metrics = metric::Registry::default();
+        let persist = Arc::new(MockPersistQueue::default());
+        let ingest_state = Arc::new(IngestState::default());
+        let monitor = MemoryPressureMonitor::new(
+            partitions.clone(),
+            Arc::clone(&persist),
+            Arc::clone(&ingest_state),
+            MemoryLimits {
+                // Persisting the largest, oldest partition is sufficient.
+                high_watermark_bytes: Some(total - large_bytes),
+                hard_limit_bytes: None,
+            },
+            &metrics,
+        );
+
+        monitor.evaluate().await;
+        assert_eq!(persisted_ids(&persist), [PartitionId::new(3)]);
+        assert_matches!(ingest_state.read(), Ok(()));
+
+        // The persisting data is still accounted for.
+        let gauge = metrics
+            .get_instrument::<Metric<U64Gauge>>("ingester_buffer_memory_bytes")
+            .unwrap()
+            .get_observer(&Attributes::from(&[]))
+            .unwrap()
+            .fetch();
+        assert_eq!(gauge, total as u64);
+
+        // The in-flight persist is expected to release enough memory, so no
+        // further partitions are persisted.
+        monitor.evaluate().await;
+        assert_eq!(persisted_ids(&persist), [PartitionId::new(3)]);
+
+        // Lowering the watermark persists the next largest partition.
+        let monitor = MemoryPressureMonitor::new(
+            partitions,
+            Arc::clone(&persist),
+            Arc::clone(&ingest_state),
+            MemoryLimits {
+                high_watermark_bytes: Some(1),
+                hard_limit_bytes: None,
+            },
+            &metric::Registry::default(),
+        );
+        monitor.evaluate().await;
+        assert_eq!(
+            persisted_ids(&persist),
+            [
+                PartitionId::new(3),
+                PartitionId::new(2),
+                PartitionId::new(1)
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_hard_limit() {
+        let p = partition(1, 10, 1);
+        let total = p.lock().memory_usage();
+
+        let persist = Arc::new(MockPersistQueue::default());
+        let ingest_state = Arc::new(IngestState::default());
+        let monitor = MemoryPressureMonitor::new(
+            vec![Arc::clone(&p)],
+            Arc::clone(&persist),
+            Arc::clone(&ingest_state),
+            MemoryLimits {
+                high_watermark_bytes: None,
+                hard_limit_bytes: Some(total),
+            },
+            &metric::Registry::default(),
+        );
+
+        monitor.evaluate().await;
+        assert_matches!(ingest_state.read(), Err(IngestStateError::MemoryLimit));
+
+        // Without a high watermark, nothing is persisted by the monitor.
+        assert!(persist.calls().is_empty());
+
+        // Releasing the buffered data clears the error state.
+        let data = p.lock().mark_persisting().unwrap();
+        p.lock().mark_persisted(data);
+        monitor.evaluate().await;
+        assert_matches!(ingest_state.read(), Ok(()));
+    }
+
+    #[tokio::test]
+    async fn test_hard_limit_hysteresis() {
+        let a = partition(1, 10, 1);
+        let b = partition(2, 1, 20);
+        let a_bytes = a.lock().memory_usage();
+        let b_bytes = b.lock().memory_usage();
+
+        let persist = Arc::new(MockPersistQueue::default());
+        let ingest_state = Arc::new(IngestState::default());
+        let monitor = MemoryPressureMonitor::new(
+            vec![Arc::clone(&a), Arc::clone(&b)],
+            Arc::clone(&persist),
+            Arc::clone(&ingest_state),
+            MemoryLimits {
+                high_watermark_bytes: Some(b_bytes + 1),
+                hard_limit_bytes: Some(a_bytes + b_bytes),
+            },
+            &metric::Registry::default(),
+        );
+
+        monitor.evaluate().await;
+        assert_matches!(ingest_state.read(), Err(IngestStateError::MemoryLimit));
+        assert_eq!(persisted_ids(&persist), [PartitionId::new(1)]);
+
+        // Still over the high watermark while the persist is in flight.
+        monitor.evaluate().await;
+        assert_matches!(ingest_state.read(), Err(IngestStateError::MemoryLimit));
+
+        // Once the persist completes, memory usage is below the high
+        // watermark and writes are accepted again.
+        async {
+            while a.lock().memory_usage() > 0 {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        }
+        .with_timeout_panic(Duration::from_secs(5))
+        .await;
+        monitor.evaluate().await;
+        assert_matches!(ingest_state.read(), Ok(()));
+    }
+
+    #[test]
+    #[should_panic(expected = "high watermark must not exceed the hard limit")]
+    fn test_invalid_limits() {
+        MemoryPressureMonitor::new(
+            Vec::<Arc<Mutex<PartitionData>>>::new(),
+            Arc::new(MockPersistQueue::default()),
+            Arc::new(IngestState::default()),
+            MemoryLimits {
+                high_watermark_bytes: Some(2),
+                hard_limit_bytes: Some(1),
+            },
+            &metric::Registry::default(),
+        );
+    }
+}
@@ -1,9 +1,11 @@
 pub(crate) mod backpressure;
 pub(super) mod compact;
+pub(crate) mod completion_observer;
 mod context;
 pub(crate) mod drain_buffer;
 pub(crate) mod handle;
 pub(crate) mod hot_partitions;
+pub(crate) mod memory_pressure;
 pub mod queue;
 mod worker;
 
@@ -12,7 +14,7 @@ mod tests {
     use std::{sync::Arc, time::Duration};
 
     use assert_matches::assert_matches;
-    use data_types::{CompactionLevel, ParquetFile, PartitionKey, ShardId};
+    use data_types::{CompactionLevel, ParquetFile, PartitionKey, SequenceNumber, ShardId};
     use dml::DmlOperation;
     use futures::TryStreamExt;
     use iox_catalog::{
@@ -40,7 +42,10 @@ mod tests {
         },
         dml_sink::DmlSink,
         ingest_state::IngestState,
-        persist::queue::PersistQueue,
+        persist::{
+            completion_observer::{mock::MockCompletionObserver, NopObserver},
+            queue::PersistQueue,
+        },
         test_util::{make_write_op, populate_catalog},
         TRANSITION_SHARD_INDEX,
     };
@@ -127,6 +132,7 @@ mod tests {
         let metrics = Arc::new(metric::Registry::default());
         let catalog: Arc<dyn Catalog> = Arc::new(MemCatalog::new(Arc::clone(&metrics)));
         let ingest_state = Arc::new(IngestState::default());
+        let completion_observer = Arc::new(MockCompletionObserver::default());
 
         // Initialise the persist system.
         let handle = PersistHandle::new(
@@ -136,6 +142,7 @@ mod tests {
             Arc::clone(&EXEC),
             storage,
             Arc::clone(&catalog),
+            Arc::clone(&completion_observer) as _,
             &metrics,
         );
         assert!(ingest_state.read().is_ok());
@@ -168,6 +175,11 @@ mod tests {
         // mark_persisted() was called.
         assert_eq!(partition.lock().completed_persistence_count(), 1);
 
+        // And the completion observer was told about the persisted write.
+        assert_matches!(&*completion_observer.calls(), [set] => {
+            assert_eq!(set.iter().collect::<Vec<_>>(), [SequenceNumber::new(0)]);
+        });
+
         // Assert the sort key was also updated
         assert_matches!(partition.lock().sort_key(), SortKeyState::Provided(Some(p)) => {
             assert_eq!(p.to_columns().collect::<Vec<_>>(), &["region", "time"]);
@@ -251,6 +263,7 @@ mod tests {
             Arc::clone(&EXEC),
             storage,
             Arc::clone(&catalog),
+            Arc::new(NopObserver),
             &metrics,
         );
         assert!(ingest_state.read().is_ok());
@@ -368,4 +381,70 @@ mod tests {
 
         assert_eq!(file.size, file_size_bytes as usize);
     }
+
+    /// Persisting data for a table that has since been deleted from the catalog
+    /// discards the data instead of blocking the persist system.
+    #[tokio::test]
+    async fn test_persist_deleted_table() {
+        maybe_start_logging();
+
+        let object_storage: Arc<dyn ObjectStore> = Arc::new(InMemory::default());
+        let storage = ParquetStorage::new(Arc::clone(&object_storage), StorageId::from("iox"));
+        let metrics = Arc::new(metric::Registry::default());
+        let catalog: Arc<dyn Catalog> = Arc::new(MemCatalog::new(Arc::clone(&metrics)));
+        let ingest_state = Arc::new(IngestState::default());
+
+        // Initialise the persist system.
+        let handle = PersistHandle::new(
+            1,
+            2,
+            Arc::clone(&ingest_state),
+            Arc::clone(&EXEC),
+            storage,
+            Arc::clone(&catalog),
+            Arc::new(NopObserver),
+            &metrics,
+        );
+
+        // Generate a partition with data
+        let partition = partition_with_write(Arc::clone(&catalog)).await;
+        let table_id = partition.lock().table_id();
+
+        // Transition it to "persisting".
+        let data = partition
+            .lock()
+            .mark_persisting()
+            .expect("partition with write should transition to persisting");
+
+        // Delete the table before the persist job runs.
+        catalog
+            .repositories()
+            .await
+            .tables()
+            .delete(table_id)
+            .await
+            .expect("failed to delete table");
+
+        // Enqueue the persist job and wait for it to complete.
+        handle
+            .enqueue(Arc::clone(&partition), data)
+            .await
+            .with_timeout(Duration::from_secs(10))
+            .await
+            .expect("timeout waiting for completion notification")
+            .expect("worker task failed");
+
+        // The partition completed persistence, releasing the data.
+        assert_eq!(partition.lock().completed_persistence_count(), 1);
+
+        // But nothing was uploaded to object storage.
+        let files: Vec<ObjectMeta> = object_storage
+            .list(None)
+            .await
+            .expect("listing object storage failed")
+            .try_collect::<Vec<_>>()
+            .await
+            .expect("failed to list object store files");
+        assert!(files.is_empty());
+    }
 }
@@ -2,8 +2,8 @@ use std::{ops::ControlFlow, sync::Arc};
 
 use async_channel::RecvError;
 use backoff::Backoff;
-use data_types::{CompactionLevel, ParquetFileParams, SequenceNumber};
-use iox_catalog::interface::{get_table_schema_by_id, CasFailure, Catalog};
+use data_types::{CompactionLevel, ParquetFileParams, SequenceNumber, TableId, TableSchema};
+use iox_catalog::interface::{CasFailure, Catalog};
 use iox_query::exec::Executor;
 
 use iox_time::{SystemProvider, TimeProvider};
@@ -18,6 +18,7 @@ use crate::persist::compact::compact_persisting_batch;
 
 use super::{
     compact::CompactedStream,
+    completion_observer::PersistCompletionObserver,
     context::{Context, PersistError, PersistRequest},
 };
 
@@ -27,6 +28,7 @@ pub(super) struct SharedWorkerState {
     pub(super) exec: Arc<Executor>,
     pub(super) store: ParquetStorage,
     pub(super) catalog: Arc<dyn Catalog>,
+    pub(super) completion_observer: Arc<dyn PersistCompletionObserver>,
 }
 
 /// The worker routine that drives a [`PersistRequest`] to completion,
@@ -108,6 +110,19 @@ pub(super) async fn run_task(
             }
         };
 
+        // Read the table schema from the catalog to act as a map of column
+        // name -> column IDs, and for the deduplication policy of the table.
+        //
+        // The table may have been deleted since this data was buffered, in
+        // which case there is nothing to persist it into.
+        let table_schema = match load_table_schema(req.table_id(), &worker_state).await {
+            Some(v) => v,
+            None => {
+                req.discard(worker_state.completion_observer.as_ref());
+                continue;
+            }
+        };
+
         let mut ctx = Context::new(req);
 
         // Compact the data, generate the parquet file from the result, and
@@ -119,7 +134,7 @@ pub(super) async fn run_task(
         // the compaction must be redone with the new sort key and uploaded
         // before continuing.
         let parquet_table_data = loop {
-            match compact_and_upload(&mut ctx, &worker_state).await {
+            match compact_and_upload(&mut ctx, &worker_state, &table_schema).await {
                 Ok(v) => break v,
                 Err(PersistError::ConcurrentSortKeyUpdate(_)) => continue,
             };
@@ -130,10 +145,34 @@ pub(super) async fn run_task(
 
         // And finally mark the persist job as complete and notify any
         // observers.
-        ctx.mark_complete(object_store_id);
+        ctx.mark_complete(object_store_id, worker_state.completion_observer.as_ref());
     }
 }
 
+/// Fetch the schema of the table identified by `table_id` from the catalog,
+/// returning [`None`] if the table no longer exists.
+async fn load_table_schema(
+    table_id: TableId,
+    worker_state: &SharedWorkerState,
+) -> Option<TableSchema> {
+    Backoff::new(&Default::default())
+        .retry_all_errors("get table schema", || async {
+            let mut repos = worker_state.catalog.repositories().await;
+            let Some(table) = repos.tables().get_by_id(table_id).await? else {
+                return Ok(None);
+            };
+
+            let mut schema = TableSchema::new_for_table(&table);
+            for col in repos.columns().list_by_table_id(table_id).await? {
+                schema.add_column(&col);
+            }
+
+            Result::<_, iox_catalog::interface::Error>::Ok(Some(schema))
+        })
+        .await
+        .expect("retry forever")
+}
+
 /// Run a compaction on the [`PersistingData`], generate a parquet file and
 /// upload it to object storage.
 ///
@@ -151,9 +190,11 @@ pub(super) async fn run_task(
 async fn compact_and_upload(
     ctx: &mut Context,
     worker_state: &SharedWorkerState,
+    table_schema: &TableSchema,
 ) -> Result<ParquetFileParams, PersistError> {
-    let compacted = compact(ctx, worker_state).await;
-    let (sort_key_update, parquet_table_data) = upload(ctx, worker_state, compacted).await;
+    let compacted = compact(ctx, worker_state, table_schema).await;
+    let (sort_key_update, parquet_table_data) =
+        upload(ctx, worker_state, table_schema, compacted).await;
 
     if let Some(update) = sort_key_update {
         update_catalog_sort_key(
@@ -169,8 +210,13 @@ async fn compact_and_upload(
 }
 
 /// Compact the data in `ctx` using sorted by the sort key returned from
-/// [`Context::sort_key()`].
-async fn compact(ctx: &Context, worker_state: &SharedWorkerState) -> CompactedStream {
+/// [`Context::sort_key()`], resolving duplicate rows according to the
+/// deduplication policy in `table_schema`.
+async fn compact(
+    ctx: &Context,
+    worker_state: &SharedWorkerState,
+    table_schema: &TableSchema,
+) -> CompactedStream {
     let sort_key = ctx.sort_key().get().await;
 
     debug!(
@@ -194,6 +240,7 @@ async fn compact(ctx: &Context, worker_state: &SharedWorkerState) -> CompactedSt
         &worker_state.exec,
         sort_key,
         ctx.table_name().get().await,
+        table_schema.dedup_policy,
         ctx.data().query_adaptor(),
     )
     .await
@@ -205,6 +252,7 @@ async fn compact(ctx: &Context, worker_state: &SharedWorkerState) -> CompactedSt
 async fn upload(
     ctx: &Context,
     worker_state: &SharedWorkerState,
+    table_schema: &TableSchema,
     compacted: CompactedStream,
 ) -> (Option<SortKey>, ParquetFileParams) {
     let CompactedStream {
@@ -266,16 +314,6 @@ async fn upload(
         "partition parquet uploaded"
     );
 
-    // Read the table schema from the catalog to act as a map of column name
-    // -> column IDs.
-    let table_schema = Backoff::new(&Default::default())
-        .retry_all_errors("get table schema", || async {
-            let mut repos = worker_state.catalog.repositories().await;
-            get_table_schema_by_id(ctx.table_id(), repos.as_mut()).await
-        })
-        .await
-        .expect("retry forever");
-
     // Build the data that must be inserted into the parquet_files catalog
     // table in order to make the file visible to queriers.
     let parquet_table_data =
@@ -449,32 +487,54 @@ async fn update_catalog_parquet(
     //
     // This has the effect of allowing the queriers to "discover" the
     // parquet file by polling / querying the catalog.
-    Backoff::new(&Default::default())
+    let parquet_file = Backoff::new(&Default::default())
         .retry_all_errors("add parquet file to catalog", || async {
             let mut repos = worker_state.catalog.repositories().await;
-            let parquet_file = repos
+            match repos
                 .parquet_files()
                 .create(parquet_table_data.clone())
-                .await?;
+                .await
+            {
+                Ok(v) => Ok(Some(v)),
+                // The table (or its namespace) was deleted after this persist
+                // job started - the uploaded object is unreferenced and left
+                // for the garbage collector.
+                Err(
+                    iox_catalog::interface::Error::TableNotFound { .. }
+                    | iox_catalog::interface::Error::ForeignKeyViolation { .. },
+                ) => Ok(None),
+                Err(e) => Err(e),
+            }
+        })
+        .await
+        .expect("retry forever");
 
-            debug!(
+    let parquet_file = match parquet_file {
+        Some(v) => v,
+        None => {
+            warn!(
                 namespace_id = %ctx.namespace_id(),
-                namespace_name = %ctx.namespace_name(),
                 table_id = %ctx.table_id(),
-                table_name = %ctx.table_name(),
                 partition_id = %ctx.partition_id(),
-                partition_key = %ctx.partition_key(),
                 %object_store_id,
-                ?parquet_table_data,
-                parquet_file_id=?parquet_file.id,
-                "parquet file added to catalog"
+                "table or namespace deleted during persist, discarding parquet file"
             );
+            return object_store_id;
+        }
+    };
 
-            // compiler insisted on getting told the type of the error :shrug:
-            Ok(()) as Result<(), iox_catalog::interface::Error>
-        })
-        .await
-        .expect("retry forever");
+    debug!(
+        namespace_id = %ctx.namespace_id(),
+        namespace_name = %ctx.namespace_name(),
+        table_id = %ctx.table_id(),
+        table_name = %ctx.table_name(),
+        partition_id = %ctx.partition_id(),
+        partition_key = %ctx.partition_key(),
+        %object_store_id,
+        ?parquet_table_data,
+        parquet_file_id=?parquet_file.id,
+        "parquet file added to catalog"
+    );
 
     object_store_id
 }
@@ -2,6 +2,7 @@ use async_trait::async_trait;
 use data_types::{NamespaceId, TableId};
 use iox_time::{SystemProvider, TimeProvider};
 use metric::{DurationHistogram, Metric};
+use predicate::Predicate;
 use trace::span::Span;
 
 use super::QueryExec;
@@ -57,13 +58,14 @@ where
         namespace_id: NamespaceId,
         table_id: TableId,
         columns: Vec<String>,
+        predicate: Predicate,
         span: Option<Span>,
     ) -> Result<Self::Response, QueryError> {
         let t = self.time_provider.now();
 
         let res = self
             .inner
-            .query_exec(namespace_id, table_id, columns, span)
+            .query_exec(namespace_id, table_id, columns, predicate, span)
             .await;
 
         if let Some(delta) = self.time_provider.now().checked_duration_since(t) {
@@ -105,7 +107,13 @@ mod tests {
 
                     // Call the decorator and assert the return value
                     let got = decorator
-                        .query_exec(NamespaceId::new(42), TableId::new(24), vec![], None)
+                        .query_exec(
+                            NamespaceId::new(42),
+                            TableId::new(24),
+                            vec![],
+                            Predicate::default(),
+                            None,
+                        )
                         .await;
                     assert_matches!(got, $($want_ret)+);
 
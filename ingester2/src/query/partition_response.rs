@@ -15,6 +15,9 @@ pub(crate) struct PartitionResponse {
 
     /// Count of persisted Parquet files for this partition by this ingester instance.
     completed_persistence_count: u64,
+
+    /// Version of the data of this partition buffered by this ingester instance.
+    buffer_version: u64,
 }
 
 impl std::fmt::Debug for PartitionResponse {
@@ -32,6 +35,7 @@ impl std::fmt::Debug for PartitionResponse {
                 "completed_persistence_count",
                 &self.completed_persistence_count,
             )
+            .field("buffer_version", &self.buffer_version)
             .finish()
     }
 }
@@ -41,11 +45,13 @@ impl PartitionResponse {
         data: Option<SendableRecordBatchStream>,
         id: PartitionId,
         completed_persistence_count: u64,
+        buffer_version: u64,
     ) -> Self {
         Self {
             batches: data,
             id,
             completed_persistence_count,
+            buffer_version,
         }
     }
 
@@ -57,6 +63,10 @@ impl PartitionResponse {
         self.completed_persistence_count
     }
 
+    pub(crate) fn buffer_version(&self) -> u64 {
+        self.buffer_version
+    }
+
     pub(crate) fn into_record_batch_stream(self) -> Option<SendableRecordBatchStream> {
         self.batches
     }
@@ -2,6 +2,7 @@ use std::borrow::Cow;
 
 use async_trait::async_trait;
 use data_types::{NamespaceId, TableId};
+use predicate::Predicate;
 use trace::span::{Span, SpanRecorder};
 
 use super::QueryExec;
@@ -41,6 +42,7 @@ where
         namespace_id: NamespaceId,
         table_id: TableId,
         columns: Vec<String>,
+        predicate: Predicate,
         span: Option<Span>,
     ) -> Result<Self::Response, QueryError> {
         let span = span.map(|s| s.child(self.name.clone()));
@@ -48,7 +50,7 @@ where
 
         match self
             .inner
-            .query_exec(namespace_id, table_id, columns, span)
+            .query_exec(namespace_id, table_id, columns, predicate, span)
             .await
         {
             Ok(v) => {
@@ -111,6 +113,7 @@ mod tests {
                 NamespaceId::new(42),
                 TableId::new(24),
                 vec![],
+                Predicate::default(),
                 Some(span.child("root span")),
             )
             .await
@@ -134,6 +137,7 @@ mod tests {
                 NamespaceId::new(42),
                 TableId::new(24),
                 vec![],
+                Predicate::default(),
                 Some(span.child("root span")),
             )
             .await
@@ -1,17 +1,19 @@
 //! An adaptor over a set of [`RecordBatch`] allowing them to be used as an IOx
 //! [`QueryChunk`].
 
-use std::{any::Any, sync::Arc};
+use std::{any::Any, collections::HashSet, sync::Arc};
 
 use arrow::record_batch::RecordBatch;
 use arrow_util::util::ensure_schema;
 use data_types::{ChunkId, ChunkOrder, DeletePredicate, PartitionId, TableSummary};
-use datafusion::error::DataFusionError;
+use datafusion::{error::DataFusionError, logical_expr::utils::expr_to_columns, prelude::Expr};
+use datafusion_util::batch_filter;
 use iox_query::{
     exec::{stringset::StringSet, IOxSessionContext},
-    util::{compute_timenanosecond_min_max, create_basic_summary},
+    util::{compute_timenanosecond_min_max, create_basic_summary, df_physical_expr_from_schema},
     QueryChunk, QueryChunkData, QueryChunkMeta,
 };
+use observability_deps::tracing::debug;
 use once_cell::sync::OnceCell;
 use predicate::Predicate;
 use schema::{merge::merge_record_batch_schemas, sort::SortKey, Projection, Schema};
@@ -97,6 +99,57 @@ impl QueryAdaptor {
             .collect()
     }
 
+    /// Return a [`QueryAdaptor`] containing only the rows that may match the
+    /// time range and expressions of `predicate`, or [`None`] if no row can
+    /// match.
+    ///
+    /// Filtering is best-effort: expressions referencing columns that are not
+    /// present in a [`RecordBatch`], or that fail to evaluate, are not applied.
+    /// The returned data may therefore contain rows that do not match
+    /// `predicate`, and the caller is expected to re-apply it.
+    ///
+    /// The data is NOT de-duplicated, so `predicate` must only contain
+    /// expressions that are safe to evaluate before de-duplication (see
+    /// [`Predicate::push_through_dedup`]).
+    pub(crate) fn filter(self, predicate: &Predicate) -> Option<Self> {
+        if predicate.range.is_none() && predicate.exprs.is_empty() {
+            return Some(self);
+        }
+
+        // Skip the whole partition if the buffered time range cannot match.
+        if let Some(range) = predicate.range {
+            let time_range = self
+                .summary()
+                .time_range()
+                .expect("query adaptor must have a time range");
+            if !time_range.overlaps(range) {
+                return None;
+            }
+        }
+
+        let data = self
+            .data
+            .iter()
+            .filter_map(|batch| match filter_batch(batch, predicate) {
+                Ok(batch) => (batch.num_rows() > 0).then(|| Arc::new(batch)),
+                Err(e) => {
+                    debug!(
+                        error=%e,
+                        partition_id=%self.partition_id,
+                        "failed to apply query predicate, returning unfiltered data"
+                    );
+                    Some(Arc::clone(batch))
+                }
+            })
+            .collect::<Vec<_>>();
+
+        if data.is_empty() {
+            return None;
+        }
+
+        Some(Self::new(self.partition_id, data))
+    }
+
     /// Returns the [`RecordBatch`] instances in this [`QueryAdaptor`].
     pub(crate) fn record_batches(&self) -> &[Arc<RecordBatch>] {
         self.data.as_ref()
@@ -109,6 +162,44 @@ impl QueryAdaptor {
     }
 }
 
+/// Apply the time range and all expressions of `predicate` that only
+/// reference columns present in `batch`.
+fn filter_batch(
+    batch: &RecordBatch,
+    predicate: &Predicate,
+) -> Result<RecordBatch, DataFusionError> {
+    let schema = batch.schema();
+
+    let exprs = predicate
+        .exprs
+        .iter()
+        .filter(|expr| references_only(expr, &schema))
+        .cloned();
+    let expr = match Predicate::default()
+        .with_maybe_timestamp_range(predicate.range)
+        .with_exprs(exprs)
+        .filter_expr()
+    {
+        Some(expr) => expr,
+        None => return Ok(batch.clone()),
+    };
+
+    let expr = df_physical_expr_from_schema(schema, expr)?;
+    Ok(batch_filter(batch, &expr)?)
+}
+
+/// Returns true if all columns referenced by `expr` exist in `schema`.
+fn references_only(expr: &Expr, schema: &arrow::datatypes::Schema) -> bool {
+    let mut columns = HashSet::new();
+    if expr_to_columns(expr, &mut columns).is_err() {
+        return false;
+    }
+
+    columns
+        .iter()
+        .all(|c| schema.field_with_name(&c.name).is_ok())
+}
+
 impl QueryChunkMeta for QueryAdaptor {
     fn summary(&self) -> Arc<TableSummary> {
         Arc::clone(self.summary.get_or_init(|| {
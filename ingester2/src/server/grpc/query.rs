@@ -4,7 +4,10 @@ use arrow::{error::ArrowError, record_batch::RecordBatch};
 use data_types::{NamespaceId, PartitionId, TableId};
 use flatbuffers::FlatBufferBuilder;
 use futures::{Stream, StreamExt};
-use generated_types::influxdata::iox::ingester::v1::{self as proto, PartitionStatus};
+use generated_types::{
+    google::FieldViolation,
+    influxdata::iox::ingester::v1::{self as proto, PartitionStatus},
+};
 use iox_arrow_flight::{
     encode::{
         prepare_batch_for_flight, prepare_schema_for_flight, split_batch_for_grpc_response,
@@ -18,6 +21,7 @@ use iox_arrow_flight::{
 use metric::U64Counter;
 use observability_deps::tracing::*;
 use pin_project::pin_project;
+use predicate::Predicate;
 use prost::Message;
 use thiserror::Error;
 use tokio::sync::{Semaphore, TryAcquireError};
@@ -45,6 +49,11 @@ enum Error {
     #[error("invalid flight ticket: {0}")]
     InvalidTicket(#[from] prost::DecodeError),
 
+    /// The [`proto::Predicate`] within the query request cannot be
+    /// deserialised into a [`Predicate`].
+    #[error("invalid query predicate: {0}")]
+    InvalidPredicate(#[from] FieldViolation),
+
     /// The [`proto::IngesterQueryResponseMetadata`] response metadata being
     /// returned to the RPC caller cannot be serialised into the protobuf
     /// response format.
@@ -83,6 +92,10 @@ impl From<Error> for tonic::Status {
                 debug!(error=%e, "invalid flight query ticket");
                 Code::InvalidArgument
             }
+            Error::InvalidPredicate(_) => {
+                debug!(error=%e, "invalid flight query predicate");
+                Code::InvalidArgument
+            }
             Error::Stream(_) | Error::SerialiseResponse(_) => {
                 error!(error=%e, "flight query response error");
                 Code::Internal
@@ -191,10 +204,14 @@ where
         let namespace_id = NamespaceId::new(request.namespace_id);
         let table_id = TableId::new(request.table_id);
 
-        // Predicate pushdown is part of the API, but not implemented.
-        if let Some(p) = request.predicate {
-            warn!(predicate=?p, "ignoring query predicate (unsupported)");
-        }
+        // The predicate is applied on a best-effort basis to reduce the amount
+        // of data returned - the querier MUST re-apply it.
+        let predicate = request
+            .predicate
+            .map(Predicate::try_from)
+            .transpose()
+            .map_err(Error::from)?
+            .unwrap_or_default();
 
         let response = self
             .query_handler
@@ -202,6 +219,7 @@ where
                 namespace_id,
                 table_id,
                 request.columns,
+                predicate,
                 span_ctx.child_span("ingester query"),
             )
             .await?;
@@ -292,6 +310,13 @@ pub enum FlatIngesterQueryResponse {
         /// [`PartitionData`]: crate::buffer_tree::partition::PartitionData
         /// [`PartitionResponse`]: crate::query::partition_response::PartitionResponse
         completed_persistence_count: u64,
+
+        /// Version of the data buffered in the [`PartitionData`] instance this
+        /// [`PartitionResponse`] was generated from.
+        ///
+        /// [`PartitionData`]: crate::buffer_tree::partition::PartitionData
+        /// [`PartitionResponse`]: crate::query::partition_response::PartitionResponse
+        buffer_version: u64,
     },
 
     /// Start a new snapshot.
@@ -317,6 +342,7 @@ impl From<QueryResponse> for FlatIngesterQueryResponseStream {
             .flat_map(|partition| {
                 let partition_id = partition.id();
                 let completed_persistence_count = partition.completed_persistence_count();
+                let buffer_version = partition.buffer_version();
                 let head = futures::stream::once(async move {
                     Ok(FlatIngesterQueryResponse::StartPartition {
                         partition_id,
@@ -324,6 +350,7 @@ impl From<QueryResponse> for FlatIngesterQueryResponseStream {
                             parquet_max_sequence_number: None,
                         },
                         completed_persistence_count,
+                        buffer_version,
                     })
                 });
 
@@ -428,6 +455,7 @@ impl Stream for FlightFrameCodec {
                     partition_id,
                     status,
                     completed_persistence_count,
+                    buffer_version,
                 }))) => {
                     let mut bytes = bytes::BytesMut::new();
                     let app_metadata = proto::IngesterQueryResponseMetadata {
@@ -437,6 +465,7 @@ impl Stream for FlightFrameCodec {
                         }),
                         ingester_uuid: this.ingester_id.to_string(),
                         completed_persistence_count,
+                        buffer_version,
                     };
                     prost::Message::encode(&app_metadata, &mut bytes).map_err(Error::from)?;
 
@@ -518,6 +547,7 @@ mod tests {
                         parquet_max_sequence_number: None,
                     },
                     completed_persistence_count: 0,
+                    buffer_version: 0,
                 }),
                 Ok(FlatIngesterQueryResponse::StartSnapshot { schema }),
                 Ok(FlatIngesterQueryResponse::RecordBatch { batch }),
@@ -532,6 +562,7 @@ mod tests {
                         }),
                         completed_persistence_count: 0,
                         ingester_uuid: ingester_id.to_string(),
+                        buffer_version: 0,
                     },
                 }),
                 Ok(DecodedFlightData {
@@ -559,6 +590,7 @@ mod tests {
                         parquet_max_sequence_number: None,
                     },
                     completed_persistence_count: 0,
+                    buffer_version: 0,
                 }),
                 Err(ArrowError::IoError("foo".into())),
                 Ok(FlatIngesterQueryResponse::StartPartition {
@@ -567,6 +599,7 @@ mod tests {
                         parquet_max_sequence_number: None,
                     },
                     completed_persistence_count: 0,
+                    buffer_version: 0,
                 }),
             ],
             vec![
@@ -579,6 +612,7 @@ mod tests {
                         }),
                         completed_persistence_count: 0,
                         ingester_uuid: ingester_id.to_string(),
+                        buffer_version: 0,
                     },
                 }),
                 Err(tonic::Code::Internal),
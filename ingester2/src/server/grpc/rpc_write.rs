@@ -48,7 +48,9 @@ impl From<RpcError> for tonic::Status {
     fn from(e: RpcError) -> Self {
         let code = match e {
             RpcError::Decode(_) | RpcError::NoPayload | RpcError::NoTables => Code::InvalidArgument,
-            RpcError::SystemState(IngestStateError::PersistSaturated) => Code::ResourceExhausted,
+            RpcError::SystemState(
+                IngestStateError::PersistSaturated | IngestStateError::MemoryLimit,
+            ) => Code::ResourceExhausted,
             RpcError::SystemState(IngestStateError::GracefulStop) => Code::FailedPrecondition,
         };
 
@@ -467,6 +469,62 @@ mod tests {
         assert_matches!(*mock.get_calls(), [DmlOperation::Write(_)]);
     }
 
+    /// Validate that exceeding the buffer memory limit prevents the ingester
+    /// from accepting new writes with a retryable error.
+    #[tokio::test]
+    async fn test_rpc_write_memory_limit() {
+        let mock = Arc::new(MockDmlSink::default().with_apply_return(vec![Ok(()), Ok(())]));
+        let timestamp = Arc::new(TimestampOracle::new(0));
+
+        let ingest_state = Arc::new(IngestState::default());
+
+        let handler = RpcWrite::new(Arc::clone(&mock), timestamp, Arc::clone(&ingest_state));
+
+        let req = proto::WriteRequest {
+            payload: Some(DatabaseBatch {
+                database_id: NAMESPACE_ID.get(),
+                partition_key: PARTITION_KEY.to_string(),
+                table_batches: vec![TableBatch {
+                    table_id: 42,
+                    columns: vec![Column {
+                        column_name: "time".to_string(),
+                        semantic_type: SemanticType::Time.into(),
+                        values: Some(Values {
+                            i64_values: vec![4242],
+                            f64_values: vec![],
+                            u64_values: vec![],
+                            string_values: vec![],
+                            bool_values: vec![],
+                            bytes_values: vec![],
+                            packed_string_values: None,
+                            interned_string_values: None,
+                        }),
+                        null_mask: vec![0],
+                    }],
+                    row_count: 1,
+                }],
+            }),
+        };
+
+        handler
+            .write(Request::new(req.clone()))
+            .await
+            .expect("write should succeed");
+
+        ingest_state.set(IngestStateError::MemoryLimit);
+
+        let err = handler
+            .write(Request::new(req))
+            .await
+            .expect_err("write should fail");
+
+        // Validate the error code returned to the user.
+        assert_eq!(err.code(), Code::ResourceExhausted);
+
+        // One write should have been passed through to the DML sinks.
+        assert_matches!(*mock.get_calls(), [DmlOperation::Write(_)]);
+    }
+
     /// Validate that the ingester being marked as stopping prevents the
     /// ingester from accepting new writes.
     #[tokio::test]
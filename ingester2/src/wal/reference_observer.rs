@@ -0,0 +1,130 @@
+use std::sync::Arc;
+
+use data_types::SequenceNumber;
+use parking_lot::{Mutex, MutexGuard};
+
+use crate::buffer_tree::{partition::PartitionData, post_write::PostWriteObserver};
+
+use super::reference_tracker::WalReferenceHandle;
+
+/// A [`PostWriteObserver`] decorator that records a WAL reference for each
+/// partition a write is buffered into, before passing the partition to the
+/// inner [`PostWriteObserver`].
+///
+/// The reference is taken while the partition lock is held, and therefore
+/// always before the partition can be marked as persisting and release it.
+#[derive(Debug)]
+pub(crate) struct WalReferenceObserver<T> {
+    inner: T,
+    wal_reference_handle: WalReferenceHandle,
+}
+
+impl<T> WalReferenceObserver<T> {
+    /// Initialise a new [`WalReferenceObserver`] recording references with
+    /// `wal_reference_handle`, and passing partitions through to `inner`.
+    pub(crate) fn new(inner: T, wal_reference_handle: WalReferenceHandle) -> Self {
+        Self {
+            inner,
+            wal_reference_handle,
+        }
+    }
+}
+
+impl<T> PostWriteObserver for WalReferenceObserver<T>
+where
+    T: PostWriteObserver,
+{
+    #[inline(always)]
+    fn observe(
+        &self,
+        partition: Arc<Mutex<PartitionData>>,
+        guard: MutexGuard<'_, PartitionData>,
+        sequence_number: SequenceNumber,
+    ) {
+        self.wal_reference_handle
+            .enqueue_buffered_write(sequence_number);
+        self.inner.observe(partition, guard, sequence_number)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use async_trait::async_trait;
+    use data_types::{
+        sequence_number_set::SequenceNumberSet, NamespaceId, PartitionId, PartitionKey, ShardId,
+        TableId,
+    };
+    use mutable_batch_lp::test_helpers::lp_to_mutable_batch;
+
+    use crate::{
+        buffer_tree::{partition::SortKeyState, post_write::mock::MockPostWriteObserver},
+        deferred_load::DeferredLoad,
+        persist::completion_observer::PersistCompletionObserver,
+        wal::reference_tracker::SegmentDeleter,
+    };
+    use wal::SegmentId;
+
+    use super::*;
+
+    #[derive(Debug)]
+    struct NopDeleter;
+
+    #[async_trait]
+    impl SegmentDeleter for NopDeleter {
+        async fn delete(&self, _id: SegmentId) {}
+    }
+
+    fn new_partition(id: i64) -> Arc<Mutex<PartitionData>> {
+        Arc::new(Mutex::new(PartitionData::new(
+            PartitionId::new(id),
+            PartitionKey::from("bananas"),
+            NamespaceId::new(1),
+            Arc::new(DeferredLoad::new(Duration::from_secs(1), async {
+                "platanos".into()
+            })),
+            TableId::new(2),
+            Arc::new(DeferredLoad::new(Duration::from_secs(1), async {
+                "bananas".into()
+            })),
+            SortKeyState::Provided(None),
+            ShardId::new(3),
+        )))
+    }
+
+    /// A write buffered into two partitions is referenced until both of them
+    /// persisted it.
+    #[tokio::test]
+    async fn test_reference_per_partition() {
+        let (handle, actor) = WalReferenceHandle::new();
+        tokio::spawn(actor.run(NopDeleter));
+
+        let observer = WalReferenceObserver::new(MockPostWriteObserver::default(), handle.clone());
+
+        let n = SequenceNumber::new(42);
+        let mut set = SequenceNumberSet::default();
+        set.add(n);
+
+        handle.enqueue_write(n);
+        for id in [1, 2] {
+            let partition = new_partition(id);
+            let mut guard = partition.lock();
+            let mb = lp_to_mutable_batch(r#"bananas,city=London people=2 10"#).1;
+            guard.buffer_write(mb, n).unwrap();
+            observer.observe(Arc::clone(&partition), guard, n);
+        }
+        handle.enqueue_applied_write(n);
+        handle.enqueue_rotated_file(SegmentId::new(1), set.clone());
+
+        // Both partitions were passed through to the inner observer.
+        assert_eq!(observer.inner.calls().len(), 2);
+
+        // The first persisted partition does not release the write.
+        handle.persist_complete(&set);
+        assert_eq!(handle.unpersisted().await.iter().collect::<Vec<_>>(), [n]);
+
+        handle.persist_complete(&set);
+        assert!(handle.unpersisted().await.is_empty());
+    }
+}
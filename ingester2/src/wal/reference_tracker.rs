@@ -0,0 +1,453 @@
+//! Reference counting of WAL segment files by the writes they contain.
+//!
+//! A closed WAL segment may only be deleted once every write it contains has
+//! been persisted (or is otherwise no longer buffered). Rather than waiting for
+//! in-flight writes to settle and persisting everything at rotation time, the
+//! [`WalReferenceActor`] tracks the [`SequenceNumberSet`] of unpersisted writes
+//! in each closed segment, removes the writes released by each completed
+//! persist job, and deletes a segment once its set becomes empty.
+//!
+//! A single write may span several tables, each buffered in (and persisted
+//! from) its own partition. The actor therefore counts the partitions that
+//! reference each write, and only releases the write once every one of them
+//! has persisted it. While a write is being applied to the buffer, the write
+//! itself holds an additional reference, so that a partition persisting it
+//! before the remaining partitions are buffered does not release it early.
+//!
+//! Because this is driven purely by sequence numbers, it is independent of the
+//! order in which writes are buffered, persisted or rotated - a persist job
+//! may complete before the segment containing its writes is closed, and hot
+//! partition persists that span several segments release their references in
+//! exactly the same way as persists at rotation time.
+
+use std::collections::{BTreeMap, HashMap};
+
+use async_trait::async_trait;
+use data_types::{sequence_number_set::SequenceNumberSet, SequenceNumber};
+use observability_deps::tracing::*;
+use tokio::sync::{mpsc, oneshot};
+use wal::SegmentId;
+
+use crate::persist::completion_observer::PersistCompletionObserver;
+
+/// Deletes WAL segment files once they are no longer referenced.
+#[async_trait]
+pub(crate) trait SegmentDeleter: Send + Sync + std::fmt::Debug {
+    /// Delete the segment identified by `id`.
+    async fn delete(&self, id: SegmentId);
+}
+
+#[async_trait]
+impl SegmentDeleter for std::sync::Arc<wal::Wal> {
+    async fn delete(&self, id: SegmentId) {
+        // The graceful shutdown handler deletes all closed segments itself, so
+        // a failure here is not necessarily fatal.
+        match wal::Wal::delete(self, id).await {
+            Ok(()) => info!(segment_id = %id, "dropped fully persisted wal segment"),
+            Err(error) => warn!(%error, segment_id = %id, "failed to drop wal segment"),
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Event {
+    /// A write is about to be applied to the buffer, and is referenced until
+    /// it has been applied.
+    Write(SequenceNumber),
+    /// A partition buffered a write, and references it until persisted.
+    Buffered(SequenceNumber),
+    /// A write was applied to the buffer, releasing its own reference.
+    Applied(SequenceNumber),
+    /// A segment was closed, containing the specified writes.
+    Rotated(SegmentId, SequenceNumberSet),
+    /// A partition persisted the specified writes, releasing its reference to
+    /// each of them.
+    Persisted(SequenceNumberSet),
+    /// Return the writes of all closed segments that are not yet persisted.
+    Unpersisted(oneshot::Sender<SequenceNumberSet>),
+}
+
+/// A cheaply cloneable handle to submit events to a [`WalReferenceActor`].
+///
+/// Events are processed in submission order. Events submitted after the actor
+/// has stopped are dropped.
+#[derive(Debug, Clone)]
+pub(crate) struct WalReferenceHandle {
+    tx: mpsc::UnboundedSender<Event>,
+}
+
+impl WalReferenceHandle {
+    /// Initialise a new [`WalReferenceHandle`] and the [`WalReferenceActor`]
+    /// it submits events to.
+    ///
+    /// Events are buffered until the actor is started with
+    /// [`WalReferenceActor::run()`].
+    pub(crate) fn new() -> (Self, WalReferenceActor) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        (Self { tx }, WalReferenceActor::new(rx))
+    }
+
+    /// Track the write `n`, which is about to be applied to the buffer.
+    ///
+    /// This MUST be called before the write is applied, so that the write is
+    /// tracked before any of its partitions can be persisted, and MUST be
+    /// followed by a call to [`Self::enqueue_applied_write()`] once applying
+    /// the write completed (successfully or not). Buffered and persisted writes
+    /// that were never tracked (such as replayed writes) are ignored.
+    pub(crate) fn enqueue_write(&self, n: SequenceNumber) {
+        let _ = self.tx.send(Event::Write(n));
+    }
+
+    /// Record that a partition buffered the tracked write `n`, and references
+    /// it until that partition is persisted.
+    ///
+    /// This MUST be called before the partition can be persisted.
+    pub(crate) fn enqueue_buffered_write(&self, n: SequenceNumber) {
+        let _ = self.tx.send(Event::Buffered(n));
+    }
+
+    /// Release the reference held by the write `n` while it was applied to
+    /// the buffer.
+    ///
+    /// Once all the partitions it was buffered into have persisted it (or if
+    /// it was not buffered at all), the write no longer references its
+    /// segment.
+    pub(crate) fn enqueue_applied_write(&self, n: SequenceNumber) {
+        let _ = self.tx.send(Event::Applied(n));
+    }
+
+    /// Track the closed segment `id`, containing the writes in
+    /// `sequence_numbers`.
+    pub(crate) fn enqueue_rotated_file(&self, id: SegmentId, sequence_numbers: SequenceNumberSet) {
+        let _ = self.tx.send(Event::Rotated(id, sequence_numbers));
+    }
+
+    /// Return the set of writes in closed segments that have not yet been
+    /// persisted.
+    ///
+    /// The result reflects all events submitted through this handle before
+    /// this call. If the actor has stopped, an empty set is returned.
+    pub(crate) async fn unpersisted(&self) -> SequenceNumberSet {
+        let (tx, rx) = oneshot::channel();
+        if self.tx.send(Event::Unpersisted(tx)).is_err() {
+            return SequenceNumberSet::default();
+        }
+        rx.await.unwrap_or_default()
+    }
+}
+
+impl PersistCompletionObserver for WalReferenceHandle {
+    fn persist_complete(&self, sequence_numbers: &SequenceNumberSet) {
+        let _ = self.tx.send(Event::Persisted(sequence_numbers.clone()));
+    }
+}
+
+/// Tracks the unpersisted writes of closed WAL segments, and deletes segments
+/// that no longer contain any.
+#[derive(Debug)]
+pub(crate) struct WalReferenceActor {
+    rx: mpsc::UnboundedReceiver<Event>,
+
+    /// The number of references (partitions, and the write itself while it
+    /// is being applied) still held to each tracked write.
+    references: HashMap<SequenceNumber, usize>,
+
+    /// The writes of each closed segment that are not yet released.
+    segments: BTreeMap<SegmentId, SequenceNumberSet>,
+
+    /// Released writes that were not (yet) part of any closed segment.
+    ///
+    /// A persist job can complete for writes still in the open segment; those
+    /// writes are retained here, and subtracted from the segment once it is
+    /// closed.
+    released: SequenceNumberSet,
+}
+
+impl WalReferenceActor {
+    fn new(rx: mpsc::UnboundedReceiver<Event>) -> Self {
+        Self {
+            rx,
+            references: HashMap::default(),
+            segments: BTreeMap::default(),
+            released: SequenceNumberSet::default(),
+        }
+    }
+
+    /// Process events until all [`WalReferenceHandle`] instances are dropped,
+    /// deleting unreferenced segments with `deleter`.
+    pub(crate) async fn run<T>(mut self, deleter: T)
+    where
+        T: SegmentDeleter,
+    {
+        while let Some(event) = self.rx.recv().await {
+            match event {
+                Event::Write(n) => {
+                    *self.references.entry(n).or_default() += 1;
+                }
+                Event::Buffered(n) => {
+                    // The write is still referenced by itself until applied,
+                    // so an untracked write is one that was never tracked.
+                    if let Some(references) = self.references.get_mut(&n) {
+                        *references += 1;
+                    }
+                }
+                Event::Applied(n) => {
+                    let set = self.release(std::iter::once(n));
+                    self.handle_released(set, &deleter).await;
+                }
+                Event::Rotated(id, set) => self.handle_rotated(id, set, &deleter).await,
+                Event::Persisted(set) => {
+                    let set = self.release(set.iter());
+                    self.handle_released(set, &deleter).await;
+                }
+                Event::Unpersisted(tx) => {
+                    let mut set = SequenceNumberSet::default();
+                    for s in self.segments.values() {
+                        set.add_set(s);
+                    }
+                    let _ = tx.send(set);
+                }
+            }
+        }
+
+        debug!("stopping wal reference actor");
+    }
+
+    /// Drop a reference to each of the writes in `writes`, returning the
+    /// writes that are no longer referenced.
+    ///
+    /// Writes that are not tracked are ignored.
+    fn release(&mut self, writes: impl Iterator<Item = SequenceNumber>) -> SequenceNumberSet {
+        let mut released = SequenceNumberSet::default();
+
+        for n in writes {
+            let Some(references) = self.references.get_mut(&n) else {
+                continue;
+            };
+            *references -= 1;
+            if *references == 0 {
+                self.references.remove(&n);
+                released.add(n);
+            }
+        }
+
+        released
+    }
+
+    async fn handle_rotated<T>(&mut self, id: SegmentId, mut set: SequenceNumberSet, deleter: &T)
+    where
+        T: SegmentDeleter,
+    {
+        // Writes released before the segment was closed are not referenced by
+        // it, and will not be seen in any other segment.
+        let all = set.clone();
+        set.remove_set(&self.released);
+        self.released.remove_set(&all);
+
+        debug!(
+            segment_id = %id,
+            n_writes = all.len(),
+            n_unpersisted = set.len(),
+            "tracking closed wal segment"
+        );
+
+        if set.is_empty() {
+            deleter.delete(id).await;
+            return;
+        }
+
+        self.segments.insert(id, set);
+    }
+
+    async fn handle_released<T>(&mut self, set: SequenceNumberSet, deleter: &T)
+    where
+        T: SegmentDeleter,
+    {
+        if set.is_empty() {
+            return;
+        }
+
+        let mut unmatched = set.clone();
+        let mut unreferenced = vec![];
+
+        for (id, segment) in self.segments.iter_mut() {
+            if !segment.intersects(&set) {
+                continue;
+            }
+            unmatched.remove_set(segment);
+            segment.remove_set(&set);
+            if segment.is_empty() {
+                unreferenced.push(*id);
+            }
+        }
+
+        // Retain the writes not yet in a closed segment until it is rotated.
+        self.released.add_set(&unmatched);
+
+        for id in unreferenced {
+            self.segments.remove(&id);
+            deleter.delete(id).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use parking_lot::Mutex;
+    use test_helpers::timeout::FutureTimeout;
+
+    use super::*;
+
+    #[derive(Debug, Default)]
+    struct MockDeleter {
+        deleted: Mutex<Vec<SegmentId>>,
+    }
+
+    #[async_trait]
+    impl SegmentDeleter for Arc<MockDeleter> {
+        async fn delete(&self, id: SegmentId) {
+            self.deleted.lock().push(id);
+        }
+    }
+
+    fn set(values: &[i64]) -> SequenceNumberSet {
+        let mut set = SequenceNumberSet::default();
+        for v in values {
+            set.add(SequenceNumber::new(*v));
+        }
+        set
+    }
+
+    /// Wait for all events submitted so far to have been processed.
+    async fn sync(handle: &WalReferenceHandle) -> Vec<SequenceNumber> {
+        handle
+            .unpersisted()
+            .with_timeout_panic(std::time::Duration::from_secs(5))
+            .await
+            .iter()
+            .collect()
+    }
+
+    /// Track each write in `values` as buffered into a single partition.
+    fn enqueue_writes(handle: &WalReferenceHandle, values: &[i64]) {
+        for v in values {
+            let n = SequenceNumber::new(*v);
+            handle.enqueue_write(n);
+            handle.enqueue_buffered_write(n);
+            handle.enqueue_applied_write(n);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reference_tracking() {
+        let deleter = Arc::new(MockDeleter::default());
+        let (handle, actor) = WalReferenceHandle::new();
+        tokio::spawn(actor.run(Arc::clone(&deleter)));
+
+        // Two segments, sharing no writes.
+        enqueue_writes(&handle, &[1, 2, 3, 4, 5]);
+        handle.enqueue_rotated_file(SegmentId::new(1), set(&[1, 2, 3]));
+        handle.enqueue_rotated_file(SegmentId::new(2), set(&[4, 5]));
+        assert_eq!(
+            sync(&handle).await,
+            set(&[1, 2, 3, 4, 5]).iter().collect::<Vec<_>>()
+        );
+
+        // A persist spanning both segments releases references in each.
+        handle.persist_complete(&set(&[1, 4]));
+        assert_eq!(
+            sync(&handle).await,
+            set(&[2, 3, 5]).iter().collect::<Vec<_>>()
+        );
+        assert!(deleter.deleted.lock().is_empty());
+
+        // Fully persisting segment 2 deletes it, leaving segment 1.
+        handle.persist_complete(&set(&[5]));
+        assert_eq!(sync(&handle).await, set(&[2, 3]).iter().collect::<Vec<_>>());
+        assert_eq!(*deleter.deleted.lock(), [SegmentId::new(2)]);
+
+        handle.persist_complete(&set(&[2, 3]));
+        assert!(sync(&handle).await.is_empty());
+        assert_eq!(
+            *deleter.deleted.lock(),
+            [SegmentId::new(2), SegmentId::new(1)]
+        );
+    }
+
+    /// Writes may be persisted before the segment containing them is closed.
+    #[tokio::test]
+    async fn test_persisted_before_rotation() {
+        let deleter = Arc::new(MockDeleter::default());
+        let (handle, actor) = WalReferenceHandle::new();
+        tokio::spawn(actor.run(Arc::clone(&deleter)));
+
+        enqueue_writes(&handle, &[1, 2, 3, 4]);
+        handle.persist_complete(&set(&[1, 2]));
+        handle.enqueue_rotated_file(SegmentId::new(1), set(&[1, 2, 3]));
+        assert_eq!(sync(&handle).await, [SequenceNumber::new(3)]);
+
+        // A segment that is entirely persisted at rotation time is deleted
+        // immediately.
+        handle.persist_complete(&set(&[4]));
+        handle.enqueue_rotated_file(SegmentId::new(2), set(&[4]));
+        assert_eq!(sync(&handle).await, [SequenceNumber::new(3)]);
+        assert_eq!(*deleter.deleted.lock(), [SegmentId::new(2)]);
+
+        handle.persist_complete(&set(&[3]));
+        assert!(sync(&handle).await.is_empty());
+        assert_eq!(
+            *deleter.deleted.lock(),
+            [SegmentId::new(2), SegmentId::new(1)]
+        );
+    }
+
+    /// A write spanning several partitions keeps its segment until every
+    /// partition persisted it.
+    #[tokio::test]
+    async fn test_multi_partition_write() {
+        let deleter = Arc::new(MockDeleter::default());
+        let (handle, actor) = WalReferenceHandle::new();
+        tokio::spawn(actor.run(Arc::clone(&deleter)));
+
+        // Write 1 is buffered into two partitions, the first of which is
+        // persisted before the second is buffered.
+        handle.enqueue_write(SequenceNumber::new(1));
+        handle.enqueue_buffered_write(SequenceNumber::new(1));
+        handle.persist_complete(&set(&[1]));
+        handle.enqueue_buffered_write(SequenceNumber::new(1));
+        handle.enqueue_applied_write(SequenceNumber::new(1));
+        handle.enqueue_rotated_file(SegmentId::new(1), set(&[1]));
+
+        // The first partition persisting does not release the segment.
+        assert_eq!(sync(&handle).await, [SequenceNumber::new(1)]);
+        assert!(deleter.deleted.lock().is_empty());
+
+        handle.persist_complete(&set(&[1]));
+        assert!(sync(&handle).await.is_empty());
+        assert_eq!(*deleter.deleted.lock(), [SegmentId::new(1)]);
+    }
+
+    /// Writes that are not tracked (such as writes replayed from the WAL) are
+    /// ignored when buffered or persisted, and writes that are not buffered
+    /// anywhere do not reference their segment once applied.
+    #[tokio::test]
+    async fn test_untracked_writes() {
+        let deleter = Arc::new(MockDeleter::default());
+        let (handle, actor) = WalReferenceHandle::new();
+        tokio::spawn(actor.run(Arc::clone(&deleter)));
+
+        handle.enqueue_buffered_write(SequenceNumber::new(1));
+        handle.persist_complete(&set(&[1]));
+        enqueue_writes(&handle, &[1]);
+        handle.enqueue_write(SequenceNumber::new(2));
+        handle.enqueue_applied_write(SequenceNumber::new(2));
+        handle.enqueue_rotated_file(SegmentId::new(1), set(&[1, 2]));
+        assert_eq!(sync(&handle).await, [SequenceNumber::new(1)]);
+
+        handle.persist_complete(&set(&[1]));
+        assert!(sync(&handle).await.is_empty());
+        assert_eq!(*deleter.deleted.lock(), [SegmentId::new(1)]);
+    }
+}
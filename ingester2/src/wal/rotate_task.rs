@@ -6,12 +6,20 @@ use crate::{
     persist::{drain_buffer::persist_partitions, queue::PersistQueue},
 };
 
-/// Rotate the `wal` segment file every `period` duration of time.
+use super::reference_tracker::WalReferenceHandle;
+
+/// Rotate the `wal` segment file every `period` duration of time, persisting
+/// the partitions that hold writes from closed segments.
+///
+/// Closed segments are not deleted by this task - they are handed to the
+/// [`WalReferenceHandle`], which deletes each segment once all the writes it
+/// contains have been persisted.
 pub(crate) async fn periodic_rotation<T, P>(
     wal: Arc<wal::Wal>,
     period: Duration,
     buffer: T,
     persist: P,
+    wal_reference_handle: WalReferenceHandle,
 ) where
     T: PartitionIter + Sync,
     P: PersistQueue + Clone,
@@ -30,55 +38,26 @@ pub(crate) async fn periodic_rotation<T, P>(
         debug!(
             closed_id = %stats.id(),
             segment_bytes = stats.size(),
+            n_ops = stats.sequence_numbers().len(),
             "rotated wal"
         );
 
-        // TEMPORARY HACK: wait 5 seconds for in-flight writes to the old WAL
-        // segment to complete before draining the partitions.
-        //
-        // This can occur because writes to the WAL & buffer tree are not atomic
-        // (avoiding a serialising mutex in the write path).
-        //
-        // A flawed solution would be to have this code read the current
-        // SequenceNumber after rotation, and then wait until at least that
-        // sequence number has been buffered in the BufferTree. This may work in
-        // most cases, but is racy / not deterministic - writes are not ordered,
-        // so sequence number 5 might be buffered before sequence number 1.
-        //
-        // As a temporary hack, wait 5 seconds for in-flight writes to complete
-        // (which should be more than enough time) before proceeding under the
-        // assumption that they have indeed completed, and all writes from the
-        // previous WAL segment are now buffered. Because they're buffered, the
-        // persist operation performed next will persist all the writes that
-        // were in the previous WAL segment, and therefore at the end of the
-        // persist operation the WAL segment can be dropped.
-        //
-        // The potential downside of this hack is that in the very unlikely
-        // situation that an in-flight write has not completed before the
-        // persist operation starts (after the 5 second sleep) and the WAL entry
-        // for it is dropped - we then reduce the durability of that write until
-        // it is persisted next time, or it is lost after an ingester crash
-        // before the next rotation.
-        //
-        // In the future, a proper fix will be to keep the set of sequence
-        // numbers wrote to each partition buffer, and each WAL segment as a
-        // bitmap, and after persistence submit the partition's bitmap to the
-        // WAL for it to do a set difference to derive the remaining sequence
-        // IDs, and therefore number of references to the WAL segment. Once the
-        // set of remaining IDs is empty (all data is persisted), the segment is
-        // safe to delete. This content-addressed reference counting technique
-        // has the added advantage of working even with parallel / out-of-order
-        // / hot partition persists that span WAL segments, and means there's no
-        // special code path between "hot partition persist" and "wal rotation
-        // persist" - it all works the same way!
-        //
-        //      https://github.com/influxdata/influxdb_iox/issues/6566
-        //
-        // TODO: this properly as described above.
+        // Track the references to the closed segment, releasing any writes
+        // that have already been persisted (for example, by a hot partition
+        // persist).
+        wal_reference_handle.enqueue_rotated_file(stats.id(), stats.sequence_numbers().clone());
 
-        tokio::time::sleep(Duration::from_secs(5)).await;
+        // Read the set of writes in all closed segments that are not yet
+        // persisted.
+        //
+        // Writes to the WAL & buffer tree are not atomic (avoiding a
+        // serialising mutex in the write path), so a write in the closed
+        // segment may not have been buffered yet. This is not a problem: the
+        // segment remains referenced by that write, is included in this set on
+        // the next rotation, and is deleted only once the write is persisted.
+        let unpersisted = wal_reference_handle.unpersisted().await;
 
-        // Drain the BufferTree of partition data and persist each one.
+        // Persist only the partitions that hold writes from closed segments.
         //
         // Writes that landed into the partition buffer after the rotation but
         // before the partition data is read will be included in the parquet
@@ -92,22 +71,132 @@ pub(crate) async fn periodic_rotation<T, P>(
         // - a small price to pay for not having to block ingest while the WAL
         // is rotated, all outstanding writes + queries complete, and all then
         // partitions are marked as persisting.
-        persist_partitions(buffer.partition_iter(), &persist).await;
+        let partitions = buffer
+            .partition_iter()
+            .filter(|p| {
+                p.lock()
+                    .buffered_sequence_numbers()
+                    .intersects(&unpersisted)
+            })
+            .collect::<Vec<_>>();
+
+        let n = persist_partitions(partitions.into_iter(), &persist).await;
 
         debug!(
             closed_id = %stats.id(),
+            n_partitions = n,
             "partitions persisted"
         );
+    }
+}
 
-        wal.delete(stats.id())
-            .await
-            .expect("failed to drop wal segment");
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
 
-        info!(
-            closed_id = %stats.id(),
-            "dropped persisted wal segment"
+    use data_types::{
+        sequence_number_set::SequenceNumberSet, NamespaceId, PartitionId, PartitionKey,
+        SequenceNumber, ShardId, TableId,
+    };
+    use dml::DmlOperation;
+    use mutable_batch_lp::test_helpers::lp_to_mutable_batch;
+    use parking_lot::Mutex;
+    use test_helpers::timeout::FutureTimeout;
+    use wal::Wal;
+
+    use super::*;
+    use crate::{
+        buffer_tree::partition::{PartitionData, SortKeyState},
+        deferred_load::DeferredLoad,
+        persist::{completion_observer::PersistCompletionObserver, queue::mock::MockPersistQueue},
+        test_util::make_write_op,
+        wal::traits::WalAppender,
+    };
+
+    fn partition_with_write(id: i64, n: SequenceNumber) -> Arc<Mutex<PartitionData>> {
+        let mut p = PartitionData::new(
+            PartitionId::new(id),
+            PartitionKey::from("bananas"),
+            NamespaceId::new(1),
+            Arc::new(DeferredLoad::new(Duration::from_secs(1), async {
+                "platanos".into()
+            })),
+            TableId::new(2),
+            Arc::new(DeferredLoad::new(Duration::from_secs(1), async {
+                "bananas".into()
+            })),
+            SortKeyState::Provided(None),
+            ShardId::new(3),
         );
+        let mb = lp_to_mutable_batch(r#"bananas,city=London people=2 10"#).1;
+        p.buffer_write(mb, n).unwrap();
+        Arc::new(Mutex::new(p))
     }
-}
 
-// TODO(test): rotate task
+    #[tokio::test]
+    async fn test_rotate_persists_referencing_partitions() {
+        let dir = tempfile::tempdir().unwrap();
+        let wal = Wal::new(dir.path()).await.unwrap();
+
+        // Write sequence number 1 to the WAL segment that will be rotated.
+        let op = make_write_op(
+            &PartitionKey::from("bananas"),
+            NamespaceId::new(1),
+            "bananas",
+            TableId::new(2),
+            1,
+            r#"bananas,city=London people=2 10"#,
+        );
+        wal.append(&DmlOperation::Write(op))
+            .changed()
+            .await
+            .unwrap();
+
+        // Partition 1 references the rotated segment, partition 2 holds only
+        // a write that has not been rotated.
+        let p1 = partition_with_write(1, SequenceNumber::new(1));
+        let p2 = partition_with_write(2, SequenceNumber::new(2));
+
+        let persist = Arc::new(MockPersistQueue::default());
+        let (handle, actor) = WalReferenceHandle::new();
+        handle.enqueue_write(SequenceNumber::new(1));
+        handle.enqueue_buffered_write(SequenceNumber::new(1));
+        handle.enqueue_applied_write(SequenceNumber::new(1));
+        tokio::spawn(actor.run(Arc::clone(&wal)));
+
+        let task = tokio::spawn(periodic_rotation(
+            Arc::clone(&wal),
+            Duration::from_millis(10),
+            vec![Arc::clone(&p1), Arc::clone(&p2)],
+            Arc::clone(&persist),
+            handle.clone(),
+        ));
+
+        // Wait for the first rotation to persist partition 1.
+        async {
+            while persist.calls().is_empty() {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        }
+        .with_timeout_panic(Duration::from_secs(5))
+        .await;
+        task.abort();
+
+        let calls = persist.calls();
+        assert!(calls
+            .iter()
+            .all(|p| p.lock().partition_id() == PartitionId::new(1)));
+
+        // The closed segment is retained until the write is persisted.
+        assert!(!wal.closed_segments().is_empty());
+
+        let mut persisted = SequenceNumberSet::default();
+        persisted.add(SequenceNumber::new(1));
+        handle.persist_complete(&persisted);
+        assert!(handle.unpersisted().await.is_empty());
+        assert!(wal
+            .closed_segments()
+            .iter()
+            .all(|s| !s.sequence_numbers().contains(SequenceNumber::new(1))));
+    }
+}
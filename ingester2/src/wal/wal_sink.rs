@@ -1,4 +1,5 @@
 use async_trait::async_trait;
+use data_types::SequenceNumber;
 use dml::DmlOperation;
 use generated_types::influxdata::iox::wal::v1::sequenced_wal_op::Op;
 use mutable_batch_pb::encode::encode_write;
@@ -8,7 +9,7 @@ use wal::{SequencedWalOp, WriteResult};
 
 use crate::dml_sink::{DmlError, DmlSink};
 
-use super::traits::WalAppender;
+use super::{reference_tracker::WalReferenceHandle, traits::WalAppender};
 
 /// A [`DmlSink`] decorator that ensures any [`DmlOperation`] is committed to
 /// the write-ahead log before passing the operation to the inner [`DmlSink`].
@@ -20,13 +21,20 @@ pub(crate) struct WalSink<T, W = wal::Wal> {
 
     /// The write-ahead log implementation.
     wal: W,
+
+    /// A handle to track the WAL references of each op while it is applied.
+    wal_reference_handle: WalReferenceHandle,
 }
 
 impl<T, W> WalSink<T, W> {
     /// Initialise a new [`WalSink`] that appends [`DmlOperation`] to `W` and
     /// on success, passes the op through to `T`.
-    pub(crate) fn new(inner: T, wal: W) -> Self {
-        Self { inner, wal }
+    pub(crate) fn new(inner: T, wal: W, wal_reference_handle: WalReferenceHandle) -> Self {
+        Self {
+            inner,
+            wal,
+            wal_reference_handle,
+        }
     }
 }
 
@@ -50,11 +58,18 @@ where
         // This can happen If the caller stops polling just after the WAL commit
         // future completes and before the inner DmlSink call returns Ready.
 
+        let sequence_number = op
+            .meta()
+            .sequence()
+            .expect("committing unsequenced dml operation to wal")
+            .sequence_number;
+
         // Append the operation to the WAL
         let mut write_result = self.wal.append(&op);
 
-        // Pass it to the inner handler while we wait for the write to be made durable
-        self.inner.apply(op).await.map_err(Into::into)?;
+        // Pass it to the inner handler while we wait for the write to be made
+        // durable.
+        self.apply_inner(sequence_number, op).await?;
 
         // wait for the write to be durable before returning
         write_result
@@ -70,6 +85,33 @@ where
     }
 }
 
+impl<T, W> WalSink<T, W>
+where
+    T: DmlSink,
+{
+    /// Apply `op` to the inner [`DmlSink`], tracking it as referencing its WAL
+    /// segment while it is applied.
+    ///
+    /// Each partition the op is buffered into takes its own reference to the
+    /// op (see [`WalReferenceObserver`]), so once the op has been applied,
+    /// successfully or not, only the partitions that buffered it keep the
+    /// segment from being deleted.
+    ///
+    /// [`WalReferenceObserver`]: super::reference_observer::WalReferenceObserver
+    async fn apply_inner(
+        &self,
+        sequence_number: SequenceNumber,
+        op: DmlOperation,
+    ) -> Result<(), DmlError> {
+        self.wal_reference_handle.enqueue_write(sequence_number);
+        let res = self.inner.apply(op).await;
+        self.wal_reference_handle
+            .enqueue_applied_write(sequence_number);
+
+        res.map_err(Into::into)
+    }
+}
+
 impl WalAppender for Arc<wal::Wal> {
     fn append(&self, op: &DmlOperation) -> Receiver<Option<WriteResult>> {
         let sequence_number = op
@@ -99,9 +141,13 @@ mod tests {
 
     use assert_matches::assert_matches;
     use data_types::{NamespaceId, PartitionKey, TableId};
+    use dml::DmlWrite;
     use wal::Wal;
 
-    use crate::{dml_sink::mock_sink::MockDmlSink, test_util::make_write_op};
+    use crate::{
+        dml_sink::mock_sink::MockDmlSink, persist::completion_observer::PersistCompletionObserver,
+        test_util::make_write_op,
+    };
 
     use super::*;
 
@@ -131,7 +177,8 @@ mod tests {
                 .await
                 .expect("failed to initialise WAL");
 
-            let wal_sink = WalSink::new(Arc::clone(&inner), wal);
+            let (handle, _actor) = WalReferenceHandle::new();
+            let wal_sink = WalSink::new(Arc::clone(&inner), wal, handle);
 
             // Apply the op through the decorator
             wal_sink
@@ -175,4 +222,146 @@ mod tests {
 
         assert_eq!(want, *payload);
     }
+
+    /// An op that is added to the WAL but fails to buffer does not keep the
+    /// WAL segment from being deleted.
+    #[tokio::test]
+    async fn test_unbuffered_write_releases_segment() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let op = make_write_op(
+            &PartitionKey::from("p1"),
+            NAMESPACE_ID,
+            TABLE_NAME,
+            TABLE_ID,
+            42,
+            r#"bananas,region=Madrid temp=35 4242424242"#,
+        );
+
+        let inner = Arc::new(
+            MockDmlSink::default().with_apply_return(vec![Err(DmlError::Wal("bang".to_string()))]),
+        );
+        let wal = Wal::new(dir.path())
+            .await
+            .expect("failed to initialise WAL");
+
+        let (handle, actor) = WalReferenceHandle::new();
+        tokio::spawn(actor.run(Arc::clone(&wal)));
+
+        let wal_sink = WalSink::new(Arc::clone(&inner), Arc::clone(&wal), handle.clone());
+        wal_sink
+            .apply(DmlOperation::Write(op.clone()))
+            .await
+            .expect_err("inner sink error should be returned");
+
+        // Wait for the op to be flushed to the open segment by waiting for a
+        // subsequent flush to complete.
+        wal.append(&DmlOperation::Write(op))
+            .changed()
+            .await
+            .expect("unable to get WAL write result");
+
+        let closed = wal.rotate().expect("failed to rotate WAL");
+        assert!(closed.sequence_numbers().contains(SequenceNumber::new(42)));
+        handle.enqueue_rotated_file(closed.id(), closed.sequence_numbers().clone());
+
+        // The segment holds no unpersisted, buffered writes and is deleted.
+        assert!(handle.unpersisted().await.is_empty());
+        assert!(wal.closed_segments().is_empty());
+    }
+
+    /// A [`DmlSink`] that buffers a write into a single partition before
+    /// failing, as if buffering the remaining tables failed.
+    #[derive(Debug)]
+    struct PartialBufferSink {
+        handle: WalReferenceHandle,
+        calls: parking_lot::Mutex<Vec<DmlOperation>>,
+    }
+
+    #[async_trait]
+    impl DmlSink for PartialBufferSink {
+        type Error = DmlError;
+
+        async fn apply(&self, op: DmlOperation) -> Result<(), Self::Error> {
+            let sequence_number = op.meta().sequence().unwrap().sequence_number;
+            self.handle.enqueue_buffered_write(sequence_number);
+            self.calls.lock().push(op);
+            Err(DmlError::Wal("bang".to_string()))
+        }
+    }
+
+    /// A write spanning several tables that fails to buffer part way through
+    /// keeps the WAL segment until the buffered partitions are persisted.
+    #[tokio::test]
+    async fn test_partially_buffered_write() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let a = make_write_op(
+            &PartitionKey::from("p1"),
+            NAMESPACE_ID,
+            TABLE_NAME,
+            TABLE_ID,
+            42,
+            r#"bananas,region=Madrid temp=35 4242424242"#,
+        );
+        let b = make_write_op(
+            &PartitionKey::from("p1"),
+            NAMESPACE_ID,
+            "platanos",
+            TableId::new(45),
+            42,
+            r#"platanos,region=Madrid temp=35 4242424242"#,
+        );
+        let meta = a.meta().clone();
+        let op = DmlWrite::new(
+            NAMESPACE_ID,
+            a.into_tables().chain(b.into_tables()).collect(),
+            PartitionKey::from("p1"),
+            meta,
+        );
+
+        let wal = Wal::new(dir.path())
+            .await
+            .expect("failed to initialise WAL");
+
+        let (handle, actor) = WalReferenceHandle::new();
+        tokio::spawn(actor.run(Arc::clone(&wal)));
+
+        let inner = PartialBufferSink {
+            handle: handle.clone(),
+            calls: Default::default(),
+        };
+        let wal_sink = WalSink::new(inner, Arc::clone(&wal), handle.clone());
+        wal_sink
+            .apply(DmlOperation::Write(op.clone()))
+            .await
+            .expect_err("inner sink error should be returned");
+
+        // The op is passed to the inner sink unmodified.
+        assert_matches!(&**wal_sink.inner.calls.lock(), [DmlOperation::Write(w)] => {
+            assert_eq!(w.table_count(), 2);
+        });
+
+        // Wait for the op to be flushed to the open segment by waiting for a
+        // subsequent flush to complete.
+        wal.append(&DmlOperation::Write(op))
+            .changed()
+            .await
+            .expect("unable to get WAL write result");
+
+        let closed = wal.rotate().expect("failed to rotate WAL");
+        handle.enqueue_rotated_file(closed.id(), closed.sequence_numbers().clone());
+
+        // The buffered partition still references the segment.
+        assert_eq!(
+            handle.unpersisted().await.iter().collect::<Vec<_>>(),
+            [SequenceNumber::new(42)]
+        );
+        assert_eq!(wal.closed_segments().len(), 1);
+
+        // Once it is persisted, the segment is deleted.
+        handle.persist_complete(closed.sequence_numbers());
+        assert!(handle.unpersisted().await.is_empty());
+        assert!(wal.closed_segments().is_empty());
+    }
 }
@@ -4,8 +4,8 @@ use arrow::datatypes::SchemaRef;
 /// Based on the "low level client" from IOx client:
 use arrow::{array::ArrayRef, datatypes::Schema, ipc, record_batch::RecordBatch};
 use arrow_flight::{
-    flight_service_client::FlightServiceClient, utils::flight_data_to_arrow_batch, FlightData,
-    FlightDescriptor, FlightInfo, HandshakeRequest, Ticket,
+    flight_service_client::FlightServiceClient, utils::flight_data_to_arrow_batch, Action,
+    FlightData, FlightDescriptor, FlightInfo, HandshakeRequest, Ticket,
 };
 use futures::ready;
 use futures_util::stream;
@@ -167,6 +167,27 @@ impl FlightClient {
         Ok(response)
     }
 
+    /// Make a `DoAction` call to the server with the provided
+    /// [`Action`], returning the body of each result sent back by
+    /// the server
+    pub async fn do_action(&mut self, action: Action) -> Result<Vec<Vec<u8>>> {
+        let request = self.make_request(action);
+
+        let mut response_stream = self
+            .inner
+            .do_action(request)
+            .await
+            .map_err(FlightError::Tonic)?
+            .into_inner();
+
+        let mut bodies = vec![];
+        while let Some(response) = response_stream.next().await {
+            let response = response.map_err(FlightError::Tonic)?;
+            bodies.push(response.body.to_vec());
+        }
+        Ok(bodies)
+    }
+
     /// return a Request, adding any configured metadata
     fn make_request<T>(&self, t: T) -> tonic::Request<T> {
         // Pass along metadata
@@ -0,0 +1,13 @@
+-- Deleted tables are retained (with a deletion timestamp) so that the parquet
+-- file records of a deleted table remain visible to the garbage collector
+-- until they are hard deleted, rather than orphaning their objects.
+ALTER TABLE table_name
+    ADD COLUMN IF NOT EXISTS deleted_at BIGINT DEFAULT NULL;
+
+-- Only live tables must have unique names, allowing the name of a deleted
+-- table to be reused.
+ALTER TABLE table_name
+    DROP CONSTRAINT table_name_unique;
+CREATE UNIQUE INDEX IF NOT EXISTS table_name_unique
+    ON table_name (namespace_id, name)
+    WHERE deleted_at IS NULL;
@@ -0,0 +1,4 @@
+-- How rows with the same primary key are deduplicated, see
+-- `data_types::DeduplicationPolicy`. Defaults to upsert (0).
+ALTER TABLE IF EXISTS table_name
+    ADD COLUMN IF NOT EXISTS dedup_policy SMALLINT NOT NULL DEFAULT 0;
@@ -0,0 +1,21 @@
+-- Queries run for consecutive, closed time windows whose results are written
+-- into another namespace, see `data_types::DownsamplingTask`.
+CREATE TABLE IF NOT EXISTS downsampling_task (
+    id BIGINT GENERATED ALWAYS AS IDENTITY,
+    name VARCHAR NOT NULL,
+    namespace_id BIGINT NOT NULL,
+    query TEXT NOT NULL,
+    -- see `data_types::QueryLanguage`
+    query_language SMALLINT NOT NULL,
+    target_namespace VARCHAR NOT NULL,
+    target_table VARCHAR NOT NULL,
+    every_ns BIGINT NOT NULL,
+    delay_ns BIGINT NOT NULL,
+    -- end (exclusive) of the last window written
+    watermark BIGINT NOT NULL,
+    PRIMARY KEY (id),
+    CONSTRAINT downsampling_task_name_unique UNIQUE (name),
+    CONSTRAINT downsampling_task_namespace_id_fkey
+        FOREIGN KEY (namespace_id) REFERENCES namespace(id)
+        ON DELETE CASCADE
+);
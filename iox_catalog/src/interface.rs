@@ -2,7 +2,8 @@
 
 use async_trait::async_trait;
 use data_types::{
-    Column, ColumnSchema, ColumnType, ColumnTypeCount, CompactionLevel, Namespace, NamespaceId,
+    Column, ColumnSchema, ColumnType, ColumnTypeCount, CompactionLevel, DeduplicationPolicy,
+    DownsamplingTask, DownsamplingTaskId, DownsamplingTaskParams, Namespace, NamespaceId,
     NamespaceSchema, ParquetFile, ParquetFileId, ParquetFileParams, Partition, PartitionId,
     PartitionKey, PartitionParam, ProcessedTombstone, QueryPool, QueryPoolId, SequenceNumber,
     Shard, ShardId, ShardIndex, SkippedCompaction, Table, TableId, TablePartition, TableSchema,
@@ -144,6 +145,12 @@ pub enum Error {
 
     #[snafu(display("could not delete namespace: {source}"))]
     CouldNotDeleteNamespace { source: sqlx::Error },
+
+    #[snafu(display("could not delete table: {source}"))]
+    CouldNotDeleteTable { source: sqlx::Error },
+
+    #[snafu(display("downsampling task {} not found", id))]
+    DownsamplingTaskNotFound { id: DownsamplingTaskId },
 }
 
 /// A specialized `Error` for Catalog errors
@@ -278,6 +285,9 @@ pub trait RepoCollection: Send + Sync + Debug {
 
     /// Repository for [processed tombstones](data_types::ProcessedTombstone).
     fn processed_tombstones(&mut self) -> &mut dyn ProcessedTombstoneRepo;
+
+    /// Repository for [downsampling tasks](data_types::DownsamplingTask).
+    fn downsampling_tasks(&mut self) -> &mut dyn DownsamplingTaskRepo;
 }
 
 /// Functions for working with topics in the catalog.
@@ -358,6 +368,26 @@ pub trait TableRepo: Send + Sync {
 
     /// List all tables.
     async fn list(&mut self) -> Result<Vec<Table>>;
+
+    /// Soft-delete the table identified by `table_id`, removing it and its
+    /// columns from the schema of its namespace.
+    ///
+    /// All parquet files of the table are flagged for deletion, but their
+    /// records (and the partitions they belong to) are retained until they are
+    /// hard deleted, so that the garbage collector removes the objects from
+    /// object storage. The name of a deleted table may be reused.
+    ///
+    /// Returns [`Error::TableNotFound`] if no such table exists.
+    async fn delete(&mut self, table_id: TableId) -> Result<()>;
+
+    /// Set the [`DeduplicationPolicy`] of the table identified by `table_id`.
+    ///
+    /// Returns [`Error::TableNotFound`] if no such table exists.
+    async fn update_dedup_policy(
+        &mut self,
+        table_id: TableId,
+        dedup_policy: DeduplicationPolicy,
+    ) -> Result<Table>;
 }
 
 /// Functions for working with columns in the catalog
@@ -579,6 +609,10 @@ pub trait TombstoneRepo: Send + Sync {
 #[async_trait]
 pub trait ParquetFileRepo: Send + Sync {
     /// create the parquet file
+    ///
+    /// Returns [`Error::TableNotFound`] if the table of the file was deleted,
+    /// so that no file is added to a table after its files were flagged for
+    /// deletion.
     async fn create(&mut self, parquet_file_params: ParquetFileParams) -> Result<ParquetFile>;
 
     /// Flag the parquet file for deletion
@@ -742,6 +776,42 @@ pub trait ProcessedTombstoneRepo: Send + Sync {
     async fn count_by_tombstone_id(&mut self, tombstone_id: TombstoneId) -> Result<i64>;
 }
 
+/// Functions for working with downsampling tasks in the catalog
+#[async_trait]
+pub trait DownsamplingTaskRepo: Send + Sync {
+    /// Create a downsampling task; its name must be unique
+    async fn create(&mut self, params: DownsamplingTaskParams) -> Result<DownsamplingTask>;
+
+    /// List all downsampling tasks
+    async fn list(&mut self) -> Result<Vec<DownsamplingTask>>;
+
+    /// Get the downsampling task with the given name
+    async fn get_by_name(&mut self, name: &str) -> Result<Option<DownsamplingTask>>;
+
+    /// Move the watermark of a downsampling task from `old_watermark` to
+    /// `new_watermark`, once the window between them has been written.
+    ///
+    /// Fails with the current watermark if it is not `old_watermark`, because
+    /// the window was written by someone else or the task was backfilled.
+    async fn cas_watermark(
+        &mut self,
+        id: DownsamplingTaskId,
+        old_watermark: Timestamp,
+        new_watermark: Timestamp,
+    ) -> Result<DownsamplingTask, CasFailure<Timestamp>>;
+
+    /// Unconditionally set the watermark of a downsampling task, e.g. to run
+    /// windows again from an earlier time
+    async fn set_watermark(
+        &mut self,
+        id: DownsamplingTaskId,
+        watermark: Timestamp,
+    ) -> Result<DownsamplingTask>;
+
+    /// Delete a downsampling task
+    async fn delete(&mut self, id: DownsamplingTaskId) -> Result<()>;
+}
+
 /// Gets the namespace schema including all tables and columns.
 pub async fn get_schema_by_id<R>(id: NamespaceId, repos: &mut R) -> Result<NamespaceSchema>
 where
@@ -788,7 +858,8 @@ where
 
     let mut table_id_to_schema = BTreeMap::new();
     for t in tables {
-        table_id_to_schema.insert(t.id, (t.name, TableSchema::new(t.id)));
+        let schema = TableSchema::new_for_table(&t);
+        table_id_to_schema.insert(t.id, (t.name, schema));
     }
 
     for c in columns {
@@ -815,7 +886,10 @@ where
     R: RepoCollection + ?Sized,
 {
     let columns = repos.columns().list_by_table_id(id).await?;
-    let mut schema = TableSchema::new(id);
+    let mut schema = match repos.tables().get_by_id(id).await? {
+        Some(table) => TableSchema::new_for_table(&table),
+        None => TableSchema::new(id),
+    };
 
     for c in columns {
         schema.columns.insert(
@@ -888,8 +962,12 @@ pub async fn list_schemas(
 
     let mut joined = HashMap::<NamespaceId, NamespaceTables>::default();
     for column in columns {
-        // Resolve the table this column references
-        let table = tables.get(&column.table_id).expect("no table for column");
+        // Resolve the table this column references, skipping columns of
+        // tables deleted after the "columns" snapshot was retrieved.
+        let table = match tables.get(&column.table_id) {
+            Some(v) => v,
+            None => continue,
+        };
 
         let table_schema = joined
             // Find or create a record in the joined <NamespaceId, Tables> map
@@ -898,7 +976,7 @@ pub async fn list_schemas(
             .or_default()
             // Fetch the schema record for this table, or create an empty one.
             .entry(table.name.clone())
-            .or_insert_with(|| TableSchema::new(column.table_id));
+            .or_insert_with(|| TableSchema::new_for_table(table));
 
         table_schema.add_column(&column);
     }
@@ -936,7 +1014,7 @@ pub(crate) mod test_helpers {
     use super::*;
     use ::test_helpers::{assert_contains, tracing::TracingCapture};
     use assert_matches::assert_matches;
-    use data_types::{ColumnId, ColumnSet, CompactionLevel};
+    use data_types::{ColumnId, ColumnSet, CompactionLevel, QueryLanguage};
     use metric::{Attributes, DurationHistogram, Metric};
     use std::{
         ops::{Add, DerefMut},
@@ -969,17 +1047,21 @@ pub(crate) mod test_helpers {
         test_txn_drop(Arc::clone(&catalog)).await;
         test_list_schemas(Arc::clone(&catalog)).await;
         test_delete_namespace(Arc::clone(&catalog)).await;
+        test_delete_table(Arc::clone(&catalog)).await;
+        test_downsampling_task(Arc::clone(&catalog)).await;
 
         let metrics = catalog.metrics();
         assert_metric_hit(&metrics, "topic_create_or_get");
         assert_metric_hit(&metrics, "query_create_or_get");
         assert_metric_hit(&metrics, "namespace_create");
         assert_metric_hit(&metrics, "table_create_or_get");
+        assert_metric_hit(&metrics, "table_delete");
         assert_metric_hit(&metrics, "column_create_or_get");
         assert_metric_hit(&metrics, "shard_create_or_get");
         assert_metric_hit(&metrics, "partition_create_or_get");
         assert_metric_hit(&metrics, "tombstone_create_or_get");
         assert_metric_hit(&metrics, "parquet_create");
+        assert_metric_hit(&metrics, "downsampling_task_create");
     }
 
     async fn test_setup(catalog: Arc<dyn Catalog>) {
@@ -1278,7 +1360,34 @@ pub(crate) mod test_helpers {
 
         // All tables should be returned by list(), regardless of namespace
         let list = repos.tables().list().await.unwrap();
-        assert_eq!(list.as_slice(), [tt, test_table, foo_table]);
+        assert_eq!(list.as_slice(), [tt, test_table, foo_table.clone()]);
+
+        // tables default to upsert deduplication, which can be changed
+        assert_eq!(foo_table.dedup_policy, DeduplicationPolicy::Upsert);
+        let updated = repos
+            .tables()
+            .update_dedup_policy(foo_table.id, DeduplicationPolicy::AppendOnly)
+            .await
+            .expect("should update dedup policy");
+        assert_eq!(updated.id, foo_table.id);
+        assert_eq!(updated.dedup_policy, DeduplicationPolicy::AppendOnly);
+        let got = repos
+            .tables()
+            .get_by_id(foo_table.id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(got.dedup_policy, DeduplicationPolicy::AppendOnly);
+        let schema = get_table_schema_by_id(foo_table.id, repos.as_mut())
+            .await
+            .unwrap();
+        assert_eq!(schema.dedup_policy, DeduplicationPolicy::AppendOnly);
+        let err = repos
+            .tables()
+            .update_dedup_policy(TableId::new(i64::MAX), DeduplicationPolicy::KeepFirst)
+            .await
+            .expect_err("should error for unknown table");
+        assert!(matches!(err, Error::TableNotFound { .. }));
 
         // test per-namespace table limits
         let latest = repos
@@ -5646,6 +5755,290 @@ pub(crate) mod test_helpers {
             .expect("processed tombstone exists check should succeed"));
     }
 
+    async fn test_delete_table(catalog: Arc<dyn Catalog>) {
+        let mut repos = catalog.repositories().await;
+        let topic = repos.topics().create_or_get("foo").await.unwrap();
+        let pool = repos.query_pools().create_or_get("foo").await.unwrap();
+        let namespace = repos
+            .namespaces()
+            .create("namespace_test_delete_table", None, topic.id, pool.id)
+            .await
+            .unwrap();
+        let shard = repos
+            .shards()
+            .create_or_get(&topic, ShardIndex::new(1))
+            .await
+            .unwrap();
+
+        // Create two tables, each with a column, a partition and a parquet
+        // file, then delete only the first.
+        let mut params = vec![];
+        let mut files = vec![];
+        let mut tables = vec![];
+        for name in ["test_table_delete_1", "test_table_delete_2"] {
+            let table = repos
+                .tables()
+                .create_or_get(name, namespace.id)
+                .await
+                .unwrap();
+            repos
+                .columns()
+                .create_or_get("column_test", table.id, ColumnType::Tag)
+                .await
+                .unwrap();
+            let partition = repos
+                .partitions()
+                .create_or_get(format!("{name}_partition").into(), shard.id, table.id)
+                .await
+                .unwrap();
+            let file_params = ParquetFileParams {
+                namespace_id: namespace.id,
+                shard_id: shard.id,
+                table_id: table.id,
+                partition_id: partition.id,
+                object_store_id: Uuid::new_v4(),
+                max_sequence_number: SequenceNumber::new(1),
+                min_time: Timestamp::new(100),
+                max_time: Timestamp::new(250),
+                file_size_bytes: 1337,
+                row_count: 0,
+                compaction_level: CompactionLevel::Initial,
+                created_at: Timestamp::new(1),
+                column_set: ColumnSet::new([ColumnId::new(1), ColumnId::new(2)]),
+            };
+            let file = repos
+                .parquet_files()
+                .create(file_params.clone())
+                .await
+                .unwrap();
+            tables.push(table);
+            params.push(file_params);
+            files.push(file);
+        }
+
+        repos
+            .tables()
+            .delete(tables[0].id)
+            .await
+            .expect("delete table should succeed");
+
+        // The deleted table and its columns are gone
+        assert!(repos
+            .tables()
+            .get_by_id(tables[0].id)
+            .await
+            .unwrap()
+            .is_none());
+        assert!(repos
+            .tables()
+            .get_by_namespace_and_name(namespace.id, "test_table_delete_1")
+            .await
+            .unwrap()
+            .is_none());
+        assert!(repos
+            .columns()
+            .list_by_table_id(tables[0].id)
+            .await
+            .unwrap()
+            .is_empty());
+        let schema = get_schema_by_id(namespace.id, repos.as_mut())
+            .await
+            .unwrap();
+        assert!(!schema.tables.contains_key("test_table_delete_1"));
+        assert!(schema.tables.contains_key("test_table_delete_2"));
+
+        // But the parquet file record is retained (flagged for deletion) for
+        // the garbage collector to observe
+        let file = repos
+            .parquet_files()
+            .get_by_object_store_id(files[0].object_store_id)
+            .await
+            .unwrap()
+            .expect("parquet file record should be retained");
+        assert!(file.to_delete.is_some());
+
+        // No more files can be added to the deleted table
+        let err = repos
+            .parquet_files()
+            .create(ParquetFileParams {
+                object_store_id: Uuid::new_v4(),
+                ..params[0].clone()
+            })
+            .await
+            .expect_err("adding a file to a deleted table should fail");
+        assert!(matches!(err, Error::TableNotFound { id } if id == tables[0].id));
+
+        // The other table is untouched
+        assert_eq!(
+            repos.tables().get_by_id(tables[1].id).await.unwrap(),
+            Some(tables[1].clone())
+        );
+        assert_eq!(
+            repos
+                .columns()
+                .list_by_table_id(tables[1].id)
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+        assert_eq!(
+            repos
+                .parquet_files()
+                .get_by_object_store_id(files[1].object_store_id)
+                .await
+                .unwrap(),
+            Some(files[1].clone())
+        );
+
+        // Deleting a table that does not exist is an error
+        let err = repos
+            .tables()
+            .delete(tables[0].id)
+            .await
+            .expect_err("deleting a missing table should fail");
+        assert!(matches!(err, Error::TableNotFound { id } if id == tables[0].id));
+
+        // The name of the deleted table can be reused by a new table
+        let table = repos
+            .tables()
+            .create_or_get("test_table_delete_1", namespace.id)
+            .await
+            .unwrap();
+        assert_ne!(table.id, tables[0].id);
+
+        repos
+            .namespaces()
+            .delete("namespace_test_delete_table")
+            .await
+            .expect("delete namespace should succeed");
+    }
+
+    async fn test_downsampling_task(catalog: Arc<dyn Catalog>) {
+        let mut repos = catalog.repositories().await;
+        let topic = repos.topics().create_or_get("foo").await.unwrap();
+        let pool = repos.query_pools().create_or_get("foo").await.unwrap();
+        let namespace = repos
+            .namespaces()
+            .create("namespace_downsampling_task_test", None, topic.id, pool.id)
+            .await
+            .unwrap();
+
+        let params = DownsamplingTaskParams {
+            name: "test_downsampling_task".to_string(),
+            namespace_id: namespace.id,
+            query: "SELECT 1".to_string(),
+            query_language: QueryLanguage::Sql,
+            target_namespace: "rollups".to_string(),
+            target_table: "cpu_1h".to_string(),
+            every_ns: 3_600_000_000_000,
+            delay_ns: 60_000_000_000,
+            watermark: Timestamp::new(7_200_000_000_000),
+        };
+        let task = repos
+            .downsampling_tasks()
+            .create(params.clone())
+            .await
+            .unwrap();
+        assert_eq!(task.name, params.name);
+        assert_eq!(task.namespace_id, namespace.id);
+        assert_eq!(task.query_language, QueryLanguage::Sql);
+        assert_eq!(task.watermark, params.watermark);
+
+        // names are unique
+        let err = repos
+            .downsampling_tasks()
+            .create(params.clone())
+            .await
+            .unwrap_err();
+        assert_matches!(err, Error::NameExists { .. });
+
+        let got = repos
+            .downsampling_tasks()
+            .get_by_name("test_downsampling_task")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(got, task);
+        assert!(repos
+            .downsampling_tasks()
+            .get_by_name("does_not_exist")
+            .await
+            .unwrap()
+            .is_none());
+        assert!(repos
+            .downsampling_tasks()
+            .list()
+            .await
+            .unwrap()
+            .contains(&task));
+
+        // advance the watermark by one window
+        let next = Timestamp::new(task.watermark.get() + task.every_ns);
+        let updated = repos
+            .downsampling_tasks()
+            .cas_watermark(task.id, task.watermark, next)
+            .await
+            .unwrap();
+        assert_eq!(updated.watermark, next);
+
+        // a stale watermark fails with the current one
+        let err = repos
+            .downsampling_tasks()
+            .cas_watermark(task.id, task.watermark, next)
+            .await
+            .unwrap_err();
+        assert_matches!(err, CasFailure::ValueMismatch(w) => {
+            assert_eq!(w, next);
+        });
+
+        // backfill
+        let updated = repos
+            .downsampling_tasks()
+            .set_watermark(task.id, Timestamp::new(0))
+            .await
+            .unwrap();
+        assert_eq!(updated.watermark, Timestamp::new(0));
+
+        repos.downsampling_tasks().delete(task.id).await.unwrap();
+        let err = repos
+            .downsampling_tasks()
+            .delete(task.id)
+            .await
+            .unwrap_err();
+        assert_matches!(err, Error::DownsamplingTaskNotFound { .. });
+        let err = repos
+            .downsampling_tasks()
+            .cas_watermark(task.id, Timestamp::new(0), next)
+            .await
+            .unwrap_err();
+        assert_matches!(
+            err,
+            CasFailure::QueryError(Error::DownsamplingTaskNotFound { .. })
+        );
+        let err = repos
+            .downsampling_tasks()
+            .set_watermark(task.id, next)
+            .await
+            .unwrap_err();
+        assert_matches!(err, Error::DownsamplingTaskNotFound { .. });
+
+        // tasks of a missing namespace cannot be created
+        let err = repos
+            .downsampling_tasks()
+            .create(DownsamplingTaskParams {
+                name: "test_downsampling_task_no_ns".to_string(),
+                namespace_id: NamespaceId::new(i64::MAX),
+                ..params
+            })
+            .await
+            .unwrap_err();
+        assert_matches!(
+            err,
+            Error::NamespaceNotFoundById { .. } | Error::ForeignKeyViolation { .. }
+        );
+    }
+
     async fn test_txn_isolation(catalog: Arc<dyn Catalog>) {
         let barrier = Arc::new(tokio::sync::Barrier::new(2));
 
@@ -13,7 +13,9 @@
     clippy::dbg_macro
 )]
 
-use crate::interface::{ColumnTypeMismatchSnafu, Error, RepoCollection, Result, Transaction};
+use crate::interface::{
+    get_table_schema_by_id, ColumnTypeMismatchSnafu, Error, RepoCollection, Result, Transaction,
+};
 use data_types::{
     ColumnType, NamespaceSchema, QueryPool, Shard, ShardId, ShardIndex, TableSchema, TopicMetadata,
 };
@@ -124,7 +126,7 @@ where
                 .tables()
                 .create_or_get(table_name, schema.id)
                 .await
-                .map(|t| TableSchema::new(t.id))?;
+                .map(|t| TableSchema::new_for_table(&t))?;
 
             // Always add a time column to all new tables.
             let time_col = repos
@@ -207,6 +209,67 @@ where
     Ok(())
 }
 
+/// Create the table `table_name` in the namespace described by `schema` with
+/// the explicitly declared set of `columns`, returning the resulting
+/// [`TableSchema`].
+///
+/// A `time` column is always created, and declaring `time` as anything other
+/// than [`ColumnType::Time`] is rejected. Declared columns fix the type of the
+/// column, so later writes that disagree with the declared type fail schema
+/// validation exactly as if an earlier write had created the column.
+///
+/// If the table already exists the declared columns are added to it, failing
+/// if any of them already exist with a different type. The per-table column
+/// limit of the namespace is enforced for the declared set.
+pub async fn create_table_with_columns<R>(
+    table_name: &str,
+    schema: &NamespaceSchema,
+    mut columns: HashMap<&str, ColumnType>,
+    repos: &mut R,
+) -> Result<TableSchema>
+where
+    R: RepoCollection + ?Sized,
+{
+    match columns.insert(TIME_COLUMN, ColumnType::Time) {
+        None | Some(ColumnType::Time) => {}
+        Some(existing) => {
+            return ColumnTypeMismatchSnafu {
+                name: TIME_COLUMN,
+                existing,
+                new: ColumnType::Time,
+            }
+            .fail()
+        }
+    }
+
+    let table = repos.tables().create_or_get(table_name, schema.id).await?;
+
+    let existing = repos.columns().list_by_table_id(table.id).await?;
+    let new_columns = columns
+        .keys()
+        .filter(|name| !existing.iter().any(|c| c.name == **name))
+        .count();
+    if existing.len() + new_columns > schema.max_columns_per_table {
+        // Report the first column that does not fit within the limit.
+        let column_name = columns
+            .keys()
+            .find(|name| !existing.iter().any(|c| c.name == **name))
+            .map(|name| name.to_string())
+            .unwrap_or_default();
+        return Err(Error::ColumnCreateLimitError {
+            column_name,
+            table_id: table.id,
+        });
+    }
+
+    repos
+        .columns()
+        .create_or_get_many_unchecked(table.id, columns)
+        .await?;
+
+    get_table_schema_by_id(table.id, repos).await
+}
+
 /// Creates or gets records in the catalog for the shared topic, query pool, and shards
 /// for each of the partitions.
 ///
@@ -509,4 +572,117 @@ mod tests {
             ],
         }
     );
+
+    #[tokio::test]
+    async fn test_create_table_with_columns() {
+        use crate::interface::Catalog;
+        use std::ops::DerefMut;
+
+        let metrics = Arc::new(metric::Registry::default());
+        let repo = MemCatalog::new(metrics);
+        let mut txn = repo.start_transaction().await.unwrap();
+        let (topic, query_pool, _) = create_or_get_default_records(2, txn.deref_mut())
+            .await
+            .unwrap();
+        let namespace = txn
+            .namespaces()
+            .create("bananas", None, topic.id, query_pool.id)
+            .await
+            .unwrap();
+        let schema = NamespaceSchema::new(
+            namespace.id,
+            namespace.topic_id,
+            namespace.query_pool_id,
+            namespace.max_columns_per_table,
+            namespace.retention_period_ns,
+        );
+
+        let table = create_table_with_columns(
+            "m1",
+            &schema,
+            [("t1", ColumnType::Tag), ("f1", ColumnType::F64)]
+                .into_iter()
+                .collect(),
+            txn.deref_mut(),
+        )
+        .await
+        .expect("table creation should succeed");
+
+        // The declared columns and the implicit time column exist.
+        let mut got = table
+            .columns
+            .iter()
+            .map(|(name, c)| (name.as_str(), c.column_type))
+            .collect::<Vec<_>>();
+        got.sort_unstable();
+        assert_eq!(
+            got,
+            [
+                ("f1", ColumnType::F64),
+                ("t1", ColumnType::Tag),
+                ("time", ColumnType::Time),
+            ]
+        );
+
+        // A write disagreeing with the declared type of f1 is rejected, even
+        // though the (stale) cached schema does not know about the table.
+        let writes = mutable_batch_lp::lines_to_batches("m1,t1=a f1=2i", 42).unwrap();
+        let err = validate_or_insert_schema(
+            writes.iter().map(|(k, v)| (k.as_str(), v)),
+            &schema,
+            txn.deref_mut(),
+        )
+        .await
+        .expect_err("conflicting write should fail");
+        assert!(matches!(err.err(), Error::ColumnTypeMismatch { .. }));
+
+        // Declaring the time column with a non-time type is an error.
+        let err = create_table_with_columns(
+            "m2",
+            &schema,
+            [("time", ColumnType::I64)].into_iter().collect(),
+            txn.deref_mut(),
+        )
+        .await
+        .expect_err("non-time time column should fail");
+        assert!(matches!(err, Error::ColumnTypeMismatch { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_create_table_with_columns_limit() {
+        use crate::interface::Catalog;
+        use std::ops::DerefMut;
+
+        let metrics = Arc::new(metric::Registry::default());
+        let repo = MemCatalog::new(metrics);
+        let mut txn = repo.start_transaction().await.unwrap();
+        let (topic, query_pool, _) = create_or_get_default_records(2, txn.deref_mut())
+            .await
+            .unwrap();
+        let namespace = txn
+            .namespaces()
+            .create("bananas", None, topic.id, query_pool.id)
+            .await
+            .unwrap();
+        let schema = NamespaceSchema::new(
+            namespace.id,
+            namespace.topic_id,
+            namespace.query_pool_id,
+            2,
+            namespace.retention_period_ns,
+        );
+
+        // Two declared columns plus the time column exceed the limit of 2.
+        let err = create_table_with_columns(
+            "m1",
+            &schema,
+            [("t1", ColumnType::Tag), ("f1", ColumnType::F64)]
+                .into_iter()
+                .collect(),
+            txn.deref_mut(),
+        )
+        .await
+        .expect_err("column limit should be enforced");
+        assert!(matches!(err, Error::ColumnCreateLimitError { .. }));
+    }
 }
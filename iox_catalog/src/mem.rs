@@ -4,16 +4,17 @@
 use crate::{
     interface::{
         sealed::TransactionFinalize, CasFailure, Catalog, ColumnRepo, ColumnTypeMismatchSnafu,
-        Error, NamespaceRepo, ParquetFileRepo, PartitionRepo, ProcessedTombstoneRepo,
-        QueryPoolRepo, RepoCollection, Result, ShardRepo, TableRepo, TombstoneRepo,
-        TopicMetadataRepo, Transaction,
+        DownsamplingTaskRepo, Error, NamespaceRepo, ParquetFileRepo, PartitionRepo,
+        ProcessedTombstoneRepo, QueryPoolRepo, RepoCollection, Result, ShardRepo, TableRepo,
+        TombstoneRepo, TopicMetadataRepo, Transaction,
     },
     metrics::MetricDecorator,
     DEFAULT_MAX_COLUMNS_PER_TABLE, DEFAULT_MAX_TABLES,
 };
 use async_trait::async_trait;
 use data_types::{
-    Column, ColumnId, ColumnType, ColumnTypeCount, CompactionLevel, Namespace, NamespaceId,
+    Column, ColumnId, ColumnType, ColumnTypeCount, CompactionLevel, DeduplicationPolicy,
+    DownsamplingTask, DownsamplingTaskId, DownsamplingTaskParams, Namespace, NamespaceId,
     ParquetFile, ParquetFileId, ParquetFileParams, Partition, PartitionId, PartitionKey,
     PartitionParam, ProcessedTombstone, QueryPool, QueryPoolId, SequenceNumber, Shard, ShardId,
     ShardIndex, SkippedCompaction, Table, TableId, TablePartition, Timestamp, Tombstone,
@@ -62,6 +63,8 @@ struct MemCollections {
     query_pools: Vec<QueryPool>,
     namespaces: Vec<Namespace>,
     tables: Vec<Table>,
+    /// Soft-deleted tables, retained so that their IDs are not reused.
+    deleted_tables: Vec<Table>,
     columns: Vec<Column>,
     shards: Vec<Shard>,
     partitions: Vec<Partition>,
@@ -69,6 +72,7 @@ struct MemCollections {
     tombstones: Vec<Tombstone>,
     parquet_files: Vec<ParquetFile>,
     processed_tombstones: Vec<ProcessedTombstone>,
+    downsampling_tasks: Vec<DownsamplingTask>,
 }
 
 #[derive(Debug)]
@@ -229,6 +233,10 @@ impl RepoCollection for MemTxn {
     fn processed_tombstones(&mut self) -> &mut dyn ProcessedTombstoneRepo {
         self
     }
+
+    fn downsampling_tasks(&mut self) -> &mut dyn DownsamplingTaskRepo {
+        self
+    }
 }
 
 #[async_trait]
@@ -359,6 +367,7 @@ impl NamespaceRepo for MemTxn {
         let table_ids: HashSet<_> = stage
             .tables
             .iter()
+            .chain(stage.deleted_tables.iter())
             .filter_map(|table| (table.namespace_id == namespace_id).then_some(table.id))
             .collect();
         // delete partitions for those tables
@@ -377,6 +386,13 @@ impl NamespaceRepo for MemTxn {
         stage
             .tables
             .retain(|t| !table_ids.iter().any(|id| *id == t.id));
+        stage
+            .deleted_tables
+            .retain(|t| !table_ids.iter().any(|id| *id == t.id));
+        // delete the downsampling tasks querying the namespace
+        stage
+            .downsampling_tasks
+            .retain(|t| t.namespace_id != namespace_id);
         // finally, delete the namespace
         stage.namespaces.retain(|n| n.id != namespace_id);
         Ok(())
@@ -467,9 +483,10 @@ impl TableRepo for MemTxn {
             Some(t) => t,
             None => {
                 let table = Table {
-                    id: TableId::new(stage.tables.len() as i64 + 1),
+                    id: TableId::new((stage.tables.len() + stage.deleted_tables.len()) as i64 + 1),
                     namespace_id,
                     name: name.to_string(),
+                    dedup_policy: Default::default(),
                 };
                 stage.tables.push(table);
                 stage.tables.last().unwrap()
@@ -515,6 +532,45 @@ impl TableRepo for MemTxn {
         let stage = self.stage();
         Ok(stage.tables.clone())
     }
+
+    // soft-deletes the table, retaining the partitions and (flagged) parquet files of the table
+    async fn delete(&mut self, table_id: TableId) -> Result<()> {
+        let deleted_at = Timestamp::from(self.time_provider.now());
+        let stage = self.stage();
+
+        let idx = stage
+            .tables
+            .iter()
+            .position(|t| t.id == table_id)
+            .ok_or(Error::TableNotFound { id: table_id })?;
+        let table = stage.tables.remove(idx);
+        stage.deleted_tables.push(table);
+
+        // flag all the parquet files of the table for deletion
+        stage
+            .parquet_files
+            .iter_mut()
+            .filter(|f| f.table_id == table_id && f.to_delete.is_none())
+            .for_each(|f| f.to_delete = Some(deleted_at));
+        // and delete the columns of the table
+        stage.columns.retain(|c| c.table_id != table_id);
+        Ok(())
+    }
+
+    async fn update_dedup_policy(
+        &mut self,
+        table_id: TableId,
+        dedup_policy: DeduplicationPolicy,
+    ) -> Result<Table> {
+        let stage = self.stage();
+        match stage.tables.iter_mut().find(|t| t.id == table_id) {
+            Some(t) => {
+                t.dedup_policy = dedup_policy;
+                Ok(t.clone())
+            }
+            None => Err(Error::TableNotFound { id: table_id }),
+        }
+    }
 }
 
 #[async_trait]
@@ -1178,6 +1234,10 @@ impl ParquetFileRepo for MemTxn {
             return Err(Error::FileExists { object_store_id });
         }
 
+        if stage.deleted_tables.iter().any(|t| t.id == table_id) {
+            return Err(Error::TableNotFound { id: table_id });
+        }
+
         let parquet_file = ParquetFile {
             id: ParquetFileId::new(stage.parquet_files.len() as i64 + 1),
             shard_id,
@@ -1779,6 +1839,108 @@ impl ProcessedTombstoneRepo for MemTxn {
     }
 }
 
+#[async_trait]
+impl DownsamplingTaskRepo for MemTxn {
+    async fn create(&mut self, params: DownsamplingTaskParams) -> Result<DownsamplingTask> {
+        let stage = self.stage();
+
+        if stage
+            .downsampling_tasks
+            .iter()
+            .any(|t| t.name == params.name)
+        {
+            return Err(Error::NameExists { name: params.name });
+        }
+        if !stage.namespaces.iter().any(|n| n.id == params.namespace_id) {
+            return Err(Error::NamespaceNotFoundById {
+                id: params.namespace_id,
+            });
+        }
+
+        let id = stage
+            .downsampling_tasks
+            .iter()
+            .map(|t| t.id.get())
+            .max()
+            .unwrap_or_default()
+            + 1;
+        let task = DownsamplingTask {
+            id: DownsamplingTaskId::new(id),
+            name: params.name,
+            namespace_id: params.namespace_id,
+            query: params.query,
+            query_language: params.query_language,
+            target_namespace: params.target_namespace,
+            target_table: params.target_table,
+            every_ns: params.every_ns,
+            delay_ns: params.delay_ns,
+            watermark: params.watermark,
+        };
+        stage.downsampling_tasks.push(task.clone());
+        Ok(task)
+    }
+
+    async fn list(&mut self) -> Result<Vec<DownsamplingTask>> {
+        let stage = self.stage();
+
+        Ok(stage.downsampling_tasks.clone())
+    }
+
+    async fn get_by_name(&mut self, name: &str) -> Result<Option<DownsamplingTask>> {
+        let stage = self.stage();
+
+        Ok(stage
+            .downsampling_tasks
+            .iter()
+            .find(|t| t.name == name)
+            .cloned())
+    }
+
+    async fn cas_watermark(
+        &mut self,
+        id: DownsamplingTaskId,
+        old_watermark: Timestamp,
+        new_watermark: Timestamp,
+    ) -> Result<DownsamplingTask, CasFailure<Timestamp>> {
+        let stage = self.stage();
+        match stage.downsampling_tasks.iter_mut().find(|t| t.id == id) {
+            Some(t) if t.watermark == old_watermark => {
+                t.watermark = new_watermark;
+                Ok(t.clone())
+            }
+            Some(t) => Err(CasFailure::ValueMismatch(t.watermark)),
+            None => Err(CasFailure::QueryError(Error::DownsamplingTaskNotFound {
+                id,
+            })),
+        }
+    }
+
+    async fn set_watermark(
+        &mut self,
+        id: DownsamplingTaskId,
+        watermark: Timestamp,
+    ) -> Result<DownsamplingTask> {
+        let stage = self.stage();
+        match stage.downsampling_tasks.iter_mut().find(|t| t.id == id) {
+            Some(t) => {
+                t.watermark = watermark;
+                Ok(t.clone())
+            }
+            None => Err(Error::DownsamplingTaskNotFound { id }),
+        }
+    }
+
+    async fn delete(&mut self, id: DownsamplingTaskId) -> Result<()> {
+        let stage = self.stage();
+        if !stage.downsampling_tasks.iter().any(|t| t.id == id) {
+            return Err(Error::DownsamplingTaskNotFound { id });
+        }
+
+        stage.downsampling_tasks.retain(|t| t.id != id);
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1,17 +1,17 @@
 //! Metric instrumentation for catalog implementations.
 
 use crate::interface::{
-    sealed::TransactionFinalize, CasFailure, ColumnRepo, NamespaceRepo, ParquetFileRepo,
-    PartitionRepo, ProcessedTombstoneRepo, QueryPoolRepo, RepoCollection, Result, ShardRepo,
-    TableRepo, TombstoneRepo, TopicMetadataRepo,
+    sealed::TransactionFinalize, CasFailure, ColumnRepo, DownsamplingTaskRepo, NamespaceRepo,
+    ParquetFileRepo, PartitionRepo, ProcessedTombstoneRepo, QueryPoolRepo, RepoCollection, Result,
+    ShardRepo, TableRepo, TombstoneRepo, TopicMetadataRepo,
 };
 use async_trait::async_trait;
 use data_types::{
-    Column, ColumnType, ColumnTypeCount, CompactionLevel, Namespace, NamespaceId, ParquetFile,
-    ParquetFileId, ParquetFileParams, Partition, PartitionId, PartitionKey, PartitionParam,
-    ProcessedTombstone, QueryPool, QueryPoolId, SequenceNumber, Shard, ShardId, ShardIndex,
-    SkippedCompaction, Table, TableId, TablePartition, Timestamp, Tombstone, TombstoneId, TopicId,
-    TopicMetadata,
+    Column, ColumnType, ColumnTypeCount, CompactionLevel, DeduplicationPolicy, DownsamplingTask,
+    DownsamplingTaskId, DownsamplingTaskParams, Namespace, NamespaceId, ParquetFile, ParquetFileId,
+    ParquetFileParams, Partition, PartitionId, PartitionKey, PartitionParam, ProcessedTombstone,
+    QueryPool, QueryPoolId, SequenceNumber, Shard, ShardId, ShardIndex, SkippedCompaction, Table,
+    TableId, TablePartition, Timestamp, Tombstone, TombstoneId, TopicId, TopicMetadata,
 };
 use iox_time::{SystemProvider, TimeProvider};
 use metric::{DurationHistogram, Metric};
@@ -54,6 +54,7 @@ where
         + TombstoneRepo
         + ProcessedTombstoneRepo
         + ParquetFileRepo
+        + DownsamplingTaskRepo
         + Debug,
     P: TimeProvider,
 {
@@ -96,6 +97,10 @@ where
     fn processed_tombstones(&mut self) -> &mut dyn ProcessedTombstoneRepo {
         self
     }
+
+    fn downsampling_tasks(&mut self) -> &mut dyn DownsamplingTaskRepo {
+        self
+    }
 }
 
 #[async_trait]
@@ -212,6 +217,8 @@ decorate!(
         "table_get_by_namespace_and_name" = get_by_namespace_and_name(&mut self, namespace_id: NamespaceId, name: &str) -> Result<Option<Table>>;
         "table_list_by_namespace_id" = list_by_namespace_id(&mut self, namespace_id: NamespaceId) -> Result<Vec<Table>>;
         "table_list" = list(&mut self) -> Result<Vec<Table>>;
+        "table_delete" = delete(&mut self, table_id: TableId) -> Result<()>;
+        "table_update_dedup_policy" = update_dedup_policy(&mut self, table_id: TableId, dedup_policy: DeduplicationPolicy) -> Result<Table>;
     ]
 );
 
@@ -304,3 +311,15 @@ decorate!(
         "processed_tombstone_count_by_tombstone_id" = count_by_tombstone_id(&mut self, tombstone_id: TombstoneId) -> Result<i64>;
     ]
 );
+
+decorate!(
+    impl_trait = DownsamplingTaskRepo,
+    methods = [
+        "downsampling_task_create" = create(&mut self, params: DownsamplingTaskParams) -> Result<DownsamplingTask>;
+        "downsampling_task_list" = list(&mut self) -> Result<Vec<DownsamplingTask>>;
+        "downsampling_task_get_by_name" = get_by_name(&mut self, name: &str) -> Result<Option<DownsamplingTask>>;
+        "downsampling_task_cas_watermark" = cas_watermark(&mut self, id: DownsamplingTaskId, old_watermark: Timestamp, new_watermark: Timestamp) -> Result<DownsamplingTask, CasFailure<Timestamp>>;
+        "downsampling_task_set_watermark" = set_watermark(&mut self, id: DownsamplingTaskId, watermark: Timestamp) -> Result<DownsamplingTask>;
+        "downsampling_task_delete" = delete(&mut self, id: DownsamplingTaskId) -> Result<()>;
+    ]
+);
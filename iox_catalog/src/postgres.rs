@@ -3,20 +3,20 @@
 use crate::{
     interface::{
         self, sealed::TransactionFinalize, CasFailure, Catalog, ColumnRepo,
-        ColumnTypeMismatchSnafu, Error, NamespaceRepo, ParquetFileRepo, PartitionRepo,
-        ProcessedTombstoneRepo, QueryPoolRepo, RepoCollection, Result, ShardRepo, TableRepo,
-        TombstoneRepo, TopicMetadataRepo, Transaction,
+        ColumnTypeMismatchSnafu, DownsamplingTaskRepo, Error, NamespaceRepo, ParquetFileRepo,
+        PartitionRepo, ProcessedTombstoneRepo, QueryPoolRepo, RepoCollection, Result, ShardRepo,
+        TableRepo, TombstoneRepo, TopicMetadataRepo, Transaction,
     },
     metrics::MetricDecorator,
     DEFAULT_MAX_COLUMNS_PER_TABLE, DEFAULT_MAX_TABLES,
 };
 use async_trait::async_trait;
 use data_types::{
-    Column, ColumnType, ColumnTypeCount, CompactionLevel, Namespace, NamespaceId, ParquetFile,
-    ParquetFileId, ParquetFileParams, Partition, PartitionId, PartitionKey, PartitionParam,
-    ProcessedTombstone, QueryPool, QueryPoolId, SequenceNumber, Shard, ShardId, ShardIndex,
-    SkippedCompaction, Table, TableId, TablePartition, Timestamp, Tombstone, TombstoneId, TopicId,
-    TopicMetadata,
+    Column, ColumnType, ColumnTypeCount, CompactionLevel, DeduplicationPolicy, DownsamplingTask,
+    DownsamplingTaskId, DownsamplingTaskParams, Namespace, NamespaceId, ParquetFile, ParquetFileId,
+    ParquetFileParams, Partition, PartitionId, PartitionKey, PartitionParam, ProcessedTombstone,
+    QueryPool, QueryPoolId, SequenceNumber, Shard, ShardId, ShardIndex, SkippedCompaction, Table,
+    TableId, TablePartition, Timestamp, Tombstone, TombstoneId, TopicId, TopicMetadata,
 };
 use iox_time::{SystemProvider, TimeProvider};
 use observability_deps::tracing::{debug, info, warn};
@@ -519,6 +519,10 @@ impl RepoCollection for PostgresTxn {
     fn processed_tombstones(&mut self) -> &mut dyn ProcessedTombstoneRepo {
         self
     }
+
+    fn downsampling_tasks(&mut self) -> &mut dyn DownsamplingTaskRepo {
+        self
+    }
 }
 
 #[async_trait]
@@ -784,11 +788,12 @@ impl TableRepo for PostgresTxn {
 INSERT INTO table_name ( name, namespace_id )
 SELECT $1, id FROM (
     SELECT namespace.id AS id, max_tables, COUNT(table_name.*) AS count
-    FROM namespace LEFT JOIN table_name ON namespace.id = table_name.namespace_id
+    FROM namespace LEFT JOIN table_name
+        ON namespace.id = table_name.namespace_id AND table_name.deleted_at IS NULL
     WHERE namespace.id = $2
     GROUP BY namespace.max_tables, table_name.namespace_id, namespace.id
 ) AS get_count WHERE count < max_tables
-ON CONFLICT ON CONSTRAINT table_name_unique
+ON CONFLICT (namespace_id, name) WHERE deleted_at IS NULL
 DO UPDATE SET name = table_name.name
 RETURNING *;
         "#,
@@ -819,7 +824,7 @@ RETURNING *;
             r#"
 SELECT *
 FROM table_name
-WHERE id = $1;
+WHERE id = $1 AND deleted_at IS NULL;
             "#,
         )
         .bind(table_id) // $1
@@ -844,7 +849,7 @@ WHERE id = $1;
             r#"
 SELECT *
 FROM table_name
-WHERE namespace_id = $1 AND name = $2;
+WHERE namespace_id = $1 AND name = $2 AND deleted_at IS NULL;
             "#,
         )
         .bind(namespace_id) // $1
@@ -866,7 +871,7 @@ WHERE namespace_id = $1 AND name = $2;
             r#"
 SELECT *
 FROM table_name
-WHERE namespace_id = $1;
+WHERE namespace_id = $1 AND deleted_at IS NULL;
             "#,
         )
         .bind(namespace_id)
@@ -878,13 +883,91 @@ WHERE namespace_id = $1;
     }
 
     async fn list(&mut self) -> Result<Vec<Table>> {
-        let rec = sqlx::query_as::<_, Table>("SELECT * FROM table_name;")
+        let rec = sqlx::query_as::<_, Table>("SELECT * FROM table_name WHERE deleted_at IS NULL;")
             .fetch_all(&mut self.inner)
             .await
             .map_err(|e| Error::SqlxError { source: e })?;
 
         Ok(rec)
     }
+
+    async fn delete(&mut self, table_id: TableId) -> Result<()> {
+        let deleted_at = Timestamp::from(self.time_provider.now());
+
+        // Soft-delete the table, retaining the record (and therefore the
+        // partition and parquet file records that reference it).
+        let result = sqlx::query(
+            r#"
+UPDATE table_name
+SET deleted_at = $1
+WHERE id = $2 AND deleted_at IS NULL;
+        "#,
+        )
+        .bind(deleted_at) // $1
+        .bind(table_id) // $2
+        .execute(&mut self.inner)
+        .await
+        .context(interface::CouldNotDeleteTableSnafu)?;
+
+        if result.rows_affected() == 0 {
+            return Err(Error::TableNotFound { id: table_id });
+        }
+
+        // Flag all the files of the table for deletion - the garbage collector
+        // removes the objects once the records are hard deleted.
+        sqlx::query(
+            r#"
+UPDATE parquet_file
+SET to_delete = $1
+WHERE table_id = $2 AND to_delete IS NULL;
+        "#,
+        )
+        .bind(deleted_at) // $1
+        .bind(table_id) // $2
+        .execute(&mut self.inner)
+        .await
+        .context(interface::CouldNotDeleteTableSnafu)?;
+
+        // The columns are not referenced by any other record.
+        sqlx::query(
+            r#"
+DELETE FROM column_name
+WHERE table_id = $1;
+        "#,
+        )
+        .bind(table_id) // $1
+        .execute(&mut self.inner)
+        .await
+        .context(interface::CouldNotDeleteTableSnafu)?;
+
+        Ok(())
+    }
+
+    async fn update_dedup_policy(
+        &mut self,
+        table_id: TableId,
+        dedup_policy: DeduplicationPolicy,
+    ) -> Result<Table> {
+        let rec = sqlx::query_as::<_, Table>(
+            r#"
+UPDATE table_name
+SET dedup_policy = $1
+WHERE id = $2 AND deleted_at IS NULL
+RETURNING *;
+        "#,
+        )
+        .bind(dedup_policy) // $1
+        .bind(table_id) // $2
+        .fetch_one(&mut self.inner)
+        .await;
+
+        let table = rec.map_err(|e| match e {
+            sqlx::Error::RowNotFound => Error::TableNotFound { id: table_id },
+            _ => Error::SqlxError { source: e },
+        })?;
+
+        Ok(table)
+    }
 }
 
 #[async_trait]
@@ -945,7 +1028,7 @@ RETURNING *;
             r#"
 SELECT column_name.* FROM table_name
 INNER JOIN column_name on column_name.table_id = table_name.id
-WHERE table_name.namespace_id = $1;
+WHERE table_name.namespace_id = $1 AND table_name.deleted_at IS NULL;
             "#,
         )
         .bind(namespace_id)
@@ -1657,13 +1740,20 @@ impl ParquetFileRepo for PostgresTxn {
             column_set,
         } = parquet_file_params;
 
+        // The file is only inserted if its table is not deleted. The table row
+        // is locked, so that a concurrent deletion either waits for the insert
+        // and flags the new file, or the insert waits for the deletion and
+        // inserts nothing.
         let rec = sqlx::query_as::<_, ParquetFile>(
             r#"
 INSERT INTO parquet_file (
     shard_id, table_id, partition_id, object_store_id,
     max_sequence_number, min_time, max_time, file_size_bytes,
     row_count, compaction_level, created_at, namespace_id, column_set )
-VALUES ( $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13 )
+SELECT $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13
+FROM table_name
+WHERE table_name.id = $2 AND table_name.deleted_at IS NULL
+FOR SHARE
 RETURNING *;
         "#,
         )
@@ -1680,7 +1770,7 @@ RETURNING *;
         .bind(created_at) // $11
         .bind(namespace_id) // $12
         .bind(column_set) // $13
-        .fetch_one(&mut self.inner)
+        .fetch_optional(&mut self.inner)
         .await
         .map_err(|e| {
             if is_unique_violation(&e) {
@@ -1690,7 +1780,8 @@ RETURNING *;
             } else {
                 Error::SqlxError { source: e }
             }
-        })?;
+        })?
+        .ok_or(Error::TableNotFound { id: table_id })?;
 
         Ok(rec)
     }
@@ -2345,6 +2436,180 @@ fn is_fk_violation(e: &sqlx::Error) -> bool {
     false
 }
 
+#[async_trait]
+impl DownsamplingTaskRepo for PostgresTxn {
+    async fn create(&mut self, params: DownsamplingTaskParams) -> Result<DownsamplingTask> {
+        let DownsamplingTaskParams {
+            name,
+            namespace_id,
+            query,
+            query_language,
+            target_namespace,
+            target_table,
+            every_ns,
+            delay_ns,
+            watermark,
+        } = params;
+
+        let rec = sqlx::query_as::<_, DownsamplingTask>(
+            r#"
+INSERT INTO downsampling_task
+    ( name, namespace_id, query, query_language, target_namespace, target_table, every_ns,
+      delay_ns, watermark )
+VALUES ( $1, $2, $3, $4, $5, $6, $7, $8, $9 )
+RETURNING *;
+        "#,
+        )
+        .bind(&name) // $1
+        .bind(namespace_id) // $2
+        .bind(query) // $3
+        .bind(query_language) // $4
+        .bind(target_namespace) // $5
+        .bind(target_table) // $6
+        .bind(every_ns) // $7
+        .bind(delay_ns) // $8
+        .bind(watermark) // $9
+        .fetch_one(&mut self.inner)
+        .await
+        .map_err(|e| {
+            if is_unique_violation(&e) {
+                Error::NameExists { name }
+            } else if is_fk_violation(&e) {
+                Error::ForeignKeyViolation { source: e }
+            } else {
+                Error::SqlxError { source: e }
+            }
+        })?;
+
+        Ok(rec)
+    }
+
+    async fn list(&mut self) -> Result<Vec<DownsamplingTask>> {
+        sqlx::query_as::<_, DownsamplingTask>(
+            r#"
+SELECT *
+FROM downsampling_task
+ORDER BY id;
+        "#,
+        )
+        .fetch_all(&mut self.inner)
+        .await
+        .map_err(|e| Error::SqlxError { source: e })
+    }
+
+    async fn get_by_name(&mut self, name: &str) -> Result<Option<DownsamplingTask>> {
+        let rec = sqlx::query_as::<_, DownsamplingTask>(
+            r#"
+SELECT *
+FROM downsampling_task
+WHERE name = $1;
+        "#,
+        )
+        .bind(name) // $1
+        .fetch_one(&mut self.inner)
+        .await;
+
+        if let Err(sqlx::Error::RowNotFound) = rec {
+            return Ok(None);
+        }
+
+        let task = rec.map_err(|e| Error::SqlxError { source: e })?;
+
+        Ok(Some(task))
+    }
+
+    async fn cas_watermark(
+        &mut self,
+        id: DownsamplingTaskId,
+        old_watermark: Timestamp,
+        new_watermark: Timestamp,
+    ) -> Result<DownsamplingTask, CasFailure<Timestamp>> {
+        let res = sqlx::query_as::<_, DownsamplingTask>(
+            r#"
+UPDATE downsampling_task
+SET watermark = $1
+WHERE id = $2 AND watermark = $3
+RETURNING *;
+        "#,
+        )
+        .bind(new_watermark) // $1
+        .bind(id) // $2
+        .bind(old_watermark) // $3
+        .fetch_one(&mut self.inner)
+        .await;
+
+        match res {
+            Ok(task) => Ok(task),
+            Err(sqlx::Error::RowNotFound) => {
+                // Either the task does not exist or its watermark differs, the
+                // same (racy) differentiation as for partition sort keys.
+                let watermark = sqlx::query_as::<_, DownsamplingTask>(
+                    r#"
+SELECT *
+FROM downsampling_task
+WHERE id = $1;
+                "#,
+                )
+                .bind(id) // $1
+                .fetch_one(&mut self.inner)
+                .await
+                .map_err(|e| match e {
+                    sqlx::Error::RowNotFound => {
+                        CasFailure::QueryError(Error::DownsamplingTaskNotFound { id })
+                    }
+                    e => CasFailure::QueryError(Error::SqlxError { source: e }),
+                })?
+                .watermark;
+                Err(CasFailure::ValueMismatch(watermark))
+            }
+            Err(e) => Err(CasFailure::QueryError(Error::SqlxError { source: e })),
+        }
+    }
+
+    async fn set_watermark(
+        &mut self,
+        id: DownsamplingTaskId,
+        watermark: Timestamp,
+    ) -> Result<DownsamplingTask> {
+        sqlx::query_as::<_, DownsamplingTask>(
+            r#"
+UPDATE downsampling_task
+SET watermark = $1
+WHERE id = $2
+RETURNING *;
+        "#,
+        )
+        .bind(watermark) // $1
+        .bind(id) // $2
+        .fetch_one(&mut self.inner)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::RowNotFound => Error::DownsamplingTaskNotFound { id },
+            _ => Error::SqlxError { source: e },
+        })
+    }
+
+    async fn delete(&mut self, id: DownsamplingTaskId) -> Result<()> {
+        let res = sqlx::query(
+            r#"
+DELETE FROM downsampling_task
+WHERE id = $1;
+        "#,
+        )
+        .bind(id) // $1
+        .execute(&mut self.inner)
+        .await
+        .map_err(|e| Error::SqlxError { source: e })?;
+
+        ensure!(
+            res.rows_affected() > 0,
+            interface::DownsamplingTaskNotFoundSnafu { id }
+        );
+
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -4,10 +4,14 @@
 pub(crate) mod context;
 pub mod field;
 pub mod fieldlist;
+pub(crate) mod gapfill;
 mod non_null_checker;
+mod query_memory;
 mod query_tracing;
+mod query_volatility;
 mod schema_pivot;
 pub mod seriesset;
+mod spill;
 pub(crate) mod split;
 pub mod stringset;
 use executor::DedicatedExecutor;
@@ -16,13 +20,14 @@ use parquet_file::storage::StorageId;
 use trace::span::{SpanExt, SpanRecorder};
 mod cross_rt_stream;
 
-use std::{collections::HashMap, sync::Arc};
+use std::{collections::HashMap, path::PathBuf, sync::Arc};
 
 use datafusion::{
     self,
     execution::{
         context::SessionState,
         disk_manager::DiskManagerConfig,
+        memory_pool::{FairSpillPool, GreedyMemoryPool, MemoryPool},
         runtime_env::{RuntimeConfig, RuntimeEnv},
     },
     logical_expr::{expr_rewriter::normalize_col, Extension},
@@ -31,7 +36,10 @@ use datafusion::{
 };
 
 pub use context::{IOxSessionConfig, IOxSessionContext, SessionContextIOxExt};
+use query_memory::FairShare;
+pub use query_volatility::QueryVolatility;
 use schema_pivot::SchemaPivotNode;
+use spill::SpillEnabled;
 
 use self::{non_null_checker::NonNullCheckerNode, split::StreamSplitNode};
 
@@ -49,6 +57,17 @@ pub struct ExecutorConfig {
 
     /// Memory pool size in bytes.
     pub mem_pool_size: usize,
+
+    /// Directory for temporary files of operators that exceed their share
+    /// of the memory pool.
+    ///
+    /// If set, sorts and stream splits spill to files in this directory
+    /// instead of failing, and each query may only reserve an even share of
+    /// the memory pool while others are running. Hash aggregations (GROUP BY)
+    /// of this DataFusion version can not spill, so they still fail with
+    /// "ResourcesExhausted" once they exceed that share. If not set, queries
+    /// fail with "ResourcesExhausted" once the memory pool is used up.
+    pub spill_dir: Option<PathBuf>,
 }
 
 #[derive(Debug)]
@@ -102,6 +121,10 @@ pub struct Executor {
     /// The DataFusion [RuntimeEnv] (including memory manager and disk
     /// manager) used for all executions
     runtime: Arc<RuntimeEnv>,
+
+    /// Divides the memory pool between concurrent queries, if spilling is
+    /// enabled
+    fair_share: Option<Arc<FairShare>>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -122,6 +145,7 @@ impl Executor {
             target_query_partitions: num_threads,
             object_stores: HashMap::default(),
             mem_pool_size,
+            spill_dir: None,
         })
     }
 
@@ -139,6 +163,7 @@ impl Executor {
             target_query_partitions: 1,
             object_stores: HashMap::default(),
             mem_pool_size: 1024 * 1024 * 1024, // 1GB
+            spill_dir: None,
         };
         let executors = Arc::new(DedicatedExecutors::new_testing());
         Self::new_with_config_and_executors(config, executors)
@@ -156,9 +181,26 @@ impl Executor {
     ) -> Self {
         assert_eq!(config.num_threads, executors.num_threads);
 
+        // Without a disk manager, nothing can spill, so operators only fail
+        // once the whole pool is used. With one, spilling operators get an
+        // even share of the pool and queries an even share of that.
+        let (disk_manager, memory_pool, fair_share): (_, Arc<dyn MemoryPool>, _) =
+            match &config.spill_dir {
+                Some(spill_dir) => (
+                    DiskManagerConfig::NewSpecified(vec![spill_dir.clone()]),
+                    Arc::new(FairSpillPool::new(config.mem_pool_size)),
+                    Some(Arc::new(FairShare::new(config.mem_pool_size))),
+                ),
+                None => (
+                    DiskManagerConfig::Disabled,
+                    Arc::new(GreedyMemoryPool::new(config.mem_pool_size)),
+                    None,
+                ),
+            };
+
         let runtime_config = RuntimeConfig::new()
-            .with_disk_manager(DiskManagerConfig::Disabled)
-            .with_memory_limit(config.mem_pool_size, 1.0);
+            .with_disk_manager(disk_manager)
+            .with_memory_pool(memory_pool);
 
         for (id, store) in &config.object_stores {
             runtime_config
@@ -172,6 +214,7 @@ impl Executor {
             executors,
             config,
             runtime,
+            fair_share,
         }
     }
 
@@ -180,8 +223,14 @@ impl Executor {
     /// Note that this context (and all its clones) will be shut down once `Executor` is dropped.
     pub fn new_execution_config(&self, executor_type: ExecutorType) -> IOxSessionConfig {
         let exec = self.executor(executor_type).clone();
-        IOxSessionConfig::new(exec, Arc::clone(&self.runtime))
+        let config = IOxSessionConfig::new(exec, Arc::clone(&self.runtime))
             .with_target_partitions(self.config.target_query_partitions)
+            .with_fair_share(self.fair_share.clone());
+
+        match self.config.spill_dir {
+            Some(_) => config.with_extension(Arc::new(SpillEnabled)),
+            None => config,
+        }
     }
 
     /// Get IOx context from DataFusion state.
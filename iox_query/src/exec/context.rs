@@ -3,7 +3,10 @@
 
 use super::{
     cross_rt_stream::CrossRtStream,
+    gapfill::{plan_gap_fill, GapFill},
     non_null_checker::NonNullCheckerNode,
+    query_memory::{FairShare, PeakMemoryPool},
+    query_volatility::QueryVolatility,
     seriesset::{series::Either, SeriesSet},
     split::StreamSplitNode,
 };
@@ -51,7 +54,10 @@ use datafusion_util::config::{iox_session_config, DEFAULT_CATALOG};
 use executor::DedicatedExecutor;
 use futures::{Stream, StreamExt, TryStreamExt};
 use observability_deps::tracing::debug;
-use query_functions::{register_scalar_functions, selectors::register_selector_aggregates};
+use query_functions::{
+    approx::register_approx_aggregates, register_scalar_functions,
+    selectors::register_selector_aggregates, transformations::register_transformation_aggregates,
+};
 use std::{convert::TryInto, fmt, sync::Arc};
 use trace::{
     ctx::SpanContext,
@@ -113,6 +119,15 @@ impl ExtensionPlanner for IOxExtensionPlanner {
                 non_null_checker.schema().as_ref().clone().into(),
                 non_null_checker.value(),
             )) as Arc<dyn ExecutionPlan>)
+        } else if let Some(gap_fill) = any.downcast_ref::<GapFill>() {
+            let gap_fill_exec = plan_gap_fill(
+                planner,
+                gap_fill,
+                logical_inputs,
+                physical_inputs,
+                session_state,
+            )?;
+            Some(Arc::new(gap_fill_exec) as Arc<dyn ExecutionPlan>)
         } else if let Some(stream_split) = any.downcast_ref::<StreamSplitNode>() {
             assert_eq!(
                 logical_inputs.len(),
@@ -168,6 +183,9 @@ pub struct IOxSessionConfig {
 
     /// Span context from which to create spans for this query
     span_ctx: Option<SpanContext>,
+
+    /// Share of the memory pool of the runtime this query may use, if limited
+    fair_share: Option<Arc<FairShare>>,
 }
 
 impl fmt::Debug for IOxSessionConfig {
@@ -186,6 +204,7 @@ impl IOxSessionConfig {
             runtime,
             default_catalog: None,
             span_ctx: None,
+            fair_share: None,
         }
     }
 
@@ -205,6 +224,22 @@ impl IOxSessionConfig {
         }
     }
 
+    /// Attach an extension to the DataFusion session of the query, e.g. to
+    /// collect information about the data that is read while the query is
+    /// planned
+    pub fn with_extension<T>(mut self, extension: Arc<T>) -> Self
+    where
+        T: Send + Sync + 'static,
+    {
+        self.session_config = self.session_config.with_extension(extension);
+        self
+    }
+
+    /// Limit this query to a fair share of the memory pool of the runtime
+    pub(super) fn with_fair_share(self, fair_share: Option<Arc<FairShare>>) -> Self {
+        Self { fair_share, ..self }
+    }
+
     /// Set the span context from which to create  distributed tracing spans for this query
     pub fn with_span_context(self, span_ctx: Option<SpanContext>) -> Self {
         Self { span_ctx, ..self }
@@ -220,10 +255,25 @@ impl IOxSessionConfig {
             .session_config
             .with_extension(Arc::new(recorder.span().cloned()));
 
-        let state = SessionState::with_config_rt(session_config, self.runtime)
+        // track the memory used by this query, while still sharing the limits
+        // (and disk manager / object stores) of the executor
+        let mut memory_pool = PeakMemoryPool::new(Arc::clone(&self.runtime.memory_pool));
+        if let Some(fair_share) = self.fair_share {
+            memory_pool = memory_pool.with_fair_share(fair_share);
+        }
+        let memory_pool = Arc::new(memory_pool);
+        let runtime = Arc::new(RuntimeEnv {
+            memory_pool: Arc::clone(&memory_pool) as _,
+            disk_manager: Arc::clone(&self.runtime.disk_manager),
+            object_store_registry: Arc::clone(&self.runtime.object_store_registry),
+        });
+
+        let state = SessionState::with_config_rt(session_config, runtime)
             .with_query_planner(Arc::new(IOxQueryPlanner {}));
 
         let state = register_selector_aggregates(state);
+        let state = register_transformation_aggregates(state);
+        let state = register_approx_aggregates(state);
         let mut state = register_scalar_functions(state);
         state.optimizer = iox_optimizer();
 
@@ -233,7 +283,7 @@ impl IOxSessionConfig {
             inner.register_catalog(DEFAULT_CATALOG, default_catalog);
         }
 
-        IOxSessionContext::new(inner, self.exec, recorder)
+        IOxSessionContext::new(inner, self.exec, recorder).with_memory_pool(memory_pool)
     }
 }
 
@@ -262,6 +312,9 @@ pub struct IOxSessionContext {
 
     /// Span context from which to create spans for this query
     recorder: SpanRecorder,
+
+    /// Memory pool tracking the memory reserved by this query, if any.
+    memory_pool: Option<Arc<PeakMemoryPool>>,
 }
 
 impl fmt::Debug for IOxSessionContext {
@@ -284,6 +337,7 @@ impl IOxSessionContext {
             inner: SessionContext::default(),
             exec: DedicatedExecutor::new_testing(),
             recorder: SpanRecorder::default(),
+            memory_pool: None,
         }
     }
 
@@ -297,9 +351,24 @@ impl IOxSessionContext {
             inner,
             exec,
             recorder,
+            memory_pool: None,
         }
     }
 
+    /// Track the memory used by this context using `memory_pool`.
+    fn with_memory_pool(self, memory_pool: Arc<PeakMemoryPool>) -> Self {
+        Self {
+            memory_pool: Some(memory_pool),
+            ..self
+        }
+    }
+
+    /// Returns the peak number of bytes reserved from the DataFusion memory
+    /// pool by this context (and all its children), if memory is tracked.
+    pub fn peak_memory(&self) -> Option<usize> {
+        self.memory_pool.as_ref().map(|pool| pool.peak())
+    }
+
     /// returns a reference to the inner datafusion execution context
     pub fn inner(&self) -> &SessionContext {
         &self.inner
@@ -347,6 +416,9 @@ impl IOxSessionContext {
     pub async fn create_physical_plan(&self, plan: &LogicalPlan) -> Result<Arc<dyn ExecutionPlan>> {
         let mut ctx = self.child_ctx("create_physical_plan");
         debug!(text=%plan.display_indent_schema(), "create_physical_plan: initial plan");
+        if let Some(volatility) = ctx.inner.state().config.get_extension::<QueryVolatility>() {
+            volatility.record(plan)?;
+        }
         let physical_plan = ctx.inner.create_physical_plan(plan).await?;
 
         ctx.recorder.event("physical plan");
@@ -631,11 +703,12 @@ impl IOxSessionContext {
 
     /// Returns a IOxSessionContext with a SpanRecorder that is a child of the current
     pub fn child_ctx(&self, name: &'static str) -> Self {
-        Self::new(
-            self.inner.clone(),
-            self.exec.clone(),
-            self.recorder.child(name),
-        )
+        Self {
+            inner: self.inner.clone(),
+            exec: self.exec.clone(),
+            recorder: self.recorder.child(name),
+            memory_pool: self.memory_pool.clone(),
+        }
     }
 
     /// Record an event on the span recorder
@@ -0,0 +1,1177 @@
+//! This module contains code for the "GapFill" DataFusion extension plan
+//! node.
+//!
+//! A GapFill node takes the output of an aggregation grouped by a time bucket
+//! (as produced by `date_bin`) and zero or more other group columns, and
+//! produces a row for every bucket within the time range of the query that has
+//! no input row, for each group.
+//!
+//! For this input, with a stride of 10 and a time range of `[10, 40]`:
+//!
+//!  region | time | avg
+//! --------+------+------
+//!   a     |  10  |  1.0
+//!   a     |  40  |  4.0
+//!   b     |  20  |  2.0
+//!
+//! The output would be:
+//!
+//!  region | time | avg
+//! --------+------+------
+//!   a     |  10  |  1.0
+//!   a     |  20  | NULL
+//!   a     |  30  | NULL
+//!   a     |  40  |  4.0
+//!   b     |  10  | NULL
+//!   b     |  20  |  2.0
+//!   b     |  30  | NULL
+//!   b     |  40  | NULL
+//!
+//! The aggregate columns of the rows produced for missing buckets are filled
+//! according to the [`FillStrategy`] of each column, so with
+//! [`FillStrategy::Locf`] the `avg` of region `a` at time `20` and `30` would
+//! be `1.0`, and with [`FillStrategy::Interpolate`] they would be `2.0` and
+//! `3.0`.
+//!
+//! The output is sorted by the group columns, then time.
+//!
+//! Queries filling more than [`MAX_GAP_FILL_BUCKETS`] time buckets are
+//! rejected, and the buffered input and the output are accounted for in the
+//! memory pool of the query, so a small stride over a wide time range fails
+//! the query rather than exhausting the memory of the querier.
+//!
+//! GapFill nodes are created by the [`HandleGapFill`] logical optimizer rule
+//! from queries using the `date_bin_gapfill` function.
+//!
+//! [`HandleGapFill`]: crate::logical_optimizer::HandleGapFill
+
+use std::{
+    any::Any,
+    fmt::{self, Debug},
+    ops::Bound,
+    sync::Arc,
+};
+
+use arrow::{
+    array::{Array, ArrayRef, BooleanArray, Float64Array, TimestampNanosecondArray, UInt32Array},
+    compute::{self, SortColumn},
+    datatypes::{DataType, SchemaRef},
+    error::ArrowError,
+    record_batch::RecordBatch,
+};
+use datafusion::{
+    common::DFSchemaRef,
+    error::{DataFusionError as Error, Result},
+    execution::{
+        context::{SessionState, TaskContext},
+        memory_pool::{MemoryConsumer, MemoryReservation},
+    },
+    logical_expr::{Expr, LogicalPlan, UserDefinedLogicalNode},
+    physical_plan::{
+        coalesce_partitions::CoalescePartitionsExec,
+        expressions::PhysicalSortExpr,
+        metrics::{BaselineMetrics, ExecutionPlanMetricsSet, MetricsSet},
+        stream::RecordBatchStreamAdapter,
+        ColumnarValue, DisplayFormatType, Distribution, ExecutionPlan, Partitioning,
+        PhysicalPlanner, SendableRecordBatchStream, Statistics,
+    },
+    scalar::ScalarValue,
+};
+use futures::{FutureExt, StreamExt};
+use observability_deps::tracing::debug;
+use schema::TIME_DATA_TYPE;
+
+const NANOS_PER_MILLI: i64 = 1_000_000;
+const NANOS_PER_DAY: i64 = 86_400 * 1_000 * NANOS_PER_MILLI;
+
+/// The maximum number of time buckets a query may fill, for each group.
+pub(crate) const MAX_GAP_FILL_BUCKETS: i64 = 1_000_000;
+
+/// How to fill the value of an aggregate column in the rows produced for
+/// buckets with no input row.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FillStrategy {
+    /// Fill with `NULL`.
+    Null,
+    /// Fill with the last non-null value of the group ("last observation
+    /// carried forward"). Null values of existing rows are filled as well.
+    Locf,
+    /// Fill with the value linearly interpolated between the previous and next
+    /// non-null values of the group. Null values of existing rows are filled
+    /// as well. Only supported for numeric columns.
+    Interpolate,
+}
+
+/// The parameters of a [`GapFill`] node, taken from the arguments of the
+/// `date_bin_gapfill` call and the time predicates of the query.
+#[derive(Debug, Clone)]
+pub struct GapFillParams {
+    /// The width of each time bucket, an interval.
+    pub stride: Expr,
+    /// The timestamp the time buckets are aligned to.
+    pub origin: Expr,
+    /// The lower bound of the time range to fill.
+    pub lower: Bound<Expr>,
+    /// The upper bound of the time range to fill.
+    pub upper: Bound<Expr>,
+}
+
+/// Implements the GapFill operation as described in this module's
+/// documentation.
+#[derive(Clone)]
+pub struct GapFill {
+    input: Arc<LogicalPlan>,
+    /// The group columns of the input, including `time_column`.
+    group_expr: Vec<Expr>,
+    /// The aggregate columns of the input.
+    aggr_expr: Vec<Expr>,
+    /// The time bucket column of the input.
+    time_column: Expr,
+    params: GapFillParams,
+    /// The fill strategy of each of `aggr_expr`.
+    fill_strategy: Vec<FillStrategy>,
+}
+
+impl GapFill {
+    /// Create a new [`GapFill`] node filling the aggregate columns
+    /// `aggr_expr` of `input` with nulls.
+    pub fn try_new(
+        input: Arc<LogicalPlan>,
+        group_expr: Vec<Expr>,
+        aggr_expr: Vec<Expr>,
+        time_column: Expr,
+        params: GapFillParams,
+    ) -> Result<Self> {
+        if matches!(params.lower, Bound::Unbounded) {
+            return Err(Error::Plan(
+                "gap-filling query is missing a lower time bound".to_string(),
+            ));
+        }
+        if matches!(params.upper, Bound::Unbounded) {
+            return Err(Error::Plan(
+                "gap-filling query is missing an upper time bound".to_string(),
+            ));
+        }
+
+        let fill_strategy = vec![FillStrategy::Null; aggr_expr.len()];
+        Ok(Self {
+            input,
+            group_expr,
+            aggr_expr,
+            time_column,
+            params,
+            fill_strategy,
+        })
+    }
+
+    /// The aggregate columns filled by this node.
+    pub fn aggr_expr(&self) -> &[Expr] {
+        &self.aggr_expr
+    }
+
+    /// Return a copy of this node that fills the aggregate column at `index`
+    /// of [`Self::aggr_expr`] with `strategy`.
+    pub fn with_fill_strategy(&self, index: usize, strategy: FillStrategy) -> Self {
+        let mut new = self.clone();
+        new.fill_strategy[index] = strategy;
+        new
+    }
+}
+
+impl Debug for GapFill {
+    /// Use explain format for the Debug format.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_for_explain(f)
+    }
+}
+
+impl UserDefinedLogicalNode for GapFill {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn inputs(&self) -> Vec<&LogicalPlan> {
+        vec![self.input.as_ref()]
+    }
+
+    /// The output schema is the same as the input schema.
+    fn schema(&self) -> &DFSchemaRef {
+        self.input.schema()
+    }
+
+    /// The group and aggregate columns, followed by the time column, stride,
+    /// origin and time bounds.
+    fn expressions(&self) -> Vec<Expr> {
+        self.group_expr
+            .iter()
+            .chain(&self.aggr_expr)
+            .chain([
+                &self.time_column,
+                &self.params.stride,
+                &self.params.origin,
+                bound_expr(&self.params.lower),
+                bound_expr(&self.params.upper),
+            ])
+            .cloned()
+            .collect()
+    }
+
+    /// For example: `GapFill: groupBy=[[region, time]], aggr=[[AVG(cpu)]], fill=[[locf]], time_column=time, stride=IntervalDayTime("60000"), range=[lower, upper)`
+    fn fmt_for_explain(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let fill = self
+            .fill_strategy
+            .iter()
+            .map(|s| match s {
+                FillStrategy::Null => "null",
+                FillStrategy::Locf => "locf",
+                FillStrategy::Interpolate => "interpolate",
+            })
+            .collect::<Vec<_>>();
+
+        write!(
+            f,
+            "GapFill: groupBy=[{:?}], aggr=[{:?}], fill=[[{}]], time_column={}, stride={}, range={}{}, {}{}",
+            self.group_expr,
+            self.aggr_expr,
+            fill.join(", "),
+            self.time_column,
+            self.params.stride,
+            match self.params.lower {
+                Bound::Excluded(_) => "(",
+                _ => "[",
+            },
+            bound_expr(&self.params.lower),
+            bound_expr(&self.params.upper),
+            match self.params.upper {
+                Bound::Included(_) => "]",
+                _ => ")",
+            },
+        )
+    }
+
+    fn from_template(
+        &self,
+        exprs: &[Expr],
+        inputs: &[LogicalPlan],
+    ) -> Arc<dyn UserDefinedLogicalNode> {
+        assert_eq!(inputs.len(), 1, "GapFill: input sizes inconsistent");
+        assert_eq!(
+            exprs.len(),
+            self.group_expr.len() + self.aggr_expr.len() + 5,
+            "GapFill: expression sizes inconsistent"
+        );
+
+        let (group_expr, rest) = exprs.split_at(self.group_expr.len());
+        let (aggr_expr, rest) = rest.split_at(self.aggr_expr.len());
+
+        Arc::new(Self {
+            input: Arc::new(inputs[0].clone()),
+            group_expr: group_expr.to_vec(),
+            aggr_expr: aggr_expr.to_vec(),
+            time_column: rest[0].clone(),
+            params: GapFillParams {
+                stride: rest[1].clone(),
+                origin: rest[2].clone(),
+                lower: with_bound_expr(&self.params.lower, rest[3].clone()),
+                upper: with_bound_expr(&self.params.upper, rest[4].clone()),
+            },
+            fill_strategy: self.fill_strategy.clone(),
+        })
+    }
+}
+
+/// The expression of a bound, which must not be [`Bound::Unbounded`].
+fn bound_expr(bound: &Bound<Expr>) -> &Expr {
+    match bound {
+        Bound::Included(e) | Bound::Excluded(e) => e,
+        Bound::Unbounded => unreachable!("GapFill time bounds are validated on creation"),
+    }
+}
+
+/// Replace the expression of `bound`, retaining its inclusivity.
+fn with_bound_expr(bound: &Bound<Expr>, expr: Expr) -> Bound<Expr> {
+    match bound {
+        Bound::Included(_) => Bound::Included(expr),
+        Bound::Excluded(_) => Bound::Excluded(expr),
+        Bound::Unbounded => Bound::Unbounded,
+    }
+}
+
+// ------ The implementation of GapFill code follows -----
+
+/// Create a [`GapFillExec`] for the logical `gap_fill` node, evaluating its
+/// (constant) stride, origin and time bounds.
+pub(crate) fn plan_gap_fill(
+    planner: &dyn PhysicalPlanner,
+    gap_fill: &GapFill,
+    logical_inputs: &[&LogicalPlan],
+    physical_inputs: &[Arc<dyn ExecutionPlan>],
+    session_state: &SessionState,
+) -> Result<GapFillExec> {
+    assert_eq!(
+        logical_inputs.len(),
+        1,
+        "Inconsistent number of logical inputs"
+    );
+    assert_eq!(
+        physical_inputs.len(),
+        1,
+        "Inconsistent number of physical inputs"
+    );
+
+    let input_dfschema = logical_inputs[0].schema();
+    let mut input = Arc::clone(&physical_inputs[0]);
+    if input.output_partitioning().partition_count() > 1 {
+        input = Arc::new(CoalescePartitionsExec::new(input));
+    }
+    let input_schema = input.schema();
+
+    let index_of = |e: &Expr| match e {
+        Expr::Column(c) => input_schema.index_of(&c.name).map_err(Error::ArrowError),
+        e => Err(Error::Internal(format!(
+            "GapFill expression is not a column: {}",
+            e
+        ))),
+    };
+
+    let evaluate = |e: &Expr| {
+        let expr = planner.create_physical_expr(e, input_dfschema, &input_schema, session_state)?;
+        match expr.evaluate(&RecordBatch::new_empty(Arc::clone(&input_schema)))? {
+            ColumnarValue::Scalar(v) => Ok(v),
+            ColumnarValue::Array(_) => Err(Error::Plan(format!(
+                "gap-filling parameter is not a constant: {}",
+                e
+            ))),
+        }
+    };
+
+    let time_index = index_of(&gap_fill.time_column)?;
+    let group_indexes = gap_fill
+        .group_expr
+        .iter()
+        .map(index_of)
+        .filter(|i| !matches!(i, Ok(i) if *i == time_index))
+        .collect::<Result<Vec<_>>>()?;
+    let aggr = gap_fill
+        .aggr_expr
+        .iter()
+        .map(index_of)
+        .zip(gap_fill.fill_strategy.iter().copied())
+        .map(|(i, strategy)| i.map(|i| (i, strategy)))
+        .collect::<Result<Vec<_>>>()?;
+
+    let stride = stride_nanos(&evaluate(&gap_fill.params.stride)?)?;
+    let origin = timestamp_nanos(&evaluate(&gap_fill.params.origin)?)?;
+    let lower = match &gap_fill.params.lower {
+        Bound::Excluded(e) => timestamp_nanos(&evaluate(e)?)?.saturating_add(1),
+        bound => timestamp_nanos(&evaluate(bound_expr(bound))?)?,
+    };
+    let upper = match &gap_fill.params.upper {
+        Bound::Excluded(e) => timestamp_nanos(&evaluate(e)?)?.saturating_sub(1),
+        bound => timestamp_nanos(&evaluate(bound_expr(bound))?)?,
+    };
+
+    let params = GapFillExecParams {
+        stride,
+        first: date_bin(stride, lower, origin)?,
+        last: date_bin(stride, upper, origin)?,
+    };
+    check_num_buckets(&params)?;
+
+    Ok(GapFillExec::new(
+        input,
+        time_index,
+        group_indexes,
+        aggr,
+        params,
+    ))
+}
+
+/// Convert a (day-time or month-day-nano) interval into nanoseconds.
+fn stride_nanos(v: &ScalarValue) -> Result<i64> {
+    let nanos = match v {
+        ScalarValue::IntervalDayTime(Some(v)) => {
+            let days = (v >> 32) as i32 as i64;
+            let millis = *v as i32 as i64;
+            days.checked_mul(NANOS_PER_DAY)
+                .and_then(|n| n.checked_add(millis * NANOS_PER_MILLI))
+        }
+        ScalarValue::IntervalMonthDayNano(Some(v)) => {
+            let months = (v >> 96) as i32;
+            let days = (v >> 64) as i32 as i64;
+            let nanos = *v as i64;
+            if months != 0 {
+                return Err(Error::NotImplemented(
+                    "date_bin_gapfill does not support strides of months or years".to_string(),
+                ));
+            }
+            days.checked_mul(NANOS_PER_DAY)
+                .and_then(|n| n.checked_add(nanos))
+        }
+        v => {
+            return Err(Error::Plan(format!(
+                "date_bin_gapfill stride must be an interval, got {}",
+                v
+            )))
+        }
+    };
+
+    match nanos {
+        Some(n) if n > 0 => Ok(n),
+        _ => Err(Error::Plan(format!(
+            "date_bin_gapfill stride must be positive, got {}",
+            v
+        ))),
+    }
+}
+
+/// Convert a timestamp (or a value that can be cast to one) into nanoseconds
+/// since the epoch.
+fn timestamp_nanos(v: &ScalarValue) -> Result<i64> {
+    let array = compute::cast(&v.to_array(), &TIME_DATA_TYPE())?;
+    let array = array
+        .as_any()
+        .downcast_ref::<TimestampNanosecondArray>()
+        .expect("cast to nanosecond timestamp");
+
+    if array.is_null(0) {
+        return Err(Error::Plan(
+            "gap-filling time bounds and origin must not be null".to_string(),
+        ));
+    }
+    Ok(array.value(0))
+}
+
+/// The start of the bucket of width `stride`, aligned to `origin`, containing
+/// `ts`.
+fn date_bin(stride: i64, ts: i64, origin: i64) -> Result<i64> {
+    let (stride, ts, origin) = (stride as i128, ts as i128, origin as i128);
+    let bin = origin + (ts - origin).div_euclid(stride) * stride;
+    i64::try_from(bin)
+        .map_err(|_| Error::Plan(format!("gap-filling time bucket out of range: {}", bin)))
+}
+
+/// Return an error if `params` fill more than [`MAX_GAP_FILL_BUCKETS`] time
+/// buckets.
+fn check_num_buckets(params: &GapFillExecParams) -> Result<()> {
+    let num_buckets = (params.last as i128 - params.first as i128) / params.stride as i128 + 1;
+    if num_buckets > MAX_GAP_FILL_BUCKETS as i128 {
+        return Err(Error::Plan(format!(
+            "date_bin_gapfill would fill {} time buckets, more than the maximum of {}; \
+             use a larger stride or a narrower time range",
+            num_buckets, MAX_GAP_FILL_BUCKETS
+        )));
+    }
+    Ok(())
+}
+
+/// The evaluated time parameters of a [`GapFillExec`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct GapFillExecParams {
+    /// The width of each time bucket, in nanoseconds.
+    stride: i64,
+    /// The first time bucket to fill.
+    first: i64,
+    /// The last time bucket to fill (inclusive).
+    last: i64,
+}
+
+/// Physical operator that implements the GapFill operation.
+///
+/// The input is buffered in its entirety, as it is the (usually small) output
+/// of an aggregation. The buffered input and the output are tracked with a
+/// [`MemoryReservation`] of the query memory pool.
+pub struct GapFillExec {
+    input: Arc<dyn ExecutionPlan>,
+    /// The index of the time bucket column in the input.
+    time_index: usize,
+    /// The indexes of the other group columns in the input.
+    group_indexes: Vec<usize>,
+    /// The indexes of the aggregate columns in the input, and how to fill
+    /// them.
+    aggr: Vec<(usize, FillStrategy)>,
+    params: GapFillExecParams,
+    /// Execution metrics
+    metrics: ExecutionPlanMetricsSet,
+}
+
+impl GapFillExec {
+    pub(crate) fn new(
+        input: Arc<dyn ExecutionPlan>,
+        time_index: usize,
+        group_indexes: Vec<usize>,
+        aggr: Vec<(usize, FillStrategy)>,
+        params: GapFillExecParams,
+    ) -> Self {
+        Self {
+            input,
+            time_index,
+            group_indexes,
+            aggr,
+            params,
+            metrics: ExecutionPlanMetricsSet::new(),
+        }
+    }
+}
+
+impl Debug for GapFillExec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "GapFillExec")
+    }
+}
+
+impl ExecutionPlan for GapFillExec {
+    fn as_any(&self) -> &(dyn std::any::Any + 'static) {
+        self
+    }
+
+    fn schema(&self) -> SchemaRef {
+        self.input.schema()
+    }
+
+    fn output_partitioning(&self) -> Partitioning {
+        Partitioning::UnknownPartitioning(1)
+    }
+
+    fn output_ordering(&self) -> Option<&[PhysicalSortExpr]> {
+        None
+    }
+
+    fn required_input_distribution(&self) -> Vec<Distribution> {
+        vec![Distribution::SinglePartition]
+    }
+
+    fn children(&self) -> Vec<Arc<dyn ExecutionPlan>> {
+        vec![Arc::clone(&self.input)]
+    }
+
+    fn with_new_children(
+        self: Arc<Self>,
+        children: Vec<Arc<dyn ExecutionPlan>>,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        match children.len() {
+            1 => Ok(Arc::new(Self::new(
+                Arc::clone(&children[0]),
+                self.time_index,
+                self.group_indexes.clone(),
+                self.aggr.clone(),
+                self.params,
+            ))),
+            _ => Err(Error::Internal(
+                "GapFillExec wrong number of children".to_string(),
+            )),
+        }
+    }
+
+    fn execute(
+        &self,
+        partition: usize,
+        context: Arc<TaskContext>,
+    ) -> Result<SendableRecordBatchStream> {
+        debug!(partition, "Start GapFillExec::execute");
+        if partition != 0 {
+            return Err(Error::Internal(format!(
+                "GapFillExec invalid partition {}",
+                partition
+            )));
+        }
+
+        let baseline_metrics = BaselineMetrics::new(&self.metrics, partition);
+        let mut reservation =
+            MemoryConsumer::new("GapFillExec").register(&context.runtime_env().memory_pool);
+        let mut input_stream = self.input.execute(partition, context)?;
+        let schema = self.schema();
+        let filler = Filler {
+            time_index: self.time_index,
+            group_indexes: self.group_indexes.clone(),
+            aggr: self.aggr.clone(),
+            params: self.params,
+        };
+
+        let fut = async move {
+            let mut batches = vec![];
+            while let Some(batch) = input_stream.next().await {
+                let batch = batch?;
+                reservation.try_grow(batch_size(&batch))?;
+                batches.push(batch);
+            }
+
+            let timer = baseline_metrics.elapsed_compute().timer();
+            let batch = filler.fill(&schema, &batches, &mut reservation)?;
+            timer.done();
+
+            baseline_metrics.record_output(batch.num_rows());
+            Ok(batch)
+        };
+        let stream = futures::stream::once(fut.map(|res: Result<RecordBatch>| {
+            res.map_err(|e| ArrowError::ExternalError(Box::new(e)))
+        }));
+
+        debug!(partition, "End GapFillExec::execute");
+        Ok(Box::pin(RecordBatchStreamAdapter::new(
+            self.schema(),
+            stream,
+        )))
+    }
+
+    fn fmt_as(&self, t: DisplayFormatType, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match t {
+            DisplayFormatType::Default => {
+                write!(
+                    f,
+                    "GapFillExec: stride={}, first={}, last={}",
+                    self.params.stride, self.params.first, self.params.last
+                )
+            }
+        }
+    }
+
+    fn metrics(&self) -> Option<MetricsSet> {
+        Some(self.metrics.clone_inner())
+    }
+
+    fn statistics(&self) -> Statistics {
+        // don't know anything about the statistics
+        Statistics::default()
+    }
+}
+
+/// A row of the output of a [`Filler`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OutputRow {
+    /// A row of the (sorted) input.
+    Input(usize),
+    /// A row produced for a missing time bucket.
+    Gap(i64),
+}
+
+/// Fills the gaps of the buffered input of a [`GapFillExec`].
+#[derive(Debug)]
+struct Filler {
+    time_index: usize,
+    group_indexes: Vec<usize>,
+    aggr: Vec<(usize, FillStrategy)>,
+    params: GapFillExecParams,
+}
+
+impl Filler {
+    /// Fill the gaps of the input `batches`, growing `reservation` by the
+    /// (estimated) size of the output before building it.
+    fn fill(
+        &self,
+        schema: &SchemaRef,
+        batches: &[RecordBatch],
+        reservation: &mut MemoryReservation,
+    ) -> Result<RecordBatch> {
+        let batch = self.sort(compute::concat_batches(schema, batches)?)?;
+
+        let times = batch
+            .column(self.time_index)
+            .as_any()
+            .downcast_ref::<TimestampNanosecondArray>()
+            .ok_or_else(|| {
+                Error::Internal(format!(
+                    "GapFill time column has unexpected type {}",
+                    batch.column(self.time_index).data_type()
+                ))
+            })?;
+
+        // The output rows of each group, and the index of the first input row
+        // of the group (that holds its group column values).
+        let mut groups = vec![];
+        for range in self.group_ranges(&batch)? {
+            let mut rows = vec![];
+            let mut row = range.start;
+            let mut bucket = (self.params.first <= self.params.last).then_some(self.params.first);
+
+            loop {
+                let row_time =
+                    (row < range.end).then(|| times.is_valid(row).then(|| times.value(row)));
+                match (row_time, bucket) {
+                    (None, None) => break,
+                    // Pass through input rows outside of the range to fill.
+                    (Some(Some(t)), Some(b)) if t < b => {
+                        rows.push(OutputRow::Input(row));
+                        row += 1;
+                    }
+                    (Some(_), None) | (Some(None), Some(_)) => {
+                        rows.push(OutputRow::Input(row));
+                        row += 1;
+                    }
+                    (Some(Some(t)), Some(b)) if t == b => {
+                        rows.push(OutputRow::Input(row));
+                        row += 1;
+                        bucket = self.next_bucket(b);
+                    }
+                    (_, Some(b)) => {
+                        rows.push(OutputRow::Gap(b));
+                        bucket = self.next_bucket(b);
+                    }
+                }
+            }
+
+            reservation.try_grow(rows.len() * std::mem::size_of::<OutputRow>())?;
+            groups.push((range.start, rows));
+        }
+
+        // Reserve the output, estimating the size of each row from the input.
+        let num_rows = groups.iter().map(|(_, rows)| rows.len()).sum::<usize>();
+        let row_size = batch_size(&batch)
+            .checked_div(batch.num_rows())
+            .unwrap_or_default()
+            + std::mem::size_of::<u32>()
+            + std::mem::size_of::<Option<i64>>();
+        reservation.try_grow(num_rows.saturating_mul(row_size))?;
+
+        let mut group_take = Vec::with_capacity(num_rows);
+        let mut output_times = Vec::with_capacity(num_rows);
+        for (group_row, rows) in &groups {
+            for row in rows {
+                group_take.push(*group_row as u32);
+                output_times.push(match row {
+                    OutputRow::Input(i) => times.is_valid(*i).then(|| times.value(*i)),
+                    OutputRow::Gap(t) => Some(*t),
+                });
+            }
+        }
+        let group_take = UInt32Array::from(group_take);
+
+        let columns = (0..batch.num_columns())
+            .map(|i| {
+                let column = batch.column(i);
+                if i == self.time_index {
+                    let times = TimestampNanosecondArray::from(output_times.clone());
+                    return Ok(compute::cast(
+                        &(Arc::new(times) as ArrayRef),
+                        column.data_type(),
+                    )?);
+                }
+
+                match self.aggr.iter().find(|(index, _)| *index == i) {
+                    Some((_, strategy)) => fill_column(column, *strategy, &groups, &output_times),
+                    // A group column
+                    None => Ok(compute::take(column.as_ref(), &group_take, None)?),
+                }
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(RecordBatch::try_new(Arc::clone(schema), columns)?)
+    }
+
+    /// Sort `batch` by the group columns, then time.
+    fn sort(&self, batch: RecordBatch) -> Result<RecordBatch> {
+        let sort_columns = self
+            .group_indexes
+            .iter()
+            .chain(std::iter::once(&self.time_index))
+            .map(|&i| SortColumn {
+                values: Arc::clone(batch.column(i)),
+                options: None,
+            })
+            .collect::<Vec<_>>();
+
+        let indices = compute::lexsort_to_indices(&sort_columns, None)?;
+        let columns = batch
+            .columns()
+            .iter()
+            .map(|c| compute::take(c.as_ref(), &indices, None))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(RecordBatch::try_new(batch.schema(), columns)?)
+    }
+
+    /// The ranges of rows of the sorted `batch` belonging to each group.
+    fn group_ranges(&self, batch: &RecordBatch) -> Result<Vec<std::ops::Range<usize>>> {
+        // Without group columns all the rows belong to a single group, that is
+        // filled even if there are no rows at all.
+        if self.group_indexes.is_empty() {
+            return Ok(vec![0..batch.num_rows()]);
+        }
+
+        let sort_columns = self
+            .group_indexes
+            .iter()
+            .map(|&i| SortColumn {
+                values: Arc::clone(batch.column(i)),
+                options: None,
+            })
+            .collect::<Vec<_>>();
+
+        Ok(compute::lexicographical_partition_ranges(&sort_columns)?.collect())
+    }
+
+    fn next_bucket(&self, bucket: i64) -> Option<i64> {
+        bucket
+            .checked_add(self.params.stride)
+            .filter(|b| *b <= self.params.last)
+    }
+}
+
+/// The memory used by the arrays of `batch`.
+fn batch_size(batch: &RecordBatch) -> usize {
+    batch
+        .columns()
+        .iter()
+        .map(|c| c.get_array_memory_size())
+        .sum()
+}
+
+/// Build the output of the aggregate `column` for the output rows of
+/// `groups`, filling the gaps with `strategy`.
+fn fill_column(
+    column: &ArrayRef,
+    strategy: FillStrategy,
+    groups: &[(usize, Vec<OutputRow>)],
+    output_times: &[Option<i64>],
+) -> Result<ArrayRef> {
+    let rows = groups.iter().flat_map(|(_, rows)| rows);
+
+    match strategy {
+        FillStrategy::Null => {
+            let take = rows
+                .map(|row| match row {
+                    OutputRow::Input(i) => Some(*i as u32),
+                    OutputRow::Gap(_) => None,
+                })
+                .collect::<UInt32Array>();
+            Ok(compute::take(column.as_ref(), &take, None)?)
+        }
+        FillStrategy::Locf => {
+            let mut take = Vec::with_capacity(output_times.len());
+            for (_, rows) in groups {
+                let mut last = None;
+                for row in rows {
+                    match row {
+                        OutputRow::Input(i) if column.is_valid(*i) => {
+                            last = Some(*i as u32);
+                            take.push(last);
+                        }
+                        _ => take.push(last),
+                    }
+                }
+            }
+            Ok(compute::take(
+                column.as_ref(),
+                &UInt32Array::from(take),
+                None,
+            )?)
+        }
+        FillStrategy::Interpolate => interpolate_column(column, groups, output_times),
+    }
+}
+
+/// Fill the gaps of the numeric `column` by linear interpolation.
+fn interpolate_column(
+    column: &ArrayRef,
+    groups: &[(usize, Vec<OutputRow>)],
+    output_times: &[Option<i64>],
+) -> Result<ArrayRef> {
+    use DataType::*;
+    if !matches!(
+        column.data_type(),
+        Int8 | Int16 | Int32 | Int64 | UInt8 | UInt16 | UInt32 | UInt64 | Float32 | Float64
+    ) {
+        return Err(Error::NotImplemented(format!(
+            "interpolate is not supported for columns of type {}",
+            column.data_type()
+        )));
+    }
+
+    let values = compute::cast(column, &Float64)?;
+    let values = values
+        .as_any()
+        .downcast_ref::<Float64Array>()
+        .expect("cast to float64");
+
+    // The input value and time of each output row, if known.
+    let mut known = Vec::with_capacity(output_times.len());
+    for (row, time) in groups.iter().flat_map(|(_, rows)| rows).zip(output_times) {
+        known.push(match (row, time) {
+            (OutputRow::Input(i), Some(t)) if values.is_valid(*i) => Some((*t, values.value(*i))),
+            _ => None,
+        });
+    }
+
+    let mut interpolated = vec![None; output_times.len()];
+    let mut offset = 0;
+    for (_, rows) in groups {
+        let range = offset..offset + rows.len();
+        offset = range.end;
+
+        let mut prev = None;
+        for i in range.clone() {
+            match known[i] {
+                Some(k) => prev = Some(k),
+                None => interpolated[i] = prev.map(|p| (p, None)),
+            }
+        }
+
+        let mut next = None;
+        for i in range.rev() {
+            match known[i] {
+                Some(k) => next = Some(k),
+                None => {
+                    interpolated[i] = match (interpolated[i], next, output_times[i]) {
+                        (Some((p, _)), Some(n), Some(_)) => Some((p, Some(n))),
+                        _ => None,
+                    }
+                }
+            }
+        }
+    }
+
+    let interpolated = interpolated
+        .into_iter()
+        .zip(output_times)
+        .map(|(v, t)| match (v, t) {
+            (Some(((t0, v0), Some((t1, v1)))), Some(t)) => {
+                Some(v0 + (v1 - v0) * (t - t0) as f64 / (t1 - t0) as f64)
+            }
+            _ => None,
+        })
+        .collect::<Float64Array>();
+    let interpolated = compute::cast(&(Arc::new(interpolated) as ArrayRef), column.data_type())?;
+
+    // Use the input values as-is where they exist, to avoid any loss of
+    // precision through the float conversion.
+    let take = groups
+        .iter()
+        .flat_map(|(_, rows)| rows)
+        .map(|row| match row {
+            OutputRow::Input(i) => Some(*i as u32),
+            OutputRow::Gap(_) => None,
+        })
+        .collect::<UInt32Array>();
+    let existing = compute::take(column.as_ref(), &take, None)?;
+    let mask = known
+        .iter()
+        .map(|k| Some(k.is_some()))
+        .collect::<BooleanArray>();
+
+    Ok(compute::kernels::zip::zip(
+        &mask,
+        existing.as_ref(),
+        interpolated.as_ref(),
+    )?)
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow::{
+        array::{Float64Array, Int64Array, StringArray},
+        datatypes::{Field, Schema},
+    };
+    use arrow_util::assert_batches_eq;
+    use datafusion::{
+        execution::{
+            memory_pool::GreedyMemoryPool,
+            runtime_env::{RuntimeConfig, RuntimeEnv},
+        },
+        physical_plan::{common::collect, memory::MemoryExec},
+        prelude::{SessionConfig, SessionContext},
+    };
+
+    use super::*;
+    use crate::exec::{Executor, ExecutorType};
+
+    fn input_batch() -> RecordBatch {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("region", DataType::Utf8, true),
+            Field::new("time", TIME_DATA_TYPE(), true),
+            Field::new("avg", DataType::Float64, true),
+            Field::new("count", DataType::Int64, true),
+        ]));
+
+        // Deliberately out of order.
+        RecordBatch::try_new(
+            schema,
+            vec![
+                Arc::new(StringArray::from(vec!["b", "a", "a"])),
+                Arc::new(TimestampNanosecondArray::from(vec![20, 40, 10])),
+                Arc::new(Float64Array::from(vec![2.0, 4.0, 1.0])),
+                Arc::new(Int64Array::from(vec![2, 7, 1])),
+            ],
+        )
+        .unwrap()
+    }
+
+    async fn run(
+        batch: RecordBatch,
+        group_indexes: Vec<usize>,
+        strategy: FillStrategy,
+    ) -> Vec<RecordBatch> {
+        let schema = batch.schema();
+        let input = Arc::new(MemoryExec::try_new(&[vec![batch]], schema, None).unwrap());
+        let exec = Arc::new(GapFillExec::new(
+            input,
+            1,
+            group_indexes,
+            vec![(2, strategy), (3, strategy)],
+            GapFillExecParams {
+                stride: 10,
+                first: 10,
+                last: 40,
+            },
+        ));
+
+        let executor = Executor::new_testing();
+        let ctx = executor.new_context(ExecutorType::Query);
+        ctx.collect(exec).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_fill_null() {
+        let got = run(input_batch(), vec![0], FillStrategy::Null).await;
+        assert_batches_eq!(
+            [
+                "+--------+--------------------------------+-----+-------+",
+                "| region | time                           | avg | count |",
+                "+--------+--------------------------------+-----+-------+",
+                "| a      | 1970-01-01T00:00:00.000000010Z | 1   | 1     |",
+                "| a      | 1970-01-01T00:00:00.000000020Z |     |       |",
+                "| a      | 1970-01-01T00:00:00.000000030Z |     |       |",
+                "| a      | 1970-01-01T00:00:00.000000040Z | 4   | 7     |",
+                "| b      | 1970-01-01T00:00:00.000000010Z |     |       |",
+                "| b      | 1970-01-01T00:00:00.000000020Z | 2   | 2     |",
+                "| b      | 1970-01-01T00:00:00.000000030Z |     |       |",
+                "| b      | 1970-01-01T00:00:00.000000040Z |     |       |",
+                "+--------+--------------------------------+-----+-------+",
+            ],
+            &got
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fill_locf() {
+        let got = run(input_batch(), vec![0], FillStrategy::Locf).await;
+        assert_batches_eq!(
+            [
+                "+--------+--------------------------------+-----+-------+",
+                "| region | time                           | avg | count |",
+                "+--------+--------------------------------+-----+-------+",
+                "| a      | 1970-01-01T00:00:00.000000010Z | 1   | 1     |",
+                "| a      | 1970-01-01T00:00:00.000000020Z | 1   | 1     |",
+                "| a      | 1970-01-01T00:00:00.000000030Z | 1   | 1     |",
+                "| a      | 1970-01-01T00:00:00.000000040Z | 4   | 7     |",
+                "| b      | 1970-01-01T00:00:00.000000010Z |     |       |",
+                "| b      | 1970-01-01T00:00:00.000000020Z | 2   | 2     |",
+                "| b      | 1970-01-01T00:00:00.000000030Z | 2   | 2     |",
+                "| b      | 1970-01-01T00:00:00.000000040Z | 2   | 2     |",
+                "+--------+--------------------------------+-----+-------+",
+            ],
+            &got
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fill_interpolate() {
+        let got = run(input_batch(), vec![0], FillStrategy::Interpolate).await;
+        assert_batches_eq!(
+            [
+                "+--------+--------------------------------+-----+-------+",
+                "| region | time                           | avg | count |",
+                "+--------+--------------------------------+-----+-------+",
+                "| a      | 1970-01-01T00:00:00.000000010Z | 1   | 1     |",
+                "| a      | 1970-01-01T00:00:00.000000020Z | 2   | 3     |",
+                "| a      | 1970-01-01T00:00:00.000000030Z | 3   | 5     |",
+                "| a      | 1970-01-01T00:00:00.000000040Z | 4   | 7     |",
+                "| b      | 1970-01-01T00:00:00.000000010Z |     |       |",
+                "| b      | 1970-01-01T00:00:00.000000020Z | 2   | 2     |",
+                "| b      | 1970-01-01T00:00:00.000000030Z |     |       |",
+                "| b      | 1970-01-01T00:00:00.000000040Z |     |       |",
+                "+--------+--------------------------------+-----+-------+",
+            ],
+            &got
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fill_without_groups() {
+        // Without group columns, the time buckets are filled even if there is
+        // no input at all.
+        let batch = input_batch();
+        let empty = RecordBatch::new_empty(batch.schema());
+
+        let got = run(empty, vec![], FillStrategy::Null).await;
+        assert_batches_eq!(
+            [
+                "+--------+--------------------------------+-----+-------+",
+                "| region | time                           | avg | count |",
+                "+--------+--------------------------------+-----+-------+",
+                "|        | 1970-01-01T00:00:00.000000010Z |     |       |",
+                "|        | 1970-01-01T00:00:00.000000020Z |     |       |",
+                "|        | 1970-01-01T00:00:00.000000030Z |     |       |",
+                "|        | 1970-01-01T00:00:00.000000040Z |     |       |",
+                "+--------+--------------------------------+-----+-------+",
+            ],
+            &got
+        );
+    }
+
+    #[tokio::test]
+    async fn test_memory_limit() {
+        let batch = input_batch();
+        let schema = batch.schema();
+        let input = Arc::new(MemoryExec::try_new(&[vec![batch]], schema, None).unwrap());
+        let exec = GapFillExec::new(
+            input,
+            1,
+            vec![0],
+            vec![(2, FillStrategy::Null), (3, FillStrategy::Null)],
+            GapFillExecParams {
+                stride: 1,
+                first: 0,
+                last: MAX_GAP_FILL_BUCKETS - 1,
+            },
+        );
+
+        // Far too small for a million rows per group.
+        let runtime = RuntimeEnv::new(
+            RuntimeConfig::new().with_memory_pool(Arc::new(GreedyMemoryPool::new(1024 * 1024))),
+        )
+        .unwrap();
+        let task_ctx =
+            SessionContext::with_config_rt(SessionConfig::new(), Arc::new(runtime)).task_ctx();
+
+        let err = collect(exec.execute(0, task_ctx).unwrap())
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("Resources exhausted"), "{}", err);
+    }
+
+    #[test]
+    fn test_params() {
+        assert_eq!(
+            stride_nanos(&ScalarValue::IntervalDayTime(Some((1 << 32) + 1_000))).unwrap(),
+            NANOS_PER_DAY + 1_000 * NANOS_PER_MILLI
+        );
+        assert_eq!(
+            stride_nanos(&ScalarValue::IntervalMonthDayNano(Some(42))).unwrap(),
+            42
+        );
+        assert!(stride_nanos(&ScalarValue::IntervalMonthDayNano(Some(1 << 96))).is_err());
+        assert!(stride_nanos(&ScalarValue::IntervalDayTime(Some(0))).is_err());
+
+        assert_eq!(date_bin(10, 15, 0).unwrap(), 10);
+        assert_eq!(date_bin(10, 15, 3).unwrap(), 13);
+        assert_eq!(date_bin(10, -5, 0).unwrap(), -10);
+
+        let params = |stride, first, last| GapFillExecParams {
+            stride,
+            first,
+            last,
+        };
+        check_num_buckets(&params(1, 0, MAX_GAP_FILL_BUCKETS - 1)).unwrap();
+        check_num_buckets(&params(1, 0, MAX_GAP_FILL_BUCKETS)).unwrap_err();
+        check_num_buckets(&params(10, 0, 10 * (MAX_GAP_FILL_BUCKETS - 1))).unwrap();
+        check_num_buckets(&params(i64::MAX, i64::MIN, i64::MAX)).unwrap();
+        check_num_buckets(&params(1, i64::MIN, i64::MAX)).unwrap_err();
+        // An empty range fills no buckets.
+        check_num_buckets(&params(1, 10, 0)).unwrap();
+
+        assert_eq!(
+            timestamp_nanos(&ScalarValue::Utf8(Some("1970-01-01T00:00:01Z".to_string()))).unwrap(),
+            1_000_000_000
+        );
+    }
+}
@@ -0,0 +1,197 @@
+//! Per-query memory accounting on top of the shared DataFusion memory pool.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use datafusion::{
+    error::{DataFusionError, Result},
+    execution::memory_pool::{MemoryConsumer, MemoryPool, MemoryReservation},
+};
+
+/// Divides a memory pool of `pool_size` bytes evenly between the queries
+/// that are currently running, so that a single query can not starve the
+/// others.
+#[derive(Debug)]
+pub(crate) struct FairShare {
+    pool_size: usize,
+    queries: AtomicUsize,
+}
+
+impl FairShare {
+    pub(crate) fn new(pool_size: usize) -> Self {
+        Self {
+            pool_size,
+            queries: AtomicUsize::new(0),
+        }
+    }
+
+    /// Number of bytes each running query may reserve.
+    fn limit(&self) -> usize {
+        self.pool_size / self.queries.load(Ordering::Relaxed).max(1)
+    }
+}
+
+/// A [`MemoryPool`] that forwards all requests to a shared inner pool, while
+/// tracking how much memory a single query has reserved and the peak of that
+/// reservation.
+///
+/// Limits are enforced by the inner pool, so wrapping does not change how much
+/// memory a query may use, unless the query is limited to its
+/// [fair share](Self::with_fair_share) of the pool.
+#[derive(Debug)]
+pub(crate) struct PeakMemoryPool {
+    inner: Arc<dyn MemoryPool>,
+    reserved: AtomicUsize,
+    peak: AtomicUsize,
+    fair_share: Option<Arc<FairShare>>,
+}
+
+impl PeakMemoryPool {
+    pub(crate) fn new(inner: Arc<dyn MemoryPool>) -> Self {
+        Self {
+            inner,
+            reserved: AtomicUsize::new(0),
+            peak: AtomicUsize::new(0),
+            fair_share: None,
+        }
+    }
+
+    /// Fail fallible reservations of the query beyond its share of the pool,
+    /// counting the query as running until this pool is dropped.
+    ///
+    /// Operators that can spill do so once their reservation fails, others
+    /// fail the query with "ResourcesExhausted".
+    pub(crate) fn with_fair_share(mut self, fair_share: Arc<FairShare>) -> Self {
+        fair_share.queries.fetch_add(1, Ordering::Relaxed);
+        self.fair_share = Some(fair_share);
+        self
+    }
+
+    /// The largest number of bytes that were reserved through this pool at
+    /// any point in time.
+    pub(crate) fn peak(&self) -> usize {
+        self.peak.load(Ordering::Relaxed)
+    }
+
+    fn add(&self, additional: usize) {
+        let reserved = self.reserved.fetch_add(additional, Ordering::Relaxed) + additional;
+        self.peak.fetch_max(reserved, Ordering::Relaxed);
+    }
+}
+
+impl MemoryPool for PeakMemoryPool {
+    fn register(&self, consumer: &MemoryConsumer) {
+        self.inner.register(consumer)
+    }
+
+    fn unregister(&self, consumer: &MemoryConsumer) {
+        self.inner.unregister(consumer)
+    }
+
+    fn grow(&self, reservation: &MemoryReservation, additional: usize) {
+        self.inner.grow(reservation, additional);
+        self.add(additional);
+    }
+
+    fn shrink(&self, reservation: &MemoryReservation, shrink: usize) {
+        self.inner.shrink(reservation, shrink);
+        self.reserved.fetch_sub(shrink, Ordering::Relaxed);
+    }
+
+    fn try_grow(&self, reservation: &MemoryReservation, additional: usize) -> Result<()> {
+        if let Some(fair_share) = &self.fair_share {
+            let limit = fair_share.limit();
+            let reserved = self.reserved.load(Ordering::Relaxed);
+            if reserved + additional > limit {
+                return Err(DataFusionError::ResourcesExhausted(format!(
+                    "Failed to allocate additional {additional} bytes: the query already reserved \
+                    {reserved} bytes of its fair share of {limit} bytes"
+                )));
+            }
+        }
+
+        self.inner.try_grow(reservation, additional)?;
+        self.add(additional);
+        Ok(())
+    }
+
+    fn reserved(&self) -> usize {
+        self.inner.reserved()
+    }
+}
+
+impl Drop for PeakMemoryPool {
+    fn drop(&mut self) {
+        if let Some(fair_share) = &self.fair_share {
+            fair_share.queries.fetch_sub(1, Ordering::Relaxed);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use datafusion::execution::memory_pool::GreedyMemoryPool;
+
+    #[test]
+    fn test_peak_memory() {
+        let shared: Arc<dyn MemoryPool> = Arc::new(GreedyMemoryPool::new(100));
+        let pool = Arc::new(PeakMemoryPool::new(Arc::clone(&shared)));
+        let pool_dyn: Arc<dyn MemoryPool> = Arc::clone(&pool) as _;
+
+        let mut r1 = MemoryConsumer::new("r1").register(&pool_dyn);
+        let mut r2 = MemoryConsumer::new("r2").register(&pool_dyn);
+
+        r1.try_grow(40).unwrap();
+        r2.try_grow(30).unwrap();
+        assert_eq!(pool.peak(), 70);
+        assert_eq!(shared.reserved(), 70);
+
+        // limits of the shared pool are still enforced
+        r2.try_grow(50).unwrap_err();
+        assert_eq!(pool.peak(), 70);
+
+        r1.shrink(40);
+        r2.try_grow(20).unwrap();
+        assert_eq!(pool.peak(), 70);
+
+        r2.try_grow(40).unwrap();
+        assert_eq!(pool.peak(), 90);
+
+        drop(r1);
+        drop(r2);
+        assert_eq!(shared.reserved(), 0);
+        assert_eq!(pool.peak(), 90);
+    }
+
+    #[test]
+    fn test_fair_share() {
+        let shared: Arc<dyn MemoryPool> = Arc::new(GreedyMemoryPool::new(100));
+        let fair_share = Arc::new(FairShare::new(100));
+
+        let pool1: Arc<dyn MemoryPool> = Arc::new(
+            PeakMemoryPool::new(Arc::clone(&shared)).with_fair_share(Arc::clone(&fair_share)),
+        );
+        let mut r1 = MemoryConsumer::new("r1").register(&pool1);
+        r1.try_grow(80).unwrap();
+
+        // a second query halves the share of the first one
+        let pool2: Arc<dyn MemoryPool> = Arc::new(
+            PeakMemoryPool::new(Arc::clone(&shared)).with_fair_share(Arc::clone(&fair_share)),
+        );
+        let mut r2 = MemoryConsumer::new("r2").register(&pool2);
+        r1.try_grow(1).unwrap_err();
+        r2.try_grow(20).unwrap();
+        r2.try_grow(40).unwrap_err();
+
+        // infallible reservations are not limited
+        r1.grow(10);
+        assert_eq!(shared.reserved(), 110);
+
+        // the first query gets the whole pool back once the second is done
+        r1.shrink(90);
+        drop(r2);
+        drop(pool2);
+        r1.try_grow(90).unwrap();
+    }
+}
@@ -0,0 +1,118 @@
+//! Detection of queries whose results depend on when or how often they are executed.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use datafusion::{
+    error::Result,
+    logical_expr::{
+        expr_visitor::{ExprVisitable, ExpressionVisitor, Recursion},
+        Expr, LogicalPlan, Volatility,
+    },
+};
+
+/// Records whether a query calls functions that are not
+/// [immutable](Volatility::Immutable), e.g. `now()` or `random()`, so that
+/// its results can not be reused for later executions.
+///
+/// Attach this to the session of a query with
+/// [`IOxSessionConfig::with_extension`](super::IOxSessionConfig::with_extension)
+/// to have it set while the query is planned. The plan is checked before it
+/// is optimized, because optimization replaces e.g. `now()` with the start
+/// time of the query.
+#[derive(Debug, Default)]
+pub struct QueryVolatility {
+    volatile: AtomicBool,
+}
+
+impl QueryVolatility {
+    /// Create a record for a query that did not call any functions yet.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// True if any plan of the query calls a function that is not immutable.
+    pub fn is_volatile(&self) -> bool {
+        self.volatile.load(Ordering::Relaxed)
+    }
+
+    /// Record the functions called by `plan`.
+    pub(crate) fn record(&self, plan: &LogicalPlan) -> Result<()> {
+        if plan_is_volatile(plan)? {
+            self.volatile.store(true, Ordering::Relaxed);
+        }
+        Ok(())
+    }
+}
+
+/// True if `plan` or any of its inputs or subqueries calls a function that is not immutable.
+fn plan_is_volatile(plan: &LogicalPlan) -> Result<bool> {
+    for expr in plan.expressions() {
+        if expr.accept(VolatilityVisitor::default())?.volatile {
+            return Ok(true);
+        }
+    }
+    for input in plan.inputs() {
+        if plan_is_volatile(input)? {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// Recursively walk an expression tree, checking if it calls a function that is not immutable.
+#[derive(Debug, Default)]
+struct VolatilityVisitor {
+    volatile: bool,
+}
+
+impl ExpressionVisitor for VolatilityVisitor {
+    fn pre_visit(mut self, expr: &Expr) -> Result<Recursion<Self>> {
+        self.volatile = match expr {
+            Expr::ScalarFunction { fun, .. } => fun.volatility() != Volatility::Immutable,
+            Expr::ScalarUDF { fun, .. } => fun.signature.volatility != Volatility::Immutable,
+            Expr::AggregateUDF { fun, .. } => fun.signature.volatility != Volatility::Immutable,
+            // e.g. `@@version`, resolved by the session
+            Expr::ScalarVariable(_, _) => true,
+            Expr::ScalarSubquery(subquery)
+            | Expr::Exists { subquery, .. }
+            | Expr::InSubquery { subquery, .. } => plan_is_volatile(&subquery.subquery)?,
+            _ => false,
+        };
+
+        Ok(if self.volatile {
+            Recursion::Stop(self)
+        } else {
+            Recursion::Continue(self)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::exec::{Executor, ExecutorType};
+
+    use super::*;
+
+    async fn is_volatile(sql: &str) -> bool {
+        let volatility = Arc::new(QueryVolatility::new());
+        let exec = Executor::new_testing();
+        let ctx = exec
+            .new_execution_config(ExecutorType::Query)
+            .with_extension(Arc::clone(&volatility))
+            .build();
+        ctx.prepare_sql(sql).await.unwrap();
+        volatility.is_volatile()
+    }
+
+    #[tokio::test]
+    async fn test_query_volatility() {
+        assert!(!is_volatile("SELECT 1 AS a").await);
+        assert!(!is_volatile("SELECT abs(-1) AS a").await);
+        assert!(is_volatile("SELECT now() AS t").await);
+        assert!(is_volatile("SELECT NOW () AS t").await);
+        assert!(is_volatile("SELECT random() AS r").await);
+        assert!(is_volatile("SELECT s.t FROM (SELECT current_date() AS t) AS s").await);
+    }
+}
@@ -141,6 +141,9 @@ fn make_schema_pivot_output_schema() -> DFSchemaRef {
 
 /// Physical operator that implements the SchemaPivot operation against
 /// data types
+///
+/// Only the set of non null columns is kept while reading the input,
+/// so it never needs to spill, regardless of the size of the input.
 pub struct SchemaPivotExec {
     input: Arc<dyn ExecutionPlan>,
     /// Output schema
@@ -0,0 +1,249 @@
+//! A queue of record batches that spills to disk under memory pressure.
+
+use std::{collections::VecDeque, fs::File, sync::Arc};
+
+use arrow::{
+    datatypes::SchemaRef,
+    ipc::{reader::FileReader, writer::FileWriter},
+    record_batch::RecordBatch,
+};
+use datafusion::{
+    error::Result,
+    execution::{disk_manager::DiskManager, memory_pool::MemoryReservation},
+};
+use observability_deps::tracing::debug;
+use tempfile::NamedTempFile;
+
+/// Extension of the DataFusion session of a query whose runtime spills to
+/// disk, i.e. whose executor has a
+/// [`spill_dir`](super::ExecutorConfig::spill_dir).
+#[derive(Debug)]
+pub(crate) struct SpillEnabled;
+
+/// A FIFO queue of [`RecordBatch`]es.
+///
+/// Batches are kept in memory as long as `reservation` can grow. Once it
+/// can't, the batches at the back of the queue are written to a temporary
+/// file of the [`DiskManager`] and read back when they reach the front.
+/// Further batches are appended to the same file until it is read from, so
+/// that a queue only has a few files open at any time.
+#[derive(Debug)]
+pub(crate) struct SpillQueue {
+    schema: SchemaRef,
+    reservation: MemoryReservation,
+    disk_manager: Arc<DiskManager>,
+    segments: VecDeque<Segment>,
+    len: usize,
+}
+
+#[derive(Debug)]
+enum Segment {
+    Memory(VecDeque<RecordBatch>),
+    Disk {
+        file: NamedTempFile,
+        /// Appends to the file until the segment is read from
+        writer: Option<FileWriter<File>>,
+        reader: Option<FileReader<File>>,
+    },
+}
+
+impl SpillQueue {
+    pub(crate) fn new(
+        schema: SchemaRef,
+        reservation: MemoryReservation,
+        disk_manager: Arc<DiskManager>,
+    ) -> Self {
+        Self {
+            schema,
+            reservation,
+            disk_manager,
+            segments: VecDeque::new(),
+            len: 0,
+        }
+    }
+
+    /// True if there are no batches in the queue.
+    pub(crate) fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Append `batch` to the queue.
+    ///
+    /// Returns the batch if it neither fits into memory nor can be spilled
+    /// because spilling is disabled.
+    pub(crate) fn push(&mut self, batch: RecordBatch) -> Result<Option<RecordBatch>> {
+        match self.segments.back_mut() {
+            // keep appending to the file that is currently written, so that
+            // batches are not spread over many files
+            Some(Segment::Disk {
+                writer: Some(writer),
+                ..
+            }) => {
+                writer.write(&batch)?;
+            }
+            back => {
+                if self.reservation.try_grow(batch_size(&batch)).is_ok() {
+                    match back {
+                        Some(Segment::Memory(batches)) => batches.push_back(batch),
+                        _ => self
+                            .segments
+                            .push_back(Segment::Memory(VecDeque::from([batch]))),
+                    }
+                } else {
+                    let file = match self.disk_manager.create_tmp_file("StreamSplitExec") {
+                        Ok(file) => file,
+                        Err(e) => {
+                            debug!(%e, "Can not spill batch");
+                            return Ok(Some(batch));
+                        }
+                    };
+                    debug!(path = %file.path().display(), "Spilling batches");
+
+                    let mut writer = FileWriter::try_new(file.reopen()?, &self.schema)?;
+                    writer.write(&batch)?;
+                    self.segments.push_back(Segment::Disk {
+                        file,
+                        writer: Some(writer),
+                        reader: None,
+                    });
+                }
+            }
+        }
+
+        self.len += 1;
+        Ok(None)
+    }
+
+    /// Remove the batch at the front of the queue, if any.
+    pub(crate) fn pop(&mut self) -> Result<Option<RecordBatch>> {
+        while let Some(segment) = self.segments.front_mut() {
+            let batch = match segment {
+                Segment::Memory(batches) => batches.pop_front().map(|batch| {
+                    self.reservation.shrink(batch_size(&batch));
+                    batch
+                }),
+                Segment::Disk {
+                    file,
+                    writer,
+                    reader,
+                } => {
+                    // stop appending, so that the file can be read
+                    if let Some(mut writer) = writer.take() {
+                        writer.finish()?;
+                    }
+                    let reader = match reader {
+                        Some(reader) => reader,
+                        None => reader.insert(FileReader::try_new(file.reopen()?, None)?),
+                    };
+                    reader.next().transpose()?
+                }
+            };
+
+            match batch {
+                Some(batch) => {
+                    self.len -= 1;
+                    return Ok(Some(batch));
+                }
+                // deletes the file of spilled segments
+                None => drop(self.segments.pop_front()),
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Remove all batches from the queue.
+    pub(crate) fn clear(&mut self) {
+        self.segments.clear();
+        self.reservation.free();
+        self.len = 0;
+    }
+}
+
+fn batch_size(batch: &RecordBatch) -> usize {
+    batch
+        .columns()
+        .iter()
+        .map(|c| c.get_array_memory_size())
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow::array::{ArrayRef, Int64Array};
+    use datafusion::execution::{
+        disk_manager::DiskManagerConfig,
+        memory_pool::{GreedyMemoryPool, MemoryConsumer, MemoryPool},
+    };
+
+    use super::*;
+
+    fn batch(v: i64) -> RecordBatch {
+        RecordBatch::try_from_iter(vec![(
+            "v",
+            Arc::new(Int64Array::from(vec![v; 10])) as ArrayRef,
+        )])
+        .unwrap()
+    }
+
+    fn queue(pool_size: usize, disk_manager: DiskManagerConfig) -> SpillQueue {
+        let pool: Arc<dyn MemoryPool> = Arc::new(GreedyMemoryPool::new(pool_size));
+        let reservation = MemoryConsumer::new("test").register(&pool);
+        SpillQueue::new(
+            batch(0).schema(),
+            reservation,
+            DiskManager::try_new(disk_manager).unwrap(),
+        )
+    }
+
+    fn pop_all(queue: &mut SpillQueue) -> Vec<i64> {
+        let mut values = vec![];
+        while let Some(batch) = queue.pop().unwrap() {
+            let array = batch
+                .column(0)
+                .as_any()
+                .downcast_ref::<Int64Array>()
+                .unwrap();
+            values.push(array.value(0));
+        }
+        values
+    }
+
+    #[test]
+    fn test_spill() {
+        // room for two batches
+        let mut queue = queue(2 * batch_size(&batch(0)), DiskManagerConfig::NewOs);
+
+        for v in 1..=5 {
+            assert!(queue.push(batch(v)).unwrap().is_none());
+        }
+        assert_eq!(queue.len, 5);
+        // 3 to 5 are appended to the same file
+        assert_eq!(queue.segments.len(), 2);
+        assert_eq!(queue.pop().unwrap().unwrap().num_rows(), 10);
+
+        for v in 6..=7 {
+            assert!(queue.push(batch(v)).unwrap().is_none());
+        }
+        assert_eq!(queue.segments.len(), 2);
+        assert_eq!(pop_all(&mut queue), vec![2, 3, 4, 5, 6, 7]);
+        assert!(queue.is_empty());
+
+        // memory is available again
+        queue.push(batch(8)).unwrap();
+        assert!(matches!(queue.segments.back(), Some(Segment::Memory(_))));
+        queue.clear();
+        assert!(queue.is_empty());
+        assert_eq!(queue.reservation.size(), 0);
+    }
+
+    #[test]
+    fn test_spill_disabled() {
+        let mut queue = queue(batch_size(&batch(0)), DiskManagerConfig::Disabled);
+
+        assert!(queue.push(batch(1)).unwrap().is_none());
+        let rejected = queue.push(batch(2)).unwrap().unwrap();
+        assert_eq!(rejected.num_rows(), 10);
+        assert_eq!(pop_all(&mut queue), vec![1]);
+    }
+}
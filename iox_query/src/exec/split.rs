@@ -63,7 +63,7 @@ use arrow::{
 use datafusion::{
     common::DFSchemaRef,
     error::{DataFusionError, Result},
-    execution::context::TaskContext,
+    execution::{context::TaskContext, memory_pool::MemoryConsumer},
     logical_expr::{Expr, LogicalPlan, UserDefinedLogicalNode},
     physical_plan::{
         expressions::PhysicalSortExpr,
@@ -78,7 +78,9 @@ use datafusion_util::{watch::WatchedTask, AdapterStream};
 use futures::StreamExt;
 use observability_deps::tracing::*;
 use parking_lot::Mutex;
-use tokio::sync::mpsc::Sender;
+use tokio::sync::mpsc::{error::TrySendError, Sender};
+
+use super::spill::{SpillEnabled, SpillQueue};
 
 /// Implements stream splitting described in `make_stream_split`
 ///
@@ -235,9 +237,11 @@ impl ExecutionPlan for StreamSplitExec {
     ///
     /// # Deadlock
     ///
-    /// This will deadlock unless all partitions are consumed from
-    /// concurrently. Failing to consume from one partition blocks the other
-    /// partitions from progressing.
+    /// If the runtime spills to disk, batches for partitions that are not
+    /// consumed from are queued in memory and spilled to disk once the memory
+    /// of the query is exhausted. Otherwise, this will deadlock unless all
+    /// partitions are consumed from concurrently. Failing to consume from one
+    /// partition then blocks the other partitions from progressing.
     fn execute(
         &self,
         partition: usize,
@@ -294,23 +298,42 @@ impl StreamSplitExec {
         );
 
         trace!("Setting up SplitStreamExec state");
+        let runtime = context.runtime_env();
+        let spill = context
+            .session_config()
+            .get_extension::<SpillEnabled>()
+            .is_some();
         let input_stream = self.input.execute(0, context)?;
 
         let split_exprs = self.split_exprs.clone();
 
         let num_streams = split_exprs.len() + 1;
         let mut baseline_metrics = Vec::with_capacity(num_streams);
+        let mut outputs = Vec::with_capacity(num_streams);
         let mut txs = Vec::with_capacity(num_streams);
         let mut rxs = Vec::with_capacity(num_streams);
         for i in 0..num_streams {
             baseline_metrics.push(BaselineMetrics::new(&self.metrics, i));
             let (tx, rx) = tokio::sync::mpsc::channel(2);
+            // without spilling, queueing would only use up the memory pool
+            // that other operators need, so wait for slow consumers instead
+            let queue = spill.then(|| {
+                let reservation = MemoryConsumer::new(format!("StreamSplitExec[{i}]"))
+                    .with_can_spill(true)
+                    .register(&runtime.memory_pool);
+                SpillQueue::new(
+                    self.input.schema(),
+                    reservation,
+                    Arc::clone(&runtime.disk_manager),
+                )
+            });
+            outputs.push(SplitOutput::new(i, tx.clone(), queue));
             txs.push(tx);
             rxs.push(rx);
         }
 
         // launch the work on a different task, with a task to handle its output values
-        let fut = split_the_stream(input_stream, split_exprs, txs.clone(), baseline_metrics);
+        let fut = split_the_stream(input_stream, split_exprs, outputs, baseline_metrics);
         let handle = WatchedTask::new(fut, txs, "split");
 
         let streams = rxs
@@ -332,15 +355,15 @@ impl StreamSplitExec {
 
 /// This function does the actual splitting: evaluates `split_exprs` on
 /// each input [`RecordBatch`], and then sends the rows to the correct
-/// output `tx[i]`
+/// output `outputs[i]`
 async fn split_the_stream(
     mut input_stream: SendableRecordBatchStream,
     split_exprs: Vec<Arc<dyn PhysicalExpr>>,
-    tx: Vec<Sender<ArrowResult<RecordBatch>>>,
+    mut outputs: Vec<SplitOutput>,
     baseline_metrics: Vec<BaselineMetrics>,
 ) -> std::result::Result<(), ArrowError> {
-    assert_eq!(split_exprs.len() + 1, tx.len());
-    assert_eq!(tx.len(), baseline_metrics.len());
+    assert_eq!(split_exprs.len() + 1, outputs.len());
+    assert_eq!(outputs.len(), baseline_metrics.len());
 
     let elapsed_computes = baseline_metrics
         .iter()
@@ -351,9 +374,6 @@ async fn split_the_stream(
         let batch = batch?;
         trace!(num_rows = batch.num_rows(), "Processing batch");
 
-        // All streams are not done yet
-        let mut tx_done = tx.iter().map(|_| false).collect::<Vec<_>>();
-
         // Get data from the current batch for each stream
         let mut remaining_indices: Option<ColumnarValue> = None;
         for i in 0..split_exprs.len() {
@@ -382,13 +402,7 @@ async fn split_the_stream(
             // record output counts
             let true_batch = true_batch.record_output(&baseline_metrics[i]);
 
-            // don't treat a hangup as an error, as it can also be caused
-            // by a LIMIT operation where the entire stream is not
-            // consumed)
-            if let Err(e) = tx[i].send(Ok(true_batch)).await {
-                debug!(%e, "Split tx[{}] hung up, ignoring", i);
-                tx_done[i] = true;
-            }
+            outputs[i].push(true_batch).await?;
         }
 
         // last stream of data gets values that did not get routed to other streams
@@ -402,27 +416,142 @@ async fn split_the_stream(
         let final_not_true_batch =
             final_not_true_batch.record_output(&baseline_metrics[elapsed_computes.len() - 1]);
 
-        // don't treat a hangup as an error, as it can also be caused
-        // by a LIMIT operation where the entire stream is not
-        // consumed)
-        if let Err(e) = tx[elapsed_computes.len() - 1]
-            .send(Ok(final_not_true_batch))
-            .await
-        {
-            debug!(%e, "Split tx[{}] hung up, ignoring", elapsed_computes.len()-1);
-            tx_done[elapsed_computes.len() - 1] = true;
-        }
+        outputs[elapsed_computes.len() - 1]
+            .push(final_not_true_batch)
+            .await?;
 
-        if tx_done.iter().all(|x| *x) {
+        if outputs.iter().all(|o| o.done) {
             debug!("All split tx ends have hung up, stopping loop");
             return Ok(());
         }
     }
 
+    // send what is still queued, in whatever order the outputs are consumed
+    futures::future::try_join_all(outputs.iter_mut().map(|o| o.drain())).await?;
+
     trace!("Splitting done successfully");
     Ok(())
 }
 
+/// An output of [`StreamSplitExec`].
+///
+/// The outputs are consumed concurrently, but not necessarily at the same
+/// pace. If the runtime spills to disk, batches for a slow consumer are
+/// queued in a [`SpillQueue`], which writes them to disk if they exceed the
+/// memory available to the query, instead of blocking all other outputs.
+/// Otherwise, the split waits for slow consumers.
+#[derive(Debug)]
+struct SplitOutput {
+    partition: usize,
+    tx: Sender<ArrowResult<RecordBatch>>,
+    queue: Option<SpillQueue>,
+    /// The receiver hung up
+    done: bool,
+}
+
+impl SplitOutput {
+    fn new(
+        partition: usize,
+        tx: Sender<ArrowResult<RecordBatch>>,
+        queue: Option<SpillQueue>,
+    ) -> Self {
+        Self {
+            partition,
+            tx,
+            queue,
+            done: false,
+        }
+    }
+
+    /// Send `batch` to the output, queueing it if the output is full and
+    /// there is a queue.
+    async fn push(&mut self, batch: RecordBatch) -> ArrowResult<()> {
+        self.forward_queued()?;
+        if self.done {
+            return Ok(());
+        }
+
+        let Some(queue) = &mut self.queue else {
+            self.send(batch).await;
+            return Ok(());
+        };
+
+        if queue.is_empty() {
+            match self.tx.try_reserve() {
+                Ok(permit) => {
+                    permit.send(Ok(batch));
+                    return Ok(());
+                }
+                Err(TrySendError::Closed(_)) => {
+                    self.hang_up();
+                    return Ok(());
+                }
+                Err(TrySendError::Full(_)) => {}
+            }
+        }
+
+        if let Some(batch) = queue.push(batch).map_err(to_arrow_error)? {
+            // can neither be held in memory nor spilled, so wait for the consumer
+            self.drain().await?;
+            self.send(batch).await;
+        }
+        Ok(())
+    }
+
+    /// Forward queued batches as long as the output has room for them.
+    fn forward_queued(&mut self) -> ArrowResult<()> {
+        while !self.done {
+            let Some(queue) = self.queue.as_mut().filter(|q| !q.is_empty()) else {
+                break;
+            };
+            match self.tx.try_reserve() {
+                Ok(permit) => {
+                    let batch = queue.pop().map_err(to_arrow_error)?;
+                    permit.send(Ok(batch.expect("queue not empty")));
+                }
+                Err(TrySendError::Closed(_)) => self.hang_up(),
+                Err(TrySendError::Full(_)) => break,
+            }
+        }
+        Ok(())
+    }
+
+    /// Send all queued batches, waiting for the consumer.
+    async fn drain(&mut self) -> ArrowResult<()> {
+        while !self.done {
+            let Some(queue) = &mut self.queue else {
+                break;
+            };
+            match queue.pop().map_err(to_arrow_error)? {
+                Some(batch) => self.send(batch).await,
+                None => break,
+            }
+        }
+        Ok(())
+    }
+
+    async fn send(&mut self, batch: RecordBatch) {
+        // don't treat a hangup as an error, as it can also be caused
+        // by a LIMIT operation where the entire stream is not
+        // consumed)
+        if let Err(e) = self.tx.send(Ok(batch)).await {
+            debug!(%e, "Split tx[{}] hung up, ignoring", self.partition);
+            self.hang_up();
+        }
+    }
+
+    fn hang_up(&mut self) {
+        self.done = true;
+        if let Some(queue) = &mut self.queue {
+            queue.clear();
+        }
+    }
+}
+
+fn to_arrow_error(e: DataFusionError) -> ArrowError {
+    ArrowError::ExternalError(Box::new(e))
+}
+
 fn compute_batch(
     input_batch: &RecordBatch,
     indices: ColumnarValue,
@@ -520,8 +649,13 @@ mod tests {
     use arrow::array::{Int64Array, StringArray};
     use arrow_util::assert_batches_sorted_eq;
     use datafusion::{
-        physical_plan::memory::MemoryExec,
-        prelude::{col, lit},
+        execution::{
+            disk_manager::DiskManagerConfig,
+            memory_pool::GreedyMemoryPool,
+            runtime_env::{RuntimeConfig, RuntimeEnv},
+        },
+        physical_plan::{common::collect, memory::MemoryExec},
+        prelude::{col, lit, SessionConfig, SessionContext},
     };
     use datafusion_util::test_collect_partition;
 
@@ -658,6 +792,47 @@ mod tests {
         assert_batches_sorted_eq!(&expected, &output2);
     }
 
+    #[tokio::test]
+    async fn test_split_spills() {
+        test_helpers::maybe_start_logging();
+        let batches: Vec<_> = (0..20)
+            .map(|i| {
+                RecordBatch::try_from_iter(vec![(
+                    "int_col",
+                    Arc::new(Int64Array::from(vec![i, -i])) as ArrayRef,
+                )])
+                .unwrap()
+            })
+            .collect();
+
+        let input = make_input(vec![batches]);
+        // int_col >= 0
+        let split_expr = df_physical_expr(input.as_ref(), col("int_col").gt_eq(lit(0))).unwrap();
+        let split_exec: Arc<dyn ExecutionPlan> =
+            Arc::new(StreamSplitExec::new(input, vec![split_expr]));
+
+        // only room for a few batches
+        let runtime = RuntimeEnv::new(
+            RuntimeConfig::new()
+                .with_memory_pool(Arc::new(GreedyMemoryPool::new(2048)))
+                .with_disk_manager(DiskManagerConfig::NewOs),
+        )
+        .unwrap();
+        let session_config = SessionConfig::new().with_extension(Arc::new(SpillEnabled));
+        let task_ctx = SessionContext::with_config_rt(session_config, Arc::new(runtime)).task_ctx();
+
+        // consume the partitions one after another, which would block without
+        // queueing the batches of partition 1
+        let stream0 = split_exec.execute(0, Arc::clone(&task_ctx)).unwrap();
+        let stream1 = split_exec.execute(1, task_ctx).unwrap();
+        let output0 = collect(stream0).await.unwrap();
+        let output1 = collect(stream1).await.unwrap();
+
+        let num_rows = |batches: &[RecordBatch]| batches.iter().map(|b| b.num_rows()).sum();
+        assert_eq!(num_rows(&output0), 21usize);
+        assert_eq!(num_rows(&output1), 19usize);
+    }
+
     #[tokio::test]
     async fn test_constant_split() {
         // test that it works with a constant expression
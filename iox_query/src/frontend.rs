@@ -1,6 +1,7 @@
 pub mod common;
 pub mod influxql;
 pub mod influxrpc;
+pub mod promql;
 pub mod reorg;
 pub mod sql;
 
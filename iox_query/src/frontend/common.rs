@@ -1,5 +1,6 @@
 use std::sync::Arc;
 
+use data_types::DeduplicationPolicy;
 use datafusion::{
     datasource::provider_as_source,
     logical_expr::{expr_rewriter::ExprRewritable, LogicalPlanBuilder},
@@ -97,6 +98,8 @@ pub struct ScanPlanBuilder<'a> {
     predicate: Option<&'a Predicate>,
     /// Do deduplication
     deduplication: bool,
+    /// How to resolve duplicated primary keys
+    dedup_policy: DeduplicationPolicy,
 }
 
 impl<'a> ScanPlanBuilder<'a> {
@@ -110,6 +113,7 @@ impl<'a> ScanPlanBuilder<'a> {
             predicate: None,
             // always do deduplication in query
             deduplication: true,
+            dedup_policy: DeduplicationPolicy::default(),
         }
     }
 
@@ -141,6 +145,12 @@ impl<'a> ScanPlanBuilder<'a> {
         self
     }
 
+    /// Sets the table's deduplication policy
+    pub fn with_dedup_policy(mut self, dedup_policy: DeduplicationPolicy) -> Self {
+        self.dedup_policy = dedup_policy;
+        self
+    }
+
     /// Creates a `ScanPlan` from the specified chunks
     pub fn build(self) -> Result<ScanPlan> {
         let Self {
@@ -151,6 +161,7 @@ impl<'a> ScanPlanBuilder<'a> {
             table_schema,
             predicate,
             deduplication,
+            dedup_policy,
         } = self;
 
         assert!(!chunks.is_empty(), "no chunks provided");
@@ -161,7 +172,8 @@ impl<'a> ScanPlanBuilder<'a> {
             table_schema.clone(),
             ctx.child_ctx("provider_builder"),
         )
-        .with_enable_deduplication(deduplication);
+        .with_enable_deduplication(deduplication)
+        .with_dedup_policy(dedup_policy);
 
         if let Some(output_sort_key) = output_sort_key {
             // Tell the scan of this provider to sort its output on the given sort_key
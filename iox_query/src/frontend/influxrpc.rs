@@ -726,6 +726,7 @@ impl InfluxRpcPlanner {
         let plans = create_plans(
             namespace,
             &table_predicates_need_chunks,
+            false,
             ctx,
             |ctx, table_name, predicate, chunks, schema| {
                 Self::field_columns_plan(
@@ -778,6 +779,7 @@ impl InfluxRpcPlanner {
         let plans = create_plans(
             namespace,
             &table_predicates,
+            false,
             ctx,
             |ctx, table_name, predicate, chunks, schema| {
                 Self::read_filter_plan(
@@ -848,9 +850,13 @@ impl InfluxRpcPlanner {
             }
         }
 
+        // the last value of each series may be kept by the namespace
+        let last_values = matches!(agg, Aggregate::Last);
+
         let plans = create_plans(
             namespace,
             &table_predicates,
+            last_values,
             ctx,
             |ctx, table_name, predicate, chunks, schema| {
                 // check group_columns for unknown columns
@@ -930,6 +936,7 @@ impl InfluxRpcPlanner {
         let plans = create_plans(
             namespace,
             &table_predicates,
+            false,
             ctx,
             |ctx, table_name, predicate, chunks, schema| {
                 Self::read_window_aggregate_plan(
@@ -1425,6 +1432,21 @@ fn table_chunk_stream<'a>(
     need_fields: bool,
     table_predicates: &'a [(Arc<str>, Predicate)],
     ctx: &'a IOxSessionContext,
+) -> impl Stream<Item = Result<(&'a Arc<str>, &'a Predicate, Vec<Arc<dyn QueryChunk>>)>> + 'a {
+    table_chunk_stream_with_last_values(namespace, need_fields, false, table_predicates, ctx)
+}
+
+/// Same as [`table_chunk_stream`], but if `last_values` is set, the chunks holding the last values
+/// of the series of a table are used instead of its chunks if the namespace keeps them (see
+/// [`QueryNamespace::last_value_chunks`]).
+///
+/// Only `read_group` with [`Aggregate::Last`] sets `last_values`.
+fn table_chunk_stream_with_last_values<'a>(
+    namespace: Arc<dyn QueryNamespace>,
+    need_fields: bool,
+    last_values: bool,
+    table_predicates: &'a [(Arc<str>, Predicate)],
+    ctx: &'a IOxSessionContext,
 ) -> impl Stream<Item = Result<(&'a Arc<str>, &'a Predicate, Vec<Arc<dyn QueryChunk>>)>> + 'a {
     futures::stream::iter(table_predicates)
         .map(move |(table_name, predicate)| {
@@ -1442,17 +1464,35 @@ fn table_chunk_stream<'a>(
             };
 
             async move {
-                let chunks = namespace
-                    .chunks(
-                        table_name,
-                        predicate,
-                        projection.as_ref(),
-                        ctx.child_ctx("table chunks"),
-                    )
-                    .await
-                    .context(GettingChunksSnafu {
-                        table_name: table_name.as_ref(),
-                    })?;
+                let last_value_chunks = if last_values {
+                    namespace
+                        .last_value_chunks(
+                            table_name,
+                            predicate,
+                            ctx.child_ctx("table last values"),
+                        )
+                        .await
+                        .context(GettingChunksSnafu {
+                            table_name: table_name.as_ref(),
+                        })?
+                } else {
+                    None
+                };
+
+                let chunks = match last_value_chunks {
+                    Some(chunks) => chunks,
+                    None => namespace
+                        .chunks(
+                            table_name,
+                            predicate,
+                            projection.as_ref(),
+                            ctx.child_ctx("table chunks"),
+                        )
+                        .await
+                        .context(GettingChunksSnafu {
+                            table_name: table_name.as_ref(),
+                        })?,
+                };
 
                 Ok((table_name, predicate, chunks))
             }
@@ -1551,6 +1591,7 @@ fn columns_in_predicates(
 async fn create_plans<F, P>(
     namespace: Arc<dyn QueryNamespace>,
     table_predicates: &[(Arc<str>, Predicate)],
+    last_values: bool,
     ctx: IOxSessionContext,
     f: F,
 ) -> Result<Vec<P>>
@@ -1567,36 +1608,42 @@ where
         + Sync,
     P: Send,
 {
-    table_chunk_stream(Arc::clone(&namespace), true, table_predicates, &ctx)
-        .and_then(|(table_name, predicate, chunks)| async move {
-            let chunks = prune_chunks_metadata(chunks, predicate)?;
-            Ok((table_name, predicate, chunks))
-        })
-        // rustc seems to heavily confused about the filter step here, esp. it dislikes `.try_filter` and even
-        // `.try_filter_map` requires some additional type annotations
-        .try_filter_map(|(table_name, predicate, chunks)| async move {
-            Ok((!chunks.is_empty()).then_some((table_name, predicate, chunks)))
-                as Result<Option<(&Arc<str>, &Predicate, Vec<_>)>>
-        })
-        .and_then(|(table_name, predicate, chunks)| {
-            let mut ctx = ctx.child_ctx("table");
-            ctx.set_metadata("table", table_name.to_string());
+    table_chunk_stream_with_last_values(
+        Arc::clone(&namespace),
+        true,
+        last_values,
+        table_predicates,
+        &ctx,
+    )
+    .and_then(|(table_name, predicate, chunks)| async move {
+        let chunks = prune_chunks_metadata(chunks, predicate)?;
+        Ok((table_name, predicate, chunks))
+    })
+    // rustc seems to heavily confused about the filter step here, esp. it dislikes `.try_filter` and even
+    // `.try_filter_map` requires some additional type annotations
+    .try_filter_map(|(table_name, predicate, chunks)| async move {
+        Ok((!chunks.is_empty()).then_some((table_name, predicate, chunks)))
+            as Result<Option<(&Arc<str>, &Predicate, Vec<_>)>>
+    })
+    .and_then(|(table_name, predicate, chunks)| {
+        let mut ctx = ctx.child_ctx("table");
+        ctx.set_metadata("table", table_name.to_string());
 
-            let namespace = Arc::clone(&namespace);
-            let f = f.clone();
+        let namespace = Arc::clone(&namespace);
+        let f = f.clone();
 
-            async move {
-                let schema = namespace
-                    .table_schema(table_name)
-                    .context(TableRemovedSnafu {
-                        table_name: table_name.as_ref(),
-                    })?;
+        async move {
+            let schema = namespace
+                .table_schema(table_name)
+                .context(TableRemovedSnafu {
+                    table_name: table_name.as_ref(),
+                })?;
 
-                f(&ctx, table_name, predicate, chunks, &schema)
-            }
-        })
-        .try_collect()
-        .await
+            f(&ctx, table_name, predicate, chunks, &schema)
+        }
+    })
+    .try_collect()
+    .await
 }
 
 /// Prunes the provided list of chunks using [`QueryChunk::apply_predicate_to_metadata`]
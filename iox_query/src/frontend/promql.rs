@@ -0,0 +1,42 @@
+use std::sync::Arc;
+
+use crate::exec::context::IOxSessionContext;
+use crate::plan::promql::{parser::parse, EvalRange, PromQLToLogicalPlan};
+use crate::QueryNamespace;
+use datafusion::{
+    error::{DataFusionError, Result},
+    physical_plan::ExecutionPlan,
+};
+use observability_deps::tracing::debug;
+
+/// This struct can create plans for running PromQL queries against databases
+#[derive(Debug, Default)]
+pub struct PromQLQueryPlanner {}
+
+impl PromQLQueryPlanner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Plan a PromQL expression evaluated at every step of `eval` against
+    /// `database`, and return a DataFusion physical execution plan that runs
+    /// on the query executor.
+    pub async fn query(
+        &self,
+        database: Arc<dyn QueryNamespace>,
+        query: &str,
+        eval: EvalRange,
+        ctx: &IOxSessionContext,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        let ctx = ctx.child_ctx("query");
+        debug!(text=%query, ?eval, "planning PromQL query");
+
+        let expr = parse(query).map_err(|e| DataFusionError::Plan(e.to_string()))?;
+
+        let planner = PromQLToLogicalPlan::new(&ctx, database, eval);
+        let logical_plan = planner.expr_to_plan(&expr).await?;
+        debug!(plan=%logical_plan.display_graphviz(), "logical plan");
+
+        ctx.create_physical_plan(&logical_plan).await
+    }
+}
@@ -2,6 +2,7 @@
 
 use std::sync::Arc;
 
+use data_types::DeduplicationPolicy;
 use datafusion::{
     logical_expr::LogicalPlan,
     prelude::{col, lit_timestamp_nano},
@@ -55,11 +56,22 @@ impl From<datafusion::error::DataFusionError> for Error {
 #[derive(Debug)]
 pub struct ReorgPlanner {
     ctx: IOxSessionContext,
+    dedup_policy: DeduplicationPolicy,
 }
 
 impl ReorgPlanner {
     pub fn new(ctx: IOxSessionContext) -> Self {
-        Self { ctx }
+        Self {
+            ctx,
+            dedup_policy: DeduplicationPolicy::default(),
+        }
+    }
+
+    /// Resolve duplicated primary keys according to the table's
+    /// `dedup_policy` in the plans created by this planner.
+    pub fn with_dedup_policy(mut self, dedup_policy: DeduplicationPolicy) -> Self {
+        self.dedup_policy = dedup_policy;
+        self
     }
 
     /// Creates an execution plan for the COMPACT operations which does the following:
@@ -86,6 +98,7 @@ impl ReorgPlanner {
             ScanPlanBuilder::new(table_name, schema, self.ctx.child_ctx("compact_plan"))
                 .with_chunks(chunks)
                 .with_output_sort_key(output_sort_key)
+                .with_dedup_policy(self.dedup_policy)
                 .build()
                 .context(BuildingScanSnafu)?;
 
@@ -166,6 +179,7 @@ impl ReorgPlanner {
         let scan_plan = ScanPlanBuilder::new(table_name, schema, self.ctx.child_ctx("split_plan"))
             .with_chunks(chunks)
             .with_output_sort_key(output_sort_key)
+            .with_dedup_policy(self.dedup_policy)
             .build()
             .context(BuildingScanSnafu)?;
 
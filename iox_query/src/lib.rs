@@ -13,8 +13,9 @@
 use arrow::record_batch::RecordBatch;
 use async_trait::async_trait;
 use data_types::{ChunkId, ChunkOrder, DeletePredicate, InfluxDbType, PartitionId, TableSummary};
-use datafusion::{error::DataFusionError, prelude::SessionContext};
+use datafusion::{error::DataFusionError, physical_plan::ExecutionPlan, prelude::SessionContext};
 use exec::{stringset::StringSet, IOxSessionContext};
+use futures::future::AbortRegistration;
 use hashbrown::HashMap;
 use observability_deps::tracing::{debug, trace};
 use parquet_file::storage::ParquetExecInput;
@@ -89,6 +90,21 @@ pub trait QueryChunkMeta {
     }
 }
 
+/// Statistics about the execution of a query, passed to the callback of a
+/// [`QueryCompletedToken`] when the query completes.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct QueryExecutionStats {
+    /// Number of rows returned to the client.
+    pub rows: u64,
+
+    /// Number of bytes (in-memory Arrow representation) returned to the client.
+    pub bytes: u64,
+
+    /// Peak number of bytes reserved from the DataFusion memory pool, if
+    /// memory was tracked for this query.
+    pub peak_memory: Option<usize>,
+}
+
 /// A `QueryCompletedToken` is returned by `record_query` implementations of
 /// a `QueryNamespace`. It is used to trigger side-effects (such as query timing)
 /// on query completion.
@@ -97,27 +113,97 @@ pub struct QueryCompletedToken {
     /// If this query completed successfully
     success: bool,
 
+    /// Statistics recorded while executing the query
+    stats: QueryExecutionStats,
+
     /// Function invoked when the token is dropped. It is passed the
-    /// vaue of `self.success`
-    f: Option<Box<dyn FnOnce(bool) + Send>>,
+    /// vaue of `self.success` and `self.stats`
+    f: Option<Box<dyn FnOnce(bool, QueryExecutionStats) + Send>>,
+
+    /// Function invoked once the query has been planned
+    on_planned: Option<Box<dyn FnOnce() + Send>>,
+
+    /// Registration used to abort the query execution if the query is
+    /// cancelled, and the function invoked once it is taken
+    abort_registration: Option<(AbortRegistration, Box<dyn FnOnce() + Send>)>,
 }
 
 impl Debug for QueryCompletedToken {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("QueryCompletedToken")
             .field("success", &self.success)
+            .field("stats", &self.stats)
             .finish()
     }
 }
 
 impl QueryCompletedToken {
     pub fn new(f: impl FnOnce(bool) + Send + 'static) -> Self {
+        Self::new_with_stats(move |success, _stats| f(success))
+    }
+
+    /// Create a token whose callback also receives the
+    /// [`QueryExecutionStats`] recorded for the query
+    pub fn new_with_stats(f: impl FnOnce(bool, QueryExecutionStats) + Send + 'static) -> Self {
         Self {
             success: false,
+            stats: QueryExecutionStats::default(),
             f: Some(Box::new(f)),
+            on_planned: None,
+            abort_registration: None,
+        }
+    }
+
+    /// Invoke `f` when [`set_planned`](Self::set_planned) is called
+    pub fn with_on_planned(self, f: impl FnOnce() + Send + 'static) -> Self {
+        Self {
+            on_planned: Some(Box::new(f)),
+            ..self
+        }
+    }
+
+    /// Attach the registration of the [`AbortHandle`] that is used to cancel
+    /// this query, invoking `on_taken` once the query execution takes it -
+    /// until then, aborting the handle has no effect.
+    ///
+    /// [`AbortHandle`]: futures::future::AbortHandle
+    pub fn with_abort_registration(
+        self,
+        abort_registration: AbortRegistration,
+        on_taken: impl FnOnce() + Send + 'static,
+    ) -> Self {
+        Self {
+            abort_registration: Some((abort_registration, Box::new(on_taken))),
+            ..self
         }
     }
 
+    /// Take the abort registration of this query, if any, so the query
+    /// execution can be wrapped in an [`Abortable`](futures::stream::Abortable)
+    pub fn take_abort_registration(&mut self) -> Option<AbortRegistration> {
+        let (abort_registration, on_taken) = self.abort_registration.take()?;
+        (on_taken)();
+        Some(abort_registration)
+    }
+
+    /// Record that planning of this query completed and execution starts
+    pub fn set_planned(&mut self) {
+        if let Some(f) = self.on_planned.take() {
+            (f)()
+        }
+    }
+
+    /// Record that `rows` rows with a total size of `bytes` were returned
+    pub fn record_output(&mut self, rows: usize, bytes: usize) {
+        self.stats.rows += rows as u64;
+        self.stats.bytes += bytes as u64;
+    }
+
+    /// Record the peak memory used by this query
+    pub fn set_peak_memory(&mut self, peak_memory: Option<usize>) {
+        self.stats.peak_memory = peak_memory;
+    }
+
     /// Record that this query completed successfully
     pub fn set_success(&mut self) {
         self.success = true;
@@ -127,7 +213,7 @@ impl QueryCompletedToken {
 impl Drop for QueryCompletedToken {
     fn drop(&mut self) {
         if let Some(f) = self.f.take() {
-            (f)(self.success)
+            (f)(self.success, self.stats)
         }
     }
 }
@@ -159,6 +245,37 @@ pub trait QueryNamespace: QueryNamespaceMeta + Debug + Send + Sync {
         ctx: IOxSessionContext,
     ) -> Result<Vec<Arc<dyn QueryChunk>>, DataFusionError>;
 
+    /// Returns chunks holding the last non-null value of each field of each series of the
+    /// table, if the namespace keeps them and they answer a query with the provided predicate.
+    ///
+    /// Aggregating these chunks with `selector_last` by series gives the same result as
+    /// aggregating the [`chunks`](Self::chunks). Returns `None` if the caller must use the
+    /// latter.
+    async fn last_value_chunks(
+        &self,
+        _table_name: &str,
+        _predicate: &Predicate,
+        _ctx: IOxSessionContext,
+    ) -> Result<Option<Vec<Arc<dyn QueryChunk>>>, DataFusionError> {
+        Ok(None)
+    }
+
+    /// Returns a plan that produces the same results as `plan`, the physical plan of the
+    /// `query_text` of `query_type` (e.g. "sql") that was planned with `ctx`.
+    ///
+    /// Namespaces that cache query results may return a plan that replays the results of an
+    /// earlier execution of the same query against the same data, or one that keeps the results
+    /// for later executions. By default, `plan` is returned unchanged.
+    async fn cached_plan(
+        &self,
+        _ctx: &IOxSessionContext,
+        _query_type: &str,
+        _query_text: &str,
+        plan: Arc<dyn ExecutionPlan>,
+    ) -> Result<Arc<dyn ExecutionPlan>, DataFusionError> {
+        Ok(plan)
+    }
+
     /// Record that particular type of query was run / planned
     fn record_query(
         &self,
@@ -0,0 +1,492 @@
+use std::{cmp::Ordering, collections::HashSet, ops::Bound, sync::Arc};
+
+use datafusion::{
+    error::{DataFusionError, Result},
+    logical_expr::{
+        expr_rewriter::ExprRewriter,
+        logical_plan::{Aggregate, Extension, Projection},
+        utils::{expr_to_columns, from_plan},
+        Between, BinaryExpr, BuiltinScalarFunction, LogicalPlan, LogicalPlanBuilder, Operator,
+    },
+    optimizer::{
+        utils::{rewrite_preserving_name, split_conjunction},
+        OptimizerConfig, OptimizerRule,
+    },
+    prelude::{lit, Column, Expr},
+    scalar::ScalarValue,
+};
+use query_functions::{DATE_BIN_GAPFILL_UDF_NAME, INTERPOLATE_UDF_NAME, LOCF_UDF_NAME};
+
+use crate::exec::gapfill::{FillStrategy, GapFill, GapFillParams};
+
+/// Plans gap-filling queries.
+///
+/// An aggregate grouped by `date_bin_gapfill(stride, time[, origin])` is
+/// replaced by the same aggregate grouped by `date_bin(stride, time, origin)`,
+/// beneath a [`GapFill`] node that produces rows for the missing time buckets
+/// within the time bounds of the query, taken from the predicates on `time`.
+///
+/// Calls to `locf(col)` and `interpolate(col)` in the projection above such an
+/// aggregate are replaced by `col`, and set the [`FillStrategy`] of the
+/// aggregate column `col` of the [`GapFill`] node.
+#[derive(Debug, Clone)]
+pub struct HandleGapFill {}
+
+impl HandleGapFill {
+    /// Create new optimizer rule.
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl OptimizerRule for HandleGapFill {
+    fn name(&self) -> &str {
+        "handle_gap_fill"
+    }
+
+    fn try_optimize(
+        &self,
+        plan: &LogicalPlan,
+        _config: &dyn OptimizerConfig,
+    ) -> Result<Option<LogicalPlan>> {
+        optimize(plan).map(Some)
+    }
+}
+
+fn optimize(plan: &LogicalPlan) -> Result<LogicalPlan> {
+    let new_inputs = plan
+        .inputs()
+        .iter()
+        .map(|input| optimize(input))
+        .collect::<Result<Vec<_>>>()?;
+
+    let plan = from_plan(plan, &plan.expressions(), &new_inputs)?;
+
+    let new_plan = match &plan {
+        LogicalPlan::Aggregate(aggr) => handle_aggregate(aggr)?,
+        LogicalPlan::Projection(proj) => handle_projection(&plan, proj)?,
+        _ => None,
+    };
+
+    Ok(new_plan.unwrap_or(plan))
+}
+
+/// Plan a [`GapFill`] node for an aggregate grouped by `date_bin_gapfill`.
+fn handle_aggregate(aggr: &Aggregate) -> Result<Option<LogicalPlan>> {
+    let Aggregate {
+        input,
+        group_expr,
+        aggr_expr,
+        ..
+    } = aggr;
+
+    let mut gapfill = None;
+    for (i, e) in group_expr.iter().enumerate() {
+        let (inner, name) = match e {
+            Expr::Alias(inner, name) => (inner.as_ref(), name.clone()),
+            e => (e, e.display_name()?),
+        };
+        if let Expr::ScalarUDF { fun, args } = inner {
+            if fun.name == DATE_BIN_GAPFILL_UDF_NAME {
+                if gapfill.is_some() {
+                    return Err(DataFusionError::Plan(format!(
+                        "{} may only be used once in a GROUP BY clause",
+                        DATE_BIN_GAPFILL_UDF_NAME
+                    )));
+                }
+                gapfill = Some((i, args.clone(), name));
+            }
+        }
+    }
+    let (time_index, args, name) = match gapfill {
+        Some(gapfill) => gapfill,
+        None => return Ok(None),
+    };
+
+    let time_column = match &args[1] {
+        Expr::Column(c) => c.clone(),
+        e => {
+            return Err(DataFusionError::Plan(format!(
+                "{} requires a column as its time argument, got {}",
+                DATE_BIN_GAPFILL_UDF_NAME, e
+            )))
+        }
+    };
+    let stride = args[0].clone();
+    let origin = args
+        .get(2)
+        .cloned()
+        .unwrap_or_else(|| lit(ScalarValue::TimestampNanosecond(Some(0), None)));
+
+    let mut new_group_expr = group_expr.clone();
+    new_group_expr[time_index] = Expr::ScalarFunction {
+        fun: BuiltinScalarFunction::DateBin,
+        args: vec![stride.clone(), args[1].clone(), origin.clone()],
+    }
+    .alias(name);
+
+    let new_aggr = LogicalPlanBuilder::from(input.as_ref().clone())
+        .aggregate(new_group_expr, aggr_expr.clone())?
+        .build()?;
+
+    let (lower, upper) = time_bounds(input, &time_column)?;
+    let columns = new_aggr
+        .schema()
+        .fields()
+        .iter()
+        .map(|f| Expr::Column(f.qualified_column()))
+        .collect::<Vec<_>>();
+    let (new_group_expr, new_aggr_expr) = columns.split_at(group_expr.len());
+    let time_column = new_group_expr[time_index].clone();
+
+    let gap_fill = GapFill::try_new(
+        Arc::new(new_aggr),
+        new_group_expr.to_vec(),
+        new_aggr_expr.to_vec(),
+        time_column,
+        GapFillParams {
+            stride,
+            origin,
+            lower,
+            upper,
+        },
+    )?;
+
+    Ok(Some(LogicalPlan::Extension(Extension {
+        node: Arc::new(gap_fill),
+    })))
+}
+
+/// Find the time bounds of the query from the predicates on `time_column`
+/// of the filters and table scans beneath an aggregate.
+///
+/// The predicates are assumed to be conjunctive, so the tightest of the lower
+/// and upper bounds found are used.
+fn time_bounds(input: &LogicalPlan, time_column: &Column) -> Result<(Bound<Expr>, Bound<Expr>)> {
+    let mut lower = Bound::Unbounded;
+    let mut upper = Bound::Unbounded;
+
+    let mut plan = input;
+    loop {
+        let predicates = match plan {
+            LogicalPlan::Filter(_) => plan.expressions(),
+            LogicalPlan::TableScan(scan) => scan.filters.clone(),
+            _ => vec![],
+        };
+
+        for conjunct in predicates.iter().flat_map(split_conjunction) {
+            let (l, u) = expr_time_bounds(conjunct, time_column);
+            lower = tightest_bound(lower, l, true)?;
+            upper = tightest_bound(upper, u, false)?;
+        }
+
+        match plan.inputs().as_slice() {
+            [input] => plan = input,
+            _ => break,
+        }
+    }
+
+    Ok((lower, upper))
+}
+
+/// The tighter of the lower (if `is_lower`) or upper time bounds `a` and `b`.
+///
+/// Both bounds must be literals to be compared, which they are once the time
+/// predicates of the query have been simplified into constants.
+fn tightest_bound(a: Bound<Expr>, b: Bound<Expr>, is_lower: bool) -> Result<Bound<Expr>> {
+    let ordering = match (&a, &b) {
+        (Bound::Unbounded, _) => return Ok(b),
+        (_, Bound::Unbounded) => return Ok(a),
+        (Bound::Included(ea) | Bound::Excluded(ea), Bound::Included(eb) | Bound::Excluded(eb)) => {
+            match (ea, eb) {
+                (Expr::Literal(va), Expr::Literal(vb)) => va.partial_cmp(vb),
+                _ => None,
+            }
+            .ok_or_else(|| {
+                DataFusionError::Plan(format!(
+                    "gap-filling query has time bounds {} and {} that cannot be compared",
+                    ea, eb
+                ))
+            })?
+        }
+    };
+
+    let a_is_tighter = match ordering {
+        // An exclusive bound is tighter than an inclusive one of the same
+        // value.
+        Ordering::Equal => matches!(a, Bound::Excluded(_)),
+        Ordering::Greater => is_lower,
+        Ordering::Less => !is_lower,
+    };
+    Ok(if a_is_tighter { a } else { b })
+}
+
+/// The time bounds implied by a single predicate, such as `time >= 'a'` or
+/// `time BETWEEN 'a' AND 'b'`.
+fn expr_time_bounds(expr: &Expr, time_column: &Column) -> (Bound<Expr>, Bound<Expr>) {
+    let is_time = |e: &Expr| matches!(e, Expr::Column(c) if c.name == time_column.name);
+
+    match expr {
+        Expr::BinaryExpr(BinaryExpr { left, op, right }) => {
+            let (op, value) = if is_time(left) && is_constant(right) {
+                (*op, right.as_ref().clone())
+            } else if is_time(right) && is_constant(left) {
+                match op {
+                    Operator::Lt => (Operator::Gt, left.as_ref().clone()),
+                    Operator::LtEq => (Operator::GtEq, left.as_ref().clone()),
+                    Operator::Gt => (Operator::Lt, left.as_ref().clone()),
+                    Operator::GtEq => (Operator::LtEq, left.as_ref().clone()),
+                    _ => return (Bound::Unbounded, Bound::Unbounded),
+                }
+            } else {
+                return (Bound::Unbounded, Bound::Unbounded);
+            };
+
+            match op {
+                Operator::Gt => (Bound::Excluded(value), Bound::Unbounded),
+                Operator::GtEq => (Bound::Included(value), Bound::Unbounded),
+                Operator::Lt => (Bound::Unbounded, Bound::Excluded(value)),
+                Operator::LtEq => (Bound::Unbounded, Bound::Included(value)),
+                _ => (Bound::Unbounded, Bound::Unbounded),
+            }
+        }
+        Expr::Between(Between {
+            expr,
+            negated: false,
+            low,
+            high,
+        }) if is_time(expr) && is_constant(low) && is_constant(high) => (
+            Bound::Included(low.as_ref().clone()),
+            Bound::Included(high.as_ref().clone()),
+        ),
+        _ => (Bound::Unbounded, Bound::Unbounded),
+    }
+}
+
+/// Return true if `expr` does not refer to any columns.
+fn is_constant(expr: &Expr) -> bool {
+    let mut columns = HashSet::new();
+    expr_to_columns(expr, &mut columns).is_ok() && columns.is_empty()
+}
+
+/// Rewrite the `locf` and `interpolate` calls in a projection above a
+/// [`GapFill`] node (possibly with a `HAVING` filter in between) into fill
+/// strategies of the node.
+fn handle_projection(plan: &LogicalPlan, proj: &Projection) -> Result<Option<LogicalPlan>> {
+    let (filter, gap_fill) = match proj.input.as_ref() {
+        LogicalPlan::Filter(_) => match proj.input.inputs().as_slice() {
+            [LogicalPlan::Extension(ext)] => (Some(proj.input.as_ref()), ext),
+            _ => return Ok(None),
+        },
+        LogicalPlan::Extension(ext) => (None, ext),
+        _ => return Ok(None),
+    };
+    let gap_fill = match gap_fill.node.as_any().downcast_ref::<GapFill>() {
+        Some(gap_fill) => gap_fill,
+        None => return Ok(None),
+    };
+
+    let mut rewriter = FillFnRewriter {
+        aggr_expr: gap_fill.aggr_expr(),
+        strategies: vec![],
+    };
+    let new_exprs = proj
+        .expr
+        .iter()
+        .map(|e| rewrite_preserving_name(e.clone(), &mut rewriter))
+        .collect::<Result<Vec<_>>>()?;
+    if rewriter.strategies.is_empty() {
+        return Ok(None);
+    }
+
+    let mut new_gap_fill = gap_fill.clone();
+    for (index, strategy) in rewriter.strategies {
+        new_gap_fill = new_gap_fill.with_fill_strategy(index, strategy);
+    }
+    let mut new_input = LogicalPlan::Extension(Extension {
+        node: Arc::new(new_gap_fill),
+    });
+    if let Some(filter) = filter {
+        new_input = from_plan(filter, &filter.expressions(), &[new_input])?;
+    }
+
+    Ok(Some(from_plan(plan, &new_exprs, &[new_input])?))
+}
+
+/// Replaces `locf(col)` and `interpolate(col)` with `col`, recording the
+/// [`FillStrategy`] of the aggregate column `col`.
+struct FillFnRewriter<'a> {
+    aggr_expr: &'a [Expr],
+    strategies: Vec<(usize, FillStrategy)>,
+}
+
+impl<'a> ExprRewriter for FillFnRewriter<'a> {
+    fn mutate(&mut self, expr: Expr) -> Result<Expr> {
+        let (fun, args) = match expr {
+            Expr::ScalarUDF { fun, args }
+                if fun.name == LOCF_UDF_NAME || fun.name == INTERPOLATE_UDF_NAME =>
+            {
+                (fun, args)
+            }
+            expr => return Ok(expr),
+        };
+        let strategy = match fun.name.as_str() {
+            LOCF_UDF_NAME => FillStrategy::Locf,
+            _ => FillStrategy::Interpolate,
+        };
+
+        let index = match &args[0] {
+            Expr::Column(c) => self
+                .aggr_expr
+                .iter()
+                .position(|e| matches!(e, Expr::Column(a) if a.name == c.name)),
+            _ => None,
+        };
+        let index = index.ok_or_else(|| {
+            DataFusionError::Plan(format!(
+                "{} must be called on an aggregate expression, got {}",
+                fun.name, args[0]
+            ))
+        })?;
+
+        match self.strategies.iter().find(|(i, _)| *i == index) {
+            Some((_, s)) if *s != strategy => {
+                return Err(DataFusionError::Plan(format!(
+                    "conflicting fill functions for aggregate {}",
+                    args[0]
+                )))
+            }
+            Some(_) => {}
+            None => self.strategies.push((index, strategy)),
+        }
+
+        Ok(args[0].clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow::{
+        array::{ArrayRef, Float64Array, StringArray, TimestampNanosecondArray},
+        record_batch::RecordBatch,
+    };
+    use arrow_util::assert_batches_eq;
+    use datafusion::datasource::MemTable;
+
+    use super::*;
+    use crate::exec::{Executor, ExecutorType};
+
+    async fn run(sql: &str) -> Result<Vec<RecordBatch>> {
+        let batch = RecordBatch::try_from_iter(vec![
+            (
+                "region",
+                Arc::new(StringArray::from(vec!["a", "a", "b"])) as ArrayRef,
+            ),
+            (
+                "time",
+                Arc::new(TimestampNanosecondArray::from(vec![
+                    1_000_000_000,
+                    4_000_000_000,
+                    2_000_000_000,
+                ])) as ArrayRef,
+            ),
+            (
+                "cpu",
+                Arc::new(Float64Array::from(vec![1.0, 4.0, 2.0])) as ArrayRef,
+            ),
+        ])
+        .unwrap();
+
+        let executor = Executor::new_testing();
+        let ctx = executor.new_context(ExecutorType::Query);
+        let table = MemTable::try_new(batch.schema(), vec![vec![batch]]).unwrap();
+        ctx.inner().register_table("t", Arc::new(table)).unwrap();
+
+        let plan = ctx.prepare_sql(sql).await?;
+        ctx.collect(plan).await
+    }
+
+    #[tokio::test]
+    async fn test_gap_fill() {
+        let got = run("SELECT region, \
+                date_bin_gapfill(INTERVAL '1 second', time, TIMESTAMP '1970-01-01T00:00:00Z') AS second, \
+                avg(cpu) AS null_fill, locf(avg(cpu)) AS locf, interpolate(avg(cpu)) AS interpolate \
+            FROM t \
+            WHERE time >= TIMESTAMP '1970-01-01T00:00:01Z' AND time <= TIMESTAMP '1970-01-01T00:00:04Z' \
+            GROUP BY region, second")
+        .await
+        .unwrap();
+
+        assert_batches_eq!(
+            [
+                "+--------+----------------------+-----------+------+-------------+",
+                "| region | second               | null_fill | locf | interpolate |",
+                "+--------+----------------------+-----------+------+-------------+",
+                "| a      | 1970-01-01T00:00:01Z | 1         | 1    | 1           |",
+                "| a      | 1970-01-01T00:00:02Z |           | 1    | 2           |",
+                "| a      | 1970-01-01T00:00:03Z |           | 1    | 3           |",
+                "| a      | 1970-01-01T00:00:04Z | 4         | 4    | 4           |",
+                "| b      | 1970-01-01T00:00:01Z |           |      |             |",
+                "| b      | 1970-01-01T00:00:02Z | 2         | 2    | 2           |",
+                "| b      | 1970-01-01T00:00:03Z |           | 2    |             |",
+                "| b      | 1970-01-01T00:00:04Z |           | 2    |             |",
+                "+--------+----------------------+-----------+------+-------------+",
+            ],
+            &got
+        );
+    }
+
+    #[tokio::test]
+    async fn test_gap_fill_tightest_bounds() {
+        let got = run("SELECT region, \
+                date_bin_gapfill(INTERVAL '1 second', time, TIMESTAMP '1970-01-01T00:00:00Z') AS second, \
+                avg(cpu) AS cpu \
+            FROM t \
+            WHERE time >= TIMESTAMP '1970-01-01T00:00:00Z' AND time <= TIMESTAMP '1970-01-01T00:00:04Z' \
+                AND time >= TIMESTAMP '1970-01-01T00:00:02Z' AND time < TIMESTAMP '1970-01-01T00:00:03Z' \
+            GROUP BY region, second")
+        .await
+        .unwrap();
+
+        assert_batches_eq!(
+            [
+                "+--------+----------------------+-----+",
+                "| region | second               | cpu |",
+                "+--------+----------------------+-----+",
+                "| b      | 1970-01-01T00:00:02Z | 2   |",
+                "+--------+----------------------+-----+",
+            ],
+            &got
+        );
+    }
+
+    #[tokio::test]
+    async fn test_gap_fill_errors() {
+        let err = run(
+            "SELECT date_bin_gapfill(INTERVAL '1 second', time) AS second, avg(cpu) \
+            FROM t \
+            WHERE time >= TIMESTAMP '1970-01-01T00:00:01Z' \
+            GROUP BY second",
+        )
+        .await
+        .unwrap_err();
+        assert!(
+            err.to_string().contains("missing an upper time bound"),
+            "unexpected error: {}",
+            err
+        );
+
+        let err = run("SELECT date_bin_gapfill(INTERVAL '1 second', time) AS second, locf(region) \
+            FROM t \
+            WHERE time >= TIMESTAMP '1970-01-01T00:00:01Z' AND time < TIMESTAMP '1970-01-01T00:00:05Z' \
+            GROUP BY second, region")
+        .await
+        .unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("must be called on an aggregate expression"),
+            "unexpected error: {}",
+            err
+        );
+    }
+}
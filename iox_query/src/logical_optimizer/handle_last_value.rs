@@ -0,0 +1,350 @@
+use std::sync::Arc;
+
+use datafusion::{
+    datasource::{provider_as_source, source_as_provider, TableProvider},
+    error::Result,
+    logical_expr::{
+        logical_plan::{Aggregate, Limit, Sort, TableScan},
+        utils::from_plan,
+        LogicalPlan,
+    },
+    optimizer::{utils::split_conjunction, OptimizerConfig, OptimizerRule},
+    prelude::Expr,
+};
+use schema::{InfluxColumnType, Schema, TIME_COLUMN_NAME};
+
+use crate::provider::{LastValueFilter, LastValueTableProvider};
+
+/// Answers queries that only need the last value of each field of each series
+/// from the last values of a table with a [`LastValueTableProvider`].
+///
+/// Two shapes of query are recognized, both above an optional chain of
+/// column-only projections and filters that only restrict tags or bound the
+/// time (see [`LastValueFilter`]):
+///
+/// - `... ORDER BY time DESC [, ...] LIMIT 1`
+/// - an aggregate grouped by tags whose aggregates are all `selector_last`
+///   calls, such as `SELECT host, selector_last(load, time) ... GROUP BY host`
+///
+/// The source of the table scan of such queries is replaced by the
+/// [last values](LastValueTableProvider::last_values) of the table.
+#[derive(Debug, Clone)]
+pub struct HandleLastValue {}
+
+impl HandleLastValue {
+    /// Create new optimizer rule.
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl OptimizerRule for HandleLastValue {
+    fn name(&self) -> &str {
+        "handle_last_value"
+    }
+
+    fn try_optimize(
+        &self,
+        plan: &LogicalPlan,
+        _config: &dyn OptimizerConfig,
+    ) -> Result<Option<LogicalPlan>> {
+        optimize(plan).map(Some)
+    }
+}
+
+fn optimize(plan: &LogicalPlan) -> Result<LogicalPlan> {
+    let answerable = match plan {
+        LogicalPlan::Limit(limit) => is_last_row(limit),
+        LogicalPlan::Aggregate(aggr) => is_last_per_series(aggr),
+        _ => false,
+    };
+    if answerable {
+        return with_last_values(plan);
+    }
+
+    let new_inputs = plan
+        .inputs()
+        .iter()
+        .map(|input| optimize(input))
+        .collect::<Result<Vec<_>>>()?;
+
+    from_plan(plan, &plan.expressions(), &new_inputs)
+}
+
+/// `LIMIT 1` above a sort on `time DESC`.
+fn is_last_row(limit: &Limit) -> bool {
+    if limit.skip != 0 || limit.fetch != Some(1) {
+        return false;
+    }
+
+    // projections of the output may sit between the limit and the sort
+    let mut input = limit.input.as_ref();
+    while let LogicalPlan::Projection(proj) = input {
+        input = proj.input.as_ref();
+    }
+
+    let LogicalPlan::Sort(Sort { expr, input, .. }) = input else {
+        return false;
+    };
+    let time_desc = matches!(
+        expr.first(),
+        Some(Expr::Sort { expr, asc: false, .. })
+            if matches!(expr.as_ref(), Expr::Column(c) if c.name == TIME_COLUMN_NAME)
+    );
+
+    time_desc && last_value_scan_schema(input).is_some()
+}
+
+/// An aggregate grouped by tags, computing only `selector_last` of columns.
+fn is_last_per_series(aggr: &Aggregate) -> bool {
+    let Aggregate {
+        input,
+        group_expr,
+        aggr_expr,
+        ..
+    } = aggr;
+
+    let Some(schema) = last_value_scan_schema(input) else {
+        return false;
+    };
+    let column_type = |e: &Expr| match e {
+        Expr::Column(c) => schema.find_index_of(&c.name).map(|idx| schema.field(idx).0),
+        _ => None,
+    };
+
+    let groups_by_tags = group_expr
+        .iter()
+        .all(|e| column_type(e) == Some(InfluxColumnType::Tag));
+
+    let all_last = !aggr_expr.is_empty()
+        && aggr_expr.iter().all(|e| {
+            let e = match e {
+                Expr::Alias(inner, _) => inner.as_ref(),
+                e => e,
+            };
+            match e {
+                Expr::AggregateUDF { fun, args, .. } if fun.name.starts_with("selector_last") => {
+                    matches!(args.as_slice(), [value, time]
+                        if matches!(column_type(value), Some(InfluxColumnType::Tag | InfluxColumnType::Field(_)))
+                            && column_type(time) == Some(InfluxColumnType::Timestamp))
+                }
+                _ => false,
+            }
+        });
+
+    groups_by_tags && all_last
+}
+
+/// The schema of the table scanned by `plan`, if `plan` is a scan of a table
+/// with a [`LastValueTableProvider`] beneath projections of columns and
+/// filters that the last values can answer.
+fn last_value_scan_schema(plan: &LogicalPlan) -> Option<Schema> {
+    match plan {
+        LogicalPlan::Projection(proj) => {
+            let plain = proj.expr.iter().all(|e| match e {
+                Expr::Column(_) => true,
+                Expr::Alias(inner, name) => {
+                    matches!(inner.as_ref(), Expr::Column(c) if &c.name == name)
+                }
+                _ => false,
+            });
+            if plain {
+                last_value_scan_schema(&proj.input)
+            } else {
+                None
+            }
+        }
+        LogicalPlan::Filter(filter) => {
+            let schema = last_value_scan_schema(&filter.input)?;
+            filters_answerable(&[filter.predicate.clone()], &schema).then_some(schema)
+        }
+        LogicalPlan::TableScan(scan) => {
+            if scan.fetch.is_some() {
+                return None;
+            }
+            let provider = last_value_provider(scan)?;
+            let schema = Schema::try_from(provider.schema()).ok()?;
+            filters_answerable(&scan.filters, &schema).then_some(schema)
+        }
+        _ => None,
+    }
+}
+
+fn filters_answerable(filters: &[Expr], schema: &Schema) -> bool {
+    filters
+        .iter()
+        .flat_map(split_conjunction)
+        .all(|e| LastValueFilter::try_new(e, schema).is_some())
+}
+
+fn last_value_provider(scan: &TableScan) -> Option<Arc<dyn TableProvider>> {
+    let provider = source_as_provider(&scan.source).ok()?;
+    provider
+        .as_any()
+        .downcast_ref::<LastValueTableProvider>()
+        .is_some()
+        .then_some(provider)
+}
+
+/// Replace the source of the table scan beneath `plan` by the last values of
+/// the table.
+fn with_last_values(plan: &LogicalPlan) -> Result<LogicalPlan> {
+    if let LogicalPlan::TableScan(scan) = plan {
+        let provider = last_value_provider(scan).expect("checked scan");
+        let provider = provider
+            .as_any()
+            .downcast_ref::<LastValueTableProvider>()
+            .expect("checked provider");
+
+        return Ok(LogicalPlan::TableScan(TableScan {
+            source: provider_as_source(Arc::clone(provider.last_values())),
+            ..scan.clone()
+        }));
+    }
+
+    let new_inputs = plan
+        .inputs()
+        .iter()
+        .map(|input| with_last_values(input))
+        .collect::<Result<Vec<_>>>()?;
+
+    from_plan(plan, &plan.expressions(), &new_inputs)
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow::{
+        array::{ArrayRef, DictionaryArray, Float64Array, TimestampNanosecondArray},
+        datatypes::Int32Type,
+        record_batch::RecordBatch,
+    };
+    use arrow_util::assert_batches_sorted_eq;
+    use datafusion::datasource::MemTable;
+    use schema::builder::SchemaBuilder;
+
+    use super::*;
+    use crate::exec::{Executor, ExecutorType};
+
+    fn batch(hosts: Vec<&str>, times: Vec<i64>, loads: Vec<Option<f64>>) -> RecordBatch {
+        let schema = SchemaBuilder::new()
+            .tag("host")
+            .influx_field("load", schema::InfluxFieldType::Float)
+            .timestamp()
+            .build()
+            .unwrap();
+
+        RecordBatch::try_new(
+            schema.as_arrow(),
+            vec![
+                Arc::new(hosts.into_iter().collect::<DictionaryArray<Int32Type>>()) as ArrayRef,
+                Arc::new(Float64Array::from(loads)) as ArrayRef,
+                Arc::new(TimestampNanosecondArray::from(times)) as ArrayRef,
+            ],
+        )
+        .unwrap()
+    }
+
+    /// Run `sql` against a table whose last values disagree with its data,
+    /// so that the results show which of them answered the query.
+    async fn run(sql: &str) -> Vec<RecordBatch> {
+        let all = batch(
+            vec!["a", "a", "b", "b"],
+            vec![1, 2, 1, 3],
+            vec![Some(1.0), Some(2.0), Some(3.0), None],
+        );
+        let last = batch(vec!["a", "b"], vec![20, 10], vec![Some(20.0), Some(30.0)]);
+
+        let inner = MemTable::try_new(all.schema(), vec![vec![all]]).unwrap();
+        let last_values = MemTable::try_new(last.schema(), vec![vec![last]]).unwrap();
+        let table = LastValueTableProvider::new(Arc::new(inner), Arc::new(last_values));
+
+        let executor = Executor::new_testing();
+        let ctx = executor.new_context(ExecutorType::Query);
+        ctx.inner().register_table("t", Arc::new(table)).unwrap();
+
+        let plan = ctx.prepare_sql(sql).await.unwrap();
+        ctx.collect(plan).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_last_row() {
+        let got =
+            run("SELECT host, load, time FROM t WHERE host = 'b' ORDER BY time DESC LIMIT 1").await;
+        assert_batches_sorted_eq!(
+            [
+                "+------+------+--------------------------------+",
+                "| host | load | time                           |",
+                "+------+------+--------------------------------+",
+                "| b    | 30   | 1970-01-01T00:00:00.000000010Z |",
+                "+------+------+--------------------------------+",
+            ],
+            &got
+        );
+
+        // more than one row
+        let got = run("SELECT host, load, time FROM t ORDER BY time DESC LIMIT 2").await;
+        assert_batches_sorted_eq!(
+            [
+                "+------+------+--------------------------------+",
+                "| host | load | time                           |",
+                "+------+------+--------------------------------+",
+                "| a    | 2    | 1970-01-01T00:00:00.000000002Z |",
+                "| b    |      | 1970-01-01T00:00:00.000000003Z |",
+                "+------+------+--------------------------------+",
+            ],
+            &got
+        );
+
+        // filter on a field
+        let got =
+            run("SELECT host, load, time FROM t WHERE load > 0 ORDER BY time DESC LIMIT 1").await;
+        assert_batches_sorted_eq!(
+            [
+                "+------+------+--------------------------------+",
+                "| host | load | time                           |",
+                "+------+------+--------------------------------+",
+                "| a    | 2    | 1970-01-01T00:00:00.000000002Z |",
+                "+------+------+--------------------------------+",
+            ],
+            &got
+        );
+    }
+
+    #[tokio::test]
+    async fn test_last_per_series() {
+        let got = run(
+            "SELECT host, selector_last(load, time)['value'] AS load FROM t \
+            WHERE time >= TIMESTAMP '1970-01-01T00:00:00Z' GROUP BY host",
+        )
+        .await;
+        assert_batches_sorted_eq!(
+            [
+                "+------+------+",
+                "| host | load |",
+                "+------+------+",
+                "| a    | 20   |",
+                "| b    | 30   |",
+                "+------+------+",
+            ],
+            &got
+        );
+
+        // other aggregates
+        let got = run(
+            "SELECT host, selector_last(load, time)['value'] AS load, count(load) AS n FROM t \
+            GROUP BY host",
+        )
+        .await;
+        assert_batches_sorted_eq!(
+            [
+                "+------+------+---+",
+                "| host | load | n |",
+                "+------+------+---+",
+                "| a    | 2    | 2 |",
+                "| b    | 3    | 1 |",
+                "+------+------+---+",
+            ],
+            &got
+        );
+    }
+}
@@ -0,0 +1,294 @@
+use std::sync::Arc;
+
+use datafusion::{
+    datasource::{provider_as_source, source_as_provider},
+    error::Result,
+    logical_expr::{
+        logical_plan::{Limit, Sort, TableScan},
+        utils::from_plan,
+        LogicalPlan,
+    },
+    optimizer::{utils::split_conjunction, OptimizerConfig, OptimizerRule},
+    prelude::{Column, Expr},
+};
+use schema::TIME_COLUMN_NAME;
+
+use crate::provider::{LastValueTableProvider, TimeOrder, TimeOrderedTableProvider};
+
+/// Scans tables with a [`TimeOrderedTableProvider`] in time order for
+/// queries that only need their first rows in that order, such as
+///
+/// ```sql
+/// SELECT * FROM cpu WHERE host = 'a' ORDER BY time DESC LIMIT 100
+/// ```
+///
+/// That is a `LIMIT` above a sort on `time` alone, above an optional chain of
+/// projections and of filters that have been pushed into the table scan. The
+/// sorted column must be the `time` column of the table, i.e. projections may
+/// only pass it through unaliased.
+///
+/// The scan then only reads as much of the table as is needed to find the
+/// first rows, e.g. only the newest files for `ORDER BY time DESC`.
+#[derive(Debug, Clone)]
+pub struct HandleTimeOrderedLimit {}
+
+impl HandleTimeOrderedLimit {
+    /// Create new optimizer rule.
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl OptimizerRule for HandleTimeOrderedLimit {
+    fn name(&self) -> &str {
+        "handle_time_ordered_limit"
+    }
+
+    fn try_optimize(
+        &self,
+        plan: &LogicalPlan,
+        _config: &dyn OptimizerConfig,
+    ) -> Result<Option<LogicalPlan>> {
+        optimize(plan).map(Some)
+    }
+}
+
+fn optimize(plan: &LogicalPlan) -> Result<LogicalPlan> {
+    if let LogicalPlan::Limit(limit) = plan {
+        if let Some(order) = time_order(limit) {
+            return with_time_order(plan, order);
+        }
+    }
+
+    let new_inputs = plan
+        .inputs()
+        .iter()
+        .map(|input| optimize(input))
+        .collect::<Result<Vec<_>>>()?;
+
+    from_plan(plan, &plan.expressions(), &new_inputs)
+}
+
+/// The order in which the table below `limit` can be scanned, if `limit` is
+/// above a sort on `time`.
+fn time_order(limit: &Limit) -> Option<TimeOrder> {
+    let fetch = limit.skip + limit.fetch?;
+
+    // projections of the output may sit between the limit and the sort
+    let mut input = limit.input.as_ref();
+    while let LogicalPlan::Projection(proj) = input {
+        input = proj.input.as_ref();
+    }
+
+    let LogicalPlan::Sort(Sort { expr, input, .. }) = input else {
+        return None;
+    };
+    let (column, descending) = match expr.as_slice() {
+        [Expr::Sort { expr, asc, .. }] => match expr.as_ref() {
+            Expr::Column(column) => (column, !*asc),
+            _ => return None,
+        },
+        _ => return None,
+    };
+
+    is_time_ordered_scan(input, column, &[]).then_some(TimeOrder { descending, fetch })
+}
+
+/// Whether `plan` is a scan of a table with a [`TimeOrderedTableProvider`]
+/// beneath projections and filters whose predicates were all pushed into the
+/// scan, and `column` of the output of `plan` is the `time` column of that
+/// table. `filters` are the predicates of the filters above `plan`.
+fn is_time_ordered_scan(plan: &LogicalPlan, column: &Column, filters: &[Expr]) -> bool {
+    match plan {
+        LogicalPlan::Projection(proj) => {
+            // follow the column to the input of the projection, which is only
+            // possible if it is passed through as is
+            let Ok(idx) = proj.schema.index_of_column(column) else {
+                return false;
+            };
+            match &proj.expr[idx] {
+                Expr::Column(column) => is_time_ordered_scan(&proj.input, column, filters),
+                _ => false,
+            }
+        }
+        LogicalPlan::Filter(filter) => {
+            let mut filters = filters.to_vec();
+            filters.extend(split_conjunction(&filter.predicate).into_iter().cloned());
+            is_time_ordered_scan(&filter.input, column, &filters)
+        }
+        LogicalPlan::TableScan(scan) => {
+            let pushed: Vec<_> = scan.filters.iter().flat_map(split_conjunction).collect();
+            column.name == TIME_COLUMN_NAME
+                && column
+                    .relation
+                    .as_ref()
+                    .map_or(true, |relation| relation == &scan.table_name)
+                && scan.fetch.is_none()
+                && time_ordered_provider(scan).is_some()
+                && filters.iter().all(|f| pushed.contains(&f))
+        }
+        _ => false,
+    }
+}
+
+/// The [`TimeOrderedTableProvider`] of the table scanned by `scan`, which may
+/// also be wrapped by a [`LastValueTableProvider`].
+fn time_ordered_provider(scan: &TableScan) -> Option<TimeOrderedTableProvider> {
+    let provider = source_as_provider(&scan.source).ok()?;
+    let provider = match provider.as_any().downcast_ref::<LastValueTableProvider>() {
+        Some(last_value) => Arc::clone(last_value.inner()),
+        None => provider,
+    };
+    provider
+        .as_any()
+        .downcast_ref::<TimeOrderedTableProvider>()
+        .cloned()
+}
+
+/// Scan the table beneath `plan` in `order`.
+fn with_time_order(plan: &LogicalPlan, order: TimeOrder) -> Result<LogicalPlan> {
+    if let LogicalPlan::TableScan(scan) = plan {
+        let provider = time_ordered_provider(scan).expect("checked scan");
+
+        return Ok(LogicalPlan::TableScan(TableScan {
+            source: provider_as_source(Arc::new(provider.with_time_order(order))),
+            ..scan.clone()
+        }));
+    }
+
+    let new_inputs = plan
+        .inputs()
+        .iter()
+        .map(|input| with_time_order(input, order))
+        .collect::<Result<Vec<_>>>()?;
+
+    from_plan(plan, &plan.expressions(), &new_inputs)
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow::record_batch::RecordBatch;
+    use arrow_util::assert_batches_eq;
+    use datafusion::physical_plan::displayable;
+
+    use super::*;
+    use crate::{
+        exec::{Executor, ExecutorType},
+        provider::ProviderBuilder,
+        test::TestChunk,
+        QueryChunk,
+    };
+
+    /// Run `sql` against a table with one chunk per row, returning the
+    /// results and the physical plan.
+    async fn run(sql: &str) -> (Vec<RecordBatch>, String) {
+        let chunks: Vec<_> = [(1, "b", 10), (2, "a", 20), (3, "a", 30)]
+            .into_iter()
+            .map(|(ts, tag, field)| {
+                Arc::new(
+                    TestChunk::new("t")
+                        .with_id(ts as u128)
+                        .with_tag_column("tag")
+                        .with_i64_field_column("field")
+                        .with_time_column()
+                        .with_timestamp_min_max(ts, ts)
+                        .with_one_row_of_specific_data(tag, field, ts),
+                ) as Arc<dyn QueryChunk>
+            })
+            .collect();
+
+        let executor = Executor::new_testing();
+        let ctx = executor.new_context(ExecutorType::Query);
+
+        let mut builder = ProviderBuilder::new(
+            Arc::from("t"),
+            chunks[0].schema().clone(),
+            ctx.child_ctx("provider"),
+        );
+        for chunk in chunks {
+            builder = builder.add_chunk(chunk);
+        }
+        let table = TimeOrderedTableProvider::new(Arc::new(builder.build().unwrap()));
+        ctx.inner().register_table("t", Arc::new(table)).unwrap();
+
+        let plan = ctx.prepare_sql(sql).await.unwrap();
+        let formatted = displayable(plan.as_ref()).indent().to_string();
+        (ctx.collect(plan).await.unwrap(), formatted)
+    }
+
+    #[tokio::test]
+    async fn test_time_ordered_limit() {
+        let (got, plan) = run("SELECT tag, field, time FROM t ORDER BY time DESC LIMIT 2").await;
+        assert!(plan.contains("OrderedConcatExec"), "{plan}");
+        assert_batches_eq!(
+            [
+                "+-----+-------+--------------------------------+",
+                "| tag | field | time                           |",
+                "+-----+-------+--------------------------------+",
+                "| a   | 30    | 1970-01-01T00:00:00.000000003Z |",
+                "| a   | 20    | 1970-01-01T00:00:00.000000002Z |",
+                "+-----+-------+--------------------------------+",
+            ],
+            &got
+        );
+
+        // filters are applied before the rows are counted
+        let (got, plan) = run(
+            "SELECT tag, field, time FROM t WHERE field < 30 ORDER BY time DESC LIMIT 1 OFFSET 1",
+        )
+        .await;
+        assert!(plan.contains("OrderedConcatExec"), "{plan}");
+        assert_batches_eq!(
+            [
+                "+-----+-------+--------------------------------+",
+                "| tag | field | time                           |",
+                "+-----+-------+--------------------------------+",
+                "| b   | 10    | 1970-01-01T00:00:00.000000001Z |",
+                "+-----+-------+--------------------------------+",
+            ],
+            &got
+        );
+
+        let (got, plan) = run("SELECT field, time FROM t ORDER BY time ASC LIMIT 1").await;
+        assert!(plan.contains("OrderedConcatExec"), "{plan}");
+        assert_batches_eq!(
+            [
+                "+-------+--------------------------------+",
+                "| field | time                           |",
+                "+-------+--------------------------------+",
+                "| 10    | 1970-01-01T00:00:00.000000001Z |",
+                "+-------+--------------------------------+",
+            ],
+            &got
+        );
+    }
+
+    #[tokio::test]
+    async fn test_not_time_ordered() {
+        // not sorted on time
+        let (got, plan) = run("SELECT field FROM t ORDER BY field DESC LIMIT 1").await;
+        assert!(!plan.contains("OrderedConcatExec"), "{plan}");
+        assert_batches_eq!(
+            [
+                "+-------+",
+                "| field |",
+                "+-------+",
+                "| 30    |",
+                "+-------+",
+            ],
+            &got
+        );
+
+        // no limit
+        let (_, plan) = run("SELECT field FROM t ORDER BY time DESC").await;
+        assert!(!plan.contains("OrderedConcatExec"), "{plan}");
+
+        // sorted on another column named `time`
+        let (got, plan) = run("SELECT tag AS time FROM t ORDER BY time LIMIT 1").await;
+        assert!(!plan.contains("OrderedConcatExec"), "{plan}");
+        assert_batches_eq!(
+            ["+------+", "| time |", "+------+", "| a    |", "+------+",],
+            &got
+        );
+    }
+}
@@ -4,8 +4,15 @@ use datafusion::optimizer::optimizer::Optimizer;
 
 use self::influx_regex_to_datafusion_regex::InfluxRegexToDataFusionRegex;
 
+mod handle_gapfill;
+mod handle_last_value;
+mod handle_time_ordered_limit;
 mod influx_regex_to_datafusion_regex;
 
+pub use handle_gapfill::HandleGapFill;
+pub use handle_last_value::HandleLastValue;
+pub use handle_time_ordered_limit::HandleTimeOrderedLimit;
+
 /// Create IOx-specific logical [`Optimizer`].
 ///
 /// This is mostly the default optimizer that DataFusion provides but with some additional passes.
@@ -13,5 +20,12 @@ pub fn iox_optimizer() -> Optimizer {
     let mut opt = Optimizer::new();
     opt.rules
         .push(Arc::new(InfluxRegexToDataFusionRegex::new()));
+    // Runs last, so that the time bounds of gap-filling queries have been
+    // simplified into constants.
+    opt.rules.push(Arc::new(HandleGapFill::new()));
+    // Runs after the filters have been pushed into the table scans.
+    opt.rules.push(Arc::new(HandleLastValue::new()));
+    // Runs after HandleLastValue, which answers the same queries with less work.
+    opt.rules.push(Arc::new(HandleTimeOrderedLimit::new()));
     opt
 }
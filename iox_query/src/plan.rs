@@ -1,4 +1,5 @@
 pub mod fieldlist;
 pub mod influxql;
+pub mod promql;
 pub mod seriesset;
 pub mod stringset;
@@ -0,0 +1,739 @@
+//! PromQL query planner.
+//!
+//! Plans PromQL expressions over tables with the layout written by the
+//! Prometheus remote write API: one table per metric, named after the
+//! metric, with the labels as tags, the sample value in the `value` field and
+//! the sample timestamp in `time`.
+//!
+//! Expressions are evaluated at every step of an [`EvalRange`]. Each vector
+//! is planned as a relation with a row per series and evaluation time, with
+//! the label columns, the evaluation time in nanoseconds in
+//! [`EVAL_TIME_COLUMN_NAME`] and the sample value in [`VALUE_COLUMN_NAME`].
+pub mod parser;
+
+use std::{sync::Arc, time::Duration};
+
+use arrow::datatypes::DataType;
+use datafusion::{
+    error::{DataFusionError, Result},
+    logical_expr::{
+        avg, cast, count, lit, max, min, sum, when, Expr, LogicalPlan, LogicalPlanBuilder,
+    },
+};
+use datafusion_util::AsExpr;
+use futures::{future::BoxFuture, FutureExt};
+use observability_deps::tracing::debug;
+use predicate::Predicate;
+use query_functions::{
+    clean_non_meta_escapes,
+    promql::{
+        PROMQL_HISTOGRAM_QUANTILE_UDAF_NAME, PROMQL_INCREASE_UDAF_NAME, PROMQL_IRATE_UDAF_NAME,
+        PROMQL_RATE_UDAF_NAME,
+    },
+    regex_match_expr, regex_not_match_expr, registry,
+    selectors::{selector_last, SelectorOutput},
+};
+use regex::Regex;
+use schema::{TIME_COLUMN_NAME, TIME_DATA_TYPE};
+
+use crate::{exec::IOxSessionContext, frontend::common::ScanPlanBuilder, QueryNamespace};
+
+use self::parser::{
+    AggregateOp, Expr as PromExpr, Grouping, LabelMatcher, MatchOp, VectorSelector,
+    METRIC_NAME_LABEL,
+};
+
+/// The column holding the sample values.
+pub const VALUE_COLUMN_NAME: &str = "value";
+
+/// The column holding the evaluation time of intermediate vectors.
+pub const EVAL_TIME_COLUMN_NAME: &str = "__eval_time";
+
+/// The column holding the index of the first step a sample is visible at.
+const FIRST_STEP_COLUMN_NAME: &str = "__first_step";
+
+/// The column holding the offset from the first step a sample is visible at.
+const STEP_OFFSET_COLUMN_NAME: &str = "__step_offset";
+
+/// The label holding the bucket bounds of histograms.
+const BUCKET_LABEL: &str = "le";
+
+/// How far back instant vector selectors look for the latest sample.
+pub const DEFAULT_LOOKBACK: Duration = Duration::from_secs(5 * 60);
+
+/// The number of evaluation times a single sample may be visible at.
+///
+/// Each sample is duplicated for every evaluation time it is visible at, so
+/// windows spanning many steps multiply the data of a query.
+pub const MAX_STEPS_PER_SAMPLE: i64 = 100;
+
+/// The times an expression is evaluated at, in nanoseconds since the epoch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EvalRange {
+    /// The first evaluation time.
+    pub start: i64,
+    /// The last evaluation time.
+    pub end: i64,
+    /// The time between evaluations.
+    pub step: i64,
+}
+
+impl EvalRange {
+    /// Evaluate at the single time `time`.
+    pub fn instant(time: i64) -> Self {
+        Self {
+            start: time,
+            end: time,
+            step: 1,
+        }
+    }
+
+    /// The number of evaluation times.
+    pub fn steps(&self) -> i64 {
+        (self.end - self.start) / self.step + 1
+    }
+}
+
+/// A planned vector.
+#[derive(Debug)]
+struct Vector {
+    /// The plan, `None` if it is known to be empty.
+    plan: Option<LogicalPlanBuilder>,
+    /// The label columns of the plan.
+    labels: Vec<String>,
+    /// The metric name, if the series still have it.
+    metric: Option<String>,
+}
+
+impl Vector {
+    fn empty() -> Self {
+        Self {
+            plan: None,
+            labels: vec![],
+            metric: None,
+        }
+    }
+
+    fn group_exprs(labels: &[String]) -> Vec<Expr> {
+        labels
+            .iter()
+            .map(|l| l.as_expr())
+            .chain(std::iter::once(EVAL_TIME_COLUMN_NAME.as_expr()))
+            .collect()
+    }
+}
+
+/// PromQL query planner
+#[derive(Debug)]
+pub struct PromQLToLogicalPlan<'a> {
+    ctx: &'a IOxSessionContext,
+    database: Arc<dyn QueryNamespace>,
+    eval: EvalRange,
+}
+
+impl<'a> PromQLToLogicalPlan<'a> {
+    pub fn new(
+        ctx: &'a IOxSessionContext,
+        database: Arc<dyn QueryNamespace>,
+        eval: EvalRange,
+    ) -> Self {
+        Self {
+            ctx,
+            database,
+            eval,
+        }
+    }
+
+    /// Create a [`LogicalPlan`] evaluating `expr` at every step of the
+    /// evaluation range.
+    ///
+    /// The plan produces a row per series and evaluation time, with the
+    /// `__name__` column if the series keep the metric name, the label
+    /// columns, the evaluation time in `time` and the sample value in
+    /// `value`, ordered by series and time.
+    pub async fn expr_to_plan(&self, expr: &PromExpr) -> Result<LogicalPlan> {
+        if self.eval.step <= 0 || self.eval.end < self.eval.start {
+            return Err(DataFusionError::Plan(
+                "invalid evaluation range: end must not be before start and step must be positive"
+                    .to_string(),
+            ));
+        }
+
+        let vector = match expr {
+            PromExpr::Number(_) => {
+                return Err(DataFusionError::NotImplemented(
+                    "scalar expressions are not supported".to_string(),
+                ))
+            }
+            PromExpr::MatrixSelector { .. } => {
+                return Err(DataFusionError::NotImplemented(
+                    "range vector selectors are only supported as function arguments".to_string(),
+                ))
+            }
+            expr => self.vector(expr).await?,
+        };
+
+        let plan = match vector.plan {
+            Some(plan) => plan,
+            None => return LogicalPlanBuilder::empty(false).build(),
+        };
+
+        let mut select_exprs = vec![];
+        let mut sort_exprs = vec![];
+        if let Some(metric) = &vector.metric {
+            select_exprs.push(lit(metric.as_str()).alias(METRIC_NAME_LABEL));
+        }
+        for label in &vector.labels {
+            select_exprs.push(label.as_expr());
+            sort_exprs.push(label.as_expr().sort(true, false));
+        }
+        select_exprs
+            .push(cast(EVAL_TIME_COLUMN_NAME.as_expr(), TIME_DATA_TYPE()).alias(TIME_COLUMN_NAME));
+        select_exprs.push(VALUE_COLUMN_NAME.as_expr());
+        sort_exprs.push(TIME_COLUMN_NAME.as_expr().sort(true, false));
+
+        let plan = plan.project(select_exprs)?.sort(sort_exprs)?.build()?;
+        debug!(plan=%plan.display_indent_schema(), "PromQL logical plan");
+        Ok(plan)
+    }
+
+    /// Plan an expression that evaluates to an instant vector.
+    fn vector<'b>(&'b self, expr: &'b PromExpr) -> BoxFuture<'b, Result<Vector>> {
+        async move {
+            match expr {
+                PromExpr::VectorSelector(selector) => self.instant_selector(selector).await,
+                PromExpr::Call { name, args } => self.call(name, args).await,
+                PromExpr::Aggregate { op, grouping, expr } => {
+                    self.aggregate(*op, grouping, expr).await
+                }
+                PromExpr::MatrixSelector { .. } => Err(DataFusionError::Plan(
+                    "expected instant vector, got range vector".to_string(),
+                )),
+                PromExpr::Number(_) => Err(DataFusionError::Plan(
+                    "expected instant vector, got scalar".to_string(),
+                )),
+            }
+        }
+        .boxed()
+    }
+
+    /// Plan an instant vector selector, the latest sample of each series
+    /// within the lookback window.
+    async fn instant_selector(&self, selector: &VectorSelector) -> Result<Vector> {
+        let window = DEFAULT_LOOKBACK.as_nanos() as i64;
+        let vector = self.select(selector, window).await?;
+        let plan = match vector.plan {
+            Some(plan) => plan,
+            None => return Ok(Vector::empty()),
+        };
+
+        let last = selector_last(&DataType::Float64, SelectorOutput::Value)
+            .call(vec![
+                VALUE_COLUMN_NAME.as_expr(),
+                TIME_COLUMN_NAME.as_expr(),
+            ])
+            .alias(VALUE_COLUMN_NAME);
+        let plan = plan.aggregate(Vector::group_exprs(&vector.labels), vec![last])?;
+
+        Ok(Vector {
+            plan: Some(plan),
+            ..vector
+        })
+    }
+
+    async fn call(&self, name: &str, args: &[PromExpr]) -> Result<Vector> {
+        match (name, args) {
+            ("rate", [arg]) => self.range_function(PROMQL_RATE_UDAF_NAME, arg).await,
+            ("irate", [arg]) => self.range_function(PROMQL_IRATE_UDAF_NAME, arg).await,
+            ("increase", [arg]) => self.range_function(PROMQL_INCREASE_UDAF_NAME, arg).await,
+            ("histogram_quantile", [PromExpr::Number(quantile), arg]) => {
+                self.histogram_quantile(*quantile, arg).await
+            }
+            ("rate" | "irate" | "increase" | "histogram_quantile", _) => Err(
+                DataFusionError::Plan(format!("invalid arguments to function {}", name)),
+            ),
+            _ => Err(DataFusionError::NotImplemented(format!(
+                "unsupported function {}",
+                name
+            ))),
+        }
+    }
+
+    /// Plan a function of the samples of each series in a range.
+    async fn range_function(&self, udaf_name: &str, arg: &PromExpr) -> Result<Vector> {
+        let (selector, range) = match arg {
+            PromExpr::MatrixSelector { selector, range } => (selector, range.as_nanos() as i64),
+            _ => {
+                return Err(DataFusionError::Plan(
+                    "expected range vector argument".to_string(),
+                ))
+            }
+        };
+
+        let vector = self.select(selector, range).await?;
+        let plan = match vector.plan {
+            Some(plan) => plan,
+            None => return Ok(Vector::empty()),
+        };
+
+        let offset = selector.offset.as_nanos() as i64;
+        let udaf = registry()
+            .udaf(udaf_name)?
+            .call(vec![
+                VALUE_COLUMN_NAME.as_expr(),
+                TIME_COLUMN_NAME.as_expr(),
+                EVAL_TIME_COLUMN_NAME.as_expr() - lit(offset),
+                lit(range),
+            ])
+            .alias(VALUE_COLUMN_NAME);
+        let plan = plan
+            .aggregate(Vector::group_exprs(&vector.labels), vec![udaf])?
+            .filter(VALUE_COLUMN_NAME.as_expr().is_not_null())?;
+
+        Ok(Vector {
+            plan: Some(plan),
+            labels: vector.labels,
+            metric: None,
+        })
+    }
+
+    async fn histogram_quantile(&self, quantile: f64, arg: &PromExpr) -> Result<Vector> {
+        let vector = self.vector(arg).await?;
+        let plan = match vector.plan {
+            // series without buckets are dropped
+            Some(plan) if vector.labels.iter().any(|l| l == BUCKET_LABEL) => plan,
+            _ => return Ok(Vector::empty()),
+        };
+
+        let labels = vector
+            .labels
+            .into_iter()
+            .filter(|l| l != BUCKET_LABEL)
+            .collect::<Vec<_>>();
+        let udaf = registry()
+            .udaf(PROMQL_HISTOGRAM_QUANTILE_UDAF_NAME)?
+            .call(vec![
+                lit(quantile),
+                BUCKET_LABEL.as_expr(),
+                VALUE_COLUMN_NAME.as_expr(),
+            ])
+            .alias(VALUE_COLUMN_NAME);
+        let plan = plan
+            .aggregate(Vector::group_exprs(&labels), vec![udaf])?
+            .filter(VALUE_COLUMN_NAME.as_expr().is_not_null())?;
+
+        Ok(Vector {
+            plan: Some(plan),
+            labels,
+            metric: None,
+        })
+    }
+
+    async fn aggregate(
+        &self,
+        op: AggregateOp,
+        grouping: &Grouping,
+        expr: &PromExpr,
+    ) -> Result<Vector> {
+        let vector = self.vector(expr).await?;
+        let plan = match vector.plan {
+            Some(plan) => plan,
+            None => return Ok(Vector::empty()),
+        };
+
+        let labels = vector
+            .labels
+            .into_iter()
+            .filter(|l| match grouping {
+                Grouping::By(by) => by.contains(l),
+                Grouping::Without(without) => !without.contains(l),
+            })
+            .collect::<Vec<_>>();
+
+        let value = VALUE_COLUMN_NAME.as_expr();
+        let aggr = match op {
+            AggregateOp::Sum => sum(value),
+            AggregateOp::Avg => avg(value),
+            AggregateOp::Min => min(value),
+            AggregateOp::Max => max(value),
+            AggregateOp::Count => count(value),
+        };
+        let plan = plan.aggregate(Vector::group_exprs(&labels), vec![aggr.alias("aggr")])?;
+
+        // count is an integer, while all sample values are floats
+        let select_exprs = Vector::group_exprs(&labels)
+            .into_iter()
+            .chain(std::iter::once(
+                cast("aggr".as_expr(), DataType::Float64).alias(VALUE_COLUMN_NAME),
+            ))
+            .collect::<Vec<_>>();
+        let plan = plan.project(select_exprs)?;
+
+        Ok(Vector {
+            plan: Some(plan),
+            labels,
+            metric: None,
+        })
+    }
+
+    /// Plan the samples of the series selected by `selector` that are in
+    /// the `window` (in nanoseconds) ending at each evaluation time.
+    ///
+    /// A sample is in the window of all the evaluation times `t` where
+    /// `sample_time + offset <= t < sample_time + offset + window`, so each
+    /// sample is joined with the evaluation times it is visible at. Fails if
+    /// these are more than [`MAX_STEPS_PER_SAMPLE`].
+    async fn select(&self, selector: &VectorSelector, window: i64) -> Result<Vector> {
+        let metric = metric_name(selector)?;
+
+        for matcher in &selector.matchers {
+            if matcher.name == METRIC_NAME_LABEL && !matches_static(matcher, metric)? {
+                return Ok(Vector::empty());
+            }
+        }
+
+        let schema = match self.database.as_meta().table_schema(metric) {
+            Some(schema) => schema,
+            None => return Ok(Vector::empty()),
+        };
+        if schema.find_index_of(VALUE_COLUMN_NAME).is_none() {
+            return Err(DataFusionError::Plan(format!(
+                "table {} has no {} field",
+                metric, VALUE_COLUMN_NAME
+            )));
+        }
+        let mut labels = schema
+            .tags_iter()
+            .map(|f| f.name().to_string())
+            .collect::<Vec<_>>();
+        labels.sort();
+
+        let mut filters = vec![];
+        for matcher in &selector.matchers {
+            if matcher.name == METRIC_NAME_LABEL {
+                continue;
+            }
+            if labels.contains(&matcher.name) {
+                filters.push(matcher_expr(matcher)?);
+            } else if !matches_static(matcher, "")? {
+                // series without the label never match
+                return Ok(Vector::empty());
+            }
+        }
+
+        let EvalRange { start, end, step } = self.eval;
+        let offset = selector.offset.as_nanos() as i64;
+
+        // each sample is visible at up to this many evaluation times
+        let max_steps = self.eval.steps().min((window + step - 1) / step);
+        if max_steps > MAX_STEPS_PER_SAMPLE {
+            return Err(DataFusionError::Plan(format!(
+                "a window of {:?} spans {} steps of {:?}, but samples may only be visible at {} \
+                 steps: increase the step or shorten the window",
+                Duration::from_nanos(window as u64),
+                max_steps,
+                Duration::from_nanos(step as u64),
+                MAX_STEPS_PER_SAMPLE
+            )));
+        }
+
+        // only the samples visible at some evaluation time
+        let predicate = Predicate::new()
+            .with_range(start - offset - window + 1, end - offset + 1)
+            .with_exprs(filters);
+
+        let chunks = self
+            .database
+            .chunks(
+                metric,
+                &predicate,
+                None,
+                self.ctx.child_ctx("promql chunks"),
+            )
+            .await?;
+        if chunks.is_empty() {
+            return Ok(Vector::empty());
+        }
+
+        let mut ctx = self.ctx.child_ctx("scan_and_filter planning");
+        ctx.set_metadata("table", metric.to_string());
+        let scan = ScanPlanBuilder::new(Arc::from(metric), &schema, ctx)
+            .with_chunks(chunks)
+            .with_predicate(&predicate)
+            .build()
+            .map_err(|e| DataFusionError::External(Box::new(e)))?;
+
+        // the index of the first evaluation time the sample is visible at
+        let visible_from = cast(TIME_COLUMN_NAME.as_expr(), DataType::Int64) + lit(offset);
+        let first_step = when(visible_from.clone().lt_eq(lit(start)), lit(0_i64))
+            .otherwise((visible_from.clone() - lit(start) + lit(step - 1)) / lit(step))?;
+
+        let label_exprs = labels.iter().map(|l| l.as_expr()).collect::<Vec<_>>();
+        let mut select_exprs = label_exprs.clone();
+        select_exprs.extend([
+            cast(VALUE_COLUMN_NAME.as_expr(), DataType::Float64).alias(VALUE_COLUMN_NAME),
+            TIME_COLUMN_NAME.as_expr(),
+            first_step.alias(FIRST_STEP_COLUMN_NAME),
+        ]);
+        let mut plan = scan.plan_builder.project(select_exprs)?;
+
+        let mut eval_step = FIRST_STEP_COLUMN_NAME.as_expr();
+        if max_steps > 1 {
+            let offsets = LogicalPlanBuilder::values(
+                (0..max_steps).map(|i| vec![lit(i)]).collect::<Vec<_>>(),
+            )?
+            .project(vec!["column1".as_expr().alias(STEP_OFFSET_COLUMN_NAME)])?
+            .build()?;
+            plan = plan.cross_join(offsets)?;
+            eval_step = eval_step + STEP_OFFSET_COLUMN_NAME.as_expr();
+        }
+
+        let eval_time = lit(start) + eval_step * lit(step);
+        let mut select_exprs = label_exprs;
+        select_exprs.extend([
+            VALUE_COLUMN_NAME.as_expr(),
+            TIME_COLUMN_NAME.as_expr(),
+            eval_time.alias(EVAL_TIME_COLUMN_NAME),
+        ]);
+        let plan = plan.project(select_exprs)?.filter(
+            EVAL_TIME_COLUMN_NAME.as_expr().lt_eq(lit(end)).and(
+                EVAL_TIME_COLUMN_NAME
+                    .as_expr()
+                    .lt(visible_from + lit(window)),
+            ),
+        )?;
+
+        Ok(Vector {
+            plan: Some(plan),
+            labels,
+            metric: Some(metric.to_string()),
+        })
+    }
+}
+
+/// The metric name of a selector, from the name before the label matchers
+/// or an equality matcher on `__name__`.
+fn metric_name(selector: &VectorSelector) -> Result<&str> {
+    selector
+        .metric
+        .as_deref()
+        .or_else(|| {
+            selector
+                .matchers
+                .iter()
+                .find(|m| m.name == METRIC_NAME_LABEL && m.op == MatchOp::Equal)
+                .map(|m| m.value.as_str())
+        })
+        .ok_or_else(|| {
+            DataFusionError::NotImplemented(
+                "vector selectors must select a single metric name".to_string(),
+            )
+        })
+}
+
+/// Compile the regular expression of a matcher, which must match the whole
+/// label value.
+fn matcher_regex(matcher: &LabelMatcher) -> Result<Regex> {
+    let pattern = format!("^(?:{})$", clean_non_meta_escapes(&matcher.value));
+    Regex::new(&pattern).map_err(|e| {
+        DataFusionError::Plan(format!(
+            "invalid regular expression {:?}: {}",
+            matcher.value, e
+        ))
+    })
+}
+
+/// Evaluate a matcher against a known label value, where a missing label is
+/// the empty string.
+fn matches_static(matcher: &LabelMatcher, value: &str) -> Result<bool> {
+    Ok(match matcher.op {
+        MatchOp::Equal => matcher.value == value,
+        MatchOp::NotEqual => matcher.value != value,
+        MatchOp::RegexMatch => matcher_regex(matcher)?.is_match(value),
+        MatchOp::RegexNotMatch => !matcher_regex(matcher)?.is_match(value),
+    })
+}
+
+/// The filter of a matcher on a label column, where NULL is the empty string.
+fn matcher_expr(matcher: &LabelMatcher) -> Result<Expr> {
+    let column = matcher.name.as_expr();
+    let matches_empty = matches_static(matcher, "")?;
+
+    let expr = match matcher.op {
+        MatchOp::Equal => column.clone().eq(lit(matcher.value.as_str())),
+        MatchOp::NotEqual => column.clone().not_eq(lit(matcher.value.as_str())),
+        MatchOp::RegexMatch => {
+            regex_match_expr(column.clone(), matcher_regex(matcher)?.as_str().to_string())
+        }
+        MatchOp::RegexNotMatch => {
+            regex_not_match_expr(column.clone(), matcher_regex(matcher)?.as_str().to_string())
+        }
+    };
+
+    Ok(if matches_empty {
+        column.is_null().or(expr)
+    } else {
+        column.is_not_null().and(expr)
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use arrow_util::assert_batches_eq;
+
+    use super::*;
+    use crate::{
+        exec::{ExecutionContextProvider, Executor},
+        test::{TestChunk, TestDatabase},
+    };
+
+    const SECOND: i64 = 1_000_000_000;
+
+    fn test_db() -> Arc<TestDatabase> {
+        let executor = Arc::new(Executor::new_testing());
+        let test_db = Arc::new(TestDatabase::new(executor));
+        // one sample of `up{job="MA", instance="MA"} 99.5` at 1000ns
+        test_db.add_chunk(
+            "my_partition_key",
+            Arc::new(
+                TestChunk::new("up")
+                    .with_quiet()
+                    .with_tag_column("job")
+                    .with_tag_column("instance")
+                    .with_f64_field_column("value")
+                    .with_time_column()
+                    .with_one_row_of_data(),
+            ),
+        );
+        test_db
+    }
+
+    async fn run(query: &str, eval: EvalRange) -> Result<Vec<arrow::record_batch::RecordBatch>> {
+        let test_db = test_db();
+        let ctx = test_db.new_query_context(None);
+        let expr = parser::parse(query).map_err(|e| DataFusionError::Plan(e.to_string()))?;
+        let plan = PromQLToLogicalPlan::new(&ctx, test_db, eval)
+            .expr_to_plan(&expr)
+            .await?;
+        let physical_plan = ctx.create_physical_plan(&plan).await?;
+        ctx.collect(physical_plan).await
+    }
+
+    #[tokio::test]
+    async fn test_instant_selector() {
+        let expected = vec![
+            "+----------+----------+-----+-----------------------------+-------+",
+            "| __name__ | instance | job | time                        | value |",
+            "+----------+----------+-----+-----------------------------+-------+",
+            "| up       | MA       | MA  | 1970-01-01T00:00:01.000001Z | 99.5  |",
+            "+----------+----------+-----+-----------------------------+-------+",
+        ];
+        let batches = run(r#"up{job="MA"}"#, EvalRange::instant(SECOND + 1000))
+            .await
+            .unwrap();
+        assert_batches_eq!(&expected, &batches);
+
+        // the sample is visible for the lookback window
+        let batches = run(
+            r#"up{job=~"M.", __name__="up"}"#,
+            EvalRange {
+                start: 0,
+                end: 10 * 60 * SECOND,
+                step: 60 * SECOND,
+            },
+        )
+        .await
+        .unwrap();
+        let num_rows = batches.iter().map(|b| b.num_rows()).sum::<usize>();
+        assert_eq!(num_rows, 5);
+    }
+
+    #[tokio::test]
+    async fn test_matchers() {
+        let eval = EvalRange::instant(SECOND);
+        for (query, rows) in [
+            (r#"up{job="MA"}"#, 1),
+            (r#"up{job!="MA"}"#, 0),
+            (r#"up{job=~"M"}"#, 0),
+            (r#"up{job!~"M"}"#, 1),
+            // missing labels are empty
+            (r#"up{missing=""}"#, 1),
+            (r#"up{missing=~"x|"}"#, 1),
+            (r#"up{missing="x"}"#, 0),
+            (r#"up{__name__!="up"}"#, 0),
+            ("missing", 0),
+        ] {
+            let batches = run(query, eval).await.unwrap();
+            let num_rows = batches.iter().map(|b| b.num_rows()).sum::<usize>();
+            assert_eq!(num_rows, rows, "{}", query);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_functions_and_aggregates() {
+        let eval = EvalRange::instant(SECOND);
+        let expected = vec![
+            "+-----+----------------------+-------+",
+            "| job | time                 | value |",
+            "+-----+----------------------+-------+",
+            "| MA  | 1970-01-01T00:00:01Z | 99.5  |",
+            "+-----+----------------------+-------+",
+        ];
+        let batches = run("sum by (job) (up)", eval).await.unwrap();
+        assert_batches_eq!(&expected, &batches);
+
+        // a single sample has no rate
+        let batches = run("rate(up[5m])", eval).await.unwrap();
+        assert!(batches.iter().all(|b| b.num_rows() == 0));
+
+        // series without buckets have no quantiles
+        let batches = run("histogram_quantile(0.9, up)", eval).await.unwrap();
+        assert!(batches.iter().all(|b| b.num_rows() == 0));
+    }
+
+    #[tokio::test]
+    async fn test_unsupported() {
+        let eval = EvalRange::instant(SECOND);
+        for (query, error) in [
+            ("1", "This feature is not implemented: scalar expressions are not supported"),
+            (
+                "up[5m]",
+                "This feature is not implemented: range vector selectors are only supported as function arguments",
+            ),
+            (
+                r#"{__name__=~"u.*"}"#,
+                "This feature is not implemented: vector selectors must select a single metric name",
+            ),
+            ("rate(up)", "Error during planning: expected range vector argument"),
+            ("abs(up)", "This feature is not implemented: unsupported function abs"),
+        ] {
+            let err = run(query, eval).await.unwrap_err();
+            assert_eq!(err.to_string(), error, "{}", query);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_max_steps_per_sample() {
+        let eval = EvalRange {
+            start: 0,
+            end: 3600 * SECOND,
+            step: 15 * SECOND,
+        };
+
+        // 20 steps
+        run("rate(up[5m])", eval).await.unwrap();
+
+        // 240 steps
+        let err = run("rate(up[1h])", eval).await.unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Error during planning: a window of 3600s spans 240 steps of 15s, but samples may \
+             only be visible at 100 steps: increase the step or shorten the window"
+        );
+
+        // only a single step of an instant query
+        run("rate(up[1h])", EvalRange::instant(SECOND))
+            .await
+            .unwrap();
+    }
+}
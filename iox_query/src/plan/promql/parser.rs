@@ -0,0 +1,659 @@
+//! A parser for the subset of PromQL supported by the planner.
+//!
+//! The grammar covers instant and range vector selectors with label
+//! matchers and offsets, function calls, aggregations with `by` or `without`
+//! clauses and number literals. Binary operators are not supported.
+use std::{fmt::Display, time::Duration};
+
+/// The name of the label holding the metric name.
+pub const METRIC_NAME_LABEL: &str = "__name__";
+
+/// A PromQL expression.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    /// A selector of the latest sample of a set of series, such as
+    /// `http_requests_total{job="api"}`.
+    VectorSelector(VectorSelector),
+
+    /// A selector of the samples in a range of a set of series, such as
+    /// `http_requests_total{job="api"}[5m]`.
+    MatrixSelector {
+        /// The selected series.
+        selector: VectorSelector,
+        /// The length of the range.
+        range: Duration,
+    },
+
+    /// A function call, such as `rate(http_requests_total[5m])`.
+    Call {
+        /// The name of the function.
+        name: String,
+        /// The arguments.
+        args: Vec<Expr>,
+    },
+
+    /// An aggregation over series, such as `sum by (job) (up)`.
+    Aggregate {
+        /// The aggregation operator.
+        op: AggregateOp,
+        /// The labels the result is grouped by.
+        grouping: Grouping,
+        /// The aggregated expression.
+        expr: Box<Expr>,
+    },
+
+    /// A number literal.
+    Number(f64),
+}
+
+/// The series selected by a vector or matrix selector.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VectorSelector {
+    /// The metric name, if specified outside the label matchers.
+    pub metric: Option<String>,
+    /// The label matchers.
+    pub matchers: Vec<LabelMatcher>,
+    /// The offset of the evaluation time.
+    pub offset: Duration,
+}
+
+/// A label matcher, such as `job=~"api|web"`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LabelMatcher {
+    /// The label name.
+    pub name: String,
+    /// The match operator.
+    pub op: MatchOp,
+    /// The value or regular expression matched against.
+    pub value: String,
+}
+
+/// A label match operator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatchOp {
+    /// `=`
+    Equal,
+    /// `!=`
+    NotEqual,
+    /// `=~`
+    RegexMatch,
+    /// `!~`
+    RegexNotMatch,
+}
+
+/// An aggregation operator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AggregateOp {
+    /// `sum`
+    Sum,
+    /// `avg`
+    Avg,
+    /// `min`
+    Min,
+    /// `max`
+    Max,
+    /// `count`
+    Count,
+}
+
+impl AggregateOp {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "sum" => Some(Self::Sum),
+            "avg" => Some(Self::Avg),
+            "min" => Some(Self::Min),
+            "max" => Some(Self::Max),
+            "count" => Some(Self::Count),
+            _ => None,
+        }
+    }
+}
+
+/// The labels an aggregation groups by.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Grouping {
+    /// Group by the listed labels.
+    By(Vec<String>),
+    /// Group by all the labels except the listed ones.
+    Without(Vec<String>),
+}
+
+/// An error parsing a PromQL expression.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    /// The byte offset of the error in the input.
+    pub position: usize,
+    /// A description of the error.
+    pub message: String,
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "parse error at char {}: {}", self.position, self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+type Result<T, E = ParseError> = std::result::Result<T, E>;
+
+/// Parse a PromQL expression.
+pub fn parse(input: &str) -> Result<Expr> {
+    let mut parser = Parser { input, pos: 0 };
+    let expr = parser.expr()?;
+    parser.skip_whitespace();
+    if parser.pos < input.len() {
+        return parser.error("unexpected input after expression");
+    }
+    Ok(expr)
+}
+
+/// Parse a PromQL duration, such as `5m` or `1h30m`.
+pub fn parse_duration(input: &str) -> Result<Duration> {
+    let mut parser = Parser { input, pos: 0 };
+    let duration = parser.duration()?;
+    if parser.pos < input.len() {
+        return parser.error("invalid duration");
+    }
+    Ok(duration)
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn error<T>(&self, message: impl Into<String>) -> Result<T> {
+        Err(ParseError {
+            position: self.pos,
+            message: message.into(),
+        })
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.input[self.pos..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn skip_whitespace(&mut self) {
+        loop {
+            let rest = self.rest();
+            let trimmed = rest.trim_start();
+            self.pos += rest.len() - trimmed.len();
+            if trimmed.starts_with('#') {
+                self.pos += trimmed.find('\n').unwrap_or(trimmed.len());
+            } else {
+                return;
+            }
+        }
+    }
+
+    /// Consume `token` after any whitespace, returning whether it was there.
+    fn consume(&mut self, token: &str) -> bool {
+        self.skip_whitespace();
+        if self.rest().starts_with(token) {
+            self.pos += token.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, token: &str) -> Result<()> {
+        if self.consume(token) {
+            Ok(())
+        } else {
+            self.error(format!("expected \"{}\"", token))
+        }
+    }
+
+    /// Consume an identifier, which may contain colons if it is a metric name.
+    fn identifier(&mut self, metric_name: bool) -> Option<&'a str> {
+        self.skip_whitespace();
+        let rest = self.rest();
+        let len = rest
+            .char_indices()
+            .find(|(i, c)| {
+                !(c.is_ascii_alphabetic()
+                    || *c == '_'
+                    || (metric_name && *c == ':')
+                    || (*i > 0 && c.is_ascii_digit()))
+            })
+            .map(|(i, _)| i)
+            .unwrap_or(rest.len());
+        if len == 0 {
+            return None;
+        }
+        self.pos += len;
+        Some(&rest[..len])
+    }
+
+    /// Consume `keyword` if it is the next identifier.
+    fn keyword(&mut self, keyword: &str) -> bool {
+        let pos = self.pos;
+        if self.identifier(false) == Some(keyword) {
+            true
+        } else {
+            self.pos = pos;
+            false
+        }
+    }
+
+    fn expr(&mut self) -> Result<Expr> {
+        self.skip_whitespace();
+        let start = self.pos;
+
+        let expr = match self.peek() {
+            Some('(') => {
+                self.pos += 1;
+                let expr = self.expr()?;
+                self.expect(")")?;
+                expr
+            }
+            Some('{') => Expr::VectorSelector(self.vector_selector(None)?),
+            Some(c) if c.is_ascii_digit() || c == '.' || c == '+' || c == '-' => self.number()?,
+            Some(_) => match self.identifier(true) {
+                Some(name) if name.eq_ignore_ascii_case("inf") => Expr::Number(f64::INFINITY),
+                Some(name) if name.eq_ignore_ascii_case("nan") => Expr::Number(f64::NAN),
+                Some(name) => {
+                    if let Some(op) = AggregateOp::from_name(name) {
+                        self.aggregate(op)?
+                    } else if self.consume("(") {
+                        Expr::Call {
+                            name: name.to_string(),
+                            args: self.args()?,
+                        }
+                    } else {
+                        Expr::VectorSelector(self.vector_selector(Some(name.to_string()))?)
+                    }
+                }
+                None => return self.error("expected expression"),
+            },
+            None => return self.error("unexpected end of input"),
+        };
+
+        let expr = match expr {
+            Expr::VectorSelector(selector) if self.consume("[") => {
+                let range = self.duration_after_whitespace()?;
+                self.expect("]")?;
+                Expr::MatrixSelector { selector, range }
+            }
+            expr => expr,
+        };
+
+        let expr = if self.keyword("offset") {
+            let offset = self.duration_after_whitespace()?;
+            match expr {
+                Expr::VectorSelector(mut selector) => {
+                    selector.offset = offset;
+                    Expr::VectorSelector(selector)
+                }
+                Expr::MatrixSelector {
+                    mut selector,
+                    range,
+                } => {
+                    selector.offset = offset;
+                    Expr::MatrixSelector { selector, range }
+                }
+                _ => {
+                    self.pos = start;
+                    return self.error("offset must follow a selector");
+                }
+            }
+        } else {
+            expr
+        };
+
+        self.skip_whitespace();
+        match self.peek() {
+            Some('+' | '-' | '*' | '/' | '%' | '^' | '=' | '!' | '<' | '>') => {
+                self.error("binary operators are not supported")
+            }
+            _ => Ok(expr),
+        }
+    }
+
+    fn args(&mut self) -> Result<Vec<Expr>> {
+        let mut args = vec![];
+        if self.consume(")") {
+            return Ok(args);
+        }
+        loop {
+            args.push(self.expr()?);
+            if self.consume(")") {
+                return Ok(args);
+            }
+            self.expect(",")?;
+        }
+    }
+
+    fn aggregate(&mut self, op: AggregateOp) -> Result<Expr> {
+        // the grouping can come before or after the arguments
+        let mut grouping = self.grouping()?;
+
+        self.expect("(")?;
+        let mut args = self.args()?;
+        if args.len() != 1 {
+            return self.error("expected exactly one argument to aggregation");
+        }
+
+        if grouping.is_none() {
+            grouping = self.grouping()?;
+        }
+
+        Ok(Expr::Aggregate {
+            op,
+            grouping: grouping.unwrap_or_else(|| Grouping::By(vec![])),
+            expr: Box::new(args.pop().unwrap()),
+        })
+    }
+
+    fn grouping(&mut self) -> Result<Option<Grouping>> {
+        let grouping: fn(Vec<String>) -> Grouping = if self.keyword("by") {
+            Grouping::By
+        } else if self.keyword("without") {
+            Grouping::Without
+        } else {
+            return Ok(None);
+        };
+
+        self.expect("(")?;
+        let mut labels = vec![];
+        if !self.consume(")") {
+            loop {
+                match self.identifier(false) {
+                    Some(label) => labels.push(label.to_string()),
+                    None => return self.error("expected label name"),
+                }
+                if self.consume(")") {
+                    break;
+                }
+                self.expect(",")?;
+            }
+        }
+        Ok(Some(grouping(labels)))
+    }
+
+    fn vector_selector(&mut self, metric: Option<String>) -> Result<VectorSelector> {
+        let mut matchers = vec![];
+        if self.consume("{") && !self.consume("}") {
+            loop {
+                let name = match self.identifier(false) {
+                    Some(name) => name.to_string(),
+                    None => return self.error("expected label name"),
+                };
+                let op = if self.consume("=~") {
+                    MatchOp::RegexMatch
+                } else if self.consume("!~") {
+                    MatchOp::RegexNotMatch
+                } else if self.consume("!=") {
+                    MatchOp::NotEqual
+                } else if self.consume("=") {
+                    MatchOp::Equal
+                } else {
+                    return self.error("expected label match operator");
+                };
+                let value = self.string()?;
+                matchers.push(LabelMatcher { name, op, value });
+
+                if self.consume("}") {
+                    break;
+                }
+                self.expect(",")?;
+                // trailing commas are allowed
+                if self.consume("}") {
+                    break;
+                }
+            }
+        }
+
+        if metric.is_none() && matchers.is_empty() {
+            return self.error("vector selector must contain at least one label matcher");
+        }
+
+        Ok(VectorSelector {
+            metric,
+            matchers,
+            offset: Duration::ZERO,
+        })
+    }
+
+    fn string(&mut self) -> Result<String> {
+        self.skip_whitespace();
+        let quote = match self.peek() {
+            Some(c @ ('"' | '\'' | '`')) => c,
+            _ => return self.error("expected string"),
+        };
+        let start = self.pos;
+        self.pos += 1;
+
+        let mut value = String::new();
+        let mut chars = self.rest().char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                c if c == quote => {
+                    self.pos += i + 1;
+                    return Ok(value);
+                }
+                // raw strings have no escapes
+                '\\' if quote != '`' => match chars.next() {
+                    Some((_, 'n')) => value.push('\n'),
+                    Some((_, 't')) => value.push('\t'),
+                    Some((_, 'r')) => value.push('\r'),
+                    Some((_, c)) => value.push(c),
+                    None => break,
+                },
+                c => value.push(c),
+            }
+        }
+        self.pos = start;
+        self.error("unterminated string")
+    }
+
+    fn number(&mut self) -> Result<Expr> {
+        let rest = self.rest();
+        let mut prev = None;
+        let len = rest
+            .char_indices()
+            .find(|(i, c)| {
+                // signs are allowed at the start and in the exponent
+                let sign_allowed = *i == 0 || matches!(prev, Some('e' | 'E'));
+                prev = Some(*c);
+                !(c.is_ascii_alphanumeric()
+                    || *c == '.'
+                    || (sign_allowed && (*c == '+' || *c == '-')))
+            })
+            .map(|(i, _)| i)
+            .unwrap_or(rest.len());
+        match rest[..len].parse::<f64>() {
+            Ok(n) => {
+                self.pos += len;
+                Ok(Expr::Number(n))
+            }
+            Err(_) => self.error(format!("invalid number \"{}\"", &rest[..len])),
+        }
+    }
+
+    fn duration_after_whitespace(&mut self) -> Result<Duration> {
+        self.skip_whitespace();
+        self.duration()
+    }
+
+    fn duration(&mut self) -> Result<Duration> {
+        let start = self.pos;
+        let mut total = Duration::ZERO;
+        loop {
+            let rest = self.rest();
+            let digits = rest
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(rest.len());
+            if digits == 0 {
+                break;
+            }
+            let n: u64 = match rest[..digits].parse() {
+                Ok(n) => n,
+                Err(_) => return self.error("invalid duration"),
+            };
+            let unit_rest = &rest[digits..];
+            let (unit_len, millis) = if unit_rest.starts_with("ms") {
+                (2, 1)
+            } else {
+                match unit_rest.chars().next() {
+                    Some('s') => (1, 1_000),
+                    Some('m') => (1, 60_000),
+                    Some('h') => (1, 3_600_000),
+                    Some('d') => (1, 86_400_000),
+                    Some('w') => (1, 7 * 86_400_000),
+                    Some('y') => (1, 365 * 86_400_000),
+                    _ => {
+                        self.pos += digits;
+                        return self.error("expected duration unit");
+                    }
+                }
+            };
+            total += Duration::from_millis(n.saturating_mul(millis));
+            self.pos += digits + unit_len;
+        }
+
+        if self.pos == start {
+            return self.error("expected duration");
+        }
+        Ok(total)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn selector(metric: &str, matchers: &[(&str, MatchOp, &str)]) -> VectorSelector {
+        VectorSelector {
+            metric: Some(metric.to_string()),
+            matchers: matchers
+                .iter()
+                .map(|(name, op, value)| LabelMatcher {
+                    name: name.to_string(),
+                    op: *op,
+                    value: value.to_string(),
+                })
+                .collect(),
+            offset: Duration::ZERO,
+        }
+    }
+
+    #[test]
+    fn test_selectors() {
+        assert_eq!(
+            parse("up").unwrap(),
+            Expr::VectorSelector(selector("up", &[]))
+        );
+        assert_eq!(
+            parse(r#"http_requests_total{job="api", code=~'5..', method!=`GET`, path!~"/a\"b",}"#)
+                .unwrap(),
+            Expr::VectorSelector(selector(
+                "http_requests_total",
+                &[
+                    ("job", MatchOp::Equal, "api"),
+                    ("code", MatchOp::RegexMatch, "5.."),
+                    ("method", MatchOp::NotEqual, "GET"),
+                    ("path", MatchOp::RegexNotMatch, "/a\"b"),
+                ]
+            ))
+        );
+        assert_eq!(
+            parse(r#"{__name__="up"}"#).unwrap(),
+            Expr::VectorSelector(VectorSelector {
+                metric: None,
+                ..selector("", &[(METRIC_NAME_LABEL, MatchOp::Equal, "up")])
+            })
+        );
+
+        let mut expected = selector("job:up:sum", &[]);
+        expected.offset = Duration::from_secs(3600);
+        assert_eq!(
+            parse("job:up:sum[1m30s] offset 1h # a comment").unwrap(),
+            Expr::MatrixSelector {
+                selector: expected,
+                range: Duration::from_secs(90),
+            }
+        );
+    }
+
+    #[test]
+    fn test_functions_and_aggregates() {
+        let rate = Expr::Call {
+            name: "rate".to_string(),
+            args: vec![Expr::MatrixSelector {
+                selector: selector("requests", &[]),
+                range: Duration::from_secs(300),
+            }],
+        };
+        let expected = Expr::Aggregate {
+            op: AggregateOp::Sum,
+            grouping: Grouping::By(vec!["job".to_string(), "le".to_string()]),
+            expr: Box::new(rate),
+        };
+        assert_eq!(
+            parse("sum by (job, le) (rate(requests[5m]))").unwrap(),
+            expected
+        );
+        assert_eq!(
+            parse("sum(rate(requests[5m])) by (job, le)").unwrap(),
+            expected
+        );
+
+        assert_eq!(
+            parse("histogram_quantile(0.9, ((latency)))").unwrap(),
+            Expr::Call {
+                name: "histogram_quantile".to_string(),
+                args: vec![
+                    Expr::Number(0.9),
+                    Expr::VectorSelector(selector("latency", &[]))
+                ],
+            }
+        );
+        assert_eq!(
+            parse("avg without (instance) (up)").unwrap(),
+            Expr::Aggregate {
+                op: AggregateOp::Avg,
+                grouping: Grouping::Without(vec!["instance".to_string()]),
+                expr: Box::new(Expr::VectorSelector(selector("up", &[]))),
+            }
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        for input in [
+            "",
+            "{}",
+            "up{job=}",
+            "up{job=\"api\"",
+            "up[5]",
+            "up + 1",
+            "sum(up, up)",
+            "rate(up[5m]) offset 5m",
+            "up down",
+        ] {
+            assert!(parse(input).is_err(), "{} should not parse", input);
+        }
+
+        assert_eq!(
+            parse("up{job=\"api}").unwrap_err().to_string(),
+            "parse error at char 7: unterminated string"
+        );
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("15s").unwrap(), Duration::from_secs(15));
+        assert_eq!(parse_duration("1d2h").unwrap(), Duration::from_secs(93_600));
+        assert_eq!(parse_duration("250ms").unwrap(), Duration::from_millis(250));
+        assert!(parse_duration("15").is_err());
+        assert!(parse_duration("5m ").is_err());
+    }
+}
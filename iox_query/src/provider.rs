@@ -1,15 +1,16 @@
 //! Implementation of a DataFusion `TableProvider` in terms of `QueryChunk`s
 
 use async_trait::async_trait;
+use data_types::DeduplicationPolicy;
 use hashbrown::HashMap;
-use std::sync::Arc;
+use std::{collections::HashSet, sync::Arc};
 
-use arrow::{datatypes::SchemaRef as ArrowSchemaRef, error::ArrowError};
+use arrow::{compute::SortOptions, datatypes::SchemaRef as ArrowSchemaRef, error::ArrowError};
 use datafusion::{
     datasource::TableProvider,
     error::{DataFusionError, Result as DataFusionResult},
     execution::context::SessionState,
-    logical_expr::{TableProviderFilterPushDown, TableType},
+    logical_expr::{utils::expr_to_columns, TableProviderFilterPushDown, TableType},
     physical_plan::{
         expressions::{col as physical_col, PhysicalSortExpr},
         filter::FilterExec,
@@ -24,6 +25,7 @@ use observability_deps::tracing::{debug, trace, warn};
 use predicate::Predicate;
 use schema::{
     interner::SchemaInterner, merge::SchemaMerger, sort::SortKey, InfluxColumnType, Schema,
+    TIME_COLUMN_NAME,
 };
 
 use crate::{
@@ -37,12 +39,18 @@ use snafu::{ResultExt, Snafu};
 
 mod adapter;
 mod deduplicate;
+mod last_value;
+mod ordered_concat;
 pub mod overlap;
 mod physical;
 mod record_batch_exec;
-use self::overlap::group_potential_duplicates;
+mod time_ordered;
+use self::overlap::{group_by_time_range, group_potential_duplicates};
 pub use deduplicate::{DeduplicateExec, RecordBatchDeduplicator};
+pub use last_value::{LastValueFilter, LastValueTableProvider};
+pub use ordered_concat::OrderedConcatExec;
 pub(crate) use physical::chunks_to_physical_nodes;
+pub use time_ordered::{TimeOrder, TimeOrderedTable, TimeOrderedTableProvider};
 
 #[cfg(test)]
 pub(crate) use record_batch_exec::RecordBatchesExec;
@@ -116,6 +124,7 @@ pub struct ProviderBuilder {
     chunks: Vec<Arc<dyn QueryChunk>>,
     output_sort_key: Option<SortKey>,
     deduplication: bool,
+    dedup_policy: DeduplicationPolicy,
 
     // execution context used for tracing
     ctx: IOxSessionContext,
@@ -130,6 +139,7 @@ impl ProviderBuilder {
             output_sort_key: None,
             ctx,
             deduplication: true,
+            dedup_policy: DeduplicationPolicy::default(),
         }
     }
 
@@ -138,6 +148,13 @@ impl ProviderBuilder {
         self
     }
 
+    /// Resolve duplicated primary keys according to the table's
+    /// `dedup_policy`. Append-only tables are never deduplicated.
+    pub fn with_dedup_policy(mut self, dedup_policy: DeduplicationPolicy) -> Self {
+        self.dedup_policy = dedup_policy;
+        self
+    }
+
     /// Produce sorted output specified by sort_key
     pub fn with_output_sort_key(self, output_sort_key: SortKey) -> Self {
         Self {
@@ -161,6 +178,7 @@ impl ProviderBuilder {
             output_sort_key: self.output_sort_key,
             ctx: self.ctx,
             deduplication: self.deduplication,
+            dedup_policy: self.dedup_policy,
         })
     }
 }
@@ -180,6 +198,8 @@ pub struct ChunkTableProvider {
     output_sort_key: Option<SortKey>,
     /// do deduplication
     deduplication: bool,
+    /// how to resolve duplicated primary keys
+    dedup_policy: DeduplicationPolicy,
 
     // execution context
     ctx: IOxSessionContext,
@@ -205,6 +225,11 @@ impl ChunkTableProvider {
     pub fn deduplication(&self) -> bool {
         self.deduplication
     }
+
+    /// The policy used to resolve duplicated primary keys
+    pub fn dedup_policy(&self) -> DeduplicationPolicy {
+        self.dedup_policy
+    }
 }
 
 #[async_trait]
@@ -223,7 +248,7 @@ impl TableProvider for ChunkTableProvider {
         _ctx: &SessionState,
         projection: Option<&Vec<usize>>,
         filters: &[Expr],
-        _limit: Option<usize>,
+        limit: Option<usize>,
     ) -> std::result::Result<Arc<dyn ExecutionPlan>, DataFusionError> {
         trace!("Create a scan node for ChunkTableProvider");
         let chunks: Vec<Arc<dyn QueryChunk>> = self.chunks.to_vec();
@@ -240,7 +265,11 @@ impl TableProvider for ChunkTableProvider {
         // optimization for providers which can offer them
         let predicate = Predicate::default().with_exprs(filters.to_vec());
         let deduplicate = Deduplicater::new(self.ctx.child_ctx("deduplicator"))
-            .enable_deduplication(self.deduplication());
+            .enable_deduplication(self.deduplication())
+            .with_dedup_policy(self.dedup_policy)
+            // DataFusion only passes a limit if there are no filters above the scan, but be
+            // defensive as the filters are not applied exactly
+            .with_limit(limit.filter(|_| filters.is_empty()));
 
         let plan = deduplicate.build_scan_plan(
             Arc::clone(&self.table_name),
@@ -266,6 +295,139 @@ impl TableProvider for ChunkTableProvider {
     }
 }
 
+#[async_trait]
+impl TimeOrderedTable for ChunkTableProvider {
+    async fn scan_time_ordered(
+        &self,
+        state: &SessionState,
+        projection: Option<&Vec<usize>>,
+        filters: &[Expr],
+        order: TimeOrder,
+    ) -> std::result::Result<Arc<dyn ExecutionPlan>, DataFusionError> {
+        let schema = projection.map(|indices| self.iox_schema.select_by_indices(indices));
+        let predicate = Predicate::default().with_exprs(filters.to_vec());
+
+        match self.build_time_ordered_plan(
+            schema.as_ref().unwrap_or(&self.iox_schema),
+            predicate,
+            order,
+        )? {
+            Some(plan) => Ok(plan),
+            None => self.scan(state, projection, filters, None).await,
+        }
+    }
+}
+
+impl ChunkTableProvider {
+    /// Build a plan that reads the chunks in `order`, one set of chunks with
+    /// overlapping time ranges after another, and stops once the first
+    /// `order.fetch` rows are found:
+    ///
+    /// ```text
+    ///                     ┌─────────────────┐
+    ///                     │OrderedConcatExec│
+    ///                     └─────────────────┘
+    ///                              ▲
+    ///              ┌───────────────┴────────────────┐
+    ///              │                                │
+    ///     ┌─────────────────┐              ┌─────────────────┐
+    ///     │ SortExec (time) │              │ SortExec (time) │
+    ///     └─────────────────┘              └─────────────────┘
+    ///              ▲                                ▲
+    ///     ┌─────────────────┐              ┌─────────────────┐
+    ///     │   FilterExec    │              │   FilterExec    │
+    ///     └─────────────────┘              └─────────────────┘
+    ///              ▲                                ▲
+    ///     ┌─────────────────┐              ┌─────────────────┐
+    ///     │    scan plan    │              │    scan plan    │
+    ///     │(newest chunks)  │              │ (older chunks)  │
+    ///     └─────────────────┘              └─────────────────┘
+    /// ```
+    ///
+    /// Returns `None` if the chunks can not be ordered by their time ranges
+    /// or all of them overlap, in which case there is nothing to gain over
+    /// the regular scan plan.
+    fn build_time_ordered_plan(
+        &self,
+        output_schema: &Schema,
+        predicate: Predicate,
+        order: TimeOrder,
+    ) -> Result<Option<Arc<dyn ExecutionPlan>>> {
+        if self.output_sort_key.is_some() || output_schema.find_index_of(TIME_COLUMN_NAME).is_none()
+        {
+            return Ok(None);
+        }
+
+        // The filters are applied to each set of chunks, so they may only refer to output columns
+        let filter_expr = predicate.filter_expr();
+        if let Some(expr) = &filter_expr {
+            let mut columns = HashSet::new();
+            if expr_to_columns(expr, &mut columns).is_err()
+                || columns
+                    .iter()
+                    .any(|c| output_schema.find_index_of(&c.name).is_none())
+            {
+                return Ok(None);
+            }
+        }
+
+        let Some(mut groups) = group_by_time_range(self.chunks.clone()) else {
+            debug!(table_name=%self.table_name, "Chunks without time range, can not scan in time order");
+            return Ok(None);
+        };
+        if groups.len() < 2 {
+            return Ok(None);
+        }
+        if order.descending {
+            groups.reverse();
+        }
+        debug!(table_name=%self.table_name, groups=groups.len(), ?order, "Scan chunks in time order");
+
+        let sort_exprs = vec![PhysicalSortExpr {
+            expr: physical_col(TIME_COLUMN_NAME, &output_schema.as_arrow())
+                .context(InternalSortSnafu)?,
+            options: SortOptions {
+                descending: order.descending,
+                nulls_first: order.descending,
+            },
+        }];
+
+        let inputs = groups
+            .into_iter()
+            .map(|chunks| {
+                let mut plan = Deduplicater::new(self.ctx.child_ctx("deduplicator"))
+                    .enable_deduplication(self.deduplication())
+                    .with_dedup_policy(self.dedup_policy)
+                    .build_scan_plan(
+                        Arc::clone(&self.table_name),
+                        output_schema,
+                        chunks,
+                        predicate.clone(),
+                        None,
+                    )?;
+
+                if let Some(expr) = &filter_expr {
+                    let expr =
+                        df_physical_expr(&*plan, expr.clone()).context(InternalFilterSnafu)?;
+                    plan = Arc::new(FilterExec::try_new(expr, plan).context(InternalFilterSnafu)?);
+                }
+
+                let plan: Arc<dyn ExecutionPlan> = Arc::new(
+                    SortExec::try_new(sort_exprs.clone(), plan, Some(order.fetch))
+                        .context(InternalSortSnafu)?,
+                );
+                Ok(plan)
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Some(Arc::new(OrderedConcatExec::new(
+            inputs,
+            sort_exprs,
+            Some(order.fetch),
+        ))))
+    }
+}
+
 /// Chunks split into disjoint categories.
 #[derive(Debug)]
 struct Chunks {
@@ -405,6 +567,12 @@ pub(crate) struct Deduplicater {
 
     // deduplication
     deduplication: bool,
+
+    // how to resolve duplicated primary keys
+    dedup_policy: DeduplicationPolicy,
+
+    // number of rows that answer the query, if any
+    limit: Option<usize>,
 }
 
 impl Deduplicater {
@@ -413,6 +581,8 @@ impl Deduplicater {
             schema_interner: Default::default(),
             ctx,
             deduplication: true,
+            dedup_policy: DeduplicationPolicy::default(),
+            limit: None,
         }
     }
 
@@ -421,6 +591,18 @@ impl Deduplicater {
         self
     }
 
+    pub(crate) fn with_dedup_policy(mut self, dedup_policy: DeduplicationPolicy) -> Self {
+        self.dedup_policy = dedup_policy;
+        self
+    }
+
+    /// Stop reading the chunks once `limit` rows were read, if that is
+    /// possible without resolving duplicates or applying delete predicates.
+    pub(crate) fn with_limit(mut self, limit: Option<usize>) -> Self {
+        self.limit = limit;
+        self
+    }
+
     /// The IOx scan process needs to deduplicate data if there are duplicates. Hence it will look
     /// like below.
     ///
@@ -504,12 +686,16 @@ impl Deduplicater {
         mut predicate: Predicate,
         output_sort_key: Option<SortKey>,
     ) -> Result<Arc<dyn ExecutionPlan>> {
+        // Append-only tables keep every row, so their duplicates are never resolved
+        let deduplication =
+            self.deduplication && self.dedup_policy != DeduplicationPolicy::AppendOnly;
+
         // find overlapped chunks and put them into the right group
-        let mut chunks = Chunks::split_overlapped_chunks(chunks, self.deduplication)?;
+        let mut chunks = Chunks::split_overlapped_chunks(chunks, deduplication)?;
 
         // Building plans
         let mut plans: Vec<Arc<dyn ExecutionPlan>> = vec![];
-        if !self.deduplication || chunks.no_duplicates() {
+        if !deduplication || chunks.no_duplicates() {
             // Either we do not want to do deduplication even if there are duplicates in the chunks or
             // the chunks have neither overlaps nor duplicates
             if !self.deduplication {
@@ -527,6 +713,12 @@ impl Deduplicater {
                 debug!(%table_name,  "All chunks neither overlap nor duplicate. Build only one scan node for all of them.");
             }
 
+            // Any rows answer the query unless they are fed through de-dup later or have to be
+            // deleted
+            let limit = self
+                .limit
+                .filter(|_| self.deduplication && chunks.no_delete_predicates());
+
             let mut non_duplicate_plans = Self::build_plans_for_non_duplicates_chunks(
                 self.ctx.child_ctx("build_plans_for_non_duplicates_chunks"),
                 output_schema,
@@ -534,7 +726,8 @@ impl Deduplicater {
                 predicate,
                 output_sort_key.as_ref(),
                 &mut self.schema_interner,
-                self.deduplication,
+                deduplication,
+                limit,
             )?;
             plans.append(&mut non_duplicate_plans);
         } else {
@@ -595,6 +788,7 @@ impl Deduplicater {
                     predicate.clone(),
                     &chunks_dedup_sort_key,
                     &mut self.schema_interner,
+                    self.dedup_policy,
                 )?);
             }
 
@@ -624,6 +818,7 @@ impl Deduplicater {
                     predicate.clone(),
                     &chunk_dedup_sort_key,
                     &mut self.schema_interner,
+                    self.dedup_policy,
                 )?);
             }
 
@@ -643,6 +838,7 @@ impl Deduplicater {
                     output_sort_key.as_ref(),
                     &mut self.schema_interner,
                     false,
+                    None,
                 )?;
                 plans.append(&mut non_duplicate_plans);
             }
@@ -810,6 +1006,7 @@ impl Deduplicater {
         predicate: Predicate,
         output_sort_key: &SortKey,
         schema_interner: &mut SchemaInterner,
+        dedup_policy: DeduplicationPolicy,
     ) -> Result<Arc<dyn ExecutionPlan>> {
         // Note that we may need to sort/deduplicate based on tag
         // columns which do not appear in the output
@@ -864,7 +1061,7 @@ impl Deduplicater {
         ));
 
         // Add DeduplicateExc
-        let plan = Self::add_deduplicate_node(sort_exprs, plan);
+        let plan = Self::add_deduplicate_node(sort_exprs, plan, dedup_policy);
 
         // select back to the requested output schema
         Self::add_projection_node_if_needed(output_schema, plan)
@@ -902,6 +1099,7 @@ impl Deduplicater {
         predicate: Predicate,
         output_sort_key: &SortKey,
         schema_interner: &mut SchemaInterner,
+        dedup_policy: DeduplicationPolicy,
     ) -> Result<Arc<dyn ExecutionPlan>> {
         // This will practically never matter because this can only happen for in-memory chunks which are currently
         // backed by RecordBatches and these don't do anything with the predicate at all. However to prevent weird
@@ -935,7 +1133,7 @@ impl Deduplicater {
         // Sort exprs for the deduplication
         let sort_exprs = arrow_sort_key_exprs(output_sort_key, &plan.schema());
         debug!(?sort_exprs, chunk_id=?chunks[0].id(), "Sort Expression for the deduplicate node of chunk");
-        let plan = Self::add_deduplicate_node(sort_exprs, plan);
+        let plan = Self::add_deduplicate_node(sort_exprs, plan, dedup_policy);
 
         // select back to the requested output schema
         Self::add_projection_node_if_needed(output_schema, plan)
@@ -945,8 +1143,9 @@ impl Deduplicater {
     fn add_deduplicate_node(
         sort_exprs: Vec<PhysicalSortExpr>,
         input: Arc<dyn ExecutionPlan>,
+        dedup_policy: DeduplicationPolicy,
     ) -> Arc<dyn ExecutionPlan> {
-        Arc::new(DeduplicateExec::new(input, sort_exprs))
+        Arc::new(DeduplicateExec::new(input, sort_exprs).with_dedup_policy(dedup_policy))
     }
 
     /// Creates a plan that produces output_schema given a plan that
@@ -1098,6 +1297,7 @@ impl Deduplicater {
             output_sort_key,
             vec![Arc::clone(&chunk)],
             predicate,
+            None,
             ctx.inner().task_ctx(),
         );
 
@@ -1259,6 +1459,7 @@ impl Deduplicater {
         output_sort_key: Option<&SortKey>,
         schema_interner: &mut SchemaInterner,
         deduplication: bool,
+        limit: Option<usize>,
     ) -> Result<Vec<Arc<dyn ExecutionPlan>>> {
         if deduplication {
             assert!(chunks.no_duplicates());
@@ -1276,6 +1477,7 @@ impl Deduplicater {
                 output_sort_key,
                 chunks.into_no_duplicates(deduplication),
                 predicate,
+                limit,
                 ctx.inner().task_ctx(),
             ));
             return Ok(plans);
@@ -1461,6 +1663,7 @@ mod test {
             None,
             vec![Arc::clone(&chunk)],
             Predicate::default(),
+            None,
             IOxSessionContext::with_testing().inner().task_ctx(),
         );
 
@@ -1545,6 +1748,7 @@ mod test {
             None,
             vec![Arc::clone(&chunk)],
             Predicate::default(),
+            None,
             IOxSessionContext::with_testing().inner().task_ctx(),
         );
         let batch = test_collect(Arc::clone(&input)).await;
@@ -1699,6 +1903,7 @@ mod test {
             Predicate::default(),
             &sort_key,
             &mut SchemaInterner::default(),
+            DeduplicationPolicy::Upsert,
         )
         .unwrap();
 
@@ -1729,6 +1934,7 @@ mod test {
             Predicate::default(),
             &sort_key,
             &mut SchemaInterner::default(),
+            DeduplicationPolicy::Upsert,
         )
         .unwrap();
 
@@ -1779,6 +1985,7 @@ mod test {
             None, // not ask to sort the output of the plan
             &mut SchemaInterner::default(),
             false,
+            None,
         )
         .unwrap();
 
@@ -1801,6 +2008,7 @@ mod test {
             Some(&sort_key), // sort output on this sort_key
             &mut SchemaInterner::default(),
             false,
+            None,
         )
         .unwrap();
 
@@ -1966,6 +2174,7 @@ mod test {
             Predicate::default(),
             &output_sort_key,
             &mut SchemaInterner::default(),
+            DeduplicationPolicy::Upsert,
         )
         .unwrap();
 
@@ -2026,6 +2235,7 @@ mod test {
             Predicate::default(),
             &output_sort_key,
             &mut SchemaInterner::default(),
+            DeduplicationPolicy::Upsert,
         )
         .unwrap();
 
@@ -2102,6 +2312,7 @@ mod test {
             Predicate::default(),
             &output_sort_key,
             &mut SchemaInterner::default(),
+            DeduplicationPolicy::Upsert,
         )
         .unwrap();
         let batch = test_collect(sort_plan).await;
@@ -2201,6 +2412,7 @@ mod test {
             Predicate::default(),
             &output_sort_key,
             &mut SchemaInterner::default(),
+            DeduplicationPolicy::Upsert,
         )
         .unwrap();
         let batch = test_collect(sort_plan).await;
@@ -2308,6 +2520,7 @@ mod test {
             Predicate::default(),
             &output_sort_key,
             &mut SchemaInterner::default(),
+            DeduplicationPolicy::Upsert,
         )
         .unwrap();
         let batch = test_collect(sort_plan).await;
@@ -2489,6 +2702,49 @@ mod test {
         let batch = test_collect(plan).await;
         // Deduplication is disabled, the output shoudl be the same as the original data
         assert_batches_sorted_eq!(&original_expected, &batch);
+
+        // ----------------------------------
+        // Test with the keep-first policy: the first written row of each key wins
+        let deduplicator = Deduplicater::new(IOxSessionContext::with_testing())
+            .with_dedup_policy(DeduplicationPolicy::KeepFirst);
+        let plan = deduplicator
+            .build_scan_plan(
+                Arc::from("t"),
+                &schema,
+                chunks.clone(),
+                Predicate::default(),
+                None,
+            )
+            .unwrap();
+        let plan_str = format!("{}", displayable(plan.as_ref()).indent());
+        assert!(plan_str.contains("policy=keep_first"), "{plan_str}");
+        let batch = test_collect(plan).await;
+        let expected = vec![
+            "+-----------+------+--------------------------------+",
+            "| field_int | tag1 | time                           |",
+            "+-----------+------+--------------------------------+",
+            "| 100       | AL   | 1970-01-01T00:00:00.000000050Z |",
+            "| 70        | CT   | 1970-01-01T00:00:00.000000100Z |",
+            "| 70        | CT   | 1970-01-01T00:00:00.000000500Z |",
+            "| 5         | MT   | 1970-01-01T00:00:00.000000005Z |",
+            "| 1000      | MT   | 1970-01-01T00:00:00.000001Z    |",
+            "| 1000      | MT   | 1970-01-01T00:00:00.000002Z    |",
+            "| 10        | MT   | 1970-01-01T00:00:00.000007Z    |",
+            "+-----------+------+--------------------------------+",
+        ];
+        assert_batches_eq!(&expected, &batch);
+
+        // ----------------------------------
+        // Test with the append-only policy: all rows are kept
+        let deduplicator = Deduplicater::new(IOxSessionContext::with_testing())
+            .with_dedup_policy(DeduplicationPolicy::AppendOnly);
+        let plan = deduplicator
+            .build_scan_plan(Arc::from("t"), &schema, chunks, Predicate::default(), None)
+            .unwrap();
+        let plan_str = format!("{}", displayable(plan.as_ref()).indent());
+        assert!(!plan_str.contains("DeduplicateExec"), "{plan_str}");
+        let batch = test_collect(plan).await;
+        assert_batches_sorted_eq!(&original_expected, &batch);
     }
 
     #[tokio::test]
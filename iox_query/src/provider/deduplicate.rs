@@ -8,6 +8,7 @@ use arrow::{
     error::{ArrowError, Result as ArrowResult},
     record_batch::RecordBatch,
 };
+use data_types::DeduplicationPolicy;
 use datafusion_util::{watch::WatchedTask, AdapterStream};
 
 pub use self::algo::RecordBatchDeduplicator;
@@ -106,10 +107,24 @@ use tokio::sync::mpsc;
 ///
 /// Thus it would not be correct to take the latest value from f2
 /// (NULL) as in the source input the field's value was not provided.
+///
+/// # Keep First
+///
+/// Tables with [`DeduplicationPolicy::KeepFirst`] instead keep the
+/// first row written for each primary key and ignore any later
+/// writes. For the example above that produces `(a, x, 2, NULL)`.
+///
+/// # Memory
+///
+/// Deduplication streams its sorted input and only holds the rows of
+/// the current primary key in memory. The sort beneath it is what
+/// needs memory for large inputs, and it spills to disk when the
+/// executor is configured with a spill directory.
 #[derive(Debug)]
 pub struct DeduplicateExec {
     input: Arc<dyn ExecutionPlan>,
     sort_keys: Vec<PhysicalSortExpr>,
+    dedup_policy: DeduplicationPolicy,
     /// Execution metrics
     metrics: ExecutionPlanMetricsSet,
 }
@@ -119,9 +134,21 @@ impl DeduplicateExec {
         Self {
             input,
             sort_keys,
+            dedup_policy: DeduplicationPolicy::default(),
             metrics: ExecutionPlanMetricsSet::new(),
         }
     }
+
+    /// Resolve duplicated primary keys according to `dedup_policy`.
+    pub fn with_dedup_policy(mut self, dedup_policy: DeduplicationPolicy) -> Self {
+        self.dedup_policy = dedup_policy;
+        self
+    }
+
+    /// The policy used to resolve duplicated primary keys.
+    pub fn dedup_policy(&self) -> DeduplicationPolicy {
+        self.dedup_policy
+    }
 }
 
 #[derive(Debug)]
@@ -178,7 +205,9 @@ impl ExecutionPlan for DeduplicateExec {
     ) -> datafusion::error::Result<Arc<dyn ExecutionPlan>> {
         assert_eq!(children.len(), 1);
         let input = Arc::clone(&children[0]);
-        Ok(Arc::new(Self::new(input, self.sort_keys.clone())))
+        Ok(Arc::new(
+            Self::new(input, self.sort_keys.clone()).with_dedup_policy(self.dedup_policy),
+        ))
     }
 
     fn execute(
@@ -204,6 +233,7 @@ impl ExecutionPlan for DeduplicateExec {
         let fut = deduplicate(
             input_stream,
             self.sort_keys.clone(),
+            self.dedup_policy,
             tx.clone(),
             deduplicate_metrics,
         );
@@ -230,7 +260,11 @@ impl ExecutionPlan for DeduplicateExec {
         match t {
             DisplayFormatType::Default => {
                 let expr: Vec<String> = self.sort_keys.iter().map(|e| e.to_string()).collect();
-                write!(f, "DeduplicateExec: [{}]", expr.join(","))
+                write!(f, "DeduplicateExec: [{}]", expr.join(","))?;
+                if self.dedup_policy != DeduplicationPolicy::Upsert {
+                    write!(f, ", policy={}", self.dedup_policy)?;
+                }
+                Ok(())
             }
         }
     }
@@ -251,6 +285,7 @@ impl ExecutionPlan for DeduplicateExec {
 async fn deduplicate(
     mut input_stream: SendableRecordBatchStream,
     sort_keys: Vec<PhysicalSortExpr>,
+    dedup_policy: DeduplicationPolicy,
     tx: mpsc::Sender<ArrowResult<RecordBatch>>,
     deduplicate_metrics: DeduplicateMetrics,
 ) -> ArrowResult<()> {
@@ -260,7 +295,8 @@ async fn deduplicate(
     } = deduplicate_metrics;
 
     let elapsed_compute = baseline_metrics.elapsed_compute();
-    let mut deduplicator = RecordBatchDeduplicator::new(sort_keys, num_dupes, None);
+    let mut deduplicator =
+        RecordBatchDeduplicator::new(sort_keys, num_dupes, None).with_dedup_policy(dedup_policy);
 
     // Stream input through the indexer
     while let Some(batch) = input_stream.next().await {
@@ -406,6 +442,60 @@ mod test {
         assert_batches_eq!(&expected, &results.output);
     }
 
+    #[tokio::test]
+    async fn test_single_tag_keep_first() {
+        // input:
+        // t1 | f1 | f2
+        // ---+----+----
+        //  a | 1  |
+        //  a | 2  | 3
+        //  b | 5  | 6
+        //  c |    | 8
+        //  c | 7  | 9
+        //
+        // expected output:
+        //
+        // t1 | f1 | f2
+        // ---+----+----
+        //  a | 1  |
+        //  b | 5  | 6
+        //  c |    | 8
+
+        let t1 = StringArray::from(vec![Some("a"), Some("a"), Some("b"), Some("c"), Some("c")]);
+        let f1 = Float64Array::from(vec![Some(1.0), Some(2.0), Some(5.0), None, Some(7.0)]);
+        let f2 = Float64Array::from(vec![None, Some(3.0), Some(6.0), Some(8.0), Some(9.0)]);
+
+        let batch = RecordBatch::try_from_iter(vec![
+            ("t1", Arc::new(t1) as ArrayRef),
+            ("f1", Arc::new(f1) as ArrayRef),
+            ("f2", Arc::new(f2) as ArrayRef),
+        ])
+        .unwrap();
+
+        let sort_keys = vec![PhysicalSortExpr {
+            expr: col("t1", &batch.schema()).unwrap(),
+            options: SortOptions {
+                descending: false,
+                nulls_first: false,
+            },
+        }];
+
+        let results =
+            dedupe_with_policy(vec![batch], sort_keys, DeduplicationPolicy::KeepFirst).await;
+
+        let expected = vec![
+            "+----+----+----+",
+            "| t1 | f1 | f2 |",
+            "+----+----+----+",
+            "| a  | 1  |    |",
+            "| b  | 5  | 6  |",
+            "| c  |    | 8  |",
+            "+----+----+----+",
+        ];
+        assert_batches_eq!(&expected, &results.output);
+        assert_eq!(results.num_dupes(), 2);
+    }
+
     #[tokio::test]
     async fn test_with_timestamp() {
         // input:
@@ -1092,6 +1182,14 @@ mod test {
 
     /// Run the input through the deduplicator and return results
     async fn dedupe(input: Vec<RecordBatch>, sort_keys: Vec<PhysicalSortExpr>) -> TestResults {
+        dedupe_with_policy(input, sort_keys, DeduplicationPolicy::Upsert).await
+    }
+
+    async fn dedupe_with_policy(
+        input: Vec<RecordBatch>,
+        sort_keys: Vec<PhysicalSortExpr>,
+        dedup_policy: DeduplicationPolicy,
+    ) -> TestResults {
         test_helpers::maybe_start_logging();
 
         // Setup in memory stream
@@ -1100,7 +1198,7 @@ mod test {
         let input = Arc::new(MemoryExec::try_new(&[input], schema, projection).unwrap());
 
         // Create and run the deduplicator
-        let exec = Arc::new(DeduplicateExec::new(input, sort_keys));
+        let exec = Arc::new(DeduplicateExec::new(input, sort_keys).with_dedup_policy(dedup_policy));
         let output = test_collect(Arc::clone(&exec) as Arc<dyn ExecutionPlan>).await;
 
         TestResults { output, exec }
@@ -11,6 +11,7 @@ use arrow::{
 };
 
 use arrow_util::optimize::optimize_dictionaries;
+use data_types::DeduplicationPolicy;
 use datafusion::physical_plan::{
     coalesce_batches::concat_batches, expressions::PhysicalSortExpr, metrics, PhysicalExpr,
 };
@@ -26,6 +27,7 @@ pub struct RecordBatchDeduplicator {
     sort_keys: Vec<PhysicalSortExpr>,
     last_batch: Option<RecordBatch>,
     num_dupes: metrics::Count,
+    dedup_policy: DeduplicationPolicy,
 }
 
 #[derive(Debug)]
@@ -49,9 +51,21 @@ impl RecordBatchDeduplicator {
             sort_keys,
             last_batch,
             num_dupes,
+            dedup_policy: DeduplicationPolicy::default(),
         }
     }
 
+    /// Resolve rows with the same primary key according to
+    /// `dedup_policy`.
+    ///
+    /// With [`DeduplicationPolicy::KeepFirst`] the first row of each
+    /// group is kept as is, otherwise the last non-null value of each
+    /// field is picked.
+    pub fn with_dedup_policy(mut self, dedup_policy: DeduplicationPolicy) -> Self {
+        self.dedup_policy = dedup_policy;
+        self
+    }
+
     /// Push a new RecordBatch into the indexer. Returns a
     /// deduplicated RecordBatch and remembers any currently opened
     /// groups
@@ -303,6 +317,7 @@ impl RecordBatchDeduplicator {
             let take_options = Some(TakeOptions {
                 check_bounds: false,
             });
+            let keep_first = self.dedup_policy == DeduplicationPolicy::KeepFirst;
 
             // Form each new column by `take`ing the indices as needed
             let new_columns = batch
@@ -310,7 +325,9 @@ impl RecordBatchDeduplicator {
                 .iter()
                 .enumerate()
                 .map(|(input_index, input_array)| {
-                    if dupe_ranges.is_sort_key[input_index] {
+                    if dupe_ranges.is_sort_key[input_index] || keep_first {
+                        // sort key columns (and, when keeping the first
+                        // row, all columns) take the first row of the group
                         arrow::compute::take(
                             input_array.as_ref(),
                             &sort_key_indices,
@@ -0,0 +1,209 @@
+//! A `TableProvider` that can answer "last value" queries from a cache.
+
+use std::{any::Any, collections::HashSet, sync::Arc};
+
+use arrow::datatypes::SchemaRef as ArrowSchemaRef;
+use async_trait::async_trait;
+use datafusion::{
+    datasource::TableProvider,
+    error::Result as DataFusionResult,
+    execution::context::SessionState,
+    logical_expr::{
+        utils::expr_to_columns, BinaryExpr, Operator, TableProviderFilterPushDown, TableType,
+    },
+    physical_plan::ExecutionPlan,
+    prelude::Expr,
+    scalar::ScalarValue,
+};
+use schema::{InfluxColumnType, Schema, TIME_COLUMN_NAME};
+
+/// Wraps the [`TableProvider`] of a table that keeps the last values of its
+/// series in a cache.
+///
+/// All scans are delegated to the `inner` provider. The `HandleLastValue`
+/// optimizer rule replaces the source of the scans of queries that only need
+/// the last value of each field of each series (such as
+/// `ORDER BY time DESC LIMIT 1` or `selector_last` grouped by tags) with the
+/// `last_values` provider, whose rows are the last non-null values of each
+/// field of each series.
+///
+/// The `last_values` provider receives the filters of the query, which only
+/// restrict tags or bound the time (see [`LastValueFilter`]). It must fall
+/// back to scanning the whole table if it cannot honor an upper time bound.
+#[derive(Debug)]
+pub struct LastValueTableProvider {
+    inner: Arc<dyn TableProvider>,
+    last_values: Arc<dyn TableProvider>,
+}
+
+impl LastValueTableProvider {
+    /// Create a new provider.
+    pub fn new(inner: Arc<dyn TableProvider>, last_values: Arc<dyn TableProvider>) -> Self {
+        Self { inner, last_values }
+    }
+
+    /// The provider of all data of the table.
+    pub fn inner(&self) -> &Arc<dyn TableProvider> {
+        &self.inner
+    }
+
+    /// The provider of the last values of the table.
+    pub fn last_values(&self) -> &Arc<dyn TableProvider> {
+        &self.last_values
+    }
+}
+
+#[async_trait]
+impl TableProvider for LastValueTableProvider {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self) -> ArrowSchemaRef {
+        self.inner.schema()
+    }
+
+    fn table_type(&self) -> TableType {
+        self.inner.table_type()
+    }
+
+    async fn scan(
+        &self,
+        ctx: &SessionState,
+        projection: Option<&Vec<usize>>,
+        filters: &[Expr],
+        limit: Option<usize>,
+    ) -> DataFusionResult<Arc<dyn ExecutionPlan>> {
+        self.inner.scan(ctx, projection, filters, limit).await
+    }
+
+    fn supports_filter_pushdown(
+        &self,
+        filter: &Expr,
+    ) -> DataFusionResult<TableProviderFilterPushDown> {
+        self.inner.supports_filter_pushdown(filter)
+    }
+}
+
+/// A filter of a query that may be answered from the last values of a table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LastValueFilter {
+    /// Only refers to tag columns.
+    Tags,
+
+    /// `time > t` or `time >= t`.
+    TimeLowerBound,
+
+    /// `time < end`, `time <= end - 1` or `time = end - 1`.
+    ///
+    /// The last values only answer a query with this filter if all of them
+    /// are before `end`.
+    TimeUpperBound(i64),
+}
+
+impl LastValueFilter {
+    /// Classify `expr` for the table with the given `schema`, returning
+    /// `None` if the query can not be answered from the last values.
+    pub fn try_new(expr: &Expr, schema: &Schema) -> Option<Self> {
+        if let Some(filter) = time_bound(expr) {
+            return Some(filter);
+        }
+
+        let mut columns = HashSet::new();
+        expr_to_columns(expr, &mut columns).ok()?;
+        columns
+            .iter()
+            .all(|c| {
+                schema
+                    .find_index_of(&c.name)
+                    .map(|idx| schema.field(idx).0 == InfluxColumnType::Tag)
+                    .unwrap_or(false)
+            })
+            .then_some(Self::Tags)
+    }
+}
+
+fn time_bound(expr: &Expr) -> Option<LastValueFilter> {
+    let Expr::BinaryExpr(BinaryExpr { left, op, right }) = expr else {
+        return None;
+    };
+
+    // normalize to `time <op> <literal>`
+    let (op, value) = match (left.as_ref(), right.as_ref()) {
+        (Expr::Column(c), Expr::Literal(v)) if c.name == TIME_COLUMN_NAME => (*op, v),
+        (Expr::Literal(v), Expr::Column(c)) if c.name == TIME_COLUMN_NAME => {
+            let op = match op {
+                Operator::Gt => Operator::Lt,
+                Operator::GtEq => Operator::LtEq,
+                Operator::Lt => Operator::Gt,
+                Operator::LtEq => Operator::GtEq,
+                op => *op,
+            };
+            (op, v)
+        }
+        _ => return None,
+    };
+    let ScalarValue::TimestampNanosecond(Some(value), _) = value else {
+        return None;
+    };
+
+    match op {
+        Operator::Gt | Operator::GtEq => Some(LastValueFilter::TimeLowerBound),
+        Operator::Lt => Some(LastValueFilter::TimeUpperBound(*value)),
+        Operator::LtEq | Operator::Eq => {
+            Some(LastValueFilter::TimeUpperBound(value.saturating_add(1)))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use datafusion::prelude::{col, lit, lit_timestamp_nano};
+    use schema::builder::SchemaBuilder;
+
+    use super::*;
+
+    #[test]
+    fn test_classify_filters() {
+        let schema = SchemaBuilder::new()
+            .tag("host")
+            .tag("region")
+            .influx_field("load", schema::InfluxFieldType::Float)
+            .timestamp()
+            .build()
+            .unwrap();
+
+        let cases = [
+            (col("host").eq(lit("a")), Some(LastValueFilter::Tags)),
+            (
+                col("host").eq(lit("a")).or(col("region").not_eq(lit("b"))),
+                Some(LastValueFilter::Tags),
+            ),
+            (col("load").gt(lit(1.0)), None),
+            (col("host").eq(lit("a")).or(col("load").gt(lit(1.0))), None),
+            (
+                col("time").gt_eq(lit_timestamp_nano(10)),
+                Some(LastValueFilter::TimeLowerBound),
+            ),
+            (
+                col("time").lt(lit_timestamp_nano(10)),
+                Some(LastValueFilter::TimeUpperBound(10)),
+            ),
+            (
+                col("time").lt_eq(lit_timestamp_nano(10)),
+                Some(LastValueFilter::TimeUpperBound(11)),
+            ),
+            (
+                lit_timestamp_nano(10).gt(col("time")),
+                Some(LastValueFilter::TimeUpperBound(10)),
+            ),
+            (col("time").not_eq(lit_timestamp_nano(10)), None),
+            (col("unknown").eq(lit("a")), None),
+        ];
+
+        for (expr, expected) in cases {
+            assert_eq!(LastValueFilter::try_new(&expr, &schema), expected, "{expr}");
+        }
+    }
+}
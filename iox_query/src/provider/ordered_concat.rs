@@ -0,0 +1,355 @@
+//! Implementation of OrderedConcatExec, which reads sorted inputs one after another
+
+use std::{
+    collections::VecDeque,
+    fmt,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use arrow::{
+    datatypes::SchemaRef,
+    error::{ArrowError, Result as ArrowResult},
+    record_batch::RecordBatch,
+};
+use datafusion::{
+    error::{DataFusionError, Result},
+    execution::context::TaskContext,
+    physical_plan::{
+        expressions::PhysicalSortExpr,
+        metrics::{BaselineMetrics, ExecutionPlanMetricsSet, MetricsSet},
+        DisplayFormatType, Distribution, ExecutionPlan, Partitioning, RecordBatchStream,
+        SendableRecordBatchStream, Statistics,
+    },
+};
+use futures::{ready, Stream, StreamExt};
+use observability_deps::tracing::trace;
+
+/// # OrderedConcatExec
+///
+/// Concatenates the output of its inputs, which are each sorted on
+/// `sort_exprs` and of which all rows of one input come before all
+/// rows of the next input in that order. The output is therefore also
+/// sorted without merging the inputs.
+///
+/// The inputs are read one after another: an input is only executed
+/// once all previous inputs are exhausted, and no more inputs are
+/// executed once `fetch` rows were produced. For a query like
+/// `ORDER BY time DESC LIMIT 100` over inputs ordered from newest to
+/// oldest, that means only the newest data is read.
+#[derive(Debug)]
+pub struct OrderedConcatExec {
+    inputs: Vec<Arc<dyn ExecutionPlan>>,
+    sort_exprs: Vec<PhysicalSortExpr>,
+    fetch: Option<usize>,
+    schema: SchemaRef,
+    /// Execution metrics
+    metrics: ExecutionPlanMetricsSet,
+}
+
+impl OrderedConcatExec {
+    /// Create a new node.
+    ///
+    /// # Panic
+    /// Panics if `inputs` is empty.
+    pub fn new(
+        inputs: Vec<Arc<dyn ExecutionPlan>>,
+        sort_exprs: Vec<PhysicalSortExpr>,
+        fetch: Option<usize>,
+    ) -> Self {
+        assert!(!inputs.is_empty(), "OrderedConcatExec requires inputs");
+        let schema = inputs[0].schema();
+
+        Self {
+            inputs,
+            sort_exprs,
+            fetch,
+            schema,
+            metrics: ExecutionPlanMetricsSet::new(),
+        }
+    }
+
+    /// The maximum number of rows produced, if any.
+    pub fn fetch(&self) -> Option<usize> {
+        self.fetch
+    }
+}
+
+impl ExecutionPlan for OrderedConcatExec {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn schema(&self) -> SchemaRef {
+        Arc::clone(&self.schema)
+    }
+
+    fn output_partitioning(&self) -> Partitioning {
+        Partitioning::UnknownPartitioning(1)
+    }
+
+    fn output_ordering(&self) -> Option<&[PhysicalSortExpr]> {
+        Some(&self.sort_exprs)
+    }
+
+    fn relies_on_input_order(&self) -> bool {
+        true
+    }
+
+    fn maintains_input_order(&self) -> bool {
+        true
+    }
+
+    fn benefits_from_input_partitioning(&self) -> bool {
+        false
+    }
+
+    fn required_input_distribution(&self) -> Vec<Distribution> {
+        vec![Distribution::SinglePartition; self.inputs.len()]
+    }
+
+    fn children(&self) -> Vec<Arc<dyn ExecutionPlan>> {
+        self.inputs.clone()
+    }
+
+    fn with_new_children(
+        self: Arc<Self>,
+        children: Vec<Arc<dyn ExecutionPlan>>,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        assert_eq!(children.len(), self.inputs.len());
+        Ok(Arc::new(Self::new(
+            children,
+            self.sort_exprs.clone(),
+            self.fetch,
+        )))
+    }
+
+    fn execute(
+        &self,
+        partition: usize,
+        context: Arc<TaskContext>,
+    ) -> Result<SendableRecordBatchStream> {
+        trace!(partition, "Start OrderedConcatExec::execute");
+
+        if partition != 0 {
+            return Err(DataFusionError::Internal(
+                "OrderedConcatExec only supports a single output stream".to_string(),
+            ));
+        }
+
+        Ok(Box::pin(OrderedConcatStream {
+            schema: self.schema(),
+            pending: self.inputs.iter().cloned().collect(),
+            current: None,
+            remaining: self.fetch,
+            context,
+            baseline_metrics: BaselineMetrics::new(&self.metrics, partition),
+        }))
+    }
+
+    fn fmt_as(&self, t: DisplayFormatType, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match t {
+            DisplayFormatType::Default => {
+                let expr: Vec<String> = self.sort_exprs.iter().map(|e| e.to_string()).collect();
+                write!(f, "OrderedConcatExec: [{}]", expr.join(","))?;
+                if let Some(fetch) = self.fetch {
+                    write!(f, ", fetch={fetch}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn metrics(&self) -> Option<MetricsSet> {
+        Some(self.metrics.clone_inner())
+    }
+
+    fn statistics(&self) -> Statistics {
+        Statistics::default()
+    }
+}
+
+/// Reads the inputs of an [`OrderedConcatExec`] one after another.
+struct OrderedConcatStream {
+    schema: SchemaRef,
+    /// Inputs that have not been executed yet
+    pending: VecDeque<Arc<dyn ExecutionPlan>>,
+    /// The input that is currently read
+    current: Option<SendableRecordBatchStream>,
+    /// Number of rows still to produce, if limited
+    remaining: Option<usize>,
+    context: Arc<TaskContext>,
+    baseline_metrics: BaselineMetrics,
+}
+
+impl OrderedConcatStream {
+    fn poll_next_inner(&mut self, cx: &mut Context<'_>) -> Poll<Option<ArrowResult<RecordBatch>>> {
+        loop {
+            if self.remaining == Some(0) {
+                // drop the current input so that it stops reading
+                self.current = None;
+                return Poll::Ready(None);
+            }
+
+            if self.current.is_none() {
+                let Some(input) = self.pending.pop_front() else {
+                    return Poll::Ready(None);
+                };
+                match input.execute(0, Arc::clone(&self.context)) {
+                    Ok(stream) => self.current = Some(stream),
+                    Err(e) => {
+                        return Poll::Ready(Some(Err(ArrowError::ExternalError(Box::new(e)))))
+                    }
+                }
+            }
+            let current = self.current.as_mut().expect("input executed above");
+
+            match ready!(current.poll_next_unpin(cx)) {
+                Some(Ok(batch)) => {
+                    let batch = match self.remaining.as_mut() {
+                        Some(remaining) if batch.num_rows() > *remaining => {
+                            let batch = batch.slice(0, *remaining);
+                            *remaining = 0;
+                            batch
+                        }
+                        Some(remaining) => {
+                            *remaining -= batch.num_rows();
+                            batch
+                        }
+                        None => batch,
+                    };
+                    return Poll::Ready(Some(Ok(batch)));
+                }
+                Some(Err(e)) => return Poll::Ready(Some(Err(e))),
+                None => {
+                    self.current = None;
+                }
+            }
+        }
+    }
+}
+
+impl Stream for OrderedConcatStream {
+    type Item = ArrowResult<RecordBatch>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let poll = self.poll_next_inner(cx);
+        self.baseline_metrics.record_poll(poll)
+    }
+}
+
+impl RecordBatchStream for OrderedConcatStream {
+    fn schema(&self) -> SchemaRef {
+        Arc::clone(&self.schema)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow::array::{ArrayRef, Int64Array};
+    use arrow_util::assert_batches_eq;
+    use datafusion::physical_plan::{collect, expressions::col, memory::MemoryExec};
+
+    use super::*;
+
+    fn input(values: Vec<Vec<i64>>) -> Arc<dyn ExecutionPlan> {
+        let batches: Vec<_> = values
+            .into_iter()
+            .map(|v| {
+                RecordBatch::try_from_iter(vec![("v", Arc::new(Int64Array::from(v)) as ArrayRef)])
+                    .unwrap()
+            })
+            .collect();
+        let schema = batches[0].schema();
+        Arc::new(MemoryExec::try_new(&[batches], schema, None).unwrap())
+    }
+
+    fn concat(inputs: Vec<Arc<dyn ExecutionPlan>>, fetch: Option<usize>) -> OrderedConcatExec {
+        let sort_exprs = vec![PhysicalSortExpr {
+            expr: col("v", &inputs[0].schema()).unwrap(),
+            options: Default::default(),
+        }];
+        OrderedConcatExec::new(inputs, sort_exprs, fetch)
+    }
+
+    #[tokio::test]
+    async fn test_concat() {
+        let exec = Arc::new(concat(
+            vec![input(vec![vec![1, 2], vec![3]]), input(vec![vec![4, 5]])],
+            None,
+        ));
+        let got = collect(exec, Arc::new(TaskContext::default()))
+            .await
+            .unwrap();
+
+        assert_batches_eq!(
+            ["+---+", "| v |", "+---+", "| 1 |", "| 2 |", "| 3 |", "| 4 |", "| 5 |", "+---+",],
+            &got
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fetch_stops_early() {
+        // The last input fails when read, so it must not be executed
+        let failing = Arc::new(FailingExec(input(vec![vec![5]]).schema()));
+
+        let exec = Arc::new(concat(
+            vec![input(vec![vec![1, 2]]), input(vec![vec![3, 4]]), failing],
+            Some(3),
+        ));
+        let got = collect(exec, Arc::new(TaskContext::default()))
+            .await
+            .unwrap();
+
+        assert_batches_eq!(
+            ["+---+", "| v |", "+---+", "| 1 |", "| 2 |", "| 3 |", "+---+",],
+            &got
+        );
+    }
+
+    /// Fails on execution.
+    #[derive(Debug)]
+    struct FailingExec(SchemaRef);
+
+    impl ExecutionPlan for FailingExec {
+        fn as_any(&self) -> &dyn std::any::Any {
+            self
+        }
+
+        fn schema(&self) -> SchemaRef {
+            Arc::clone(&self.0)
+        }
+
+        fn output_partitioning(&self) -> Partitioning {
+            Partitioning::UnknownPartitioning(1)
+        }
+
+        fn output_ordering(&self) -> Option<&[PhysicalSortExpr]> {
+            None
+        }
+
+        fn children(&self) -> Vec<Arc<dyn ExecutionPlan>> {
+            vec![]
+        }
+
+        fn with_new_children(
+            self: Arc<Self>,
+            _children: Vec<Arc<dyn ExecutionPlan>>,
+        ) -> Result<Arc<dyn ExecutionPlan>> {
+            unimplemented!()
+        }
+
+        fn execute(
+            &self,
+            _partition: usize,
+            _context: Arc<TaskContext>,
+        ) -> Result<SendableRecordBatchStream> {
+            Err(DataFusionError::Internal("must not be read".to_string()))
+        }
+
+        fn statistics(&self) -> Statistics {
+            Statistics::default()
+        }
+    }
+}
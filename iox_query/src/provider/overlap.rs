@@ -111,6 +111,31 @@ pub fn group_potential_duplicates(
     Ok(groups)
 }
 
+/// Groups query chunks into disjoint sets of overlapped time range, ordered
+/// by time such that all rows of a set are before the rows of the next set.
+///
+/// Returns `None` if at least one of the chunks has no time range.
+pub fn group_by_time_range(
+    chunks: Vec<Arc<dyn QueryChunk>>,
+) -> Option<Vec<Vec<Arc<dyn QueryChunk>>>> {
+    if chunks
+        .iter()
+        .any(|c| timestamp_min_max(c.as_ref()).is_none())
+    {
+        return None;
+    }
+
+    let mut groups = group_potential_duplicates(chunks).ok()?;
+    groups.sort_by_key(|group| {
+        group
+            .iter()
+            .filter_map(|c| timestamp_min_max(c.as_ref()))
+            .map(|ts| ts.min)
+            .min()
+    });
+    Some(groups)
+}
+
 fn timestamp_min_max(chunk: &dyn QueryChunk) -> Option<TimestampMinMax> {
     chunk.summary().time_range()
 }
@@ -282,6 +307,28 @@ mod test {
         assert_groups_eq!(expected, groups);
     }
 
+    #[test]
+    fn group_by_time_range_ordered() {
+        let c1 = Arc::new(TestChunk::new("chunk1").with_timestamp_min_max(25, 35));
+        let c2 = Arc::new(TestChunk::new("chunk2").with_timestamp_min_max(1, 10));
+        let c3 = Arc::new(TestChunk::new("chunk3").with_timestamp_min_max(10, 20));
+        let c4 = Arc::new(TestChunk::new("chunk4").with_timestamp_min_max(21, 24));
+
+        let groups = group_by_time_range(vec![c1, c2, c3, c4]).expect("all have time ranges");
+
+        let expected = vec![
+            "Group 0: [chunk2, chunk3]",
+            "Group 1: [chunk4]",
+            "Group 2: [chunk1]",
+        ];
+        assert_groups_eq!(expected, groups);
+
+        // no time range
+        let c1 = Arc::new(TestChunk::new("chunk1").with_timestamp_min_max(1, 10));
+        let c2 = Arc::new(TestChunk::new("chunk2"));
+        assert!(group_by_time_range(vec![c1, c2]).is_none());
+    }
+
     // --- Test infrastructure --
     fn to_string(groups: Vec<Vec<Arc<dyn QueryChunk>>>) -> Vec<String> {
         let mut s = vec![];
@@ -17,6 +17,7 @@ use datafusion::{
     },
 };
 use object_store::ObjectMeta;
+use parquet_file::bloom_filter::{RowGroupSelection, RowGroupSelectionReaderFactory};
 use predicate::Predicate;
 use schema::{sort::SortKey, Schema};
 use std::{
@@ -32,10 +33,12 @@ use std::{
 #[derive(Debug)]
 struct ParquetChunkList {
     object_store_url: ObjectStoreUrl,
-    object_metas: Vec<ObjectMeta>,
+    files: Vec<PartitionedFile>,
     /// Sort key to place on the ParquetExec, validated to be
     /// compatible with all chunk sort keys
     sort_key: Option<SortKey>,
+    /// True if any of the files only needs a subset of its row groups scanned.
+    has_row_group_selection: bool,
 }
 
 impl ParquetChunkList {
@@ -47,24 +50,48 @@ impl ParquetChunkList {
         object_store_url: ObjectStoreUrl,
         chunk: &dyn QueryChunk,
         meta: ObjectMeta,
+        row_group_selection: Option<RowGroupSelection>,
         output_sort_key: Option<&SortKey>,
     ) -> Self {
         let sort_key = combine_sort_key(output_sort_key.cloned(), chunk.sort_key());
 
-        Self {
+        let mut list = Self {
             object_store_url,
-            object_metas: vec![meta],
+            files: vec![],
             sort_key,
-        }
+            has_row_group_selection: false,
+        };
+        list.push_file(meta, row_group_selection);
+        list
     }
 
     /// Add the parquet file the list of files to be scanned, updating
     /// the sort key as necessary.
-    fn add_parquet_file(&mut self, chunk: &dyn QueryChunk, meta: ObjectMeta) {
-        self.object_metas.push(meta);
+    fn add_parquet_file(
+        &mut self,
+        chunk: &dyn QueryChunk,
+        meta: ObjectMeta,
+        row_group_selection: Option<RowGroupSelection>,
+    ) {
+        self.push_file(meta, row_group_selection);
 
         self.sort_key = combine_sort_key(self.sort_key.take(), chunk.sort_key());
     }
+
+    fn push_file(
+        &mut self,
+        object_meta: ObjectMeta,
+        row_group_selection: Option<RowGroupSelection>,
+    ) {
+        self.has_row_group_selection |= row_group_selection.is_some();
+
+        self.files.push(PartitionedFile {
+            object_meta,
+            partition_values: vec![],
+            range: None,
+            extensions: row_group_selection.map(|s| Arc::new(s) as _),
+        });
+    }
 }
 
 /// Combines the existing sort key with the sort key of the chunk,
@@ -118,11 +145,17 @@ fn combine_sort_key(
 /// The give `predicate` will only be applied to [`ParquetExec`] nodes since they are the only node type benifiting from
 /// pushdown ([`RecordBatchesExec`] has NO builtin filter function). Delete predicates are NOT applied at all. The
 /// caller is responsible for wrapping the output node into appropriate filter nodes.
+///
+/// # Limit
+/// The given `limit` is pushed into the [`ParquetExec`] nodes so that they stop reading files once enough rows were
+/// produced. It must only be set if any `limit` rows of the chunks answer the query, i.e. if the chunks neither need
+/// deduplication nor have delete predicates.
 pub fn chunks_to_physical_nodes(
     iox_schema: &Schema,
     output_sort_key: Option<&SortKey>,
     chunks: Vec<Arc<dyn QueryChunk>>,
     predicate: Predicate,
+    limit: Option<usize>,
     context: Arc<TaskContext>,
 ) -> Arc<dyn ExecutionPlan> {
     if chunks.is_empty() {
@@ -141,14 +174,18 @@ pub fn chunks_to_physical_nodes(
                 let url_str = parquet_input.object_store_url.as_str().to_owned();
                 match parquet_chunks.entry(url_str) {
                     Entry::Occupied(mut o) => {
-                        o.get_mut()
-                            .add_parquet_file(chunk.as_ref(), parquet_input.object_meta);
+                        o.get_mut().add_parquet_file(
+                            chunk.as_ref(),
+                            parquet_input.object_meta,
+                            parquet_input.row_group_selection,
+                        );
                     }
                     Entry::Vacant(v) => {
                         v.insert(ParquetChunkList::new(
                             parquet_input.object_store_url,
                             chunk.as_ref(),
                             parquet_input.object_meta,
+                            parquet_input.row_group_selection,
                             output_sort_key,
                         ));
                     }
@@ -170,19 +207,20 @@ pub fn chunks_to_physical_nodes(
     for (_url_str, chunk_list) in parquet_chunks {
         let ParquetChunkList {
             object_store_url,
-            object_metas,
+            files,
             sort_key,
+            has_row_group_selection,
         } = chunk_list;
 
-        let file_groups = distribute(
-            object_metas.into_iter().map(|object_meta| PartitionedFile {
-                object_meta,
-                partition_values: vec![],
-                range: None,
-                extensions: None,
-            }),
-            target_partitions,
-        );
+        // Files that only need some of their row groups scanned (e.g. due to
+        // bloom filter pruning) require a reader that understands the
+        // selection attached to them.
+        let reader_factory = has_row_group_selection
+            .then(|| context.runtime_env().object_store(&object_store_url).ok())
+            .flatten()
+            .map(|store| Arc::new(RowGroupSelectionReaderFactory::new(store)));
+
+        let file_groups = distribute(files, target_partitions);
 
         // Tell datafusion about the sort key, if any
         let file_schema = iox_schema.as_arrow();
@@ -195,12 +233,16 @@ pub fn chunks_to_physical_nodes(
             file_groups,
             statistics: Statistics::default(),
             projection: None,
-            limit: None,
+            limit,
             table_partition_cols: vec![],
             output_ordering,
         };
         let meta_size_hint = None;
-        let parquet_exec = ParquetExec::new(base_config, predicate.filter_expr(), meta_size_hint);
+        let mut parquet_exec =
+            ParquetExec::new(base_config, predicate.filter_expr(), meta_size_hint);
+        if let Some(reader_factory) = reader_factory {
+            parquet_exec = parquet_exec.with_parquet_file_reader_factory(reader_factory);
+        }
         output_nodes.push(Arc::new(parquet_exec));
     }
 
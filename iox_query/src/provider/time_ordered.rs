@@ -0,0 +1,116 @@
+//! A `TableProvider` that can scan the first rows of a table in time order.
+
+use std::{any::Any, sync::Arc};
+
+use arrow::datatypes::SchemaRef as ArrowSchemaRef;
+use async_trait::async_trait;
+use datafusion::{
+    datasource::TableProvider,
+    error::Result as DataFusionResult,
+    execution::context::SessionState,
+    logical_expr::{TableProviderFilterPushDown, TableType},
+    physical_plan::ExecutionPlan,
+    prelude::Expr,
+};
+
+/// The order in which a query reads the rows of a table, for queries like
+/// `ORDER BY time DESC LIMIT 100`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeOrder {
+    /// Read the newest rows first.
+    pub descending: bool,
+
+    /// Number of rows the query needs in that order.
+    pub fetch: usize,
+}
+
+/// A table that can scan its rows in [`TimeOrder`].
+#[async_trait]
+pub trait TimeOrderedTable: TableProvider {
+    /// Create a scan that produces at least the first `order.fetch` rows that
+    /// match all `filters`, sorted by time.
+    ///
+    /// Unlike [`TableProvider::scan`], the filters must be applied exactly
+    /// before the rows are counted. If the table can not do better, it may
+    /// fall back to [`TableProvider::scan`] and produce all rows.
+    async fn scan_time_ordered(
+        &self,
+        state: &SessionState,
+        projection: Option<&Vec<usize>>,
+        filters: &[Expr],
+        order: TimeOrder,
+    ) -> DataFusionResult<Arc<dyn ExecutionPlan>>;
+}
+
+/// Wraps a [`TimeOrderedTable`].
+///
+/// Scans are delegated to [`TableProvider::scan`] of the table unless the
+/// `HandleTimeOrderedLimit` optimizer rule found that the query only needs
+/// the first rows of the table in time order and set the
+/// [order](Self::with_time_order) of the scan. Such scans are delegated to
+/// [`TimeOrderedTable::scan_time_ordered`], which for instance only reads
+/// the newest files of a table for `ORDER BY time DESC LIMIT 100`.
+#[derive(Debug, Clone)]
+pub struct TimeOrderedTableProvider {
+    table: Arc<dyn TimeOrderedTable>,
+    order: Option<TimeOrder>,
+}
+
+impl TimeOrderedTableProvider {
+    /// Create a new provider.
+    pub fn new(table: Arc<dyn TimeOrderedTable>) -> Self {
+        Self { table, order: None }
+    }
+
+    /// Scan the table in the given order.
+    pub fn with_time_order(&self, order: TimeOrder) -> Self {
+        Self {
+            table: Arc::clone(&self.table),
+            order: Some(order),
+        }
+    }
+
+    /// The order in which the table is scanned, if any.
+    pub fn time_order(&self) -> Option<TimeOrder> {
+        self.order
+    }
+}
+
+#[async_trait]
+impl TableProvider for TimeOrderedTableProvider {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self) -> ArrowSchemaRef {
+        self.table.schema()
+    }
+
+    fn table_type(&self) -> TableType {
+        self.table.table_type()
+    }
+
+    async fn scan(
+        &self,
+        state: &SessionState,
+        projection: Option<&Vec<usize>>,
+        filters: &[Expr],
+        limit: Option<usize>,
+    ) -> DataFusionResult<Arc<dyn ExecutionPlan>> {
+        match self.order {
+            Some(order) => {
+                self.table
+                    .scan_time_ordered(state, projection, filters, order)
+                    .await
+            }
+            None => self.table.scan(state, projection, filters, limit).await,
+        }
+    }
+
+    fn supports_filter_pushdown(
+        &self,
+        filter: &Expr,
+    ) -> DataFusionResult<TableProviderFilterPushDown> {
+        self.table.supports_filter_pushdown(filter)
+    }
+}
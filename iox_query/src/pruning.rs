@@ -9,13 +9,15 @@ use arrow::{
 };
 use data_types::{StatValues, Statistics, TableSummary};
 use datafusion::{
+    logical_expr::{BinaryExpr, Operator},
     physical_optimizer::pruning::{PruningPredicate, PruningStatistics},
-    prelude::Column,
+    prelude::{Column, Expr},
+    scalar::ScalarValue,
 };
 use observability_deps::tracing::{debug, trace, warn};
 use predicate::Predicate;
 use query_functions::group_by::Aggregate;
-use schema::Schema;
+use schema::{InfluxColumnType, Schema};
 use std::sync::Arc;
 
 /// Reason why a chunk could not be pruned.
@@ -31,6 +33,12 @@ pub enum NotPrunedReason {
 
     /// DataFusion pruning failed
     DataFusionPruningFailed,
+
+    /// The file has no bloom filter for any of the predicate columns
+    NoBloomFilter,
+
+    /// Reading the bloom filters of the file failed
+    BloomFilterReadFailed,
 }
 
 impl NotPrunedReason {
@@ -40,6 +48,8 @@ impl NotPrunedReason {
             Self::NoExpressionOnPredicate => "No expression on predicate",
             Self::CanNotCreatePruningPredicate => "Can not create pruning predicate",
             Self::DataFusionPruningFailed => "DataFusion pruning failed",
+            Self::NoBloomFilter => "No bloom filter",
+            Self::BloomFilterReadFailed => "Bloom filter read failed",
         }
     }
 }
@@ -122,6 +132,113 @@ pub fn prune_summaries(
     Ok(results)
 }
 
+/// Extract the tag value sets that can be checked against bloom filters from
+/// `predicate`.
+///
+/// Each returned `(column, values)` pair states that a row can only match
+/// `predicate` if the value of the tag `column` is one of `values`. This is
+/// derived from equality (`tag = 'v'`), `IN` list (`tag IN ('a', 'b')`) and
+/// disjunctions of equalities on the same tag (`tag = 'a' OR tag = 'b'`) that
+/// are AND-ed together in the predicate.
+///
+/// Expressions that do not reference a tag column of `table_schema`, or that
+/// cannot be expressed as a set of values, are ignored.
+pub fn bloom_filter_values(
+    table_schema: &Schema,
+    predicate: &Predicate,
+) -> Vec<(String, Vec<String>)> {
+    let mut out = vec![];
+    for expr in &predicate.exprs {
+        collect_bloom_filter_values(table_schema, expr, &mut out);
+    }
+    out
+}
+
+fn collect_bloom_filter_values(
+    table_schema: &Schema,
+    expr: &Expr,
+    out: &mut Vec<(String, Vec<String>)>,
+) {
+    match expr {
+        Expr::BinaryExpr(BinaryExpr {
+            left,
+            op: Operator::And,
+            right,
+        }) => {
+            collect_bloom_filter_values(table_schema, left, out);
+            collect_bloom_filter_values(table_schema, right, out);
+        }
+        expr => {
+            if let Some((column, values)) = tag_values(expr) {
+                let is_tag = table_schema
+                    .find_index_of(&column.name)
+                    .map(|idx| table_schema.field(idx).0 == InfluxColumnType::Tag)
+                    .unwrap_or_default();
+                if is_tag {
+                    out.push((column.name.clone(), values));
+                }
+            }
+        }
+    }
+}
+
+/// Returns the column and set of values it must be equal to for `expr` to
+/// evaluate to true, if `expr` can be expressed that way.
+fn tag_values(expr: &Expr) -> Option<(&Column, Vec<String>)> {
+    match expr {
+        Expr::BinaryExpr(BinaryExpr {
+            left,
+            op: Operator::Eq,
+            right,
+        }) => match (left.as_ref(), right.as_ref()) {
+            (Expr::Column(c), Expr::Literal(v)) | (Expr::Literal(v), Expr::Column(c)) => {
+                Some((c, vec![string_literal(v)?]))
+            }
+            _ => None,
+        },
+        Expr::BinaryExpr(BinaryExpr {
+            left,
+            op: Operator::Or,
+            right,
+        }) => {
+            let (left_col, mut values) = tag_values(left)?;
+            let (right_col, right_values) = tag_values(right)?;
+            if left_col != right_col {
+                return None;
+            }
+            values.extend(right_values);
+            Some((left_col, values))
+        }
+        Expr::InList {
+            expr,
+            list,
+            negated: false,
+        } => match expr.as_ref() {
+            Expr::Column(c) => {
+                let values = list
+                    .iter()
+                    .map(|e| match e {
+                        Expr::Literal(v) => string_literal(v),
+                        _ => None,
+                    })
+                    .collect::<Option<Vec<_>>>()?;
+                Some((c, values))
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Returns the string value of a (possibly dictionary-encoded) string literal.
+fn string_literal(v: &ScalarValue) -> Option<String> {
+    match v {
+        ScalarValue::Utf8(Some(s)) => Some(s.clone()),
+        ScalarValue::Dictionary(_, v) => string_literal(v),
+        _ => None,
+    }
+}
+
 /// Wraps a collection of [`QueryChunk`] and implements the [`PruningStatistics`]
 /// interface required by [`PruningPredicate`]
 struct ChunkPruningStatistics<'a> {
@@ -713,4 +830,38 @@ mod test {
             vec![true, false, false, true, false, true]
         );
     }
+
+    #[test]
+    fn test_bloom_filter_values() {
+        let chunk = TestChunk::new("chunk1")
+            .with_tag_column("tag1")
+            .with_tag_column("tag2")
+            .with_i64_field_column("field1");
+        let schema = chunk.schema();
+
+        let predicate = Predicate::new()
+            .with_expr(col("tag1").eq(lit("a")))
+            .with_expr(
+                col("tag2")
+                    .in_list(vec![lit("b"), lit_dict("c")], false)
+                    .and(lit("d").eq(col("tag1")).or(col("tag1").eq(lit("e")))),
+            )
+            // not tag columns
+            .with_expr(col("field1").eq(lit(1i64)))
+            .with_expr(col("missing").eq(lit("x")))
+            // not expressible as a set of values
+            .with_expr(col("tag2").not_eq(lit("f")))
+            .with_expr(col("tag2").in_list(vec![lit("g")], true))
+            .with_expr(col("tag1").eq(lit("h")).or(col("tag2").eq(lit("i"))));
+
+        let got = bloom_filter_values(schema, &predicate);
+        assert_eq!(
+            got,
+            vec![
+                ("tag1".to_string(), vec!["a".to_string()]),
+                ("tag2".to_string(), vec!["b".to_string(), "c".to_string()]),
+                ("tag1".to_string(), vec!["d".to_string(), "e".to_string()]),
+            ]
+        );
+    }
 }
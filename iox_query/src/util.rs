@@ -9,7 +9,7 @@ use std::{
 use arrow::{
     array::TimestampNanosecondArray,
     compute::SortOptions,
-    datatypes::{DataType, Schema as ArrowSchema},
+    datatypes::{DataType, Schema as ArrowSchema, SchemaRef},
     record_batch::RecordBatch,
 };
 
@@ -121,8 +121,14 @@ pub fn df_physical_expr(
     input: &dyn ExecutionPlan,
     expr: Expr,
 ) -> std::result::Result<Arc<dyn PhysicalExpr>, DataFusionError> {
-    let schema = input.schema();
+    df_physical_expr_from_schema(input.schema(), expr)
+}
 
+/// Build a datafusion physical expression from a logical one, evaluated against `schema`
+pub fn df_physical_expr_from_schema(
+    schema: SchemaRef,
+    expr: Expr,
+) -> std::result::Result<Arc<dyn PhysicalExpr>, DataFusionError> {
     let df_schema = Arc::clone(&schema).to_dfschema_ref()?;
 
     let props = ExecutionProps::new();
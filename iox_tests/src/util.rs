@@ -91,6 +91,7 @@ impl TestCatalog {
                     Arc::clone(parquet_store.object_store()),
                 )]),
                 mem_pool_size: 1024 * 1024 * 1024,
+                spill_dir: None,
             },
             exec,
         ));
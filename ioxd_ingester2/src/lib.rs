@@ -167,10 +167,13 @@ pub async fn create_ingester_server_type(
         PERSIST_BACKGROUND_FETCH_TIME,
         ingester_config.wal_directory.clone(),
         Duration::from_secs(ingester_config.wal_rotation_period_seconds),
+        ingester_config.wal_replay_skip_corrupt_tail,
         exec,
         ingester_config.persist_max_parallelism,
         ingester_config.persist_queue_depth,
         ingester_config.persist_hot_partition_cost,
+        ingester_config.buffer_memory_high_watermark_bytes,
+        ingester_config.buffer_memory_hard_limit_bytes,
         object_store,
         shutdown_rx.map(|v| v.expect("shutdown sender dropped without calling shutdown")),
     )
@@ -0,0 +1,29 @@
+//! Ingester discovery sources that depend on the querier CLI config.
+use std::path::PathBuf;
+
+use async_trait::async_trait;
+use clap_blocks::querier::read_shard_to_ingesters_file;
+use querier::{DiscoveryError, IngesterDiscovery, IngesterTopology};
+
+/// Discovers ingesters by re-reading a `--shard-to-ingesters-file`.
+#[derive(Debug)]
+pub(crate) struct FileIngesterDiscovery {
+    file: PathBuf,
+}
+
+impl FileIngesterDiscovery {
+    pub(crate) fn new(file: PathBuf) -> Self {
+        Self { file }
+    }
+}
+
+#[async_trait]
+impl IngesterDiscovery for FileIngesterDiscovery {
+    async fn discover(&self) -> Result<IngesterTopology, DiscoveryError> {
+        let file = self.file.clone();
+        let map =
+            tokio::task::spawn_blocking(move || read_shard_to_ingesters_file(&file)).await??;
+
+        Ok(IngesterTopology::by_shard(map))
+    }
+}
@@ -0,0 +1,489 @@
+//! Prometheus compatible HTTP query API.
+//!
+//! Serves PromQL instant queries on `/api/v1/query` and range queries on
+//! `/api/v1/query_range`, taking the parameters and returning the JSON
+//! responses of the [Prometheus HTTP API], so the querier can be used as a
+//! Prometheus data source by Grafana. The namespace is selected by the `org`
+//! and `bucket` parameters, like the Prometheus remote write endpoint of the
+//! router.
+//!
+//! [Prometheus HTTP API]: https://prometheus.io/docs/prometheus/latest/querying/api/
+use std::{collections::BTreeMap, sync::Arc};
+
+use arrow::{
+    array::{Array, Float64Array, StringArray, TimestampNanosecondArray},
+    compute,
+    datatypes::DataType,
+    record_batch::RecordBatch,
+};
+use data_types::{org_and_bucket_to_namespace, OrgBucketMappingError};
+use futures::future::{AbortHandle, Abortable};
+use hyper::{body::HttpBody, header::CONTENT_TYPE, Body, Method, Request, Response, StatusCode};
+use iox_query::{
+    exec::ExecutionContextProvider,
+    plan::promql::{parser::parse_duration, EvalRange, VALUE_COLUMN_NAME},
+    QueryNamespace,
+};
+use iox_time::{Time, TimeProvider};
+use observability_deps::tracing::debug;
+use schema::TIME_COLUMN_NAME;
+use serde_json::{json, Value};
+use service_common::{planner::Planner, QueryNamespaceProvider};
+use thiserror::Error;
+
+/// The maximum number of evaluation times of a range query, as in Prometheus.
+const MAX_POINTS: i64 = 11_000;
+
+/// The maximum size of a request body.
+const MAX_REQUEST_BYTES: usize = 1024 * 1024;
+
+const NANOS_PER_SECOND: f64 = 1_000_000_000.0;
+
+/// Errors returned by the Prometheus query API.
+#[derive(Debug, Error)]
+enum Error {
+    #[error("invalid parameters: {0}")]
+    InvalidParameters(#[from] serde::de::value::Error),
+
+    #[error("missing parameter {0:?}")]
+    MissingParameter(&'static str),
+
+    #[error("invalid parameter {name:?}: {reason}")]
+    InvalidParameter { name: &'static str, reason: String },
+
+    #[error(transparent)]
+    InvalidOrgBucket(#[from] OrgBucketMappingError),
+
+    #[error("namespace {0} not found")]
+    NamespaceNotFound(String),
+
+    #[error("client disconnected")]
+    ClientHangup(hyper::Error),
+
+    #[error("max request size ({0} bytes) exceeded")]
+    RequestSizeExceeded(usize),
+
+    #[error("{0}")]
+    Planning(service_common::planner::Error),
+
+    #[error("{0}")]
+    Execution(service_common::planner::Error),
+
+    #[error("query cancelled")]
+    Cancelled,
+}
+
+impl Error {
+    /// The Prometheus error type and HTTP status code of the error.
+    fn error_type(&self) -> (&'static str, StatusCode) {
+        match self {
+            Self::NamespaceNotFound(_) => ("not_found", StatusCode::NOT_FOUND),
+            Self::Execution(_) => ("execution", StatusCode::UNPROCESSABLE_ENTITY),
+            Self::Cancelled => ("canceled", StatusCode::SERVICE_UNAVAILABLE),
+            _ => ("bad_data", StatusCode::BAD_REQUEST),
+        }
+    }
+}
+
+/// The type of a query.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum QueryType {
+    /// Evaluated at a single time, returning a vector.
+    Instant,
+    /// Evaluated at every step of a range, returning a matrix.
+    Range,
+}
+
+/// Serves the Prometheus query API of a [`QueryNamespaceProvider`].
+#[derive(Debug)]
+pub(crate) struct PromQLHttpApi<S> {
+    server: Arc<S>,
+    time_provider: Arc<dyn TimeProvider>,
+}
+
+impl<S: QueryNamespaceProvider> PromQLHttpApi<S> {
+    pub(crate) fn new(server: Arc<S>, time_provider: Arc<dyn TimeProvider>) -> Self {
+        Self {
+            server,
+            time_provider,
+        }
+    }
+
+    /// Serve `req`, returning `None` if it is not a request to the API.
+    pub(crate) async fn route_request(&self, req: Request<Body>) -> Option<Response<Body>> {
+        let query_type = match (req.method(), req.uri().path()) {
+            (&Method::GET | &Method::POST, "/api/v1/query") => QueryType::Instant,
+            (&Method::GET | &Method::POST, "/api/v1/query_range") => QueryType::Range,
+            _ => return None,
+        };
+
+        let response = match self.query(query_type, req).await {
+            Ok(data) => json!({"status": "success", "data": data}),
+            Err(e) => {
+                debug!(%e, "PromQL query failed");
+                let (error_type, status) = e.error_type();
+                return Some(json_response(
+                    status,
+                    json!({"status": "error", "errorType": error_type, "error": e.to_string()}),
+                ));
+            }
+        };
+        Some(json_response(StatusCode::OK, response))
+    }
+
+    async fn query(&self, query_type: QueryType, req: Request<Body>) -> Result<Value, Error> {
+        let params = Params::from_request(req).await?;
+
+        let namespace = org_and_bucket_to_namespace(
+            params.get("org").unwrap_or_default(),
+            params.get("bucket").unwrap_or_default(),
+        )?;
+        let query = params
+            .get("query")
+            .ok_or(Error::MissingParameter("query"))?
+            .to_string();
+        let eval = match query_type {
+            QueryType::Instant => {
+                let time = match params.get("time") {
+                    Some(time) => parse_time("time", time)?,
+                    None => self.time_provider.now().timestamp_nanos(),
+                };
+                EvalRange::instant(time)
+            }
+            QueryType::Range => range_params(&params)?,
+        };
+
+        let db = self
+            .server
+            .db(&namespace, None)
+            .await
+            .ok_or_else(|| Error::NamespaceNotFound(namespace.to_string()))?;
+        let _permit = self.server.acquire_semaphore(None).await;
+
+        let ctx = db.new_query_context(None);
+        let mut token = db.record_query(&ctx, "promql", Box::new(query.clone()));
+
+        // queries that can not be cancelled get a registration that is never aborted
+        let abort_registration = token
+            .take_abort_registration()
+            .unwrap_or_else(|| AbortHandle::new_pair().1);
+        let batches = Abortable::new(
+            async {
+                let plan = Planner::new(&ctx)
+                    .promql(Arc::clone(&db) as _, query, eval)
+                    .await
+                    .map_err(Error::Planning)?;
+                token.set_planned();
+
+                ctx.collect(plan).await.map_err(Error::Execution)
+            },
+            abort_registration,
+        )
+        .await
+        .map_err(|_| Error::Cancelled)??;
+        token.set_success();
+
+        Ok(format_result(query_type, &batches))
+    }
+}
+
+/// The parameters of a request, from the URL query and the form encoded
+/// body of POST requests.
+#[derive(Debug, Default)]
+struct Params(Vec<(String, String)>);
+
+impl Params {
+    async fn from_request(req: Request<Body>) -> Result<Self, Error> {
+        let mut params: Vec<(String, String)> = match req.uri().query() {
+            Some(query) => serde_urlencoded::from_str(query)?,
+            None => vec![],
+        };
+
+        if req.method() == Method::POST {
+            let mut body = req.into_body();
+            let mut bytes = vec![];
+            while let Some(chunk) = body.data().await {
+                let chunk = chunk.map_err(Error::ClientHangup)?;
+                if bytes.len() + chunk.len() > MAX_REQUEST_BYTES {
+                    return Err(Error::RequestSizeExceeded(MAX_REQUEST_BYTES));
+                }
+                bytes.extend_from_slice(&chunk);
+            }
+            // the body takes precedence over the URL query
+            let mut form: Vec<(String, String)> = serde_urlencoded::from_bytes(&bytes)?;
+            form.append(&mut params);
+            params = form;
+        }
+
+        Ok(Self(params))
+    }
+
+    fn get(&self, name: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// Parse the parameters of a range query.
+fn range_params(params: &Params) -> Result<EvalRange, Error> {
+    let start = parse_time(
+        "start",
+        params
+            .get("start")
+            .ok_or(Error::MissingParameter("start"))?,
+    )?;
+    let end = parse_time(
+        "end",
+        params.get("end").ok_or(Error::MissingParameter("end"))?,
+    )?;
+    let step = parse_step(params.get("step").ok_or(Error::MissingParameter("step"))?)?;
+
+    if end < start {
+        return Err(Error::InvalidParameter {
+            name: "end",
+            reason: "end timestamp must not be before start time".to_string(),
+        });
+    }
+
+    let eval = EvalRange { start, end, step };
+    if eval.steps() > MAX_POINTS {
+        return Err(Error::InvalidParameter {
+            name: "step",
+            reason: format!(
+                "exceeded maximum resolution of {} points per timeseries, try decreasing the query resolution",
+                MAX_POINTS
+            ),
+        });
+    }
+    Ok(eval)
+}
+
+/// Parse a timestamp in seconds since the epoch or in RFC 3339 format to
+/// nanoseconds since the epoch.
+fn parse_time(name: &'static str, value: &str) -> Result<i64, Error> {
+    if let Ok(seconds) = value.parse::<f64>() {
+        if seconds.is_finite() && seconds.abs() < i64::MAX as f64 / NANOS_PER_SECOND {
+            return Ok((seconds * NANOS_PER_SECOND).round() as i64);
+        }
+    }
+
+    Time::from_rfc3339(value)
+        .map(|t| t.timestamp_nanos())
+        .map_err(|_| Error::InvalidParameter {
+            name,
+            reason: format!("cannot parse {:?} to a valid timestamp", value),
+        })
+}
+
+/// Parse a step in seconds or as a PromQL duration to nanoseconds.
+fn parse_step(value: &str) -> Result<i64, Error> {
+    let step = match value.parse::<f64>() {
+        Ok(seconds) if seconds.is_finite() => Some((seconds * NANOS_PER_SECOND).round() as i64),
+        Ok(_) => None,
+        Err(_) => parse_duration(value).ok().map(|d| d.as_nanos() as i64),
+    };
+
+    match step {
+        Some(step) if step > 0 => Ok(step),
+        Some(_) => Err(Error::InvalidParameter {
+            name: "step",
+            reason: "zero or negative query resolution step widths are not accepted".to_string(),
+        }),
+        None => Err(Error::InvalidParameter {
+            name: "step",
+            reason: format!("cannot parse {:?} to a valid duration", value),
+        }),
+    }
+}
+
+/// A sample, with the time in seconds and the value formatted as a string.
+type Sample = (f64, String);
+
+/// Format the result of a query, with a column per label, the evaluation
+/// time in `time` and the sample value in `value`, as a vector or matrix.
+fn format_result(query_type: QueryType, batches: &[RecordBatch]) -> Value {
+    let mut series: BTreeMap<BTreeMap<String, String>, Vec<Sample>> = BTreeMap::new();
+
+    for batch in batches {
+        let batch_schema = batch.schema();
+        let (mut labels, mut times, mut values) = (vec![], None, None);
+        for (field, column) in batch_schema.fields().iter().zip(batch.columns()) {
+            match field.name().as_str() {
+                TIME_COLUMN_NAME => {
+                    times = column.as_any().downcast_ref::<TimestampNanosecondArray>()
+                }
+                VALUE_COLUMN_NAME => values = column.as_any().downcast_ref::<Float64Array>(),
+                name => {
+                    let column = compute::cast(column, &DataType::Utf8)
+                        .expect("labels can be cast to strings");
+                    labels.push((name, column));
+                }
+            }
+        }
+        let (times, values) = match (times, values) {
+            (Some(times), Some(values)) => (times, values),
+            _ => continue,
+        };
+
+        for row in 0..batch.num_rows() {
+            let metric = labels
+                .iter()
+                .filter_map(|(name, column)| {
+                    let column = column.as_any().downcast_ref::<StringArray>()?;
+                    // missing labels are omitted
+                    (column.is_valid(row) && !column.value(row).is_empty())
+                        .then(|| (name.to_string(), column.value(row).to_string()))
+                })
+                .collect();
+            series.entry(metric).or_default().push((
+                times.value(row) as f64 / NANOS_PER_SECOND,
+                format_value(values.value(row)),
+            ));
+        }
+    }
+
+    let result = series
+        .into_iter()
+        .map(|(metric, samples)| match query_type {
+            QueryType::Instant => {
+                let (t, v) = samples.into_iter().last().expect("series have samples");
+                json!({"metric": metric, "value": [t, v]})
+            }
+            QueryType::Range => {
+                let values = samples
+                    .into_iter()
+                    .map(|(t, v)| json!([t, v]))
+                    .collect::<Vec<_>>();
+                json!({"metric": metric, "values": values})
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let result_type = match query_type {
+        QueryType::Instant => "vector",
+        QueryType::Range => "matrix",
+    };
+    json!({"resultType": result_type, "result": result})
+}
+
+/// Format a sample value like Prometheus.
+fn format_value(value: f64) -> String {
+    if value.is_nan() {
+        "NaN".to_string()
+    } else if value == f64::INFINITY {
+        "+Inf".to_string()
+    } else if value == f64::NEG_INFINITY {
+        "-Inf".to_string()
+    } else {
+        value.to_string()
+    }
+}
+
+fn json_response(status: StatusCode, body: Value) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header(CONTENT_TYPE, "application/json")
+        .body(Body::from(body.to_string()))
+        .expect("valid response")
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow::array::{ArrayRef, DictionaryArray};
+    use arrow::datatypes::Int32Type;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_time() {
+        assert_eq!(parse_time("time", "1.5").unwrap(), 1_500_000_000);
+        assert_eq!(
+            parse_time("time", "1970-01-01T00:00:02Z").unwrap(),
+            2_000_000_000
+        );
+        assert_eq!(
+            parse_time("time", "yesterday").unwrap_err().to_string(),
+            "invalid parameter \"time\": cannot parse \"yesterday\" to a valid timestamp"
+        );
+    }
+
+    #[test]
+    fn test_parse_step() {
+        assert_eq!(parse_step("15").unwrap(), 15_000_000_000);
+        assert_eq!(parse_step("0.5").unwrap(), 500_000_000);
+        assert_eq!(parse_step("1m").unwrap(), 60_000_000_000);
+        assert!(parse_step("0").is_err());
+        assert!(parse_step("-1").is_err());
+        assert!(parse_step("fast").is_err());
+    }
+
+    #[test]
+    fn test_range_params() {
+        let params = |start: &str, end: &str, step: &str| {
+            Params(vec![
+                ("start".to_string(), start.to_string()),
+                ("end".to_string(), end.to_string()),
+                ("step".to_string(), step.to_string()),
+            ])
+        };
+
+        assert_eq!(
+            range_params(&params("0", "60", "15s")).unwrap(),
+            EvalRange {
+                start: 0,
+                end: 60_000_000_000,
+                step: 15_000_000_000
+            }
+        );
+        assert!(range_params(&params("60", "0", "15s")).is_err());
+        assert!(range_params(&params("0", "86400", "1")).is_err());
+        assert!(range_params(&Params::default()).is_err());
+    }
+
+    #[test]
+    fn test_format_result() {
+        let job: DictionaryArray<Int32Type> =
+            vec![Some("api"), Some("api"), None].into_iter().collect();
+        let batch = RecordBatch::try_from_iter(vec![
+            ("job", Arc::new(job) as ArrayRef),
+            (
+                "time",
+                Arc::new(TimestampNanosecondArray::from(vec![
+                    1_000_000_000,
+                    1_500_000_000,
+                    1_000_000_000,
+                ])),
+            ),
+            (
+                "value",
+                Arc::new(Float64Array::from(vec![1.0, f64::INFINITY, 0.25])),
+            ),
+        ])
+        .unwrap();
+
+        assert_eq!(
+            format_result(QueryType::Range, &[batch.clone()]),
+            json!({
+                "resultType": "matrix",
+                "result": [
+                    {"metric": {}, "values": [[1.0, "0.25"]]},
+                    {"metric": {"job": "api"}, "values": [[1.0, "1"], [1.5, "+Inf"]]},
+                ],
+            })
+        );
+        assert_eq!(
+            format_result(QueryType::Instant, &[batch]),
+            json!({
+                "resultType": "vector",
+                "result": [
+                    {"metric": {}, "value": [1.0, "0.25"]},
+                    {"metric": {"job": "api"}, "value": [1.5, "+Inf"]},
+                ],
+            })
+        );
+        assert_eq!(format_value(f64::NAN), "NaN");
+        assert_eq!(format_value(-2.5), "-2.5");
+    }
+}
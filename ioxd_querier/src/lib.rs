@@ -15,8 +15,9 @@ use ioxd_common::{
 use metric::Registry;
 use object_store::DynObjectStore;
 use querier::{
-    create_ingester_connections, QuerierCatalogCache, QuerierDatabase, QuerierHandler,
-    QuerierHandlerImpl, QuerierServer,
+    create_ingester_connections, create_ingester_connections_with_discovery, DnsIngesterDiscovery,
+    DownsamplingScheduler, IngesterTopology, ParquetDiskCache, QuerierCatalogCache,
+    QuerierDatabase, QuerierHandler, QuerierHandlerImpl, QuerierServer, RouterDownsamplingSink,
 };
 use std::{
     fmt::{Debug, Display},
@@ -27,11 +28,16 @@ use tokio::runtime::Handle;
 use tokio_util::sync::CancellationToken;
 use trace::TraceCollector;
 
+use crate::{discovery::FileIngesterDiscovery, http::PromQLHttpApi};
+
+mod discovery;
+mod http;
 mod rpc;
 
 pub struct QuerierServerType<C: QuerierHandler> {
     database: Arc<QuerierDatabase>,
     server: QuerierServer<C>,
+    http: PromQLHttpApi<QuerierDatabase>,
     trace_collector: Option<Arc<dyn TraceCollector>>,
 }
 
@@ -45,10 +51,12 @@ impl<C: QuerierHandler> QuerierServerType<C> {
     pub fn new(
         server: QuerierServer<C>,
         database: Arc<QuerierDatabase>,
+        time_provider: Arc<dyn TimeProvider>,
         common_state: &CommonServerState,
     ) -> Self {
         Self {
             server,
+            http: PromQLHttpApi::new(Arc::clone(&database), time_provider),
             database,
             trace_collector: common_state.trace_collector(),
         }
@@ -67,12 +75,15 @@ impl<C: QuerierHandler + std::fmt::Debug + 'static> ServerType for QuerierServer
         self.trace_collector.as_ref().map(Arc::clone)
     }
 
-    /// Just return "not found".
+    /// Serve the Prometheus query API, and return "not found" for anything else.
     async fn route_http_request(
         &self,
-        _req: Request<Body>,
+        req: Request<Body>,
     ) -> Result<Response<Body>, Box<dyn HttpApiErrorSource>> {
-        Err(Box::new(IoxHttpError::NotFound))
+        self.http
+            .route_request(req)
+            .await
+            .ok_or_else(|| Box::new(IoxHttpError::NotFound) as _)
     }
 
     /// Configure the gRPC services.
@@ -113,7 +124,7 @@ impl<C: QuerierHandler + std::fmt::Debug + 'static> ServerType for QuerierServer
     }
 }
 
-/// Simple error struct, we're not really providing an HTTP interface for the compactor.
+/// Simple error struct for requests outside of the Prometheus query API.
 #[derive(Debug)]
 pub enum IoxHttpError {
     NotFound,
@@ -159,19 +170,37 @@ pub struct QuerierServerTypeArgs<'a> {
 pub enum Error {
     #[error("querier error: {0}")]
     Querier(#[from] querier::QuerierDatabaseError),
+
+    #[error("cannot set up parquet disk cache: {0}")]
+    ParquetDiskCache(std::io::Error),
 }
 
 /// Instantiate a querier server
 pub async fn create_querier_server_type(
     args: QuerierServerTypeArgs<'_>,
 ) -> Result<Arc<dyn ServerType>, Error> {
+    let parquet_disk_cache = args
+        .querier_config
+        .parquet_disk_cache_dir()
+        .map(|dir| {
+            ParquetDiskCache::new(
+                dir,
+                args.querier_config.parquet_disk_cache_bytes(),
+                &args.metric_registry,
+            )
+        })
+        .transpose()
+        .map_err(Error::ParquetDiskCache)?;
+
     let catalog_cache = Arc::new(QuerierCatalogCache::new(
         Arc::clone(&args.catalog),
-        args.time_provider,
+        Arc::clone(&args.time_provider),
         Arc::clone(&args.metric_registry),
         Arc::clone(&args.object_store),
         args.querier_config.ram_pool_metadata_bytes(),
         args.querier_config.ram_pool_data_bytes(),
+        parquet_disk_cache,
+        args.querier_config.namespace_cache_max_refresh_interval,
         &Handle::current(),
     ));
 
@@ -198,12 +227,21 @@ pub async fn create_querier_server_type(
                     either unset `INFLUXDB_IOX_RPC_MODE` or specify `--ingester-addresses` instead"
                 );
             }
-            Some(create_ingester_connections(
-                Some(map),
-                None,
-                Arc::clone(&catalog_cache),
-                args.querier_config.ingester_circuit_breaker_threshold,
-            ))
+            match &args.querier_config.shard_to_ingesters_file {
+                Some(file) => Some(create_ingester_connections_with_discovery(
+                    Arc::new(FileIngesterDiscovery::new(file.clone())),
+                    IngesterTopology::by_shard(map),
+                    args.querier_config.ingester_discovery_interval(),
+                    Arc::clone(&catalog_cache),
+                    args.querier_config.ingester_circuit_breaker_threshold,
+                )),
+                None => Some(create_ingester_connections(
+                    Some(map),
+                    None,
+                    Arc::clone(&catalog_cache),
+                    args.querier_config.ingester_circuit_breaker_threshold,
+                )),
+            }
         }
         IngesterAddresses::List(list) => {
             if !args.rpc_write {
@@ -219,6 +257,21 @@ pub async fn create_querier_server_type(
                 args.querier_config.ingester_circuit_breaker_threshold,
             ))
         }
+        IngesterAddresses::Dns(name) => {
+            if !args.rpc_write {
+                panic!(
+                    "`INFLUXDB_IOX_RPC_MODE` is unset but an ingester DNS name was provided; \
+                    either set `INFLUXDB_IOX_RPC_MODE` or specify shard to ingester mappings instead"
+                );
+            }
+            Some(create_ingester_connections_with_discovery(
+                Arc::new(DnsIngesterDiscovery::new(name)),
+                IngesterTopology::default(),
+                args.querier_config.ingester_discovery_interval(),
+                Arc::clone(&catalog_cache),
+                args.querier_config.ingester_circuit_breaker_threshold,
+            ))
+        }
     };
 
     let database = Arc::new(
@@ -230,18 +283,33 @@ pub async fn create_querier_server_type(
             args.querier_config.max_concurrent_queries(),
             args.rpc_write,
         )
-        .await?,
+        .await?
+        .with_last_value_cache_tables(args.querier_config.last_value_cache_tables.clone())
+        .with_query_result_cache(args.querier_config.query_result_cache_bytes()),
     );
-    let querier_handler = Arc::new(QuerierHandlerImpl::new(
-        args.catalog,
+    let time_provider = Arc::clone(&args.time_provider);
+    let mut querier_handler = QuerierHandlerImpl::new(
+        Arc::clone(&args.catalog),
         Arc::clone(&database),
         Arc::clone(&args.object_store),
-    ));
+    );
+    if let Some(router_address) = &args.querier_config.downsampling_router_address {
+        querier_handler = querier_handler.with_downsampling(DownsamplingScheduler::new(
+            args.catalog,
+            Arc::clone(&database),
+            Arc::new(RouterDownsamplingSink::new(router_address.as_str())),
+            args.time_provider,
+            args.querier_config.downsampling_interval,
+            &args.metric_registry,
+        ));
+    }
+    let querier_handler = Arc::new(querier_handler);
 
     let querier = QuerierServer::new(args.metric_registry, querier_handler);
     Ok(Arc::new(QuerierServerType::new(
         querier,
         database,
+        time_provider,
         args.common_state,
     )))
 }
@@ -21,14 +21,19 @@ use router::{
         DmlHandler, DmlHandlerChainExt, FanOutAdaptor, InstrumentationDecorator, Partitioner,
         RetentionValidator, RpcWrite, SchemaValidator, ShardedWriteBuffer, WriteSummaryAdapter,
     },
+    monitoring::SelfMonitor,
     namespace_cache::{
-        metrics::InstrumentedCache, MemoryNamespaceCache, NamespaceCache, ShardedCache,
+        metrics::InstrumentedCache, refresh::NamespaceCacheRefresher, LruCache,
+        MemoryNamespaceCache, NamespaceCache, ShardedCache,
     },
     namespace_resolver::{
         MissingNamespaceAction, NamespaceAutocreation, NamespaceResolver, NamespaceSchemaResolver,
     },
     server::{
-        grpc::{sharder::ShardService, GrpcDelegate, RpcWriteGrpcDelegate},
+        grpc::{
+            downsampling::DownsamplingWriteService, otlp::OtlpMetricsService,
+            sharder::ShardService, GrpcDelegate, RpcWriteGrpcDelegate,
+        },
         http::HttpDelegate,
         RouterServer, RpcWriteRouterServer,
     },
@@ -39,6 +44,7 @@ use std::{
     collections::BTreeSet,
     fmt::{Debug, Display},
     sync::Arc,
+    time::Duration,
 };
 use thiserror::Error;
 use tokio_util::sync::CancellationToken;
@@ -64,18 +70,21 @@ pub enum Error {
 
     #[error("Failed to init shard grpc service: {0}")]
     ShardServiceInit(iox_catalog::interface::Error),
+
+    #[error("Invalid self-monitoring namespace: {0}")]
+    SelfMonitoringNamespace(data_types::NamespaceNameError),
 }
 
 pub type Result<T, E = Error> = std::result::Result<T, E>;
 
-pub struct RouterServerType<D, N, S> {
-    server: RouterServer<D, N, S>,
+pub struct RouterServerType<D, N, S, C> {
+    server: RouterServer<D, N, S, C>,
     shutdown: CancellationToken,
     trace_collector: Option<Arc<dyn TraceCollector>>,
 }
 
-impl<D, N, S> RouterServerType<D, N, S> {
-    pub fn new(server: RouterServer<D, N, S>, common_state: &CommonServerState) -> Self {
+impl<D, N, S, C> RouterServerType<D, N, S, C> {
+    pub fn new(server: RouterServer<D, N, S, C>, common_state: &CommonServerState) -> Self {
         Self {
             server,
             shutdown: CancellationToken::new(),
@@ -84,18 +93,21 @@ impl<D, N, S> RouterServerType<D, N, S> {
     }
 }
 
-impl<D, N, S> std::fmt::Debug for RouterServerType<D, N, S> {
+impl<D, N, S, C> std::fmt::Debug for RouterServerType<D, N, S, C> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "Router")
     }
 }
 
 #[async_trait]
-impl<D, N, S> ServerType for RouterServerType<D, N, S>
+impl<D, N, S, C> ServerType for RouterServerType<D, N, S, C>
 where
-    D: DmlHandler<WriteInput = HashMap<String, MutableBatch>, WriteOutput = WriteSummary> + 'static,
+    D: DmlHandler<WriteInput = HashMap<String, MutableBatch>, WriteOutput = WriteSummary>
+        + Clone
+        + 'static,
     S: Sharder<(), Item = Arc<Shard>> + Clone + 'static,
-    N: NamespaceResolver + 'static,
+    N: NamespaceResolver + Clone + 'static,
+    C: NamespaceCache + Clone + 'static,
 {
     /// Return the [`metric::Registry`] used by the router.
     fn metric_registry(&self) -> Arc<Registry> {
@@ -132,6 +144,10 @@ where
         add_service!(builder, self.server.grpc().object_store_service());
         add_service!(builder, self.server.grpc().shard_service());
         add_service!(builder, self.server.grpc().namespace_service());
+        add_service!(builder, self.server.grpc().table_service());
+        add_service!(builder, self.server.grpc().downsampling_service());
+        add_service!(builder, self.server.otlp().service());
+        add_service!(builder, self.server.downsampling_write().service());
         serve_builder!(builder);
 
         Ok(())
@@ -147,14 +163,14 @@ where
     }
 }
 
-pub struct RpcWriteRouterServerType<D, N> {
-    server: RpcWriteRouterServer<D, N>,
+pub struct RpcWriteRouterServerType<D, N, C> {
+    server: RpcWriteRouterServer<D, N, C>,
     shutdown: CancellationToken,
     trace_collector: Option<Arc<dyn TraceCollector>>,
 }
 
-impl<D, N> RpcWriteRouterServerType<D, N> {
-    pub fn new(server: RpcWriteRouterServer<D, N>, common_state: &CommonServerState) -> Self {
+impl<D, N, C> RpcWriteRouterServerType<D, N, C> {
+    pub fn new(server: RpcWriteRouterServer<D, N, C>, common_state: &CommonServerState) -> Self {
         Self {
             server,
             shutdown: CancellationToken::new(),
@@ -163,17 +179,20 @@ impl<D, N> RpcWriteRouterServerType<D, N> {
     }
 }
 
-impl<D, N> std::fmt::Debug for RpcWriteRouterServerType<D, N> {
+impl<D, N, C> std::fmt::Debug for RpcWriteRouterServerType<D, N, C> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "RpcWriteRouter")
     }
 }
 
 #[async_trait]
-impl<D, N> ServerType for RpcWriteRouterServerType<D, N>
+impl<D, N, C> ServerType for RpcWriteRouterServerType<D, N, C>
 where
-    D: DmlHandler<WriteInput = HashMap<String, MutableBatch>, WriteOutput = WriteSummary> + 'static,
-    N: NamespaceResolver + 'static,
+    D: DmlHandler<WriteInput = HashMap<String, MutableBatch>, WriteOutput = WriteSummary>
+        + Clone
+        + 'static,
+    N: NamespaceResolver + Clone + 'static,
+    C: NamespaceCache + Clone + 'static,
 {
     /// Return the [`metric::Registry`] used by the router.
     fn metric_registry(&self) -> Arc<Registry> {
@@ -208,6 +227,10 @@ where
         add_service!(builder, self.server.grpc().schema_service());
         add_service!(builder, self.server.grpc().catalog_service());
         add_service!(builder, self.server.grpc().object_store_service());
+        add_service!(builder, self.server.grpc().table_service());
+        add_service!(builder, self.server.grpc().downsampling_service());
+        add_service!(builder, self.server.otlp().service());
+        add_service!(builder, self.server.downsampling_write().service());
         serve_builder!(builder);
 
         Ok(())
@@ -282,11 +305,15 @@ pub async fn create_router2_server_type(
     // a. Namespace cache
     // Initialise an instrumented namespace cache to be shared with the schema
     // validator, and namespace auto-creator that reports cache hit/miss/update
-    // metrics.
-    let ns_cache = Arc::new(InstrumentedCache::new(
-        Arc::new(ShardedCache::new(
-            std::iter::repeat_with(|| Arc::new(MemoryNamespaceCache::default())).take(10),
+    // metrics, bounded in size by evicting the least recently used namespaces.
+    let ns_cache = Arc::new(LruCache::new(
+        Arc::new(InstrumentedCache::new(
+            Arc::new(ShardedCache::new(
+                std::iter::repeat_with(|| Arc::new(MemoryNamespaceCache::default())).take(10),
+            )),
+            &metrics,
         )),
+        router_config.namespace_cache_max_entries,
         &metrics,
     ));
 
@@ -294,6 +321,11 @@ pub async fn create_router2_server_type(
         .await
         .expect("namespace cache pre-warming failed");
 
+    // Periodically reconcile the cached namespaces with the catalog, to observe
+    // namespace changes made through other routers.
+    let ns_cache_refresher =
+        NamespaceCacheRefresher::new(Arc::clone(&ns_cache), Arc::clone(&catalog), &metrics);
+
     // b. Schema validator
     // Initialise and instrument the schema validator
     let schema_validator =
@@ -403,7 +435,26 @@ pub async fn create_router2_server_type(
 
     // 3. N/A: Shard mapping setup is only relevant to the write buffer router path
 
-    // 4. START: Initialize the HTTP API delegate, this is the same in both router paths
+    // 4. START: Initialize the HTTP API delegate, the OTLP metrics and downsampling write
+    //    services and the self-monitoring reporter writing through the same handler stack,
+    //    this is the same in both router paths
+    let namespace_resolver = Arc::new(namespace_resolver);
+    let handler_stack = Arc::new(handler_stack);
+    let self_monitor = init_self_monitor(
+        router_config.self_monitoring_enabled,
+        &router_config.self_monitoring_namespace,
+        Arc::clone(&metrics),
+        Arc::clone(&namespace_resolver),
+        Arc::clone(&handler_stack),
+    )?;
+
+    let otlp = OtlpMetricsService::new(
+        Arc::clone(&namespace_resolver),
+        Arc::clone(&handler_stack),
+        &metrics,
+    );
+    let downsampling_write =
+        DownsamplingWriteService::new(Arc::clone(&namespace_resolver), Arc::clone(&handler_stack));
     let http = HttpDelegate::new(
         common_state.run_config().max_http_request_size,
         router_config.http_request_limit,
@@ -416,11 +467,29 @@ pub async fn create_router2_server_type(
     // 5. START: Initialize the gRPC API delegate that creates the services relevant to the RPC
     //    write router path and use it to create the relevant `RpcWriteRouterServer` and
     //    `RpcWriteRouterServerType`.
-    let grpc = RpcWriteGrpcDelegate::new(catalog, object_store);
-
-    let router_server =
-        RpcWriteRouterServer::new(http, grpc, metrics, common_state.trace_collector());
+    let grpc = RpcWriteGrpcDelegate::new(catalog, object_store, ns_cache);
+
+    let router_server = RpcWriteRouterServer::new(
+        http,
+        grpc,
+        otlp,
+        downsampling_write,
+        metrics,
+        common_state.trace_collector(),
+    );
     let server_type = Arc::new(RpcWriteRouterServerType::new(router_server, common_state));
+    spawn_namespace_cache_refresher(
+        ns_cache_refresher,
+        Duration::from_secs(router_config.namespace_cache_refresh_interval_seconds),
+        server_type.shutdown.clone(),
+    );
+    if let Some(self_monitor) = self_monitor {
+        spawn_self_monitor(
+            self_monitor,
+            Duration::from_secs(router_config.self_monitoring_interval_seconds),
+            server_type.shutdown.clone(),
+        );
+    }
     Ok(server_type)
     // 5. END
 }
@@ -463,11 +532,15 @@ pub async fn create_router_server_type(
     // a. Namespace cache
     // Initialise an instrumented namespace cache to be shared with the schema
     // validator, and namespace auto-creator that reports cache hit/miss/update
-    // metrics.
-    let ns_cache = Arc::new(InstrumentedCache::new(
-        Arc::new(ShardedCache::new(
-            std::iter::repeat_with(|| Arc::new(MemoryNamespaceCache::default())).take(10),
+    // metrics, bounded in size by evicting the least recently used namespaces.
+    let ns_cache = Arc::new(LruCache::new(
+        Arc::new(InstrumentedCache::new(
+            Arc::new(ShardedCache::new(
+                std::iter::repeat_with(|| Arc::new(MemoryNamespaceCache::default())).take(10),
+            )),
+            &metrics,
         )),
+        router_config.namespace_cache_max_entries,
         &metrics,
     ));
 
@@ -475,6 +548,11 @@ pub async fn create_router_server_type(
         .await
         .expect("namespace cache pre-warming failed");
 
+    // Periodically reconcile the cached namespaces with the catalog, to observe
+    // namespace changes made through other routers.
+    let ns_cache_refresher =
+        NamespaceCacheRefresher::new(Arc::clone(&ns_cache), Arc::clone(&catalog), &metrics);
+
     // b. Schema validator
     // Initialise and instrument the schema validator
     let schema_validator =
@@ -590,7 +668,26 @@ pub async fn create_router_server_type(
         init_shard_service(sharder, write_buffer_config, Arc::clone(&catalog)).await?;
     // 3. END
 
-    // 4. START: Initialize the HTTP API delegate, this is the same in both router paths
+    // 4. START: Initialize the HTTP API delegate, the OTLP metrics and downsampling write
+    //    services and the self-monitoring reporter writing through the same handler stack,
+    //    this is the same in both router paths
+    let namespace_resolver = Arc::new(namespace_resolver);
+    let handler_stack = Arc::new(handler_stack);
+    let self_monitor = init_self_monitor(
+        router_config.self_monitoring_enabled,
+        &router_config.self_monitoring_namespace,
+        Arc::clone(&metrics),
+        Arc::clone(&namespace_resolver),
+        Arc::clone(&handler_stack),
+    )?;
+
+    let otlp = OtlpMetricsService::new(
+        Arc::clone(&namespace_resolver),
+        Arc::clone(&handler_stack),
+        &metrics,
+    );
+    let downsampling_write =
+        DownsamplingWriteService::new(Arc::clone(&namespace_resolver), Arc::clone(&handler_stack));
     let http = HttpDelegate::new(
         common_state.run_config().max_http_request_size,
         router_config.http_request_limit,
@@ -603,10 +700,36 @@ pub async fn create_router_server_type(
     // 5. START: Initialize the gRPC API delegate that creates the services relevant to the write
     //    buffer router path and use it to create the relevant `RouterServer` and
     //    `RouterServerType`.
-    let grpc = GrpcDelegate::new(topic_id, query_id, catalog, object_store, shard_service);
+    let grpc = GrpcDelegate::new(
+        topic_id,
+        query_id,
+        catalog,
+        object_store,
+        shard_service,
+        ns_cache,
+    );
 
-    let router_server = RouterServer::new(http, grpc, metrics, common_state.trace_collector());
+    let router_server = RouterServer::new(
+        http,
+        grpc,
+        otlp,
+        downsampling_write,
+        metrics,
+        common_state.trace_collector(),
+    );
     let server_type = Arc::new(RouterServerType::new(router_server, common_state));
+    spawn_namespace_cache_refresher(
+        ns_cache_refresher,
+        Duration::from_secs(router_config.namespace_cache_refresh_interval_seconds),
+        server_type.shutdown.clone(),
+    );
+    if let Some(self_monitor) = self_monitor {
+        spawn_self_monitor(
+            self_monitor,
+            Duration::from_secs(router_config.self_monitoring_interval_seconds),
+            server_type.shutdown.clone(),
+        );
+    }
     Ok(server_type)
     // 5. END
 }
@@ -684,6 +807,66 @@ where
         .map_err(Error::ShardServiceInit)
 }
 
+/// Refresh the namespace cache every `interval` in a background task, until
+/// `shutdown` is cancelled.
+fn spawn_namespace_cache_refresher<C>(
+    refresher: NamespaceCacheRefresher<C>,
+    interval: Duration,
+    shutdown: CancellationToken,
+) where
+    C: NamespaceCache + 'static,
+{
+    tokio::spawn(async move {
+        tokio::select! {
+            _ = refresher.run(interval) => {},
+            _ = shutdown.cancelled() => {},
+        }
+    });
+}
+
+/// Initialise a [`SelfMonitor`] writing the contents of `metrics` to `namespace` through
+/// `handler_stack`, if `enabled`.
+fn init_self_monitor<D, N>(
+    enabled: bool,
+    namespace: &str,
+    metrics: Arc<metric::Registry>,
+    namespace_resolver: N,
+    handler_stack: D,
+) -> Result<Option<SelfMonitor<D, N>>> {
+    if !enabled {
+        return Ok(None);
+    }
+
+    let namespace =
+        NamespaceName::new(namespace.to_string()).map_err(Error::SelfMonitoringNamespace)?;
+    info!(%namespace, "writing router metrics to self-monitoring namespace");
+
+    Ok(Some(SelfMonitor::new(
+        namespace,
+        metrics,
+        namespace_resolver,
+        handler_stack,
+    )))
+}
+
+/// Write a snapshot of the metrics every `interval` in a background task,
+/// until `shutdown` is cancelled.
+fn spawn_self_monitor<D, N>(
+    self_monitor: SelfMonitor<D, N>,
+    interval: Duration,
+    shutdown: CancellationToken,
+) where
+    D: DmlHandler<WriteInput = HashMap<String, MutableBatch>> + 'static,
+    N: NamespaceResolver + 'static,
+{
+    tokio::spawn(async move {
+        tokio::select! {
+            _ = self_monitor.run(interval) => {},
+            _ = shutdown.cancelled() => {},
+        }
+    });
+}
+
 /// Pre-populate `cache` with the all existing schemas in `catalog`.
 async fn pre_warm_schema_cache<T>(
     cache: &T,
@@ -11,6 +11,9 @@
 use metric::{Attributes, MetricKind, Observation};
 use std::io::Write;
 
+mod line_protocol;
+pub use line_protocol::LineProtocolEncoder;
+
 use observability_deps::tracing::error;
 use prometheus::proto::{Bucket, Histogram};
 use prometheus::{
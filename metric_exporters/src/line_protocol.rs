@@ -0,0 +1,266 @@
+use bytes::BufMut;
+use influxdb_line_protocol::{builder::AfterField, LineProtocolBuilder};
+use metric::{Attributes, HistogramObservation, MetricKind, Observation};
+
+/// A `metric::Reporter` that writes a snapshot of the reported metrics as
+/// [line protocol], allowing them to be written back into IOx.
+///
+/// Each metric becomes a measurement, named as per the [`PrometheusTextEncoder`]
+/// so the same metric has the same name in both, and each set of attributes
+/// becomes a line with the attributes as tags:
+///
+/// * Counters and gauges have a single `value` field, durations in seconds.
+/// * Histograms have `count` and `sum` fields, and a cumulative count field
+///   per bucket named after its upper bound, such as `le_0.5` or `le_+Inf`.
+///
+/// Attributes named `time`, or named like one of the fields of the metric, are
+/// suffixed with `_tag` so that they do not clash with the column of the same
+/// name.
+///
+/// All lines share the timestamp the encoder was created with.
+///
+/// [line protocol]: https://docs.influxdata.com/influxdb/cloud/reference/syntax/line-protocol
+/// [`PrometheusTextEncoder`]: crate::PrometheusTextEncoder
+#[derive(Debug)]
+pub struct LineProtocolEncoder<'a, B: BufMut> {
+    /// The measurement name and kind of the metric in progress.
+    measurement: Option<(String, MetricKind)>,
+
+    timestamp: i64,
+    buf: &'a mut B,
+}
+
+impl<'a, B: BufMut> LineProtocolEncoder<'a, B> {
+    /// Encode metrics into `buf`, with `timestamp` nanoseconds since the epoch.
+    pub fn new(buf: &'a mut B, timestamp: i64) -> Self {
+        Self {
+            measurement: None,
+            timestamp,
+            buf,
+        }
+    }
+}
+
+impl<'a, B: BufMut> metric::Reporter for LineProtocolEncoder<'a, B> {
+    fn start_metric(
+        &mut self,
+        metric_name: &'static str,
+        _description: &'static str,
+        kind: MetricKind,
+    ) {
+        assert!(self.measurement.is_none(), "metric already in progress");
+
+        let measurement = match kind {
+            MetricKind::U64Counter => format!("{}_total", metric_name),
+            MetricKind::U64Gauge | MetricKind::U64Histogram => metric_name.to_string(),
+            MetricKind::DurationCounter => format!("{}_seconds_total", metric_name),
+            MetricKind::DurationGauge | MetricKind::DurationHistogram => {
+                format!("{}_seconds", metric_name)
+            }
+        };
+        self.measurement = Some((measurement, kind));
+    }
+
+    fn report_observation(&mut self, attributes: &Attributes, observation: Observation) {
+        let (measurement, kind) = self.measurement.as_ref().expect("no metric in progress");
+
+        let mut line = LineProtocolBuilder::new_with(&mut *self.buf).measurement(measurement);
+        for (key, value) in attributes.iter() {
+            // Line protocol cannot represent an empty tag value, which is
+            // equivalent to the tag being absent.
+            if value.is_empty() {
+                continue;
+            }
+            line = match is_reserved(*kind, key) {
+                true => line.tag(&format!("{}_tag", key), value),
+                false => line.tag(key, value),
+            };
+        }
+
+        let line = match observation {
+            Observation::U64Counter(v) | Observation::U64Gauge(v) => line.field("value", v),
+            Observation::DurationCounter(v) | Observation::DurationGauge(v) => {
+                line.field("value", v.as_secs_f64())
+            }
+            Observation::U64Histogram(v) => {
+                let HistogramObservation { total, buckets } = v;
+                let line = line.field("sum", total);
+                histogram_fields(
+                    line,
+                    buckets.into_iter().map(|b| {
+                        let le = match b.le {
+                            u64::MAX => "+Inf".to_string(),
+                            v => v.to_string(),
+                        };
+                        (le, b.count)
+                    }),
+                )
+            }
+            Observation::DurationHistogram(v) => {
+                let HistogramObservation { total, buckets } = v;
+                let line = line.field("sum", total.as_secs_f64());
+                histogram_fields(
+                    line,
+                    buckets.into_iter().map(|b| {
+                        let le = match b.le {
+                            metric::DURATION_MAX => "+Inf".to_string(),
+                            v => v.as_secs_f64().to_string(),
+                        };
+                        (le, b.count)
+                    }),
+                )
+            }
+        };
+
+        line.timestamp(self.timestamp).close_line();
+    }
+
+    fn finish_metric(&mut self) {
+        self.measurement = None;
+    }
+}
+
+/// Returns true if `key` cannot be used as the name of a tag of a metric of
+/// `kind`, as it is the name of the time column or of one of its fields.
+fn is_reserved(kind: MetricKind, key: &str) -> bool {
+    if key == "time" {
+        return true;
+    }
+
+    match kind {
+        MetricKind::U64Counter
+        | MetricKind::U64Gauge
+        | MetricKind::DurationCounter
+        | MetricKind::DurationGauge => key == "value",
+        MetricKind::U64Histogram | MetricKind::DurationHistogram => {
+            key == "sum" || key == "count" || key.starts_with("le_")
+        }
+    }
+}
+
+/// Add the cumulative count of each `(upper bound, count)` bucket, and the
+/// overall `count`, to `line`.
+fn histogram_fields<B: BufMut>(
+    mut line: LineProtocolBuilder<B, AfterField>,
+    buckets: impl Iterator<Item = (String, u64)>,
+) -> LineProtocolBuilder<B, AfterField> {
+    let mut cumulative_count = 0;
+    for (le, count) in buckets {
+        cumulative_count += count;
+        line = line.field(&format!("le_{}", le), cumulative_count);
+    }
+    line.field("count", cumulative_count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use metric::{
+        DurationCounter, DurationGauge, DurationHistogram, DurationHistogramOptions, Metric,
+        Registry, U64Counter, U64Gauge, U64Histogram, U64HistogramOptions,
+    };
+    use std::time::Duration;
+
+    #[test]
+    fn test_reserved_attributes() {
+        let registry = Registry::new();
+
+        let counter: Metric<U64Counter> = registry.register_metric("foo", "a counter metric");
+        counter
+            .recorder(&[("value", "a"), ("time", "b"), ("sum", "c")])
+            .inc(1);
+
+        let histogram: Metric<U64Histogram> =
+            registry.register_metric_with_options("bar", "a histogram metric", || {
+                U64HistogramOptions::new([u64::MAX])
+            });
+        histogram
+            .recorder(&[("count", "a"), ("le_5", "b"), ("value", "c")])
+            .record(1);
+
+        let mut buffer = Vec::new();
+        let mut encoder = LineProtocolEncoder::new(&mut buffer, 1234);
+        registry.report(&mut encoder);
+
+        let buffer = String::from_utf8(buffer).unwrap();
+
+        let expected = r#"
+bar,count_tag=a,le_5_tag=b,value=c sum=1u,le_+Inf=1u,count=1u 1234
+foo_total,sum=c,time_tag=b,value_tag=a value=1u 1234
+"#
+        .trim_start();
+
+        assert_eq!(&buffer, expected, "{}", buffer);
+    }
+
+    #[test]
+    fn test_encode() {
+        let registry = Registry::new();
+
+        let counter: Metric<U64Counter> = registry.register_metric("foo", "a counter metric");
+        counter
+            .recorder(&[("tag1", "value"), ("tag2", "a value")])
+            .inc(5);
+        counter.recorder(&[("tag1", "value"), ("tag2", "")]).inc(7);
+
+        let gauge: Metric<U64Gauge> = registry.register_metric("gauge", "a gauge");
+        gauge.recorder(&[]).set(42);
+
+        let histogram: Metric<U64Histogram> =
+            registry.register_metric_with_options("bar", "a histogram metric", || {
+                U64HistogramOptions::new([5, 10, u64::MAX])
+            });
+        let recorder = histogram.recorder(&[("tag1", "value1")]);
+        recorder.record(3);
+        recorder.record(8);
+        recorder.record(40);
+
+        let duration: Metric<DurationHistogram> =
+            registry.register_metric_with_options("latency", "a duration histogram", || {
+                DurationHistogramOptions::new([Duration::from_millis(500), metric::DURATION_MAX])
+            });
+        duration
+            .recorder(&[("tag1", "value1")])
+            .record(Duration::from_millis(100));
+
+        let duration_gauge: Metric<DurationGauge> =
+            registry.register_metric("duration_gauge", "a duration gauge");
+        duration_gauge
+            .recorder(&[("tag1", "value1")])
+            .set(Duration::from_millis(100));
+
+        let duration_counter: Metric<DurationCounter> =
+            registry.register_metric("duration_counter", "a duration counter");
+        duration_counter
+            .recorder(&[("tag1", "value1")])
+            .inc(Duration::from_millis(1200));
+
+        // unused metrics produce no lines
+        let _unused: Metric<DurationHistogram> = registry.register_metric("unused", "unused");
+
+        let mut buffer = Vec::new();
+        let mut encoder = LineProtocolEncoder::new(&mut buffer, 1234);
+        registry.report(&mut encoder);
+
+        let buffer = String::from_utf8(buffer).unwrap();
+
+        let expected = r#"
+bar,tag1=value1 sum=51u,le_5=1u,le_10=2u,le_+Inf=3u,count=3u 1234
+duration_counter_seconds_total,tag1=value1 value=1.2 1234
+duration_gauge_seconds,tag1=value1 value=0.1 1234
+foo_total,tag1=value value=7u 1234
+foo_total,tag1=value,tag2=a\ value value=5u 1234
+gauge value=42u 1234
+latency_seconds,tag1=value1 sum=0.1,le_0.5=1u,le_+Inf=1u,count=1u 1234
+"#
+        .trim_start();
+
+        assert_eq!(&buffer, expected, "{}", buffer);
+
+        // The output is valid line protocol.
+        let lines = influxdb_line_protocol::parse_lines(&buffer)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(lines.len(), 7);
+    }
+}
@@ -0,0 +1,467 @@
+//! Bloom filter based pruning of parquet row groups.
+//!
+//! IOx writes a bloom filter for each tag column of a parquet file (see
+//! [`BloomFilterConfig`]). For equality and `IN` predicates on tag columns,
+//! these filters can prove that a row group (or an entire file) does not
+//! contain any matching row, which min/max statistics cannot do for
+//! high-cardinality tags.
+//!
+//! [`BloomFilterConfig`]: crate::serialize::BloomFilterConfig
+
+use std::{collections::BTreeSet, ops::Range, sync::Arc};
+
+use bytes::{Buf, Bytes};
+use datafusion::physical_plan::{
+    file_format::{FileMeta, ParquetFileReaderFactory},
+    metrics::ExecutionPlanMetricsSet,
+};
+use futures::{future::BoxFuture, FutureExt};
+use object_store::{path::Path, DynObjectStore, ObjectMeta};
+use parquet::{
+    arrow::async_reader::AsyncFileReader,
+    bloom_filter::Sbbf,
+    errors::ParquetError,
+    file::{
+        footer::{decode_footer, decode_metadata},
+        metadata::ParquetMetaData,
+        reader::{ChunkReader, Length},
+    },
+};
+use thiserror::Error;
+
+/// Errors returned by [`prune_row_groups()`].
+#[derive(Debug, Error)]
+pub enum BloomFilterError {
+    /// Decoding the parquet file or its bloom filters failed.
+    #[error("failed to read bloom filters: {0}")]
+    Parquet(#[from] ParquetError),
+
+    /// None of the predicate columns have a bloom filter in any row group of
+    /// the file, for example because it was written before bloom filters were
+    /// enabled.
+    #[error("file contains no bloom filter for the predicate columns")]
+    NoBloomFilter,
+}
+
+/// The set of row groups within a parquet file that need to be scanned.
+///
+/// A [`RowGroupSelection`] can be attached to a [`ParquetExecInput`], causing
+/// all row groups not included in the selection to be skipped during the scan.
+///
+/// [`ParquetExecInput`]: crate::storage::ParquetExecInput
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RowGroupSelection {
+    keep: Arc<[bool]>,
+}
+
+impl RowGroupSelection {
+    /// Create a selection from a per-row-group "keep" mask.
+    pub fn new(keep: impl Into<Arc<[bool]>>) -> Self {
+        Self { keep: keep.into() }
+    }
+
+    /// Returns true if the row group at `idx` must be scanned.
+    pub fn contains(&self, idx: usize) -> bool {
+        self.keep.get(idx).copied().unwrap_or(true)
+    }
+
+    /// Total number of row groups in the file.
+    pub fn num_row_groups(&self) -> usize {
+        self.keep.len()
+    }
+
+    /// Number of row groups that must be scanned.
+    pub fn num_selected(&self) -> usize {
+        self.keep.iter().filter(|keep| **keep).count()
+    }
+
+    /// Returns true if every row group must be scanned.
+    pub fn selects_all(&self) -> bool {
+        self.keep.iter().all(|keep| *keep)
+    }
+
+    /// Returns true if no row group needs to be scanned, and the whole file
+    /// can be skipped.
+    pub fn selects_none(&self) -> bool {
+        !self.keep.iter().any(|keep| *keep)
+    }
+}
+
+/// Evaluate the bloom filters of the parquet file at `location` (of
+/// `file_size` bytes) in `object_store` against `column_values`.
+///
+/// Each `(column, values)` entry in `column_values` states that a matching row
+/// must have a value in `values` for `column`, and all entries must hold for a
+/// row to match (i.e. the entries are AND-ed together).
+///
+/// A row group is excluded from the returned [`RowGroupSelection`] if, for at
+/// least one entry, the bloom filter of the column proves that none of the
+/// values are present in the row group. Columns without a bloom filter never
+/// cause a row group to be excluded.
+///
+/// Only the footer metadata and the bloom filters of the predicate columns are
+/// fetched, each with a ranged GET, rather than the whole file.
+///
+/// # Errors
+///
+/// Returns [`BloomFilterError::NoBloomFilter`] if none of the columns have a
+/// bloom filter in any row group.
+pub async fn prune_row_groups(
+    object_store: &DynObjectStore,
+    location: &Path,
+    file_size: usize,
+    column_values: &[(String, Vec<String>)],
+) -> Result<RowGroupSelection, BloomFilterError> {
+    let (metadata, metadata_start) = fetch_metadata(object_store, location, file_size).await?;
+
+    // Resolve the predicate columns to their leaf column index, ignoring
+    // columns that do not exist in this file.
+    let schema = metadata.file_metadata().schema_descr();
+    let columns: Vec<_> = column_values
+        .iter()
+        .filter_map(|(name, values)| {
+            let idx = schema.columns().iter().position(|c| c.name() == name)?;
+            Some((idx, values))
+        })
+        .collect();
+
+    // The length of a bloom filter is not recorded in the metadata, so each
+    // filter is bounded by the next section of the file known to start after
+    // it.
+    let boundaries = section_offsets(&metadata, metadata_start);
+
+    let mut saw_filter = false;
+    let mut keep = Vec::with_capacity(metadata.num_row_groups());
+    for row_group in metadata.row_groups() {
+        let mut keep_row_group = true;
+        for (column_idx, values) in &columns {
+            let column = row_group.column(*column_idx);
+            let Some(offset) = column.bloom_filter_offset() else {
+                continue;
+            };
+            let offset = offset as usize;
+            let end = boundaries
+                .range(offset + 1..)
+                .next()
+                .copied()
+                .unwrap_or(metadata_start);
+
+            let data = get_range(object_store, location, offset..end).await?;
+            let reader = Arc::new(FileRange { offset, data });
+            let Some(filter) = Sbbf::read_from_column_chunk(column, reader)? else {
+                continue;
+            };
+            saw_filter = true;
+
+            if !values.iter().any(|v| filter.check(&v.as_str())) {
+                keep_row_group = false;
+                break;
+            }
+        }
+        keep.push(keep_row_group);
+    }
+
+    if !saw_filter {
+        return Err(BloomFilterError::NoBloomFilter);
+    }
+
+    Ok(RowGroupSelection::new(keep))
+}
+
+/// Return the start offset of every section of the file described by
+/// `metadata` (column chunk pages, indexes and bloom filters), and of the
+/// footer metadata itself.
+fn section_offsets(metadata: &ParquetMetaData, metadata_start: usize) -> BTreeSet<usize> {
+    metadata
+        .row_groups()
+        .iter()
+        .flat_map(|row_group| row_group.columns())
+        .flat_map(|column| {
+            [
+                Some(column.data_page_offset()),
+                column.dictionary_page_offset(),
+                column.bloom_filter_offset(),
+                column.column_index_offset(),
+                column.offset_index_offset(),
+            ]
+        })
+        .flatten()
+        .map(|offset| offset as usize)
+        .chain(std::iter::once(metadata_start))
+        .collect()
+}
+
+/// A [`ChunkReader`] over the bytes of a file starting at `offset`.
+struct FileRange {
+    offset: usize,
+    data: Bytes,
+}
+
+impl Length for FileRange {
+    fn len(&self) -> u64 {
+        (self.offset + self.data.len()) as u64
+    }
+}
+
+impl ChunkReader for FileRange {
+    type T = bytes::buf::Reader<Bytes>;
+
+    fn get_read(&self, start: u64, length: usize) -> parquet::errors::Result<Self::T> {
+        Ok(self.get_bytes(start, length)?.reader())
+    }
+
+    fn get_bytes(&self, start: u64, length: usize) -> parquet::errors::Result<Bytes> {
+        let start = (start as usize)
+            .checked_sub(self.offset)
+            .filter(|start| *start <= self.data.len())
+            .ok_or_else(|| {
+                ParquetError::General(format!(
+                    "read at offset {start} outside of fetched range starting at {}",
+                    self.offset
+                ))
+            })?;
+        let end = (start + length).min(self.data.len());
+        Ok(self.data.slice(start..end))
+    }
+}
+
+/// Fetch `range` of the object at `location`.
+async fn get_range(
+    object_store: &DynObjectStore,
+    location: &Path,
+    range: Range<usize>,
+) -> parquet::errors::Result<Bytes> {
+    object_store
+        .get_range(location, range)
+        .await
+        .map_err(|e| ParquetError::General(format!("failed to fetch data: {e}")))
+}
+
+/// Fetch and decode the footer metadata of the parquet file at `location`,
+/// returning it along with the offset at which it starts.
+async fn fetch_metadata(
+    object_store: &DynObjectStore,
+    location: &Path,
+    file_size: usize,
+) -> parquet::errors::Result<(ParquetMetaData, usize)> {
+    if file_size < 8 {
+        return Err(ParquetError::EOF(format!(
+            "file size of {file_size} is less than footer"
+        )));
+    }
+
+    let footer = get_range(object_store, location, file_size - 8..file_size).await?;
+    let mut buf = [0_u8; 8];
+    buf.copy_from_slice(&footer);
+    let metadata_len = decode_footer(&buf)?;
+    if file_size < metadata_len + 8 {
+        return Err(ParquetError::EOF(format!(
+            "file size of {file_size} is less than footer + metadata {}",
+            metadata_len + 8
+        )));
+    }
+
+    let metadata_start = file_size - 8 - metadata_len;
+    let metadata = get_range(object_store, location, metadata_start..file_size - 8).await?;
+    let metadata = decode_metadata(&metadata)?;
+
+    Ok((metadata, metadata_start))
+}
+
+/// A [`ParquetFileReaderFactory`] that reads parquet files from an object
+/// store, hiding all row groups that are not part of the [`RowGroupSelection`]
+/// attached to a file as [`FileMeta::extensions`].
+///
+/// Files without a [`RowGroupSelection`] are read in full.
+#[derive(Debug)]
+pub struct RowGroupSelectionReaderFactory {
+    object_store: Arc<DynObjectStore>,
+}
+
+impl RowGroupSelectionReaderFactory {
+    /// Create a factory that reads files from `object_store`.
+    pub fn new(object_store: Arc<DynObjectStore>) -> Self {
+        Self { object_store }
+    }
+}
+
+impl ParquetFileReaderFactory for RowGroupSelectionReaderFactory {
+    fn create_reader(
+        &self,
+        _partition_index: usize,
+        file_meta: FileMeta,
+        _metadata_size_hint: Option<usize>,
+        _metrics: &ExecutionPlanMetricsSet,
+    ) -> datafusion::error::Result<Box<dyn AsyncFileReader + Send>> {
+        let selection = file_meta
+            .extensions
+            .as_ref()
+            .and_then(|ext| ext.downcast_ref::<RowGroupSelection>())
+            .cloned();
+
+        Ok(Box::new(SelectingReader {
+            object_store: Arc::clone(&self.object_store),
+            meta: file_meta.object_meta,
+            selection,
+        }))
+    }
+}
+
+/// An [`AsyncFileReader`] that removes unselected row groups from the file
+/// metadata, so they are never read.
+#[derive(Debug)]
+struct SelectingReader {
+    object_store: Arc<DynObjectStore>,
+    meta: ObjectMeta,
+    selection: Option<RowGroupSelection>,
+}
+
+impl AsyncFileReader for SelectingReader {
+    fn get_bytes(&mut self, range: Range<usize>) -> BoxFuture<'_, parquet::errors::Result<Bytes>> {
+        get_range(self.object_store.as_ref(), &self.meta.location, range).boxed()
+    }
+
+    fn get_metadata(&mut self) -> BoxFuture<'_, parquet::errors::Result<Arc<ParquetMetaData>>> {
+        async move {
+            let (metadata, _) = fetch_metadata(
+                self.object_store.as_ref(),
+                &self.meta.location,
+                self.meta.size,
+            )
+            .await?;
+
+            let metadata = match &self.selection {
+                Some(selection) => {
+                    let row_groups = metadata
+                        .row_groups()
+                        .iter()
+                        .enumerate()
+                        .filter(|(idx, _)| selection.contains(*idx))
+                        .map(|(_, row_group)| row_group.clone())
+                        .collect();
+                    ParquetMetaData::new(metadata.file_metadata().clone(), row_groups)
+                }
+                None => metadata,
+            };
+
+            Ok(Arc::new(metadata))
+        }
+        .boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        metadata::IoxMetadata,
+        serialize::{to_parquet_bytes, BloomFilterConfig},
+    };
+    use arrow::{
+        array::{ArrayRef, DictionaryArray, StringArray, TimestampNanosecondArray},
+        datatypes::Int32Type,
+        record_batch::RecordBatch,
+    };
+    use data_types::{CompactionLevel, NamespaceId, PartitionId, SequenceNumber, ShardId, TableId};
+    use datafusion_util::MemoryStream;
+    use iox_time::Time;
+    use object_store::{memory::InMemory, ObjectStore};
+    use schema::{builder::SchemaBuilder, InfluxFieldType};
+
+    fn meta() -> IoxMetadata {
+        IoxMetadata {
+            object_store_id: Default::default(),
+            creation_timestamp: Time::from_timestamp_nanos(42),
+            namespace_id: NamespaceId::new(1),
+            namespace_name: "bananas".into(),
+            shard_id: ShardId::new(2),
+            table_id: TableId::new(3),
+            table_name: "platanos".into(),
+            partition_id: PartitionId::new(4),
+            partition_key: "potato".into(),
+            max_sequence_number: SequenceNumber::new(11),
+            compaction_level: CompactionLevel::FileNonOverlapped,
+            sort_key: None,
+        }
+    }
+
+    fn batch(hosts: &[&str]) -> RecordBatch {
+        let schema = SchemaBuilder::new()
+            .tag("host")
+            .influx_field("value", InfluxFieldType::String)
+            .timestamp()
+            .build()
+            .unwrap();
+
+        let host: DictionaryArray<Int32Type> = hosts.iter().copied().collect();
+        let value: StringArray = hosts.iter().map(|_| Some("v")).collect();
+        let time = TimestampNanosecondArray::from_iter_values(0..hosts.len() as i64);
+
+        RecordBatch::try_new(
+            schema.as_arrow(),
+            vec![
+                Arc::new(host) as ArrayRef,
+                Arc::new(value) as ArrayRef,
+                Arc::new(time) as ArrayRef,
+            ],
+        )
+        .unwrap()
+    }
+
+    /// Encode a parquet file and store it in an in-memory object store,
+    /// returning the store, path and size of the file.
+    async fn encode(bloom_filters: Option<&BloomFilterConfig>) -> (InMemory, Path, usize) {
+        let stream = Box::pin(MemoryStream::new(vec![batch(&["a", "b", "c"])]));
+        let (bytes, _) = to_parquet_bytes(stream, &meta(), bloom_filters)
+            .await
+            .expect("should serialize");
+
+        let store = InMemory::new();
+        let path = Path::from("file.parquet");
+        let size = bytes.len();
+        store.put(&path, Bytes::from(bytes)).await.unwrap();
+        (store, path, size)
+    }
+
+    fn values(column: &str, values: &[&str]) -> Vec<(String, Vec<String>)> {
+        vec![(
+            column.to_string(),
+            values.iter().map(ToString::to_string).collect(),
+        )]
+    }
+
+    #[tokio::test]
+    async fn test_prune_row_groups() {
+        let (store, path, size) = encode(Some(&BloomFilterConfig::default())).await;
+
+        let selection = prune_row_groups(&store, &path, size, &values("host", &["a"]))
+            .await
+            .unwrap();
+        assert!(selection.selects_all());
+
+        let selection = prune_row_groups(&store, &path, size, &values("host", &["x", "c"]))
+            .await
+            .unwrap();
+        assert!(selection.selects_all());
+
+        let selection = prune_row_groups(&store, &path, size, &values("host", &["x", "y"]))
+            .await
+            .unwrap();
+        assert!(selection.selects_none());
+        assert_eq!(selection.num_row_groups(), 1);
+        assert_eq!(selection.num_selected(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_prune_row_groups_no_filter() {
+        // Fields do not get bloom filters
+        let (store, path, size) = encode(Some(&BloomFilterConfig::default())).await;
+        let got = prune_row_groups(&store, &path, size, &values("value", &["x"])).await;
+        assert!(matches!(got, Err(BloomFilterError::NoBloomFilter)));
+
+        // Files written without bloom filters
+        let (store, path, size) = encode(None).await;
+        let got = prune_row_groups(&store, &path, size, &values("host", &["x"])).await;
+        assert!(matches!(got, Err(BloomFilterError::NoBloomFilter)));
+    }
+}
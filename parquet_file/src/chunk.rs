@@ -2,6 +2,7 @@
 //! download & execute a scan.
 
 use crate::{
+    bloom_filter::RowGroupSelection,
     storage::{ParquetExecInput, ParquetStorage},
     ParquetFilePath,
 };
@@ -22,6 +23,9 @@ pub struct ParquetChunk {
 
     /// Persists the parquet file within a namespace's relative path
     store: ParquetStorage,
+
+    /// The row groups of the file that need to be scanned, if not all.
+    row_group_selection: Option<RowGroupSelection>,
 }
 
 impl ParquetChunk {
@@ -31,9 +35,16 @@ impl ParquetChunk {
             parquet_file,
             schema,
             store,
+            row_group_selection: None,
         }
     }
 
+    /// Only scan the row groups included in `selection`.
+    pub fn with_row_group_selection(mut self, selection: RowGroupSelection) -> Self {
+        self.row_group_selection = Some(selection);
+        self
+    }
+
     /// Store that contains this file.
     pub fn store(&self) -> &ParquetStorage {
         &self.store
@@ -86,7 +97,9 @@ impl ParquetChunk {
     /// [`ParquetExec`]: datafusion::physical_plan::file_format::ParquetExec
     pub fn parquet_exec_input(&self) -> ParquetExecInput {
         let path: ParquetFilePath = self.parquet_file.as_ref().into();
-        self.store.parquet_exec_input(&path, self.file_size_bytes())
+        let mut input = self.store.parquet_exec_input(&path, self.file_size_bytes());
+        input.row_group_selection = self.row_group_selection.clone();
+        input
     }
 
     /// The total number of rows in all row groups in this chunk.
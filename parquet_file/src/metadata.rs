@@ -1058,7 +1058,7 @@ mod tests {
         let batch = RecordBatch::try_new(schema, vec![data, timestamps]).unwrap();
         let stream = Box::pin(MemoryStream::new(vec![batch.clone()]));
 
-        let (bytes, file_meta) = crate::serialize::to_parquet_bytes(stream, &meta)
+        let (bytes, file_meta) = crate::serialize::to_parquet_bytes(stream, &meta, None)
             .await
             .expect("should serialize");
 
@@ -4,7 +4,7 @@
 
 use std::{io::Write, sync::Arc};
 
-use arrow::error::ArrowError;
+use arrow::{datatypes::SchemaRef, error::ArrowError};
 use datafusion::physical_plan::SendableRecordBatchStream;
 use datafusion_util::config::BATCH_SIZE;
 use futures::{pin_mut, TryStreamExt};
@@ -14,7 +14,9 @@ use parquet::{
     basic::Compression,
     errors::ParquetError,
     file::{metadata::KeyValue, properties::WriterProperties},
+    schema::types::ColumnPath,
 };
+use schema::Schema;
 use thiserror::Error;
 
 use crate::metadata::{IoxMetadata, METADATA_KEY};
@@ -27,6 +29,31 @@ pub const ROW_GROUP_WRITE_SIZE: usize = 1024 * 1024;
 #[allow(clippy::assertions_on_constants)]
 const _: () = assert!(ROW_GROUP_WRITE_SIZE % BATCH_SIZE == 0);
 
+/// Configuration of the bloom filters written for each tag column of a parquet
+/// file.
+///
+/// Bloom filters allow readers to skip row groups (and entire files) that
+/// definitely do not contain a tag value, which min/max statistics cannot do
+/// for high-cardinality tags.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BloomFilterConfig {
+    /// The target false positive probability of each bloom filter.
+    pub fpp: f64,
+
+    /// The expected number of distinct values of a tag column within a single
+    /// row group, used to size each bloom filter.
+    pub ndv: u64,
+}
+
+impl Default for BloomFilterConfig {
+    fn default() -> Self {
+        Self {
+            fpp: 0.01,
+            ndv: 10_000,
+        }
+    }
+}
+
 /// [`RecordBatch`] to Parquet serialisation errors.
 ///
 /// [`RecordBatch`]: arrow::record_batch::RecordBatch
@@ -73,6 +100,9 @@ pub enum CodecError {
 /// [`METADATA_KEY`], with a base64-wrapped, protobuf serialized
 /// [`proto::IoxMetadata`] structure.
 ///
+/// If `bloom_filters` is specified, a bloom filter is written for each tag
+/// column of every row group.
+///
 /// Returns the serialized [`FileMetaData`] for the encoded parquet file, from
 /// which an [`IoxParquetMetaData`] can be derived.
 ///
@@ -92,6 +122,7 @@ pub enum CodecError {
 pub async fn to_parquet<W>(
     batches: SendableRecordBatchStream,
     meta: &IoxMetadata,
+    bloom_filters: Option<&BloomFilterConfig>,
     sink: W,
 ) -> Result<parquet::format::FileMetaData, CodecError>
 where
@@ -105,7 +136,7 @@ where
     pin_mut!(stream);
 
     // Serialize the IoxMetadata to the protobuf bytes.
-    let props = writer_props(meta)?;
+    let props = writer_props(meta, &schema, bloom_filters)?;
     let write_batch_size = props.write_batch_size();
     let max_row_group_size = props.max_row_group_size();
 
@@ -142,6 +173,7 @@ where
 pub async fn to_parquet_bytes(
     batches: SendableRecordBatchStream,
     meta: &IoxMetadata,
+    bloom_filters: Option<&BloomFilterConfig>,
 ) -> Result<(Vec<u8>, parquet::format::FileMetaData), CodecError> {
     let mut bytes = vec![];
 
@@ -153,7 +185,7 @@ pub async fn to_parquet_bytes(
     );
 
     // Serialize the record batches into the in-memory buffer
-    let meta = to_parquet(batches, meta, &mut bytes).await?;
+    let meta = to_parquet(batches, meta, bloom_filters, &mut bytes).await?;
     bytes.shrink_to_fit();
 
     trace!(?partition_id, ?meta, "generated parquet file metadata");
@@ -164,8 +196,15 @@ pub async fn to_parquet_bytes(
 /// Helper to construct [`WriterProperties`] for the [`ArrowWriter`],
 /// serialising the given [`IoxMetadata`] and embedding it as a key=value
 /// property keyed by [`METADATA_KEY`].
-fn writer_props(meta: &IoxMetadata) -> Result<WriterProperties, prost::EncodeError> {
-    let builder = WriterProperties::builder()
+///
+/// If `bloom_filters` is specified, bloom filters are enabled for all the tag
+/// columns in `schema`.
+fn writer_props(
+    meta: &IoxMetadata,
+    schema: &SchemaRef,
+    bloom_filters: Option<&BloomFilterConfig>,
+) -> Result<WriterProperties, prost::EncodeError> {
+    let mut builder = WriterProperties::builder()
         .set_key_value_metadata(Some(vec![KeyValue {
             key: METADATA_KEY.to_string(),
             value: Some(meta.to_base64()?),
@@ -173,6 +212,23 @@ fn writer_props(meta: &IoxMetadata) -> Result<WriterProperties, prost::EncodeErr
         .set_compression(Compression::ZSTD)
         .set_max_row_group_size(ROW_GROUP_WRITE_SIZE);
 
+    if let Some(config) = bloom_filters {
+        // Only IOx schemas identify tag columns - anything else is written
+        // without bloom filters.
+        match Schema::try_from(Arc::clone(schema)) {
+            Ok(schema) => {
+                for field in schema.tags_iter() {
+                    let path = ColumnPath::from(field.name().as_str());
+                    builder = builder
+                        .set_column_bloom_filter_enabled(path.clone(), true)
+                        .set_column_bloom_filter_fpp(path.clone(), config.fpp)
+                        .set_column_bloom_filter_ndv(path, config.ndv);
+                }
+            }
+            Err(e) => warn!(error=%e, "not writing bloom filters for non-IOx schema"),
+        }
+    }
+
     Ok(builder.build())
 }
 
@@ -211,7 +267,7 @@ mod tests {
         let batch = RecordBatch::try_from_iter([("a", to_string_array(&["value"]))]).unwrap();
         let stream = Box::pin(MemoryStream::new(vec![batch.clone()]));
 
-        let (bytes, _file_meta) = to_parquet_bytes(stream, &meta)
+        let (bytes, _file_meta) = to_parquet_bytes(stream, &meta, None)
             .await
             .expect("should serialize");
 
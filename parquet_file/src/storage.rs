@@ -2,8 +2,9 @@
 //! object store and reading it back.
 
 use crate::{
+    bloom_filter::RowGroupSelection,
     metadata::{IoxMetadata, IoxParquetMetaData},
-    serialize::{self, CodecError},
+    serialize::{self, BloomFilterConfig, CodecError},
     ParquetFilePath,
 };
 use arrow::{
@@ -87,6 +88,9 @@ pub struct ParquetExecInput {
 
     /// Object metadata.
     pub object_meta: ObjectMeta,
+
+    /// The row groups that need to be scanned, if not all.
+    pub row_group_selection: Option<RowGroupSelection>,
 }
 
 impl ParquetExecInput {
@@ -155,13 +159,27 @@ pub struct ParquetStorage {
 
     /// Storage ID to hook it into DataFusion.
     id: StorageId,
+
+    /// Bloom filter configuration for uploaded files, if enabled.
+    bloom_filters: Option<BloomFilterConfig>,
 }
 
 impl ParquetStorage {
     /// Initialise a new [`ParquetStorage`] using `object_store` as the
     /// persistence layer.
     pub fn new(object_store: Arc<DynObjectStore>, id: StorageId) -> Self {
-        Self { object_store, id }
+        Self {
+            object_store,
+            id,
+            bloom_filters: None,
+        }
+    }
+
+    /// Write bloom filters for the tag columns of all files uploaded by this
+    /// [`ParquetStorage`], using the given `config`.
+    pub fn with_bloom_filters(mut self, config: BloomFilterConfig) -> Self {
+        self.bloom_filters = Some(config);
+        self
     }
 
     /// Get underlying object store.
@@ -211,7 +229,8 @@ impl ParquetStorage {
         //
         // This is not a huge concern, as the resulting parquet files are
         // currently smallish on average.
-        let (data, parquet_file_meta) = serialize::to_parquet_bytes(batches, meta).await?;
+        let (data, parquet_file_meta) =
+            serialize::to_parquet_bytes(batches, meta, self.bloom_filters.as_ref()).await?;
 
         // Read the IOx-specific parquet metadata from the file metadata
         let parquet_meta =
@@ -274,6 +293,7 @@ impl ParquetStorage {
                 last_modified: Default::default(),
                 size: file_size,
             },
+            row_group_selection: None,
         }
     }
 }
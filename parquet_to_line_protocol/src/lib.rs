@@ -34,7 +34,7 @@ use std::{
 };
 
 mod batch;
-use batch::convert_to_lines;
+pub use batch::convert_to_lines;
 
 #[derive(Debug, Snafu)]
 pub enum Error {
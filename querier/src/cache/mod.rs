@@ -5,13 +5,18 @@ use backoff::BackoffConfig;
 use cache_system::backend::policy::lru::ResourcePool;
 use iox_catalog::interface::Catalog;
 use iox_time::TimeProvider;
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 use tokio::runtime::Handle;
 
 use self::{
-    namespace::NamespaceCache, object_store::ObjectStoreCache, parquet_file::ParquetFileCache,
-    partition::PartitionCache, processed_tombstones::ProcessedTombstonesCache,
-    projected_schema::ProjectedSchemaCache, ram::RamSize, tombstones::TombstoneCache,
+    namespace::NamespaceCache,
+    object_store::{ObjectStoreCache, ParquetDiskCache},
+    parquet_file::ParquetFileCache,
+    partition::PartitionCache,
+    processed_tombstones::ProcessedTombstonesCache,
+    projected_schema::ProjectedSchemaCache,
+    ram::RamSize,
+    tombstones::TombstoneCache,
 };
 
 pub mod namespace;
@@ -20,6 +25,7 @@ pub mod parquet_file;
 pub mod partition;
 pub mod processed_tombstones;
 pub mod projected_schema;
+pub mod query_result;
 mod ram;
 pub mod tombstones;
 
@@ -62,6 +68,11 @@ pub struct CatalogCache {
 
 impl CatalogCache {
     /// Create empty cache.
+    ///
+    /// If `parquet_disk_cache` is set, object store data that is loaded into the RAM cache is also kept on local disk.
+    ///
+    /// If `namespace_max_refresh_interval` is set, cached namespace schemas are refreshed at least that often.
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         catalog: Arc<dyn Catalog>,
         time_provider: Arc<dyn TimeProvider>,
@@ -69,6 +80,8 @@ impl CatalogCache {
         object_store: Arc<dyn ObjectStore>,
         ram_pool_metadata_bytes: usize,
         ram_pool_data_bytes: usize,
+        parquet_disk_cache: Option<ParquetDiskCache>,
+        namespace_max_refresh_interval: Option<Duration>,
         handle: &Handle,
     ) -> Self {
         Self::new_internal(
@@ -78,6 +91,8 @@ impl CatalogCache {
             object_store,
             ram_pool_metadata_bytes,
             ram_pool_data_bytes,
+            parquet_disk_cache,
+            namespace_max_refresh_interval,
             handle,
             false,
         )
@@ -100,6 +115,8 @@ impl CatalogCache {
             object_store,
             usize::MAX,
             usize::MAX,
+            None,
+            None,
             handle,
             true,
         )
@@ -113,6 +130,8 @@ impl CatalogCache {
         object_store: Arc<dyn ObjectStore>,
         ram_pool_metadata_bytes: usize,
         ram_pool_data_bytes: usize,
+        parquet_disk_cache: Option<ParquetDiskCache>,
+        namespace_max_refresh_interval: Option<Duration>,
         handle: &Handle,
         testing: bool,
     ) -> Self {
@@ -143,6 +162,7 @@ impl CatalogCache {
             Arc::clone(&time_provider),
             &metric_registry,
             Arc::clone(&ram_pool_metadata),
+            namespace_max_refresh_interval,
             handle,
             testing,
         );
@@ -182,6 +202,7 @@ impl CatalogCache {
             Arc::clone(&time_provider),
             &metric_registry,
             Arc::clone(&ram_pool_data),
+            parquet_disk_cache,
             testing,
         );
 
@@ -245,7 +266,6 @@ impl CatalogCache {
     }
 
     /// Object store cache.
-    #[allow(dead_code)]
     pub(crate) fn object_store(&self) -> &ObjectStoreCache {
         &self.object_store_cache
     }
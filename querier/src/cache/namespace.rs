@@ -13,7 +13,9 @@ use cache_system::{
     loader::{metrics::MetricsLoader, FunctionLoader},
     resource_consumption::FunctionEstimator,
 };
-use data_types::{ColumnId, NamespaceId, NamespaceSchema, TableId, TableSchema};
+use data_types::{
+    ColumnId, DeduplicationPolicy, NamespaceId, NamespaceSchema, TableId, TableSchema,
+};
 use iox_catalog::interface::{get_schema_by_name, Catalog};
 use iox_time::TimeProvider;
 use schema::Schema;
@@ -36,6 +38,8 @@ pub const TTL_EXISTING: Duration = Duration::from_secs(300);
 /// This policy is chosen to:
 /// 1. decorrelate refreshes which smooths out catalog load
 /// 2. refresh commonly accessed keys less frequently
+///
+/// The interval between refreshes can be bounded with the `max_refresh_interval` of [`NamespaceCache::new`].
 pub const REFRESH_EXISTING: BackoffConfig = BackoffConfig {
     init_backoff: Duration::from_secs(30),
     max_backoff: Duration::MAX,
@@ -74,12 +78,17 @@ pub struct NamespaceCache {
 
 impl NamespaceCache {
     /// Create new empty cache.
+    ///
+    /// If `max_refresh_interval` is set, existing namespaces are refreshed at least that often, which bounds how long
+    /// schema changes made by other nodes that do not expire the cache, such as a deleted table, remain unobserved.
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         catalog: Arc<dyn Catalog>,
         backoff_config: BackoffConfig,
         time_provider: Arc<dyn TimeProvider>,
         metric_registry: &metric::Registry,
         ram_pool: Arc<ResourcePool<RamSize>>,
+        max_refresh_interval: Option<Duration>,
         handle: &Handle,
         testing: bool,
     ) -> Self {
@@ -122,11 +131,15 @@ impl NamespaceCache {
             CACHE_ID,
             metric_registry,
         ));
+        let refresh_existing = BackoffConfig {
+            max_backoff: max_refresh_interval.unwrap_or(REFRESH_EXISTING.max_backoff),
+            ..REFRESH_EXISTING
+        };
         backend.add_policy(RefreshPolicy::new(
             Arc::clone(&time_provider),
             Arc::new(OptionalValueRefreshDurationProvider::new(
                 None,
-                Some(REFRESH_EXISTING),
+                Some(refresh_existing),
             )),
             Arc::clone(&loader) as _,
             CACHE_ID,
@@ -210,6 +223,7 @@ pub struct CachedTable {
     pub column_id_map: HashMap<ColumnId, Arc<str>>,
     pub column_id_map_rev: HashMap<Arc<str>, ColumnId>,
     pub primary_key_column_ids: Vec<ColumnId>,
+    pub dedup_policy: DeduplicationPolicy,
 }
 
 impl CachedTable {
@@ -242,6 +256,7 @@ impl From<TableSchema> for CachedTable {
         column_id_map.shrink_to_fit();
 
         let id = table.id;
+        let dedup_policy = table.dedup_policy;
         let schema: Schema = table.try_into().expect("Catalog table schema broken");
 
         let mut column_id_map_rev: HashMap<Arc<str>, ColumnId> = column_id_map
@@ -267,6 +282,7 @@ impl From<TableSchema> for CachedTable {
             column_id_map,
             column_id_map_rev,
             primary_key_column_ids,
+            dedup_policy,
         }
     }
 }
@@ -348,6 +364,7 @@ mod tests {
             catalog.time_provider(),
             &catalog.metric_registry(),
             test_ram_pool(),
+            None,
             &Handle::current(),
             true,
         );
@@ -386,6 +403,7 @@ mod tests {
                             (Arc::from(col113.column.name.clone()), col113.column.id),
                         ]),
                         primary_key_column_ids: vec![col112.column.id, col113.column.id],
+                        dedup_policy: Default::default(),
                     }),
                 ),
                 (
@@ -407,6 +425,7 @@ mod tests {
                             (Arc::from(col122.column.name.clone()), col122.column.id),
                         ]),
                         primary_key_column_ids: vec![col122.column.id],
+                        dedup_policy: Default::default(),
                     }),
                 ),
             ]),
@@ -439,6 +458,7 @@ mod tests {
                         col211.column.id,
                     )]),
                     primary_key_column_ids: vec![col211.column.id],
+                    dedup_policy: Default::default(),
                 }),
             )]),
         };
@@ -463,6 +483,7 @@ mod tests {
             catalog.time_provider(),
             &catalog.metric_registry(),
             test_ram_pool(),
+            None,
             &Handle::current(),
             true,
         );
@@ -486,6 +507,7 @@ mod tests {
             catalog.time_provider(),
             &catalog.metric_registry(),
             test_ram_pool(),
+            None,
             &Handle::current(),
             true,
         );
@@ -1,13 +1,16 @@
 //! Cache for immutable object store entires.
-use std::{mem::size_of_val, ops::Range, sync::Arc};
+use std::{io, mem::size_of_val, ops::Range, path::PathBuf, sync::Arc};
 
 use async_trait::async_trait;
 use backoff::{Backoff, BackoffConfig};
 use bytes::Bytes;
 use cache_system::{
-    backend::policy::{
-        lru::{LruPolicy, ResourcePool},
-        PolicyBackend,
+    backend::{
+        disk::DiskBackend,
+        policy::{
+            lru::{LruPolicy, ResourcePool},
+            PolicyBackend,
+        },
     },
     cache::{driver::CacheDriver, metrics::CacheWithMetrics, Cache},
     loader::{metrics::MetricsLoader, FunctionLoader},
@@ -15,10 +18,12 @@ use cache_system::{
 };
 use futures::{stream::BoxStream, StreamExt};
 use iox_time::TimeProvider;
+use metric::U64Counter;
 use object_store::{
     path::Path, Error as ObjectStoreError, GetResult, ListResult, MultipartId, ObjectMeta,
     ObjectStore,
 };
+use parking_lot::Mutex;
 use tokio::io::AsyncWrite;
 use trace::span::Span;
 
@@ -45,6 +50,67 @@ async fn read_from_store(
     Ok(Some(data))
 }
 
+/// Bounded on-disk cache tier that sits below the RAM cache of [`ObjectStoreCache`].
+///
+/// Objects that are loaded from the object store are also written to local disk (in the background), so that they can be served without
+/// another object store request after they were evicted from RAM or after a restart. Entries are checksummed and
+/// evicted in LRU order once the configured size limit is reached.
+#[derive(Debug, Clone)]
+pub struct ParquetDiskCache {
+    backend: Arc<Mutex<DiskBackend<String, Bytes>>>,
+    metric_hit: U64Counter,
+    metric_miss: U64Counter,
+}
+
+impl ParquetDiskCache {
+    /// Create disk cache in `dir` that uses at most `limit_bytes` of disk space.
+    ///
+    /// Entries that already exist in `dir` (e.g. from a previous run) are indexed, so they can be served without
+    /// another object store request. Their content is only read on first access.
+    pub fn new(
+        dir: impl Into<PathBuf>,
+        limit_bytes: u64,
+        metric_registry: &metric::Registry,
+    ) -> io::Result<Self> {
+        let backend = DiskBackend::new(dir, limit_bytes)?;
+
+        let metric = metric_registry.register_metric::<U64Counter>(
+            "cache_disk_get",
+            "Number of gets against the on-disk cache tier",
+        );
+        let metric_hit = metric.recorder(&[("name", CACHE_ID), ("status", "hit")]);
+        let metric_miss = metric.recorder(&[("name", CACHE_ID), ("status", "miss")]);
+
+        Ok(Self {
+            backend: Arc::new(Mutex::new(backend)),
+            metric_hit,
+            metric_miss,
+        })
+    }
+
+    async fn get(&self, path: &Path) -> Option<Bytes> {
+        let backend = Arc::clone(&self.backend);
+        let key = path.to_string();
+        let data = tokio::task::spawn_blocking(move || DiskBackend::get_shared(&backend, &key))
+            .await
+            .expect("disk cache read panicked");
+
+        match &data {
+            Some(_) => self.metric_hit.inc(1),
+            None => self.metric_miss.inc(1),
+        }
+
+        data
+    }
+
+    /// Write entry to disk in the background.
+    fn set(&self, path: &Path, data: Bytes) {
+        let backend = Arc::clone(&self.backend);
+        let key = path.to_string();
+        tokio::task::spawn_blocking(move || DiskBackend::set_shared(&backend, key, data));
+    }
+}
+
 type CacheT = Box<
     dyn Cache<
         K = Path,
@@ -65,6 +131,9 @@ type CacheT = Box<
 pub struct ObjectStoreCache {
     // this is the virtual object store
     object_store: Arc<dyn ObjectStore>,
+
+    // the store below the cache
+    inner: Arc<dyn ObjectStore>,
 }
 
 impl ObjectStoreCache {
@@ -75,15 +144,23 @@ impl ObjectStoreCache {
         time_provider: Arc<dyn TimeProvider>,
         metric_registry: &metric::Registry,
         ram_pool: Arc<ResourcePool<RamSize>>,
+        disk_cache: Option<ParquetDiskCache>,
         testing: bool,
     ) -> Self {
         let object_store_captured = Arc::clone(&object_store);
         let loader = FunctionLoader::new(move |key: Path, _extra: ()| {
             let backoff_config = backoff_config.clone();
             let object_store = Arc::clone(&object_store_captured);
+            let disk_cache = disk_cache.clone();
 
             async move {
-                Backoff::new(&backoff_config)
+                if let Some(disk_cache) = &disk_cache {
+                    if let Some(data) = disk_cache.get(&key).await {
+                        return Some(data);
+                    }
+                }
+
+                let data = Backoff::new(&backoff_config)
                     .retry_all_errors::<_, _, _, ObjectStoreError>(
                         "get object from object store",
                         || async {
@@ -93,7 +170,14 @@ impl ObjectStoreCache {
                         },
                     )
                     .await
-                    .expect("retry forever")
+                    .expect("retry forever");
+
+                // "not found" results are NOT persisted, they are only cached in RAM
+                if let (Some(disk_cache), Some(data)) = (&disk_cache, &data) {
+                    disk_cache.set(&key, data.clone());
+                }
+
+                data
             }
         });
         let loader = Arc::new(MetricsLoader::new(
@@ -127,12 +211,16 @@ impl ObjectStoreCache {
             metric_registry,
         ));
 
+        let inner = object_store;
         let object_store = Arc::new(CachedObjectStore {
             cache,
-            inner: object_store,
+            inner: Arc::clone(&inner),
         });
 
-        Self { object_store }
+        Self {
+            object_store,
+            inner,
+        }
     }
 
     /// Get object store.
@@ -140,6 +228,14 @@ impl ObjectStoreCache {
     pub fn object_store(&self) -> &Arc<dyn ObjectStore> {
         &self.object_store
     }
+
+    /// Get the object store below the cache.
+    ///
+    /// Use this for reads of small parts of an object, e.g. the metadata of a parquet file, that shall not load the
+    /// whole object into the cache.
+    pub fn uncached_object_store(&self) -> &Arc<dyn ObjectStore> {
+        &self.inner
+    }
 }
 
 #[derive(Debug)]
@@ -259,6 +355,8 @@ impl ObjectStore for CachedObjectStore {
 
 #[cfg(test)]
 mod tests {
+    use std::time::Duration;
+
     use assert_matches::assert_matches;
     use futures::TryStreamExt;
     use iox_time::SystemProvider;
@@ -304,6 +402,7 @@ mod tests {
             time_provider,
             &metric_registry,
             test_ram_pool(),
+            None,
             true,
         );
         let cached_store = cache.object_store();
@@ -410,6 +509,138 @@ mod tests {
         assert_eq!(get_count_miss(&metric_registry), 1);
     }
 
+    #[tokio::test]
+    async fn test_uncached_object_store() {
+        let inner = Arc::new(InMemory::new());
+
+        let path = Path::from("foo");
+        let bytes = Bytes::from(b"data_foo" as &'static [u8]);
+        inner.put(&path, bytes.clone()).await.unwrap();
+
+        let metric_registry = metric::Registry::new();
+        let time_provider = Arc::new(SystemProvider::new());
+        let instrumented_store = ObjectStoreMetrics::new(
+            Arc::clone(&inner) as _,
+            Arc::clone(&time_provider) as _,
+            &metric_registry,
+        );
+        let cache = ObjectStoreCache::new(
+            BackoffConfig::default(),
+            Arc::new(instrumented_store),
+            time_provider,
+            &metric_registry,
+            test_ram_pool(),
+            None,
+            true,
+        );
+
+        // ranged reads do not fetch the whole object
+        assert_eq!(
+            cache
+                .uncached_object_store()
+                .get_range(&path, 0..4)
+                .await
+                .unwrap(),
+            bytes.slice(0..4),
+        );
+        assert_eq!(get_count_hit(&metric_registry), 0);
+
+        // nor do they populate the cache
+        assert_eq!(get_bytes(cache.object_store().as_ref(), &path).await, bytes);
+        assert_eq!(get_count_hit(&metric_registry), 1);
+    }
+
+    #[tokio::test]
+    async fn test_disk_cache() {
+        let inner = Arc::new(InMemory::new());
+
+        let path_1 = Path::from("foo");
+        let bytes_1 = Bytes::from(b"data_foo" as &'static [u8]);
+        inner.put(&path_1, bytes_1.clone()).await.unwrap();
+
+        let path_2 = Path::from("bar");
+
+        let dir = test_helpers::tmp_dir().unwrap();
+        let metric_registry = metric::Registry::new();
+        let time_provider = Arc::new(SystemProvider::new());
+        let instrumented_store = Arc::new(ObjectStoreMetrics::new(
+            Arc::clone(&inner) as _,
+            Arc::clone(&time_provider) as _,
+            &metric_registry,
+        ));
+
+        let disk_cache = ParquetDiskCache::new(dir.path(), 1_000, &metric_registry).unwrap();
+        let disk_cache_captured = disk_cache.clone();
+        let cache = ObjectStoreCache::new(
+            BackoffConfig::default(),
+            Arc::clone(&instrumented_store) as _,
+            Arc::clone(&time_provider) as _,
+            &metric_registry,
+            test_ram_pool(),
+            Some(disk_cache),
+            true,
+        );
+        let cached_store = cache.object_store();
+
+        assert_eq!(get_bytes(cached_store.as_ref(), &path_1).await, bytes_1);
+        assert_matches!(
+            cached_store.get(&path_2).await.unwrap_err(),
+            ObjectStoreError::NotFound { .. }
+        );
+        assert_eq!(get_count_hit(&metric_registry), 1);
+        assert_eq!(get_count_miss(&metric_registry), 1);
+        assert_eq!(disk_count(&metric_registry, "hit"), 0);
+        assert_eq!(disk_count(&metric_registry, "miss"), 2);
+
+        // disk writes happen in the background
+        tokio::time::timeout(Duration::from_secs(10), async {
+            while disk_cache_captured.backend.lock().n_entries() == 0 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+
+        // a new cache (e.g. after a restart) with an empty RAM tier is served from disk
+        let metric_registry_2 = metric::Registry::new();
+        let disk_cache = ParquetDiskCache::new(dir.path(), 1_000, &metric_registry_2).unwrap();
+        let cache = ObjectStoreCache::new(
+            BackoffConfig::default(),
+            Arc::clone(&instrumented_store) as _,
+            time_provider,
+            &metric_registry_2,
+            test_ram_pool(),
+            Some(disk_cache),
+            true,
+        );
+        let cached_store = cache.object_store();
+
+        assert_eq!(get_bytes(cached_store.as_ref(), &path_1).await, bytes_1);
+        assert_eq!(get_count_hit(&metric_registry), 1);
+        assert_eq!(disk_count(&metric_registry_2, "hit"), 1);
+
+        // "not found" is not persisted
+        assert_matches!(
+            cached_store.get(&path_2).await.unwrap_err(),
+            ObjectStoreError::NotFound { .. }
+        );
+        assert_eq!(get_count_miss(&metric_registry), 2);
+        assert_eq!(disk_count(&metric_registry_2, "miss"), 1);
+    }
+
+    async fn get_bytes(store: &dyn ObjectStore, path: &Path) -> Bytes {
+        store.get(path).await.unwrap().bytes().await.unwrap()
+    }
+
+    fn disk_count(metric_registry: &metric::Registry, status: &'static str) -> u64 {
+        metric_registry
+            .get_instrument::<Metric<U64Counter>>("cache_disk_get")
+            .unwrap()
+            .get_observer(&Attributes::from(&[("name", CACHE_ID), ("status", status)]))
+            .unwrap()
+            .fetch()
+    }
+
     async fn list(store: &dyn ObjectStore) -> Vec<Path> {
         let mut paths: Vec<_> = store
             .list(None)
@@ -213,6 +213,7 @@ mod tests {
                 ColumnId::new(3),
                 ColumnId::new(4),
             ],
+            dedup_policy: Default::default(),
         });
         let table_1b = Arc::new(CachedTable {
             id: table_id_1,
@@ -225,6 +226,7 @@ mod tests {
                 ColumnId::new(3),
                 ColumnId::new(4),
             ],
+            dedup_policy: Default::default(),
         });
         let table_2a = Arc::new(CachedTable {
             id: table_id_2,
@@ -238,6 +240,7 @@ mod tests {
                 ColumnId::new(4),
                 ColumnId::new(5),
             ],
+            dedup_policy: Default::default(),
         });
 
         // initial request
@@ -0,0 +1,597 @@
+//! Cache for query results.
+//!
+//! While this is technically NOT caching catalog requests (i.e. CPU and IO work), it allows dashboards that issue the
+//! same queries over and over again to skip the execution of queries whose data did not change.
+//!
+//! Entries are keyed by the query and by the versions of all data that the query read, which are recorded while the
+//! query is planned (see [`QueryDataVersions`]). Planning a query therefore always fetches the current state of the
+//! catalog and the ingesters, and a cached result is only used if it was computed from exactly the same data. Queries
+//! calling functions that are not immutable (e.g. `now()`) are never cached, see [`QueryVolatility`].
+use std::{
+    any::Any,
+    collections::BTreeSet,
+    fmt,
+    mem::{size_of, size_of_val},
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use arrow::{datatypes::SchemaRef, error::Result as ArrowResult, record_batch::RecordBatch};
+use cache_system::{
+    backend::{
+        policy::{
+            lru::{LruPolicy, ResourcePool},
+            PolicyBackend,
+        },
+        CacheBackend,
+    },
+    resource_consumption::FunctionEstimator,
+};
+use data_types::{DeletePredicate, PartitionId};
+use datafusion::{
+    error::DataFusionError,
+    execution::context::{SessionState, TaskContext},
+    physical_plan::{
+        coalesce_partitions::CoalescePartitionsExec, expressions::PhysicalSortExpr,
+        memory::MemoryExec, DisplayFormatType, ExecutionPlan, Partitioning, RecordBatchStream,
+        SendableRecordBatchStream, Statistics,
+    },
+};
+use futures::{ready, Stream, StreamExt};
+use iox_query::{
+    exec::{IOxSessionContext, QueryVolatility},
+    QueryChunk, QueryChunkData,
+};
+use iox_time::TimeProvider;
+use metric::U64Counter;
+use observability_deps::tracing::debug;
+use parking_lot::Mutex;
+use schema::Schema;
+use uuid::Uuid;
+
+use crate::ingester::IngesterChunk;
+
+use super::ram::RamSize;
+
+const CACHE_ID: &str = "query_result";
+
+/// Version of data that a query read.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub(crate) enum DataVersion {
+    /// Schema of a table, which e.g. determines the columns of `SELECT *`.
+    Table {
+        table_name: Arc<str>,
+        schema: String,
+    },
+
+    /// An immutable parquet file, along with the delete predicates that may remove rows of it.
+    ParquetFile {
+        location: String,
+        delete_predicates: Vec<DeletePredicate>,
+    },
+
+    /// Data of a partition that is buffered in an ingester, identified by the version the ingester assigned to it.
+    IngesterPartition {
+        partition_id: PartitionId,
+        ingester_uuid: Uuid,
+        buffer_version: u64,
+        delete_predicates: Vec<DeletePredicate>,
+    },
+}
+
+impl DataVersion {
+    fn table(table_name: &Arc<str>, schema: &Schema) -> Self {
+        Self::Table {
+            table_name: Arc::clone(table_name),
+            schema: format!("{:?}", schema.as_arrow().fields()),
+        }
+    }
+
+    /// Version of the data of `chunk`, or `None` if it has none (e.g. the data of an ingester that does not track
+    /// versions).
+    fn chunk(chunk: &dyn QueryChunk) -> Option<Self> {
+        // predicates that do not overlap the chunk (e.g. the retention predicate of a chunk that is entirely within
+        // the retention period) don't change its data, but do change from query to query
+        let time_range = chunk.summary().time_range();
+        let mut delete_predicates: Vec<_> = chunk
+            .delete_predicates()
+            .iter()
+            .filter(|pred| time_range.map(|t| t.overlaps(pred.range)).unwrap_or(true))
+            .map(|pred| pred.as_ref().clone())
+            .collect();
+        delete_predicates.sort();
+
+        match chunk.data() {
+            QueryChunkData::Parquet(input) => Some(Self::ParquetFile {
+                location: input.object_meta.location.to_string(),
+                delete_predicates,
+            }),
+            QueryChunkData::RecordBatches(_) => {
+                let (ingester_uuid, buffer_version) = chunk
+                    .as_any()
+                    .downcast_ref::<IngesterChunk>()?
+                    .buffer_version()?;
+                Some(Self::IngesterPartition {
+                    partition_id: chunk.partition_id(),
+                    ingester_uuid,
+                    buffer_version,
+                    delete_predicates,
+                })
+            }
+        }
+    }
+
+    /// Size in bytes, including `Self`.
+    fn size(&self) -> usize {
+        let delete_predicates_size =
+            |preds: &[DeletePredicate]| preds.iter().map(DeletePredicate::size).sum::<usize>();
+
+        size_of_val(self)
+            + match self {
+                Self::Table { table_name, schema } => table_name.len() + schema.capacity(),
+                Self::ParquetFile {
+                    location,
+                    delete_predicates,
+                } => location.capacity() + delete_predicates_size(delete_predicates),
+                Self::IngesterPartition {
+                    delete_predicates, ..
+                } => delete_predicates_size(delete_predicates),
+            }
+    }
+}
+
+/// Versions of the data that a query reads, recorded by the tables while the query is planned.
+///
+/// This is attached to the DataFusion session of queries against namespaces that cache query results.
+#[derive(Debug)]
+pub(crate) struct QueryDataVersions {
+    /// The versions, or `None` if the query read data that has no version (e.g. system tables).
+    versions: Mutex<Option<BTreeSet<DataVersion>>>,
+}
+
+impl QueryDataVersions {
+    /// Create empty versions.
+    pub(crate) fn new() -> Self {
+        Self {
+            versions: Mutex::new(Some(BTreeSet::new())),
+        }
+    }
+
+    /// Record that the query planned with `ctx` reads `chunks` of the table `table_name`, or that its results can not
+    /// be cached if any chunk has no version.
+    pub(crate) fn record_chunks(
+        ctx: &SessionState,
+        table_name: &Arc<str>,
+        schema: &Schema,
+        chunks: &[Arc<dyn QueryChunk>],
+    ) {
+        if let Some(this) = ctx.config.get_extension::<Self>() {
+            let chunk_versions: Option<Vec<_>> = chunks
+                .iter()
+                .map(|chunk| DataVersion::chunk(chunk.as_ref()))
+                .collect();
+
+            let mut versions = this.versions.lock();
+            match chunk_versions {
+                Some(chunk_versions) => {
+                    if let Some(versions) = versions.as_mut() {
+                        versions.insert(DataVersion::table(table_name, schema));
+                        versions.extend(chunk_versions);
+                    }
+                }
+                None => *versions = None,
+            }
+        }
+    }
+
+    /// Record that the query planned with `ctx` reads data that has no version, so that its results can not be
+    /// cached.
+    pub(crate) fn record_unversioned(ctx: &SessionState) {
+        if let Some(this) = ctx.config.get_extension::<Self>() {
+            *this.versions.lock() = None;
+        }
+    }
+
+    fn take(&self) -> Option<Vec<DataVersion>> {
+        self.versions
+            .lock()
+            .take()
+            .map(|versions| versions.into_iter().collect())
+    }
+}
+
+/// Cache key.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+struct CacheKey {
+    namespace: Arc<str>,
+    query_type: String,
+    query_text: String,
+    data: Vec<DataVersion>,
+}
+
+impl CacheKey {
+    /// Size in bytes, including `Self`.
+    fn size(&self) -> usize {
+        size_of_val(self)
+            + self.namespace.len()
+            + self.query_type.capacity()
+            + self.query_text.capacity()
+            + self.data.iter().map(DataVersion::size).sum::<usize>()
+            + (self.data.capacity() - self.data.len()) * size_of::<DataVersion>()
+    }
+}
+
+/// Results of a query.
+#[derive(Debug)]
+struct CachedResult {
+    schema: SchemaRef,
+    batches: Vec<RecordBatch>,
+}
+
+impl CachedResult {
+    /// Size in bytes, including `Self`.
+    fn size(&self) -> usize {
+        size_of_val(self) + self.batches.iter().map(batch_size).sum::<usize>()
+    }
+}
+
+fn batch_size(batch: &RecordBatch) -> usize {
+    batch
+        .columns()
+        .iter()
+        .map(|c| c.get_array_memory_size())
+        .sum()
+}
+
+type Backend = PolicyBackend<CacheKey, Arc<CachedResult>>;
+
+/// Cache for the results of queries, see [module level docs](self).
+#[derive(Debug)]
+pub struct QueryResultCache {
+    backend: Arc<Mutex<Backend>>,
+
+    /// Results larger than this are not kept.
+    max_result_bytes: usize,
+
+    hits: U64Counter,
+    misses: U64Counter,
+    uncacheable: U64Counter,
+}
+
+impl QueryResultCache {
+    /// Create new empty cache that keeps up to `ram_pool_bytes` of query results.
+    pub fn new(
+        time_provider: Arc<dyn TimeProvider>,
+        metric_registry: Arc<metric::Registry>,
+        ram_pool_bytes: usize,
+    ) -> Self {
+        let ram_pool = Arc::new(ResourcePool::new(
+            "ram_query_results",
+            RamSize(ram_pool_bytes),
+            Arc::clone(&metric_registry),
+        ));
+
+        let mut backend = PolicyBackend::hashmap_backed(time_provider);
+        backend.add_policy(LruPolicy::new(
+            ram_pool,
+            CACHE_ID,
+            Arc::new(FunctionEstimator::new(
+                |k: &CacheKey, v: &Arc<CachedResult>| RamSize(k.size() + v.size()),
+            )),
+        ));
+
+        let requests = metric_registry.register_metric::<U64Counter>(
+            "querier_query_result_cache_requests",
+            "Number of queries that were looked up in the query result cache",
+        );
+
+        Self {
+            backend: Arc::new(Mutex::new(backend)),
+            max_result_bytes: ram_pool_bytes,
+            hits: requests.recorder(&[("result", "hit")]),
+            misses: requests.recorder(&[("result", "miss")]),
+            uncacheable: requests.recorder(&[("result", "uncacheable")]),
+        }
+    }
+
+    /// Returns a plan that replays the cached results of `plan` or that caches them when it is executed.
+    ///
+    /// See [`QueryNamespace::cached_plan`](iox_query::QueryNamespace::cached_plan).
+    pub(crate) fn cached_plan(
+        &self,
+        namespace: &Arc<str>,
+        ctx: &IOxSessionContext,
+        query_type: &str,
+        query_text: &str,
+        plan: Arc<dyn ExecutionPlan>,
+    ) -> Arc<dyn ExecutionPlan> {
+        let state = ctx.inner().state();
+        let versions = state
+            .config
+            .get_extension::<QueryDataVersions>()
+            .and_then(|versions| versions.take());
+        // without a record of the functions the query calls, it may depend on when it is executed
+        let volatile = state
+            .config
+            .get_extension::<QueryVolatility>()
+            .map(|volatility| volatility.is_volatile())
+            .unwrap_or(true);
+        let Some(data) = versions.filter(|_| !volatile) else {
+            self.uncacheable.inc(1);
+            return plan;
+        };
+
+        let key = CacheKey {
+            namespace: Arc::clone(namespace),
+            query_type: query_type.to_owned(),
+            query_text: normalize_query_text(query_text),
+            data,
+        };
+
+        let cached = self.backend.lock().get(&key);
+        match cached {
+            Some(result) if result.schema == plan.schema() => {
+                debug!(%namespace, %query_text, "Query result cache hit");
+                self.hits.inc(1);
+                let exec = MemoryExec::try_new(&[result.batches.clone()], plan.schema(), None)
+                    .expect("schema checked");
+                Arc::new(exec)
+            }
+            _ => {
+                debug!(%namespace, %query_text, "Query result cache miss");
+                self.misses.inc(1);
+                let plan = match plan.output_partitioning().partition_count() {
+                    1 => plan,
+                    _ => Arc::new(CoalescePartitionsExec::new(plan)),
+                };
+                Arc::new(CacheResultExec {
+                    input: plan,
+                    backend: Arc::clone(&self.backend),
+                    key,
+                    max_result_bytes: self.max_result_bytes,
+                })
+            }
+        }
+    }
+}
+
+/// Collapse whitespace outside of quoted strings and identifiers, so that differently formatted versions of the same
+/// query share their results.
+fn normalize_query_text(query_text: &str) -> String {
+    let mut normalized = String::with_capacity(query_text.len());
+    let mut quote = None;
+    let mut pending_space = false;
+
+    for c in query_text.trim().trim_end_matches(';').trim_end().chars() {
+        match quote {
+            None if c.is_whitespace() => {
+                pending_space = true;
+                continue;
+            }
+            None if c == '\'' || c == '"' => quote = Some(c),
+            Some(q) if c == q => quote = None,
+            _ => {}
+        }
+        if pending_space {
+            normalized.push(' ');
+            pending_space = false;
+        }
+        normalized.push(c);
+    }
+
+    normalized.shrink_to_fit();
+    normalized
+}
+
+/// Passes through the results of its single input partition and caches them once the input is exhausted.
+#[derive(Debug)]
+struct CacheResultExec {
+    input: Arc<dyn ExecutionPlan>,
+    backend: Arc<Mutex<Backend>>,
+    key: CacheKey,
+    max_result_bytes: usize,
+}
+
+impl ExecutionPlan for CacheResultExec {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self) -> SchemaRef {
+        self.input.schema()
+    }
+
+    fn output_partitioning(&self) -> Partitioning {
+        Partitioning::UnknownPartitioning(1)
+    }
+
+    fn output_ordering(&self) -> Option<&[PhysicalSortExpr]> {
+        self.input.output_ordering()
+    }
+
+    fn children(&self) -> Vec<Arc<dyn ExecutionPlan>> {
+        vec![Arc::clone(&self.input)]
+    }
+
+    fn with_new_children(
+        self: Arc<Self>,
+        children: Vec<Arc<dyn ExecutionPlan>>,
+    ) -> Result<Arc<dyn ExecutionPlan>, DataFusionError> {
+        assert_eq!(children.len(), 1);
+        Ok(Arc::new(Self {
+            input: Arc::clone(&children[0]),
+            backend: Arc::clone(&self.backend),
+            key: self.key.clone(),
+            max_result_bytes: self.max_result_bytes,
+        }))
+    }
+
+    fn execute(
+        &self,
+        partition: usize,
+        context: Arc<TaskContext>,
+    ) -> Result<SendableRecordBatchStream, DataFusionError> {
+        assert_eq!(partition, 0);
+        Ok(Box::pin(CacheResultStream {
+            input: self.input.execute(0, context)?,
+            result: Some(CachedResult {
+                schema: self.input.schema(),
+                batches: vec![],
+            }),
+            result_bytes: 0,
+            backend: Arc::clone(&self.backend),
+            key: Some(self.key.clone()),
+            max_result_bytes: self.max_result_bytes,
+        }))
+    }
+
+    fn fmt_as(&self, t: DisplayFormatType, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match t {
+            DisplayFormatType::Default => write!(f, "CacheResultExec"),
+        }
+    }
+
+    fn statistics(&self) -> Statistics {
+        self.input.statistics()
+    }
+}
+
+/// Stream of [`CacheResultExec`].
+struct CacheResultStream {
+    input: SendableRecordBatchStream,
+    /// The result so far, or `None` if it is not cached because it is too large or the query failed.
+    result: Option<CachedResult>,
+    result_bytes: usize,
+    backend: Arc<Mutex<Backend>>,
+    key: Option<CacheKey>,
+    max_result_bytes: usize,
+}
+
+impl Stream for CacheResultStream {
+    type Item = ArrowResult<RecordBatch>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        let item = ready!(this.input.poll_next_unpin(cx));
+
+        match &item {
+            Some(Ok(batch)) => {
+                this.result_bytes += batch_size(batch);
+                if this.result_bytes > this.max_result_bytes {
+                    this.result = None;
+                } else if let Some(result) = this.result.as_mut() {
+                    result.batches.push(batch.clone());
+                }
+            }
+            Some(Err(_)) => this.result = None,
+            None => {
+                if let (Some(key), Some(result)) = (this.key.take(), this.result.take()) {
+                    this.backend.lock().set(key, Arc::new(result));
+                }
+            }
+        }
+
+        Poll::Ready(item)
+    }
+}
+
+impl RecordBatchStream for CacheResultStream {
+    fn schema(&self) -> SchemaRef {
+        self.input.schema()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow::array::{ArrayRef, Int64Array};
+    use datafusion::physical_plan::common::collect;
+    use iox_query::exec::{Executor, ExecutorType};
+    use iox_time::SystemProvider;
+    use metric::{Attributes, Metric};
+
+    use super::*;
+
+    fn batch(values: Vec<i64>) -> RecordBatch {
+        RecordBatch::try_from_iter(vec![("v", Arc::new(Int64Array::from(values)) as ArrayRef)])
+            .unwrap()
+    }
+
+    fn requests(registry: &metric::Registry, result: &'static str) -> u64 {
+        registry
+            .get_instrument::<Metric<U64Counter>>("querier_query_result_cache_requests")
+            .unwrap()
+            .get_observer(&Attributes::from(&[("result", result)]))
+            .unwrap()
+            .fetch()
+    }
+
+    #[test]
+    fn test_normalize_query_text() {
+        assert_eq!(
+            normalize_query_text("  SELECT *\n  FROM   cpu\tWHERE host = 'a  b' ;"),
+            "SELECT * FROM cpu WHERE host = 'a  b'"
+        );
+        assert_eq!(
+            normalize_query_text(r#"SELECT "my  field" FROM cpu WHERE host = 'it''s  x'"#),
+            r#"SELECT "my  field" FROM cpu WHERE host = 'it''s  x'"#
+        );
+    }
+
+    #[tokio::test]
+    async fn test_cached_plan() {
+        let registry = Arc::new(metric::Registry::new());
+        let cache = QueryResultCache::new(
+            Arc::new(SystemProvider::new()),
+            Arc::clone(&registry),
+            usize::MAX,
+        );
+        let namespace: Arc<str> = Arc::from("ns");
+        let input = batch(vec![1, 2]);
+        let plan = || {
+            Arc::new(MemoryExec::try_new(&[vec![input.clone()]], input.schema(), None).unwrap())
+                as Arc<dyn ExecutionPlan>
+        };
+        let exec = Executor::new_testing();
+        let ctx = || {
+            exec.new_execution_config(ExecutorType::Query)
+                .with_extension(Arc::new(QueryDataVersions::new()))
+                .with_extension(Arc::new(QueryVolatility::new()))
+                .build()
+        };
+
+        // not cached yet
+        let ctx1 = ctx();
+        let plan1 = cache.cached_plan(&namespace, &ctx1, "sql", "SELECT v FROM t", plan());
+        assert!(plan1.as_any().downcast_ref::<CacheResultExec>().is_some());
+        assert_eq!(requests(&registry, "miss"), 1);
+        let got = collect(plan1.execute(0, ctx1.inner().task_ctx()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(got, vec![input.clone()]);
+
+        // same query, formatted differently
+        let ctx2 = ctx();
+        let plan2 = cache.cached_plan(&namespace, &ctx2, "sql", "SELECT v\nFROM t;", plan());
+        assert!(plan2.as_any().downcast_ref::<MemoryExec>().is_some());
+        assert_eq!(requests(&registry, "hit"), 1);
+        let got = collect(plan2.execute(0, ctx2.inner().task_ctx()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(got, vec![input.clone()]);
+
+        // other query type
+        let ctx3 = ctx();
+        let plan3 = cache.cached_plan(&namespace, &ctx3, "influxql", "SELECT v FROM t", plan());
+        assert!(plan3.as_any().downcast_ref::<CacheResultExec>().is_some());
+        assert_eq!(requests(&registry, "miss"), 2);
+
+        // depends on the time of the query
+        let ctx4 = ctx();
+        let query_text = "SELECT now() AS t";
+        ctx4.prepare_sql(query_text).await.unwrap();
+        let plan4 = cache.cached_plan(&namespace, &ctx4, "sql", query_text, plan());
+        assert!(plan4.as_any().downcast_ref::<MemoryExec>().is_some());
+        assert_eq!(requests(&registry, "uncacheable"), 1);
+    }
+}
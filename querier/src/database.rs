@@ -1,8 +1,13 @@
 //! Database for the querier that contains all namespaces.
 
 use crate::{
-    cache::CatalogCache, ingester::IngesterConnection, namespace::QuerierNamespace,
-    parquet::ChunkAdapter, query_log::QueryLog, table::PruneMetrics,
+    cache::{query_result::QueryResultCache, CatalogCache},
+    ingester::IngesterConnection,
+    last_value::LastValueCache,
+    namespace::QuerierNamespace,
+    parquet::ChunkAdapter,
+    query_log::QueryLog,
+    table::PruneMetrics,
 };
 use async_trait::async_trait;
 use backoff::{Backoff, BackoffConfig};
@@ -17,6 +22,7 @@ use trace::span::{Span, SpanRecorder};
 use tracker::{
     AsyncSemaphoreMetrics, InstrumentedAsyncOwnedSemaphorePermit, InstrumentedAsyncSemaphore,
 };
+use uuid::Uuid;
 
 /// The number of entries to store in the circular query buffer log.
 ///
@@ -49,7 +55,6 @@ pub struct QuerierDatabase {
     chunk_adapter: Arc<ChunkAdapter>,
 
     /// Metric registry
-    #[allow(dead_code)]
     metric_registry: Arc<metric::Registry>,
 
     /// Executor for queries.
@@ -74,6 +79,12 @@ pub struct QuerierDatabase {
 
     /// Chunk prune metrics.
     prune_metrics: Arc<PruneMetrics>,
+
+    /// Last values of the series of the tables that keep them.
+    last_value_cache: Arc<LastValueCache>,
+
+    /// Results of earlier queries, if enabled.
+    query_result_cache: Option<Arc<QueryResultCache>>,
 }
 
 #[async_trait]
@@ -90,6 +101,13 @@ impl QueryNamespaceProvider for QuerierDatabase {
             .await
             .expect("Semaphore should not be closed by anyone")
     }
+
+    fn cancel_query(&self, query_id: &str) -> bool {
+        match Uuid::parse_str(query_id) {
+            Ok(query_id) => self.query_log.cancel(query_id),
+            Err(_) => false,
+        }
+    }
 }
 
 impl QuerierDatabase {
@@ -140,6 +158,7 @@ impl QuerierDatabase {
         };
 
         let prune_metrics = Arc::new(PruneMetrics::new(&metric_registry));
+        let last_value_cache = Arc::new(LastValueCache::new([], &metric_registry));
 
         Ok(Self {
             backoff_config,
@@ -152,9 +171,38 @@ impl QuerierDatabase {
             query_execution_semaphore,
             sharder,
             prune_metrics,
+            last_value_cache,
+            query_result_cache: None,
         })
     }
 
+    /// Keep the last values of the series of the given tables, as `<namespace>/<table>`.
+    pub fn with_last_value_cache_tables(self, tables: Vec<String>) -> Self {
+        let last_value_cache = Arc::new(LastValueCache::new(tables, &self.metric_registry));
+        Self {
+            last_value_cache,
+            ..self
+        }
+    }
+
+    /// Keep up to `ram_pool_bytes` of query results, which are reused by later executions of
+    /// the same query as long as the data that the query reads does not change.
+    ///
+    /// A size of 0 disables the cache.
+    pub fn with_query_result_cache(self, ram_pool_bytes: usize) -> Self {
+        let query_result_cache = (ram_pool_bytes > 0).then(|| {
+            Arc::new(QueryResultCache::new(
+                self.catalog_cache.time_provider(),
+                Arc::clone(&self.metric_registry),
+                ram_pool_bytes,
+            ))
+        });
+        Self {
+            query_result_cache,
+            ..self
+        }
+    }
+
     /// Get namespace if it exists.
     ///
     /// This will await the internal namespace semaphore. Existence of namespaces is checked AFTER
@@ -172,16 +220,20 @@ impl QuerierDatabase {
                 span_recorder.child_span("cache GET namespace schema"),
             )
             .await?;
-        Some(Arc::new(QuerierNamespace::new(
-            Arc::clone(&self.chunk_adapter),
-            ns,
-            name,
-            Arc::clone(&self.exec),
-            self.ingester_connection.clone(),
-            Arc::clone(&self.query_log),
-            self.sharder.clone(),
-            Arc::clone(&self.prune_metrics),
-        )))
+        Some(Arc::new(
+            QuerierNamespace::new(
+                Arc::clone(&self.chunk_adapter),
+                ns,
+                name,
+                Arc::clone(&self.exec),
+                self.ingester_connection.clone(),
+                Arc::clone(&self.query_log),
+                self.sharder.clone(),
+                Arc::clone(&self.prune_metrics),
+                Arc::clone(&self.last_value_cache),
+            )
+            .with_query_result_cache(self.query_result_cache.clone()),
+        ))
     }
 
     /// Return all namespaces this querier knows about
@@ -0,0 +1,704 @@
+//! Continuous downsampling.
+//!
+//! The queries of the [`DownsamplingTask`]s in the catalog are run for
+//! consecutive, closed time windows and their results are written into a
+//! table of another namespace, usually at a coarser resolution.
+use std::{fmt::Debug, sync::Arc, time::Duration};
+
+use arrow::{
+    array::{
+        Array, ArrayRef, BooleanArray, DictionaryArray, Float64Array, Int64Array, StringArray,
+        TimestampNanosecondArray, UInt64Array,
+    },
+    datatypes::{DataType, Int32Type, TimeUnit},
+    record_batch::RecordBatch,
+};
+use async_trait::async_trait;
+use data_types::{
+    DownsamplingTask, NamespaceId, QueryLanguage, Timestamp, DOWNSAMPLING_WINDOW_END,
+    DOWNSAMPLING_WINDOW_START,
+};
+use datafusion::error::DataFusionError;
+use futures::future::{AbortHandle, Abortable};
+use influxdb_iox_client::{
+    connection::{self, Connection},
+    downsampling,
+};
+use iox_catalog::interface::{CasFailure, Catalog};
+use iox_query::{exec::ExecutionContextProvider, QueryNamespace};
+use iox_time::{Time, TimeProvider};
+use metric::U64Counter;
+use mutable_batch::{writer::Writer, MutableBatch};
+use mutable_batch_pb::encode::encode_batch;
+use observability_deps::tracing::*;
+use schema::TIME_COLUMN_NAME;
+use service_common::{planner::Planner, QueryNamespaceProvider};
+use snafu::{ensure, OptionExt, ResultExt, Snafu};
+use tokio::{sync::Mutex, time::MissedTickBehavior};
+use tokio_util::sync::CancellationToken;
+
+/// The maximum number of windows run for a single task per tick, so that a
+/// large backfill does not hold up the other tasks.
+const MAX_WINDOWS_PER_TICK: usize = 100;
+
+/// Error returned by a [`DownsamplingSink`].
+pub type SinkError = Box<dyn std::error::Error + Send + Sync>;
+
+#[derive(Debug, Snafu)]
+enum Error {
+    #[snafu(display("Catalog error: {source}"))]
+    Catalog {
+        source: iox_catalog::interface::Error,
+    },
+
+    #[snafu(display("Namespace {id} of the task not found"))]
+    NamespaceNotFound { id: NamespaceId },
+
+    #[snafu(display("Query does not refer to {placeholder}"))]
+    MissingPlaceholder { placeholder: &'static str },
+
+    #[snafu(display("Error planning query: {source}"))]
+    Planning { source: DataFusionError },
+
+    #[snafu(display("Error running query: {source}"))]
+    Execution { source: DataFusionError },
+
+    #[snafu(display("Query cancelled"))]
+    Cancelled,
+
+    #[snafu(display("Unsupported type {data_type} of result column {name}"))]
+    UnsupportedColumn { name: String, data_type: DataType },
+
+    #[snafu(display("Error converting query results: {source}"))]
+    Conversion {
+        source: mutable_batch::writer::Error,
+    },
+
+    #[snafu(display("Error writing results to namespace {namespace}: {source}"))]
+    Write {
+        namespace: String,
+        source: SinkError,
+    },
+}
+
+/// Destination of the results of the windows of downsampling tasks.
+#[async_trait]
+pub trait DownsamplingSink: Debug + Send + Sync {
+    /// Write `batch` into `table` of `namespace`.
+    async fn write(
+        &self,
+        namespace: &str,
+        table: &str,
+        batch: MutableBatch,
+    ) -> Result<(), SinkError>;
+}
+
+/// A [`DownsamplingSink`] writing [`MutableBatch`]es to the downsampling
+/// write gRPC API of a router, so that results are validated and partitioned
+/// like any other write.
+#[derive(Debug)]
+pub struct RouterDownsamplingSink {
+    router_address: Arc<str>,
+    connection: Mutex<Option<Connection>>,
+}
+
+impl RouterDownsamplingSink {
+    /// Create a sink writing to the router at `router_address`, which is
+    /// connected to on first use.
+    pub fn new(router_address: impl Into<Arc<str>>) -> Self {
+        Self {
+            router_address: router_address.into(),
+            connection: Mutex::new(None),
+        }
+    }
+
+    /// Return the connection to the router, creating it if needed.
+    async fn connect(&self) -> Result<Connection, connection::Error> {
+        let mut connection = self.connection.lock().await;
+        if let Some(connection) = connection.as_ref() {
+            return Ok(connection.clone());
+        }
+
+        let router_address = self.router_address.as_ref();
+        debug!(%router_address, "Connecting to router");
+        let new = connection::Builder::new().build(router_address).await?;
+        *connection = Some(new.clone());
+        Ok(new)
+    }
+}
+
+#[async_trait]
+impl DownsamplingSink for RouterDownsamplingSink {
+    async fn write(
+        &self,
+        namespace: &str,
+        table: &str,
+        batch: MutableBatch,
+    ) -> Result<(), SinkError> {
+        let connection = self.connect().await?;
+        downsampling::Client::new(connection)
+            .write_window(namespace, table, encode_batch(0, &batch))
+            .await?;
+
+        Ok(())
+    }
+}
+
+/// Periodically runs the windows of all [`DownsamplingTask`]s that are due.
+///
+/// A window `[watermark, watermark + every)` is due once `delay` has passed
+/// after its end. Once the results of the window are written, the watermark
+/// of the task is advanced past it with a compare-and-swap in the catalog.
+/// If running the window or writing its results fails, or the querier stops
+/// before the watermark is advanced, the window is run again.
+///
+/// Windows are therefore written at least once, and several queriers may
+/// write the same window. Writing a window again writes the same rows, which
+/// replace the earlier ones under the default `upsert` deduplication policy,
+/// so every window is stored exactly once unless the target table is
+/// `append_only`.
+#[derive(Debug)]
+pub struct DownsamplingScheduler<D> {
+    catalog: Arc<dyn Catalog>,
+    database: Arc<D>,
+    sink: Arc<dyn DownsamplingSink>,
+    time_provider: Arc<dyn TimeProvider>,
+    interval: Duration,
+    windows_ok: U64Counter,
+    windows_error: U64Counter,
+    windows_conflict: U64Counter,
+}
+
+impl<D> DownsamplingScheduler<D>
+where
+    D: QueryNamespaceProvider,
+{
+    /// Create a scheduler running the tasks in `catalog` against `database`
+    /// every `interval`.
+    pub fn new(
+        catalog: Arc<dyn Catalog>,
+        database: Arc<D>,
+        sink: Arc<dyn DownsamplingSink>,
+        time_provider: Arc<dyn TimeProvider>,
+        interval: Duration,
+        metric_registry: &metric::Registry,
+    ) -> Self {
+        let windows = metric_registry.register_metric::<U64Counter>(
+            "querier_downsampling_windows",
+            "Number of windows of downsampling tasks run",
+        );
+
+        Self {
+            catalog,
+            database,
+            sink,
+            time_provider,
+            interval,
+            windows_ok: windows.recorder(&[("result", "success")]),
+            windows_error: windows.recorder(&[("result", "error")]),
+            windows_conflict: windows.recorder(&[("result", "conflict")]),
+        }
+    }
+
+    /// Run the windows that are due every `interval`, until `shutdown` is
+    /// cancelled.
+    pub async fn run(self, shutdown: CancellationToken) {
+        let mut interval = tokio::time::interval(self.interval);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            tokio::select! {
+                _ = shutdown.cancelled() => return,
+                _ = interval.tick() => self.run_due_windows().await,
+            }
+        }
+    }
+
+    /// Run the windows of all tasks that are due.
+    async fn run_due_windows(&self) {
+        let tasks = self
+            .catalog
+            .repositories()
+            .await
+            .downsampling_tasks()
+            .list()
+            .await;
+        let tasks = match tasks {
+            Ok(tasks) => tasks,
+            Err(e) => {
+                warn!(error=%e, "failed to list downsampling tasks");
+                return;
+            }
+        };
+
+        for task in tasks {
+            self.run_task(&task).await;
+        }
+    }
+
+    /// Run the windows of `task` that are due, advancing its watermark after
+    /// each.
+    async fn run_task(&self, task: &DownsamplingTask) {
+        let now = self.time_provider.now().timestamp_nanos();
+        let mut watermark = task.watermark;
+
+        for _ in 0..MAX_WINDOWS_PER_TICK {
+            let Some(end) = due_window_end(watermark.get(), task.every_ns, task.delay_ns, now) else {
+                return;
+            };
+            let start = watermark;
+
+            let rows = match self.run_window(task, start.get(), end).await {
+                Ok(rows) => rows,
+                Err(e) => {
+                    warn!(error=%e, task=%task.name, start=start.get(), end, "failed to run downsampling window");
+                    self.windows_error.inc(1);
+                    return;
+                }
+            };
+
+            // Only advance the watermark once the results are written, so
+            // that the window is run again if anything fails before.
+            let res = self
+                .catalog
+                .repositories()
+                .await
+                .downsampling_tasks()
+                .cas_watermark(task.id, start, Timestamp::new(end))
+                .await;
+            match res {
+                Ok(_) => {
+                    debug!(task=%task.name, start=start.get(), end, rows, "ran downsampling window");
+                    self.windows_ok.inc(1);
+                    watermark = Timestamp::new(end);
+                }
+                Err(CasFailure::ValueMismatch(current)) => {
+                    // Written by someone else or backfilled in the meantime,
+                    // pick up from the new watermark on the next tick.
+                    info!(task=%task.name, start=start.get(), current=current.get(), "downsampling task watermark changed concurrently");
+                    self.windows_conflict.inc(1);
+                    return;
+                }
+                Err(CasFailure::QueryError(e)) => {
+                    warn!(error=%e, task=%task.name, start=start.get(), end, "failed to advance downsampling task watermark");
+                    self.windows_error.inc(1);
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Run the query of `task` for the window `[start, end)` and write its
+    /// results, returning the number of rows written.
+    async fn run_window(
+        &self,
+        task: &DownsamplingTask,
+        start: i64,
+        end: i64,
+    ) -> Result<usize, Error> {
+        let namespace = self
+            .catalog
+            .repositories()
+            .await
+            .namespaces()
+            .get_by_id(task.namespace_id)
+            .await
+            .context(CatalogSnafu)?
+            .context(NamespaceNotFoundSnafu {
+                id: task.namespace_id,
+            })?;
+        let db = self
+            .database
+            .db(&namespace.name, None)
+            .await
+            .context(NamespaceNotFoundSnafu {
+                id: task.namespace_id,
+            })?;
+
+        let query = window_query(&task.query, start, end)?;
+        let ctx = db.new_query_context(None);
+        let query_type = match task.query_language {
+            QueryLanguage::Sql => "sql",
+            QueryLanguage::InfluxQl => "influxql",
+        };
+        let mut token = db.record_query(&ctx, query_type, Box::new(query.clone()));
+
+        // queries that can not be cancelled get a registration that is never aborted
+        let abort_registration = token
+            .take_abort_registration()
+            .unwrap_or_else(|| AbortHandle::new_pair().1);
+        let batches = Abortable::new(
+            async {
+                let planner = Planner::new(&ctx);
+                let plan = match task.query_language {
+                    QueryLanguage::Sql => planner.sql(query).await,
+                    QueryLanguage::InfluxQl => planner.influxql(Arc::clone(&db) as _, query).await,
+                }
+                .context(PlanningSnafu)?;
+                token.set_planned();
+
+                ctx.collect(plan).await.context(ExecutionSnafu)
+            },
+            abort_registration,
+        )
+        .await
+        .map_err(|_| Error::Cancelled)??;
+        token.set_success();
+
+        let batch = to_mutable_batch(&batches, start)?;
+        let rows = batch.rows();
+        if rows > 0 {
+            self.sink
+                .write(&task.target_namespace, &task.target_table, batch)
+                .await
+                .context(WriteSnafu {
+                    namespace: &task.target_namespace,
+                })?;
+        }
+
+        Ok(rows)
+    }
+}
+
+/// Return the end of the window starting at `watermark` if it is due at
+/// `now`.
+fn due_window_end(watermark: i64, every: i64, delay: i64, now: i64) -> Option<i64> {
+    let end = watermark.checked_add(every)?;
+    (end.checked_add(delay)? <= now).then_some(end)
+}
+
+/// Substitute the bounds of the window `[start, end)` into `query`, which
+/// must refer to both.
+fn window_query(query: &str, start: i64, end: i64) -> Result<String, Error> {
+    for placeholder in [DOWNSAMPLING_WINDOW_START, DOWNSAMPLING_WINDOW_END] {
+        ensure!(
+            query.contains(placeholder),
+            MissingPlaceholderSnafu { placeholder }
+        );
+    }
+
+    let quote = |ts| format!("'{}'", Time::from_timestamp_nanos(ts).to_rfc3339());
+    Ok(query
+        .replace(DOWNSAMPLING_WINDOW_START, &quote(start))
+        .replace(DOWNSAMPLING_WINDOW_END, &quote(end)))
+}
+
+/// Convert the results of the window starting at `window_start` into a
+/// [`MutableBatch`].
+///
+/// Dictionary encoded string columns become tags, all other supported
+/// columns fields. Rows without a timestamp are written at the start of the
+/// window.
+fn to_mutable_batch(batches: &[RecordBatch], window_start: i64) -> Result<MutableBatch, Error> {
+    let mut out = MutableBatch::new();
+
+    for batch in batches {
+        let rows = batch.num_rows();
+        if rows == 0 {
+            continue;
+        }
+
+        let schema = batch.schema();
+        let mut writer = Writer::new(&mut out, rows);
+        let mut has_time = false;
+        for (field, array) in schema.fields().iter().zip(batch.columns()) {
+            let name = field.name().as_str();
+            let mask = valid_mask(array.as_ref());
+            let mask = mask.as_deref();
+
+            match array.data_type() {
+                DataType::Timestamp(TimeUnit::Nanosecond, _) if name == TIME_COLUMN_NAME => {
+                    let array = downcast::<TimestampNanosecondArray>(array);
+                    writer
+                        .write_time(name, array.iter().map(|v| v.unwrap_or(window_start)))
+                        .context(ConversionSnafu)?;
+                    has_time = true;
+                }
+                DataType::Dictionary(key, value)
+                    if **key == DataType::Int32 && **value == DataType::Utf8 =>
+                {
+                    let array = downcast::<DictionaryArray<Int32Type>>(array);
+                    let values = downcast::<StringArray>(array.values());
+                    writer
+                        .write_tag(
+                            name,
+                            mask,
+                            array
+                                .keys()
+                                .iter()
+                                .flatten()
+                                .map(|k| values.value(k as usize)),
+                        )
+                        .context(ConversionSnafu)?;
+                }
+                DataType::Utf8 => writer
+                    .write_string(name, mask, downcast::<StringArray>(array).iter().flatten())
+                    .context(ConversionSnafu)?,
+                DataType::Float64 => writer
+                    .write_f64(name, mask, downcast::<Float64Array>(array).iter().flatten())
+                    .context(ConversionSnafu)?,
+                DataType::Int64 => writer
+                    .write_i64(name, mask, downcast::<Int64Array>(array).iter().flatten())
+                    .context(ConversionSnafu)?,
+                DataType::UInt64 => writer
+                    .write_u64(name, mask, downcast::<UInt64Array>(array).iter().flatten())
+                    .context(ConversionSnafu)?,
+                DataType::Boolean => writer
+                    .write_bool(name, mask, downcast::<BooleanArray>(array).iter().flatten())
+                    .context(ConversionSnafu)?,
+                data_type => {
+                    return UnsupportedColumnSnafu {
+                        name,
+                        data_type: data_type.clone(),
+                    }
+                    .fail()
+                }
+            }
+        }
+
+        if !has_time {
+            writer
+                .write_time(TIME_COLUMN_NAME, std::iter::repeat(window_start).take(rows))
+                .context(ConversionSnafu)?;
+        }
+        writer.commit();
+    }
+
+    Ok(out)
+}
+
+fn downcast<T: 'static>(array: &ArrayRef) -> &T {
+    array
+        .as_any()
+        .downcast_ref::<T>()
+        .expect("array type matches its data type")
+}
+
+/// The bitset of the valid (non-null) rows of `array` the [`Writer`]
+/// expects, or [`None`] if all rows are valid.
+fn valid_mask(array: &dyn Array) -> Option<Vec<u8>> {
+    if array.null_count() == 0 {
+        return None;
+    }
+
+    let mut mask = vec![0u8; (array.len() + 7) / 8];
+    for i in (0..array.len()).filter(|&i| array.is_valid(i)) {
+        mask[i / 8] |= 1 << (i % 8);
+    }
+    Some(mask)
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow::datatypes::{Field, Schema};
+    use arrow_util::{assert_batches_eq, assert_batches_sorted_eq};
+    use data_types::DownsamplingTaskParams;
+    use iox_query::test::{TestChunk, TestDatabase};
+    use iox_tests::util::TestCatalog;
+    use iox_time::MockProvider;
+    use parking_lot::Mutex as SyncMutex;
+    use schema::Projection;
+    use service_common::test_util::TestDatabaseStore;
+
+    use super::*;
+
+    const HOUR: i64 = 3_600_000_000_000;
+
+    #[derive(Debug, Default)]
+    struct MockSink {
+        writes: SyncMutex<Vec<(String, String, RecordBatch)>>,
+    }
+
+    #[async_trait]
+    impl DownsamplingSink for MockSink {
+        async fn write(
+            &self,
+            namespace: &str,
+            table: &str,
+            batch: MutableBatch,
+        ) -> Result<(), SinkError> {
+            self.writes.lock().push((
+                namespace.to_string(),
+                table.to_string(),
+                batch.to_arrow(Projection::All)?,
+            ));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_due_window_end() {
+        assert_eq!(due_window_end(0, 10, 0, 9), None);
+        assert_eq!(due_window_end(0, 10, 0, 10), Some(10));
+        assert_eq!(due_window_end(0, 10, 5, 14), None);
+        assert_eq!(due_window_end(0, 10, 5, 15), Some(10));
+        assert_eq!(due_window_end(i64::MAX - 1, 10, 0, i64::MAX), None);
+    }
+
+    #[test]
+    fn test_window_query() {
+        assert_eq!(
+            window_query(
+                "SELECT * FROM cpu WHERE time >= $window_start AND time < $window_end",
+                0,
+                HOUR
+            )
+            .unwrap(),
+            "SELECT * FROM cpu WHERE time >= '1970-01-01T00:00:00+00:00' \
+             AND time < '1970-01-01T01:00:00+00:00'"
+        );
+
+        let err =
+            window_query("SELECT * FROM cpu WHERE time >= $window_start", 0, HOUR).unwrap_err();
+        assert_eq!(err.to_string(), "Query does not refer to $window_end");
+    }
+
+    #[test]
+    fn test_to_mutable_batch() {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new(
+                "host",
+                DataType::Dictionary(Box::new(DataType::Int32), Box::new(DataType::Utf8)),
+                true,
+            ),
+            Field::new("mean", DataType::Float64, true),
+            Field::new("count", DataType::Int64, true),
+            Field::new("up", DataType::Boolean, true),
+        ]));
+        let host: DictionaryArray<Int32Type> =
+            vec![Some("a"), None, Some("b")].into_iter().collect();
+        let batch = RecordBatch::try_new(
+            schema,
+            vec![
+                Arc::new(host),
+                Arc::new(Float64Array::from(vec![Some(1.5), Some(2.5), None])),
+                Arc::new(Int64Array::from(vec![1, 2, 3])),
+                Arc::new(BooleanArray::from(vec![None, Some(true), Some(false)])),
+            ],
+        )
+        .unwrap();
+
+        let out = to_mutable_batch(&[batch], HOUR).unwrap();
+        assert_batches_eq!(
+            &[
+                "+-------+------+------+----------------------+-------+",
+                "| count | host | mean | time                 | up    |",
+                "+-------+------+------+----------------------+-------+",
+                "| 1     | a    | 1.5  | 1970-01-01T01:00:00Z |       |",
+                "| 2     |      | 2.5  | 1970-01-01T01:00:00Z | true  |",
+                "| 3     | b    |      | 1970-01-01T01:00:00Z | false |",
+                "+-------+------+------+----------------------+-------+",
+            ],
+            &[out.to_arrow(Projection::All).unwrap()]
+        );
+    }
+
+    #[test]
+    fn test_to_mutable_batch_unsupported() {
+        let schema = Arc::new(Schema::new(vec![Field::new("v", DataType::Int32, false)]));
+        let batch = RecordBatch::try_new(
+            schema,
+            vec![Arc::new(arrow::array::Int32Array::from(vec![1]))],
+        )
+        .unwrap();
+
+        let err = to_mutable_batch(&[batch], 0).unwrap_err();
+        assert!(matches!(err, Error::UnsupportedColumn { .. }), "{err}");
+    }
+
+    #[tokio::test]
+    async fn test_run_due_windows() {
+        let catalog = TestCatalog::new();
+        let ns = catalog.create_namespace_1hr_retention("ns").await;
+        let time_provider = Arc::new(MockProvider::new(Time::from_timestamp_nanos(3 * HOUR + 10)));
+
+        let store = Arc::new(TestDatabaseStore::new());
+        let db: Arc<TestDatabase> = store.db_or_create("ns").await;
+        db.add_chunk(
+            "p1",
+            Arc::new(
+                TestChunk::new("cpu")
+                    .with_time_column()
+                    .with_tag_column("tag1")
+                    .with_i64_field_column("field_int")
+                    .with_five_rows_of_data(),
+            ),
+        );
+
+        let task = catalog
+            .catalog()
+            .repositories()
+            .await
+            .downsampling_tasks()
+            .create(DownsamplingTaskParams {
+                name: "cpu_1h".to_string(),
+                namespace_id: ns.namespace.id,
+                query: "SELECT tag1, count(*) AS n FROM cpu \
+                        WHERE time >= $window_start AND time < $window_end GROUP BY tag1"
+                    .to_string(),
+                query_language: QueryLanguage::Sql,
+                target_namespace: "rollups".to_string(),
+                target_table: "cpu_1h".to_string(),
+                every_ns: HOUR,
+                delay_ns: 0,
+                watermark: Timestamp::new(HOUR),
+            })
+            .await
+            .unwrap();
+
+        let sink = Arc::new(MockSink::default());
+        let scheduler = DownsamplingScheduler::new(
+            catalog.catalog(),
+            store,
+            Arc::clone(&sink) as _,
+            Arc::clone(&time_provider) as _,
+            Duration::from_secs(1),
+            &catalog.metric_registry(),
+        );
+
+        // Two windows are due, [1h, 2h) and [2h, 3h).
+        scheduler.run_due_windows().await;
+        let task = catalog
+            .catalog()
+            .repositories()
+            .await
+            .downsampling_tasks()
+            .get_by_name(&task.name)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(task.watermark, Timestamp::new(3 * HOUR));
+
+        // The test chunk has no data in these windows.
+        assert!(sink.writes.lock().is_empty());
+
+        // Backfilled from the start of the data, all of which is in [0, 1h).
+        catalog
+            .catalog()
+            .repositories()
+            .await
+            .downsampling_tasks()
+            .set_watermark(task.id, Timestamp::new(0))
+            .await
+            .unwrap();
+        scheduler.run_due_windows().await;
+
+        let writes = sink.writes.lock();
+        assert_eq!(writes.len(), 1);
+        let (namespace, table, batch) = &writes[0];
+        assert_eq!(namespace, "rollups");
+        assert_eq!(table, "cpu_1h");
+        assert_batches_sorted_eq!(
+            &[
+                "+---+------+----------------------+",
+                "| n | tag1 | time                 |",
+                "+---+------+----------------------+",
+                "| 1 | AL   | 1970-01-01T00:00:00Z |",
+                "| 1 | CT   | 1970-01-01T00:00:00Z |",
+                "| 3 | MT   | 1970-01-01T00:00:00Z |",
+                "+---+------+----------------------+",
+            ],
+            &[batch.clone()]
+        );
+    }
+}
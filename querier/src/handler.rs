@@ -22,7 +22,9 @@ use thiserror::Error;
 use tokio::task::{JoinError, JoinHandle};
 use tokio_util::sync::CancellationToken;
 
-use crate::{database::QuerierDatabase, poison::PoisonCabinet};
+use crate::{
+    database::QuerierDatabase, downsampling::DownsamplingScheduler, poison::PoisonCabinet,
+};
 
 #[derive(Debug, Error)]
 #[allow(missing_copy_implementations, missing_docs)]
@@ -53,7 +55,6 @@ pub trait QuerierHandler: Send + Sync {
 type SharedJoinHandle = Shared<BoxFuture<'static, Result<(), Arc<JoinError>>>>;
 
 /// Convert a [`JoinHandle`] into a [`SharedJoinHandle`].
-#[allow(dead_code)]
 fn shared_handle(handle: JoinHandle<()>) -> SharedJoinHandle {
     handle.map_err(Arc::new).boxed().shared()
 }
@@ -101,6 +102,15 @@ impl QuerierHandlerImpl {
             poison_cabinet,
         }
     }
+
+    /// Run the windows of the downsampling tasks in the catalog with
+    /// `scheduler` until shut down.
+    pub fn with_downsampling(mut self, scheduler: DownsamplingScheduler<QuerierDatabase>) -> Self {
+        let handle = tokio::spawn(scheduler.run(self.shutdown.clone()));
+        self.join_handles
+            .push(("downsampling".to_string(), shared_handle(handle)));
+        self
+    }
 }
 
 #[async_trait]
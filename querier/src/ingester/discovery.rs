@@ -0,0 +1,218 @@
+//! Runtime discovery of the ingesters the querier talks to.
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Debug,
+    sync::Arc,
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use data_types::{IngesterMapping, ShardIndex};
+use observability_deps::tracing::{info, warn};
+use tokio::sync::watch;
+
+/// Error returned by an [`IngesterDiscovery`].
+pub type DiscoveryError = Box<dyn std::error::Error + Send + Sync>;
+
+/// The set of ingesters known to the querier at a given point in time.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IngesterTopology {
+    shard_to_ingesters: HashMap<ShardIndex, IngesterMapping>,
+    unique_ingester_addresses: HashSet<Arc<str>>,
+}
+
+impl IngesterTopology {
+    /// Topology for the write buffer path, given a map of shard indexes to ingester addresses.
+    pub fn by_shard(shard_to_ingesters: HashMap<ShardIndex, IngesterMapping>) -> Self {
+        let unique_ingester_addresses = shard_to_ingesters
+            .values()
+            .flat_map(|v| match v {
+                IngesterMapping::Addr(addr) => Some(addr),
+                _ => None,
+            })
+            .cloned()
+            .collect();
+
+        Self {
+            shard_to_ingesters,
+            unique_ingester_addresses,
+        }
+    }
+
+    /// Topology for the RPC write path, given a list of ingester2 addresses.
+    pub fn by_addrs(ingester_addresses: impl IntoIterator<Item = Arc<str>>) -> Self {
+        Self {
+            shard_to_ingesters: HashMap::new(),
+            unique_ingester_addresses: ingester_addresses.into_iter().collect(),
+        }
+    }
+
+    /// Mapping of shard indexes to ingesters. Empty for the RPC write path.
+    pub fn shard_to_ingesters(&self) -> &HashMap<ShardIndex, IngesterMapping> {
+        &self.shard_to_ingesters
+    }
+
+    /// Addresses of all known ingesters.
+    pub fn unique_ingester_addresses(&self) -> &HashSet<Arc<str>> {
+        &self.unique_ingester_addresses
+    }
+}
+
+/// A source that tells the querier which ingesters currently exist.
+///
+/// Sources are polled periodically, see [`watch_ingester_topology`].
+#[async_trait]
+pub trait IngesterDiscovery: Debug + Send + Sync + 'static {
+    /// Return the current ingester topology.
+    async fn discover(&self) -> Result<IngesterTopology, DiscoveryError>;
+}
+
+/// Discovers ingester2 instances by resolving a DNS name (e.g. a headless Kubernetes service) to all its A/AAAA
+/// records.
+#[derive(Debug)]
+pub struct DnsIngesterDiscovery {
+    /// `host:port`
+    name: String,
+}
+
+impl DnsIngesterDiscovery {
+    /// Create discovery for `name`, which must be of the form `host:port`.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self { name: name.into() }
+    }
+}
+
+#[async_trait]
+impl IngesterDiscovery for DnsIngesterDiscovery {
+    async fn discover(&self) -> Result<IngesterTopology, DiscoveryError> {
+        let addrs = tokio::net::lookup_host(self.name.as_str()).await?;
+
+        Ok(IngesterTopology::by_addrs(
+            addrs.map(|addr| Arc::from(format!("http://{addr}"))),
+        ))
+    }
+}
+
+/// Poll `discovery` every `interval` and publish every change of the topology to the returned receiver.
+///
+/// The receiver starts out with `initial`. Failed discovery attempts are logged and keep the last known topology.
+/// Polling stops once all receivers are dropped.
+pub fn watch_ingester_topology(
+    discovery: Arc<dyn IngesterDiscovery>,
+    initial: IngesterTopology,
+    interval: Duration,
+) -> watch::Receiver<Arc<IngesterTopology>> {
+    let (tx, rx) = watch::channel(Arc::new(initial));
+
+    tokio::spawn(async move {
+        loop {
+            match discovery.discover().await {
+                Ok(topology) => {
+                    tx.send_if_modified(|current| {
+                        if current.as_ref() == &topology {
+                            return false;
+                        }
+
+                        info!(
+                            ?discovery,
+                            ingesters=?topology.unique_ingester_addresses(),
+                            "ingester topology changed",
+                        );
+                        *current = Arc::new(topology);
+                        true
+                    });
+                }
+                Err(e) => {
+                    warn!(
+                        ?discovery,
+                        %e,
+                        "ingester discovery failed, keeping last known topology",
+                    );
+                }
+            }
+
+            tokio::select! {
+                _ = tx.closed() => return,
+                _ = tokio::time::sleep(interval) => {},
+            }
+        }
+    });
+
+    rx
+}
+
+#[cfg(test)]
+mod tests {
+    use parking_lot::Mutex;
+
+    use super::*;
+
+    #[derive(Debug)]
+    struct MockDiscovery {
+        results: Mutex<Vec<Result<IngesterTopology, String>>>,
+    }
+
+    #[async_trait]
+    impl IngesterDiscovery for MockDiscovery {
+        async fn discover(&self) -> Result<IngesterTopology, DiscoveryError> {
+            let mut results = self.results.lock();
+            if results.len() > 1 {
+                results.remove(0).map_err(Into::into)
+            } else {
+                results[0].clone().map_err(Into::into)
+            }
+        }
+    }
+
+    fn addrs(addrs: &[&str]) -> IngesterTopology {
+        IngesterTopology::by_addrs(addrs.iter().map(|a| Arc::from(*a)))
+    }
+
+    #[tokio::test]
+    async fn test_watch() {
+        let discovery = Arc::new(MockDiscovery {
+            results: Mutex::new(vec![
+                Ok(addrs(&["a", "b"])),
+                Err(String::from("boom")),
+                Ok(addrs(&["b", "c"])),
+            ]),
+        });
+
+        let mut rx = watch_ingester_topology(
+            Arc::clone(&discovery) as _,
+            addrs(&["a"]),
+            Duration::from_millis(1),
+        );
+
+        rx.changed().await.unwrap();
+        assert_eq!(rx.borrow().as_ref(), &addrs(&["a", "b"]));
+
+        // the error is skipped
+        rx.changed().await.unwrap();
+        assert_eq!(rx.borrow().as_ref(), &addrs(&["b", "c"]));
+
+        // the watcher stops once the receiver is gone
+        drop(rx);
+        tokio::time::timeout(Duration::from_secs(10), async {
+            while Arc::strong_count(&discovery) > 1 {
+                tokio::time::sleep(Duration::from_millis(1)).await;
+            }
+        })
+        .await
+        .unwrap();
+    }
+
+    #[test]
+    fn test_by_shard() {
+        let topology = IngesterTopology::by_shard(HashMap::from([
+            (ShardIndex::new(1), IngesterMapping::Addr(Arc::from("a"))),
+            (ShardIndex::new(2), IngesterMapping::Addr(Arc::from("a"))),
+            (ShardIndex::new(3), IngesterMapping::Ignore),
+        ]));
+        assert_eq!(
+            topology.unique_ingester_addresses(),
+            &HashSet::from([Arc::from("a")]),
+        );
+        assert_eq!(topology.shard_to_ingesters().len(), 3);
+    }
+}
@@ -1,5 +1,6 @@
 use self::{
     circuit_breaker::CircuitBreakerFlightClient,
+    discovery::{watch_ingester_topology, IngesterDiscovery, IngesterTopology},
     flight_client::{
         Error as FlightClientError, FlightClientImpl, FlightError, IngesterFlightClient,
     },
@@ -40,10 +41,12 @@ use std::{
     sync::Arc,
     time::Duration,
 };
+use tokio::sync::watch;
 use trace::span::{Span, SpanRecorder};
 use uuid::Uuid;
 
 mod circuit_breaker;
+pub(crate) mod discovery;
 pub(crate) mod flight_client;
 pub(crate) mod test_util;
 
@@ -154,23 +157,6 @@ pub fn create_ingester_connections(
     catalog_cache: Arc<CatalogCache>,
     open_circuit_after_n_errors: u64,
 ) -> Arc<dyn IngesterConnection> {
-    // This backoff config is used to retry requests for a specific table-scoped query.
-    let retry_backoff_config = BackoffConfig {
-        init_backoff: Duration::from_millis(100),
-        max_backoff: Duration::from_secs(1),
-        base: 3.0,
-        deadline: Some(Duration::from_secs(10)),
-    };
-
-    // This backoff config is used to half-open the circuit after it was opened. Circuits are
-    // ingester-scoped.
-    let circuit_breaker_backoff_config = BackoffConfig {
-        init_backoff: Duration::from_secs(1),
-        max_backoff: Duration::from_secs(60),
-        base: 3.0,
-        deadline: None,
-    };
-
     // Exactly one of `shard_to_ingesters` or `ingester_addreses` must be specified.
     // `shard_to_ingesters` uses the Kafka write buffer path.
     // `ingester_addresses` uses the RPC write path.
@@ -182,20 +168,64 @@ pub fn create_ingester_connections(
         (Some(shard_to_ingesters), None) => Arc::new(IngesterConnectionImpl::by_shard(
             shard_to_ingesters,
             catalog_cache,
-            retry_backoff_config,
-            circuit_breaker_backoff_config,
+            retry_backoff_config(),
+            circuit_breaker_backoff_config(),
             open_circuit_after_n_errors,
         )),
         (None, Some(ingester_addresses)) => Arc::new(IngesterConnectionImpl::by_addrs(
             ingester_addresses,
             catalog_cache,
-            retry_backoff_config,
-            circuit_breaker_backoff_config,
+            retry_backoff_config(),
+            circuit_breaker_backoff_config(),
             open_circuit_after_n_errors,
         )),
     }
 }
 
+/// Create a new set of connections whose ingesters are updated at runtime.
+///
+/// The connection starts out with the `initial` topology and polls `discovery` every
+/// `discovery_interval`. Queries that are already running keep using the topology they started
+/// with.
+pub fn create_ingester_connections_with_discovery(
+    discovery: Arc<dyn IngesterDiscovery>,
+    initial: IngesterTopology,
+    discovery_interval: Duration,
+    catalog_cache: Arc<CatalogCache>,
+    open_circuit_after_n_errors: u64,
+) -> Arc<dyn IngesterConnection> {
+    Arc::new(IngesterConnectionImpl::by_discovery(
+        discovery,
+        initial,
+        discovery_interval,
+        catalog_cache,
+        retry_backoff_config(),
+        circuit_breaker_backoff_config(),
+        open_circuit_after_n_errors,
+    ))
+}
+
+/// This backoff config is used to retry requests for a specific table-scoped query.
+fn retry_backoff_config() -> BackoffConfig {
+    BackoffConfig {
+        init_backoff: Duration::from_millis(100),
+        max_backoff: Duration::from_secs(1),
+        base: 3.0,
+        deadline: Some(Duration::from_secs(10)),
+    }
+}
+
+/// This backoff config is used to half-open the circuit after it was opened. Circuits are
+/// ingester-scoped.
+fn circuit_breaker_backoff_config() -> BackoffConfig {
+    BackoffConfig {
+        init_backoff: Duration::from_secs(1),
+        max_backoff: Duration::from_secs(60),
+        base: 3.0,
+        deadline: None,
+    }
+}
+
 /// Create a new ingester suitable for testing
 pub fn create_ingester_connection_for_testing() -> Arc<dyn IngesterConnection> {
     Arc::new(MockIngesterConnection::new())
@@ -350,8 +380,7 @@ impl<'a> Drop for ObserveIngesterRequest<'a> {
 /// IngesterConnection that communicates with an ingester.
 #[derive(Debug)]
 pub struct IngesterConnectionImpl {
-    shard_to_ingesters: HashMap<ShardIndex, IngesterMapping>,
-    unique_ingester_addresses: HashSet<Arc<str>>,
+    topology: watch::Receiver<Arc<IngesterTopology>>,
     flight_client: Arc<dyn IngesterFlightClient>,
     catalog_cache: Arc<CatalogCache>,
     metrics: Arc<IngesterConnectionMetrics>,
@@ -381,14 +410,11 @@ impl IngesterConnectionImpl {
         circuit_breaker_backoff_config: BackoffConfig,
         open_circuit_after_n_errors: u64,
     ) -> Self {
-        let flight_client = Arc::new(FlightClientImpl::new());
-        let flight_client = Arc::new(CircuitBreakerFlightClient::new(
-            flight_client,
-            catalog_cache.time_provider(),
-            catalog_cache.metric_registry(),
-            open_circuit_after_n_errors,
+        let flight_client = Self::circuit_breaker_flight_client(
+            &catalog_cache,
             circuit_breaker_backoff_config,
-        ));
+            open_circuit_after_n_errors,
+        );
         Self::by_shard_with_flight_client(
             shard_to_ingesters,
             flight_client,
@@ -407,26 +433,10 @@ impl IngesterConnectionImpl {
         catalog_cache: Arc<CatalogCache>,
         backoff_config: BackoffConfig,
     ) -> Self {
-        let unique_ingester_addresses: HashSet<_> = shard_to_ingesters
-            .values()
-            .flat_map(|v| match v {
-                IngesterMapping::Addr(addr) => Some(addr),
-                _ => None,
-            })
-            .cloned()
-            .collect();
-
-        let metric_registry = catalog_cache.metric_registry();
-        let metrics = Arc::new(IngesterConnectionMetrics::new(&metric_registry));
+        let (_tx, topology) =
+            watch::channel(Arc::new(IngesterTopology::by_shard(shard_to_ingesters)));
 
-        Self {
-            shard_to_ingesters,
-            unique_ingester_addresses,
-            flight_client,
-            catalog_cache,
-            metrics,
-            backoff_config,
-        }
+        Self::new_with_topology(topology, flight_client, catalog_cache, backoff_config)
     }
 
     /// Create a new set of connections given a list of ingester2 addresses.
@@ -437,27 +447,71 @@ impl IngesterConnectionImpl {
         circuit_breaker_backoff_config: BackoffConfig,
         open_circuit_after_n_errors: u64,
     ) -> Self {
-        let flight_client = Arc::new(FlightClientImpl::new());
-        let flight_client = Arc::new(CircuitBreakerFlightClient::new(
-            flight_client,
-            catalog_cache.time_provider(),
-            catalog_cache.metric_registry(),
+        let flight_client = Self::circuit_breaker_flight_client(
+            &catalog_cache,
+            circuit_breaker_backoff_config,
             open_circuit_after_n_errors,
+        );
+        let (_tx, topology) =
+            watch::channel(Arc::new(IngesterTopology::by_addrs(ingester_addresses)));
+
+        Self::new_with_topology(topology, flight_client, catalog_cache, backoff_config)
+    }
+
+    /// Create a new set of connections whose ingesters are periodically refreshed from
+    /// `discovery`.
+    pub fn by_discovery(
+        discovery: Arc<dyn IngesterDiscovery>,
+        initial: IngesterTopology,
+        discovery_interval: Duration,
+        catalog_cache: Arc<CatalogCache>,
+        backoff_config: BackoffConfig,
+        circuit_breaker_backoff_config: BackoffConfig,
+        open_circuit_after_n_errors: u64,
+    ) -> Self {
+        let flight_client = Self::circuit_breaker_flight_client(
+            &catalog_cache,
             circuit_breaker_backoff_config,
-        ));
+            open_circuit_after_n_errors,
+        );
+        let topology = watch_ingester_topology(discovery, initial, discovery_interval);
+
+        Self::new_with_topology(topology, flight_client, catalog_cache, backoff_config)
+    }
 
+    /// Create new set of connections that follows the given `topology`.
+    fn new_with_topology(
+        topology: watch::Receiver<Arc<IngesterTopology>>,
+        flight_client: Arc<dyn IngesterFlightClient>,
+        catalog_cache: Arc<CatalogCache>,
+        backoff_config: BackoffConfig,
+    ) -> Self {
         let metric_registry = catalog_cache.metric_registry();
         let metrics = Arc::new(IngesterConnectionMetrics::new(&metric_registry));
 
         Self {
-            shard_to_ingesters: HashMap::new(),
-            unique_ingester_addresses: ingester_addresses.into_iter().collect(),
+            topology,
             flight_client,
             catalog_cache,
             metrics,
             backoff_config,
         }
     }
+
+    fn circuit_breaker_flight_client(
+        catalog_cache: &CatalogCache,
+        circuit_breaker_backoff_config: BackoffConfig,
+        open_circuit_after_n_errors: u64,
+    ) -> Arc<dyn IngesterFlightClient> {
+        let flight_client = Arc::new(FlightClientImpl::new());
+        Arc::new(CircuitBreakerFlightClient::new(
+            flight_client,
+            catalog_cache.time_provider(),
+            catalog_cache.metric_registry(),
+            open_circuit_after_n_errors,
+            circuit_breaker_backoff_config,
+        ))
+    }
 }
 
 /// Struct that names all parameters to `execute`
@@ -726,7 +780,8 @@ impl IngesterStreamDecoder {
                     status.parquet_max_sequence_number.map(SequenceNumber::new),
                     None,
                     partition_sort_key,
-                );
+                )
+                .with_buffer_version(md.buffer_version);
                 self.current_partition = Some(partition);
             }
             DecodedPayload::Schema(schema) => {
@@ -812,10 +867,13 @@ impl IngesterConnection for IngesterConnectionImpl {
         predicate: &Predicate,
         span: Option<Span>,
     ) -> Result<Vec<IngesterPartition>> {
+        // Take a snapshot of the topology so that concurrent changes do not affect this query.
+        let topology = Arc::clone(&self.topology.borrow());
+
         let relevant_ingester_addresses = match shard_indexes {
             // If shard indexes is None, we're using the RPC write path, and all ingesters should
             // be queried.
-            None => self.unique_ingester_addresses.clone(),
+            None => topology.unique_ingester_addresses().clone(),
             // If shard indexes is Some([]), no ingester addresses can be found. This is a
             // configuration problem somewhwere.
             Some(shard_indexes) if shard_indexes.is_empty() => {
@@ -830,7 +888,7 @@ impl IngesterConnection for IngesterConnectionImpl {
                 let mut relevant_ingester_addresses = HashSet::new();
 
                 for shard_index in &shard_indexes {
-                    match self.shard_to_ingesters.get(shard_index) {
+                    match topology.shard_to_ingesters().get(shard_index) {
                         None => {
                             return NoIngesterFoundForShardSnafu {
                                 shard_index: *shard_index,
@@ -934,8 +992,9 @@ impl IngesterConnection for IngesterConnectionImpl {
     }
 
     async fn get_write_info(&self, write_token: &str) -> Result<GetWriteInfoResponse> {
-        let responses = self
-            .unique_ingester_addresses
+        let topology = Arc::clone(&self.topology.borrow());
+        let responses = topology
+            .unique_ingester_addresses()
             .iter()
             .map(|ingester_address| execute_get_write_infos(ingester_address, write_token))
             .collect::<FuturesUnordered<_>>()
@@ -998,6 +1057,10 @@ pub struct IngesterPartition {
     /// UUID has persisted for this partition.
     completed_persistence_count: u64,
 
+    /// If using ingester2/rpc write path, this will be the version of the data of this partition
+    /// buffered by this ingester UUID, or 0 if the ingester does not track versions.
+    buffer_version: u64,
+
     /// Maximum sequence number of parquet files the ingester has
     /// persisted for this partition
     parquet_max_sequence_number: Option<SequenceNumber>,
@@ -1032,6 +1095,7 @@ impl IngesterPartition {
             partition_id,
             shard_id,
             completed_persistence_count,
+            buffer_version: 0,
             parquet_max_sequence_number,
             tombstone_max_sequence_number,
             partition_sort_key,
@@ -1039,6 +1103,14 @@ impl IngesterPartition {
         }
     }
 
+    /// Set the version of the data of this partition buffered by the ingester.
+    pub(crate) fn with_buffer_version(self, buffer_version: u64) -> Self {
+        Self {
+            buffer_version,
+            ..self
+        }
+    }
+
     /// Try to add a new chunk to this partition.
     pub(crate) fn try_add_chunk(
         mut self,
@@ -1080,6 +1152,10 @@ impl IngesterPartition {
             batches,
             ts_min_max,
             summary,
+            buffer_version: self
+                .ingester_uuid
+                .filter(|_| self.buffer_version > 0)
+                .map(|uuid| (uuid, self.buffer_version)),
         };
 
         self.chunks.push(chunk);
@@ -1154,6 +1230,10 @@ pub struct IngesterChunk {
 
     /// Summary Statistics
     summary: Arc<TableSummary>,
+
+    /// UUID of the ingester and version of the data of the partition it buffered, if the ingester
+    /// tracks versions.
+    buffer_version: Option<(Uuid, u64)>,
 }
 
 impl IngesterChunk {
@@ -1191,6 +1271,15 @@ impl IngesterChunk {
             .map(|batch| batch.num_rows())
             .sum::<usize>()
     }
+
+    /// UUID of the ingester and version of the data of the partition it buffered, if the ingester
+    /// tracks versions.
+    ///
+    /// The data of the chunk only differs between two queries with the same predicate and
+    /// projection if this differs.
+    pub(crate) fn buffer_version(&self) -> Option<(Uuid, u64)> {
+        self.buffer_version
+    }
 }
 
 impl QueryChunkMeta for IngesterChunk {
@@ -1441,6 +1530,55 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_topology_change() {
+        let mock_flight_client = Arc::new(
+            MockFlightClient::new([
+                ("addr1", Ok(MockQueryData { results: vec![] })),
+                (
+                    "addr2",
+                    Err(FlightClientError::Flight {
+                        source: tonic::Status::internal("cow exploded").into(),
+                    }),
+                ),
+            ])
+            .await,
+        );
+        let (tx, topology) =
+            watch::channel(Arc::new(IngesterTopology::by_addrs([Arc::from("addr1")])));
+        let mut ingester_conn = mock_flight_client.ingester_conn().await;
+        ingester_conn.topology = topology;
+
+        let partitions = ingester_conn
+            .partitions(
+                None,
+                NamespaceId::new(1),
+                cached_table(),
+                vec![String::from("col")],
+                &Predicate::default(),
+                None,
+            )
+            .await
+            .unwrap();
+        assert!(partitions.is_empty());
+
+        // queries now go to the new ingester
+        tx.send(Arc::new(IngesterTopology::by_addrs([Arc::from("addr2")])))
+            .unwrap();
+        let err = ingester_conn
+            .partitions(
+                None,
+                NamespaceId::new(1),
+                cached_table(),
+                vec![String::from("col")],
+                &Predicate::default(),
+                None,
+            )
+            .await
+            .unwrap_err();
+        assert_matches!(err, Error::RemoteQuery { .. });
+    }
+
     #[tokio::test]
     async fn test_flight_no_batches() {
         let mock_flight_client = Arc::new(
@@ -2036,6 +2174,7 @@ mod tests {
                 // These fields are only used in ingester2.
                 ingester_uuid: String::new(),
                 completed_persistence_count: 0,
+                buffer_version: 0,
             },
         ))
     }
@@ -2053,6 +2192,7 @@ mod tests {
                 status,
                 ingester_uuid: ingester_uuid.into(),
                 completed_persistence_count,
+                buffer_version: 0,
             },
         ))
     }
@@ -2161,6 +2301,37 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_ingester_partition_buffer_version() {
+        let expected_schema = SchemaBuilder::new().timestamp().build().unwrap();
+        let batch = RecordBatch::try_from_iter(vec![("time", ts_array())]).unwrap();
+        let uuid = Uuid::new_v4();
+
+        for (ingester_uuid, buffer_version, expected) in [
+            (Some(uuid), 3, Some((uuid, 3))),
+            // ingester does not track versions
+            (Some(uuid), 0, None),
+            // write buffer path
+            (None, 3, None),
+        ] {
+            let ingester_partition = IngesterPartition::new(
+                "ingester".into(),
+                ingester_uuid,
+                PartitionId::new(1),
+                ShardId::new(1),
+                0,
+                None,
+                None,
+                None,
+            )
+            .with_buffer_version(buffer_version)
+            .try_add_chunk(ChunkId::new(), expected_schema.clone(), vec![batch.clone()])
+            .unwrap();
+
+            assert_eq!(ingester_partition.chunks[0].buffer_version(), expected);
+        }
+    }
+
     #[test]
     fn test_ingester_partition_type_cast() {
         let expected_schema = SchemaBuilder::new().tag("t").timestamp().build().unwrap();
@@ -2264,6 +2435,7 @@ mod tests {
             column_id_map: Default::default(),
             column_id_map_rev: Default::default(),
             primary_key_column_ids: Default::default(),
+            dedup_policy: Default::default(),
         })
     }
 }
@@ -0,0 +1,686 @@
+//! Cache of the last values of the series of tables.
+
+use crate::parquet::QuerierParquetChunk;
+use arrow::{
+    array::{
+        new_empty_array, Array, ArrayRef, DictionaryArray, StringArray, TimestampNanosecondArray,
+    },
+    compute::cast,
+    datatypes::{DataType, Int32Type},
+    record_batch::RecordBatch,
+};
+use data_types::{
+    ChunkId, ChunkOrder, DeduplicationPolicy, DeletePredicate, NamespaceId, ParquetFileId,
+    PartitionId, TableId, TableSummary, TimestampMinMax, MIN_NANO_TIME,
+};
+use datafusion::{error::DataFusionError, scalar::ScalarValue};
+use iox_query::{
+    exec::{stringset::StringSet, IOxSessionContext},
+    util::create_basic_summary,
+    QueryChunk, QueryChunkData, QueryChunkMeta,
+};
+use metric::U64Counter;
+use parking_lot::Mutex;
+use predicate::Predicate;
+use schema::{sort::SortKey, InfluxColumnType, Projection, Schema, TIME_COLUMN_NAME};
+use std::{
+    any::Any,
+    collections::{BTreeMap, HashMap, HashSet},
+    sync::Arc,
+};
+
+/// Tag values of a series, sorted by tag name. Tags that are NULL are omitted.
+type SeriesKey = Vec<(Arc<str>, Arc<str>)>;
+
+/// Keeps the last non-null value of each field of each series of the tables it
+/// is enabled for.
+///
+/// The last values of a table are brought up to date on use: the rows of
+/// parquet files that have not been seen before and of the data returned by
+/// the ingesters are absorbed, so that they reflect newly persisted files as
+/// well as unpersisted writes.
+///
+/// The last values of a table are dropped when they can not be trusted any
+/// more because of deletes. Deletes of everything older than some time, such
+/// as the retention period, are applied when reading them instead.
+#[derive(Debug)]
+pub struct LastValueCache {
+    /// Tables (`<namespace>/<table>`) whose last values are kept.
+    tables: HashSet<String>,
+
+    /// Last values, by table.
+    state: Mutex<HashMap<TableId, TableLastValues>>,
+
+    /// Number of queries answered from the last values.
+    hits: U64Counter,
+
+    /// Number of queries that could have used the last values but fell back to
+    /// scanning the table.
+    misses: U64Counter,
+}
+
+impl LastValueCache {
+    /// Create a cache that keeps the last values of the given tables, each
+    /// given as `<namespace>/<table>`.
+    pub fn new(
+        tables: impl IntoIterator<Item = String>,
+        metric_registry: &metric::Registry,
+    ) -> Self {
+        let requests = metric_registry.register_metric::<U64Counter>(
+            "querier_last_value_cache_requests",
+            "Number of queries that may be answered from the last values of a table",
+        );
+
+        Self {
+            tables: tables.into_iter().collect(),
+            state: Default::default(),
+            hits: requests.recorder(&[("result", "hit")]),
+            misses: requests.recorder(&[("result", "miss")]),
+        }
+    }
+
+    /// Whether the last values of the given table are kept.
+    pub(crate) fn is_enabled(&self, namespace_name: &str, table_name: &str) -> bool {
+        self.tables
+            .contains(&format!("{namespace_name}/{table_name}"))
+    }
+
+    /// Record whether a query was answered from the last values.
+    pub(crate) fn record(&self, hit: bool) {
+        if hit {
+            self.hits.inc(1);
+        } else {
+            self.misses.inc(1);
+        }
+    }
+
+    /// Drop the last values of the given table.
+    pub(crate) fn forget(&self, table_id: TableId) {
+        self.state.lock().remove(&table_id);
+    }
+
+    /// Split the current `chunks` of a table into the IDs of its parquet files
+    /// and the chunks whose rows have not been absorbed yet.
+    pub(crate) fn unabsorbed(
+        &self,
+        table_id: TableId,
+        chunks: Vec<Arc<dyn QueryChunk>>,
+    ) -> (HashSet<ParquetFileId>, Vec<Arc<dyn QueryChunk>>) {
+        let state = self.state.lock();
+        let absorbed = state.get(&table_id).map(|t| &t.parquet_files);
+
+        let mut parquet_files = HashSet::with_capacity(chunks.len());
+        let mut unabsorbed = vec![];
+        for chunk in chunks {
+            match chunk.as_any().downcast_ref::<QuerierParquetChunk>() {
+                Some(parquet_chunk) => {
+                    let id = parquet_chunk.meta().parquet_file_id();
+                    parquet_files.insert(id);
+                    if !absorbed.map(|files| files.contains(&id)).unwrap_or(false) {
+                        unabsorbed.push(chunk);
+                    }
+                }
+                // ingester data is always absorbed, it may have changed
+                None => unabsorbed.push(chunk),
+            }
+        }
+
+        (parquet_files, unabsorbed)
+    }
+
+    /// Absorb the (deduplicated) rows of the unabsorbed chunks of a table,
+    /// which now has the given parquet files.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn absorb(
+        &self,
+        namespace_id: NamespaceId,
+        table_id: TableId,
+        table_name: &Arc<str>,
+        schema: &Schema,
+        dedup_policy: DeduplicationPolicy,
+        parquet_files: HashSet<ParquetFileId>,
+        batches: &[RecordBatch],
+    ) -> Result<(), DataFusionError> {
+        let mut state = self.state.lock();
+        let table = state.entry(table_id).or_insert_with(|| TableLastValues {
+            namespace_id,
+            table_name: Arc::clone(table_name),
+            parquet_files: Default::default(),
+            series: Default::default(),
+        });
+
+        for batch in batches {
+            table.absorb_batch(batch, schema, dedup_policy)?;
+        }
+
+        // forget about files that are gone, e.g. compacted
+        table.parquet_files = parquet_files;
+
+        Ok(())
+    }
+
+    /// The last values of a table, leaving out those before `cutoff`.
+    pub(crate) fn last_values(
+        &self,
+        table_id: TableId,
+        schema: &Schema,
+        cutoff: Option<i64>,
+    ) -> Result<LastValues, DataFusionError> {
+        let state = self.state.lock();
+        let empty = BTreeMap::new();
+        let series = state.get(&table_id).map(|t| &t.series).unwrap_or(&empty);
+
+        Ok(LastValues {
+            schema: schema.clone(),
+            batch: to_record_batch(series, schema, cutoff)?,
+        })
+    }
+
+    /// All last values kept for tables of the given namespace.
+    pub(crate) fn entries(&self, namespace_id: NamespaceId) -> Vec<LastValueEntry> {
+        let state = self.state.lock();
+
+        let mut entries = vec![];
+        for table in state.values() {
+            if table.namespace_id != namespace_id {
+                continue;
+            }
+            for (key, values) in &table.series {
+                let series = key
+                    .iter()
+                    .map(|(tag, value)| format!("{tag}={value}"))
+                    .collect::<Vec<_>>()
+                    .join(",");
+                for (field, last) in values {
+                    entries.push(LastValueEntry {
+                        table_name: Arc::clone(&table.table_name),
+                        series: series.clone(),
+                        field: Arc::clone(field),
+                        time: last.time,
+                        value: last.value.to_string(),
+                    });
+                }
+            }
+        }
+        entries.sort_by(|a, b| {
+            (&a.table_name, &a.series, &a.field).cmp(&(&b.table_name, &b.series, &b.field))
+        });
+
+        entries
+    }
+}
+
+/// A last value kept by the [`LastValueCache`], as presented in `system.last_values`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct LastValueEntry {
+    pub(crate) table_name: Arc<str>,
+    pub(crate) series: String,
+    pub(crate) field: Arc<str>,
+    pub(crate) time: i64,
+    pub(crate) value: String,
+}
+
+/// Last values of a single table.
+#[derive(Debug)]
+struct TableLastValues {
+    namespace_id: NamespaceId,
+    table_name: Arc<str>,
+
+    /// Parquet files whose rows have been absorbed.
+    parquet_files: HashSet<ParquetFileId>,
+
+    /// Last non-null value of each field, by series.
+    series: BTreeMap<SeriesKey, BTreeMap<Arc<str>, LastValue>>,
+}
+
+#[derive(Debug, Clone)]
+struct LastValue {
+    time: i64,
+    value: ScalarValue,
+}
+
+impl TableLastValues {
+    fn absorb_batch(
+        &mut self,
+        batch: &RecordBatch,
+        schema: &Schema,
+        dedup_policy: DeduplicationPolicy,
+    ) -> Result<(), DataFusionError> {
+        let batch_schema = batch.schema();
+        let column = |name: &str| {
+            batch_schema
+                .index_of(name)
+                .ok()
+                .map(|idx| batch.column(idx))
+        };
+
+        let times = column(TIME_COLUMN_NAME)
+            .and_then(|col| col.as_any().downcast_ref::<TimestampNanosecondArray>())
+            .ok_or_else(|| {
+                DataFusionError::Internal("last values require a time column".to_string())
+            })?;
+
+        let mut tags = vec![];
+        let mut fields = vec![];
+        for (t, field) in schema.iter() {
+            let Some(col) = column(field.name()) else {
+                continue;
+            };
+            let name: Arc<str> = Arc::from(field.name().as_str());
+            match t {
+                InfluxColumnType::Tag => tags.push((name, cast(col, &DataType::Utf8)?)),
+                InfluxColumnType::Field(_) => fields.push((name, Arc::clone(col))),
+                InfluxColumnType::Timestamp => {}
+            }
+        }
+        tags.sort_by(|a, b| a.0.cmp(&b.0));
+
+        for row in 0..batch.num_rows() {
+            let key: SeriesKey = tags
+                .iter()
+                .filter_map(|(tag, col)| {
+                    let col = col.as_any().downcast_ref::<StringArray>()?;
+                    col.is_valid(row)
+                        .then(|| (Arc::clone(tag), Arc::from(col.value(row))))
+                })
+                .collect();
+            let time = times.value(row);
+
+            let values = self.series.entry(key).or_default();
+            for (field, col) in &fields {
+                if col.is_null(row) {
+                    continue;
+                }
+
+                // rows absorbed later are newer, unless they tie
+                let replace = match values.get(field) {
+                    None => true,
+                    Some(last) => {
+                        time > last.time
+                            || (time == last.time && dedup_policy != DeduplicationPolicy::KeepFirst)
+                    }
+                };
+                if replace {
+                    let value = ScalarValue::try_from_array(col, row)?;
+                    values.insert(Arc::clone(field), LastValue { time, value });
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Convert last values to rows of the table with the given schema: one row for
+/// each series and time of one of its last values, holding all fields whose
+/// last value is at that time.
+fn to_record_batch(
+    series: &BTreeMap<SeriesKey, BTreeMap<Arc<str>, LastValue>>,
+    schema: &Schema,
+    cutoff: Option<i64>,
+) -> Result<RecordBatch, DataFusionError> {
+    struct Row<'a> {
+        key: &'a SeriesKey,
+        time: i64,
+        values: Vec<(&'a str, &'a ScalarValue)>,
+    }
+
+    let mut rows = vec![];
+    for (key, values) in series {
+        let mut by_time: BTreeMap<i64, Vec<_>> = BTreeMap::new();
+        for (field, last) in values {
+            if cutoff.map(|cutoff| last.time < cutoff).unwrap_or(false) {
+                continue;
+            }
+            by_time
+                .entry(last.time)
+                .or_default()
+                .push((field.as_ref(), &last.value));
+        }
+        rows.extend(
+            by_time
+                .into_iter()
+                .map(|(time, values)| Row { key, time, values }),
+        );
+    }
+
+    let columns = schema
+        .iter()
+        .map(|(t, field)| {
+            let name = field.name().as_str();
+            let array: ArrayRef = match t {
+                InfluxColumnType::Tag => Arc::new(
+                    rows.iter()
+                        .map(|row| {
+                            row.key
+                                .iter()
+                                .find(|(tag, _)| tag.as_ref() == name)
+                                .map(|(_, value)| value.as_ref())
+                        })
+                        .collect::<DictionaryArray<Int32Type>>(),
+                ),
+                InfluxColumnType::Timestamp => Arc::new(TimestampNanosecondArray::from(
+                    rows.iter().map(|row| row.time).collect::<Vec<_>>(),
+                )),
+                InfluxColumnType::Field(_) if rows.is_empty() => new_empty_array(field.data_type()),
+                InfluxColumnType::Field(_) => {
+                    let null = ScalarValue::try_from(field.data_type())?;
+                    ScalarValue::iter_to_array(rows.iter().map(|row| {
+                        row.values
+                            .iter()
+                            .find(|(field, _)| *field == name)
+                            .map(|(_, value)| (*value).clone())
+                            .unwrap_or_else(|| null.clone())
+                    }))?
+                }
+            };
+            Ok(array)
+        })
+        .collect::<Result<Vec<_>, DataFusionError>>()?;
+
+    Ok(RecordBatch::try_new(schema.as_arrow(), columns)?)
+}
+
+/// Whether the deletes of the chunks of a table allow answering queries from
+/// its last values. Returns the time before which all data was deleted, if
+/// any, or `Err(())` if some other delete may have removed a last value.
+pub(crate) fn delete_cutoff<'a>(
+    delete_predicates: impl IntoIterator<Item = &'a Arc<DeletePredicate>>,
+) -> Result<Option<i64>, ()> {
+    let mut cutoff = None;
+    for pred in delete_predicates {
+        if !pred.exprs.is_empty() || pred.range.start() > MIN_NANO_TIME {
+            return Err(());
+        }
+        cutoff = cutoff.max(Some(pred.range.end()));
+    }
+    Ok(cutoff)
+}
+
+/// The last values of a table, as rows of the table.
+#[derive(Debug)]
+pub(crate) struct LastValues {
+    schema: Schema,
+    batch: RecordBatch,
+}
+
+impl LastValues {
+    /// Whether all last values are before `end`.
+    pub(crate) fn all_before(&self, end: i64) -> bool {
+        self.max_time().map(|max| max < end).unwrap_or(true)
+    }
+
+    fn max_time(&self) -> Option<i64> {
+        let times = self
+            .batch
+            .column(self.schema.find_index_of(TIME_COLUMN_NAME)?)
+            .as_any()
+            .downcast_ref::<TimestampNanosecondArray>()?;
+        arrow::compute::max(times)
+    }
+
+    /// Chunks holding the last values.
+    pub(crate) fn into_chunks(self) -> Vec<Arc<dyn QueryChunk>> {
+        let Some(max) = self.max_time() else {
+            return vec![];
+        };
+        let times = self
+            .batch
+            .column(
+                self.schema
+                    .find_index_of(TIME_COLUMN_NAME)
+                    .expect("checked"),
+            )
+            .as_any()
+            .downcast_ref::<TimestampNanosecondArray>()
+            .expect("checked");
+        let min = arrow::compute::min(times).expect("not empty");
+
+        let summary = Arc::new(create_basic_summary(
+            self.batch.num_rows() as u64,
+            &self.schema,
+            TimestampMinMax::new(min, max),
+        ));
+
+        vec![Arc::new(LastValueChunk {
+            id: ChunkId::new(),
+            schema: self.schema,
+            batch: self.batch,
+            summary,
+        })]
+    }
+}
+
+/// A chunk holding the last values of a table.
+#[derive(Debug)]
+struct LastValueChunk {
+    id: ChunkId,
+    schema: Schema,
+    batch: RecordBatch,
+    summary: Arc<TableSummary>,
+}
+
+impl QueryChunkMeta for LastValueChunk {
+    fn summary(&self) -> Arc<TableSummary> {
+        Arc::clone(&self.summary)
+    }
+
+    fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
+    fn partition_sort_key(&self) -> Option<&SortKey> {
+        None
+    }
+
+    fn partition_id(&self) -> PartitionId {
+        // spans all partitions
+        PartitionId::new(0)
+    }
+
+    fn sort_key(&self) -> Option<&SortKey> {
+        None
+    }
+
+    fn delete_predicates(&self) -> &[Arc<DeletePredicate>] {
+        &[]
+    }
+}
+
+impl QueryChunk for LastValueChunk {
+    fn id(&self) -> ChunkId {
+        self.id
+    }
+
+    fn may_contain_pk_duplicates(&self) -> bool {
+        // one row per series and time
+        false
+    }
+
+    fn column_names(
+        &self,
+        _ctx: IOxSessionContext,
+        _predicate: &Predicate,
+        _columns: Projection<'_>,
+    ) -> Result<Option<StringSet>, DataFusionError> {
+        Ok(None)
+    }
+
+    fn column_values(
+        &self,
+        _ctx: IOxSessionContext,
+        _column_name: &str,
+        _predicate: &Predicate,
+    ) -> Result<Option<StringSet>, DataFusionError> {
+        Ok(None)
+    }
+
+    fn data(&self) -> QueryChunkData {
+        QueryChunkData::RecordBatches(vec![self.batch.clone()])
+    }
+
+    fn chunk_type(&self) -> &str {
+        "LastValues"
+    }
+
+    fn order(&self) -> ChunkOrder {
+        ChunkOrder::new(0)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::table::test_util::lp_to_record_batch;
+    use arrow_util::assert_batches_sorted_eq;
+    use schema::builder::SchemaBuilder;
+
+    fn schema() -> Schema {
+        SchemaBuilder::new()
+            .tag("host")
+            .tag("region")
+            .influx_field("load", schema::InfluxFieldType::Float)
+            .influx_field("temp", schema::InfluxFieldType::Integer)
+            .timestamp()
+            .build()
+            .unwrap()
+    }
+
+    fn absorb(cache: &LastValueCache, dedup_policy: DeduplicationPolicy, lp: &str) {
+        cache
+            .absorb(
+                NamespaceId::new(1),
+                TableId::new(1),
+                &Arc::from("cpu"),
+                &schema(),
+                dedup_policy,
+                HashSet::new(),
+                &[lp_to_record_batch(lp)],
+            )
+            .unwrap();
+    }
+
+    fn last_values(cache: &LastValueCache, cutoff: Option<i64>) -> Vec<RecordBatch> {
+        let last_values = cache
+            .last_values(TableId::new(1), &schema(), cutoff)
+            .unwrap();
+        vec![last_values.batch]
+    }
+
+    #[test]
+    fn test_last_values() {
+        let cache = LastValueCache::new(["ns/cpu".to_string()], &metric::Registry::default());
+        assert!(cache.is_enabled("ns", "cpu"));
+        assert!(!cache.is_enabled("ns", "mem"));
+
+        absorb(
+            &cache,
+            DeduplicationPolicy::Upsert,
+            &[
+                "cpu,host=a load=1,temp=10i 10",
+                "cpu,host=a load=2 20",
+                "cpu,host=b,region=west load=3 15",
+            ]
+            .join("\n"),
+        );
+        // newer data, an older row, and a tie
+        absorb(
+            &cache,
+            DeduplicationPolicy::Upsert,
+            &[
+                "cpu,host=a temp=11i 5",
+                "cpu,host=b,region=west load=4 15",
+                "cpu,host=c temp=12i 30",
+            ]
+            .join("\n"),
+        );
+
+        assert_batches_sorted_eq!(
+            [
+                "+------+--------+------+------+--------------------------------+",
+                "| host | region | load | temp | time                           |",
+                "+------+--------+------+------+--------------------------------+",
+                "| a    |        |      | 10   | 1970-01-01T00:00:00.000000010Z |",
+                "| a    |        | 2    |      | 1970-01-01T00:00:00.000000020Z |",
+                "| b    | west   | 4    |      | 1970-01-01T00:00:00.000000015Z |",
+                "| c    |        |      | 12   | 1970-01-01T00:00:00.000000030Z |",
+                "+------+--------+------+------+--------------------------------+",
+            ],
+            &last_values(&cache, None)
+        );
+
+        assert_batches_sorted_eq!(
+            [
+                "+------+--------+------+------+--------------------------------+",
+                "| host | region | load | temp | time                           |",
+                "+------+--------+------+------+--------------------------------+",
+                "| a    |        | 2    |      | 1970-01-01T00:00:00.000000020Z |",
+                "| c    |        |      | 12   | 1970-01-01T00:00:00.000000030Z |",
+                "+------+--------+------+------+--------------------------------+",
+            ],
+            &last_values(&cache, Some(20))
+        );
+
+        let entries = cache.entries(NamespaceId::new(1));
+        assert_eq!(entries.len(), 4);
+        assert_eq!(
+            entries[2],
+            LastValueEntry {
+                table_name: Arc::from("cpu"),
+                series: "host=b,region=west".to_string(),
+                field: Arc::from("load"),
+                time: 15,
+                value: "4".to_string(),
+            }
+        );
+        assert!(cache.entries(NamespaceId::new(2)).is_empty());
+
+        cache.forget(TableId::new(1));
+        assert!(cache.entries(NamespaceId::new(1)).is_empty());
+    }
+
+    #[test]
+    fn test_last_values_keep_first() {
+        let cache = LastValueCache::new(["ns/cpu".to_string()], &metric::Registry::default());
+
+        absorb(
+            &cache,
+            DeduplicationPolicy::KeepFirst,
+            "cpu,host=a load=1 10",
+        );
+        absorb(
+            &cache,
+            DeduplicationPolicy::KeepFirst,
+            "cpu,host=a load=2 10",
+        );
+
+        assert_batches_sorted_eq!(
+            [
+                "+------+--------+------+------+--------------------------------+",
+                "| host | region | load | temp | time                           |",
+                "+------+--------+------+------+--------------------------------+",
+                "| a    |        | 1    |      | 1970-01-01T00:00:00.000000010Z |",
+                "+------+--------+------+------+--------------------------------+",
+            ],
+            &last_values(&cache, None)
+        );
+    }
+
+    #[test]
+    fn test_delete_cutoff() {
+        let retention = Arc::new(DeletePredicate::retention_delete_predicate(100));
+        let other_retention = Arc::new(DeletePredicate::retention_delete_predicate(50));
+        assert_eq!(delete_cutoff([]), Ok(None));
+        assert_eq!(delete_cutoff([&retention, &other_retention]), Ok(Some(100)));
+
+        let delete = Arc::new(DeletePredicate {
+            range: data_types::TimestampRange::new(10, 20),
+            exprs: vec![],
+        });
+        assert_eq!(delete_cutoff([&retention, &delete]), Err(()));
+    }
+}
@@ -13,8 +13,10 @@
 
 mod cache;
 mod database;
+mod downsampling;
 mod handler;
 mod ingester;
+mod last_value;
 mod namespace;
 mod parquet;
 mod poison;
@@ -24,16 +26,24 @@ mod system_tables;
 mod table;
 mod tombstone;
 
+pub use cache::object_store::ParquetDiskCache;
 pub use cache::CatalogCache as QuerierCatalogCache;
 pub use database::{Error as QuerierDatabaseError, QuerierDatabase};
+pub use downsampling::{
+    DownsamplingScheduler, DownsamplingSink, RouterDownsamplingSink,
+    SinkError as DownsamplingSinkError,
+};
 pub use handler::{QuerierHandler, QuerierHandlerImpl};
 pub use ingester::{
     create_ingester_connection_for_testing, create_ingester_connections,
+    create_ingester_connections_with_discovery,
+    discovery::{DiscoveryError, DnsIngesterDiscovery, IngesterDiscovery, IngesterTopology},
     flight_client::{
         Error as IngesterFlightClientError, IngesterFlightClient,
         QueryData as IngesterFlightClientQueryData,
     },
     Error as IngesterError, IngesterConnection, IngesterConnectionImpl, IngesterPartition,
 };
+pub use last_value::LastValueCache;
 pub use namespace::QuerierNamespace;
 pub use server::QuerierServer;
@@ -1,8 +1,9 @@
 //! Namespace within the whole catalog.
 
 use crate::{
-    cache::{namespace::CachedNamespace, CatalogCache},
+    cache::{namespace::CachedNamespace, query_result::QueryResultCache, CatalogCache},
     ingester::IngesterConnection,
+    last_value::LastValueCache,
     parquet::ChunkAdapter,
     query_log::QueryLog,
     table::{PruneMetrics, QuerierTable, QuerierTableArgs},
@@ -46,6 +47,12 @@ pub struct QuerierNamespace {
 
     /// Query log.
     query_log: Arc<QueryLog>,
+
+    /// Last values of the series of the tables that keep them.
+    last_value_cache: Arc<LastValueCache>,
+
+    /// Results of earlier queries, if enabled.
+    query_result_cache: Option<Arc<QueryResultCache>>,
 }
 
 impl QuerierNamespace {
@@ -60,6 +67,7 @@ impl QuerierNamespace {
         query_log: Arc<QueryLog>,
         sharder: Option<Arc<JumpHash<Arc<ShardIndex>>>>,
         prune_metrics: Arc<PruneMetrics>,
+        last_value_cache: Arc<LastValueCache>,
     ) -> Self {
         let tables: HashMap<_, _> = ns
             .tables
@@ -73,10 +81,14 @@ impl QuerierNamespace {
                     table_id: cached_table.id,
                     table_name: Arc::clone(table_name),
                     schema: cached_table.schema.clone(),
+                    dedup_policy: cached_table.dedup_policy,
                     ingester_connection: ingester_connection.clone(),
                     chunk_adapter: Arc::clone(&chunk_adapter),
                     exec: Arc::clone(&exec),
                     prune_metrics: Arc::clone(&prune_metrics),
+                    last_value_cache: last_value_cache
+                        .is_enabled(&name, table_name)
+                        .then(|| Arc::clone(&last_value_cache)),
                 }));
 
                 (Arc::clone(table_name), table)
@@ -92,6 +104,19 @@ impl QuerierNamespace {
             exec,
             catalog_cache: Arc::clone(chunk_adapter.catalog_cache()),
             query_log,
+            last_value_cache,
+            query_result_cache: None,
+        }
+    }
+
+    /// Reuse the results of earlier queries from `query_result_cache`, if any.
+    pub(crate) fn with_query_result_cache(
+        self,
+        query_result_cache: Option<Arc<QueryResultCache>>,
+    ) -> Self {
+        Self {
+            query_result_cache,
+            ..self
         }
     }
 
@@ -106,6 +131,7 @@ impl QuerierNamespace {
         ingester_connection: Option<Arc<dyn IngesterConnection>>,
         sharder: Arc<JumpHash<Arc<ShardIndex>>>,
         rpc_write: bool,
+        last_value_cache: Arc<LastValueCache>,
     ) -> Self {
         let time_provider = catalog_cache.time_provider();
         let chunk_adapter = Arc::new(ChunkAdapter::new(catalog_cache, metric_registry, rpc_write));
@@ -121,6 +147,7 @@ impl QuerierNamespace {
             query_log,
             Some(sharder),
             prune_metrics,
+            last_value_cache,
         )
     }
 
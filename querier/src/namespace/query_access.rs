@@ -1,10 +1,12 @@
 //! This module contains implementations of [`iox_query`] interfaces for [QuerierNamespace].
 
 use crate::{
+    cache::query_result::QueryDataVersions,
+    last_value::LastValueCache,
     namespace::QuerierNamespace,
     query_log::QueryLog,
     system_tables::{SystemSchemaProvider, SYSTEM_SCHEMA},
-    table::QuerierTable,
+    table::{QuerierTable, QuerierTableLastValues},
 };
 use async_trait::async_trait;
 use data_types::NamespaceId;
@@ -12,10 +14,13 @@ use datafusion::{
     catalog::{catalog::CatalogProvider, schema::SchemaProvider},
     datasource::TableProvider,
     error::DataFusionError,
+    physical_plan::ExecutionPlan,
 };
 use datafusion_util::config::DEFAULT_SCHEMA;
+use futures::future::AbortHandle;
 use iox_query::{
-    exec::{ExecutionContextProvider, ExecutorType, IOxSessionContext},
+    exec::{ExecutionContextProvider, ExecutorType, IOxSessionContext, QueryVolatility},
+    provider::{LastValueFilter, LastValueTableProvider, TimeOrderedTableProvider},
     QueryChunk, QueryCompletedToken, QueryNamespace, QueryText,
 };
 use observability_deps::tracing::{debug, trace};
@@ -80,6 +85,57 @@ impl QueryNamespace for QuerierNamespace {
         Ok(chunks)
     }
 
+    async fn last_value_chunks(
+        &self,
+        table_name: &str,
+        predicate: &Predicate,
+        ctx: IOxSessionContext,
+    ) -> Result<Option<Vec<Arc<dyn QueryChunk>>>, DataFusionError> {
+        let table = match self.tables.get(table_name) {
+            Some(table) if table.has_last_values() => Arc::clone(table),
+            _ => return Ok(None),
+        };
+
+        let answerable = predicate.value_expr.is_empty()
+            && predicate.exprs.iter().all(|e| {
+                LastValueFilter::try_new(e, table.schema()) == Some(LastValueFilter::Tags)
+            });
+        if !answerable {
+            table.record_last_values_use(false);
+            return Ok(None);
+        }
+
+        let last_values = table
+            .last_values(
+                ctx.span()
+                    .map(|span| span.child("querier table last values")),
+            )
+            .await?
+            .filter(|last_values| {
+                predicate
+                    .range
+                    .map(|range| last_values.all_before(range.end()))
+                    .unwrap_or(true)
+            });
+
+        debug!(%table_name, %predicate, hit=last_values.is_some(), "Last value chunks for table");
+        table.record_last_values_use(last_values.is_some());
+        Ok(last_values.map(|last_values| last_values.into_chunks()))
+    }
+
+    async fn cached_plan(
+        &self,
+        ctx: &IOxSessionContext,
+        query_type: &str,
+        query_text: &str,
+        plan: Arc<dyn ExecutionPlan>,
+    ) -> Result<Arc<dyn ExecutionPlan>, DataFusionError> {
+        Ok(match &self.query_result_cache {
+            Some(cache) => cache.cached_plan(&self.name, ctx, query_type, query_text, plan),
+            None => plan,
+        })
+    }
+
     fn record_query(
         &self,
         ctx: &IOxSessionContext,
@@ -90,8 +146,19 @@ impl QueryNamespace for QuerierNamespace {
         // will be set.
         let query_log = Arc::clone(&self.query_log);
         let trace_id = ctx.span().map(|s| s.ctx.trace_id);
-        let entry = query_log.push(self.id, query_type, query_text, trace_id);
-        QueryCompletedToken::new(move |success| query_log.set_completed(entry, success))
+        let (abort_handle, abort_registration) = AbortHandle::new_pair();
+        let entry = query_log.push(self.id, query_type, query_text, trace_id, abort_handle);
+
+        let planned_log = Arc::clone(&query_log);
+        let planned_entry = Arc::clone(&entry);
+        let cancellable_entry = Arc::clone(&entry);
+        QueryCompletedToken::new_with_stats(move |success, stats| {
+            query_log.set_completed(entry, success, stats)
+        })
+        .with_on_planned(move || planned_log.set_planned(&planned_entry))
+        .with_abort_registration(abort_registration, move || {
+            cancellable_entry.set_cancellable()
+        })
     }
 
     fn as_meta(&self) -> &dyn QueryNamespaceMeta {
@@ -108,6 +175,9 @@ pub struct QuerierCatalogProvider {
 
     /// Query log.
     query_log: Arc<QueryLog>,
+
+    /// Last values of the series of the tables that keep them.
+    last_value_cache: Arc<LastValueCache>,
 }
 
 impl QuerierCatalogProvider {
@@ -116,6 +186,7 @@ impl QuerierCatalogProvider {
             namespace_id: namespace.id,
             tables: Arc::clone(&namespace.tables),
             query_log: Arc::clone(&namespace.query_log),
+            last_value_cache: Arc::clone(&namespace.last_value_cache),
         }
     }
 }
@@ -137,6 +208,7 @@ impl CatalogProvider for QuerierCatalogProvider {
             SYSTEM_SCHEMA => Some(Arc::new(SystemSchemaProvider::new(
                 Arc::clone(&self.query_log),
                 self.namespace_id,
+                Arc::clone(&self.last_value_cache),
             ))),
             _ => None,
         }
@@ -175,7 +247,17 @@ impl SchemaProvider for UserSchemaProvider {
     }
 
     fn table(&self, name: &str) -> Option<Arc<dyn TableProvider>> {
-        self.tables.get(name).map(|t| Arc::clone(t) as _)
+        let table = self.tables.get(name)?;
+        let time_ordered = Arc::new(TimeOrderedTableProvider::new(Arc::clone(table) as _));
+        if table.has_last_values() {
+            let last_values = QuerierTableLastValues::new(Arc::clone(table));
+            Some(Arc::new(LastValueTableProvider::new(
+                time_ordered,
+                Arc::new(last_values),
+            )))
+        } else {
+            Some(time_ordered)
+        }
     }
 
     fn table_exist(&self, name: &str) -> bool {
@@ -185,18 +267,29 @@ impl SchemaProvider for UserSchemaProvider {
 
 impl ExecutionContextProvider for QuerierNamespace {
     fn new_query_context(&self, span_ctx: Option<SpanContext>) -> IOxSessionContext {
-        self.exec
+        let mut config = self
+            .exec
             .new_execution_config(ExecutorType::Query)
             .with_default_catalog(Arc::new(QuerierCatalogProvider::from_namespace(self)) as _)
-            .with_span_context(span_ctx)
-            .build()
+            .with_span_context(span_ctx);
+        if self.query_result_cache.is_some() {
+            config = config
+                .with_extension(Arc::new(QueryDataVersions::new()))
+                .with_extension(Arc::new(QueryVolatility::new()));
+        }
+        config.build()
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::namespace::test_util::{clear_parquet_cache, querier_namespace};
+    use crate::{
+        cache::query_result::QueryResultCache,
+        namespace::test_util::{
+            clear_parquet_cache, querier_namespace, querier_namespace_with_last_values,
+        },
+    };
     use arrow::record_batch::RecordBatch;
     use arrow_util::assert_batches_sorted_eq;
     use data_types::ColumnType;
@@ -587,6 +680,219 @@ mod tests {
             .await;
     }
 
+    #[tokio::test]
+    async fn test_last_values() {
+        test_helpers::maybe_start_logging();
+
+        let catalog = TestCatalog::new();
+        let ns = catalog.create_namespace_with_retention("ns", None).await;
+        let shard = ns.create_shard(1).await;
+
+        let table_cpu = ns.create_table("cpu").await;
+        table_cpu.create_column("host", ColumnType::Tag).await;
+        table_cpu.create_column("time", ColumnType::Time).await;
+        table_cpu.create_column("load", ColumnType::F64).await;
+
+        let partition_a = table_cpu.with_shard(&shard).create_partition("a").await;
+        let partition_b = table_cpu.with_shard(&shard).create_partition("b").await;
+
+        let builder = TestParquetFileBuilder::default()
+            .with_line_protocol("cpu,host=a load=1 11\ncpu,host=b load=5 11")
+            .with_max_seq(1)
+            .with_min_time(11)
+            .with_max_time(11);
+        partition_a.create_parquet_file(builder).await;
+
+        let builder = TestParquetFileBuilder::default()
+            .with_line_protocol("cpu,host=a load=2 22")
+            .with_max_seq(2)
+            .with_min_time(22)
+            .with_max_time(22);
+        partition_b.create_parquet_file(builder).await;
+
+        let querier_namespace = Arc::new(querier_namespace_with_last_values(&ns, &["cpu"]).await);
+
+        assert_query(
+            &querier_namespace,
+            "SELECT host, load, time FROM cpu ORDER BY time DESC LIMIT 1",
+            &[
+                "+------+------+--------------------------------+",
+                "| host | load | time                           |",
+                "+------+------+--------------------------------+",
+                "| a    | 2    | 1970-01-01T00:00:00.000000022Z |",
+                "+------+------+--------------------------------+",
+            ],
+        )
+        .await;
+
+        assert_query(
+            &querier_namespace,
+            "SELECT host, selector_last(load, time)['value'] AS load FROM cpu GROUP BY host",
+            &[
+                "+------+------+",
+                "| host | load |",
+                "+------+------+",
+                "| a    | 2    |",
+                "| b    | 5    |",
+                "+------+------+",
+            ],
+        )
+        .await;
+
+        assert_query(
+            &querier_namespace,
+            "SELECT * FROM system.last_values",
+            &[
+                "+------------+--------+-------+--------------------------------+-------+",
+                "| table_name | series | field | time                           | value |",
+                "+------------+--------+-------+--------------------------------+-------+",
+                "| cpu        | host=a | load  | 1970-01-01T00:00:00.000000022Z | 2     |",
+                "| cpu        | host=b | load  | 1970-01-01T00:00:00.000000011Z | 5     |",
+                "+------------+--------+-------+--------------------------------+-------+",
+            ],
+        )
+        .await;
+
+        // the last values are after the upper time bound, so the table is scanned
+        assert_query(
+            &querier_namespace,
+            "SELECT host, load, time FROM cpu WHERE host = 'a' AND time < TIMESTAMP '1970-01-01T00:00:00.000000020Z' \
+            ORDER BY time DESC LIMIT 1",
+            &[
+                "+------+------+--------------------------------+",
+                "| host | load | time                           |",
+                "+------+------+--------------------------------+",
+                "| a    | 1    | 1970-01-01T00:00:00.000000011Z |",
+                "+------+------+--------------------------------+",
+            ],
+        )
+        .await;
+
+        // newly persisted data is absorbed
+        let builder = TestParquetFileBuilder::default()
+            .with_line_protocol("cpu,host=b load=6 33")
+            .with_max_seq(3)
+            .with_min_time(33)
+            .with_max_time(33);
+        partition_b.create_parquet_file(builder).await;
+        clear_parquet_cache(&querier_namespace, table_cpu.table.id);
+
+        assert_query(
+            &querier_namespace,
+            "SELECT host, load, time FROM cpu ORDER BY time DESC LIMIT 1",
+            &[
+                "+------+------+--------------------------------+",
+                "| host | load | time                           |",
+                "+------+------+--------------------------------+",
+                "| b    | 6    | 1970-01-01T00:00:00.000000033Z |",
+                "+------+------+--------------------------------+",
+            ],
+        )
+        .await;
+
+        let mut reporter = RawReporter::default();
+        catalog.metric_registry().report(&mut reporter);
+        let requests = reporter
+            .metric("querier_last_value_cache_requests")
+            .unwrap();
+        assert_eq!(
+            requests.observation(&[("result", "hit")]).unwrap(),
+            &Observation::U64Counter(3),
+        );
+        assert_eq!(
+            requests.observation(&[("result", "miss")]).unwrap(),
+            &Observation::U64Counter(1),
+        );
+    }
+
+    #[tokio::test]
+    async fn test_query_result_cache() {
+        test_helpers::maybe_start_logging();
+
+        let catalog = TestCatalog::new();
+        let ns = catalog.create_namespace_with_retention("ns", None).await;
+        let shard = ns.create_shard(1).await;
+
+        let table_cpu = ns.create_table("cpu").await;
+        table_cpu.create_column("host", ColumnType::Tag).await;
+        table_cpu.create_column("time", ColumnType::Time).await;
+        table_cpu.create_column("load", ColumnType::F64).await;
+
+        let partition = table_cpu.with_shard(&shard).create_partition("a").await;
+
+        let builder = TestParquetFileBuilder::default()
+            .with_line_protocol("cpu,host=a load=1 11")
+            .with_max_seq(1)
+            .with_min_time(11)
+            .with_max_time(11);
+        partition.create_parquet_file(builder).await;
+
+        let cache = Arc::new(QueryResultCache::new(
+            catalog.time_provider(),
+            catalog.metric_registry(),
+            usize::MAX,
+        ));
+        let querier_namespace = Arc::new(
+            querier_namespace(&ns)
+                .await
+                .with_query_result_cache(Some(cache)),
+        );
+
+        let sql = "SELECT host, load FROM cpu";
+        let expected = [
+            "+------+------+",
+            "| host | load |",
+            "+------+------+",
+            "| a    | 1    |",
+            "+------+------+",
+        ];
+        assert_batches_sorted_eq!(&expected, &run_cached(&querier_namespace, sql).await);
+        assert_batches_sorted_eq!(&expected, &run_cached(&querier_namespace, sql).await);
+
+        // new data invalidates the cached result
+        let builder = TestParquetFileBuilder::default()
+            .with_line_protocol("cpu,host=b load=2 22")
+            .with_max_seq(2)
+            .with_min_time(22)
+            .with_max_time(22);
+        partition.create_parquet_file(builder).await;
+        clear_parquet_cache(&querier_namespace, table_cpu.table.id);
+
+        assert_batches_sorted_eq!(
+            &[
+                "+------+------+",
+                "| host | load |",
+                "+------+------+",
+                "| a    | 1    |",
+                "| b    | 2    |",
+                "+------+------+",
+            ],
+            &run_cached(&querier_namespace, sql).await
+        );
+
+        // volatile queries are never cached, however they are spelled
+        run_cached(&querier_namespace, "SELECT host, now() FROM cpu").await;
+        run_cached(&querier_namespace, "SELECT host, NOW () AS t FROM cpu").await;
+
+        let mut reporter = RawReporter::default();
+        catalog.metric_registry().report(&mut reporter);
+        let requests = reporter
+            .metric("querier_query_result_cache_requests")
+            .unwrap();
+        assert_eq!(
+            requests.observation(&[("result", "hit")]).unwrap(),
+            &Observation::U64Counter(1),
+        );
+        assert_eq!(
+            requests.observation(&[("result", "miss")]).unwrap(),
+            &Observation::U64Counter(2),
+        );
+        assert_eq!(
+            requests.observation(&[("result", "uncacheable")]).unwrap(),
+            &Observation::U64Counter(2),
+        );
+    }
+
     async fn assert_query(
         querier_namespace: &Arc<QuerierNamespace>,
         sql: &str,
@@ -637,6 +943,19 @@ mod tests {
             .expect("Build+run plan")
     }
 
+    async fn run_cached(querier_namespace: &Arc<QuerierNamespace>, sql: &str) -> Vec<RecordBatch> {
+        let planner = SqlQueryPlanner::default();
+        let ctx = querier_namespace.new_query_context(None);
+
+        let physical_plan = planner.query(sql, &ctx).await.unwrap();
+        let physical_plan = querier_namespace
+            .cached_plan(&ctx, "sql", sql, physical_plan)
+            .await
+            .unwrap();
+
+        ctx.collect(physical_plan).await.unwrap()
+    }
+
     #[derive(Debug, Snafu)]
     enum RunError {
         #[snafu(display("Cannot build plan: {}", source))]
@@ -1,6 +1,7 @@
 use super::QuerierNamespace;
 use crate::{
-    cache::namespace::CachedNamespace, create_ingester_connection_for_testing, QuerierCatalogCache,
+    cache::namespace::CachedNamespace, create_ingester_connection_for_testing, LastValueCache,
+    QuerierCatalogCache,
 };
 use data_types::{ShardIndex, TableId};
 use iox_catalog::interface::get_schema_by_name;
@@ -12,6 +13,14 @@ use tokio::runtime::Handle;
 
 /// Create [`QuerierNamespace`] for testing.
 pub async fn querier_namespace(ns: &Arc<TestNamespace>) -> QuerierNamespace {
+    querier_namespace_with_last_values(ns, &[]).await
+}
+
+/// Create [`QuerierNamespace`] for testing that keeps the last values of the given tables.
+pub async fn querier_namespace_with_last_values(
+    ns: &Arc<TestNamespace>,
+    tables: &[&str],
+) -> QuerierNamespace {
     let mut repos = ns.catalog.catalog.repositories().await;
     let schema = get_schema_by_name(&ns.namespace.name, repos.as_mut())
         .await
@@ -40,6 +49,12 @@ pub async fn querier_namespace(ns: &Arc<TestNamespace>) -> QuerierNamespace {
         );
 
     let sharder = Arc::new(JumpHash::new((0..1).map(ShardIndex::new).map(Arc::new)));
+    let last_value_cache = Arc::new(LastValueCache::new(
+        tables
+            .iter()
+            .map(|table| format!("{}/{}", ns.namespace.name, table)),
+        &ns.catalog.metric_registry(),
+    ));
 
     QuerierNamespace::new_testing(
         catalog_cache,
@@ -50,6 +65,7 @@ pub async fn querier_namespace(ns: &Arc<TestNamespace>) -> QuerierNamespace {
         Some(create_ingester_connection_for_testing()),
         sharder,
         false,
+        last_value_cache,
     )
 }
 
@@ -3,9 +3,16 @@ use std::{collections::HashSet, sync::Arc};
 use data_types::{ChunkId, ChunkOrder, ColumnId, ParquetFile, TimestampMinMax};
 use futures::StreamExt;
 use iox_catalog::interface::Catalog;
-use iox_query::{pruning::prune_summaries, util::create_basic_summary};
-use observability_deps::tracing::debug;
-use parquet_file::chunk::ParquetChunk;
+use iox_query::{
+    pruning::{bloom_filter_values, prune_summaries, NotPrunedReason},
+    util::create_basic_summary,
+};
+use observability_deps::tracing::{debug, warn};
+use parquet_file::{
+    bloom_filter::{prune_row_groups, BloomFilterError, RowGroupSelection},
+    chunk::ParquetChunk,
+    ParquetFilePath,
+};
 use predicate::Predicate;
 use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
 use schema::sort::SortKey;
@@ -124,14 +131,26 @@ impl ChunkAdapter {
         let mut rng = StdRng::seed_from_u64(cached_table.id.get() as u64);
         parquet_files.shuffle(&mut rng);
 
+        // Equality / IN predicates on tag columns may be able to prune files (or row groups within them) using the
+        // bloom filters of the files
+        let bloom_values = bloom_filter_values(&cached_table.schema, predicate);
+
         futures::stream::iter(parquet_files)
             .map(|cached_parquet_file| {
                 let span_recorder = &span_recorder;
+                let bloom_values = &bloom_values;
+                let early_pruning_observer = &early_pruning_observer;
                 let cached_table = Arc::clone(&cached_table);
                 async move {
                     let span = span_recorder.child_span("new_chunk");
-                    self.new_chunk(cached_table, cached_parquet_file, span)
-                        .await
+                    self.new_chunk(
+                        cached_table,
+                        cached_parquet_file,
+                        bloom_values,
+                        early_pruning_observer,
+                        span,
+                    )
+                    .await
                 }
             })
             .buffer_unordered(CONCURRENT_CHUNK_CREATION_JOBS)
@@ -144,6 +163,8 @@ impl ChunkAdapter {
         &self,
         cached_table: Arc<CachedTable>,
         parquet_file: Arc<ParquetFile>,
+        bloom_values: &[(String, Vec<String>)],
+        pruning_observer: &MetricPruningObserver,
         span: Option<Span>,
     ) -> Option<QuerierParquetChunk> {
         let span_recorder = SpanRecorder::new(span);
@@ -151,6 +172,45 @@ impl ChunkAdapter {
         let parquet_file_cols: HashSet<ColumnId> =
             parquet_file.column_set.iter().copied().collect();
 
+        // Only consult the bloom filters if the file contains at least one of the predicate columns - files lacking
+        // all of them are handled by the statistics-based pruning.
+        let has_bloom_columns = parquet_file_cols
+            .iter()
+            .filter_map(|id| cached_table.column_id_map.get(id))
+            .any(|name| {
+                bloom_values
+                    .iter()
+                    .any(|(c, _)| c.as_str() == name.as_ref())
+            });
+        let mut row_group_selection = None;
+        if has_bloom_columns {
+            let row_count = parquet_file.row_count as u64;
+            let size = parquet_file.file_size_bytes as u64;
+            match self
+                .bloom_filter_selection(&parquet_file, bloom_values)
+                .await
+            {
+                Ok(selection) => {
+                    let num_selected = selection.num_selected();
+                    pruning_observer.bloom_filter_row_groups(
+                        (selection.num_row_groups() - num_selected) as u64,
+                        num_selected as u64,
+                    );
+
+                    if selection.selects_none() {
+                        pruning_observer.was_pruned_by_bloom_filter(row_count, size);
+                        return None;
+                    }
+                    if !selection.selects_all() {
+                        row_group_selection = Some(selection);
+                    }
+                }
+                Err(reason) => {
+                    pruning_observer.could_not_prune_by_bloom_filter(reason, row_count, size);
+                }
+            }
+        }
+
         // relevant_pk_columns is everything from the primary key for the table, that is actually in this parquet file
         let relevant_pk_columns: Vec<_> = cached_table
             .primary_key_column_ids
@@ -228,11 +288,12 @@ impl ChunkAdapter {
             compaction_level: parquet_file.compaction_level,
         });
 
-        let parquet_chunk = Arc::new(ParquetChunk::new(
-            parquet_file,
-            schema,
-            self.catalog_cache.parquet_store(),
-        ));
+        let mut parquet_chunk =
+            ParquetChunk::new(parquet_file, schema, self.catalog_cache.parquet_store());
+        if let Some(selection) = row_group_selection {
+            parquet_chunk = parquet_chunk.with_row_group_selection(selection);
+        }
+        let parquet_chunk = Arc::new(parquet_chunk);
 
         Some(QuerierParquetChunk::new(
             parquet_chunk,
@@ -240,4 +301,33 @@ impl ChunkAdapter {
             Some(Arc::clone(&partition_sort_key.sort_key)),
         ))
     }
+
+    /// Check the bloom filters of `parquet_file` against `bloom_values`, returning the row groups that need to be
+    /// scanned.
+    async fn bloom_filter_selection(
+        &self,
+        parquet_file: &ParquetFile,
+        bloom_values: &[(String, Vec<String>)],
+    ) -> Result<RowGroupSelection, NotPrunedReason> {
+        // Only the footer and the bloom filters of the predicate columns are fetched, with ranged GETs against the
+        // store below the object store cache. Reading them through the cache would load the whole file into it, even
+        // if the file is then pruned.
+        let path = ParquetFilePath::from(parquet_file).object_store_path();
+        let store = self.catalog_cache.object_store().uncached_object_store();
+
+        prune_row_groups(
+            store.as_ref(),
+            &path,
+            parquet_file.file_size_bytes as usize,
+            bloom_values,
+        )
+        .await
+        .map_err(|e| match e {
+            BloomFilterError::NoBloomFilter => NotPrunedReason::NoBloomFilter,
+            e @ BloomFilterError::Parquet(_) => {
+                warn!(error=%e, %path, "Cannot read bloom filters of parquet file");
+                NotPrunedReason::BloomFilterReadFailed
+            }
+        })
+    }
 }
@@ -1,21 +1,29 @@
 //! Ring buffer of queries that have been run with some brief information
 
 use data_types::NamespaceId;
-use iox_query::QueryText;
+use futures::future::AbortHandle;
+use iox_query::{QueryExecutionStats, QueryText};
 use iox_time::{Time, TimeProvider};
 use parking_lot::Mutex;
 use std::{
-    collections::VecDeque,
+    collections::{HashMap, VecDeque},
     sync::{atomic, Arc},
     time::Duration,
 };
 use trace::ctx::TraceId;
+use uuid::Uuid;
 
 // The query duration used for queries still running.
 const UNCOMPLETED_DURATION: i64 = -1;
 
+// The peak memory used for queries that did not track memory.
+const UNKNOWN_PEAK_MEMORY: i64 = -1;
+
 /// Information about a single query that was executed
 pub struct QueryLogEntry {
+    /// Unique ID of this query, used to cancel it.
+    pub query_id: Uuid,
+
     /// Namespace ID.
     pub namespace_id: NamespaceId,
 
@@ -35,18 +43,48 @@ pub struct QueryLogEntry {
     /// indicating query not completed).
     query_completed_duration: atomic::AtomicI64,
 
+    /// Duration in nanoseconds it took to plan the query (-1 is a sentinel
+    /// value indicating the query was not planned yet).
+    query_planned_duration: atomic::AtomicI64,
+
+    /// Number of rows returned.
+    rows: atomic::AtomicU64,
+
+    /// Number of bytes returned.
+    bytes: atomic::AtomicU64,
+
+    /// Peak memory reserved from the DataFusion memory pool in bytes (-1 is a
+    /// sentinel value indicating memory was not tracked).
+    peak_memory: atomic::AtomicI64,
+
     /// If the query completed successfully
     pub success: atomic::AtomicBool,
+
+    /// If the query was cancelled
+    cancelled: atomic::AtomicBool,
+
+    /// If the query execution observes `abort_handle`, and can therefore be
+    /// cancelled
+    cancellable: atomic::AtomicBool,
+
+    /// Handle to abort the execution of the query.
+    abort_handle: AbortHandle,
 }
 
 impl std::fmt::Debug for QueryLogEntry {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("QueryLogEntry")
+            .field("query_id", &self.query_id)
             .field("query_type", &self.query_type)
             .field("query_text", &self.query_text.to_string())
             .field("issue_time", &self.issue_time)
             .field("query_completed_duration", &self.query_completed_duration)
+            .field("query_planned_duration", &self.query_planned_duration)
+            .field("rows", &self.rows)
+            .field("bytes", &self.bytes)
+            .field("peak_memory", &self.peak_memory)
             .field("success", &self.success)
+            .field("cancelled", &self.cancelled)
             .finish()
     }
 }
@@ -54,20 +92,30 @@ impl std::fmt::Debug for QueryLogEntry {
 impl QueryLogEntry {
     /// Creates a new QueryLogEntry -- use `QueryLog::push` to add new entries to the log
     fn new(
+        query_id: Uuid,
         namespace_id: NamespaceId,
         query_type: String,
         query_text: QueryText,
         trace_id: Option<TraceId>,
         issue_time: Time,
+        abort_handle: AbortHandle,
     ) -> Self {
         Self {
+            query_id,
             namespace_id,
             query_type,
             query_text,
             trace_id,
             issue_time,
             query_completed_duration: UNCOMPLETED_DURATION.into(),
+            query_planned_duration: UNCOMPLETED_DURATION.into(),
+            rows: atomic::AtomicU64::new(0),
+            bytes: atomic::AtomicU64::new(0),
+            peak_memory: UNKNOWN_PEAK_MEMORY.into(),
             success: atomic::AtomicBool::new(false),
+            cancelled: atomic::AtomicBool::new(false),
+            cancellable: atomic::AtomicBool::new(false),
+            abort_handle,
         }
     }
 
@@ -83,11 +131,87 @@ impl QueryLogEntry {
         }
     }
 
+    /// If this query was planned, returns `Some(duration)` of how long
+    /// planning took
+    pub fn plan_duration(&self) -> Option<Duration> {
+        match self.query_planned_duration.load(atomic::Ordering::Relaxed) {
+            UNCOMPLETED_DURATION => None,
+            d => Some(Duration::from_nanos(d as u64)),
+        }
+    }
+
+    /// If this query was planned and is completed, returns `Some(duration)`
+    /// of how long the execution (after planning) took
+    pub fn execute_duration(&self) -> Option<Duration> {
+        let completed = self.query_completed_duration()?;
+        let planned = self.plan_duration()?;
+        Some(completed.saturating_sub(planned))
+    }
+
+    /// Number of rows returned by this query so far
+    pub fn rows(&self) -> u64 {
+        self.rows.load(atomic::Ordering::Relaxed)
+    }
+
+    /// Number of bytes returned by this query so far
+    pub fn bytes(&self) -> u64 {
+        self.bytes.load(atomic::Ordering::Relaxed)
+    }
+
+    /// Peak memory reserved by this query, if known
+    pub fn peak_memory(&self) -> Option<u64> {
+        match self.peak_memory.load(atomic::Ordering::Relaxed) {
+            UNKNOWN_PEAK_MEMORY => None,
+            m => Some(m as u64),
+        }
+    }
+
     /// Returns true if `set_completed` was called with `success=true`
     pub fn success(&self) -> bool {
         self.success.load(atomic::Ordering::SeqCst)
     }
 
+    /// Returns true if this query was cancelled
+    pub fn cancelled(&self) -> bool {
+        self.cancelled.load(atomic::Ordering::SeqCst)
+    }
+
+    /// Mark this entry planned as of `now`.
+    pub fn set_planned(&self, now: Time) {
+        let dur = now - self.issue_time;
+        self.query_planned_duration
+            .store(dur.as_nanos() as i64, atomic::Ordering::Relaxed);
+    }
+
+    /// Record the execution statistics of this query.
+    pub fn set_stats(&self, stats: QueryExecutionStats) {
+        self.rows.store(stats.rows, atomic::Ordering::Relaxed);
+        self.bytes.store(stats.bytes, atomic::Ordering::Relaxed);
+        self.peak_memory.store(
+            stats
+                .peak_memory
+                .map(|m| m as i64)
+                .unwrap_or(UNKNOWN_PEAK_MEMORY),
+            atomic::Ordering::Relaxed,
+        );
+    }
+
+    /// Record that the execution of this query observes its abort handle.
+    pub fn set_cancellable(&self) {
+        self.cancellable.store(true, atomic::Ordering::SeqCst);
+    }
+
+    /// Returns true if the execution of this query can be cancelled.
+    pub fn cancellable(&self) -> bool {
+        self.cancellable.load(atomic::Ordering::SeqCst)
+    }
+
+    /// Abort the execution of this query.
+    fn cancel(&self) {
+        self.cancelled.store(true, atomic::Ordering::SeqCst);
+        self.abort_handle.abort();
+    }
+
     /// Mark this entry complete as of `now`. `success` records if the
     /// entry is successful or not.
     pub fn set_completed(&self, now: Time, success: bool) {
@@ -105,6 +229,10 @@ pub struct QueryLog {
     log: Mutex<VecDeque<Arc<QueryLogEntry>>>,
     max_size: usize,
     time_provider: Arc<dyn TimeProvider>,
+
+    /// Queries that have not completed yet, independent of whether they
+    /// were already evicted from `log`.
+    running: Mutex<HashMap<Uuid, Arc<QueryLogEntry>>>,
 }
 
 impl QueryLog {
@@ -115,24 +243,35 @@ impl QueryLog {
             log: Mutex::new(VecDeque::with_capacity(max_size)),
             max_size,
             time_provider,
+            running: Default::default(),
         }
     }
 
+    /// Add a new running query to the log. The query can be cancelled
+    /// using [`cancel`](Self::cancel) until it is marked completed, which
+    /// aborts `abort_handle`.
     pub fn push(
         &self,
         namespace_id: NamespaceId,
         query_type: impl Into<String>,
         query_text: QueryText,
         trace_id: Option<TraceId>,
+        abort_handle: AbortHandle,
     ) -> Arc<QueryLogEntry> {
         let entry = Arc::new(QueryLogEntry::new(
+            Uuid::new_v4(),
             namespace_id,
             query_type.into(),
             query_text,
             trace_id,
             self.time_provider.now(),
+            abort_handle,
         ));
 
+        self.running
+            .lock()
+            .insert(entry.query_id, Arc::clone(&entry));
+
         if self.max_size == 0 {
             return entry;
         }
@@ -153,11 +292,37 @@ impl QueryLog {
         log.clone()
     }
 
+    /// Marks the provided query entry as planned using the current time.
+    pub fn set_planned(&self, entry: &QueryLogEntry) {
+        entry.set_planned(self.time_provider.now())
+    }
+
     /// Marks the provided query entry as completed using the current time.
     /// `success` specifies the query ran successfully
-    pub fn set_completed(&self, entry: Arc<QueryLogEntry>, success: bool) {
+    pub fn set_completed(
+        &self,
+        entry: Arc<QueryLogEntry>,
+        success: bool,
+        stats: QueryExecutionStats,
+    ) {
+        self.running.lock().remove(&entry.query_id);
+        entry.set_stats(stats);
         entry.set_completed(self.time_provider.now(), success)
     }
+
+    /// Cancel the running query with the given ID.
+    ///
+    /// Returns false if there is no such query, it has already completed, or
+    /// its execution cannot be cancelled.
+    pub fn cancel(&self, query_id: Uuid) -> bool {
+        match self.running.lock().get(&query_id) {
+            Some(entry) if entry.cancellable() => {
+                entry.cancel();
+                true
+            }
+            _ => false,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -171,11 +336,13 @@ mod test_super {
         let time_provider = MockProvider::new(Time::from_timestamp_millis(100).unwrap());
 
         let entry = Arc::new(QueryLogEntry::new(
+            Uuid::new_v4(),
             NamespaceId::new(1),
             "sql".into(),
             Box::new("SELECT 1"),
             None,
             time_provider.now(),
+            AbortHandle::new_pair().0,
         ));
         // query has not completed
         assert_eq!(entry.query_completed_duration(), None);
@@ -198,4 +365,81 @@ mod test_super {
         );
         assert!(!entry.success());
     }
+
+    #[test]
+    fn test_query_log_entry_planned() {
+        let time_provider = Arc::new(MockProvider::new(Time::from_timestamp_millis(100).unwrap()));
+        let query_log = QueryLog::new(10, Arc::clone(&time_provider) as _);
+
+        let entry = query_log.push(
+            NamespaceId::new(1),
+            "sql",
+            Box::new("SELECT 1"),
+            None,
+            AbortHandle::new_pair().0,
+        );
+        assert_eq!(entry.plan_duration(), None);
+        assert_eq!(entry.execute_duration(), None);
+        assert_eq!(entry.peak_memory(), None);
+
+        time_provider.set(Time::from_timestamp_millis(130).unwrap());
+        query_log.set_planned(&entry);
+        assert_eq!(entry.plan_duration(), Some(Duration::from_millis(30)));
+        assert_eq!(entry.execute_duration(), None);
+
+        time_provider.set(Time::from_timestamp_millis(200).unwrap());
+        query_log.set_completed(
+            Arc::clone(&entry),
+            true,
+            QueryExecutionStats {
+                rows: 3,
+                bytes: 42,
+                peak_memory: Some(1024),
+            },
+        );
+        assert_eq!(
+            entry.query_completed_duration(),
+            Some(Duration::from_millis(100))
+        );
+        assert_eq!(entry.plan_duration(), Some(Duration::from_millis(30)));
+        assert_eq!(entry.execute_duration(), Some(Duration::from_millis(70)));
+        assert_eq!(entry.rows(), 3);
+        assert_eq!(entry.bytes(), 42);
+        assert_eq!(entry.peak_memory(), Some(1024));
+    }
+
+    #[test]
+    fn test_query_log_cancel() {
+        let time_provider = Arc::new(MockProvider::new(Time::from_timestamp_millis(100).unwrap()));
+        // running queries can be cancelled even if the log does not retain them
+        let query_log = QueryLog::new(0, Arc::clone(&time_provider) as _);
+
+        let (abort_handle, abort_registration) = AbortHandle::new_pair();
+        let entry = query_log.push(
+            NamespaceId::new(1),
+            "sql",
+            Box::new("SELECT 1"),
+            None,
+            abort_handle,
+        );
+        let aborted =
+            futures::future::Abortable::new(futures::future::pending::<()>(), abort_registration);
+
+        assert!(!query_log.cancel(Uuid::new_v4()));
+        assert!(!entry.cancelled());
+
+        // queries whose execution does not observe the abort handle can not
+        // be cancelled
+        assert!(!query_log.cancel(entry.query_id));
+        assert!(!entry.cancelled());
+
+        entry.set_cancellable();
+        assert!(query_log.cancel(entry.query_id));
+        assert!(entry.cancelled());
+        assert!(aborted.is_aborted());
+
+        // completed queries can not be cancelled
+        query_log.set_completed(Arc::clone(&entry), false, Default::default());
+        assert!(!query_log.cancel(entry.query_id));
+    }
 }
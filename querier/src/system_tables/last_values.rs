@@ -0,0 +1,112 @@
+use crate::{
+    last_value::{LastValueCache, LastValueEntry},
+    system_tables::{BatchIterator, IoxSystemTable},
+};
+use arrow::{
+    array::{ArrayRef, StringArray, TimestampNanosecondArray},
+    datatypes::{DataType, Field, Schema, SchemaRef, TimeUnit},
+    error::Result,
+    record_batch::RecordBatch,
+};
+use data_types::NamespaceId;
+use observability_deps::tracing::error;
+use std::sync::Arc;
+
+/// Implementation of system.last_values table
+#[derive(Debug)]
+pub(super) struct LastValuesTable {
+    schema: SchemaRef,
+    last_value_cache: Arc<LastValueCache>,
+    namespace_id: NamespaceId,
+}
+
+impl LastValuesTable {
+    pub(super) fn new(last_value_cache: Arc<LastValueCache>, namespace_id: NamespaceId) -> Self {
+        Self {
+            schema: last_values_schema(),
+            last_value_cache,
+            namespace_id,
+        }
+    }
+}
+
+impl IoxSystemTable for LastValuesTable {
+    fn schema(&self) -> SchemaRef {
+        Arc::clone(&self.schema)
+    }
+
+    fn scan(&self, batch_size: usize) -> Result<BatchIterator> {
+        let schema = self.schema();
+        let entries = self.last_value_cache.entries(self.namespace_id);
+
+        let mut offset = 0;
+        Ok(Box::new(std::iter::from_fn(move || {
+            if offset >= entries.len() {
+                return None;
+            }
+
+            let len = batch_size.min(entries.len() - offset);
+            match from_last_value_entries(Arc::clone(&schema), &entries[offset..offset + len]) {
+                Ok(batch) => {
+                    offset += len;
+                    Some(Ok(batch))
+                }
+                Err(e) => {
+                    error!("Error system.last_values table: {:?}", e);
+                    Some(Err(e))
+                }
+            }
+        })))
+    }
+}
+
+fn last_values_schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new("table_name", DataType::Utf8, false),
+        Field::new("series", DataType::Utf8, false),
+        Field::new("field", DataType::Utf8, false),
+        Field::new(
+            "time",
+            DataType::Timestamp(TimeUnit::Nanosecond, None),
+            false,
+        ),
+        Field::new("value", DataType::Utf8, false),
+    ]))
+}
+
+fn from_last_value_entries(schema: SchemaRef, entries: &[LastValueEntry]) -> Result<RecordBatch> {
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(
+            entries
+                .iter()
+                .map(|e| Some(e.table_name.as_ref()))
+                .collect::<StringArray>(),
+        ),
+        Arc::new(
+            entries
+                .iter()
+                .map(|e| Some(&e.series))
+                .collect::<StringArray>(),
+        ),
+        Arc::new(
+            entries
+                .iter()
+                .map(|e| Some(e.field.as_ref()))
+                .collect::<StringArray>(),
+        ),
+        Arc::new(
+            entries
+                .iter()
+                .map(|e| Some(e.time))
+                .collect::<TimestampNanosecondArray>(),
+        ),
+        Arc::new(
+            entries
+                .iter()
+                .map(|e| Some(&e.value))
+                .collect::<StringArray>(),
+        ),
+    ];
+
+    RecordBatch::try_new(schema, columns)
+}
@@ -1,4 +1,6 @@
-use crate::query_log::QueryLog;
+use crate::{
+    cache::query_result::QueryDataVersions, last_value::LastValueCache, query_log::QueryLog,
+};
 use arrow::{datatypes::SchemaRef, error::Result as ArrowResult, record_batch::RecordBatch};
 use async_trait::async_trait;
 use data_types::NamespaceId;
@@ -21,25 +23,41 @@ use std::{
     task::{Context, Poll},
 };
 
+mod last_values;
 mod queries;
 
 pub const SYSTEM_SCHEMA: &str = "system";
 
 const QUERIES_TABLE: &str = "queries";
+const LAST_VALUES_TABLE: &str = "last_values";
 
-const ALL_SYSTEM_TABLES: &[&str] = &[QUERIES_TABLE];
+const ALL_SYSTEM_TABLES: &[&str] = &[QUERIES_TABLE, LAST_VALUES_TABLE];
 
 pub struct SystemSchemaProvider {
     queries: Arc<dyn TableProvider>,
+    last_values: Arc<dyn TableProvider>,
 }
 
 impl SystemSchemaProvider {
-    pub fn new(query_log: Arc<QueryLog>, namespace_id: NamespaceId) -> Self {
+    pub fn new(
+        query_log: Arc<QueryLog>,
+        namespace_id: NamespaceId,
+        last_value_cache: Arc<LastValueCache>,
+    ) -> Self {
         let queries = Arc::new(SystemTableProvider {
             table: Arc::new(queries::QueriesTable::new(query_log, Some(namespace_id))),
         });
+        let last_values = Arc::new(SystemTableProvider {
+            table: Arc::new(last_values::LastValuesTable::new(
+                last_value_cache,
+                namespace_id,
+            )),
+        });
 
-        Self { queries }
+        Self {
+            queries,
+            last_values,
+        }
     }
 }
 
@@ -58,6 +76,7 @@ impl SchemaProvider for SystemSchemaProvider {
     fn table(&self, name: &str) -> Option<Arc<dyn TableProvider>> {
         match name {
             QUERIES_TABLE => Some(Arc::clone(&self.queries)),
+            LAST_VALUES_TABLE => Some(Arc::clone(&self.last_values)),
             _ => None,
         }
     }
@@ -100,12 +119,15 @@ where
 
     async fn scan(
         &self,
-        _ctx: &SessionState,
+        ctx: &SessionState,
         projection: Option<&Vec<usize>>,
         // It would be cool to push projection and limit down
         _filters: &[Expr],
         _limit: Option<usize>,
     ) -> DataFusionResult<Arc<dyn ExecutionPlan>> {
+        // system tables reflect the state of the querier, not of data
+        QueryDataVersions::record_unversioned(ctx);
+
         let schema = self.table.schema();
         let projected_schema = match projection.as_ref() {
             Some(projection) => Arc::new(schema.project(projection)?),
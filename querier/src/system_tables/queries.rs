@@ -5,7 +5,7 @@ use crate::{
 use arrow::{
     array::{
         ArrayRef, BooleanArray, DurationNanosecondArray, Int64Array, StringArray,
-        TimestampNanosecondArray,
+        TimestampNanosecondArray, UInt64Array,
     },
     datatypes::{DataType, Field, Schema, SchemaRef, TimeUnit},
     error::Result,
@@ -92,8 +92,23 @@ fn queries_schema(include_namespace_id: bool) -> SchemaRef {
             DataType::Duration(TimeUnit::Nanosecond),
             true,
         ),
+        Field::new(
+            "plan_duration",
+            DataType::Duration(TimeUnit::Nanosecond),
+            true,
+        ),
+        Field::new(
+            "execute_duration",
+            DataType::Duration(TimeUnit::Nanosecond),
+            true,
+        ),
+        Field::new("rows", DataType::UInt64, false),
+        Field::new("bytes", DataType::UInt64, false),
+        Field::new("peak_memory", DataType::UInt64, true),
         Field::new("success", DataType::Boolean, false),
+        Field::new("cancelled", DataType::Boolean, false),
         Field::new("trace_id", DataType::Utf8, true),
+        Field::new("query_id", DataType::Utf8, false),
     ]);
 
     Arc::new(Schema::new(columns))
@@ -156,6 +171,51 @@ fn from_query_log_entries(
             .collect::<DurationNanosecondArray>(),
     ));
 
+    columns.push(Arc::new(
+        entries
+            .iter()
+            .skip(offset)
+            .take(len)
+            .map(|e| e.plan_duration().map(|d| d.as_nanos() as i64))
+            .collect::<DurationNanosecondArray>(),
+    ));
+
+    columns.push(Arc::new(
+        entries
+            .iter()
+            .skip(offset)
+            .take(len)
+            .map(|e| e.execute_duration().map(|d| d.as_nanos() as i64))
+            .collect::<DurationNanosecondArray>(),
+    ));
+
+    columns.push(Arc::new(
+        entries
+            .iter()
+            .skip(offset)
+            .take(len)
+            .map(|e| Some(e.rows()))
+            .collect::<UInt64Array>(),
+    ));
+
+    columns.push(Arc::new(
+        entries
+            .iter()
+            .skip(offset)
+            .take(len)
+            .map(|e| Some(e.bytes()))
+            .collect::<UInt64Array>(),
+    ));
+
+    columns.push(Arc::new(
+        entries
+            .iter()
+            .skip(offset)
+            .take(len)
+            .map(|e| e.peak_memory())
+            .collect::<UInt64Array>(),
+    ));
+
     columns.push(Arc::new(
         entries
             .iter()
@@ -165,6 +225,15 @@ fn from_query_log_entries(
             .collect::<BooleanArray>(),
     ));
 
+    columns.push(Arc::new(
+        entries
+            .iter()
+            .skip(offset)
+            .take(len)
+            .map(|e| Some(e.cancelled()))
+            .collect::<BooleanArray>(),
+    ));
+
     columns.push(Arc::new(
         entries
             .iter()
@@ -174,6 +243,15 @@ fn from_query_log_entries(
             .collect::<StringArray>(),
     ));
 
+    columns.push(Arc::new(
+        entries
+            .iter()
+            .skip(offset)
+            .take(len)
+            .map(|e| Some(e.query_id.to_string()))
+            .collect::<StringArray>(),
+    ));
+
     RecordBatch::try_new(schema, columns)
 }
 
@@ -181,6 +259,8 @@ fn from_query_log_entries(
 mod tests {
     use super::*;
     use arrow_util::assert_batches_eq;
+    use futures::future::AbortHandle;
+    use iox_query::QueryExecutionStats;
     use iox_time::{Time, TimeProvider};
     use trace::ctx::TraceId;
 
@@ -196,67 +276,117 @@ mod tests {
             10,
             Arc::clone(&time_provider) as Arc<dyn TimeProvider>,
         ));
-        query_log.push(id1, "sql", Box::new("select * from foo"), None);
+        let sql1_entry = query_log.push(
+            id1,
+            "sql",
+            Box::new("select * from foo"),
+            None,
+            AbortHandle::new_pair().0,
+        );
         time_provider.inc(std::time::Duration::from_secs(24 * 60 * 60));
-        let sql2_entry = query_log.push(id1, "sql", Box::new("select * from bar"), None);
+        let sql2_entry = query_log.push(
+            id1,
+            "sql",
+            Box::new("select * from bar"),
+            None,
+            AbortHandle::new_pair().0,
+        );
         let read_filter_entry = query_log.push(
             id2,
             "read_filter",
             Box::new("json goop"),
             Some(TraceId::new(0x45fe).unwrap()),
+            AbortHandle::new_pair().0,
         );
 
         let table = QueriesTable::new(Arc::clone(&query_log), None);
 
         let expected = vec![
-            "+--------------+----------------------+-------------+-------------------+--------------------+---------+----------+",
-            "| namespace_id | issue_time           | query_type  | query_text        | completed_duration | success | trace_id |",
-            "+--------------+----------------------+-------------+-------------------+--------------------+---------+----------+",
-            "| 1            | 1996-12-19T16:39:57Z | sql         | select * from foo |                    | false   |          |",
-            "| 1            | 1996-12-20T16:39:57Z | sql         | select * from bar |                    | false   |          |",
-            "| 2            | 1996-12-20T16:39:57Z | read_filter | json goop         |                    | false   | 45fe     |",
-            "+--------------+----------------------+-------------+-------------------+--------------------+---------+----------+",
+            "+--------------+----------------------+-------------+-------------------+--------------------+---------------+------------------+------+-------+-------------+---------+-----------+----------+",
+            "| namespace_id | issue_time           | query_type  | query_text        | completed_duration | plan_duration | execute_duration | rows | bytes | peak_memory | success | cancelled | trace_id |",
+            "+--------------+----------------------+-------------+-------------------+--------------------+---------------+------------------+------+-------+-------------+---------+-----------+----------+",
+            "| 1            | 1996-12-19T16:39:57Z | sql         | select * from foo |                    |               |                  | 0    | 0     |             | false   | false     |          |",
+            "| 1            | 1996-12-20T16:39:57Z | sql         | select * from bar |                    |               |                  | 0    | 0     |             | false   | false     |          |",
+            "| 2            | 1996-12-20T16:39:57Z | read_filter | json goop         |                    |               |                  | 0    | 0     |             | false   | false     | 45fe     |",
+            "+--------------+----------------------+-------------+-------------------+--------------------+---------------+------------------+------+-------+-------------+---------+-----------+----------+",
         ];
 
         let entries = table.scan(3).unwrap().collect::<Result<Vec<_>>>().unwrap();
         assert_eq!(entries.len(), 1);
-        assert_batches_eq!(&expected, &entries);
+        assert_batches_eq!(&expected, &without_query_id(&entries));
+        assert_query_ids(&entries, &[&sql1_entry, &sql2_entry, &read_filter_entry]);
 
         // mark the sql query completed after 4s unsuccessfully
         let now = Time::from_rfc3339("1996-12-20T16:40:01+00:00").unwrap();
         sql2_entry.set_completed(now, false);
 
-        // mark the read_filter query completed after 4s successfuly
+        // mark the read_filter query planned after 1s and completed after 4s successfuly
+        read_filter_entry.set_planned(Time::from_rfc3339("1996-12-20T16:39:58+00:00").unwrap());
+        read_filter_entry.set_stats(QueryExecutionStats {
+            rows: 10,
+            bytes: 1000,
+            peak_memory: Some(2048),
+        });
         read_filter_entry.set_completed(now, true);
 
+        // cancel the first query
+        assert!(query_log.cancel(sql1_entry.query_id));
+
         let expected = vec![
-            "+--------------+----------------------+-------------+-------------------+--------------------+---------+----------+",
-            "| namespace_id | issue_time           | query_type  | query_text        | completed_duration | success | trace_id |",
-            "+--------------+----------------------+-------------+-------------------+--------------------+---------+----------+",
-            "| 1            | 1996-12-19T16:39:57Z | sql         | select * from foo |                    | false   |          |",
-            "| 1            | 1996-12-20T16:39:57Z | sql         | select * from bar | 4s                 | false   |          |",
-            "| 2            | 1996-12-20T16:39:57Z | read_filter | json goop         | 4s                 | true    | 45fe     |",
-            "+--------------+----------------------+-------------+-------------------+--------------------+---------+----------+",
+            "+--------------+----------------------+-------------+-------------------+--------------------+---------------+------------------+------+-------+-------------+---------+-----------+----------+",
+            "| namespace_id | issue_time           | query_type  | query_text        | completed_duration | plan_duration | execute_duration | rows | bytes | peak_memory | success | cancelled | trace_id |",
+            "+--------------+----------------------+-------------+-------------------+--------------------+---------------+------------------+------+-------+-------------+---------+-----------+----------+",
+            "| 1            | 1996-12-19T16:39:57Z | sql         | select * from foo |                    |               |                  | 0    | 0     |             | false   | true      |          |",
+            "| 1            | 1996-12-20T16:39:57Z | sql         | select * from bar | 4s                 |               |                  | 0    | 0     |             | false   | false     |          |",
+            "| 2            | 1996-12-20T16:39:57Z | read_filter | json goop         | 4s                 | 1s            | 3s               | 10   | 1000  | 2048        | true    | false     | 45fe     |",
+            "+--------------+----------------------+-------------+-------------------+--------------------+---------------+------------------+------+-------+-------------+---------+-----------+----------+",
         ];
 
         let entries = table.scan(2).unwrap().collect::<Result<Vec<_>>>().unwrap();
         assert_eq!(entries.len(), 2);
-        assert_batches_eq!(&expected, &entries);
+        assert_batches_eq!(&expected, &without_query_id(&entries));
 
         // test namespace scoping
         let table = QueriesTable::new(Arc::clone(&query_log), Some(id1));
 
         let expected = vec![
-            "+----------------------+------------+-------------------+--------------------+---------+----------+",
-            "| issue_time           | query_type | query_text        | completed_duration | success | trace_id |",
-            "+----------------------+------------+-------------------+--------------------+---------+----------+",
-            "| 1996-12-19T16:39:57Z | sql        | select * from foo |                    | false   |          |",
-            "| 1996-12-20T16:39:57Z | sql        | select * from bar | 4s                 | false   |          |",
-            "+----------------------+------------+-------------------+--------------------+---------+----------+",
+            "+----------------------+------------+-------------------+--------------------+---------------+------------------+------+-------+-------------+---------+-----------+----------+",
+            "| issue_time           | query_type | query_text        | completed_duration | plan_duration | execute_duration | rows | bytes | peak_memory | success | cancelled | trace_id |",
+            "+----------------------+------------+-------------------+--------------------+---------------+------------------+------+-------+-------------+---------+-----------+----------+",
+            "| 1996-12-19T16:39:57Z | sql        | select * from foo |                    |               |                  | 0    | 0     |             | false   | true      |          |",
+            "| 1996-12-20T16:39:57Z | sql        | select * from bar | 4s                 |               |                  | 0    | 0     |             | false   | false     |          |",
+            "+----------------------+------------+-------------------+--------------------+---------------+------------------+------+-------+-------------+---------+-----------+----------+",
         ];
 
         let entries = table.scan(3).unwrap().collect::<Result<Vec<_>>>().unwrap();
         assert_eq!(entries.len(), 1);
-        assert_batches_eq!(&expected, &entries);
+        assert_batches_eq!(&expected, &without_query_id(&entries));
+        assert_query_ids(&entries, &[&sql1_entry, &sql2_entry]);
+    }
+
+    /// Remove the randomly generated `query_id` column (the last column).
+    fn without_query_id(batches: &[RecordBatch]) -> Vec<RecordBatch> {
+        batches
+            .iter()
+            .map(|batch| {
+                let indices: Vec<_> = (0..batch.num_columns() - 1).collect();
+                batch.project(&indices).unwrap()
+            })
+            .collect()
+    }
+
+    fn assert_query_ids(batches: &[RecordBatch], expected: &[&QueryLogEntry]) {
+        let actual: Vec<_> = batches
+            .iter()
+            .flat_map(|batch| {
+                let col = batch.column(batch.num_columns() - 1);
+                let col = col.as_any().downcast_ref::<StringArray>().unwrap();
+                col.iter()
+                    .map(|id| id.unwrap().to_string())
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        let expected: Vec<_> = expected.iter().map(|e| e.query_id.to_string()).collect();
+        assert_eq!(actual, expected);
     }
 }
@@ -2,13 +2,22 @@ use self::query_access::QuerierTableChunkPruner;
 use self::state_reconciler::Reconciler;
 use crate::{
     ingester::{self, IngesterPartition},
+    last_value::{delete_cutoff, LastValueCache, LastValues},
     parquet::ChunkAdapter,
     IngesterConnection,
 };
-use data_types::{ColumnId, DeletePredicate, NamespaceId, PartitionId, ShardIndex, TableId};
+use data_types::{
+    ColumnId, DeduplicationPolicy, DeletePredicate, NamespaceId, PartitionId, ShardIndex, TableId,
+};
 use datafusion::error::DataFusionError;
 use futures::join;
-use iox_query::{exec::Executor, provider, provider::ChunkPruner, QueryChunk};
+use iox_query::{
+    exec::{Executor, ExecutorType},
+    frontend::common::ScanPlanBuilder,
+    provider,
+    provider::ChunkPruner,
+    QueryChunk,
+};
 use observability_deps::tracing::{debug, trace};
 use predicate::Predicate;
 use schema::Schema;
@@ -26,12 +35,13 @@ use uuid::Uuid;
 
 pub use self::query_access::metrics::PruneMetrics;
 pub(crate) use self::query_access::MetricPruningObserver;
+pub(crate) use self::query_access::QuerierTableLastValues;
 
 mod query_access;
 mod state_reconciler;
 
 #[cfg(test)]
-mod test_util;
+pub(crate) mod test_util;
 
 #[derive(Debug, Snafu)]
 #[allow(clippy::large_enum_variant)]
@@ -58,6 +68,14 @@ pub enum Error {
 
     #[snafu(display("Chunk pruning failed: {}", source))]
     ChunkPruning { source: provider::Error },
+
+    #[snafu(display("Cannot plan scan for last values: {}", source))]
+    LastValuesPlanning {
+        source: iox_query::frontend::common::Error,
+    },
+
+    #[snafu(display("Cannot read last values: {}", source))]
+    LastValues { source: DataFusionError },
 }
 
 pub type Result<T, E = Error> = std::result::Result<T, E>;
@@ -77,10 +95,12 @@ pub struct QuerierTableArgs {
     pub table_id: TableId,
     pub table_name: Arc<str>,
     pub schema: Schema,
+    pub dedup_policy: DeduplicationPolicy,
     pub ingester_connection: Option<Arc<dyn IngesterConnection>>,
     pub chunk_adapter: Arc<ChunkAdapter>,
     pub exec: Arc<Executor>,
     pub prune_metrics: Arc<PruneMetrics>,
+    pub last_value_cache: Option<Arc<LastValueCache>>,
 }
 
 /// Table representation for the querier.
@@ -108,6 +128,9 @@ pub struct QuerierTable {
     /// Table schema.
     schema: Schema,
 
+    /// How duplicated primary keys of this table are resolved.
+    dedup_policy: DeduplicationPolicy,
+
     /// Connection to ingester
     ingester_connection: Option<Arc<dyn IngesterConnection>>,
 
@@ -119,6 +142,9 @@ pub struct QuerierTable {
 
     /// Metrics for chunk pruning.
     prune_metrics: Arc<PruneMetrics>,
+
+    /// Cache of the last values of the series of this table, if they are kept.
+    last_value_cache: Option<Arc<LastValueCache>>,
 }
 
 impl QuerierTable {
@@ -132,10 +158,12 @@ impl QuerierTable {
             table_id,
             table_name,
             schema,
+            dedup_policy,
             ingester_connection,
             chunk_adapter,
             exec,
             prune_metrics,
+            last_value_cache,
         } = args;
 
         Self {
@@ -146,10 +174,12 @@ impl QuerierTable {
             table_name,
             table_id,
             schema,
+            dedup_policy,
             ingester_connection,
             chunk_adapter,
             exec,
             prune_metrics,
+            last_value_cache,
         }
     }
 
@@ -169,6 +199,11 @@ impl QuerierTable {
         &self.schema
     }
 
+    /// Deduplication policy.
+    pub fn dedup_policy(&self) -> DeduplicationPolicy {
+        self.dedup_policy
+    }
+
     /// Query all chunks within this table.
     ///
     /// This currently contains all parquet files linked to their unprocessed tombstones.
@@ -386,6 +421,92 @@ impl QuerierTable {
         Ok(chunks)
     }
 
+    /// Whether the last values of the series of this table are kept.
+    pub fn has_last_values(&self) -> bool {
+        self.last_value_cache.is_some()
+    }
+
+    /// The last non-null value of each field of each series of this table.
+    ///
+    /// The [`LastValueCache`] is brought up to date with the chunks of the table first. Returns
+    /// `None` if the last values of this table are not kept or if deletes prevent answering
+    /// queries from them.
+    pub(crate) async fn last_values(&self, span: Option<Span>) -> Result<Option<LastValues>> {
+        let Some(cache) = &self.last_value_cache else {
+            return Ok(None);
+        };
+        let span_recorder = SpanRecorder::new(span);
+
+        let chunks = self
+            .chunks(
+                &Predicate::default(),
+                span_recorder.child_span("querier table chunks"),
+                None,
+            )
+            .await?;
+
+        let Ok(cutoff) = delete_cutoff(chunks.iter().flat_map(|c| c.delete_predicates())) else {
+            debug!(
+                namespace=%self.namespace_name,
+                table_name=%self.table_name(),
+                "Deletes prevent use of last values"
+            );
+            cache.forget(self.table_id);
+            return Ok(None);
+        };
+
+        let (parquet_files, unabsorbed) = cache.unabsorbed(self.table_id, chunks);
+        let batches = if unabsorbed.is_empty() {
+            vec![]
+        } else {
+            let ctx = self
+                .exec
+                .new_context(ExecutorType::Query)
+                .with_span_context(span_recorder.span().map(|span| span.ctx.clone()));
+            let plan = ScanPlanBuilder::new(
+                Arc::clone(&self.table_name),
+                &self.schema,
+                ctx.child_ctx("last values scan planning"),
+            )
+            .with_chunks(unabsorbed)
+            .with_dedup_policy(self.dedup_policy)
+            .build()
+            .context(LastValuesPlanningSnafu)?
+            .plan_builder
+            .build()
+            .context(LastValuesSnafu)?;
+            let physical_plan = ctx
+                .create_physical_plan(&plan)
+                .await
+                .context(LastValuesSnafu)?;
+            ctx.collect(physical_plan).await.context(LastValuesSnafu)?
+        };
+
+        cache
+            .absorb(
+                self.namespace_id,
+                self.table_id,
+                &self.table_name,
+                &self.schema,
+                self.dedup_policy,
+                parquet_files,
+                &batches,
+            )
+            .context(LastValuesSnafu)?;
+
+        cache
+            .last_values(self.table_id, &self.schema, cutoff)
+            .map(Some)
+            .context(LastValuesSnafu)
+    }
+
+    /// Record whether a query was answered from the last values of this table.
+    pub(crate) fn record_last_values_use(&self, hit: bool) {
+        if let Some(cache) = &self.last_value_cache {
+            cache.record(hit);
+        }
+    }
+
     /// Get a chunk pruner that can be used to prune chunks retrieved via [`chunks`](Self::chunks)
     pub fn chunk_pruner(&self) -> Arc<dyn ChunkPruner> {
         Arc::new(QuerierTableChunkPruner::new(Arc::clone(
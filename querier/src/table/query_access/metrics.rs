@@ -80,6 +80,23 @@ pub struct PruneMetrics {
     /// We could not prune these chunks because DataFusion failed to apply the pruning predicate to the chunks. This is
     /// most likely a missing feature in DataFusion.
     pub could_not_prune_df: PruneMetricsGroup,
+
+    /// Chunks that have been pruned because their bloom filters prove that no row group contains a value required by
+    /// an equality / `IN` predicate on a tag column.
+    pub pruned_bloom_filter: PruneMetricsGroup,
+
+    /// We could not use bloom filters to prune these chunks because the parquet file contains no bloom filter for the
+    /// predicate columns, e.g. because it was written before bloom filters were enabled.
+    pub could_not_prune_no_bloom_filter: PruneMetricsGroup,
+
+    /// We could not use bloom filters to prune these chunks because reading the filters failed.
+    pub could_not_prune_bloom_filter_read_failed: PruneMetricsGroup,
+
+    /// Row groups within non-pruned parquet files that were skipped based on bloom filters.
+    pub bloom_filter_row_groups_pruned: U64Counter,
+
+    /// Row groups within non-pruned parquet files that had their bloom filters checked but need to be scanned.
+    pub bloom_filter_row_groups_not_pruned: U64Counter,
 }
 
 impl PruneMetrics {
@@ -115,6 +132,32 @@ impl PruneMetrics {
             ],
         );
 
+        let pruned_bloom_filter =
+            PruneMetricsGroup::new(metric_registry, &[("result", "pruned_bloom_filter")]);
+        let could_not_prune_no_bloom_filter = PruneMetricsGroup::new(
+            metric_registry,
+            &[
+                ("result", "could_not_prune"),
+                ("reason", NotPrunedReason::NoBloomFilter.name()),
+            ],
+        );
+        let could_not_prune_bloom_filter_read_failed = PruneMetricsGroup::new(
+            metric_registry,
+            &[
+                ("result", "could_not_prune"),
+                ("reason", NotPrunedReason::BloomFilterReadFailed.name()),
+            ],
+        );
+
+        let bloom_filter_row_groups = metric_registry.register_metric::<U64Counter>(
+            "query_pruner_bloom_filter_row_groups",
+            "Number of parquet row groups checked against bloom filters by the chunk pruner",
+        );
+        let bloom_filter_row_groups_pruned =
+            bloom_filter_row_groups.recorder(&[("result", "pruned")]);
+        let bloom_filter_row_groups_not_pruned =
+            bloom_filter_row_groups.recorder(&[("result", "not_pruned")]);
+
         Self {
             pruned_early,
             pruned_late,
@@ -122,6 +165,11 @@ impl PruneMetrics {
             could_not_prune_no_expression,
             could_not_prune_cannot_create_predicate,
             could_not_prune_df,
+            pruned_bloom_filter,
+            could_not_prune_no_bloom_filter,
+            could_not_prune_bloom_filter_read_failed,
+            bloom_filter_row_groups_pruned,
+            bloom_filter_row_groups_not_pruned,
         }
     }
 
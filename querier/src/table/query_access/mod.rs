@@ -12,16 +12,21 @@ use datafusion::{
 };
 use iox_query::{
     exec::{ExecutorType, SessionContextIOxExt},
-    provider::{ChunkPruner, Error as ProviderError, ProviderBuilder},
+    provider::{
+        ChunkPruner, ChunkTableProvider, Error as ProviderError, LastValueFilter, ProviderBuilder,
+        TimeOrder, TimeOrderedTable,
+    },
     pruning::{prune_chunks, NotPrunedReason, PruningObserver},
     QueryChunk,
 };
 use predicate::Predicate;
 use schema::Schema;
 
-use crate::{ingester::IngesterChunk, parquet::QuerierParquetChunk};
+use crate::{
+    cache::query_result::QueryDataVersions, ingester::IngesterChunk, parquet::QuerierParquetChunk,
+};
 
-use self::metrics::PruneMetrics;
+use self::metrics::{PruneMetrics, PruneMetricsGroup};
 
 use super::QuerierTable;
 
@@ -48,6 +53,44 @@ impl TableProvider for QuerierTable {
         filters: &[Expr],
         limit: Option<usize>,
     ) -> Result<Arc<dyn ExecutionPlan>, DataFusionError> {
+        let provider = self.chunk_provider(ctx, projection, filters).await?;
+        provider.scan(ctx, projection, filters, limit).await
+    }
+
+    fn supports_filter_pushdown(
+        &self,
+        _filter: &Expr,
+    ) -> Result<TableProviderFilterPushDown, DataFusionError> {
+        // we may apply filtering (via pruning) but can not guarantee
+        // that the filter catches all row during scan
+        Ok(TableProviderFilterPushDown::Inexact)
+    }
+}
+
+#[async_trait]
+impl TimeOrderedTable for QuerierTable {
+    async fn scan_time_ordered(
+        &self,
+        ctx: &SessionState,
+        projection: Option<&Vec<usize>>,
+        filters: &[Expr],
+        order: TimeOrder,
+    ) -> Result<Arc<dyn ExecutionPlan>, DataFusionError> {
+        let provider = self.chunk_provider(ctx, projection, filters).await?;
+        provider
+            .scan_time_ordered(ctx, projection, filters, order)
+            .await
+    }
+}
+
+impl QuerierTable {
+    /// Provider for the chunks of this table that may match `filters`.
+    async fn chunk_provider(
+        &self,
+        ctx: &SessionState,
+        projection: Option<&Vec<usize>>,
+        filters: &[Expr],
+    ) -> Result<ChunkTableProvider, DataFusionError> {
         // build provider out of all chunks
         // TODO: push down some predicates to catalog
         let iox_ctx = self.exec.new_context_from_df(ExecutorType::Query, ctx);
@@ -56,7 +99,8 @@ impl TableProvider for QuerierTable {
             Arc::clone(self.table_name()),
             self.schema().clone(),
             iox_ctx,
-        );
+        )
+        .with_dedup_policy(self.dedup_policy());
 
         let pruning_predicate = filters
             .iter()
@@ -70,11 +114,92 @@ impl TableProvider for QuerierTable {
                 projection,
             )
             .await?;
+        QueryDataVersions::record_chunks(ctx, self.table_name(), self.schema(), &chunks);
 
         for chunk in chunks {
             builder = builder.add_chunk(chunk);
         }
 
+        match builder.build() {
+            Ok(provider) => Ok(provider),
+            Err(e) => panic!("unexpected error: {:?}", e),
+        }
+    }
+}
+
+/// Provides the last values of a [`QuerierTable`] to the `HandleLastValue` optimizer rule.
+///
+/// Falls back to scanning the whole table if the last values are not available or can not honor
+/// the upper time bound of the query.
+#[derive(Debug)]
+pub(crate) struct QuerierTableLastValues {
+    table: Arc<QuerierTable>,
+}
+
+impl QuerierTableLastValues {
+    pub(crate) fn new(table: Arc<QuerierTable>) -> Self {
+        Self { table }
+    }
+}
+
+#[async_trait]
+impl TableProvider for QuerierTableLastValues {
+    fn as_any(&self) -> &dyn Any {
+        self as &dyn Any
+    }
+
+    fn schema(&self) -> SchemaRef {
+        self.table.schema().as_arrow()
+    }
+
+    fn table_type(&self) -> TableType {
+        TableType::Base
+    }
+
+    async fn scan(
+        &self,
+        ctx: &SessionState,
+        projection: Option<&Vec<usize>>,
+        filters: &[Expr],
+        limit: Option<usize>,
+    ) -> Result<Arc<dyn ExecutionPlan>, DataFusionError> {
+        let last_values = self
+            .table
+            .last_values(ctx.child_span("querier table last values"))
+            .await?;
+
+        let last_values = last_values.filter(|last_values| {
+            filters.iter().all(|filter| {
+                match LastValueFilter::try_new(filter, self.table.schema()) {
+                    Some(LastValueFilter::TimeUpperBound(end)) => last_values.all_before(end),
+                    Some(_) => true,
+                    None => false,
+                }
+            })
+        });
+
+        let Some(last_values) = last_values else {
+            self.table.record_last_values_use(false);
+            return self.table.scan(ctx, projection, filters, limit).await;
+        };
+        self.table.record_last_values_use(true);
+        // the last values are not versioned
+        QueryDataVersions::record_unversioned(ctx);
+
+        let iox_ctx = self
+            .table
+            .exec
+            .new_context_from_df(ExecutorType::Query, ctx);
+        let mut builder = ProviderBuilder::new(
+            Arc::clone(self.table.table_name()),
+            self.table.schema().clone(),
+            iox_ctx,
+        )
+        .with_dedup_policy(self.table.dedup_policy());
+        for chunk in last_values.into_chunks() {
+            builder = builder.add_chunk(chunk);
+        }
+
         let provider = match builder.build() {
             Ok(provider) => provider,
             Err(e) => panic!("unexpected error: {:?}", e),
@@ -87,8 +212,6 @@ impl TableProvider for QuerierTable {
         &self,
         _filter: &Expr,
     ) -> Result<TableProviderFilterPushDown, DataFusionError> {
-        // we may apply filtering (via pruning) but can not guarantee
-        // that the filter catches all row during scan
         Ok(TableProviderFilterPushDown::Inexact)
     }
 }
@@ -161,6 +284,47 @@ impl MetricPruningObserver {
     pub(crate) fn was_pruned_early(&self, row_count: u64, size_estimate: u64) {
         self.metrics.pruned_early.inc(1, row_count, size_estimate);
     }
+
+    /// Called when the bloom filters of a parquet file proved that none of its row groups can match, before fully
+    /// creating the chunk structure.
+    pub(crate) fn was_pruned_by_bloom_filter(&self, row_count: u64, size_estimate: u64) {
+        self.metrics
+            .pruned_bloom_filter
+            .inc(1, row_count, size_estimate);
+    }
+
+    /// Called when the bloom filters of a parquet file could not be used for pruning.
+    pub(crate) fn could_not_prune_by_bloom_filter(
+        &self,
+        reason: NotPrunedReason,
+        row_count: u64,
+        size_estimate: u64,
+    ) {
+        self.reason_group(reason).inc(1, row_count, size_estimate);
+    }
+
+    /// Called with the number of row groups of a parquet file that were `pruned` and `not_pruned` based on bloom
+    /// filters.
+    pub(crate) fn bloom_filter_row_groups(&self, pruned: u64, not_pruned: u64) {
+        self.metrics.bloom_filter_row_groups_pruned.inc(pruned);
+        self.metrics
+            .bloom_filter_row_groups_not_pruned
+            .inc(not_pruned);
+    }
+
+    fn reason_group(&self, reason: NotPrunedReason) -> &PruneMetricsGroup {
+        match reason {
+            NotPrunedReason::NoExpressionOnPredicate => &self.metrics.could_not_prune_no_expression,
+            NotPrunedReason::CanNotCreatePruningPredicate => {
+                &self.metrics.could_not_prune_cannot_create_predicate
+            }
+            NotPrunedReason::DataFusionPruningFailed => &self.metrics.could_not_prune_df,
+            NotPrunedReason::NoBloomFilter => &self.metrics.could_not_prune_no_bloom_filter,
+            NotPrunedReason::BloomFilterReadFailed => {
+                &self.metrics.could_not_prune_bloom_filter_read_failed
+            }
+        }
+    }
 }
 
 impl PruningObserver for MetricPruningObserver {
@@ -181,15 +345,7 @@ impl PruningObserver for MetricPruningObserver {
     }
 
     fn could_not_prune(&self, reason: NotPrunedReason, chunk: &dyn QueryChunk) {
-        let group = match reason {
-            NotPrunedReason::NoExpressionOnPredicate => &self.metrics.could_not_prune_no_expression,
-            NotPrunedReason::CanNotCreatePruningPredicate => {
-                &self.metrics.could_not_prune_cannot_create_predicate
-            }
-            NotPrunedReason::DataFusionPruningFailed => &self.metrics.could_not_prune_df,
-        };
-
-        group.inc(
+        self.reason_group(reason).inc(
             1,
             chunk_rows(chunk) as u64,
             chunk_estimate_size(chunk) as u64,
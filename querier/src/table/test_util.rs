@@ -33,6 +33,7 @@ pub async fn querier_table(catalog: &Arc<TestCatalog>, table: &Arc<TestTable>) -
         .await
         .unwrap();
     let schema = catalog_schema.tables.remove(&table.table.name).unwrap();
+    let dedup_policy = schema.dedup_policy;
     let schema = Schema::try_from(schema).unwrap();
 
     let namespace_name = Arc::from(table.namespace.namespace.name.as_str());
@@ -52,10 +53,12 @@ pub async fn querier_table(catalog: &Arc<TestCatalog>, table: &Arc<TestTable>) -
         table_id: table.table.id,
         table_name: table.table.name.clone().into(),
         schema,
+        dedup_policy,
         ingester_connection: Some(create_ingester_connection_for_testing()),
         chunk_adapter,
         exec: catalog.exec(),
         prune_metrics: Arc::new(PruneMetrics::new(&catalog.metric_registry())),
+        last_value_cache: None,
     })
 }
 
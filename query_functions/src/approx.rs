@@ -0,0 +1,812 @@
+//! Implementation of approximate aggregate functions.
+//!
+//! The functions are DataFusion user defined aggregate functions that keep a
+//! fixed size sketch of their input instead of the values themselves:
+//!
+//! | function                                       | returns |
+//! |------------------------------------------------|---------|
+//! | `approx_distinct_hll(value)`                   | the approximate number of distinct values, using a HyperLogLog sketch |
+//! | `approx_percentile_tdigest(value, percentile)` | the approximate `percentile` (between 0 and 100) of the values, using a t-digest |
+//!
+//! ```sql
+//! SELECT
+//!   approx_distinct_hll(host),
+//!   approx_percentile_tdigest(usage, 99)
+//! FROM cpu
+//! ```
+//!
+//! The sketches are the partial aggregate states, and sketches of different
+//! partitions (or chunks) can be merged without loss of accuracy.
+//!
+//! `NULL` values are ignored. `approx_distinct_hll` returns `UInt64`, and
+//! `approx_percentile_tdigest` returns `Float64`, or `NULL` if there are no
+//! values.
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    f64::consts::PI,
+    hash::{Hash, Hasher},
+    sync::Arc,
+};
+
+use arrow::{
+    array::{
+        Array, ArrayRef, BinaryArray, BooleanArray, Float64Array, Int64Array, LargeStringArray,
+        StringArray, UInt64Array,
+    },
+    compute,
+    datatypes::DataType,
+};
+use datafusion::{
+    error::{DataFusionError, Result as DataFusionResult},
+    execution::context::SessionState,
+    logical_expr::{
+        AccumulatorFunctionImplementation, ReturnTypeFunction, Signature, StateTypeFunction,
+        TypeSignature, Volatility,
+    },
+    physical_plan::{udaf::AggregateUDF, Accumulator},
+    scalar::ScalarValue,
+};
+use once_cell::sync::Lazy;
+
+/// The name of the approx_distinct_hll UDAF given to DataFusion.
+pub const APPROX_DISTINCT_HLL_UDAF_NAME: &str = "approx_distinct_hll";
+
+/// The name of the approx_percentile_tdigest UDAF given to DataFusion.
+pub const APPROX_PERCENTILE_TDIGEST_UDAF_NAME: &str = "approx_percentile_tdigest";
+
+/// Number of bits of the hash that select the HyperLogLog register.
+const HLL_PRECISION: u32 = 14;
+
+/// Number of HyperLogLog registers, for a standard error of about 0.8%.
+const HLL_REGISTERS: usize = 1 << HLL_PRECISION;
+
+/// Compression of the t-digest, which bounds the number of its centroids.
+const TDIGEST_COMPRESSION: f64 = 100.0;
+
+pub(crate) static APPROX_DISTINCT_HLL: Lazy<Arc<AggregateUDF>> = Lazy::new(|| {
+    let return_type_func: ReturnTypeFunction = Arc::new(|_| Ok(Arc::new(DataType::UInt64)));
+    let accumulator: AccumulatorFunctionImplementation =
+        Arc::new(|_| Ok(Box::<HyperLogLogAccumulator>::default()));
+    let state_type: StateTypeFunction = Arc::new(|_| Ok(Arc::new(vec![DataType::Binary])));
+
+    Arc::new(AggregateUDF::new(
+        APPROX_DISTINCT_HLL_UDAF_NAME,
+        &Signature::any(1, Volatility::Immutable),
+        &return_type_func,
+        &accumulator,
+        &state_type,
+    ))
+});
+
+pub(crate) static APPROX_PERCENTILE_TDIGEST: Lazy<Arc<AggregateUDF>> = Lazy::new(|| {
+    let return_type_func: ReturnTypeFunction = Arc::new(|_| Ok(Arc::new(DataType::Float64)));
+    let accumulator: AccumulatorFunctionImplementation =
+        Arc::new(|_| Ok(Box::<TDigestAccumulator>::default()));
+    let state_type: StateTypeFunction =
+        Arc::new(|_| Ok(Arc::new(vec![DataType::Binary, DataType::Float64])));
+
+    let signatures = [DataType::Float64, DataType::Int64, DataType::UInt64]
+        .into_iter()
+        .flat_map(|v| {
+            [
+                TypeSignature::Exact(vec![v.clone(), DataType::Float64]),
+                TypeSignature::Exact(vec![v, DataType::Int64]),
+            ]
+        })
+        .collect();
+
+    Arc::new(AggregateUDF::new(
+        APPROX_PERCENTILE_TDIGEST_UDAF_NAME,
+        &Signature::one_of(signatures, Volatility::Immutable),
+        &return_type_func,
+        &accumulator,
+        &state_type,
+    ))
+});
+
+/// registers approximate aggregate functions so they can be invoked via SQL
+pub fn register_approx_aggregates(mut state: SessionState) -> SessionState {
+    for udaf in [&APPROX_DISTINCT_HLL, &APPROX_PERCENTILE_TDIGEST] {
+        let udaf = Arc::clone(udaf);
+        state.aggregate_functions.insert(udaf.name.clone(), udaf);
+    }
+
+    state
+}
+
+/// The registers of a HyperLogLog sketch.
+///
+/// Few distinct values only set a few registers, so they are kept in a map
+/// until it would use more memory than all the registers.
+#[derive(Debug)]
+enum HyperLogLog {
+    Sparse(HashMap<u16, u8>),
+    Dense(Vec<u8>),
+}
+
+impl Default for HyperLogLog {
+    fn default() -> Self {
+        Self::Sparse(HashMap::new())
+    }
+}
+
+impl HyperLogLog {
+    const SPARSE: u8 = 0;
+    const DENSE: u8 = 1;
+
+    fn add_hash(&mut self, hash: u64) {
+        let index = (hash >> (64 - HLL_PRECISION)) as u16;
+        let rank = ((hash << HLL_PRECISION).leading_zeros() + 1).min(64 - HLL_PRECISION + 1) as u8;
+        self.set_register(index, rank);
+    }
+
+    /// Set the register to `rank`, unless it is already higher.
+    fn set_register(&mut self, index: u16, rank: u8) {
+        match self {
+            Self::Sparse(registers) => {
+                let register = registers.entry(index).or_default();
+                *register = (*register).max(rank);
+                if registers.len() > HLL_REGISTERS / 4 {
+                    let mut dense = vec![0; HLL_REGISTERS];
+                    for (index, rank) in registers.drain() {
+                        dense[index as usize] = rank;
+                    }
+                    *self = Self::Dense(dense);
+                }
+            }
+            Self::Dense(registers) => {
+                let register = &mut registers[index as usize];
+                *register = (*register).max(rank);
+            }
+        }
+    }
+
+    fn merge(&mut self, other: &Self) {
+        match other {
+            Self::Sparse(registers) => {
+                for (index, rank) in registers {
+                    self.set_register(*index, *rank);
+                }
+            }
+            Self::Dense(registers) => {
+                for (index, rank) in registers.iter().enumerate().filter(|(_, r)| **r > 0) {
+                    self.set_register(index as u16, *rank);
+                }
+            }
+        }
+    }
+
+    fn estimate(&self) -> u64 {
+        let m = HLL_REGISTERS as f64;
+        let (sum, zeros) = match self {
+            Self::Sparse(registers) => {
+                let zeros = HLL_REGISTERS - registers.len();
+                let sum = registers
+                    .values()
+                    .map(|r| 2f64.powi(-(*r as i32)))
+                    .sum::<f64>();
+                (sum + zeros as f64, zeros)
+            }
+            Self::Dense(registers) => (
+                registers.iter().map(|r| 2f64.powi(-(*r as i32))).sum(),
+                registers.iter().filter(|r| **r == 0).count(),
+            ),
+        };
+
+        let alpha = 0.7213 / (1.0 + 1.079 / m);
+        let estimate = alpha * m * m / sum;
+        let estimate = if estimate <= 2.5 * m && zeros > 0 {
+            // linear counting is more accurate for small cardinalities
+            m * (m / zeros as f64).ln()
+        } else {
+            estimate
+        };
+        estimate.round() as u64
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            Self::Sparse(registers) => {
+                let mut bytes = Vec::with_capacity(1 + 3 * registers.len());
+                bytes.push(Self::SPARSE);
+                for (index, rank) in registers {
+                    bytes.extend_from_slice(&index.to_le_bytes());
+                    bytes.push(*rank);
+                }
+                bytes
+            }
+            Self::Dense(registers) => {
+                let mut bytes = Vec::with_capacity(1 + registers.len());
+                bytes.push(Self::DENSE);
+                bytes.extend_from_slice(registers);
+                bytes
+            }
+        }
+    }
+
+    fn try_from_bytes(bytes: &[u8]) -> DataFusionResult<Self> {
+        match bytes.split_first() {
+            Some((&Self::SPARSE, entries)) if entries.len() % 3 == 0 => Ok(Self::Sparse(
+                entries
+                    .chunks_exact(3)
+                    .map(|e| (u16::from_le_bytes([e[0], e[1]]), e[2]))
+                    .collect(),
+            )),
+            Some((&Self::DENSE, registers)) if registers.len() == HLL_REGISTERS => {
+                Ok(Self::Dense(registers.to_vec()))
+            }
+            _ => Err(DataFusionError::Internal(format!(
+                "Internal error: invalid {} state of {} bytes",
+                APPROX_DISTINCT_HLL_UDAF_NAME,
+                bytes.len()
+            ))),
+        }
+    }
+
+    fn size(&self) -> usize {
+        match self {
+            Self::Sparse(registers) => registers.capacity() * std::mem::size_of::<(u16, u8)>(),
+            Self::Dense(registers) => registers.capacity(),
+        }
+    }
+}
+
+/// Accumulates the distinct values of `approx_distinct_hll`.
+#[derive(Debug, Default)]
+struct HyperLogLogAccumulator {
+    hll: HyperLogLog,
+}
+
+impl Accumulator for HyperLogLogAccumulator {
+    fn state(&self) -> DataFusionResult<Vec<ScalarValue>> {
+        Ok(vec![ScalarValue::Binary(Some(self.hll.to_bytes()))])
+    }
+
+    fn update_batch(&mut self, values: &[ArrayRef]) -> DataFusionResult<()> {
+        match values.first() {
+            Some(values) => for_each_hash(values, &mut |hash| self.hll.add_hash(hash)),
+            None => Ok(()),
+        }
+    }
+
+    fn merge_batch(&mut self, states: &[ArrayRef]) -> DataFusionResult<()> {
+        let states = match states.first() {
+            Some(states) => as_array::<BinaryArray>(states)?,
+            None => return Ok(()),
+        };
+
+        for state in states.iter().flatten() {
+            self.hll.merge(&HyperLogLog::try_from_bytes(state)?);
+        }
+        Ok(())
+    }
+
+    fn evaluate(&self) -> DataFusionResult<ScalarValue> {
+        Ok(ScalarValue::UInt64(Some(self.hll.estimate())))
+    }
+
+    fn size(&self) -> usize {
+        std::mem::size_of_val(self) + self.hll.size()
+    }
+}
+
+/// Call `f` with the hash of every non-`NULL` value of `values`.
+///
+/// Equal values of the same type have the same hash, also in different
+/// accumulators, so their sketches can be merged.
+fn for_each_hash(values: &ArrayRef, f: &mut dyn FnMut(u64)) -> DataFusionResult<()> {
+    fn hash(v: impl Hash) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        v.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    match values.data_type() {
+        DataType::Dictionary(_, value_type) => {
+            for_each_hash(&compute::cast(values, value_type)?, f)?
+        }
+        DataType::Boolean => as_array::<BooleanArray>(values)?
+            .iter()
+            .flatten()
+            .for_each(|v| f(hash(v))),
+        DataType::Utf8 => as_array::<StringArray>(values)?
+            .iter()
+            .flatten()
+            .for_each(|v| f(hash(v))),
+        DataType::LargeUtf8 => as_array::<LargeStringArray>(values)?
+            .iter()
+            .flatten()
+            .for_each(|v| f(hash(v))),
+        DataType::Int8
+        | DataType::Int16
+        | DataType::Int32
+        | DataType::Int64
+        | DataType::Timestamp(_, _) => {
+            let values = compute::cast(values, &DataType::Int64)?;
+            as_array::<Int64Array>(&values)?
+                .iter()
+                .flatten()
+                .for_each(|v| f(hash(v)))
+        }
+        DataType::UInt8 | DataType::UInt16 | DataType::UInt32 | DataType::UInt64 => {
+            let values = compute::cast(values, &DataType::UInt64)?;
+            as_array::<UInt64Array>(&values)?
+                .iter()
+                .flatten()
+                .for_each(|v| f(hash(v)))
+        }
+        DataType::Float16 | DataType::Float32 | DataType::Float64 => {
+            let values = compute::cast(values, &DataType::Float64)?;
+            as_array::<Float64Array>(&values)?
+                .iter()
+                .flatten()
+                // -0.0 and 0.0 are the same value
+                .for_each(|v| f(hash((v + 0.0).to_bits())))
+        }
+        _ => for_each_hash(&compute::cast(values, &DataType::Utf8)?, f)?,
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Centroid {
+    mean: f64,
+    weight: f64,
+}
+
+/// A merging t-digest, which summarizes the distribution of values by a
+/// bounded number of centroids that are smaller towards the extremes.
+///
+/// See <https://arxiv.org/abs/1902.04023>.
+#[derive(Debug, Default, PartialEq)]
+struct TDigest {
+    /// The centroids, ordered by mean.
+    centroids: Vec<Centroid>,
+    min: f64,
+    max: f64,
+}
+
+impl TDigest {
+    /// Merge `centroids` into the digest.
+    fn merge(&mut self, centroids: impl IntoIterator<Item = Centroid>, min: f64, max: f64) {
+        let mut items = self.centroids.drain(..).collect::<Vec<_>>();
+        let len = items.len();
+        items.extend(centroids);
+        if items.len() == len {
+            self.centroids = items;
+            return;
+        }
+        if len == 0 {
+            (self.min, self.max) = (min, max);
+        } else {
+            (self.min, self.max) = (self.min.min(min), self.max.max(max));
+        }
+        items.sort_by(|a, b| a.mean.total_cmp(&b.mean));
+
+        // The scale function maps a quantile to the index of its centroid,
+        // so that a centroid covers at most a difference of one.
+        let k = |q: f64| TDIGEST_COMPRESSION / (2.0 * PI) * (2.0 * q - 1.0).asin();
+        let q = |k: f64| {
+            ((k * 2.0 * PI / TDIGEST_COMPRESSION)
+                .clamp(-PI / 2.0, PI / 2.0)
+                .sin()
+                + 1.0)
+                / 2.0
+        };
+
+        let total = items.iter().map(|c| c.weight).sum::<f64>();
+        let mut items = items.into_iter();
+        let mut current = items.next().expect("checked non-empty");
+        let mut weight_so_far = current.weight;
+        let mut weight_limit = total * q(k(0.0) + 1.0);
+        for next in items {
+            if weight_so_far + next.weight <= weight_limit {
+                current.weight += next.weight;
+                current.mean += (next.mean - current.mean) * next.weight / current.weight;
+            } else {
+                self.centroids.push(current);
+                weight_limit = total * q(k(weight_so_far / total) + 1.0);
+                current = next;
+            }
+            weight_so_far += next.weight;
+        }
+        self.centroids.push(current);
+    }
+
+    /// Estimate the value at quantile `quantile` (between 0 and 1).
+    ///
+    /// The estimate interpolates between the means of the centroids, which
+    /// are placed at the middle of their weight, and the minimum and maximum
+    /// value at the ends.
+    fn quantile(&self, quantile: f64) -> Option<f64> {
+        if self.centroids.is_empty() {
+            return None;
+        }
+
+        let total = self.centroids.iter().map(|c| c.weight).sum::<f64>();
+        let target = quantile * total;
+
+        let mut prev = (0.0, self.min);
+        let mut weight_so_far = 0.0;
+        let points = self
+            .centroids
+            .iter()
+            .map(|c| {
+                let point = (weight_so_far + c.weight / 2.0, c.mean);
+                weight_so_far += c.weight;
+                point
+            })
+            .chain(std::iter::once((total, self.max)));
+        for (position, value) in points {
+            if target <= position {
+                let (prev_position, prev_value) = prev;
+                if position <= prev_position {
+                    return Some(value);
+                }
+                let fraction = (target - prev_position) / (position - prev_position);
+                return Some(prev_value + fraction * (value - prev_value));
+            }
+            prev = (position, value);
+        }
+        Some(self.max)
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(8 * (2 + 2 * self.centroids.len()));
+        bytes.extend_from_slice(&self.min.to_le_bytes());
+        bytes.extend_from_slice(&self.max.to_le_bytes());
+        for c in &self.centroids {
+            bytes.extend_from_slice(&c.mean.to_le_bytes());
+            bytes.extend_from_slice(&c.weight.to_le_bytes());
+        }
+        bytes
+    }
+
+    fn try_from_bytes(bytes: &[u8]) -> DataFusionResult<Self> {
+        if bytes.len() < 16 || bytes.len() % 16 != 0 {
+            return Err(DataFusionError::Internal(format!(
+                "Internal error: invalid {} state of {} bytes",
+                APPROX_PERCENTILE_TDIGEST_UDAF_NAME,
+                bytes.len()
+            )));
+        }
+
+        let mut floats = bytes
+            .chunks_exact(8)
+            .map(|b| f64::from_le_bytes(b.try_into().expect("chunks of 8 bytes")));
+        let min = floats.next().expect("checked length");
+        let max = floats.next().expect("checked length");
+        let mut centroids = vec![];
+        while let (Some(mean), Some(weight)) = (floats.next(), floats.next()) {
+            centroids.push(Centroid { mean, weight });
+        }
+
+        Ok(Self {
+            centroids,
+            min,
+            max,
+        })
+    }
+}
+
+/// Accumulates the distribution of the values of `approx_percentile_tdigest`.
+#[derive(Debug, Default)]
+struct TDigestAccumulator {
+    digest: TDigest,
+    /// The percentile, between 0 and 100.
+    percentile: Option<f64>,
+}
+
+impl TDigestAccumulator {
+    fn set_percentile(&mut self, percentile: &ArrayRef) -> DataFusionResult<()> {
+        if percentile.is_empty() || percentile.is_null(0) {
+            return Ok(());
+        }
+
+        let percentile = match ScalarValue::try_from_array(percentile, 0)? {
+            ScalarValue::Float64(Some(p)) => p,
+            ScalarValue::Int64(Some(p)) => p as f64,
+            v => {
+                return Err(DataFusionError::Plan(format!(
+                    "{} requires a numeric percentile, got {}",
+                    APPROX_PERCENTILE_TDIGEST_UDAF_NAME, v
+                )))
+            }
+        };
+        if !(0.0..=100.0).contains(&percentile) {
+            return Err(DataFusionError::Plan(format!(
+                "{} requires a percentile between 0 and 100, got {}",
+                APPROX_PERCENTILE_TDIGEST_UDAF_NAME, percentile
+            )));
+        }
+        self.percentile = Some(percentile);
+        Ok(())
+    }
+}
+
+impl Accumulator for TDigestAccumulator {
+    fn state(&self) -> DataFusionResult<Vec<ScalarValue>> {
+        Ok(vec![
+            ScalarValue::Binary(Some(self.digest.to_bytes())),
+            ScalarValue::Float64(self.percentile),
+        ])
+    }
+
+    fn update_batch(&mut self, values: &[ArrayRef]) -> DataFusionResult<()> {
+        if values.is_empty() {
+            return Ok(());
+        }
+        if values.len() != 2 {
+            return Err(DataFusionError::Internal(format!(
+                "Internal error: Expected 2 arguments passed to {} but got {}",
+                APPROX_PERCENTILE_TDIGEST_UDAF_NAME,
+                values.len()
+            )));
+        }
+
+        self.set_percentile(&values[1])?;
+
+        let value_arr = compute::cast(&values[0], &DataType::Float64)?;
+        let values = as_array::<Float64Array>(&value_arr)?
+            .iter()
+            .flatten()
+            .filter(|v| !v.is_nan())
+            .collect::<Vec<_>>();
+        let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+        let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        self.digest.merge(
+            values
+                .into_iter()
+                .map(|mean| Centroid { mean, weight: 1.0 }),
+            min,
+            max,
+        );
+        Ok(())
+    }
+
+    fn merge_batch(&mut self, states: &[ArrayRef]) -> DataFusionResult<()> {
+        if states.is_empty() {
+            return Ok(());
+        }
+        if states.len() != 2 {
+            return Err(DataFusionError::Internal(format!(
+                "Internal error: Expected 2 states passed to {} but got {}",
+                APPROX_PERCENTILE_TDIGEST_UDAF_NAME,
+                states.len()
+            )));
+        }
+
+        let digests = as_array::<BinaryArray>(&states[0])?;
+        let percentiles = as_array::<Float64Array>(&states[1])?;
+        for i in 0..digests.len() {
+            if percentiles.is_valid(i) {
+                self.percentile = Some(percentiles.value(i));
+            }
+            if digests.is_null(i) {
+                continue;
+            }
+
+            let digest = TDigest::try_from_bytes(digests.value(i))?;
+            self.digest.merge(digest.centroids, digest.min, digest.max);
+        }
+        Ok(())
+    }
+
+    fn evaluate(&self) -> DataFusionResult<ScalarValue> {
+        let percentile = self.percentile.unwrap_or(50.0);
+        Ok(ScalarValue::Float64(
+            self.digest.quantile(percentile / 100.0),
+        ))
+    }
+
+    fn size(&self) -> usize {
+        std::mem::size_of_val(self)
+            + self.digest.centroids.capacity() * std::mem::size_of::<Centroid>()
+    }
+}
+
+fn as_array<T: 'static>(arr: &ArrayRef) -> DataFusionResult<&T> {
+    arr.as_any().downcast_ref::<T>().ok_or_else(|| {
+        DataFusionError::Internal(format!(
+            "Internal error: unexpected array of type {}",
+            arr.data_type()
+        ))
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use arrow::{array::DictionaryArray, datatypes::Int32Type, record_batch::RecordBatch};
+    use datafusion::{assert_batches_eq, datasource::MemTable, prelude::SessionContext};
+
+    use super::*;
+
+    fn accumulator(udaf: &AggregateUDF) -> Box<dyn Accumulator> {
+        (udaf.accumulator)(&DataType::Float64).unwrap()
+    }
+
+    fn state(acc: &dyn Accumulator) -> Vec<ArrayRef> {
+        acc.state()
+            .unwrap()
+            .into_iter()
+            .map(|s| s.to_array())
+            .collect()
+    }
+
+    fn percentile_args(values: Vec<f64>, percentile: i64) -> Vec<ArrayRef> {
+        let len = values.len();
+        vec![
+            Arc::new(Float64Array::from(values)),
+            ScalarValue::Int64(Some(percentile)).to_array_of_size(len),
+        ]
+    }
+
+    fn assert_within(got: f64, expected: f64, error: f64) {
+        assert!(
+            (got - expected).abs() <= error * expected,
+            "got {}, expected {} within {}",
+            got,
+            expected,
+            error
+        );
+    }
+
+    #[test]
+    fn test_approx_distinct() {
+        let mut acc = accumulator(&APPROX_DISTINCT_HLL);
+        assert_eq!(acc.evaluate().unwrap(), ScalarValue::UInt64(Some(0)));
+
+        let values: ArrayRef = Arc::new(StringArray::from(vec![
+            Some("a"),
+            Some("b"),
+            None,
+            Some("a"),
+        ]));
+        acc.update_batch(&[values]).unwrap();
+        assert_eq!(acc.evaluate().unwrap(), ScalarValue::UInt64(Some(2)));
+
+        // dictionaries hash like their values
+        let values: ArrayRef = Arc::new(
+            vec!["a", "c"]
+                .into_iter()
+                .collect::<DictionaryArray<Int32Type>>(),
+        );
+        acc.update_batch(&[values]).unwrap();
+        assert_eq!(acc.evaluate().unwrap(), ScalarValue::UInt64(Some(3)));
+    }
+
+    #[test]
+    fn test_approx_distinct_merge() {
+        // overlapping ranges of 60k values in total, enough for dense sketches
+        let mut acc1 = accumulator(&APPROX_DISTINCT_HLL);
+        let mut acc2 = accumulator(&APPROX_DISTINCT_HLL);
+        acc1.update_batch(&[Arc::new(Int64Array::from_iter_values(0..40_000)) as ArrayRef])
+            .unwrap();
+        acc2.update_batch(&[Arc::new(Int64Array::from_iter_values(20_000..60_000)) as ArrayRef])
+            .unwrap();
+
+        let mut merged = accumulator(&APPROX_DISTINCT_HLL);
+        merged.merge_batch(&state(acc1.as_ref())).unwrap();
+        merged.merge_batch(&state(acc2.as_ref())).unwrap();
+
+        let got = match merged.evaluate().unwrap() {
+            ScalarValue::UInt64(Some(n)) => n as f64,
+            v => panic!("unexpected value {}", v),
+        };
+        assert_within(got, 60_000.0, 0.03);
+    }
+
+    #[test]
+    fn test_approx_percentile() {
+        let mut acc = accumulator(&APPROX_PERCENTILE_TDIGEST);
+        assert_eq!(acc.evaluate().unwrap(), ScalarValue::Float64(None));
+
+        acc.update_batch(&percentile_args(vec![3.0, 1.0, 2.0], 50))
+            .unwrap();
+        assert_eq!(acc.evaluate().unwrap(), ScalarValue::Float64(Some(2.0)));
+
+        let mut acc = accumulator(&APPROX_PERCENTILE_TDIGEST);
+        acc.update_batch(&percentile_args(vec![3.0, 1.0, 2.0], 100))
+            .unwrap();
+        assert_eq!(acc.evaluate().unwrap(), ScalarValue::Float64(Some(3.0)));
+
+        let mut acc = accumulator(&APPROX_PERCENTILE_TDIGEST);
+        let err = acc
+            .update_batch(&percentile_args(vec![1.0], 101))
+            .unwrap_err();
+        assert!(err.to_string().contains("between 0 and 100"), "{}", err);
+    }
+
+    #[test]
+    fn test_approx_percentile_merge() {
+        // two halves of 1..=100_000 in separate accumulators
+        let mut acc1 = accumulator(&APPROX_PERCENTILE_TDIGEST);
+        let mut acc2 = accumulator(&APPROX_PERCENTILE_TDIGEST);
+        for i in 0..100 {
+            let values = (1..=1_000).map(|v| (i * 1_000 + v) as f64).collect();
+            let acc = if i % 2 == 0 { &mut acc1 } else { &mut acc2 };
+            acc.update_batch(&percentile_args(values, 99)).unwrap();
+        }
+
+        let mut merged = accumulator(&APPROX_PERCENTILE_TDIGEST);
+        merged.merge_batch(&state(acc1.as_ref())).unwrap();
+        merged.merge_batch(&state(acc2.as_ref())).unwrap();
+
+        let got = match merged.evaluate().unwrap() {
+            ScalarValue::Float64(Some(v)) => v,
+            v => panic!("unexpected value {}", v),
+        };
+        assert_within(got, 99_000.0, 0.005);
+    }
+
+    #[test]
+    fn test_tdigest_compression() {
+        let mut digest = TDigest::default();
+        for i in 0..100 {
+            let values = (0..1_000).map(|v| Centroid {
+                mean: (v * 100 + i) as f64,
+                weight: 1.0,
+            });
+            digest.merge(values, i as f64, (99_900 + i) as f64);
+        }
+
+        assert!(
+            digest.centroids.len() <= TDIGEST_COMPRESSION as usize,
+            "{}",
+            digest.centroids.len()
+        );
+        assert_eq!(
+            digest.centroids.iter().map(|c| c.weight).sum::<f64>(),
+            100_000.0
+        );
+        assert_eq!((digest.min, digest.max), (0.0, 99_999.0));
+        assert_within(digest.quantile(0.5).unwrap(), 50_000.0, 0.01);
+
+        let roundtrip = TDigest::try_from_bytes(&digest.to_bytes()).unwrap();
+        assert_eq!(roundtrip, digest);
+    }
+
+    #[tokio::test]
+    async fn test_sql() {
+        let batch = RecordBatch::try_from_iter(vec![
+            (
+                "host",
+                Arc::new(StringArray::from(vec!["a", "b", "a", "c"])) as ArrayRef,
+            ),
+            (
+                "v",
+                Arc::new(Int64Array::from(vec![10, 20, 30, 40])) as ArrayRef,
+            ),
+        ])
+        .unwrap();
+        let provider =
+            MemTable::try_new(batch.schema(), vec![vec![batch.clone()], vec![batch]]).unwrap();
+
+        let ctx = SessionContext::new();
+        ctx.register_udaf(APPROX_DISTINCT_HLL.as_ref().clone());
+        ctx.register_udaf(APPROX_PERCENTILE_TDIGEST.as_ref().clone());
+        ctx.register_table("t", Arc::new(provider)).unwrap();
+
+        let got = ctx
+            .sql(
+                "SELECT approx_distinct_hll(host) AS d, approx_percentile_tdigest(v, 0) AS p0, \
+                approx_percentile_tdigest(v, 100.0) AS p100 FROM t",
+            )
+            .await
+            .unwrap()
+            .collect()
+            .await
+            .unwrap();
+
+        assert_batches_eq!(
+            [
+                "+---+----+------+",
+                "| d | p0 | p100 |",
+                "+---+----+------+",
+                "| 3 | 10 | 40   |",
+                "+---+----+------+",
+            ],
+            &got
+        );
+    }
+}
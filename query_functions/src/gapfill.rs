@@ -0,0 +1,157 @@
+//! Scalar functions to support queries that perform gap filling.
+//!
+//! These functions are placeholders: a query using them is rewritten by the
+//! IOx logical optimizer into a gap-filling plan, and the functions themselves
+//! are never invoked. If a query uses them in a way the optimizer cannot
+//! rewrite (e.g. `locf` without `date_bin_gapfill`), an error is returned when
+//! the query is executed.
+//!
+//! For example, this query produces a row for every minute between the time
+//! bounds in the `WHERE` clause, for each region, carrying the last observed
+//! average forward into the minutes without data:
+//!
+//! ```sql
+//! SELECT
+//!   date_bin_gapfill(INTERVAL '1 minute', time, TIMESTAMP '1970-01-01T00:00:00Z') AS minute,
+//!   region,
+//!   locf(avg(cpu))
+//! FROM t
+//! WHERE time >= '2023-01-01T00:00:00Z' AND time < '2023-01-01T01:00:00Z'
+//! GROUP BY minute, region
+//! ```
+use std::sync::Arc;
+
+use arrow::datatypes::{DataType, IntervalUnit};
+use datafusion::{
+    error::DataFusionError,
+    logical_expr::{
+        ReturnTypeFunction, ScalarFunctionImplementation, ScalarUDF, Signature, TypeSignature,
+        Volatility,
+    },
+};
+use once_cell::sync::Lazy;
+use schema::TIME_DATA_TYPE;
+
+/// The name of the date_bin_gapfill UDF given to DataFusion.
+pub const DATE_BIN_GAPFILL_UDF_NAME: &str = "date_bin_gapfill";
+
+/// The name of the locf UDF given to DataFusion.
+pub const LOCF_UDF_NAME: &str = "locf";
+
+/// The name of the interpolate UDF given to DataFusion.
+pub const INTERPOLATE_UDF_NAME: &str = "interpolate";
+
+/// Placeholder for `date_bin_gapfill(stride, time[, origin])`.
+///
+/// Behaves like DataFusion's `date_bin`, but additionally produces a row for
+/// every bucket within the time bounds of the query that has no input rows,
+/// for each group.
+pub(crate) static DATE_BIN_GAPFILL: Lazy<Arc<ScalarUDF>> = Lazy::new(|| {
+    let signatures: Vec<_> = [IntervalUnit::DayTime, IntervalUnit::MonthDayNano]
+        .into_iter()
+        .flat_map(|unit| {
+            let stride = DataType::Interval(unit);
+            [
+                TypeSignature::Exact(vec![stride.clone(), TIME_DATA_TYPE()]),
+                TypeSignature::Exact(vec![stride, TIME_DATA_TYPE(), TIME_DATA_TYPE()]),
+            ]
+        })
+        .collect();
+
+    let return_type: ReturnTypeFunction = Arc::new(|_| Ok(Arc::new(TIME_DATA_TYPE())));
+    Arc::new(ScalarUDF::new(
+        DATE_BIN_GAPFILL_UDF_NAME,
+        &Signature::one_of(signatures, Volatility::Immutable),
+        &return_type,
+        &unimplemented_udf(DATE_BIN_GAPFILL_UDF_NAME),
+    ))
+});
+
+/// Placeholder for `locf(aggregate)`: fill the gaps in the aggregate with the
+/// last observed non-null value of its group.
+pub(crate) static LOCF: Lazy<Arc<ScalarUDF>> = Lazy::new(|| fill_udf(LOCF_UDF_NAME));
+
+/// Placeholder for `interpolate(aggregate)`: fill the gaps in a numeric
+/// aggregate by linear interpolation between the surrounding non-null values
+/// of its group.
+pub(crate) static INTERPOLATE: Lazy<Arc<ScalarUDF>> = Lazy::new(|| fill_udf(INTERPOLATE_UDF_NAME));
+
+/// A fill function takes a single argument and returns a value of the same
+/// type.
+fn fill_udf(name: &'static str) -> Arc<ScalarUDF> {
+    let return_type: ReturnTypeFunction = Arc::new(|args| Ok(Arc::new(args[0].clone())));
+    Arc::new(ScalarUDF::new(
+        name,
+        &Signature::any(1, Volatility::Immutable),
+        &return_type,
+        &unimplemented_udf(name),
+    ))
+}
+
+fn unimplemented_udf(name: &'static str) -> ScalarFunctionImplementation {
+    Arc::new(move |_| {
+        Err(DataFusionError::NotImplemented(format!(
+            "{} is only supported in a gap-filling aggregate query using {} in its GROUP BY clause",
+            name, DATE_BIN_GAPFILL_UDF_NAME
+        )))
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use arrow::{
+        array::{ArrayRef, Float64Array, TimestampNanosecondArray},
+        record_batch::RecordBatch,
+    };
+    use datafusion::prelude::col;
+    use datafusion_util::context_with_table;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_fill_functions_not_invoked() {
+        let batch = RecordBatch::try_from_iter(vec![
+            (
+                "time",
+                Arc::new(TimestampNanosecondArray::from(vec![Some(1000)])) as ArrayRef,
+            ),
+            (
+                "v",
+                Arc::new(Float64Array::from(vec![Some(1.0)])) as ArrayRef,
+            ),
+        ])
+        .unwrap();
+        let ctx = context_with_table(batch);
+
+        for udf in [&*LOCF, &*INTERPOLATE] {
+            let err = ctx
+                .table("t")
+                .unwrap()
+                .select(vec![udf.call(vec![col("v")])])
+                .unwrap()
+                .collect()
+                .await
+                .expect_err("fill function should not be executable");
+
+            assert!(
+                err.to_string().contains("gap-filling aggregate query"),
+                "unexpected error: {}",
+                err
+            );
+        }
+
+        // The return type of a fill function is that of its argument.
+        let df = ctx
+            .table("t")
+            .unwrap()
+            .select(vec![LOCF.call(vec![col("v")]).alias("v")])
+            .unwrap();
+        assert_eq!(
+            df.schema()
+                .field_with_unqualified_name("v")
+                .unwrap()
+                .data_type(),
+            &DataType::Float64
+        );
+    }
+}
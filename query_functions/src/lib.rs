@@ -18,21 +18,34 @@ use datafusion::{
 use group_by::WindowDuration;
 use window::EncodedWindowDuration;
 
+/// Approximate aggregate expressions
+pub mod approx;
+
 /// Grouping by structs
 pub mod group_by;
 
+/// PromQL functions
+pub mod promql;
+
 /// Regular Expressions
 mod regex;
 
+/// Gap filling expressions
+mod gapfill;
+
 /// Flux selector expressions
 pub mod selectors;
 
+/// InfluxQL transformation expressions
+pub mod transformations;
+
 /// window_bounds expressions
 mod window;
 
 /// Function registry
 mod registry;
 
+pub use crate::gapfill::{DATE_BIN_GAPFILL_UDF_NAME, INTERPOLATE_UDF_NAME, LOCF_UDF_NAME};
 pub use crate::regex::clean_non_meta_escapes;
 pub use crate::regex::REGEX_MATCH_UDF_NAME;
 pub use crate::regex::REGEX_NOT_MATCH_UDF_NAME;
@@ -63,6 +76,32 @@ pub fn regex_not_match_expr(input: Expr, pattern: String) -> Expr {
         .call(vec![input, lit(pattern)])
 }
 
+/// Return an Expr that invokes `approx_distinct_hll`, the approximate
+/// number of distinct values of `input`. Equivalent to:
+///
+/// ```text
+/// COUNT(DISTINCT input)
+/// ```
+pub fn approx_distinct_expr(input: Expr) -> Expr {
+    registry()
+        .udaf(approx::APPROX_DISTINCT_HLL_UDAF_NAME)
+        .expect("ApproxDistinctHll function not registered")
+        .call(vec![input])
+}
+
+/// Return an Expr that invokes `approx_percentile_tdigest`, the approximate
+/// `percentile` (between 0 and 100) of the values of `input`. Equivalent to:
+///
+/// ```text
+/// PERCENTILE(input, percentile)
+/// ```
+pub fn approx_percentile_expr(input: Expr, percentile: f64) -> Expr {
+    registry()
+        .udaf(approx::APPROX_PERCENTILE_TDIGEST_UDAF_NAME)
+        .expect("ApproxPercentileTdigest function not registered")
+        .call(vec![input, lit(percentile)])
+}
+
 /// Create a DataFusion `Expr` that invokes `window_bounds` with the
 /// appropriate every and offset arguments at runtime
 pub fn make_window_bound_expr(
@@ -106,7 +145,7 @@ pub fn register_scalar_functions(mut state: SessionState) -> SessionState {
 #[cfg(test)]
 mod test {
     use arrow::{
-        array::{ArrayRef, StringArray, TimestampNanosecondArray},
+        array::{ArrayRef, Int64Array, StringArray, TimestampNanosecondArray},
         record_batch::RecordBatch,
     };
     use datafusion::{assert_batches_eq, prelude::col};
@@ -172,6 +211,49 @@ mod test {
         assert_batches_eq!(&expected, &result);
     }
 
+    /// plumbing test to validate registry is connected. functions are
+    /// tested more thoroughly in their own modules
+    #[tokio::test]
+    async fn test_approx_exprs() {
+        let batch = RecordBatch::try_from_iter(vec![
+            (
+                "host",
+                Arc::new(StringArray::from(vec!["a", "b", "a"])) as ArrayRef,
+            ),
+            (
+                "v",
+                Arc::new(Int64Array::from(vec![10, 20, 30])) as ArrayRef,
+            ),
+        ])
+        .unwrap();
+
+        let ctx = context_with_table(batch);
+        let result = ctx
+            .table("t")
+            .unwrap()
+            .aggregate(
+                vec![],
+                vec![
+                    approx_distinct_expr(col("host")).alias("distinct"),
+                    approx_percentile_expr(col("v"), 100.0).alias("max"),
+                ],
+            )
+            .unwrap()
+            .collect()
+            .await
+            .unwrap();
+
+        let expected = vec![
+            "+----------+-----+",
+            "| distinct | max |",
+            "+----------+-----+",
+            "| 2        | 30  |",
+            "+----------+-----+",
+        ];
+
+        assert_batches_eq!(&expected, &result);
+    }
+
     /// plumbing test to validate registry is connected. functions are
     /// tested more thoroughly in their own modules
     #[tokio::test]
@@ -0,0 +1,674 @@
+//! Implementation of PromQL functions that aggregate the samples of a series.
+//!
+//! The functions are DataFusion user defined aggregate functions, used by the
+//! PromQL planner with one group per series and evaluation timestamp:
+//!
+//! | function                                             | returns |
+//! |------------------------------------------------------|---------|
+//! | `promql_rate(value, time, range_end, range)`         | the per-second average rate of increase of a counter, as PromQL `rate` |
+//! | `promql_irate(value, time, range_end, range)`        | the per-second rate of increase between the last two samples, as PromQL `irate` |
+//! | `promql_increase(value, time, range_end, range)`     | the increase of a counter, as PromQL `increase` |
+//! | `promql_histogram_quantile(quantile, le, value)`     | the `quantile` of the histogram with bucket bounds `le` and cumulative counts `value`, as PromQL `histogram_quantile` |
+//!
+//! `range_end` and `range` are the end (in nanoseconds since the epoch) and
+//! the length (in nanoseconds) of the range the samples were selected from,
+//! which the counter functions extrapolate their result to.
+//!
+//! All the results are `Float64`, and `NULL` if there are too few samples.
+use std::sync::Arc;
+
+use arrow::{
+    array::{Array, ArrayRef, Float64Array, Int64Array, ListArray, StringArray},
+    compute,
+    datatypes::{DataType, Field},
+};
+use datafusion::{
+    error::{DataFusionError, Result as DataFusionResult},
+    logical_expr::{
+        AccumulatorFunctionImplementation, ReturnTypeFunction, Signature, StateTypeFunction,
+        Volatility,
+    },
+    physical_plan::{udaf::AggregateUDF, Accumulator},
+    scalar::ScalarValue,
+};
+use once_cell::sync::Lazy;
+use schema::TIME_DATA_TYPE;
+
+/// The name of the promql_rate UDAF given to DataFusion.
+pub const PROMQL_RATE_UDAF_NAME: &str = "promql_rate";
+
+/// The name of the promql_irate UDAF given to DataFusion.
+pub const PROMQL_IRATE_UDAF_NAME: &str = "promql_irate";
+
+/// The name of the promql_increase UDAF given to DataFusion.
+pub const PROMQL_INCREASE_UDAF_NAME: &str = "promql_increase";
+
+/// The name of the promql_histogram_quantile UDAF given to DataFusion.
+pub const PROMQL_HISTOGRAM_QUANTILE_UDAF_NAME: &str = "promql_histogram_quantile";
+
+const NANOS_PER_SECOND: f64 = 1_000_000_000.0;
+
+pub(crate) static PROMQL_RATE: Lazy<Arc<AggregateUDF>> =
+    Lazy::new(|| make_range_udaf(PROMQL_RATE_UDAF_NAME, RangeFunction::Rate));
+
+pub(crate) static PROMQL_IRATE: Lazy<Arc<AggregateUDF>> =
+    Lazy::new(|| make_range_udaf(PROMQL_IRATE_UDAF_NAME, RangeFunction::IRate));
+
+pub(crate) static PROMQL_INCREASE: Lazy<Arc<AggregateUDF>> =
+    Lazy::new(|| make_range_udaf(PROMQL_INCREASE_UDAF_NAME, RangeFunction::Increase));
+
+pub(crate) static PROMQL_HISTOGRAM_QUANTILE: Lazy<Arc<AggregateUDF>> = Lazy::new(|| {
+    let return_type_func: ReturnTypeFunction = Arc::new(|_| Ok(Arc::new(DataType::Float64)));
+    let accumulator: AccumulatorFunctionImplementation =
+        Arc::new(|_| Ok(Box::<HistogramQuantileAccumulator>::default()));
+    let state_type: StateTypeFunction = Arc::new(|_| {
+        Ok(Arc::new(vec![
+            DataType::List(Box::new(Field::new("item", DataType::Float64, true))),
+            DataType::List(Box::new(Field::new("item", DataType::Float64, true))),
+            DataType::Float64,
+        ]))
+    });
+
+    Arc::new(AggregateUDF::new(
+        PROMQL_HISTOGRAM_QUANTILE_UDAF_NAME,
+        &Signature::any(3, Volatility::Immutable),
+        &return_type_func,
+        &accumulator,
+        &state_type,
+    ))
+});
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RangeFunction {
+    Rate,
+    IRate,
+    Increase,
+}
+
+fn make_range_udaf(name: &'static str, function: RangeFunction) -> Arc<AggregateUDF> {
+    let return_type_func: ReturnTypeFunction = Arc::new(|_| Ok(Arc::new(DataType::Float64)));
+
+    let accumulator: AccumulatorFunctionImplementation = Arc::new(move |_| {
+        let accumulator: Box<dyn Accumulator> = Box::new(RangeAccumulator::new(name, function));
+        Ok(accumulator)
+    });
+
+    let state_type: StateTypeFunction = Arc::new(|_| {
+        Ok(Arc::new(vec![
+            DataType::List(Box::new(Field::new("item", DataType::Int64, true))),
+            DataType::List(Box::new(Field::new("item", DataType::Float64, true))),
+            DataType::Int64,
+            DataType::Int64,
+        ]))
+    });
+
+    let signature = Signature::exact(
+        vec![
+            DataType::Float64,
+            TIME_DATA_TYPE(),
+            DataType::Int64,
+            DataType::Int64,
+        ],
+        Volatility::Immutable,
+    );
+
+    Arc::new(AggregateUDF::new(
+        name,
+        &signature,
+        &return_type_func,
+        &accumulator,
+        &state_type,
+    ))
+}
+
+/// Accumulates the (time, value) samples of a series in a range.
+#[derive(Debug)]
+struct RangeAccumulator {
+    name: &'static str,
+    function: RangeFunction,
+    /// The end of the range, in nanoseconds since the epoch.
+    range_end: Option<i64>,
+    /// The length of the range, in nanoseconds.
+    range: Option<i64>,
+    /// The samples, in no particular order.
+    points: Vec<(i64, f64)>,
+}
+
+impl RangeAccumulator {
+    fn new(name: &'static str, function: RangeFunction) -> Self {
+        Self {
+            name,
+            function,
+            range_end: None,
+            range: None,
+            points: vec![],
+        }
+    }
+
+    /// Discard the points that are not needed, i.e. all but the last two for
+    /// `irate`, without ordering the others.
+    fn prune(&mut self) {
+        let len = self.points.len();
+        if self.function == RangeFunction::IRate && len > 2 {
+            self.points.select_nth_unstable_by_key(len - 2, |(t, _)| *t);
+            self.points.drain(..len - 2);
+        }
+    }
+
+    /// The rate or increase of a counter over the range of the `points`
+    /// ordered by time, as computed by `extrapolatedRate` of Prometheus.
+    fn extrapolated_rate(&self, points: &[(i64, f64)], range_end: i64, range: i64) -> Option<f64> {
+        let (first, last) = match points {
+            [first, .., last] => (*first, *last),
+            _ => return None,
+        };
+
+        // counter resets add the value before the reset
+        let mut result = last.1 - first.1;
+        for w in points.windows(2) {
+            if w[1].1 < w[0].1 {
+                result += w[0].1;
+            }
+        }
+
+        let range_start = range_end - range;
+        let mut duration_to_start = (first.0 - range_start) as f64 / NANOS_PER_SECOND;
+        let duration_to_end = (range_end - last.0) as f64 / NANOS_PER_SECOND;
+        let sampled_interval = (last.0 - first.0) as f64 / NANOS_PER_SECOND;
+        let average_duration_between_samples = sampled_interval / (points.len() - 1) as f64;
+
+        // counters can not be extrapolated below zero
+        if result > 0.0 && first.1 >= 0.0 {
+            let duration_to_zero = sampled_interval * (first.1 / result);
+            if duration_to_zero < duration_to_start {
+                duration_to_start = duration_to_zero;
+            }
+        }
+
+        // extrapolate to the range boundaries if the samples are close to
+        // them, and by half an interval otherwise
+        let extrapolation_threshold = average_duration_between_samples * 1.1;
+        let mut extrapolate_to_interval = sampled_interval;
+        for duration in [duration_to_start, duration_to_end] {
+            extrapolate_to_interval += if duration < extrapolation_threshold {
+                duration
+            } else {
+                average_duration_between_samples / 2.0
+            };
+        }
+
+        let result = result * (extrapolate_to_interval / sampled_interval);
+        Some(match self.function {
+            RangeFunction::Rate => result / (range as f64 / NANOS_PER_SECOND),
+            _ => result,
+        })
+    }
+
+    /// The per-second rate between the last two of the `points` ordered by
+    /// time, as computed by `instantValue` of Prometheus.
+    fn instant_rate(points: &[(i64, f64)]) -> Option<f64> {
+        let (prev, last) = match points {
+            [.., prev, last] => (*prev, *last),
+            _ => return None,
+        };
+
+        let duration = (last.0 - prev.0) as f64 / NANOS_PER_SECOND;
+        if duration == 0.0 {
+            return None;
+        }
+
+        let increase = if last.1 < prev.1 {
+            // counter reset
+            last.1
+        } else {
+            last.1 - prev.1
+        };
+        Some(increase / duration)
+    }
+}
+
+impl Accumulator for RangeAccumulator {
+    fn state(&self) -> DataFusionResult<Vec<ScalarValue>> {
+        let times = self
+            .points
+            .iter()
+            .map(|(t, _)| ScalarValue::Int64(Some(*t)))
+            .collect();
+        let values = self
+            .points
+            .iter()
+            .map(|(_, v)| ScalarValue::Float64(Some(*v)))
+            .collect();
+
+        Ok(vec![
+            ScalarValue::new_list(Some(times), DataType::Int64),
+            ScalarValue::new_list(Some(values), DataType::Float64),
+            ScalarValue::Int64(self.range_end),
+            ScalarValue::Int64(self.range),
+        ])
+    }
+
+    fn update_batch(&mut self, values: &[ArrayRef]) -> DataFusionResult<()> {
+        if values.is_empty() {
+            return Ok(());
+        }
+        if values.len() != 4 {
+            return Err(DataFusionError::Internal(format!(
+                "Internal error: Expected 4 arguments passed to {} but got {}",
+                self.name,
+                values.len()
+            )));
+        }
+
+        let value_arr = as_array::<Float64Array>(&values[0])?;
+        let time_arr = compute::cast(&values[1], &DataType::Int64)?;
+        let time_arr = as_array::<Int64Array>(&time_arr)?;
+        let range_end_arr = as_array::<Int64Array>(&values[2])?;
+        let range_arr = as_array::<Int64Array>(&values[3])?;
+
+        // the range is the same for all the samples of a group
+        if let Some(range_end) = range_end_arr.iter().flatten().next() {
+            self.range_end = Some(range_end);
+        }
+        if let Some(range) = range_arr.iter().flatten().next() {
+            self.range = Some(range);
+        }
+
+        self.points.extend(
+            value_arr
+                .iter()
+                .zip(time_arr.iter())
+                .filter_map(|(v, t)| Some((t?, v?))),
+        );
+        self.prune();
+        Ok(())
+    }
+
+    fn merge_batch(&mut self, states: &[ArrayRef]) -> DataFusionResult<()> {
+        if states.is_empty() {
+            return Ok(());
+        }
+        if states.len() != 4 {
+            return Err(DataFusionError::Internal(format!(
+                "Internal error: Expected 4 states passed to {} but got {}",
+                self.name,
+                states.len()
+            )));
+        }
+
+        let times = as_array::<ListArray>(&states[0])?;
+        let values = as_array::<ListArray>(&states[1])?;
+        let range_ends = as_array::<Int64Array>(&states[2])?;
+        let ranges = as_array::<Int64Array>(&states[3])?;
+
+        for i in 0..times.len() {
+            if range_ends.is_valid(i) {
+                self.range_end = Some(range_ends.value(i));
+            }
+            if ranges.is_valid(i) {
+                self.range = Some(ranges.value(i));
+            }
+            if times.is_null(i) || values.is_null(i) {
+                continue;
+            }
+
+            let t = times.value(i);
+            let v = values.value(i);
+            let t = as_array::<Int64Array>(&t)?;
+            let v = as_array::<Float64Array>(&v)?;
+            self.points
+                .extend(v.iter().zip(t.iter()).filter_map(|(v, t)| Some((t?, v?))));
+        }
+        self.prune();
+        Ok(())
+    }
+
+    fn evaluate(&self) -> DataFusionResult<ScalarValue> {
+        // the points are only ordered here, once all of them are accumulated
+        let mut points = self.points.clone();
+        points.sort_by_key(|(t, _)| *t);
+
+        let result = match (self.function, self.range_end, self.range) {
+            (RangeFunction::IRate, _, _) => Self::instant_rate(&points),
+            (_, Some(range_end), Some(range)) if range > 0 => {
+                self.extrapolated_rate(&points, range_end, range)
+            }
+            _ => None,
+        };
+        Ok(ScalarValue::Float64(result))
+    }
+
+    fn size(&self) -> usize {
+        std::mem::size_of_val(self) + self.points.capacity() * std::mem::size_of::<(i64, f64)>()
+    }
+}
+
+/// Accumulates the (upper bound, cumulative count) buckets of a histogram.
+#[derive(Debug, Default)]
+struct HistogramQuantileAccumulator {
+    /// The quantile, between 0 and 1.
+    quantile: Option<f64>,
+    buckets: Vec<(f64, f64)>,
+}
+
+impl Accumulator for HistogramQuantileAccumulator {
+    fn state(&self) -> DataFusionResult<Vec<ScalarValue>> {
+        let bounds = self
+            .buckets
+            .iter()
+            .map(|(b, _)| ScalarValue::Float64(Some(*b)))
+            .collect();
+        let counts = self
+            .buckets
+            .iter()
+            .map(|(_, c)| ScalarValue::Float64(Some(*c)))
+            .collect();
+
+        Ok(vec![
+            ScalarValue::new_list(Some(bounds), DataType::Float64),
+            ScalarValue::new_list(Some(counts), DataType::Float64),
+            ScalarValue::Float64(self.quantile),
+        ])
+    }
+
+    fn update_batch(&mut self, values: &[ArrayRef]) -> DataFusionResult<()> {
+        if values.is_empty() {
+            return Ok(());
+        }
+        if values.len() != 3 {
+            return Err(DataFusionError::Internal(format!(
+                "Internal error: Expected 3 arguments passed to {} but got {}",
+                PROMQL_HISTOGRAM_QUANTILE_UDAF_NAME,
+                values.len()
+            )));
+        }
+
+        let quantile_arr = compute::cast(&values[0], &DataType::Float64)?;
+        if let Some(quantile) = as_array::<Float64Array>(&quantile_arr)?
+            .iter()
+            .flatten()
+            .next()
+        {
+            self.quantile = Some(quantile);
+        }
+
+        let le_arr = compute::cast(&values[1], &DataType::Utf8)?;
+        let le_arr = as_array::<StringArray>(&le_arr)?;
+        let count_arr = compute::cast(&values[2], &DataType::Float64)?;
+        let count_arr = as_array::<Float64Array>(&count_arr)?;
+
+        // series with an invalid bucket bound are ignored, like in Prometheus
+        self.buckets.extend(
+            le_arr
+                .iter()
+                .zip(count_arr.iter())
+                .filter_map(|(le, count)| Some((le?.parse::<f64>().ok()?, count?))),
+        );
+        Ok(())
+    }
+
+    fn merge_batch(&mut self, states: &[ArrayRef]) -> DataFusionResult<()> {
+        if states.is_empty() {
+            return Ok(());
+        }
+        if states.len() != 3 {
+            return Err(DataFusionError::Internal(format!(
+                "Internal error: Expected 3 states passed to {} but got {}",
+                PROMQL_HISTOGRAM_QUANTILE_UDAF_NAME,
+                states.len()
+            )));
+        }
+
+        let bounds = as_array::<ListArray>(&states[0])?;
+        let counts = as_array::<ListArray>(&states[1])?;
+        let quantiles = as_array::<Float64Array>(&states[2])?;
+
+        for i in 0..bounds.len() {
+            if quantiles.is_valid(i) {
+                self.quantile = Some(quantiles.value(i));
+            }
+            if bounds.is_null(i) || counts.is_null(i) {
+                continue;
+            }
+
+            let b = bounds.value(i);
+            let c = counts.value(i);
+            let b = as_array::<Float64Array>(&b)?;
+            let c = as_array::<Float64Array>(&c)?;
+            self.buckets
+                .extend(b.iter().zip(c.iter()).filter_map(|(b, c)| Some((b?, c?))));
+        }
+        Ok(())
+    }
+
+    fn evaluate(&self) -> DataFusionResult<ScalarValue> {
+        Ok(ScalarValue::Float64(
+            self.quantile
+                .and_then(|q| bucket_quantile(q, self.buckets.clone())),
+        ))
+    }
+
+    fn size(&self) -> usize {
+        std::mem::size_of_val(self) + self.buckets.capacity() * std::mem::size_of::<(f64, f64)>()
+    }
+}
+
+/// Estimate the quantile `q` of a histogram from its (upper bound,
+/// cumulative count) buckets, as computed by `bucketQuantile` of Prometheus.
+///
+/// Returns `None` if there are no buckets.
+fn bucket_quantile(q: f64, mut buckets: Vec<(f64, f64)>) -> Option<f64> {
+    if buckets.is_empty() {
+        return None;
+    }
+    if q.is_nan() {
+        return Some(f64::NAN);
+    }
+    if q < 0.0 {
+        return Some(f64::NEG_INFINITY);
+    }
+    if q > 1.0 {
+        return Some(f64::INFINITY);
+    }
+
+    // merge buckets with the same bound, which can be the result of
+    // dropping other labels
+    buckets.sort_by(|a, b| a.0.total_cmp(&b.0));
+    buckets.dedup_by(|b, a| {
+        let same = a.0 == b.0;
+        if same {
+            a.1 += b.1;
+        }
+        same
+    });
+
+    match buckets.last() {
+        Some((bound, _)) if *bound == f64::INFINITY => {}
+        _ => return Some(f64::NAN),
+    }
+    if buckets.len() < 2 {
+        return Some(f64::NAN);
+    }
+
+    // the counts are cumulative, so can not decrease
+    let mut max = f64::NEG_INFINITY;
+    for (_, count) in &mut buckets {
+        max = max.max(*count);
+        *count = max;
+    }
+
+    let observations = buckets[buckets.len() - 1].1;
+    if observations == 0.0 {
+        return Some(f64::NAN);
+    }
+
+    let mut rank = q * observations;
+    let b = buckets
+        .iter()
+        .position(|(_, count)| *count >= rank)
+        .unwrap_or(buckets.len() - 1);
+
+    if b == buckets.len() - 1 {
+        return Some(buckets[buckets.len() - 2].0);
+    }
+    if b == 0 && buckets[0].0 <= 0.0 {
+        return Some(buckets[0].0);
+    }
+
+    let (bucket_end, mut count) = buckets[b];
+    let mut bucket_start = 0.0;
+    if b > 0 {
+        bucket_start = buckets[b - 1].0;
+        count -= buckets[b - 1].1;
+        rank -= buckets[b - 1].1;
+    }
+    Some(bucket_start + (bucket_end - bucket_start) * (rank / count))
+}
+
+fn as_array<T: 'static>(arr: &ArrayRef) -> DataFusionResult<&T> {
+    arr.as_any().downcast_ref::<T>().ok_or_else(|| {
+        DataFusionError::Internal(format!(
+            "Internal error: unexpected array of type {}",
+            arr.data_type()
+        ))
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use arrow::array::TimestampNanosecondArray;
+
+    use super::*;
+
+    const SECOND: i64 = 1_000_000_000;
+
+    fn accumulator(udaf: &AggregateUDF) -> Box<dyn Accumulator> {
+        (udaf.accumulator)(&DataType::Float64).unwrap()
+    }
+
+    fn range_args(values: &[f64], times: &[i64], range_end: i64, range: i64) -> Vec<ArrayRef> {
+        vec![
+            Arc::new(Float64Array::from(values.to_vec())),
+            Arc::new(TimestampNanosecondArray::from(times.to_vec())),
+            ScalarValue::Int64(Some(range_end)).to_array_of_size(values.len()),
+            ScalarValue::Int64(Some(range)).to_array_of_size(values.len()),
+        ]
+    }
+
+    fn evaluate(udaf: &AggregateUDF, args: Vec<ArrayRef>) -> ScalarValue {
+        let mut acc = accumulator(udaf);
+        acc.update_batch(&args).unwrap();
+        acc.evaluate().unwrap()
+    }
+
+    #[test]
+    fn test_range_functions() {
+        // a counter increasing by 1/s, sampled every 10s, with samples at
+        // the boundaries of the range
+        let values = [0.0, 10.0, 20.0, 30.0, 40.0, 50.0, 60.0];
+        let times = [0, 10, 20, 30, 40, 50, 60].map(|t| t * SECOND);
+        let args = || range_args(&values, &times, 60 * SECOND, 60 * SECOND);
+
+        assert_eq!(
+            evaluate(&PROMQL_RATE, args()),
+            ScalarValue::Float64(Some(1.0))
+        );
+        assert_eq!(
+            evaluate(&PROMQL_INCREASE, args()),
+            ScalarValue::Float64(Some(60.0))
+        );
+        assert_eq!(
+            evaluate(&PROMQL_IRATE, args()),
+            ScalarValue::Float64(Some(1.0))
+        );
+
+        // counter reset from 30 to 5, and the range extends half an interval
+        // beyond the samples
+        let values = [10.0, 20.0, 30.0, 5.0, 15.0];
+        let times = [10, 20, 30, 40, 50].map(|t| t * SECOND);
+        let args = || range_args(&values, &times, 55 * SECOND, 50 * SECOND);
+        assert_eq!(
+            evaluate(&PROMQL_INCREASE, args()),
+            ScalarValue::Float64(Some(35.0 * 50.0 / 40.0))
+        );
+        assert_eq!(
+            evaluate(&PROMQL_IRATE, args()),
+            ScalarValue::Float64(Some(1.0))
+        );
+
+        // too few samples
+        let args = range_args(&[1.0], &[0], 60 * SECOND, 60 * SECOND);
+        assert_eq!(evaluate(&PROMQL_RATE, args), ScalarValue::Float64(None));
+    }
+
+    #[test]
+    fn test_range_merge() {
+        let values = [0.0, 10.0, 20.0, 30.0, 40.0, 50.0, 60.0];
+        let times = [0, 10, 20, 30, 40, 50, 60].map(|t| t * SECOND);
+
+        let mut acc1 = accumulator(&PROMQL_RATE);
+        acc1.update_batch(&range_args(
+            &values[4..],
+            &times[4..],
+            60 * SECOND,
+            60 * SECOND,
+        ))
+        .unwrap();
+        let mut acc2 = accumulator(&PROMQL_RATE);
+        acc2.update_batch(&range_args(
+            &values[..4],
+            &times[..4],
+            60 * SECOND,
+            60 * SECOND,
+        ))
+        .unwrap();
+
+        let state = acc2
+            .state()
+            .unwrap()
+            .into_iter()
+            .map(|s| s.to_array())
+            .collect::<Vec<_>>();
+        acc1.merge_batch(&state).unwrap();
+
+        assert_eq!(acc1.evaluate().unwrap(), ScalarValue::Float64(Some(1.0)));
+    }
+
+    #[test]
+    fn test_bucket_quantile() {
+        let buckets = vec![
+            (0.25, 10.0),
+            (0.5, 30.0),
+            (1.0, 40.0),
+            (f64::INFINITY, 40.0),
+        ];
+
+        assert_eq!(bucket_quantile(0.5, buckets.clone()), Some(0.375));
+        assert_eq!(bucket_quantile(0.25, buckets.clone()), Some(0.25));
+        assert_eq!(bucket_quantile(1.0, buckets.clone()), Some(1.0));
+        assert_eq!(bucket_quantile(2.0, buckets.clone()), Some(f64::INFINITY));
+
+        // buckets with the same bound are added up
+        let split = vec![(0.5, 5.0), (0.5, 5.0), (f64::INFINITY, 10.0)];
+        assert_eq!(bucket_quantile(0.5, split), Some(0.25));
+
+        // the +Inf bucket is required
+        assert!(bucket_quantile(0.5, vec![(0.1, 1.0), (0.5, 2.0)])
+            .unwrap()
+            .is_nan());
+        assert_eq!(bucket_quantile(0.5, vec![]), None);
+    }
+
+    #[test]
+    fn test_histogram_quantile() {
+        let mut acc = accumulator(&PROMQL_HISTOGRAM_QUANTILE);
+        acc.update_batch(&[
+            ScalarValue::Float64(Some(0.5)).to_array_of_size(4),
+            Arc::new(StringArray::from(vec!["0.25", "0.5", "+Inf", "invalid"])),
+            Arc::new(Float64Array::from(vec![10.0, 30.0, 40.0, 100.0])),
+        ])
+        .unwrap();
+
+        assert_eq!(acc.evaluate().unwrap(), ScalarValue::Float64(Some(0.375)));
+    }
+}
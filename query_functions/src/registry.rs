@@ -7,7 +7,7 @@ use datafusion::{
 };
 use once_cell::sync::Lazy;
 
-use crate::{regex, window};
+use crate::{approx, gapfill, promql, regex, transformations, window};
 
 static REGISTRY: Lazy<IOxFunctionRegistry> = Lazy::new(IOxFunctionRegistry::new);
 
@@ -24,6 +24,9 @@ impl IOxFunctionRegistry {
 impl FunctionRegistry for IOxFunctionRegistry {
     fn udfs(&self) -> HashSet<String> {
         [
+            gapfill::DATE_BIN_GAPFILL_UDF_NAME,
+            gapfill::LOCF_UDF_NAME,
+            gapfill::INTERPOLATE_UDF_NAME,
             regex::REGEX_MATCH_UDF_NAME,
             regex::REGEX_NOT_MATCH_UDF_NAME,
             window::WINDOW_BOUNDS_UDF_NAME,
@@ -35,6 +38,9 @@ impl FunctionRegistry for IOxFunctionRegistry {
 
     fn udf(&self, name: &str) -> DataFusionResult<Arc<ScalarUDF>> {
         match name {
+            gapfill::DATE_BIN_GAPFILL_UDF_NAME => Ok(gapfill::DATE_BIN_GAPFILL.clone()),
+            gapfill::LOCF_UDF_NAME => Ok(gapfill::LOCF.clone()),
+            gapfill::INTERPOLATE_UDF_NAME => Ok(gapfill::INTERPOLATE.clone()),
             regex::REGEX_MATCH_UDF_NAME => Ok(regex::REGEX_MATCH_UDF.clone()),
             regex::REGEX_NOT_MATCH_UDF_NAME => Ok(regex::REGEX_NOT_MATCH_UDF.clone()),
             window::WINDOW_BOUNDS_UDF_NAME => Ok(window::WINDOW_BOUNDS_UDF.clone()),
@@ -46,10 +52,38 @@ impl FunctionRegistry for IOxFunctionRegistry {
     }
 
     fn udaf(&self, name: &str) -> DataFusionResult<Arc<AggregateUDF>> {
-        Err(DataFusionError::Plan(format!(
-            "IOx FunctionRegistry does not contain user defined aggregate function '{}'",
-            name
-        )))
+        match name {
+            transformations::DERIVATIVE_UDAF_NAME => Ok(transformations::DERIVATIVE.clone()),
+            transformations::NON_NEGATIVE_DERIVATIVE_UDAF_NAME => {
+                Ok(transformations::NON_NEGATIVE_DERIVATIVE.clone())
+            }
+            transformations::DIFFERENCE_UDAF_NAME => Ok(transformations::DIFFERENCE.clone()),
+            transformations::NON_NEGATIVE_DIFFERENCE_UDAF_NAME => {
+                Ok(transformations::NON_NEGATIVE_DIFFERENCE.clone())
+            }
+            transformations::ELAPSED_UDAF_NAME => Ok(transformations::ELAPSED.clone()),
+            transformations::MOVING_AVERAGE_UDAF_NAME => {
+                Ok(transformations::MOVING_AVERAGE.clone())
+            }
+            transformations::CUMULATIVE_SUM_UDAF_NAME => {
+                Ok(transformations::CUMULATIVE_SUM.clone())
+            }
+            transformations::INTEGRAL_UDAF_NAME => Ok(transformations::INTEGRAL.clone()),
+            approx::APPROX_DISTINCT_HLL_UDAF_NAME => Ok(approx::APPROX_DISTINCT_HLL.clone()),
+            approx::APPROX_PERCENTILE_TDIGEST_UDAF_NAME => {
+                Ok(approx::APPROX_PERCENTILE_TDIGEST.clone())
+            }
+            promql::PROMQL_RATE_UDAF_NAME => Ok(promql::PROMQL_RATE.clone()),
+            promql::PROMQL_IRATE_UDAF_NAME => Ok(promql::PROMQL_IRATE.clone()),
+            promql::PROMQL_INCREASE_UDAF_NAME => Ok(promql::PROMQL_INCREASE.clone()),
+            promql::PROMQL_HISTOGRAM_QUANTILE_UDAF_NAME => {
+                Ok(promql::PROMQL_HISTOGRAM_QUANTILE.clone())
+            }
+            _ => Err(DataFusionError::Plan(format!(
+                "IOx FunctionRegistry does not contain user defined aggregate function '{}'",
+                name
+            ))),
+        }
     }
 }
 
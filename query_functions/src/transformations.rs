@@ -0,0 +1,716 @@
+//! Implementation of InfluxQL-style time series transformation functions.
+//!
+//! The functions are DataFusion user defined aggregate functions. They are
+//! intended to be used as window functions over each series ordered by time,
+//! where the default window frame (all the preceding rows of the series, up to
+//! and including the current row) produces a value for each row:
+//!
+//! ```sql
+//! SELECT
+//!   host, time,
+//!   derivative(usage, time, INTERVAL '1 second') OVER (PARTITION BY host ORDER BY time)
+//! FROM cpu
+//! ```
+//!
+//! As aggregate functions, they compute the value for the last row of each
+//! group.
+//!
+//! | function                                          | returns |
+//! |---------------------------------------------------|---------|
+//! | `derivative(value, time[, unit])`                 | the rate of change between the last two values, per `unit` (default 1s) |
+//! | `non_negative_derivative(value, time[, unit])`    | as `derivative`, but `NULL` if negative |
+//! | `difference(value, time)`                         | the difference between the last two values |
+//! | `non_negative_difference(value, time)`            | as `difference`, but `NULL` if negative |
+//! | `elapsed(value, time[, unit])`                    | the time between the last two values, in `unit` (default 1ns) |
+//! | `moving_average(value, time, n)`                  | the average of the last `n` values, `NULL` if there are fewer |
+//! | `cumulative_sum(value)`                           | the sum of the values |
+//! | `integral(value, time[, unit])`                   | the area under the curve of the values, per `unit` (default 1s) |
+//!
+//! `NULL` values are ignored, and all the numeric results are `Float64`,
+//! except `elapsed` which is `Int64`.
+use std::sync::Arc;
+
+use arrow::{
+    array::{Array, ArrayRef, Float64Array, Int64Array, ListArray, TimestampNanosecondArray},
+    compute,
+    datatypes::{DataType, Field, IntervalUnit},
+};
+use datafusion::{
+    error::{DataFusionError, Result as DataFusionResult},
+    execution::context::SessionState,
+    logical_expr::{
+        AccumulatorFunctionImplementation, ReturnTypeFunction, Signature, StateTypeFunction,
+        TypeSignature, Volatility,
+    },
+    physical_plan::{udaf::AggregateUDF, Accumulator},
+    scalar::ScalarValue,
+};
+use once_cell::sync::Lazy;
+use schema::TIME_DATA_TYPE;
+
+/// The name of the derivative UDAF given to DataFusion.
+pub const DERIVATIVE_UDAF_NAME: &str = "derivative";
+
+/// The name of the non_negative_derivative UDAF given to DataFusion.
+pub const NON_NEGATIVE_DERIVATIVE_UDAF_NAME: &str = "non_negative_derivative";
+
+/// The name of the difference UDAF given to DataFusion.
+pub const DIFFERENCE_UDAF_NAME: &str = "difference";
+
+/// The name of the non_negative_difference UDAF given to DataFusion.
+pub const NON_NEGATIVE_DIFFERENCE_UDAF_NAME: &str = "non_negative_difference";
+
+/// The name of the elapsed UDAF given to DataFusion.
+pub const ELAPSED_UDAF_NAME: &str = "elapsed";
+
+/// The name of the moving_average UDAF given to DataFusion.
+pub const MOVING_AVERAGE_UDAF_NAME: &str = "moving_average";
+
+/// The name of the cumulative_sum UDAF given to DataFusion.
+pub const CUMULATIVE_SUM_UDAF_NAME: &str = "cumulative_sum";
+
+/// The name of the integral UDAF given to DataFusion.
+pub const INTEGRAL_UDAF_NAME: &str = "integral";
+
+const NANOS_PER_MILLI: i64 = 1_000_000;
+const NANOS_PER_SECOND: i64 = 1_000 * NANOS_PER_MILLI;
+const NANOS_PER_DAY: i64 = 86_400 * NANOS_PER_SECOND;
+
+pub(crate) static DERIVATIVE: Lazy<Arc<AggregateUDF>> = Lazy::new(|| {
+    make_udaf(
+        DERIVATIVE_UDAF_NAME,
+        Transformation::Derivative {
+            non_negative: false,
+        },
+    )
+});
+
+pub(crate) static NON_NEGATIVE_DERIVATIVE: Lazy<Arc<AggregateUDF>> = Lazy::new(|| {
+    make_udaf(
+        NON_NEGATIVE_DERIVATIVE_UDAF_NAME,
+        Transformation::Derivative { non_negative: true },
+    )
+});
+
+pub(crate) static DIFFERENCE: Lazy<Arc<AggregateUDF>> = Lazy::new(|| {
+    make_udaf(
+        DIFFERENCE_UDAF_NAME,
+        Transformation::Difference {
+            non_negative: false,
+        },
+    )
+});
+
+pub(crate) static NON_NEGATIVE_DIFFERENCE: Lazy<Arc<AggregateUDF>> = Lazy::new(|| {
+    make_udaf(
+        NON_NEGATIVE_DIFFERENCE_UDAF_NAME,
+        Transformation::Difference { non_negative: true },
+    )
+});
+
+pub(crate) static ELAPSED: Lazy<Arc<AggregateUDF>> =
+    Lazy::new(|| make_udaf(ELAPSED_UDAF_NAME, Transformation::Elapsed));
+
+pub(crate) static MOVING_AVERAGE: Lazy<Arc<AggregateUDF>> =
+    Lazy::new(|| make_udaf(MOVING_AVERAGE_UDAF_NAME, Transformation::MovingAverage));
+
+pub(crate) static CUMULATIVE_SUM: Lazy<Arc<AggregateUDF>> =
+    Lazy::new(|| make_udaf(CUMULATIVE_SUM_UDAF_NAME, Transformation::CumulativeSum));
+
+pub(crate) static INTEGRAL: Lazy<Arc<AggregateUDF>> =
+    Lazy::new(|| make_udaf(INTEGRAL_UDAF_NAME, Transformation::Integral));
+
+/// registers transformation functions so they can be invoked via SQL
+pub fn register_transformation_aggregates(mut state: SessionState) -> SessionState {
+    for udaf in [
+        &DERIVATIVE,
+        &NON_NEGATIVE_DERIVATIVE,
+        &DIFFERENCE,
+        &NON_NEGATIVE_DIFFERENCE,
+        &ELAPSED,
+        &MOVING_AVERAGE,
+        &CUMULATIVE_SUM,
+        &INTEGRAL,
+    ] {
+        let udaf = Arc::clone(udaf);
+        state.aggregate_functions.insert(udaf.name.clone(), udaf);
+    }
+
+    state
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Transformation {
+    Derivative { non_negative: bool },
+    Difference { non_negative: bool },
+    Elapsed,
+    MovingAverage,
+    CumulativeSum,
+    Integral,
+}
+
+impl Transformation {
+    fn return_type(&self) -> DataType {
+        match self {
+            Self::Elapsed => DataType::Int64,
+            _ => DataType::Float64,
+        }
+    }
+
+    /// The default of the optional third argument, the unit in nanoseconds.
+    fn default_param(&self) -> Option<i64> {
+        match self {
+            Self::Derivative { .. } | Self::Integral => Some(NANOS_PER_SECOND),
+            Self::Elapsed => Some(1),
+            Self::Difference { .. } | Self::MovingAverage | Self::CumulativeSum => None,
+        }
+    }
+
+    /// The number of most recent points needed to evaluate the function, or
+    /// `None` if all the points are needed.
+    fn points_needed(&self, param: Option<i64>) -> Option<usize> {
+        match self {
+            Self::Derivative { .. } | Self::Difference { .. } | Self::Elapsed => Some(2),
+            Self::MovingAverage => param.map(|n| n as usize),
+            Self::CumulativeSum | Self::Integral => None,
+        }
+    }
+
+    fn signature(&self) -> Signature {
+        let value_types = [DataType::Float64, DataType::Int64, DataType::UInt64];
+
+        let signatures = match self {
+            Self::CumulativeSum => {
+                return Signature::uniform(1, value_types.to_vec(), Volatility::Immutable)
+            }
+            Self::Difference { .. } => value_types
+                .into_iter()
+                .map(|v| TypeSignature::Exact(vec![v, TIME_DATA_TYPE()]))
+                .collect(),
+            Self::MovingAverage => value_types
+                .into_iter()
+                .map(|v| TypeSignature::Exact(vec![v, TIME_DATA_TYPE(), DataType::Int64]))
+                .collect(),
+            Self::Derivative { .. } | Self::Elapsed | Self::Integral => value_types
+                .into_iter()
+                .flat_map(|v| {
+                    [
+                        TypeSignature::Exact(vec![v.clone(), TIME_DATA_TYPE()]),
+                        TypeSignature::Exact(vec![
+                            v.clone(),
+                            TIME_DATA_TYPE(),
+                            DataType::Interval(IntervalUnit::DayTime),
+                        ]),
+                        TypeSignature::Exact(vec![
+                            v,
+                            TIME_DATA_TYPE(),
+                            DataType::Interval(IntervalUnit::MonthDayNano),
+                        ]),
+                    ]
+                })
+                .collect(),
+        };
+
+        Signature::one_of(signatures, Volatility::Immutable)
+    }
+}
+
+fn make_udaf(name: &'static str, transformation: Transformation) -> Arc<AggregateUDF> {
+    let return_type = transformation.return_type();
+    let return_type_func: ReturnTypeFunction = Arc::new(move |_| Ok(Arc::new(return_type.clone())));
+
+    let accumulator: AccumulatorFunctionImplementation = Arc::new(move |_| {
+        let accumulator: Box<dyn Accumulator> = match transformation {
+            Transformation::CumulativeSum => Box::<CumulativeSumAccumulator>::default(),
+            _ => Box::new(PointsAccumulator::new(name, transformation)),
+        };
+        Ok(accumulator)
+    });
+
+    let state_type: StateTypeFunction = Arc::new(move |_| {
+        Ok(Arc::new(match transformation {
+            Transformation::CumulativeSum => vec![DataType::Float64],
+            _ => vec![
+                DataType::List(Box::new(Field::new("item", TIME_DATA_TYPE(), true))),
+                DataType::List(Box::new(Field::new("item", DataType::Float64, true))),
+                DataType::Int64,
+            ],
+        }))
+    });
+
+    Arc::new(AggregateUDF::new(
+        name,
+        &transformation.signature(),
+        &return_type_func,
+        &accumulator,
+        &state_type,
+    ))
+}
+
+/// Accumulates the (time, value) points needed to evaluate a
+/// [`Transformation`].
+#[derive(Debug)]
+struct PointsAccumulator {
+    name: &'static str,
+    transformation: Transformation,
+    /// The unit in nanoseconds, or the number of points of a moving average.
+    param: Option<i64>,
+    /// The points, ordered by time.
+    points: Vec<(i64, f64)>,
+}
+
+impl PointsAccumulator {
+    fn new(name: &'static str, transformation: Transformation) -> Self {
+        Self {
+            name,
+            transformation,
+            param: transformation.default_param(),
+            points: vec![],
+        }
+    }
+
+    /// Order the points by time, discarding those that are not needed.
+    fn prune(&mut self) {
+        self.points.sort_by_key(|(t, _)| *t);
+        if let Some(n) = self.transformation.points_needed(self.param) {
+            let len = self.points.len();
+            if len > n {
+                self.points.drain(..len - n);
+            }
+        }
+    }
+
+    fn set_param(&mut self, param: &ArrayRef) -> DataFusionResult<()> {
+        if param.is_empty() || param.is_null(0) {
+            return Ok(());
+        }
+
+        let param = match ScalarValue::try_from_array(param, 0)? {
+            ScalarValue::Int64(Some(n)) if n > 0 => n,
+            ScalarValue::Int64(_) => {
+                return Err(DataFusionError::Plan(format!(
+                    "{} requires a positive number of points",
+                    self.name
+                )))
+            }
+            v => interval_nanos(self.name, &v)?,
+        };
+        self.param = Some(param);
+        Ok(())
+    }
+}
+
+impl Accumulator for PointsAccumulator {
+    fn state(&self) -> DataFusionResult<Vec<ScalarValue>> {
+        let times = self
+            .points
+            .iter()
+            .map(|(t, _)| ScalarValue::TimestampNanosecond(Some(*t), None))
+            .collect();
+        let values = self
+            .points
+            .iter()
+            .map(|(_, v)| ScalarValue::Float64(Some(*v)))
+            .collect();
+
+        Ok(vec![
+            ScalarValue::new_list(Some(times), TIME_DATA_TYPE()),
+            ScalarValue::new_list(Some(values), DataType::Float64),
+            ScalarValue::Int64(self.param),
+        ])
+    }
+
+    fn update_batch(&mut self, values: &[ArrayRef]) -> DataFusionResult<()> {
+        if values.is_empty() {
+            return Ok(());
+        }
+        if values.len() < 2 {
+            return Err(DataFusionError::Internal(format!(
+                "Internal error: Expected at least 2 arguments passed to {} but got {}",
+                self.name,
+                values.len()
+            )));
+        }
+
+        if let Some(param) = values.get(2) {
+            self.set_param(param)?;
+        }
+
+        let value_arr = compute::cast(&values[0], &DataType::Float64)?;
+        let value_arr = as_array::<Float64Array>(&value_arr)?;
+        let time_arr = as_array::<TimestampNanosecondArray>(&values[1])?;
+
+        self.points.extend(
+            value_arr
+                .iter()
+                .zip(time_arr.iter())
+                .filter_map(|(v, t)| Some((t?, v?))),
+        );
+        self.prune();
+        Ok(())
+    }
+
+    fn merge_batch(&mut self, states: &[ArrayRef]) -> DataFusionResult<()> {
+        if states.is_empty() {
+            return Ok(());
+        }
+        if states.len() != 3 {
+            return Err(DataFusionError::Internal(format!(
+                "Internal error: Expected 3 states passed to {} but got {}",
+                self.name,
+                states.len()
+            )));
+        }
+
+        let times = as_array::<ListArray>(&states[0])?;
+        let values = as_array::<ListArray>(&states[1])?;
+        let params = as_array::<Int64Array>(&states[2])?;
+
+        for i in 0..times.len() {
+            if params.is_valid(i) {
+                self.param = Some(params.value(i));
+            }
+            if times.is_null(i) || values.is_null(i) {
+                continue;
+            }
+
+            let t = times.value(i);
+            let v = values.value(i);
+            let t = as_array::<TimestampNanosecondArray>(&t)?;
+            let v = as_array::<Float64Array>(&v)?;
+            self.points
+                .extend(v.iter().zip(t.iter()).filter_map(|(v, t)| Some((t?, v?))));
+        }
+        self.prune();
+        Ok(())
+    }
+
+    fn evaluate(&self) -> DataFusionResult<ScalarValue> {
+        let unit = self.param.unwrap_or(1) as f64;
+        let last_two = match self.points.as_slice() {
+            [.., p0, p1] => Some((*p0, *p1)),
+            _ => None,
+        };
+
+        Ok(match self.transformation {
+            Transformation::Derivative { non_negative } => ScalarValue::Float64(
+                last_two
+                    .filter(|((t0, _), (t1, _))| t1 > t0)
+                    .map(|((t0, v0), (t1, v1))| (v1 - v0) / ((t1 - t0) as f64 / unit))
+                    .filter(|d| !non_negative || *d >= 0.0),
+            ),
+            Transformation::Difference { non_negative } => ScalarValue::Float64(
+                last_two
+                    .map(|((_, v0), (_, v1))| v1 - v0)
+                    .filter(|d| !non_negative || *d >= 0.0),
+            ),
+            Transformation::Elapsed => ScalarValue::Int64(
+                last_two.map(|((t0, _), (t1, _))| (t1 - t0) / self.param.unwrap_or(1)),
+            ),
+            Transformation::MovingAverage => {
+                let n = self.param.unwrap_or(1) as usize;
+                ScalarValue::Float64((self.points.len() >= n).then(|| {
+                    self.points[self.points.len() - n..]
+                        .iter()
+                        .map(|(_, v)| v)
+                        .sum::<f64>()
+                        / n as f64
+                }))
+            }
+            Transformation::Integral => {
+                ScalarValue::Float64((!self.points.is_empty()).then(|| {
+                    self.points
+                        .windows(2)
+                        .map(|w| {
+                            let ((t0, v0), (t1, v1)) = (w[0], w[1]);
+                            (v0 + v1) / 2.0 * ((t1 - t0) as f64 / unit)
+                        })
+                        .sum()
+                }))
+            }
+            Transformation::CumulativeSum => {
+                return Err(DataFusionError::Internal(format!(
+                    "{} is not evaluated by a points accumulator",
+                    self.name
+                )))
+            }
+        })
+    }
+
+    fn size(&self) -> usize {
+        std::mem::size_of_val(self) + self.points.capacity() * std::mem::size_of::<(i64, f64)>()
+    }
+}
+
+/// Accumulates the sum of the values of `cumulative_sum`.
+#[derive(Debug, Default)]
+struct CumulativeSumAccumulator {
+    sum: Option<f64>,
+}
+
+impl CumulativeSumAccumulator {
+    fn add(&mut self, arr: &ArrayRef) -> DataFusionResult<()> {
+        let arr = compute::cast(arr, &DataType::Float64)?;
+        if let Some(sum) = compute::sum(as_array::<Float64Array>(&arr)?) {
+            self.sum = Some(self.sum.unwrap_or_default() + sum);
+        }
+        Ok(())
+    }
+}
+
+impl Accumulator for CumulativeSumAccumulator {
+    fn state(&self) -> DataFusionResult<Vec<ScalarValue>> {
+        Ok(vec![ScalarValue::Float64(self.sum)])
+    }
+
+    fn update_batch(&mut self, values: &[ArrayRef]) -> DataFusionResult<()> {
+        match values.first() {
+            Some(values) => self.add(values),
+            None => Ok(()),
+        }
+    }
+
+    // The state is a partial sum, so is merged in the same way as inputs.
+    fn merge_batch(&mut self, states: &[ArrayRef]) -> DataFusionResult<()> {
+        self.update_batch(states)
+    }
+
+    fn evaluate(&self) -> DataFusionResult<ScalarValue> {
+        Ok(ScalarValue::Float64(self.sum))
+    }
+
+    fn size(&self) -> usize {
+        std::mem::size_of_val(self)
+    }
+}
+
+fn as_array<T: 'static>(arr: &ArrayRef) -> DataFusionResult<&T> {
+    arr.as_any().downcast_ref::<T>().ok_or_else(|| {
+        DataFusionError::Internal(format!(
+            "Internal error: unexpected array of type {}",
+            arr.data_type()
+        ))
+    })
+}
+
+/// Convert a unit interval into a (positive) number of nanoseconds.
+fn interval_nanos(name: &str, v: &ScalarValue) -> DataFusionResult<i64> {
+    let nanos = match v {
+        ScalarValue::IntervalDayTime(Some(v)) => {
+            let days = (v >> 32) as i32 as i64;
+            let millis = *v as i32 as i64;
+            days.checked_mul(NANOS_PER_DAY)
+                .and_then(|n| n.checked_add(millis * NANOS_PER_MILLI))
+        }
+        ScalarValue::IntervalMonthDayNano(Some(v)) if (v >> 96) as i32 == 0 => {
+            let days = (v >> 64) as i32 as i64;
+            days.checked_mul(NANOS_PER_DAY)
+                .and_then(|n| n.checked_add(*v as i64))
+        }
+        _ => None,
+    };
+
+    match nanos {
+        Some(n) if n > 0 => Ok(n),
+        _ => Err(DataFusionError::Plan(format!(
+            "{} requires a positive unit of days or less, got {}",
+            name, v
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use arrow::{array::UInt64Array, record_batch::RecordBatch};
+    use datafusion::{assert_batches_eq, datasource::MemTable, prelude::SessionContext};
+
+    use super::*;
+
+    fn accumulator(udaf: &AggregateUDF) -> Box<dyn Accumulator> {
+        (udaf.accumulator)(&DataType::Float64).unwrap()
+    }
+
+    fn args(values: &[u64], times: &[i64], param: Option<ScalarValue>) -> Vec<ArrayRef> {
+        let mut args: Vec<ArrayRef> = vec![
+            Arc::new(UInt64Array::from(values.to_vec())),
+            Arc::new(TimestampNanosecondArray::from(times.to_vec())),
+        ];
+        if let Some(param) = param {
+            args.push(param.to_array_of_size(values.len()));
+        }
+        args
+    }
+
+    fn evaluate(
+        udaf: &AggregateUDF,
+        values: &[u64],
+        times: &[i64],
+        param: Option<ScalarValue>,
+    ) -> ScalarValue {
+        let mut acc = accumulator(udaf);
+        acc.update_batch(&args(values, times, param)).unwrap();
+        acc.evaluate().unwrap()
+    }
+
+    #[test]
+    fn test_transformations() {
+        let two_seconds = Some(ScalarValue::IntervalDayTime(Some(2_000)));
+        let values = [10, 30, 20];
+        let times = [0, 2 * NANOS_PER_SECOND, NANOS_PER_SECOND];
+
+        let cases = [
+            (&DERIVATIVE, None, ScalarValue::Float64(Some(10.0))),
+            (
+                &DERIVATIVE,
+                two_seconds.clone(),
+                ScalarValue::Float64(Some(20.0)),
+            ),
+            (
+                &NON_NEGATIVE_DERIVATIVE,
+                None,
+                ScalarValue::Float64(Some(10.0)),
+            ),
+            (&DIFFERENCE, None, ScalarValue::Float64(Some(10.0))),
+            (
+                &NON_NEGATIVE_DIFFERENCE,
+                None,
+                ScalarValue::Float64(Some(10.0)),
+            ),
+            (&ELAPSED, None, ScalarValue::Int64(Some(NANOS_PER_SECOND))),
+            (
+                &MOVING_AVERAGE,
+                Some(ScalarValue::Int64(Some(2))),
+                ScalarValue::Float64(Some(25.0)),
+            ),
+            (
+                &MOVING_AVERAGE,
+                Some(ScalarValue::Int64(Some(4))),
+                ScalarValue::Float64(None),
+            ),
+            (&CUMULATIVE_SUM, None, ScalarValue::Float64(Some(60.0))),
+            (&INTEGRAL, None, ScalarValue::Float64(Some(40.0))),
+            (&INTEGRAL, two_seconds, ScalarValue::Float64(Some(20.0))),
+        ];
+
+        for (udaf, param, expected) in cases {
+            let got = if udaf.name == CUMULATIVE_SUM_UDAF_NAME {
+                let mut acc = accumulator(udaf);
+                acc.update_batch(&args(&values, &times, None)[..1]).unwrap();
+                acc.evaluate().unwrap()
+            } else {
+                evaluate(udaf, &values, &times, param)
+            };
+            assert_eq!(got, expected, "{}", udaf.name);
+        }
+
+        // Decreasing values
+        let values = [30, 10];
+        let times = [0, NANOS_PER_SECOND];
+        assert_eq!(
+            evaluate(&NON_NEGATIVE_DERIVATIVE, &values, &times, None),
+            ScalarValue::Float64(None)
+        );
+        assert_eq!(
+            evaluate(&NON_NEGATIVE_DIFFERENCE, &values, &times, None),
+            ScalarValue::Float64(None)
+        );
+        assert_eq!(
+            evaluate(&DIFFERENCE, &values, &times, None),
+            ScalarValue::Float64(Some(-20.0))
+        );
+
+        // Too few points
+        assert_eq!(
+            evaluate(&DERIVATIVE, &[1], &[0], None),
+            ScalarValue::Float64(None)
+        );
+        assert_eq!(evaluate(&ELAPSED, &[], &[], None), ScalarValue::Int64(None));
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut acc1 = accumulator(&INTEGRAL);
+        acc1.update_batch(&args(&[10, 30], &[0, 2 * NANOS_PER_SECOND], None))
+            .unwrap();
+        let mut acc2 = accumulator(&INTEGRAL);
+        acc2.update_batch(&args(&[20], &[NANOS_PER_SECOND], None))
+            .unwrap();
+
+        let state = acc2
+            .state()
+            .unwrap()
+            .into_iter()
+            .map(|s| s.to_array())
+            .collect::<Vec<_>>();
+        acc1.merge_batch(&state).unwrap();
+
+        assert_eq!(acc1.evaluate().unwrap(), ScalarValue::Float64(Some(40.0)));
+    }
+
+    #[test]
+    fn test_invalid_params() {
+        let mut acc = accumulator(&MOVING_AVERAGE);
+        let err = acc
+            .update_batch(&args(&[1], &[0], Some(ScalarValue::Int64(Some(0)))))
+            .unwrap_err();
+        assert!(
+            err.to_string().contains("positive number of points"),
+            "{}",
+            err
+        );
+
+        let mut acc = accumulator(&DERIVATIVE);
+        let err = acc
+            .update_batch(&args(
+                &[1],
+                &[0],
+                Some(ScalarValue::IntervalMonthDayNano(Some(1 << 96))),
+            ))
+            .unwrap_err();
+        assert!(err.to_string().contains("positive unit"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn test_sql() {
+        let batch = RecordBatch::try_from_iter(vec![
+            (
+                "v",
+                Arc::new(Float64Array::from(vec![10.0, 20.0, 30.0])) as ArrayRef,
+            ),
+            (
+                "time",
+                Arc::new(TimestampNanosecondArray::from(vec![
+                    0,
+                    NANOS_PER_SECOND,
+                    2 * NANOS_PER_SECOND,
+                ])) as ArrayRef,
+            ),
+        ])
+        .unwrap();
+        let provider = MemTable::try_new(batch.schema(), vec![vec![batch]]).unwrap();
+
+        let ctx = SessionContext::new();
+        ctx.register_udaf(DERIVATIVE.as_ref().clone());
+        ctx.register_udaf(INTEGRAL.as_ref().clone());
+        ctx.register_table("t", Arc::new(provider)).unwrap();
+
+        let got = ctx
+            .sql("SELECT derivative(v, time) AS d, integral(v, time) AS i FROM t")
+            .await
+            .unwrap()
+            .collect()
+            .await
+            .unwrap();
+
+        assert_batches_eq!(
+            [
+                "+----+----+",
+                "| d  | i  |",
+                "+----+----+",
+                "| 10 | 40 |",
+                "+----+----+",
+            ],
+            &got
+        );
+    }
+}
@@ -27,7 +27,7 @@ use mutable_batch_lp::LinesConverter;
 use once_cell::sync::Lazy;
 use querier::{
     IngesterConnectionImpl, IngesterFlightClient, IngesterFlightClientError,
-    IngesterFlightClientQueryData, QuerierCatalogCache, QuerierNamespace,
+    IngesterFlightClientQueryData, LastValueCache, QuerierCatalogCache, QuerierNamespace,
 };
 use schema::Projection;
 use sharder::JumpHash;
@@ -965,6 +965,7 @@ impl MockIngester {
             Some(ingester_connection),
             sharder,
             false,
+            Arc::new(LastValueCache::new([], &catalog.metric_registry())),
         ))
     }
 }
@@ -1036,6 +1037,7 @@ impl QueryDataAdapter {
                                 // These fields are only used in ingester2.
                                 ingester_uuid: String::new(),
                                 completed_persistence_count: 0,
+                                buffer_version: 0,
                             },
                         ),
                         FlatIngesterQueryResponse::StartSnapshot { schema } => (
@@ -109,6 +109,7 @@
 #![allow(clippy::missing_docs_in_private_items)]
 
 pub mod dml_handlers;
+pub mod monitoring;
 pub mod namespace_cache;
 pub mod namespace_resolver;
 pub mod server;
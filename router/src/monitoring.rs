@@ -0,0 +1,312 @@
+//! Self-monitoring: periodically write this router's own metrics into an IOx
+//! namespace.
+//!
+//! The contents of the [`metric::Registry`] are encoded as line protocol by a
+//! [`LineProtocolEncoder`] and pushed through the router's own DML handler
+//! stack, exactly as if they were a line protocol write received over HTTP. The
+//! history of the metrics can then be queried with IOx itself, without running
+//! a separate metrics store scraping the `/metrics` endpoint.
+//!
+//! Only routers self-monitor, as writing requires the DML handler stack. The
+//! metrics of the other services (ingesters, queriers and compactors) are only
+//! available by scraping their `/metrics` endpoint.
+
+use std::{sync::Arc, time::Duration};
+
+use data_types::NamespaceName;
+use hashbrown::HashMap;
+use iox_time::{SystemProvider, TimeProvider};
+use metric::{U64Counter, U64Gauge};
+use metric_exporters::LineProtocolEncoder;
+use mutable_batch::MutableBatch;
+use mutable_batch_lp::LinesConverter;
+use observability_deps::tracing::*;
+use thiserror::Error;
+use tokio::time::MissedTickBehavior;
+
+use crate::{
+    dml_handlers::{DmlError, DmlHandler},
+    namespace_resolver::{self, NamespaceResolver},
+};
+
+/// Errors writing the metrics to the monitoring namespace.
+#[derive(Debug, Error)]
+pub enum Error {
+    /// The encoded metrics could not be converted into [`MutableBatch`].
+    #[error("failed to convert metrics to batches: {0}")]
+    Convert(#[from] mutable_batch_lp::Error),
+
+    /// The monitoring namespace could not be resolved.
+    #[error(transparent)]
+    NamespaceResolver(#[from] namespace_resolver::Error),
+
+    /// The DML handler stack rejected the write.
+    #[error(transparent)]
+    DmlHandler(#[from] DmlError),
+}
+
+/// Periodically writes a snapshot of a [`metric::Registry`] to a namespace
+/// through a [`DmlHandler`].
+#[derive(Debug)]
+pub struct SelfMonitor<D, N, T = SystemProvider> {
+    namespace: NamespaceName<'static>,
+    registry: Arc<metric::Registry>,
+    namespace_resolver: N,
+    dml_handler: D,
+    time_provider: T,
+
+    /// Successful and failed writes of the metric snapshots.
+    write_ok: U64Counter,
+    write_error: U64Counter,
+    /// The number of lines in the last snapshot.
+    lines: U64Gauge,
+    /// Series that could not be converted and were skipped.
+    skipped: U64Counter,
+}
+
+impl<D, N> SelfMonitor<D, N> {
+    /// Initialise a [`SelfMonitor`] writing the metrics in `registry` to
+    /// `namespace`, resolved by `namespace_resolver` and written to
+    /// `dml_handler`.
+    pub fn new(
+        namespace: NamespaceName<'static>,
+        registry: Arc<metric::Registry>,
+        namespace_resolver: N,
+        dml_handler: D,
+    ) -> Self {
+        let writes = registry.register_metric::<U64Counter>(
+            "self_monitoring_writes",
+            "number of metric snapshots written to the monitoring namespace",
+        );
+        let lines = registry
+            .register_metric::<U64Gauge>(
+                "self_monitoring_lines",
+                "number of lines in the last metric snapshot written to the monitoring namespace",
+            )
+            .recorder(&[]);
+        let skipped = registry
+            .register_metric::<U64Counter>(
+                "self_monitoring_skipped_lines",
+                "number of metric lines skipped as they could not be written to the monitoring namespace",
+            )
+            .recorder(&[]);
+
+        Self {
+            namespace,
+            write_ok: writes.recorder(&[("result", "success")]),
+            write_error: writes.recorder(&[("result", "error")]),
+            lines,
+            skipped,
+            registry,
+            namespace_resolver,
+            dml_handler,
+            time_provider: Default::default(),
+        }
+    }
+}
+
+impl<D, N, T> SelfMonitor<D, N, T> {
+    /// Timestamp the metric snapshots with the current time of
+    /// `time_provider`.
+    pub fn with_time_provider<U>(self, time_provider: U) -> SelfMonitor<D, N, U> {
+        SelfMonitor {
+            namespace: self.namespace,
+            registry: self.registry,
+            namespace_resolver: self.namespace_resolver,
+            dml_handler: self.dml_handler,
+            time_provider,
+            write_ok: self.write_ok,
+            write_error: self.write_error,
+            lines: self.lines,
+            skipped: self.skipped,
+        }
+    }
+}
+
+impl<D, N, T> SelfMonitor<D, N, T>
+where
+    D: DmlHandler<WriteInput = HashMap<String, MutableBatch>>,
+    N: NamespaceResolver,
+    T: TimeProvider,
+{
+    /// Write a metric snapshot every `interval`, forever.
+    pub async fn run(self, interval: Duration) {
+        let mut interval = tokio::time::interval(interval);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            interval.tick().await;
+            match self.report().await {
+                Ok(()) => self.write_ok.inc(1),
+                Err(error) => {
+                    warn!(%error, namespace=%self.namespace, "failed to write self-monitoring metrics");
+                    self.write_error.inc(1);
+                }
+            }
+        }
+    }
+
+    /// Write a single snapshot of the metrics to the monitoring namespace.
+    pub async fn report(&self) -> Result<(), Error> {
+        let now = self.time_provider.now().timestamp_nanos();
+
+        let mut lp = Vec::new();
+        self.registry
+            .report(&mut LineProtocolEncoder::new(&mut lp, now));
+        let lp = String::from_utf8(lp).expect("line protocol encoder writes utf-8");
+
+        // Convert the lines one by one, so that a series that cannot be
+        // written (for example because an attribute clashes with a column
+        // of another type) is skipped without dropping the rest of the
+        // snapshot.
+        let mut converter = LinesConverter::new(now);
+        let mut n_lines = 0;
+        for line in lp.lines() {
+            match converter.write_lp(line) {
+                Ok(()) => n_lines += 1,
+                Err(error) => {
+                    debug!(%error, %line, "skipping self-monitoring series");
+                    self.skipped.inc(1);
+                }
+            }
+        }
+        self.lines.set(n_lines);
+
+        let batches = match converter.finish() {
+            Ok((batches, _stats)) => batches
+                .into_iter()
+                .filter(|(_, batch)| batch.rows() > 0)
+                .collect::<HashMap<_, _>>(),
+            Err(mutable_batch_lp::Error::EmptyPayload) => return Ok(()),
+            Err(e) => return Err(e.into()),
+        };
+        if batches.is_empty() {
+            return Ok(());
+        }
+
+        let namespace_id = self
+            .namespace_resolver
+            .get_namespace_id(&self.namespace)
+            .await?;
+
+        self.dml_handler
+            .write(&self.namespace, namespace_id, batches, None)
+            .await
+            .map_err(Into::into)?;
+
+        debug!(namespace=%self.namespace, "wrote self-monitoring metrics");
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use assert_matches::assert_matches;
+    use data_types::NamespaceId;
+    use iox_time::{MockProvider, Time};
+    use write_summary::WriteSummary;
+
+    use super::*;
+    use crate::{
+        dml_handlers::mock::{MockDmlHandler, MockDmlHandlerCall},
+        namespace_resolver::mock::MockNamespaceResolver,
+    };
+
+    const NAMESPACE: &str = "_monitoring";
+    const NAMESPACE_ID: NamespaceId = NamespaceId::new(42);
+
+    fn new_monitor(
+        registry: &Arc<metric::Registry>,
+        dml_handler: &Arc<MockDmlHandler<HashMap<String, MutableBatch>>>,
+    ) -> SelfMonitor<
+        Arc<MockDmlHandler<HashMap<String, MutableBatch>>>,
+        MockNamespaceResolver,
+        MockProvider,
+    > {
+        SelfMonitor::new(
+            NamespaceName::new(NAMESPACE).unwrap(),
+            Arc::clone(registry),
+            MockNamespaceResolver::default().with_mapping(NAMESPACE, NAMESPACE_ID),
+            Arc::clone(dml_handler),
+        )
+        .with_time_provider(MockProvider::new(Time::from_timestamp_nanos(1234)))
+    }
+
+    #[tokio::test]
+    async fn test_report() {
+        let registry = Arc::new(metric::Registry::default());
+        registry
+            .register_metric::<U64Counter>("bananas", "a counter")
+            .recorder(&[("platanos", "yes")])
+            .inc(7);
+
+        let dml_handler =
+            Arc::new(MockDmlHandler::default().with_write_return([Ok(WriteSummary::default())]));
+        let monitor = new_monitor(&registry, &dml_handler);
+
+        monitor.report().await.expect("report should succeed");
+
+        let calls = dml_handler.calls();
+        assert_matches!(calls.as_slice(), [MockDmlHandlerCall::Write {
+            namespace,
+            namespace_id,
+            write_input,
+        }] => {
+            assert_eq!(namespace, NAMESPACE);
+            assert_eq!(*namespace_id, NAMESPACE_ID);
+
+            let batch = write_input.get("bananas_total").expect("missing counter table");
+            assert_eq!(batch.rows(), 1);
+
+            let column = batch.column("platanos").expect("missing attribute tag");
+            assert_eq!(column.influx_type(), schema::InfluxColumnType::Tag);
+
+            // The monitor's own metrics are included in the snapshot.
+            assert!(write_input.contains_key("self_monitoring_writes_total"));
+        });
+    }
+
+    #[tokio::test]
+    async fn test_report_skips_conflicting_series() {
+        let registry = Arc::new(metric::Registry::default());
+        let counter = registry.register_metric::<U64Counter>("bananas", "a counter");
+        counter.recorder(&[("platanos", "yes")]).inc(7);
+        // Renaming the "value" attribute clashes with the "value_tag"
+        // attribute.
+        counter
+            .recorder(&[("value", "a"), ("value_tag", "b")])
+            .inc(1);
+
+        let dml_handler =
+            Arc::new(MockDmlHandler::default().with_write_return([Ok(WriteSummary::default())]));
+        let monitor = new_monitor(&registry, &dml_handler);
+
+        monitor.report().await.expect("report should succeed");
+
+        let calls = dml_handler.calls();
+        assert_matches!(calls.as_slice(), [MockDmlHandlerCall::Write { write_input, .. }] => {
+            let batch = write_input.get("bananas_total").expect("missing counter table");
+            assert_eq!(batch.rows(), 1);
+        });
+        assert_eq!(monitor.skipped.fetch(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_report_error() {
+        let registry = Arc::new(metric::Registry::default());
+
+        let dml_handler = Arc::new(
+            MockDmlHandler::default()
+                .with_write_return([Err(DmlError::NamespaceNotFound("bananas".to_string()))]),
+        );
+        let monitor = new_monitor(&registry, &dml_handler);
+
+        assert_matches!(
+            monitor.report().await,
+            Err(Error::DmlHandler(DmlError::NamespaceNotFound(_)))
+        );
+    }
+}
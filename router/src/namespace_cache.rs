@@ -3,10 +3,14 @@
 mod memory;
 pub use memory::*;
 
+mod lru;
+pub use lru::*;
+
 mod sharded_cache;
 pub use sharded_cache::*;
 
 pub mod metrics;
+pub mod refresh;
 
 use std::{fmt::Debug, sync::Arc};
 
@@ -25,4 +29,29 @@ pub trait NamespaceCache: Debug + Send + Sync {
         namespace: NamespaceName<'static>,
         schema: impl Into<Arc<NamespaceSchema>>,
     ) -> Option<Arc<NamespaceSchema>>;
+
+    /// Overwrite the [`NamespaceSchema`] mapped to `namespace` with `schema`,
+    /// refreshed from the catalog, returning the previous value, if any.
+    ///
+    /// Implementations that track the usage of entries (such as for eviction)
+    /// MUST NOT treat this call as a use of the entry, and MAY ignore it if
+    /// `namespace` is no longer cached.
+    fn refresh_schema(
+        &self,
+        namespace: NamespaceName<'static>,
+        schema: impl Into<Arc<NamespaceSchema>>,
+    ) -> Option<Arc<NamespaceSchema>> {
+        self.put_schema(namespace, schema)
+    }
+
+    /// Remove the [`NamespaceSchema`] mapped to `namespace`, returning the
+    /// removed value, if any.
+    fn remove_schema(&self, namespace: &NamespaceName<'_>) -> Option<Arc<NamespaceSchema>>;
+
+    /// Return a snapshot of all the cached namespaces and their
+    /// [`NamespaceSchema`].
+    ///
+    /// Implementations that track the usage of entries (such as for eviction)
+    /// MUST NOT treat this call as a use of the returned entries.
+    fn list(&self) -> Vec<(NamespaceName<'static>, Arc<NamespaceSchema>)>;
 }
@@ -0,0 +1,254 @@
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
+
+use data_types::{NamespaceName, NamespaceSchema};
+use hashbrown::HashMap;
+use metric::U64Counter;
+use parking_lot::RwLock;
+
+use super::NamespaceCache;
+
+/// A decorator bounding the number of namespaces held in the inner
+/// [`NamespaceCache`] to `max_entries`, evicting the least recently used
+/// namespace when the limit is exceeded.
+///
+/// Evictions are performed by calling [`NamespaceCache::remove_schema()`] on
+/// the inner cache, so decorators such as the
+/// [`InstrumentedCache`](super::metrics::InstrumentedCache) SHOULD be wrapped
+/// by this type to observe them.
+///
+/// Reads only need to update the usage timestamp of an entry and do not block
+/// each other. Finding the least recently used entry is `O(n)` in the number
+/// of entries, but only occurs when inserting a new namespace into a full
+/// cache.
+#[derive(Debug)]
+pub struct LruCache<T> {
+    inner: T,
+    max_entries: usize,
+
+    /// A logical clock incremented for each cache use.
+    clock: AtomicU64,
+
+    /// The value of `clock` when each cached namespace was last used.
+    ///
+    /// The write lock is held for the duration of all mutations of `inner`,
+    /// keeping this set of keys consistent with the entries in `inner`.
+    last_used: RwLock<HashMap<NamespaceName<'static>, AtomicU64>>,
+
+    evictions: U64Counter,
+}
+
+impl<T> LruCache<T> {
+    /// Bound `inner` to at most `max_entries` namespaces.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_entries` is 0.
+    pub fn new(inner: T, max_entries: usize, registry: &metric::Registry) -> Self {
+        assert!(max_entries > 0, "namespace cache size must be non-zero");
+
+        let evictions = registry
+            .register_metric::<U64Counter>(
+                "namespace_cache_evictions",
+                "number of namespaces evicted from the cache to stay within the size limit",
+            )
+            .recorder([]);
+
+        Self {
+            inner,
+            max_entries,
+            clock: AtomicU64::new(0),
+            last_used: Default::default(),
+            evictions,
+        }
+    }
+
+    fn tick(&self) -> u64 {
+        self.clock.fetch_add(1, Ordering::Relaxed)
+    }
+}
+
+impl<T> NamespaceCache for Arc<LruCache<T>>
+where
+    T: NamespaceCache,
+{
+    fn get_schema(&self, namespace: &NamespaceName<'_>) -> Option<Arc<NamespaceSchema>> {
+        let res = self.inner.get_schema(namespace)?;
+
+        if let Some(t) = self.last_used.read().get(namespace) {
+            t.store(self.tick(), Ordering::Relaxed);
+        }
+
+        Some(res)
+    }
+
+    fn put_schema(
+        &self,
+        namespace: NamespaceName<'static>,
+        schema: impl Into<Arc<NamespaceSchema>>,
+    ) -> Option<Arc<NamespaceSchema>> {
+        let mut last_used = self.last_used.write();
+
+        let res = self.inner.put_schema(namespace.clone(), schema);
+        last_used.insert(namespace, AtomicU64::new(self.tick()));
+
+        while last_used.len() > self.max_entries {
+            let victim = last_used
+                .iter()
+                .min_by_key(|(_, t)| t.load(Ordering::Relaxed))
+                .map(|(k, _)| k.clone())
+                .expect("cache over limit must contain entries");
+
+            last_used.remove(&victim);
+            self.inner.remove_schema(&victim);
+            self.evictions.inc(1);
+        }
+
+        res
+    }
+
+    fn refresh_schema(
+        &self,
+        namespace: NamespaceName<'static>,
+        schema: impl Into<Arc<NamespaceSchema>>,
+    ) -> Option<Arc<NamespaceSchema>> {
+        let last_used = self.last_used.write();
+
+        // Neither mark the namespace as used, nor re-insert it if it was
+        // evicted or removed since it was refreshed.
+        if !last_used.contains_key(&namespace) {
+            return None;
+        }
+
+        self.inner.refresh_schema(namespace, schema)
+    }
+
+    fn remove_schema(&self, namespace: &NamespaceName<'_>) -> Option<Arc<NamespaceSchema>> {
+        let mut last_used = self.last_used.write();
+        last_used.remove(namespace);
+        self.inner.remove_schema(namespace)
+    }
+
+    fn list(&self) -> Vec<(NamespaceName<'static>, Arc<NamespaceSchema>)> {
+        self.inner.list()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use data_types::{NamespaceId, QueryPoolId, TopicId};
+    use metric::{Attributes, Metric};
+
+    use super::*;
+    use crate::namespace_cache::MemoryNamespaceCache;
+
+    fn schema_with_id(id: i64) -> NamespaceSchema {
+        NamespaceSchema {
+            id: NamespaceId::new(id),
+            topic_id: TopicId::new(1),
+            query_pool_id: QueryPoolId::new(1),
+            tables: Default::default(),
+            max_columns_per_table: 7,
+            retention_period_ns: None,
+        }
+    }
+
+    fn name(v: &str) -> NamespaceName<'static> {
+        NamespaceName::new(v.to_string()).expect("namespace name is valid")
+    }
+
+    #[test]
+    fn test_lru_eviction() {
+        let registry = metric::Registry::default();
+        let inner = Arc::new(MemoryNamespaceCache::default());
+        let cache = Arc::new(LruCache::new(Arc::clone(&inner), 2, &registry));
+
+        let (a, b, c) = (name("a"), name("b"), name("c"));
+
+        assert!(cache.put_schema(a.clone(), schema_with_id(1)).is_none());
+        assert!(cache.put_schema(b.clone(), schema_with_id(2)).is_none());
+
+        // Use "a", making "b" the least recently used.
+        assert!(cache.get_schema(&a).is_some());
+
+        // Listing does not count as a use.
+        assert_eq!(cache.list().len(), 2);
+
+        // Adding "c" evicts "b".
+        assert!(cache.put_schema(c.clone(), schema_with_id(3)).is_none());
+        assert!(cache.get_schema(&a).is_some());
+        assert!(cache.get_schema(&b).is_none());
+        assert!(cache.get_schema(&c).is_some());
+
+        // The eviction is applied to the inner cache.
+        assert!(inner.get_schema(&b).is_none());
+        assert_eq!(inner.list().len(), 2);
+
+        // Updating an existing entry does not evict anything.
+        assert!(cache.put_schema(c.clone(), schema_with_id(4)).is_some());
+        assert!(cache.get_schema(&a).is_some());
+
+        let evictions = registry
+            .get_instrument::<Metric<U64Counter>>("namespace_cache_evictions")
+            .expect("failed to read metric")
+            .get_observer(&Attributes::from(&[]))
+            .expect("failed to get observer")
+            .fetch();
+        assert_eq!(evictions, 1);
+    }
+
+    #[test]
+    fn test_remove() {
+        let inner = Arc::new(MemoryNamespaceCache::default());
+        let cache = Arc::new(LruCache::new(
+            Arc::clone(&inner),
+            1,
+            &metric::Registry::default(),
+        ));
+
+        let (a, b) = (name("a"), name("b"));
+
+        cache.put_schema(a.clone(), schema_with_id(1));
+        assert!(cache.remove_schema(&a).is_some());
+        assert!(inner.list().is_empty());
+
+        // The removed entry no longer counts towards the limit.
+        cache.put_schema(b.clone(), schema_with_id(2));
+        cache.put_schema(a.clone(), schema_with_id(1));
+        assert!(cache.get_schema(&b).is_none());
+        assert!(cache.get_schema(&a).is_some());
+    }
+
+    #[test]
+    fn test_refresh() {
+        let inner = Arc::new(MemoryNamespaceCache::default());
+        let cache = Arc::new(LruCache::new(
+            Arc::clone(&inner),
+            2,
+            &metric::Registry::default(),
+        ));
+
+        let (a, b, c) = (name("a"), name("b"), name("c"));
+
+        cache.put_schema(a.clone(), schema_with_id(1));
+        cache.put_schema(b.clone(), schema_with_id(2));
+
+        // Refreshing "a" updates it without counting as a use, leaving it the
+        // least recently used.
+        let mut refreshed = schema_with_id(1);
+        refreshed.max_columns_per_table = 42;
+        assert!(cache.refresh_schema(a.clone(), refreshed).is_some());
+        assert_eq!(inner.get_schema(&a).unwrap().max_columns_per_table, 42);
+
+        cache.put_schema(c.clone(), schema_with_id(3));
+        assert!(cache.get_schema(&a).is_none());
+        assert!(cache.get_schema(&b).is_some());
+
+        // Refreshing an evicted entry does not re-insert it.
+        assert!(cache.refresh_schema(a.clone(), schema_with_id(1)).is_none());
+        assert!(inner.get_schema(&a).is_none());
+        assert_eq!(inner.list().len(), 2);
+    }
+}
@@ -25,6 +25,18 @@ impl NamespaceCache for Arc<MemoryNamespaceCache> {
     ) -> Option<Arc<NamespaceSchema>> {
         self.cache.write().insert(namespace, schema.into())
     }
+
+    fn remove_schema(&self, namespace: &NamespaceName<'_>) -> Option<Arc<NamespaceSchema>> {
+        self.cache.write().remove(namespace)
+    }
+
+    fn list(&self) -> Vec<(NamespaceName<'static>, Arc<NamespaceSchema>)> {
+        self.cache
+            .read()
+            .iter()
+            .map(|(k, v)| (k.clone(), Arc::clone(v)))
+            .collect()
+    }
 }
 
 #[cfg(test)]
@@ -67,5 +79,17 @@ mod tests {
             schema1
         );
         assert_eq!(*cache.get_schema(&ns).expect("lookup failure"), schema2);
+
+        assert_eq!(cache.list(), [(ns.clone(), Arc::new(schema2.clone()))]);
+
+        assert_eq!(
+            *cache
+                .remove_schema(&ns)
+                .expect("should have existing schema"),
+            schema2
+        );
+        assert!(cache.get_schema(&ns).is_none());
+        assert!(cache.remove_schema(&ns).is_none());
+        assert!(cache.list().is_empty());
     }
 }
@@ -132,6 +132,21 @@ where
             }
         }
     }
+
+    fn remove_schema(&self, namespace: &NamespaceName<'_>) -> Option<Arc<NamespaceSchema>> {
+        let res = self.inner.remove_schema(namespace)?;
+
+        // Remove the evicted namespace stats from the counts.
+        let stats = NamespaceStats::new(&res);
+        self.table_count.delta(-(stats.table_count as i64));
+        self.column_count.delta(-(stats.column_count as i64));
+
+        Some(res)
+    }
+
+    fn list(&self) -> Vec<(NamespaceName<'static>, Arc<NamespaceSchema>)> {
+        self.inner.list()
+    }
 }
 
 #[derive(Debug)]
@@ -188,6 +203,7 @@ mod tests {
                     TableSchema {
                         id: TableId::new(i as _),
                         columns,
+                        dedup_policy: Default::default(),
                     },
                 )
             })
@@ -382,5 +398,12 @@ mod tests {
             ("result", "hit"),
             1,
         );
+
+        // Removing a namespace removes its stats from the counts.
+        assert!(cache.remove_schema(&ns).is_some());
+        assert_eq!(cache.table_count.observe(), Observation::U64Gauge(2));
+        assert_eq!(cache.column_count.observe(), Observation::U64Gauge(11));
+        assert!(cache.remove_schema(&ns).is_none());
+        assert_eq!(cache.table_count.observe(), Observation::U64Gauge(2));
     }
 }
@@ -0,0 +1,340 @@
+//! Background refresh of cached [`NamespaceSchema`] from the catalog.
+//!
+//! Namespace-level properties (such as the retention period and column limit)
+//! may be changed, and namespaces and tables deleted, through any router
+//! instance or directly in the catalog. Periodically reconciling the cached
+//! namespaces against the catalog bounds the staleness of these properties in
+//! every router to the refresh interval.
+//!
+//! Cached tables that were deleted (or deleted and recreated with a new ID)
+//! are removed, and table deduplication policies are updated. Columns are not
+//! refreshed - they are only ever added, and a router that has not observed a
+//! new column (or table) adds it to its cache when it is first written.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+    time::Duration,
+};
+
+use data_types::{Namespace, NamespaceSchema, Table};
+use iox_catalog::interface::Catalog;
+use metric::U64Counter;
+use observability_deps::tracing::*;
+use tokio::time::MissedTickBehavior;
+
+use super::NamespaceCache;
+
+/// Periodically reconciles the namespaces in a [`NamespaceCache`] with the
+/// state of the catalog.
+#[derive(Debug)]
+pub struct NamespaceCacheRefresher<C> {
+    cache: C,
+    catalog: Arc<dyn Catalog>,
+
+    /// Cached namespaces updated to reflect a catalog change.
+    updated: U64Counter,
+    /// Cached namespaces removed after being deleted (or recreated) in the
+    /// catalog.
+    removed: U64Counter,
+}
+
+impl<C> NamespaceCacheRefresher<C>
+where
+    C: NamespaceCache,
+{
+    /// Initialise a [`NamespaceCacheRefresher`] updating `cache` from
+    /// `catalog`.
+    pub fn new(cache: C, catalog: Arc<dyn Catalog>, registry: &metric::Registry) -> Self {
+        let refreshed = registry.register_metric::<U64Counter>(
+            "namespace_cache_refresh",
+            "number of cached namespaces changed by a background catalog refresh",
+        );
+
+        Self {
+            cache,
+            catalog,
+            updated: refreshed.recorder(&[("op", "update")]),
+            removed: refreshed.recorder(&[("op", "remove")]),
+        }
+    }
+
+    /// Refresh the cache every `interval`, forever.
+    pub async fn run(self, interval: Duration) {
+        let mut interval = tokio::time::interval(interval);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        // The cache is pre-warmed at startup, so skip the first, immediate
+        // tick.
+        interval.tick().await;
+
+        loop {
+            interval.tick().await;
+            if let Err(error) = self.refresh().await {
+                warn!(%error, "failed to refresh namespace cache");
+            }
+        }
+    }
+
+    /// Reconcile all cached namespaces with the catalog once.
+    ///
+    /// Each cached namespace (and its tables) is read from the catalog
+    /// individually, so the cost of a refresh is bounded by the size of the
+    /// cache rather than the size of the catalog.
+    pub async fn refresh(&self) -> Result<(), iox_catalog::interface::Error> {
+        for (name, schema) in self.cache.list() {
+            let mut repos = self.catalog.repositories().await;
+
+            match repos.namespaces().get_by_name(name.as_str()).await? {
+                Some(ns) if ns.id == schema.id => {
+                    let tables = repos
+                        .tables()
+                        .list_by_namespace_id(ns.id)
+                        .await?
+                        .into_iter()
+                        .map(|t| (t.name.clone(), t))
+                        .collect::<HashMap<_, _>>();
+                    let Some(new) = apply_namespace(&schema, &ns, &tables) else {
+                        continue;
+                    };
+
+                    info!(
+                        %name,
+                        retention_period_ns = ?new.retention_period_ns,
+                        max_columns_per_table = new.max_columns_per_table,
+                        n_tables = new.tables.len(),
+                        "refreshed cached namespace"
+                    );
+
+                    // A schema put by a concurrent write between listing the
+                    // cache and this refresh may be overwritten, dropping any
+                    // columns it added - these are re-added from the catalog
+                    // when next written.
+                    self.cache.refresh_schema(name, new);
+                    self.updated.inc(1);
+                }
+                _ => {
+                    // The namespace was deleted, or deleted and recreated with
+                    // a new ID (invalidating the cached tables). The schema is
+                    // loaded from the catalog when next used, if it exists.
+                    info!(%name, "removing deleted namespace from cache");
+                    self.cache.remove_schema(&name);
+                    self.removed.inc(1);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Return a copy of `schema` with the namespace-level properties of `ns` and
+/// the cached tables reconciled with `tables`, or [`None`] if nothing changed.
+///
+/// Tables missing from `tables`, or present with a different ID, were deleted
+/// and are removed.
+fn apply_namespace(
+    schema: &NamespaceSchema,
+    ns: &Namespace,
+    tables: &HashMap<String, Table>,
+) -> Option<NamespaceSchema> {
+    let max_columns_per_table = ns.max_columns_per_table as usize;
+
+    let tables = schema
+        .tables
+        .iter()
+        .filter_map(|(name, cached)| {
+            let table = tables.get(name).filter(|t| t.id == cached.id)?;
+            let mut cached = cached.clone();
+            cached.dedup_policy = table.dedup_policy;
+            Some((name.clone(), cached))
+        })
+        .collect::<BTreeMap<_, _>>();
+
+    if schema.retention_period_ns == ns.retention_period_ns
+        && schema.max_columns_per_table == max_columns_per_table
+        && schema.tables == tables
+    {
+        return None;
+    }
+
+    Some(NamespaceSchema {
+        retention_period_ns: ns.retention_period_ns,
+        max_columns_per_table,
+        tables,
+        ..schema.clone()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use data_types::{DeduplicationPolicy, NamespaceName};
+    use iox_catalog::{interface::get_schema_by_name, mem::MemCatalog};
+
+    use super::*;
+    use crate::namespace_cache::MemoryNamespaceCache;
+
+    const NAMESPACE: &str = "bananas";
+
+    async fn cached_schema(catalog: &dyn Catalog, cache: &Arc<MemoryNamespaceCache>) {
+        let mut repos = catalog.repositories().await;
+        let schema = get_schema_by_name(NAMESPACE, &mut *repos).await.unwrap();
+        cache.put_schema(NamespaceName::new(NAMESPACE).unwrap(), schema);
+    }
+
+    #[tokio::test]
+    async fn test_refresh() {
+        let metrics = Arc::new(metric::Registry::default());
+        let catalog: Arc<dyn Catalog> = Arc::new(MemCatalog::new(Arc::clone(&metrics)));
+        let name = NamespaceName::new(NAMESPACE).unwrap();
+
+        let mut repos = catalog.repositories().await;
+        let topic = repos.topics().create_or_get("foo").await.unwrap();
+        let pool = repos.query_pools().create_or_get("foo").await.unwrap();
+        repos
+            .namespaces()
+            .create(NAMESPACE, None, topic.id, pool.id)
+            .await
+            .unwrap();
+        drop(repos);
+
+        let cache = Arc::new(MemoryNamespaceCache::default());
+        cached_schema(&*catalog, &cache).await;
+
+        let refresher =
+            NamespaceCacheRefresher::new(Arc::clone(&cache), Arc::clone(&catalog), &metrics);
+
+        // Nothing changed.
+        let before = cache.get_schema(&name).unwrap();
+        refresher.refresh().await.unwrap();
+        assert!(Arc::ptr_eq(&before, &cache.get_schema(&name).unwrap()));
+
+        // Changes to the retention period and column limit are applied.
+        {
+            let mut repos = catalog.repositories().await;
+            repos
+                .namespaces()
+                .update_retention_period(NAMESPACE, Some(42))
+                .await
+                .unwrap();
+            repos
+                .namespaces()
+                .update_column_limit(NAMESPACE, 7)
+                .await
+                .unwrap();
+        }
+        refresher.refresh().await.unwrap();
+        let got = cache.get_schema(&name).unwrap();
+        assert_eq!(got.retention_period_ns, Some(42));
+        assert_eq!(got.max_columns_per_table, 7);
+        assert_eq!(got.id, before.id);
+
+        // A deleted namespace is removed from the cache.
+        catalog
+            .repositories()
+            .await
+            .namespaces()
+            .delete(NAMESPACE)
+            .await
+            .unwrap();
+        refresher.refresh().await.unwrap();
+        assert!(cache.get_schema(&name).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_refresh_recreated() {
+        let metrics = Arc::new(metric::Registry::default());
+        let catalog: Arc<dyn Catalog> = Arc::new(MemCatalog::new(Arc::clone(&metrics)));
+        let name = NamespaceName::new(NAMESPACE).unwrap();
+
+        let mut repos = catalog.repositories().await;
+        let topic = repos.topics().create_or_get("foo").await.unwrap();
+        let pool = repos.query_pools().create_or_get("foo").await.unwrap();
+        repos
+            .namespaces()
+            .create(NAMESPACE, None, topic.id, pool.id)
+            .await
+            .unwrap();
+        drop(repos);
+
+        let cache = Arc::new(MemoryNamespaceCache::default());
+        cached_schema(&*catalog, &cache).await;
+
+        // Recreate the namespace with the same name.
+        let mut repos = catalog.repositories().await;
+        repos.namespaces().delete(NAMESPACE).await.unwrap();
+        repos
+            .namespaces()
+            .create(NAMESPACE, None, topic.id, pool.id)
+            .await
+            .unwrap();
+        drop(repos);
+
+        // The stale schema (with the old namespace ID) is invalidated.
+        NamespaceCacheRefresher::new(Arc::clone(&cache), catalog, &metrics)
+            .refresh()
+            .await
+            .unwrap();
+        assert!(cache.get_schema(&name).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_refresh_tables() {
+        let metrics = Arc::new(metric::Registry::default());
+        let catalog: Arc<dyn Catalog> = Arc::new(MemCatalog::new(Arc::clone(&metrics)));
+        let name = NamespaceName::new(NAMESPACE).unwrap();
+
+        let mut repos = catalog.repositories().await;
+        let topic = repos.topics().create_or_get("foo").await.unwrap();
+        let pool = repos.query_pools().create_or_get("foo").await.unwrap();
+        let ns = repos
+            .namespaces()
+            .create(NAMESPACE, None, topic.id, pool.id)
+            .await
+            .unwrap();
+        let bananas = repos
+            .tables()
+            .create_or_get("bananas", ns.id)
+            .await
+            .unwrap();
+        let platanos = repos
+            .tables()
+            .create_or_get("platanos", ns.id)
+            .await
+            .unwrap();
+        let fruit = repos.tables().create_or_get("fruit", ns.id).await.unwrap();
+        drop(repos);
+
+        let cache = Arc::new(MemoryNamespaceCache::default());
+        cached_schema(&*catalog, &cache).await;
+        assert_eq!(cache.get_schema(&name).unwrap().tables.len(), 3);
+
+        // Delete one table, recreate another, and change the deduplication
+        // policy of the last one.
+        {
+            let mut repos = catalog.repositories().await;
+            repos.tables().delete(platanos.id).await.unwrap();
+            repos.tables().delete(fruit.id).await.unwrap();
+            repos.tables().create_or_get("fruit", ns.id).await.unwrap();
+            repos
+                .tables()
+                .update_dedup_policy(bananas.id, DeduplicationPolicy::AppendOnly)
+                .await
+                .unwrap();
+        }
+
+        NamespaceCacheRefresher::new(Arc::clone(&cache), catalog, &metrics)
+            .refresh()
+            .await
+            .unwrap();
+
+        // Only the table that still exists with the same ID remains cached.
+        let got = cache.get_schema(&name).unwrap();
+        assert_eq!(got.tables.keys().collect::<Vec<_>>(), ["bananas"]);
+        assert_eq!(got.tables["bananas"].id, bananas.id);
+        assert_eq!(
+            got.tables["bananas"].dedup_policy,
+            DeduplicationPolicy::AppendOnly
+        );
+    }
+}
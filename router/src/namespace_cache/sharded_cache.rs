@@ -36,6 +36,14 @@ where
     ) -> Option<Arc<NamespaceSchema>> {
         self.shards.hash(&namespace).put_schema(namespace, schema)
     }
+
+    fn remove_schema(&self, namespace: &NamespaceName<'_>) -> Option<Arc<NamespaceSchema>> {
+        self.shards.hash(namespace).remove_schema(namespace)
+    }
+
+    fn list(&self) -> Vec<(NamespaceName<'static>, Arc<NamespaceSchema>)> {
+        self.shards.shards().iter().flat_map(|s| s.list()).collect()
+    }
 }
 
 #[cfg(test)]
@@ -101,9 +109,18 @@ mod tests {
         }
 
         // The mapping should be stable
-        for (name, id) in names {
-            let want = schema_with_id(id as _);
-            assert_eq!(cache.get_schema(&name), Some(Arc::new(want)));
+        for (name, id) in &names {
+            let want = schema_with_id(*id as _);
+            assert_eq!(cache.get_schema(name), Some(Arc::new(want)));
+        }
+
+        // All entries are listed across all shards.
+        assert_eq!(cache.list().len(), names.len());
+
+        // And removed from the shard they were placed in.
+        for name in names.keys() {
+            assert!(cache.remove_schema(name).is_some());
         }
+        assert!(cache.list().is_empty());
     }
 }
@@ -37,6 +37,19 @@ pub trait NamespaceResolver: std::fmt::Debug + Send + Sync {
     ) -> Result<NamespaceId, Error>;
 }
 
+#[async_trait]
+impl<T> NamespaceResolver for Arc<T>
+where
+    T: NamespaceResolver,
+{
+    async fn get_namespace_id(
+        &self,
+        namespace: &NamespaceName<'static>,
+    ) -> Result<NamespaceId, Error> {
+        (**self).get_namespace_id(namespace).await
+    }
+}
+
 /// An implementation of [`NamespaceResolver`] that queries the [`Catalog`] to
 /// resolve a [`NamespaceId`], and populates the [`NamespaceCache`] as a side
 /// effect.
@@ -7,31 +7,39 @@ use mutable_batch::MutableBatch;
 use trace::TraceCollector;
 
 use self::{
-    grpc::{GrpcDelegate, RpcWriteGrpcDelegate},
+    grpc::{
+        downsampling::DownsamplingWriteService, otlp::OtlpMetricsService, GrpcDelegate,
+        RpcWriteGrpcDelegate,
+    },
     http::HttpDelegate,
 };
 use crate::dml_handlers::DmlHandler;
 
 pub mod grpc;
 pub mod http;
+mod otlp;
 
 /// The [`RpcWriteRouterServer`] manages the lifecycle and contains all state for a
 /// `router-rpc-write` server instance.
 #[derive(Debug)]
-pub struct RpcWriteRouterServer<D, N> {
+pub struct RpcWriteRouterServer<D, N, C> {
     metrics: Arc<metric::Registry>,
     trace_collector: Option<Arc<dyn TraceCollector>>,
 
     http: HttpDelegate<D, N>,
-    grpc: RpcWriteGrpcDelegate,
+    grpc: RpcWriteGrpcDelegate<C>,
+    otlp: OtlpMetricsService<D, N>,
+    downsampling_write: DownsamplingWriteService<D, N>,
 }
 
-impl<D, N> RpcWriteRouterServer<D, N> {
-    /// Initialise a new [`RpcWriteRouterServer`] using the provided HTTP and gRPC
-    /// handlers.
+impl<D, N, C> RpcWriteRouterServer<D, N, C> {
+    /// Initialise a new [`RpcWriteRouterServer`] using the provided HTTP, gRPC,
+    /// OTLP and downsampling write handlers.
     pub fn new(
         http: HttpDelegate<D, N>,
-        grpc: RpcWriteGrpcDelegate,
+        grpc: RpcWriteGrpcDelegate<C>,
+        otlp: OtlpMetricsService<D, N>,
+        downsampling_write: DownsamplingWriteService<D, N>,
         metrics: Arc<metric::Registry>,
         trace_collector: Option<Arc<dyn TraceCollector>>,
     ) -> Self {
@@ -40,6 +48,8 @@ impl<D, N> RpcWriteRouterServer<D, N> {
             trace_collector,
             http,
             grpc,
+            otlp,
+            downsampling_write,
         }
     }
 
@@ -59,28 +69,42 @@ impl<D, N> RpcWriteRouterServer<D, N> {
     }
 
     /// Get a reference to the router grpc delegate.
-    pub fn grpc(&self) -> &RpcWriteGrpcDelegate {
+    pub fn grpc(&self) -> &RpcWriteGrpcDelegate<C> {
         &self.grpc
     }
+
+    /// Get a reference to the router OTLP metrics gRPC service.
+    pub fn otlp(&self) -> &OtlpMetricsService<D, N> {
+        &self.otlp
+    }
+
+    /// Get a reference to the router downsampling write gRPC service.
+    pub fn downsampling_write(&self) -> &DownsamplingWriteService<D, N> {
+        &self.downsampling_write
+    }
 }
 
 /// The [`RouterServer`] manages the lifecycle and contains all state for a
 /// `router` server instance.
 #[derive(Debug)]
-pub struct RouterServer<D, N, S> {
+pub struct RouterServer<D, N, S, C> {
     metrics: Arc<metric::Registry>,
     trace_collector: Option<Arc<dyn TraceCollector>>,
 
     http: HttpDelegate<D, N>,
-    grpc: GrpcDelegate<S>,
+    grpc: GrpcDelegate<S, C>,
+    otlp: OtlpMetricsService<D, N>,
+    downsampling_write: DownsamplingWriteService<D, N>,
 }
 
-impl<D, N, S> RouterServer<D, N, S> {
-    /// Initialise a new [`RouterServer`] using the provided HTTP and gRPC
-    /// handlers.
+impl<D, N, S, C> RouterServer<D, N, S, C> {
+    /// Initialise a new [`RouterServer`] using the provided HTTP, gRPC, OTLP
+    /// and downsampling write handlers.
     pub fn new(
         http: HttpDelegate<D, N>,
-        grpc: GrpcDelegate<S>,
+        grpc: GrpcDelegate<S, C>,
+        otlp: OtlpMetricsService<D, N>,
+        downsampling_write: DownsamplingWriteService<D, N>,
         metrics: Arc<metric::Registry>,
         trace_collector: Option<Arc<dyn TraceCollector>>,
     ) -> Self {
@@ -89,6 +113,8 @@ impl<D, N, S> RouterServer<D, N, S> {
             trace_collector,
             http,
             grpc,
+            otlp,
+            downsampling_write,
         }
     }
 
@@ -103,7 +129,7 @@ impl<D, N, S> RouterServer<D, N, S> {
     }
 }
 
-impl<D, N, S> RouterServer<D, N, S>
+impl<D, N, S, C> RouterServer<D, N, S, C>
 where
     D: DmlHandler<WriteInput = HashMap<String, MutableBatch>>,
 {
@@ -113,7 +139,17 @@ where
     }
 
     /// Get a reference to the router grpc delegate.
-    pub fn grpc(&self) -> &GrpcDelegate<S> {
+    pub fn grpc(&self) -> &GrpcDelegate<S, C> {
         &self.grpc
     }
+
+    /// Get a reference to the router OTLP metrics gRPC service.
+    pub fn otlp(&self) -> &OtlpMetricsService<D, N> {
+        &self.otlp
+    }
+
+    /// Get a reference to the router downsampling write gRPC service.
+    pub fn downsampling_write(&self) -> &DownsamplingWriteService<D, N> {
+        &self.downsampling_write
+    }
 }
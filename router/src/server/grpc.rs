@@ -1,37 +1,47 @@
 //! gRPC service implementations for `router`.
 
+pub mod downsampling;
+pub mod otlp;
 pub mod sharder;
+pub mod table;
 
 use std::sync::Arc;
 
 use ::sharder::Sharder;
 use data_types::{QueryPoolId, TopicId};
 use generated_types::influxdata::iox::{
-    catalog::v1::*, namespace::v1::*, object_store::v1::*, schema::v1::*, sharder::v1::*,
+    catalog::v1::*, downsampling::v1::*, namespace::v1::*, object_store::v1::*, schema::v1::*,
+    sharder::v1::*, table::v1::*,
 };
 use iox_catalog::interface::Catalog;
+use iox_time::SystemProvider;
 use object_store::DynObjectStore;
 use service_grpc_catalog::CatalogService;
 use service_grpc_namespace::NamespaceService;
 use service_grpc_object_store::ObjectStoreService;
 use service_grpc_schema::SchemaService;
 
-use self::sharder::ShardService;
-use crate::shard::Shard;
+use self::{downsampling::DownsamplingService, sharder::ShardService, table::TableService};
+use crate::{namespace_cache::NamespaceCache, shard::Shard};
 
 /// This type manages all gRPC services exposed by a `router` using the RPC write path.
 #[derive(Debug)]
-pub struct RpcWriteGrpcDelegate {
+pub struct RpcWriteGrpcDelegate<C> {
     catalog: Arc<dyn Catalog>,
     object_store: Arc<DynObjectStore>,
+    ns_cache: C,
 }
 
-impl RpcWriteGrpcDelegate {
+impl<C> RpcWriteGrpcDelegate<C>
+where
+    C: NamespaceCache + Clone + 'static,
+{
     /// Create a new gRPC handler
-    pub fn new(catalog: Arc<dyn Catalog>, object_store: Arc<DynObjectStore>) -> Self {
+    pub fn new(catalog: Arc<dyn Catalog>, object_store: Arc<DynObjectStore>, ns_cache: C) -> Self {
         Self {
             catalog,
             object_store,
+            ns_cache,
         }
     }
 
@@ -69,19 +79,42 @@ impl RpcWriteGrpcDelegate {
             Arc::clone(&self.object_store),
         ))
     }
+
+    /// Acquire a [`TableService`] gRPC service implementation.
+    ///
+    /// [`TableService`]: generated_types::influxdata::iox::table::v1::table_service_server::TableService.
+    pub fn table_service(&self) -> table_service_server::TableServiceServer<TableService<C>> {
+        table_service_server::TableServiceServer::new(TableService::new(
+            Arc::clone(&self.catalog),
+            self.ns_cache.clone(),
+        ))
+    }
+
+    /// Acquire a [`DownsamplingService`] gRPC service implementation.
+    ///
+    /// [`DownsamplingService`]: generated_types::influxdata::iox::downsampling::v1::downsampling_service_server::DownsamplingService.
+    pub fn downsampling_service(
+        &self,
+    ) -> downsampling_service_server::DownsamplingServiceServer<DownsamplingService> {
+        downsampling_service_server::DownsamplingServiceServer::new(DownsamplingService::new(
+            Arc::clone(&self.catalog),
+            Arc::new(SystemProvider::default()),
+        ))
+    }
 }
 
 /// This type is responsible for managing all gRPC services exposed by `router`.
 #[derive(Debug)]
-pub struct GrpcDelegate<S> {
+pub struct GrpcDelegate<S, C> {
     topic_id: TopicId,
     query_pool_id: QueryPoolId,
     catalog: Arc<dyn Catalog>,
     object_store: Arc<DynObjectStore>,
     shard_service: ShardService<S>,
+    ns_cache: C,
 }
 
-impl<S> GrpcDelegate<S> {
+impl<S, C> GrpcDelegate<S, C> {
     /// Initialise a new gRPC handler, dispatching DML operations to `dml_handler`.
     pub fn new(
         topic_id: TopicId,
@@ -89,6 +122,7 @@ impl<S> GrpcDelegate<S> {
         catalog: Arc<dyn Catalog>,
         object_store: Arc<DynObjectStore>,
         shard_service: ShardService<S>,
+        ns_cache: C,
     ) -> Self {
         Self {
             topic_id,
@@ -96,13 +130,15 @@ impl<S> GrpcDelegate<S> {
             catalog,
             object_store,
             shard_service,
+            ns_cache,
         }
     }
 }
 
-impl<S> GrpcDelegate<S>
+impl<S, C> GrpcDelegate<S, C>
 where
     S: Sharder<(), Item = Arc<Shard>> + Clone + 'static,
+    C: NamespaceCache + Clone + 'static,
 {
     /// Acquire a [`SchemaService`] gRPC service implementation.
     ///
@@ -160,4 +196,26 @@ where
             Some(self.query_pool_id),
         ))
     }
+
+    /// Acquire a [`TableService`] gRPC service implementation.
+    ///
+    /// [`TableService`]: generated_types::influxdata::iox::table::v1::table_service_server::TableService.
+    pub fn table_service(&self) -> table_service_server::TableServiceServer<TableService<C>> {
+        table_service_server::TableServiceServer::new(TableService::new(
+            Arc::clone(&self.catalog),
+            self.ns_cache.clone(),
+        ))
+    }
+
+    /// Acquire a [`DownsamplingService`] gRPC service implementation.
+    ///
+    /// [`DownsamplingService`]: generated_types::influxdata::iox::downsampling::v1::downsampling_service_server::DownsamplingService.
+    pub fn downsampling_service(
+        &self,
+    ) -> downsampling_service_server::DownsamplingServiceServer<DownsamplingService> {
+        downsampling_service_server::DownsamplingServiceServer::new(DownsamplingService::new(
+            Arc::clone(&self.catalog),
+            Arc::new(SystemProvider::default()),
+        ))
+    }
 }
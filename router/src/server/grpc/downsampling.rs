@@ -0,0 +1,621 @@
+//! gRPC services to create, list, delete and backfill downsampling tasks, and
+//! to write the results of their windows.
+
+use std::{ops::DerefMut, sync::Arc};
+
+use data_types::{
+    DownsamplingTask as CatalogDownsamplingTask, DownsamplingTaskParams, NamespaceName, Timestamp,
+    DOWNSAMPLING_WINDOW_END, DOWNSAMPLING_WINDOW_START,
+};
+use generated_types::influxdata::iox::downsampling::v1::*;
+use hashbrown::HashMap;
+use iox_catalog::interface::{Catalog, Error as CatalogError, RepoCollection};
+use iox_time::TimeProvider;
+use mutable_batch::MutableBatch;
+use mutable_batch_pb::decode::write_table_batch;
+use observability_deps::tracing::*;
+use tonic::{Request, Response, Status};
+use trace::ctx::SpanContext;
+
+use super::otlp::dml_error_to_status;
+use crate::{
+    dml_handlers::{DmlError, DmlHandler},
+    namespace_resolver::NamespaceResolver,
+};
+
+/// A [`DownsamplingService`] exposes a [gRPC endpoint] to manage the
+/// downsampling tasks stored in the catalog.
+///
+/// The tasks are run by the queriers configured to do so, which write the
+/// results of each window back through a router and advance the watermark of
+/// the task. This service only manages the task definitions and their
+/// watermarks.
+///
+/// [gRPC endpoint]: generated_types::influxdata::iox::downsampling::v1::downsampling_service_server::DownsamplingService
+#[derive(Debug)]
+pub struct DownsamplingService {
+    catalog: Arc<dyn Catalog>,
+    time_provider: Arc<dyn TimeProvider>,
+}
+
+impl DownsamplingService {
+    /// Initialise a gRPC [`DownsamplingService`] handler, using
+    /// `time_provider` to pick the first window of tasks created without an
+    /// explicit start.
+    pub fn new(catalog: Arc<dyn Catalog>, time_provider: Arc<dyn TimeProvider>) -> Self {
+        Self {
+            catalog,
+            time_provider,
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl downsampling_service_server::DownsamplingService for DownsamplingService {
+    async fn get_downsampling_tasks(
+        &self,
+        _request: Request<GetDownsamplingTasksRequest>,
+    ) -> Result<Response<GetDownsamplingTasksResponse>, Status> {
+        let mut repos = self.catalog.repositories().await;
+
+        let tasks = repos
+            .downsampling_tasks()
+            .list()
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+
+        let mut out = Vec::with_capacity(tasks.len());
+        for task in tasks {
+            out.push(task_to_proto(task, repos.deref_mut()).await?);
+        }
+
+        Ok(Response::new(GetDownsamplingTasksResponse { tasks: out }))
+    }
+
+    async fn create_downsampling_task(
+        &self,
+        request: Request<CreateDownsamplingTaskRequest>,
+    ) -> Result<Response<CreateDownsamplingTaskResponse>, Status> {
+        let CreateDownsamplingTaskRequest {
+            name,
+            namespace_name,
+            query,
+            query_language,
+            target_namespace,
+            target_table,
+            every_ns,
+            delay_ns,
+            start_ns,
+        } = request.into_inner();
+
+        if name.is_empty() {
+            return Err(Status::invalid_argument("task name must not be empty"));
+        }
+        for placeholder in [DOWNSAMPLING_WINDOW_START, DOWNSAMPLING_WINDOW_END] {
+            if !query.contains(placeholder) {
+                return Err(Status::invalid_argument(format!(
+                    "query must refer to the window bounds as {} and {}",
+                    DOWNSAMPLING_WINDOW_START, DOWNSAMPLING_WINDOW_END
+                )));
+            }
+        }
+        if target_table.is_empty() {
+            return Err(Status::invalid_argument(
+                "target table name must not be empty",
+            ));
+        }
+        if every_ns <= 0 {
+            return Err(Status::invalid_argument("every_ns must be positive"));
+        }
+        if delay_ns < 0 {
+            return Err(Status::invalid_argument("delay_ns must not be negative"));
+        }
+        let namespace = NamespaceName::try_from(namespace_name)
+            .map_err(|e| Status::invalid_argument(e.to_string()))?;
+        let target_namespace = NamespaceName::try_from(target_namespace)
+            .map_err(|e| Status::invalid_argument(e.to_string()))?;
+        let query_language = query_language_from_proto(query_language)?;
+
+        let start_ns = start_ns.unwrap_or_else(|| self.time_provider.now().timestamp_nanos());
+
+        let mut repos = self.catalog.repositories().await;
+
+        let ns = repos
+            .namespaces()
+            .get_by_name(&namespace)
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?
+            .ok_or_else(|| Status::not_found(format!("namespace {namespace} not found")))?;
+
+        let task = repos
+            .downsampling_tasks()
+            .create(DownsamplingTaskParams {
+                name: name.clone(),
+                namespace_id: ns.id,
+                query,
+                query_language,
+                target_namespace: target_namespace.to_string(),
+                target_table,
+                every_ns,
+                delay_ns,
+                watermark: Timestamp::new(align_down(start_ns, every_ns)),
+            })
+            .await
+            .map_err(|e| {
+                warn!(error=%e, %namespace, task=%name, "failed to create downsampling task");
+                catalog_error_to_status(e)
+            })?;
+
+        info!(%namespace, task=%name, task_id=%task.id, "created downsampling task");
+
+        Ok(Response::new(CreateDownsamplingTaskResponse {
+            task: Some(task_to_proto(task, repos.deref_mut()).await?),
+        }))
+    }
+
+    async fn delete_downsampling_task(
+        &self,
+        request: Request<DeleteDownsamplingTaskRequest>,
+    ) -> Result<Response<DeleteDownsamplingTaskResponse>, Status> {
+        let DeleteDownsamplingTaskRequest { name } = request.into_inner();
+
+        let mut repos = self.catalog.repositories().await;
+
+        let task = lookup_task(&name, repos.deref_mut()).await?;
+        repos
+            .downsampling_tasks()
+            .delete(task.id)
+            .await
+            .map_err(|e| {
+                warn!(error=%e, task=%name, "failed to delete downsampling task");
+                catalog_error_to_status(e)
+            })?;
+
+        info!(task=%name, task_id=%task.id, "deleted downsampling task");
+
+        Ok(Response::new(DeleteDownsamplingTaskResponse {}))
+    }
+
+    async fn backfill_downsampling_task(
+        &self,
+        request: Request<BackfillDownsamplingTaskRequest>,
+    ) -> Result<Response<BackfillDownsamplingTaskResponse>, Status> {
+        let BackfillDownsamplingTaskRequest { name, start_ns } = request.into_inner();
+
+        let mut repos = self.catalog.repositories().await;
+
+        let task = lookup_task(&name, repos.deref_mut()).await?;
+        let watermark = Timestamp::new(align_down(start_ns, task.every_ns));
+        if watermark > task.watermark {
+            // Moving the watermark forward would silently skip windows.
+            return Err(Status::failed_precondition(format!(
+                "backfill start {} is later than the watermark {} of task {name}",
+                watermark.get(),
+                task.watermark.get(),
+            )));
+        }
+
+        let task = repos
+            .downsampling_tasks()
+            .set_watermark(task.id, watermark)
+            .await
+            .map_err(|e| {
+                warn!(error=%e, task=%name, "failed to backfill downsampling task");
+                catalog_error_to_status(e)
+            })?;
+
+        info!(task=%name, task_id=%task.id, watermark=%watermark.get(), "backfilling downsampling task");
+
+        Ok(Response::new(BackfillDownsamplingTaskResponse {
+            task: Some(task_to_proto(task, repos.deref_mut()).await?),
+        }))
+    }
+}
+
+/// A [`DownsamplingWriteService`] exposes a [gRPC endpoint] accepting the
+/// results of the windows of downsampling tasks as [`MutableBatch`]es, and
+/// writes them to a [`DmlHandler`] like any other write.
+///
+/// [gRPC endpoint]: generated_types::influxdata::iox::downsampling::v1::downsampling_write_service_server::DownsamplingWriteService
+#[derive(Debug, Clone)]
+pub struct DownsamplingWriteService<D, N> {
+    namespace_resolver: N,
+    dml_handler: D,
+}
+
+impl<D, N> DownsamplingWriteService<D, N> {
+    /// Initialise a gRPC [`DownsamplingWriteService`] handler, resolving
+    /// namespaces with `namespace_resolver` and writing to `dml_handler`.
+    pub fn new(namespace_resolver: N, dml_handler: D) -> Self {
+        Self {
+            namespace_resolver,
+            dml_handler,
+        }
+    }
+}
+
+impl<D, N> DownsamplingWriteService<D, N>
+where
+    Self: downsampling_write_service_server::DownsamplingWriteService + Clone,
+{
+    /// Acquire a [`DownsamplingWriteServiceServer`] gRPC service for this
+    /// handler.
+    ///
+    /// [`DownsamplingWriteServiceServer`]: downsampling_write_service_server::DownsamplingWriteServiceServer
+    pub fn service(
+        &self,
+    ) -> downsampling_write_service_server::DownsamplingWriteServiceServer<Self> {
+        downsampling_write_service_server::DownsamplingWriteServiceServer::new(self.clone())
+    }
+}
+
+#[tonic::async_trait]
+impl<D, N> downsampling_write_service_server::DownsamplingWriteService
+    for DownsamplingWriteService<D, N>
+where
+    D: DmlHandler<WriteInput = HashMap<String, MutableBatch>> + 'static,
+    N: NamespaceResolver + 'static,
+{
+    async fn write_window(
+        &self,
+        request: Request<WriteWindowRequest>,
+    ) -> Result<Response<WriteWindowResponse>, Status> {
+        let span_ctx: Option<SpanContext> = request.extensions().get().cloned();
+        let WriteWindowRequest {
+            namespace,
+            table,
+            batch,
+        } = request.into_inner();
+
+        let namespace = NamespaceName::try_from(namespace)
+            .map_err(|e| Status::invalid_argument(e.to_string()))?;
+        if table.is_empty() {
+            return Err(Status::invalid_argument("table name must not be empty"));
+        }
+        let table_batch = batch.ok_or_else(|| Status::invalid_argument("missing batch"))?;
+
+        let mut batch = MutableBatch::new();
+        write_table_batch(&mut batch, &table_batch)
+            .map_err(|e| Status::invalid_argument(e.to_string()))?;
+        if batch.rows() == 0 {
+            return Ok(Response::new(WriteWindowResponse {}));
+        }
+
+        debug!(%namespace, %table, rows = batch.rows(), "routing downsampled window");
+
+        let namespace_id = self
+            .namespace_resolver
+            .get_namespace_id(&namespace)
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+
+        let mut batches = HashMap::with_capacity(1);
+        batches.insert(table, batch);
+
+        self.dml_handler
+            .write(&namespace, namespace_id, batches, span_ctx)
+            .await
+            .map_err(|e| {
+                let e: DmlError = e.into();
+                dml_error_to_status(&e)
+            })?;
+
+        Ok(Response::new(WriteWindowResponse {}))
+    }
+}
+
+/// Round `ts` down to a multiple of `every`, the start of the window
+/// containing it.
+fn align_down(ts: i64, every: i64) -> i64 {
+    ts - ts.rem_euclid(every)
+}
+
+/// Resolve the catalog [`CatalogDownsamplingTask`] named `name`.
+async fn lookup_task<R>(name: &str, repos: &mut R) -> Result<CatalogDownsamplingTask, Status>
+where
+    R: RepoCollection + ?Sized,
+{
+    repos
+        .downsampling_tasks()
+        .get_by_name(name)
+        .await
+        .map_err(|e| Status::internal(e.to_string()))?
+        .ok_or_else(|| Status::not_found(format!("downsampling task {name} not found")))
+}
+
+fn catalog_error_to_status(e: CatalogError) -> Status {
+    match e {
+        CatalogError::NameExists { .. } => Status::already_exists(e.to_string()),
+        CatalogError::DownsamplingTaskNotFound { .. }
+        | CatalogError::NamespaceNotFoundById { .. } => Status::not_found(e.to_string()),
+        _ => Status::internal(e.to_string()),
+    }
+}
+
+fn query_language_from_proto(v: i32) -> Result<data_types::QueryLanguage, Status> {
+    QueryLanguage::from_i32(v)
+        .map(Into::into)
+        .ok_or_else(|| Status::invalid_argument(format!("invalid query language {v}")))
+}
+
+async fn task_to_proto<R>(
+    task: CatalogDownsamplingTask,
+    repos: &mut R,
+) -> Result<DownsamplingTask, Status>
+where
+    R: RepoCollection + ?Sized,
+{
+    let namespace_name = repos
+        .namespaces()
+        .get_by_id(task.namespace_id)
+        .await
+        .map_err(|e| Status::internal(e.to_string()))?
+        .map(|ns| ns.name)
+        .unwrap_or_default();
+
+    Ok(DownsamplingTask {
+        id: task.id.get(),
+        name: task.name,
+        namespace_name,
+        query: task.query,
+        query_language: QueryLanguage::from(task.query_language) as i32,
+        target_namespace: task.target_namespace,
+        target_table: task.target_table,
+        every_ns: task.every_ns,
+        delay_ns: task.delay_ns,
+        watermark_ns: task.watermark.get(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_matches::assert_matches;
+    use data_types::NamespaceId;
+    use generated_types::influxdata::iox::downsampling::v1::{
+        downsampling_service_server::DownsamplingService as _,
+        downsampling_write_service_server::DownsamplingWriteService as _,
+    };
+    use iox_catalog::mem::MemCatalog;
+    use iox_time::{MockProvider, Time};
+    use mutable_batch_lp::lines_to_batches;
+    use mutable_batch_pb::encode::encode_batch;
+    use tonic::Code;
+    use write_summary::WriteSummary;
+
+    use super::*;
+    use crate::{
+        dml_handlers::mock::{MockDmlHandler, MockDmlHandlerCall},
+        namespace_resolver::mock::MockNamespaceResolver,
+    };
+
+    const NAMESPACE: &str = "bananas";
+    const HOUR: i64 = 3_600_000_000_000;
+
+    async fn setup() -> DownsamplingService {
+        let metrics = Arc::new(metric::Registry::default());
+        let catalog: Arc<dyn Catalog> = Arc::new(MemCatalog::new(metrics));
+
+        let mut repos = catalog.repositories().await;
+        let topic = repos.topics().create_or_get("topic").await.unwrap();
+        let pool = repos.query_pools().create_or_get("pool").await.unwrap();
+        repos
+            .namespaces()
+            .create(NAMESPACE, None, topic.id, pool.id)
+            .await
+            .unwrap();
+        drop(repos);
+
+        let time_provider = Arc::new(MockProvider::new(Time::from_timestamp_nanos(
+            10 * HOUR + 42,
+        )));
+        DownsamplingService::new(catalog, time_provider)
+    }
+
+    fn create_request(name: &str) -> CreateDownsamplingTaskRequest {
+        CreateDownsamplingTaskRequest {
+            name: name.to_string(),
+            namespace_name: NAMESPACE.to_string(),
+            query: "SELECT * FROM cpu WHERE time >= $window_start AND time < $window_end"
+                .to_string(),
+            query_language: QueryLanguage::Unspecified as i32,
+            target_namespace: "rollups".to_string(),
+            target_table: "cpu_1h".to_string(),
+            every_ns: HOUR,
+            delay_ns: 0,
+            start_ns: None,
+        }
+    }
+
+    async fn list(service: &DownsamplingService) -> Vec<DownsamplingTask> {
+        service
+            .get_downsampling_tasks(Request::new(GetDownsamplingTasksRequest {}))
+            .await
+            .unwrap()
+            .into_inner()
+            .tasks
+    }
+
+    #[tokio::test]
+    async fn test_create_list_delete() {
+        let service = setup().await;
+
+        let task = service
+            .create_downsampling_task(Request::new(create_request("cpu_rollup")))
+            .await
+            .unwrap()
+            .into_inner()
+            .task
+            .unwrap();
+        assert_eq!(task.name, "cpu_rollup");
+        assert_eq!(task.namespace_name, NAMESPACE);
+        assert_eq!(task.query_language, QueryLanguage::Sql as i32);
+        // The first window is the current one.
+        assert_eq!(task.watermark_ns, 10 * HOUR);
+
+        assert_eq!(list(&service).await, vec![task]);
+
+        let err = service
+            .create_downsampling_task(Request::new(create_request("cpu_rollup")))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::AlreadyExists);
+
+        service
+            .delete_downsampling_task(Request::new(DeleteDownsamplingTaskRequest {
+                name: "cpu_rollup".to_string(),
+            }))
+            .await
+            .unwrap();
+        assert!(list(&service).await.is_empty());
+
+        let err = service
+            .delete_downsampling_task(Request::new(DeleteDownsamplingTaskRequest {
+                name: "cpu_rollup".to_string(),
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::NotFound);
+    }
+
+    #[tokio::test]
+    async fn test_create_invalid() {
+        let service = setup().await;
+
+        for req in [
+            CreateDownsamplingTaskRequest {
+                every_ns: 0,
+                ..create_request("a")
+            },
+            CreateDownsamplingTaskRequest {
+                delay_ns: -1,
+                ..create_request("a")
+            },
+            CreateDownsamplingTaskRequest {
+                query_language: 42,
+                ..create_request("a")
+            },
+            CreateDownsamplingTaskRequest {
+                target_table: String::new(),
+                ..create_request("a")
+            },
+            CreateDownsamplingTaskRequest {
+                query: "SELECT * FROM cpu WHERE time >= $window_start".to_string(),
+                ..create_request("a")
+            },
+            create_request(""),
+        ] {
+            let err = service
+                .create_downsampling_task(Request::new(req))
+                .await
+                .unwrap_err();
+            assert_eq!(err.code(), Code::InvalidArgument);
+        }
+
+        let err = service
+            .create_downsampling_task(Request::new(CreateDownsamplingTaskRequest {
+                namespace_name: "platanos".to_string(),
+                ..create_request("a")
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::NotFound);
+    }
+
+    #[tokio::test]
+    async fn test_backfill() {
+        let service = setup().await;
+
+        let task = service
+            .create_downsampling_task(Request::new(CreateDownsamplingTaskRequest {
+                start_ns: Some(5 * HOUR + 1),
+                ..create_request("cpu_rollup")
+            }))
+            .await
+            .unwrap()
+            .into_inner()
+            .task
+            .unwrap();
+        assert_eq!(task.watermark_ns, 5 * HOUR);
+
+        let task = service
+            .backfill_downsampling_task(Request::new(BackfillDownsamplingTaskRequest {
+                name: "cpu_rollup".to_string(),
+                start_ns: 2 * HOUR + 1,
+            }))
+            .await
+            .unwrap()
+            .into_inner()
+            .task
+            .unwrap();
+        assert_eq!(task.watermark_ns, 2 * HOUR);
+
+        // The watermark cannot be moved forward.
+        let err = service
+            .backfill_downsampling_task(Request::new(BackfillDownsamplingTaskRequest {
+                name: "cpu_rollup".to_string(),
+                start_ns: 3 * HOUR,
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::FailedPrecondition);
+
+        let err = service
+            .backfill_downsampling_task(Request::new(BackfillDownsamplingTaskRequest {
+                name: "missing".to_string(),
+                start_ns: 0,
+            }))
+            .await
+            .unwrap_err();
+        assert_matches!(err.code(), Code::NotFound);
+    }
+
+    #[tokio::test]
+    async fn test_write_window() {
+        let dml_handler =
+            Arc::new(MockDmlHandler::default().with_write_return([Ok(WriteSummary::default())]));
+        let service = DownsamplingWriteService::new(
+            MockNamespaceResolver::default().with_mapping("rollups", NamespaceId::new(42)),
+            Arc::clone(&dml_handler),
+        );
+
+        let batches = lines_to_batches("cpu,host=a usage=1 1", 0).unwrap();
+        let request = |table: &str, batch| {
+            Request::new(WriteWindowRequest {
+                namespace: "rollups".to_string(),
+                table: table.to_string(),
+                batch,
+            })
+        };
+
+        service
+            .write_window(request("cpu_1h", Some(encode_batch(0, &batches["cpu"]))))
+            .await
+            .unwrap();
+        assert_matches!(dml_handler.calls().as_slice(), [MockDmlHandlerCall::Write {
+            namespace,
+            namespace_id,
+            write_input,
+        }] => {
+            assert_eq!(namespace, "rollups");
+            assert_eq!(*namespace_id, NamespaceId::new(42));
+            assert_eq!(write_input["cpu_1h"].rows(), 1);
+        });
+
+        for req in [
+            request("cpu_1h", None),
+            request("", Some(encode_batch(0, &batches["cpu"]))),
+        ] {
+            let err = service.write_window(req).await.unwrap_err();
+            assert_eq!(err.code(), Code::InvalidArgument);
+        }
+        assert_eq!(dml_handler.calls().len(), 1);
+    }
+
+    #[test]
+    fn test_align_down() {
+        assert_eq!(align_down(0, 10), 0);
+        assert_eq!(align_down(9, 10), 0);
+        assert_eq!(align_down(10, 10), 10);
+        assert_eq!(align_down(-1, 10), -10);
+    }
+}
@@ -0,0 +1,315 @@
+//! A gRPC service accepting OpenTelemetry (OTLP) metrics exports.
+
+use data_types::{org_and_bucket_to_namespace, NamespaceName};
+use generated_types::opentelemetry::proto::collector::metrics::v1::{
+    metrics_service_server::{self, MetricsServiceServer},
+    ExportMetricsServiceRequest, ExportMetricsServiceResponse,
+};
+use hashbrown::HashMap;
+use hyper::StatusCode;
+use metric::U64Counter;
+use mutable_batch::MutableBatch;
+use observability_deps::tracing::*;
+use tonic::{metadata::MetadataMap, Request, Response, Status};
+use trace::ctx::SpanContext;
+
+use crate::{
+    dml_handlers::{DmlError, DmlHandler},
+    namespace_resolver::NamespaceResolver,
+    server::otlp,
+};
+
+/// The request metadata key specifying the organisation of the destination
+/// namespace.
+const ORG_METADATA_KEY: &str = "org";
+
+/// The request metadata key specifying the bucket of the destination
+/// namespace.
+const BUCKET_METADATA_KEY: &str = "bucket";
+
+/// An implementation of the OTLP [`MetricsService`] that converts the exported
+/// metrics into [`MutableBatch`] and writes them to a [`DmlHandler`].
+///
+/// The destination namespace is derived from the `org` and `bucket` request
+/// metadata, as for the HTTP write API, and the metrics are converted as
+/// described in the [`otlp`] module. Successful exports are recorded in the
+/// same metrics as the HTTP write API, so OTLP writes are accounted for
+/// identically regardless of the transport used.
+///
+/// [`MetricsService`]: metrics_service_server::MetricsService
+#[derive(Debug, Clone)]
+pub struct OtlpMetricsService<D, N> {
+    namespace_resolver: N,
+    dml_handler: D,
+
+    write_metric_data_points: U64Counter,
+    write_metric_fields: U64Counter,
+    write_metric_tables: U64Counter,
+}
+
+impl<D, N> OtlpMetricsService<D, N> {
+    /// Initialise a new [`OtlpMetricsService`], resolving namespaces with
+    /// `namespace_resolver` and writing the converted metrics to
+    /// `dml_handler`.
+    pub fn new(namespace_resolver: N, dml_handler: D, metrics: &metric::Registry) -> Self {
+        let write_metric_data_points = metrics
+            .register_metric::<U64Counter>(
+                "http_otlp_write_data_points",
+                "cumulative number of otlp metric data points successfully routed",
+            )
+            .recorder(&[]);
+        let write_metric_fields = metrics
+            .register_metric::<U64Counter>(
+                "http_write_fields",
+                "cumulative number of line protocol fields successfully routed",
+            )
+            .recorder(&[]);
+        let write_metric_tables = metrics
+            .register_metric::<U64Counter>(
+                "http_write_tables",
+                "cumulative number of tables in each write request",
+            )
+            .recorder(&[]);
+
+        Self {
+            namespace_resolver,
+            dml_handler,
+            write_metric_data_points,
+            write_metric_fields,
+            write_metric_tables,
+        }
+    }
+}
+
+impl<D, N> OtlpMetricsService<D, N>
+where
+    Self: metrics_service_server::MetricsService + Clone,
+{
+    /// Acquire a [`MetricsServiceServer`] gRPC service for this handler.
+    pub fn service(&self) -> MetricsServiceServer<Self> {
+        MetricsServiceServer::new(self.clone())
+    }
+}
+
+#[tonic::async_trait]
+impl<D, N> metrics_service_server::MetricsService for OtlpMetricsService<D, N>
+where
+    D: DmlHandler<WriteInput = HashMap<String, MutableBatch>> + 'static,
+    N: NamespaceResolver + 'static,
+{
+    async fn export(
+        &self,
+        request: Request<ExportMetricsServiceRequest>,
+    ) -> Result<Response<ExportMetricsServiceResponse>, Status> {
+        let span_ctx: Option<SpanContext> = request.extensions().get().cloned();
+        let namespace = namespace_from_metadata(request.metadata())?;
+        let req = request.into_inner();
+
+        let (batches, stats) = otlp::export_request_to_batches(&req)
+            .map_err(|e| Status::invalid_argument(e.to_string()))?;
+
+        if !batches.is_empty() {
+            let num_tables = batches.len();
+            debug!(
+                num_data_points = stats.num_data_points,
+                num_fields = stats.num_fields,
+                num_tables,
+                %namespace,
+                "routing otlp metrics",
+            );
+
+            let namespace_id = self
+                .namespace_resolver
+                .get_namespace_id(&namespace)
+                .await
+                .map_err(|e| Status::internal(e.to_string()))?;
+
+            self.dml_handler
+                .write(&namespace, namespace_id, batches, span_ctx)
+                .await
+                .map_err(|e| {
+                    let e: DmlError = e.into();
+                    dml_error_to_status(&e)
+                })?;
+
+            self.write_metric_data_points
+                .inc(stats.num_data_points as _);
+            self.write_metric_fields.inc(stats.num_fields as _);
+            self.write_metric_tables.inc(num_tables as _);
+        }
+
+        Ok(Response::new(ExportMetricsServiceResponse {
+            partial_success: None,
+        }))
+    }
+}
+
+/// Derive the destination namespace from the `org` and `bucket` request
+/// metadata.
+fn namespace_from_metadata(metadata: &MetadataMap) -> Result<NamespaceName<'static>, Status> {
+    let org = metadata_str(metadata, ORG_METADATA_KEY)?;
+    let bucket = metadata_str(metadata, BUCKET_METADATA_KEY)?;
+
+    org_and_bucket_to_namespace(org, bucket).map_err(|e| Status::invalid_argument(e.to_string()))
+}
+
+/// Read the string value of the request metadata `key`.
+fn metadata_str<'a>(metadata: &'a MetadataMap, key: &str) -> Result<&'a str, Status> {
+    metadata
+        .get(key)
+        .ok_or_else(|| Status::invalid_argument(format!("missing {} request metadata", key)))?
+        .to_str()
+        .map_err(|e| Status::invalid_argument(format!("invalid {} request metadata: {}", key, e)))
+}
+
+/// Map a [`DmlError`] to the gRPC [`Status`] equivalent of the HTTP status code
+/// returned for it by the HTTP write API.
+pub(super) fn dml_error_to_status(e: &DmlError) -> Status {
+    let msg = e.to_string();
+    match StatusCode::from(e) {
+        StatusCode::BAD_REQUEST => Status::invalid_argument(msg),
+        StatusCode::NOT_FOUND => Status::not_found(msg),
+        StatusCode::FORBIDDEN => Status::permission_denied(msg),
+        StatusCode::NOT_IMPLEMENTED => Status::unimplemented(msg),
+        StatusCode::GATEWAY_TIMEOUT => Status::deadline_exceeded(msg),
+        _ => Status::internal(msg),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use assert_matches::assert_matches;
+    use data_types::NamespaceId;
+    use generated_types::opentelemetry::proto::metrics::v1::{
+        metric::Data, number_data_point, Gauge, Metric, NumberDataPoint, ResourceMetrics,
+        ScopeMetrics,
+    };
+    use metric::{Attributes, Metric as MetricInstrument};
+    use tonic::Code;
+    use write_summary::WriteSummary;
+
+    use super::*;
+    use crate::{
+        dml_handlers::mock::{MockDmlHandler, MockDmlHandlerCall},
+        namespace_resolver::mock::MockNamespaceResolver,
+    };
+
+    const NAMESPACE_ID: NamespaceId = NamespaceId::new(42);
+
+    type Service = OtlpMetricsService<
+        Arc<MockDmlHandler<HashMap<String, MutableBatch>>>,
+        MockNamespaceResolver,
+    >;
+
+    fn new_service(
+        dml_handler: &Arc<MockDmlHandler<HashMap<String, MutableBatch>>>,
+        metrics: &metric::Registry,
+    ) -> Service {
+        OtlpMetricsService::new(
+            MockNamespaceResolver::default().with_mapping("bananas_test", NAMESPACE_ID),
+            Arc::clone(dml_handler),
+            metrics,
+        )
+    }
+
+    fn export_request(org: &str, bucket: &str) -> Request<ExportMetricsServiceRequest> {
+        let mut request = Request::new(ExportMetricsServiceRequest {
+            resource_metrics: vec![ResourceMetrics {
+                resource: None,
+                scope_metrics: vec![ScopeMetrics {
+                    scope: None,
+                    metrics: vec![Metric {
+                        name: "cpu".to_string(),
+                        description: String::new(),
+                        unit: String::new(),
+                        data: Some(Data::Gauge(Gauge {
+                            data_points: vec![NumberDataPoint {
+                                attributes: vec![],
+                                start_time_unix_nano: 0,
+                                time_unix_nano: 1,
+                                value: Some(number_data_point::Value::AsInt(42)),
+                                exemplars: vec![],
+                                flags: 0,
+                            }],
+                        })),
+                    }],
+                    schema_url: String::new(),
+                }],
+                schema_url: String::new(),
+            }],
+        });
+        request
+            .metadata_mut()
+            .insert(ORG_METADATA_KEY, org.parse().unwrap());
+        request
+            .metadata_mut()
+            .insert(BUCKET_METADATA_KEY, bucket.parse().unwrap());
+        request
+    }
+
+    #[tokio::test]
+    async fn test_export() {
+        use metrics_service_server::MetricsService;
+
+        let dml_handler =
+            Arc::new(MockDmlHandler::default().with_write_return([Ok(WriteSummary::default())]));
+        let metrics = metric::Registry::default();
+        let service = new_service(&dml_handler, &metrics);
+
+        service
+            .export(export_request("bananas", "test"))
+            .await
+            .expect("export should succeed");
+
+        assert_matches!(dml_handler.calls().as_slice(), [MockDmlHandlerCall::Write {
+            namespace,
+            namespace_id,
+            write_input,
+        }] => {
+            assert_eq!(namespace, "bananas_test");
+            assert_eq!(*namespace_id, NAMESPACE_ID);
+            assert_eq!(write_input["cpu"].rows(), 1);
+        });
+
+        for (name, want) in [
+            ("http_otlp_write_data_points", 1),
+            ("http_write_fields", 1),
+            ("http_write_tables", 1),
+        ] {
+            let got = metrics
+                .get_instrument::<MetricInstrument<U64Counter>>(name)
+                .expect("failed to read metric")
+                .get_observer(&Attributes::from(&[]))
+                .expect("failed to get observer")
+                .fetch();
+            assert_eq!(got, want, "{name}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_export_errors() {
+        use metrics_service_server::MetricsService;
+
+        let dml_handler = Arc::new(
+            MockDmlHandler::default()
+                .with_write_return([Err(DmlError::NamespaceNotFound("bananas".to_string()))]),
+        );
+        let metrics = metric::Registry::default();
+        let service = new_service(&dml_handler, &metrics);
+
+        let mut request = export_request("bananas", "test");
+        request.metadata_mut().remove(BUCKET_METADATA_KEY);
+        assert_matches!(
+            service.export(request).await,
+            Err(e) => assert_eq!(e.code(), Code::InvalidArgument)
+        );
+        assert!(dml_handler.calls().is_empty());
+
+        assert_matches!(
+            service.export(export_request("bananas", "test")).await,
+            Err(e) => assert_eq!(e.code(), Code::NotFound)
+        );
+    }
+}
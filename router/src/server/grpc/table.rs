@@ -0,0 +1,637 @@
+//! A gRPC service to explicitly create, list and delete tables.
+
+use std::{collections::HashMap, ops::DerefMut, sync::Arc};
+
+use data_types::{ColumnType, NamespaceName, Table as CatalogTable, TableSchema};
+use generated_types::influxdata::iox::{
+    schema::v1::{column_schema, ColumnSchema},
+    table::v1::*,
+};
+use iox_catalog::{
+    create_table_with_columns,
+    interface::{
+        get_schema_by_name, get_table_schema_by_id, Catalog, Error as CatalogError, RepoCollection,
+    },
+};
+use observability_deps::tracing::*;
+use tonic::{Request, Response, Status};
+
+use crate::namespace_cache::NamespaceCache;
+
+/// A [`TableService`] exposes a [gRPC endpoint] to explicitly manage the tables
+/// of a namespace, rather than relying on writes to implicitly create them.
+///
+/// Tables created through this service have their declared columns created
+/// up-front, fixing the type of each column so that writes disagreeing with
+/// the declared schema are rejected by schema validation.
+///
+/// The [`DeduplicationPolicy`] of a table may be declared when it is created,
+/// and changed afterwards.
+///
+/// Deleting a table removes it from the namespace schema and flags all of its
+/// parquet files for deletion, leaving the objects to be removed by the garbage
+/// collector. Once a mutation has been committed the namespace schema is
+/// reloaded from the catalog and placed into the router's [`NamespaceCache`],
+/// so writes handled by this router immediately observe the change. Other
+/// routers observe a deletion at their next background namespace cache
+/// refresh, and queriers when their cached namespace schema is next refreshed.
+///
+/// [gRPC endpoint]: generated_types::influxdata::iox::table::v1::table_service_server::TableService
+#[derive(Debug)]
+pub struct TableService<C> {
+    catalog: Arc<dyn Catalog>,
+    cache: C,
+}
+
+impl<C> TableService<C> {
+    /// Initialise a gRPC [`TableService`] handler, updating `cache` after every
+    /// successful table mutation.
+    pub fn new(catalog: Arc<dyn Catalog>, cache: C) -> Self {
+        Self { catalog, cache }
+    }
+}
+
+impl<C> TableService<C>
+where
+    C: NamespaceCache,
+{
+    /// Load the schema of `namespace` from `repos` and place it into the
+    /// namespace cache.
+    async fn refresh_cached_schema<R>(&self, namespace: &NamespaceName<'static>, repos: &mut R)
+    where
+        R: RepoCollection + ?Sized,
+    {
+        match get_schema_by_name(namespace, repos).await {
+            Ok(schema) => {
+                self.cache.put_schema(namespace.clone(), schema);
+            }
+            Err(e) => {
+                // The change has been committed, so failing the request would
+                // be misleading - the cached schema is eventually corrected
+                // by a subsequent write that misses the cache.
+                warn!(error=%e, %namespace, "failed to refresh cached namespace schema");
+            }
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl<C> table_service_server::TableService for TableService<C>
+where
+    C: NamespaceCache + 'static,
+{
+    async fn get_tables(
+        &self,
+        request: Request<GetTablesRequest>,
+    ) -> Result<Response<GetTablesResponse>, Status> {
+        let req = request.into_inner();
+        let mut repos = self.catalog.repositories().await;
+
+        let schema = get_schema_by_name(&req.namespace_name, repos.deref_mut())
+            .await
+            .map_err(|e| catalog_error_to_status(e, &req.namespace_name))?;
+
+        let tables = schema
+            .tables
+            .into_iter()
+            .map(|(name, table)| table_to_proto(name, schema.id.get(), &table))
+            .collect();
+
+        Ok(Response::new(GetTablesResponse { tables }))
+    }
+
+    async fn create_table(
+        &self,
+        request: Request<CreateTableRequest>,
+    ) -> Result<Response<CreateTableResponse>, Status> {
+        let CreateTableRequest {
+            namespace_name,
+            name,
+            columns,
+            dedup_policy,
+        } = request.into_inner();
+
+        let namespace = NamespaceName::try_from(namespace_name)
+            .map_err(|e| Status::invalid_argument(e.to_string()))?;
+        validate_table_name(&name)?;
+        let columns = columns
+            .iter()
+            .map(|(column_name, column_type)| {
+                column_type_from_proto(*column_type)
+                    .map(|t| (column_name.as_str(), t))
+                    .ok_or_else(|| {
+                        Status::invalid_argument(format!(
+                            "invalid column type for column {column_name}"
+                        ))
+                    })
+            })
+            .collect::<Result<HashMap<_, _>, _>>()?;
+        let dedup_policy = dedup_policy_from_proto(dedup_policy)?;
+
+        // Create the table and all of its columns atomically, so a rejected
+        // column does not leave behind a partially declared table.
+        let mut txn = self
+            .catalog
+            .start_transaction()
+            .await
+            .map_err(|e| Status::unavailable(e.to_string()))?;
+
+        let (namespace_id, table) =
+            match create_table(&namespace, &name, columns, dedup_policy, txn.deref_mut()).await {
+                Ok(v) => v,
+                Err(e) => {
+                    if let Err(abort_err) = txn.abort().await {
+                        warn!(error=%abort_err, "failed to abort table creation transaction");
+                    }
+                    return Err(e);
+                }
+            };
+
+        txn.commit()
+            .await
+            .map_err(|e| Status::unavailable(e.to_string()))?;
+
+        info!(%namespace, table=%name, table_id=%table.id, "created table");
+
+        let mut repos = self.catalog.repositories().await;
+        self.refresh_cached_schema(&namespace, repos.deref_mut())
+            .await;
+
+        Ok(Response::new(CreateTableResponse {
+            table: Some(table_to_proto(name, namespace_id, &table)),
+        }))
+    }
+
+    async fn delete_table(
+        &self,
+        request: Request<DeleteTableRequest>,
+    ) -> Result<Response<DeleteTableResponse>, Status> {
+        let DeleteTableRequest {
+            namespace_name,
+            name,
+        } = request.into_inner();
+
+        let namespace = NamespaceName::try_from(namespace_name)
+            .map_err(|e| Status::invalid_argument(e.to_string()))?;
+
+        // Delete the table and flag its files for deletion atomically.
+        let mut txn = self
+            .catalog
+            .start_transaction()
+            .await
+            .map_err(|e| Status::unavailable(e.to_string()))?;
+
+        let table = match delete_table(&namespace, &name, txn.deref_mut()).await {
+            Ok(v) => v,
+            Err(e) => {
+                if let Err(abort_err) = txn.abort().await {
+                    warn!(error=%abort_err, "failed to abort table deletion transaction");
+                }
+                return Err(e);
+            }
+        };
+
+        txn.commit()
+            .await
+            .map_err(|e| Status::unavailable(e.to_string()))?;
+
+        info!(%namespace, table=%name, table_id=%table.id, "deleted table");
+
+        let mut repos = self.catalog.repositories().await;
+        self.refresh_cached_schema(&namespace, repos.deref_mut())
+            .await;
+
+        Ok(Response::new(DeleteTableResponse {}))
+    }
+
+    async fn update_table_dedup_policy(
+        &self,
+        request: Request<UpdateTableDedupPolicyRequest>,
+    ) -> Result<Response<UpdateTableDedupPolicyResponse>, Status> {
+        let UpdateTableDedupPolicyRequest {
+            namespace_name,
+            name,
+            dedup_policy,
+        } = request.into_inner();
+
+        let namespace = NamespaceName::try_from(namespace_name)
+            .map_err(|e| Status::invalid_argument(e.to_string()))?;
+        let dedup_policy = dedup_policy_from_proto(dedup_policy)?;
+
+        let mut repos = self.catalog.repositories().await;
+
+        let table = lookup_table(&namespace, &name, repos.deref_mut()).await?;
+        let table = repos
+            .tables()
+            .update_dedup_policy(table.id, dedup_policy)
+            .await
+            .map_err(|e| {
+                warn!(error=%e, %namespace, table=%name, "failed to update table dedup policy");
+                catalog_error_to_status(e, &namespace)
+            })?;
+
+        info!(%namespace, table=%name, table_id=%table.id, %dedup_policy, "updated table dedup policy");
+
+        let schema = get_table_schema_by_id(table.id, repos.deref_mut())
+            .await
+            .map_err(|e| catalog_error_to_status(e, &namespace))?;
+
+        self.refresh_cached_schema(&namespace, repos.deref_mut())
+            .await;
+
+        Ok(Response::new(UpdateTableDedupPolicyResponse {
+            table: Some(table_to_proto(name, table.namespace_id.get(), &schema)),
+        }))
+    }
+}
+
+/// Create the table `name` in `namespace` with the declared `columns` and
+/// `dedup_policy`, returning the ID of the namespace and the schema of the new
+/// table.
+async fn create_table<R>(
+    namespace: &NamespaceName<'_>,
+    name: &str,
+    columns: HashMap<&str, ColumnType>,
+    dedup_policy: data_types::DeduplicationPolicy,
+    repos: &mut R,
+) -> Result<(i64, TableSchema), Status>
+where
+    R: RepoCollection + ?Sized,
+{
+    let schema = get_schema_by_name(namespace, repos)
+        .await
+        .map_err(|e| catalog_error_to_status(e, namespace))?;
+    if schema.tables.contains_key(name) {
+        return Err(Status::already_exists(format!(
+            "table {name} already exists in namespace {namespace}"
+        )));
+    }
+
+    let mut table = create_table_with_columns(name, &schema, columns, repos)
+        .await
+        .map_err(|e| {
+            warn!(error=%e, %namespace, table=%name, "failed to create table");
+            catalog_error_to_status(e, namespace)
+        })?;
+
+    if dedup_policy != table.dedup_policy {
+        table.dedup_policy = repos
+            .tables()
+            .update_dedup_policy(table.id, dedup_policy)
+            .await
+            .map_err(|e| catalog_error_to_status(e, namespace))?
+            .dedup_policy;
+    }
+
+    Ok((schema.id.get(), table))
+}
+
+/// Delete the table `name` in `namespace`, returning the deleted table.
+async fn delete_table<R>(
+    namespace: &NamespaceName<'_>,
+    name: &str,
+    repos: &mut R,
+) -> Result<CatalogTable, Status>
+where
+    R: RepoCollection + ?Sized,
+{
+    let table = lookup_table(namespace, name, repos).await?;
+    repos.tables().delete(table.id).await.map_err(|e| {
+        warn!(error=%e, %namespace, table=%name, "failed to delete table");
+        catalog_error_to_status(e, namespace)
+    })?;
+
+    Ok(table)
+}
+
+/// Reject table names that could not be written to, applying the same
+/// validation as the line protocol write path by round-tripping `name` through
+/// it as a measurement.
+fn validate_table_name(name: &str) -> Result<(), Status> {
+    let escaped = name
+        .replace('\\', "\\\\")
+        .replace(',', "\\,")
+        .replace(' ', "\\ ");
+
+    match mutable_batch_lp::lines_to_batches(&format!("{escaped} v=1"), 0) {
+        Ok(batches) if batches.len() == 1 && batches.contains_key(name) => Ok(()),
+        _ => Err(Status::invalid_argument(format!(
+            "invalid table name {name:?}"
+        ))),
+    }
+}
+
+/// Resolve the catalog [`CatalogTable`] named `name` in `namespace`.
+async fn lookup_table<R>(
+    namespace: &NamespaceName<'_>,
+    name: &str,
+    repos: &mut R,
+) -> Result<CatalogTable, Status>
+where
+    R: RepoCollection + ?Sized,
+{
+    let ns = repos
+        .namespaces()
+        .get_by_name(namespace)
+        .await
+        .map_err(|e| Status::internal(e.to_string()))?
+        .ok_or_else(|| Status::not_found(format!("namespace {namespace} not found")))?;
+
+    repos
+        .tables()
+        .get_by_namespace_and_name(ns.id, name)
+        .await
+        .map_err(|e| Status::internal(e.to_string()))?
+        .ok_or_else(|| {
+            Status::not_found(format!("table {name} not found in namespace {namespace}"))
+        })
+}
+
+fn catalog_error_to_status(e: CatalogError, namespace: &str) -> Status {
+    match e {
+        CatalogError::NamespaceNotFoundByName { .. } => {
+            Status::not_found(format!("namespace {namespace} not found"))
+        }
+        CatalogError::TableNotFound { .. } => Status::not_found(e.to_string()),
+        CatalogError::ColumnTypeMismatch { .. } => Status::failed_precondition(e.to_string()),
+        CatalogError::ColumnCreateLimitError { .. }
+        | CatalogError::TableCreateLimitError { .. } => Status::resource_exhausted(e.to_string()),
+        _ => Status::internal(e.to_string()),
+    }
+}
+
+fn column_type_from_proto(v: i32) -> Option<ColumnType> {
+    column_schema::ColumnType::from_i32(v)?.try_into().ok()
+}
+
+fn dedup_policy_from_proto(v: i32) -> Result<data_types::DeduplicationPolicy, Status> {
+    DeduplicationPolicy::from_i32(v)
+        .map(Into::into)
+        .ok_or_else(|| Status::invalid_argument(format!("invalid dedup policy {v}")))
+}
+
+fn table_to_proto(name: String, namespace_id: i64, table: &TableSchema) -> Table {
+    Table {
+        id: table.id.get(),
+        name,
+        namespace_id,
+        columns: table
+            .columns
+            .iter()
+            .map(|(name, c)| {
+                (
+                    name.clone(),
+                    ColumnSchema {
+                        id: c.id.get(),
+                        column_type: c.column_type as i32,
+                    },
+                )
+            })
+            .collect(),
+        dedup_policy: DeduplicationPolicy::from(table.dedup_policy) as i32,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_matches::assert_matches;
+    use generated_types::influxdata::iox::table::v1::table_service_server::TableService as _;
+    use iox_catalog::mem::MemCatalog;
+    use tonic::Code;
+
+    use super::*;
+    use crate::namespace_cache::MemoryNamespaceCache;
+
+    const NAMESPACE: &str = "bananas";
+
+    async fn setup() -> (
+        Arc<dyn Catalog>,
+        Arc<MemoryNamespaceCache>,
+        TableService<Arc<MemoryNamespaceCache>>,
+    ) {
+        let metrics = Arc::new(metric::Registry::default());
+        let catalog: Arc<dyn Catalog> = Arc::new(MemCatalog::new(metrics));
+
+        let mut repos = catalog.repositories().await;
+        let topic = repos.topics().create_or_get("topic").await.unwrap();
+        let pool = repos.query_pools().create_or_get("pool").await.unwrap();
+        repos
+            .namespaces()
+            .create(NAMESPACE, None, topic.id, pool.id)
+            .await
+            .unwrap();
+        drop(repos);
+
+        let cache = Arc::new(MemoryNamespaceCache::default());
+        let service = TableService::new(Arc::clone(&catalog), Arc::clone(&cache));
+
+        (catalog, cache, service)
+    }
+
+    fn create_request(
+        name: &str,
+        columns: &[(&str, column_schema::ColumnType)],
+    ) -> CreateTableRequest {
+        CreateTableRequest {
+            namespace_name: NAMESPACE.to_string(),
+            name: name.to_string(),
+            columns: columns
+                .iter()
+                .map(|(name, t)| (name.to_string(), *t as i32))
+                .collect(),
+            dedup_policy: DeduplicationPolicy::Unspecified as i32,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_create_list_delete() {
+        let (_catalog, cache, service) = setup().await;
+        let ns = NamespaceName::new(NAMESPACE).unwrap();
+
+        let table = service
+            .create_table(Request::new(create_request(
+                "platanos",
+                &[
+                    ("region", column_schema::ColumnType::Tag),
+                    ("temp", column_schema::ColumnType::F64),
+                ],
+            )))
+            .await
+            .expect("create should succeed")
+            .into_inner()
+            .table
+            .expect("response should contain table");
+
+        assert_eq!(table.name, "platanos");
+        let mut columns = table.columns.keys().cloned().collect::<Vec<_>>();
+        columns.sort_unstable();
+        assert_eq!(columns, ["region", "temp", "time"]);
+
+        // The router's cached schema now contains the table.
+        let cached = cache.get_schema(&ns).expect("schema should be cached");
+        assert!(cached.tables.contains_key("platanos"));
+
+        // Creating it again is rejected.
+        let err = service
+            .create_table(Request::new(create_request("platanos", &[])))
+            .await
+            .expect_err("duplicate create should fail");
+        assert_eq!(err.code(), Code::AlreadyExists);
+
+        let tables = service
+            .get_tables(Request::new(GetTablesRequest {
+                namespace_name: NAMESPACE.to_string(),
+            }))
+            .await
+            .expect("list should succeed")
+            .into_inner()
+            .tables;
+        assert_matches!(tables.as_slice(), [t] => {
+            assert_eq!(t.id, table.id);
+        });
+
+        service
+            .delete_table(Request::new(DeleteTableRequest {
+                namespace_name: NAMESPACE.to_string(),
+                name: "platanos".to_string(),
+            }))
+            .await
+            .expect("delete should succeed");
+
+        // The table is removed from the cached schema.
+        let cached = cache.get_schema(&ns).expect("schema should be cached");
+        assert!(!cached.tables.contains_key("platanos"));
+
+        let err = service
+            .delete_table(Request::new(DeleteTableRequest {
+                namespace_name: NAMESPACE.to_string(),
+                name: "platanos".to_string(),
+            }))
+            .await
+            .expect_err("deleting a missing table should fail");
+        assert_eq!(err.code(), Code::NotFound);
+    }
+
+    #[tokio::test]
+    async fn test_create_invalid_time_column() {
+        let (catalog, _cache, service) = setup().await;
+
+        let err = service
+            .create_table(Request::new(create_request(
+                "platanos",
+                &[("time", column_schema::ColumnType::String)],
+            )))
+            .await
+            .expect_err("create should fail");
+        assert_eq!(err.code(), Code::FailedPrecondition);
+
+        // The transaction was aborted, leaving no table behind.
+        let tables = catalog.repositories().await.tables().list().await.unwrap();
+        assert!(tables.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_create_invalid_name() {
+        let (_catalog, _cache, service) = setup().await;
+
+        for name in ["", "bananas\nplatanos", "#platanos"] {
+            let err = service
+                .create_table(Request::new(create_request(name, &[])))
+                .await
+                .expect_err("create should fail");
+            assert_eq!(err.code(), Code::InvalidArgument, "{name:?}");
+        }
+
+        // Names that need escaping in line protocol are accepted.
+        for name in ["bananas platanos", "bananas,platanos", "bananas\\platanos"] {
+            service
+                .create_table(Request::new(create_request(name, &[])))
+                .await
+                .expect("create should succeed");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_create_unknown_namespace() {
+        let (_catalog, _cache, service) = setup().await;
+
+        let err = service
+            .create_table(Request::new(CreateTableRequest {
+                namespace_name: "missing".to_string(),
+                name: "platanos".to_string(),
+                columns: Default::default(),
+                dedup_policy: Default::default(),
+            }))
+            .await
+            .expect_err("create should fail");
+        assert_eq!(err.code(), Code::NotFound);
+    }
+
+    #[tokio::test]
+    async fn test_dedup_policy() {
+        let (catalog, cache, service) = setup().await;
+        let ns = NamespaceName::new(NAMESPACE).unwrap();
+
+        let table = service
+            .create_table(Request::new(CreateTableRequest {
+                dedup_policy: DeduplicationPolicy::AppendOnly as i32,
+                ..create_request("platanos", &[])
+            }))
+            .await
+            .expect("create should succeed")
+            .into_inner()
+            .table
+            .expect("response should contain table");
+        assert_eq!(table.dedup_policy, DeduplicationPolicy::AppendOnly as i32);
+
+        let cached = cache.get_schema(&ns).expect("schema should be cached");
+        assert_eq!(
+            cached.tables["platanos"].dedup_policy,
+            data_types::DeduplicationPolicy::AppendOnly
+        );
+
+        let table = service
+            .update_table_dedup_policy(Request::new(UpdateTableDedupPolicyRequest {
+                namespace_name: NAMESPACE.to_string(),
+                name: "platanos".to_string(),
+                dedup_policy: DeduplicationPolicy::KeepFirst as i32,
+            }))
+            .await
+            .expect("update should succeed")
+            .into_inner()
+            .table
+            .expect("response should contain table");
+        assert_eq!(table.dedup_policy, DeduplicationPolicy::KeepFirst as i32);
+
+        let tables = catalog.repositories().await.tables().list().await.unwrap();
+        assert_matches!(tables.as_slice(), [t] => {
+            assert_eq!(t.dedup_policy, data_types::DeduplicationPolicy::KeepFirst);
+        });
+        let cached = cache.get_schema(&ns).expect("schema should be cached");
+        assert_eq!(
+            cached.tables["platanos"].dedup_policy,
+            data_types::DeduplicationPolicy::KeepFirst
+        );
+
+        // Unknown tables and policies are rejected.
+        let err = service
+            .update_table_dedup_policy(Request::new(UpdateTableDedupPolicyRequest {
+                namespace_name: NAMESPACE.to_string(),
+                name: "missing".to_string(),
+                dedup_policy: DeduplicationPolicy::KeepFirst as i32,
+            }))
+            .await
+            .expect_err("update should fail");
+        assert_eq!(err.code(), Code::NotFound);
+
+        let err = service
+            .update_table_dedup_policy(Request::new(UpdateTableDedupPolicyRequest {
+                namespace_name: NAMESPACE.to_string(),
+                name: "platanos".to_string(),
+                dedup_policy: 42,
+            }))
+            .await
+            .expect_err("update should fail");
+        assert_eq!(err.code(), Code::InvalidArgument);
+    }
+}
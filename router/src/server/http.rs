@@ -1,18 +1,26 @@
 //! HTTP service implementations for `router`.
 
 mod delete_predicate;
+mod prom_write;
 
 use bytes::{Bytes, BytesMut};
 use data_types::{org_and_bucket_to_namespace, OrgBucketMappingError};
 use futures::StreamExt;
+use generated_types::opentelemetry::proto::collector::metrics::v1::{
+    ExportMetricsServiceRequest, ExportMetricsServiceResponse,
+};
 use hashbrown::HashMap;
-use hyper::{header::CONTENT_ENCODING, Body, Method, Request, Response, StatusCode};
+use hyper::{
+    header::{CONTENT_ENCODING, CONTENT_TYPE},
+    Body, Method, Request, Response, StatusCode,
+};
 use iox_time::{SystemProvider, TimeProvider};
 use metric::{DurationHistogram, U64Counter};
 use mutable_batch::MutableBatch;
 use mutable_batch_lp::LinesConverter;
 use observability_deps::tracing::*;
 use predicate::delete_predicate::parse_delete_predicate;
+use prost::Message;
 use serde::Deserialize;
 use std::{str::Utf8Error, time::Instant};
 use thiserror::Error;
@@ -21,6 +29,7 @@ use trace::ctx::SpanContext;
 use write_summary::WriteSummary;
 
 use self::delete_predicate::parse_http_delete_request;
+use super::otlp;
 use crate::{
     dml_handlers::{
         DmlError, DmlHandler, PartitionError, RetentionError, RpcWriteError, SchemaError,
@@ -65,10 +74,26 @@ pub enum Error {
     #[error("error decoding gzip stream: {0}")]
     InvalidGzip(std::io::Error),
 
+    /// Decoding a snappy-compressed block of data failed.
+    #[error("error decoding snappy block: {0}")]
+    InvalidSnappy(snap::Error),
+
     /// Failure to decode the provided line protocol.
     #[error("failed to parse line protocol: {0}")]
     ParseLineProtocol(mutable_batch_lp::Error),
 
+    /// Failure to decode the provided Prometheus remote write request.
+    #[error("failed to parse prometheus remote write request: {0}")]
+    ParsePromWrite(#[from] prom_write::Error),
+
+    /// The request body is not a valid protobuf message.
+    #[error("failed to decode protobuf request: {0}")]
+    InvalidProtobuf(prost::DecodeError),
+
+    /// Failure to convert the provided OTLP metrics export request.
+    #[error("failed to convert otlp metrics: {0}")]
+    ParseOtlp(#[from] otlp::Error),
+
     /// Failure to parse the request delete predicate.
     #[error("failed to parse delete predicate: {0}")]
     ParseDelete(#[from] predicate::delete_predicate::Error),
@@ -105,7 +130,11 @@ impl Error {
             Error::InvalidGzip(_) => StatusCode::BAD_REQUEST,
             Error::NonUtf8ContentHeader(_) => StatusCode::BAD_REQUEST,
             Error::NonUtf8Body(_) => StatusCode::BAD_REQUEST,
+            Error::InvalidSnappy(_) => StatusCode::BAD_REQUEST,
             Error::ParseLineProtocol(_) => StatusCode::BAD_REQUEST,
+            Error::ParsePromWrite(_) => StatusCode::BAD_REQUEST,
+            Error::InvalidProtobuf(_) => StatusCode::BAD_REQUEST,
+            Error::ParseOtlp(_) => StatusCode::BAD_REQUEST,
             Error::ParseDelete(_) => StatusCode::BAD_REQUEST,
             Error::ParseHttpDelete(_) => StatusCode::BAD_REQUEST,
             Error::RequestSizeExceeded(_) => StatusCode::PAYLOAD_TOO_LARGE,
@@ -257,6 +286,8 @@ pub struct HttpDelegate<D, N, T = SystemProvider> {
     write_metric_fields: U64Counter,
     write_metric_tables: U64Counter,
     write_metric_body_size: U64Counter,
+    prom_write_metric_samples: U64Counter,
+    otlp_metric_data_points: U64Counter,
     delete_metric_body_size: U64Counter,
     request_limit_rejected: U64Counter,
 }
@@ -298,6 +329,18 @@ impl<D, N> HttpDelegate<D, N, SystemProvider> {
                 "cumulative byte size of successfully routed (decompressed) line protocol write requests",
             )
             .recorder(&[]);
+        let prom_write_metric_samples = metrics
+            .register_metric::<U64Counter>(
+                "http_prom_write_samples",
+                "cumulative number of prometheus remote write samples successfully routed",
+            )
+            .recorder(&[]);
+        let otlp_metric_data_points = metrics
+            .register_metric::<U64Counter>(
+                "http_otlp_write_data_points",
+                "cumulative number of otlp metric data points successfully routed",
+            )
+            .recorder(&[]);
         let delete_metric_body_size = metrics
             .register_metric::<U64Counter>(
                 "http_delete_body_bytes",
@@ -328,6 +371,8 @@ impl<D, N> HttpDelegate<D, N, SystemProvider> {
             write_metric_fields,
             write_metric_tables,
             write_metric_body_size,
+            prom_write_metric_samples,
+            otlp_metric_data_points,
             delete_metric_body_size,
             request_limit_rejected,
         }
@@ -363,6 +408,25 @@ where
         // Route the request to a handler.
         match (req.method(), req.uri().path()) {
             (&Method::POST, "/api/v2/write") => self.write_handler(req).await,
+            (&Method::POST, "/api/v1/prom/write") => self.prom_write_handler(req).await,
+            (&Method::POST, "/v1/metrics") => {
+                // OTLP/HTTP exporters expect a 200 response carrying an
+                // encoded ExportMetricsServiceResponse, rather than the empty
+                // 204 of the other write endpoints.
+                return self.otlp_metrics_handler(req).await.map(|summary| {
+                    let body = ExportMetricsServiceResponse {
+                        partial_success: None,
+                    }
+                    .encode_to_vec();
+
+                    Response::builder()
+                        .status(StatusCode::OK)
+                        .header(CONTENT_TYPE, "application/x-protobuf")
+                        .header(WRITE_TOKEN_HTTP_HEADER, summary.to_token())
+                        .body(Body::from(body))
+                        .unwrap()
+                });
+            }
             (&Method::POST, "/api/v2/delete") => self.delete_handler(req).await,
             _ => return Err(Error::NoHandler),
         }
@@ -442,6 +506,112 @@ where
         Ok(summary)
     }
 
+    async fn prom_write_handler(&self, req: Request<Body>) -> Result<WriteSummary, Error> {
+        let span_ctx: Option<SpanContext> = req.extensions().get().cloned();
+
+        let write_info = WriteInfo::try_from(&req)?;
+        let namespace = org_and_bucket_to_namespace(&write_info.org, &write_info.bucket)
+            .map_err(OrgBucketError::MappingFail)?;
+
+        trace!(
+            org=%write_info.org,
+            bucket=%write_info.bucket,
+            %namespace,
+            "processing prometheus remote write request"
+        );
+
+        // Remote write request bodies are always snappy-compressed.
+        let body = self.read_snappy_body(req).await?;
+
+        let (batches, stats) = prom_write::write_request_to_batches(&body)?;
+        if batches.is_empty() {
+            debug!("nothing to write");
+            return Ok(WriteSummary::default());
+        }
+
+        let num_tables = batches.len();
+        debug!(
+            num_series=stats.num_series,
+            num_samples=stats.num_samples,
+            num_tables,
+            body_size=body.len(),
+            %namespace,
+            org=%write_info.org,
+            bucket=%write_info.bucket,
+            "routing prometheus remote write",
+        );
+
+        let namespace_id = self.namespace_resolver.get_namespace_id(&namespace).await?;
+
+        let summary = self
+            .dml_handler
+            .write(&namespace, namespace_id, batches, span_ctx)
+            .await
+            .map_err(Into::into)?;
+
+        self.prom_write_metric_samples.inc(stats.num_samples as _);
+        self.write_metric_tables.inc(num_tables as _);
+        self.write_metric_body_size.inc(body.len() as _);
+
+        Ok(summary)
+    }
+
+    /// Handle an OTLP/HTTP metrics export request, carrying a protobuf encoded
+    /// `ExportMetricsServiceRequest`.
+    ///
+    /// The metrics are converted as described in the [`otlp`] module.
+    async fn otlp_metrics_handler(&self, req: Request<Body>) -> Result<WriteSummary, Error> {
+        let span_ctx: Option<SpanContext> = req.extensions().get().cloned();
+
+        let write_info = WriteInfo::try_from(&req)?;
+        let namespace = org_and_bucket_to_namespace(&write_info.org, &write_info.bucket)
+            .map_err(OrgBucketError::MappingFail)?;
+
+        trace!(
+            org=%write_info.org,
+            bucket=%write_info.bucket,
+            %namespace,
+            "processing otlp metrics request"
+        );
+
+        let body = self.read_body(req).await?;
+        let export =
+            ExportMetricsServiceRequest::decode(body.as_ref()).map_err(Error::InvalidProtobuf)?;
+
+        let (batches, stats) = otlp::export_request_to_batches(&export)?;
+        if batches.is_empty() {
+            debug!("nothing to write");
+            return Ok(WriteSummary::default());
+        }
+
+        let num_tables = batches.len();
+        debug!(
+            num_data_points=stats.num_data_points,
+            num_fields=stats.num_fields,
+            num_tables,
+            body_size=body.len(),
+            %namespace,
+            org=%write_info.org,
+            bucket=%write_info.bucket,
+            "routing otlp metrics",
+        );
+
+        let namespace_id = self.namespace_resolver.get_namespace_id(&namespace).await?;
+
+        let summary = self
+            .dml_handler
+            .write(&namespace, namespace_id, batches, span_ctx)
+            .await
+            .map_err(Into::into)?;
+
+        self.otlp_metric_data_points.inc(stats.num_data_points as _);
+        self.write_metric_fields.inc(stats.num_fields as _);
+        self.write_metric_tables.inc(num_tables as _);
+        self.write_metric_body_size.inc(body.len() as _);
+
+        Ok(summary)
+    }
+
     async fn delete_handler(&self, req: Request<Body>) -> Result<WriteSummary, Error> {
         let span_ctx: Option<SpanContext> = req.extensions().get().cloned();
 
@@ -509,18 +679,7 @@ where
             Some(v) => return Err(Error::InvalidContentEncoding(v.to_string())),
         };
 
-        let mut payload = req.into_body();
-
-        let mut body = BytesMut::new();
-        while let Some(chunk) = payload.next().await {
-            let chunk = chunk.map_err(Error::ClientHangup)?;
-            // limit max size of in-memory payload
-            if (body.len() + chunk.len()) > self.max_request_bytes {
-                return Err(Error::RequestSizeExceeded(self.max_request_bytes));
-            }
-            body.extend_from_slice(&chunk);
-        }
-        let body = body.freeze();
+        let body = self.read_payload(req.into_body()).await?;
 
         // If the body is not compressed, return early.
         if !ungzip {
@@ -551,6 +710,49 @@ where
 
         Ok(decoded_data.into())
     }
+
+    /// Parse the request's snappy block-compressed body into raw bytes,
+    /// applying the configured size limits.
+    async fn read_snappy_body(&self, req: hyper::Request<Body>) -> Result<Bytes, Error> {
+        let encoding = req
+            .headers()
+            .get(&CONTENT_ENCODING)
+            .map(|v| v.to_str().map_err(Error::NonUtf8ContentHeader))
+            .transpose()?;
+        match encoding {
+            None | Some("snappy") => {}
+            Some(v) => return Err(Error::InvalidContentEncoding(v.to_string())),
+        }
+
+        let body = self.read_payload(req.into_body()).await?;
+
+        // Check the decompressed length recorded in the block header before
+        // allocating the output, to prevent a decompression bomb based DoS.
+        let len = snap::raw::decompress_len(&body).map_err(Error::InvalidSnappy)?;
+        if len > self.max_request_bytes {
+            return Err(Error::RequestSizeExceeded(self.max_request_bytes));
+        }
+
+        snap::raw::Decoder::new()
+            .decompress_vec(&body)
+            .map(Into::into)
+            .map_err(Error::InvalidSnappy)
+    }
+
+    /// Read the request payload into memory, returning an error if it exceeds
+    /// the configured maximum size.
+    async fn read_payload(&self, mut payload: Body) -> Result<Bytes, Error> {
+        let mut body = BytesMut::new();
+        while let Some(chunk) = payload.next().await {
+            let chunk = chunk.map_err(Error::ClientHangup)?;
+            // limit max size of in-memory payload
+            if (body.len() + chunk.len()) > self.max_request_bytes {
+                return Err(Error::RequestSizeExceeded(self.max_request_bytes));
+            }
+            body.extend_from_slice(&chunk);
+        }
+        Ok(body.freeze())
+    }
 }
 
 #[cfg(test)]
@@ -1200,6 +1402,242 @@ mod tests {
         );
     }
 
+    /// Encode a remote write request containing a single sample of `metric`,
+    /// snappy-compressed.
+    fn prom_write_body(metric: &str) -> Vec<u8> {
+        use generated_types::prometheus::{Label, Sample, TimeSeries, WriteRequest};
+
+        let req = WriteRequest {
+            timeseries: vec![TimeSeries {
+                labels: vec![
+                    Label {
+                        name: "__name__".to_string(),
+                        value: metric.to_string(),
+                    },
+                    Label {
+                        name: "job".to_string(),
+                        value: "iox".to_string(),
+                    },
+                ],
+                samples: vec![Sample {
+                    value: 42.0,
+                    timestamp: 1647622847000,
+                }],
+                exemplars: vec![],
+            }],
+            metadata: vec![],
+        };
+
+        snap::raw::Encoder::new()
+            .compress_vec(&req.encode_to_vec())
+            .unwrap()
+    }
+
+    async fn prom_write(
+        body: Vec<u8>,
+        encoding: Option<&'static str>,
+        dml_handler: &Arc<MockDmlHandler<HashMap<String, MutableBatch>>>,
+        metrics: &metric::Registry,
+    ) -> Result<Response<Body>, Error> {
+        let mut request = Request::builder()
+            .uri("https://bananas.example/api/v1/prom/write?org=bananas&bucket=test")
+            .method("POST")
+            .body(Body::from(body))
+            .unwrap();
+        if let Some(encoding) = encoding {
+            request
+                .headers_mut()
+                .insert(CONTENT_ENCODING, HeaderValue::from_static(encoding));
+        }
+
+        let delegate = HttpDelegate::new(
+            MAX_BYTES,
+            100,
+            MockNamespaceResolver::default().with_mapping("bananas_test", NAMESPACE_ID),
+            Arc::clone(dml_handler),
+            metrics,
+        );
+
+        delegate.route(request).await
+    }
+
+    #[tokio::test]
+    async fn test_prom_write_ok() {
+        let dml_handler =
+            Arc::new(MockDmlHandler::default().with_write_return([Ok(summary()), Ok(summary())]));
+        let metrics = metric::Registry::default();
+
+        for encoding in [Some("snappy"), None] {
+            let got = prom_write(prom_write_body("up"), encoding, &dml_handler, &metrics).await;
+            assert_matches!(got, Ok(r) => assert_eq!(r.status(), StatusCode::NO_CONTENT));
+        }
+
+        let calls = dml_handler.calls();
+        assert_matches!(calls.as_slice(), [
+            MockDmlHandlerCall::Write { namespace, namespace_id, write_input },
+            MockDmlHandlerCall::Write { .. },
+        ] => {
+            assert_eq!(namespace, "bananas_test");
+            assert_eq!(*namespace_id, NAMESPACE_ID);
+
+            let table = write_input.get("up").expect("table not found");
+            assert_eq!(table.rows(), 1);
+            assert!(table.column("job").is_ok());
+            let ts = table.timestamp_summary().expect("no timestamp summary");
+            assert_eq!(Some(1647622847000000000), ts.stats.min);
+        });
+
+        assert_metric_hit(&metrics, "http_prom_write_samples", Some(2));
+        assert_metric_hit(&metrics, "http_write_tables", Some(2));
+    }
+
+    #[tokio::test]
+    async fn test_prom_write_errors() {
+        let dml_handler = Arc::new(MockDmlHandler::default());
+        let metrics = metric::Registry::default();
+
+        let got = prom_write(prom_write_body("up"), Some("gzip"), &dml_handler, &metrics).await;
+        assert_matches!(got, Err(Error::InvalidContentEncoding(_)));
+
+        let got = prom_write(b"not snappy".to_vec(), None, &dml_handler, &metrics).await;
+        assert_matches!(got, Err(Error::InvalidSnappy(_)));
+
+        // A body that decompresses to more than the maximum request size is
+        // rejected before it is decompressed.
+        let body = snap::raw::Encoder::new()
+            .compress_vec(&[42; MAX_BYTES + 1])
+            .unwrap();
+        assert!(body.len() < MAX_BYTES);
+        let got = prom_write(body, None, &dml_handler, &metrics).await;
+        assert_matches!(got, Err(Error::RequestSizeExceeded(_)));
+
+        let body = snap::raw::Encoder::new()
+            .compress_vec(b"not a protobuf")
+            .unwrap();
+        let got = prom_write(body, None, &dml_handler, &metrics).await;
+        assert_matches!(got, Err(e) => assert_eq!(e.as_status_code(), StatusCode::BAD_REQUEST));
+
+        assert!(dml_handler.calls().is_empty());
+    }
+
+    fn otlp_metrics_body(metric: &str) -> Vec<u8> {
+        use generated_types::opentelemetry::proto::{
+            common::v1::{any_value, AnyValue, KeyValue},
+            metrics::v1::{
+                metric::Data, number_data_point, Gauge, Metric, NumberDataPoint, ResourceMetrics,
+                ScopeMetrics,
+            },
+        };
+
+        ExportMetricsServiceRequest {
+            resource_metrics: vec![ResourceMetrics {
+                resource: None,
+                scope_metrics: vec![ScopeMetrics {
+                    scope: None,
+                    metrics: vec![Metric {
+                        name: metric.to_string(),
+                        description: String::new(),
+                        unit: String::new(),
+                        data: Some(Data::Gauge(Gauge {
+                            data_points: vec![NumberDataPoint {
+                                attributes: vec![KeyValue {
+                                    key: "job".to_string(),
+                                    value: Some(AnyValue {
+                                        value: Some(any_value::Value::StringValue(
+                                            "iox".to_string(),
+                                        )),
+                                    }),
+                                }],
+                                start_time_unix_nano: 0,
+                                time_unix_nano: 1647622847000000000,
+                                value: Some(number_data_point::Value::AsDouble(42.0)),
+                                exemplars: vec![],
+                                flags: 0,
+                            }],
+                        })),
+                    }],
+                    schema_url: String::new(),
+                }],
+                schema_url: String::new(),
+            }],
+        }
+        .encode_to_vec()
+    }
+
+    async fn otlp_metrics(
+        body: Vec<u8>,
+        dml_handler: &Arc<MockDmlHandler<HashMap<String, MutableBatch>>>,
+        metrics: &metric::Registry,
+    ) -> Result<Response<Body>, Error> {
+        let request = Request::builder()
+            .uri("https://bananas.example/v1/metrics?org=bananas&bucket=test")
+            .method("POST")
+            .body(Body::from(body))
+            .unwrap();
+
+        let delegate = HttpDelegate::new(
+            MAX_BYTES,
+            100,
+            MockNamespaceResolver::default().with_mapping("bananas_test", NAMESPACE_ID),
+            Arc::clone(dml_handler),
+            metrics,
+        );
+
+        delegate.route(request).await
+    }
+
+    #[tokio::test]
+    async fn test_otlp_metrics_ok() {
+        let dml_handler = Arc::new(MockDmlHandler::default().with_write_return([Ok(summary())]));
+        let metrics = metric::Registry::default();
+
+        let got = otlp_metrics(otlp_metrics_body("cpu"), &dml_handler, &metrics).await;
+        assert_matches!(got, Ok(r) => {
+            assert_eq!(r.status(), StatusCode::OK);
+            assert_eq!(r.headers()[CONTENT_TYPE], "application/x-protobuf");
+
+            let body = hyper::body::to_bytes(r.into_body()).await.unwrap();
+            let resp = ExportMetricsServiceResponse::decode(body).expect("invalid response body");
+            assert_eq!(resp.partial_success, None);
+        });
+
+        let calls = dml_handler.calls();
+        assert_matches!(calls.as_slice(), [
+            MockDmlHandlerCall::Write { namespace, namespace_id, write_input },
+        ] => {
+            assert_eq!(namespace, "bananas_test");
+            assert_eq!(*namespace_id, NAMESPACE_ID);
+
+            let table = write_input.get("cpu").expect("table not found");
+            assert_eq!(table.rows(), 1);
+            assert!(table.column("job").is_ok());
+            assert!(table.column("value").is_ok());
+            let ts = table.timestamp_summary().expect("no timestamp summary");
+            assert_eq!(Some(1647622847000000000), ts.stats.min);
+        });
+
+        assert_metric_hit(&metrics, "http_otlp_write_data_points", Some(1));
+        assert_metric_hit(&metrics, "http_write_fields", Some(1));
+        assert_metric_hit(&metrics, "http_write_tables", Some(1));
+    }
+
+    #[tokio::test]
+    async fn test_otlp_metrics_errors() {
+        let dml_handler = Arc::new(MockDmlHandler::default());
+        let metrics = metric::Registry::default();
+
+        let got = otlp_metrics(b"not a protobuf".to_vec(), &dml_handler, &metrics).await;
+        assert_matches!(got, Err(Error::InvalidProtobuf(_)));
+
+        let got = otlp_metrics(otlp_metrics_body(""), &dml_handler, &metrics).await;
+        assert_matches!(got, Err(e) => {
+            assert_matches!(e, Error::ParseOtlp(otlp::Error::MissingMetricName));
+            assert_eq!(e.as_status_code(), StatusCode::BAD_REQUEST);
+        });
+
+        assert!(dml_handler.calls().is_empty());
+    }
+
     #[derive(Debug, Error)]
     enum MockError {
         #[error("bad stuff")]
@@ -1441,6 +1879,26 @@ mod tests {
             "error decoding gzip stream: [io Error]",
         ),
 
+        (
+            InvalidSnappy(snap::Error::Empty),
+            "error decoding snappy block: snappy: corrupt input (empty)",
+        ),
+
+        (
+            ParsePromWrite(prom_write::Error::MissingMetricName),
+            "failed to parse prometheus remote write request: time series has no __name__ label",
+        ),
+
+        (
+            InvalidProtobuf(prost::DecodeError::new("bananas")),
+            "failed to decode protobuf request: failed to decode Protobuf message: bananas",
+        ),
+
+        (
+            ParseOtlp(otlp::Error::MissingMetricName),
+            "failed to convert otlp metrics: metric has no name",
+        ),
+
         (
             ParseLineProtocol(mutable_batch_lp::Error::LineProtocol {
                 source: influxdb_line_protocol::Error::FieldSetMissing,
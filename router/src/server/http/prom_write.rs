@@ -0,0 +1,281 @@
+//! Conversion of Prometheus [remote write] requests into [`MutableBatch`].
+//!
+//! Each time series is written to the table named by its `__name__` label, with
+//! the remaining labels as tags, the sample values in a float `value` field and
+//! the (millisecond) sample timestamps in the `time` column.
+//!
+//! [remote write]: https://prometheus.io/docs/concepts/remote_write_spec/
+
+use generated_types::prometheus::{TimeSeries, WriteRequest};
+use hashbrown::{HashMap, HashSet};
+use mutable_batch::{writer::Writer, MutableBatch};
+use prost::Message;
+use thiserror::Error;
+
+/// The label holding the metric name of a time series.
+const METRIC_NAME_LABEL: &str = "__name__";
+
+/// The field the sample values are written to.
+const VALUE_FIELD: &str = "value";
+
+/// Errors converting a Prometheus remote write request.
+#[derive(Debug, Error)]
+pub enum Error {
+    /// The request body is not a valid `WriteRequest` protobuf.
+    #[error("invalid remote write protobuf: {0}")]
+    Decode(#[from] prost::DecodeError),
+
+    /// A time series has no metric name.
+    #[error("time series has no {METRIC_NAME_LABEL} label")]
+    MissingMetricName,
+
+    /// A time series has more than one label with the same name.
+    #[error("time series for metric {metric} has duplicate label {label}")]
+    DuplicateLabel {
+        /// The metric name.
+        metric: String,
+        /// The duplicated label name.
+        label: String,
+    },
+
+    /// A sample timestamp cannot be represented in nanoseconds.
+    #[error("sample timestamp {0}ms overflows nanosecond timestamp")]
+    TimestampOverflow(i64),
+
+    /// The time series could not be written to the batch for its metric, such
+    /// as a label conflicting with the `value` or `time` columns.
+    #[error("failed to write time series for metric {metric}: {source}")]
+    Write {
+        /// The metric name.
+        metric: String,
+        /// The underlying error.
+        source: mutable_batch::writer::Error,
+    },
+}
+
+/// Statistics about a converted remote write request.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct PromWriteStatistics {
+    /// The number of time series in the request.
+    pub(crate) num_series: usize,
+    /// The number of samples across all time series.
+    pub(crate) num_samples: usize,
+}
+
+/// Decode the (uncompressed) protobuf `WriteRequest` in `body` and convert it
+/// into a set of [`MutableBatch`] keyed by table name.
+///
+/// Metric metadata and exemplars are ignored.
+pub(crate) fn write_request_to_batches(
+    body: &[u8],
+) -> Result<(HashMap<String, MutableBatch>, PromWriteStatistics), Error> {
+    let req = WriteRequest::decode(body)?;
+
+    let mut batches = HashMap::new();
+    let mut stats = PromWriteStatistics::default();
+    for series in &req.timeseries {
+        if series.samples.is_empty() {
+            continue;
+        }
+
+        write_series(&mut batches, series)?;
+
+        stats.num_series += 1;
+        stats.num_samples += series.samples.len();
+    }
+
+    Ok((batches, stats))
+}
+
+/// Append all the samples of `series` to the batch for its metric.
+fn write_series(
+    batches: &mut HashMap<String, MutableBatch>,
+    series: &TimeSeries,
+) -> Result<(), Error> {
+    let metric = series
+        .labels
+        .iter()
+        .find(|l| l.name == METRIC_NAME_LABEL)
+        .map(|l| l.value.as_str())
+        .filter(|v| !v.is_empty())
+        .ok_or(Error::MissingMetricName)?;
+
+    // Writing the same column twice through a single writer panics, so reject
+    // duplicate labels up front.
+    let mut seen = HashSet::with_capacity(series.labels.len());
+    if let Some(dupe) = series.labels.iter().find(|l| !seen.insert(l.name.as_str())) {
+        return Err(Error::DuplicateLabel {
+            metric: metric.to_string(),
+            label: dupe.name.clone(),
+        });
+    }
+
+    let times = series
+        .samples
+        .iter()
+        .map(|s| {
+            s.timestamp
+                .checked_mul(1_000_000)
+                .ok_or(Error::TimestampOverflow(s.timestamp))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let batch = batches
+        .raw_entry_mut()
+        .from_key(metric)
+        .or_insert_with(|| (metric.to_string(), MutableBatch::new()))
+        .1;
+
+    let n = series.samples.len();
+    let mut writer = Writer::new(batch, n);
+    let write_err = |source| Error::Write {
+        metric: metric.to_string(),
+        source,
+    };
+
+    // An empty label value is equivalent to the label being absent.
+    for label in series
+        .labels
+        .iter()
+        .filter(|l| l.name != METRIC_NAME_LABEL && !l.value.is_empty())
+    {
+        writer
+            .write_tag(
+                &label.name,
+                None,
+                std::iter::repeat(label.value.as_str()).take(n),
+            )
+            .map_err(write_err)?;
+    }
+    writer
+        .write_f64(VALUE_FIELD, None, series.samples.iter().map(|s| s.value))
+        .map_err(write_err)?;
+    writer
+        .write_time("time", times.into_iter())
+        .map_err(write_err)?;
+    writer.commit();
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_matches::assert_matches;
+    use generated_types::prometheus::{Label, Sample};
+    use schema::Projection;
+
+    use super::*;
+
+    fn label(name: &str, value: &str) -> Label {
+        Label {
+            name: name.to_string(),
+            value: value.to_string(),
+        }
+    }
+
+    fn series(labels: &[(&str, &str)], samples: &[(f64, i64)]) -> TimeSeries {
+        TimeSeries {
+            labels: labels.iter().map(|(n, v)| label(n, v)).collect(),
+            samples: samples
+                .iter()
+                .map(|&(value, timestamp)| Sample { value, timestamp })
+                .collect(),
+            exemplars: vec![],
+        }
+    }
+
+    fn encode(timeseries: Vec<TimeSeries>) -> Vec<u8> {
+        WriteRequest {
+            timeseries,
+            metadata: vec![],
+        }
+        .encode_to_vec()
+    }
+
+    #[test]
+    fn test_convert() {
+        let body = encode(vec![
+            series(
+                &[("__name__", "http_requests_total"), ("code", "200")],
+                &[(1.0, 1_000), (2.0, 2_000)],
+            ),
+            series(
+                &[
+                    ("__name__", "http_requests_total"),
+                    ("code", "500"),
+                    ("path", "/write"),
+                    ("empty", ""),
+                ],
+                &[(3.0, 1_000)],
+            ),
+            series(&[("__name__", "up")], &[(1.0, 3_000)]),
+            // Series without samples are skipped.
+            series(&[("__name__", "bananas")], &[]),
+        ]);
+
+        let (batches, stats) = write_request_to_batches(&body).unwrap();
+        assert_eq!(
+            stats,
+            PromWriteStatistics {
+                num_series: 3,
+                num_samples: 4,
+            }
+        );
+
+        let mut tables = batches.keys().map(String::as_str).collect::<Vec<_>>();
+        tables.sort_unstable();
+        assert_eq!(tables, ["http_requests_total", "up"]);
+
+        let batch = batches["http_requests_total"]
+            .to_arrow(Projection::All)
+            .unwrap();
+        arrow_util::assert_batches_sorted_eq!(
+            [
+                "+------+--------+----------------------+-------+",
+                "| code | path   | time                 | value |",
+                "+------+--------+----------------------+-------+",
+                "| 200  |        | 1970-01-01T00:00:01Z | 1     |",
+                "| 200  |        | 1970-01-01T00:00:02Z | 2     |",
+                "| 500  | /write | 1970-01-01T00:00:01Z | 3     |",
+                "+------+--------+----------------------+-------+",
+            ],
+            &[batch]
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let body = encode(vec![series(&[("job", "iox")], &[(1.0, 1)])]);
+        assert_matches!(
+            write_request_to_batches(&body),
+            Err(Error::MissingMetricName)
+        );
+
+        let body = encode(vec![series(
+            &[("__name__", "up"), ("job", "a"), ("job", "b")],
+            &[(1.0, 1)],
+        )]);
+        assert_matches!(
+            write_request_to_batches(&body),
+            Err(Error::DuplicateLabel { label, .. }) => assert_eq!(label, "job")
+        );
+
+        let body = encode(vec![series(&[("__name__", "up")], &[(1.0, i64::MAX)])]);
+        assert_matches!(
+            write_request_to_batches(&body),
+            Err(Error::TimestampOverflow(_))
+        );
+
+        // A label conflicts with the value field.
+        let body = encode(vec![series(
+            &[("__name__", "up"), ("value", "x")],
+            &[(1.0, 1)],
+        )]);
+        assert_matches!(
+            write_request_to_batches(&body),
+            Err(Error::Write { metric, .. }) => assert_eq!(metric, "up")
+        );
+
+        assert_matches!(write_request_to_batches(b"\xff\xff"), Err(Error::Decode(_)));
+    }
+}
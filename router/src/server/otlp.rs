@@ -0,0 +1,831 @@
+//! Conversion of OpenTelemetry (OTLP) metrics export requests into
+//! [`MutableBatch`].
+//!
+//! Each data point is written as a row of the table named after its metric.
+//! The attributes of the resource, instrumentation scope and data point become
+//! tags, with the data point attributes taking precedence over the scope
+//! attributes, which take precedence over the resource attributes. String,
+//! boolean, integer and double attribute values are converted to their string
+//! representation; array, key/value list and bytes values are not supported
+//! and are skipped. The `time` column holds the data point timestamp.
+//!
+//! The fields of each row depend on the type of the metric:
+//!
+//! * Gauges and sums have a single `value` field, holding a float. Integer
+//!   data point values are converted to floats, so a metric that switches
+//!   between integer and double values always maps to the same column type.
+//! * Histograms have a `count` field, optional `sum`, `min` and `max` fields,
+//!   and a cumulative count field for each bucket named after its upper bound,
+//!   such as `le_0.5` or `le_+Inf`, matching the Prometheus bucketing.
+//! * Exponential histograms have `count`, `zero_count` and `scale` fields, the
+//!   optional `sum`, `min` and `max` fields, and for each of the positive and
+//!   negative ranges an offset field, such as `positive_offset`, and a count
+//!   field for each populated bucket named after its position relative to the
+//!   offset, such as `positive_3` or `negative_0`. The bucket at position `i`
+//!   covers the values in `(base^(offset + i), base^(offset + i + 1)]`, where
+//!   `base = 2^(2^-scale)`. Data points spanning more than
+//!   [`MAX_EXPONENTIAL_BUCKETS`] buckets in either range are downscaled by
+//!   merging adjacent buckets until they fit, bounding the number of bucket
+//!   columns of the table.
+//! * Summaries have `count` and `sum` fields, and a field for each quantile
+//!   named after it, such as `quantile_0.99`.
+//!
+//! Counts are written as unsigned integers, scales and bucket offsets as signed
+//! integers, and all other values as floats.
+//! Metric descriptions, units, aggregation temporality, exemplars and data
+//! points flagged as having no recorded value are ignored.
+
+use std::collections::BTreeMap;
+
+use generated_types::opentelemetry::proto::{
+    collector::metrics::v1::ExportMetricsServiceRequest,
+    common::v1::{any_value, KeyValue},
+    metrics::v1::{
+        exponential_histogram_data_point::Buckets, metric::Data, number_data_point, DataPointFlags,
+        ExponentialHistogramDataPoint, HistogramDataPoint, NumberDataPoint, SummaryDataPoint,
+    },
+};
+use hashbrown::{HashMap, HashSet};
+use mutable_batch::{writer::Writer, MutableBatch};
+use thiserror::Error;
+
+/// The maximum number of buckets written for each of the positive and negative
+/// ranges of an exponential histogram data point, matching the default maximum
+/// size of the OpenTelemetry SDK exponential histogram aggregation.
+pub(crate) const MAX_EXPONENTIAL_BUCKETS: i64 = 160;
+
+/// Errors converting an OTLP metrics export request.
+#[derive(Debug, Error)]
+pub enum Error {
+    /// A metric has no name.
+    #[error("metric has no name")]
+    MissingMetricName,
+
+    /// A data point timestamp cannot be represented as a signed nanosecond
+    /// timestamp.
+    #[error("data point timestamp {0} for metric {1} is out of range")]
+    TimestampOverflow(u64, String),
+
+    /// A histogram data point has a number of bucket counts that does not
+    /// match its number of explicit bounds.
+    #[error(
+        "histogram data point for metric {metric} has {counts} bucket counts for {bounds} bounds"
+    )]
+    InvalidHistogram {
+        /// The metric name.
+        metric: String,
+        /// The number of bucket counts.
+        counts: usize,
+        /// The number of explicit bounds.
+        bounds: usize,
+    },
+
+    /// A data point maps to the same field more than once, such as a histogram
+    /// with a repeated bound.
+    #[error("data point for metric {metric} has duplicate field {field}")]
+    DuplicateField {
+        /// The metric name.
+        metric: String,
+        /// The duplicated field name.
+        field: String,
+    },
+
+    /// The data point could not be written to the batch for its metric, such
+    /// as an attribute conflicting with a field name.
+    #[error("failed to write data point for metric {metric}: {source}")]
+    Write {
+        /// The metric name.
+        metric: String,
+        /// The underlying error.
+        source: mutable_batch::writer::Error,
+    },
+}
+
+/// Statistics about a converted OTLP metrics export request.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct OtlpWriteStatistics {
+    /// The number of data points written.
+    pub(crate) num_data_points: usize,
+    /// The number of fields across all data points.
+    pub(crate) num_fields: usize,
+}
+
+/// A field value of a data point row.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FieldValue {
+    F64(f64),
+    I64(i64),
+    U64(u64),
+}
+
+/// Convert the metrics in `req` into a set of [`MutableBatch`] keyed by table
+/// name.
+pub(crate) fn export_request_to_batches(
+    req: &ExportMetricsServiceRequest,
+) -> Result<(HashMap<String, MutableBatch>, OtlpWriteStatistics), Error> {
+    let mut batches = HashMap::new();
+    let mut stats = OtlpWriteStatistics::default();
+
+    for resource_metrics in &req.resource_metrics {
+        let mut resource_tags = BTreeMap::new();
+        if let Some(resource) = &resource_metrics.resource {
+            add_tags(&mut resource_tags, &resource.attributes);
+        }
+
+        for scope_metrics in &resource_metrics.scope_metrics {
+            let mut scope_tags = resource_tags.clone();
+            if let Some(scope) = &scope_metrics.scope {
+                add_tags(&mut scope_tags, &scope.attributes);
+            }
+
+            for metric in &scope_metrics.metrics {
+                if metric.name.is_empty() {
+                    return Err(Error::MissingMetricName);
+                }
+
+                let mut writer = PointWriter {
+                    batches: &mut batches,
+                    stats: &mut stats,
+                    metric: &metric.name,
+                    tags: &scope_tags,
+                };
+
+                match &metric.data {
+                    Some(Data::Gauge(v)) => writer.write_all(&v.data_points, number_point)?,
+                    Some(Data::Sum(v)) => writer.write_all(&v.data_points, number_point)?,
+                    Some(Data::Histogram(v)) => {
+                        writer.write_all(&v.data_points, histogram_point)?
+                    }
+                    Some(Data::ExponentialHistogram(v)) => {
+                        writer.write_all(&v.data_points, exponential_histogram_point)?
+                    }
+                    Some(Data::Summary(v)) => writer.write_all(&v.data_points, summary_point)?,
+                    None => {}
+                }
+            }
+        }
+    }
+
+    Ok((batches, stats))
+}
+
+/// Add the supported `attributes` to `tags`, overwriting any existing tags
+/// with the same key.
+fn add_tags(tags: &mut BTreeMap<String, String>, attributes: &[KeyValue]) {
+    for kv in attributes {
+        let value = match kv.value.as_ref().and_then(|v| v.value.as_ref()) {
+            Some(any_value::Value::StringValue(v)) => v.clone(),
+            Some(any_value::Value::BoolValue(v)) => v.to_string(),
+            Some(any_value::Value::IntValue(v)) => v.to_string(),
+            Some(any_value::Value::DoubleValue(v)) => v.to_string(),
+            Some(
+                any_value::Value::ArrayValue(_)
+                | any_value::Value::KvlistValue(_)
+                | any_value::Value::BytesValue(_),
+            )
+            | None => continue,
+        };
+
+        // An empty tag value is equivalent to the tag being absent.
+        if kv.key.is_empty() || value.is_empty() {
+            continue;
+        }
+
+        tags.insert(kv.key.clone(), value);
+    }
+}
+
+/// The attributes, timestamp and flags common to all data point types.
+trait DataPoint {
+    fn attributes(&self) -> &[KeyValue];
+    fn time_unix_nano(&self) -> u64;
+    fn flags(&self) -> u32;
+}
+
+macro_rules! impl_data_point {
+    ($($t:ty),+) => {
+        $(
+            impl DataPoint for $t {
+                fn attributes(&self) -> &[KeyValue] {
+                    &self.attributes
+                }
+                fn time_unix_nano(&self) -> u64 {
+                    self.time_unix_nano
+                }
+                fn flags(&self) -> u32 {
+                    self.flags
+                }
+            }
+        )+
+    };
+}
+
+impl_data_point!(
+    NumberDataPoint,
+    HistogramDataPoint,
+    ExponentialHistogramDataPoint,
+    SummaryDataPoint
+);
+
+/// Writes the data points of a single metric to its batch.
+#[derive(Debug)]
+struct PointWriter<'a> {
+    batches: &'a mut HashMap<String, MutableBatch>,
+    stats: &'a mut OtlpWriteStatistics,
+    metric: &'a str,
+    /// The resource and scope tags of the metric.
+    tags: &'a BTreeMap<String, String>,
+}
+
+impl<'a> PointWriter<'a> {
+    /// Write a row for each of `points`, with the fields returned by
+    /// `to_fields`.
+    fn write_all<P, F>(&mut self, points: &[P], to_fields: F) -> Result<(), Error>
+    where
+        P: DataPoint,
+        F: Fn(&P, &str) -> Result<Vec<(String, FieldValue)>, Error>,
+    {
+        for point in points {
+            if point.flags() & DataPointFlags::FlagNoRecordedValue as u32 != 0 {
+                continue;
+            }
+
+            let fields = to_fields(point, self.metric)?;
+            if fields.is_empty() {
+                continue;
+            }
+
+            let time = i64::try_from(point.time_unix_nano()).map_err(|_| {
+                Error::TimestampOverflow(point.time_unix_nano(), self.metric.to_string())
+            })?;
+
+            let mut tags = self.tags.clone();
+            add_tags(&mut tags, point.attributes());
+
+            self.write_row(&tags, &fields, time)?;
+
+            self.stats.num_data_points += 1;
+            self.stats.num_fields += fields.len();
+        }
+
+        Ok(())
+    }
+
+    fn write_row(
+        &mut self,
+        tags: &BTreeMap<String, String>,
+        fields: &[(String, FieldValue)],
+        time: i64,
+    ) -> Result<(), Error> {
+        // Writing the same column twice through a single writer panics, so
+        // reject duplicate fields up front.
+        let mut seen = HashSet::with_capacity(fields.len());
+        if let Some((dupe, _)) = fields.iter().find(|(name, _)| !seen.insert(name.as_str())) {
+            return Err(Error::DuplicateField {
+                metric: self.metric.to_string(),
+                field: dupe.clone(),
+            });
+        }
+
+        let metric = self.metric;
+        let batch = self
+            .batches
+            .raw_entry_mut()
+            .from_key(metric)
+            .or_insert_with(|| (metric.to_string(), MutableBatch::new()))
+            .1;
+
+        let write_err = |source| Error::Write {
+            metric: metric.to_string(),
+            source,
+        };
+
+        let mut writer = Writer::new(batch, 1);
+        for (key, value) in tags {
+            writer
+                .write_tag(key, None, std::iter::once(value.as_str()))
+                .map_err(write_err)?;
+        }
+        for (name, value) in fields {
+            match *value {
+                FieldValue::F64(v) => writer.write_f64(name, None, std::iter::once(v)),
+                FieldValue::I64(v) => writer.write_i64(name, None, std::iter::once(v)),
+                FieldValue::U64(v) => writer.write_u64(name, None, std::iter::once(v)),
+            }
+            .map_err(write_err)?;
+        }
+        writer
+            .write_time("time", std::iter::once(time))
+            .map_err(write_err)?;
+        writer.commit();
+
+        Ok(())
+    }
+}
+
+/// The fields of a gauge or sum data point.
+fn number_point(p: &NumberDataPoint, _metric: &str) -> Result<Vec<(String, FieldValue)>, Error> {
+    Ok(match p.value {
+        Some(number_data_point::Value::AsDouble(v)) => {
+            vec![("value".to_string(), FieldValue::F64(v))]
+        }
+        Some(number_data_point::Value::AsInt(v)) => {
+            vec![("value".to_string(), FieldValue::F64(v as f64))]
+        }
+        None => vec![],
+    })
+}
+
+/// The `sum`, `min` and `max` fields, if set.
+fn optional_fields(
+    fields: &mut Vec<(String, FieldValue)>,
+    sum: Option<f64>,
+    min: Option<f64>,
+    max: Option<f64>,
+) {
+    for (name, value) in [("sum", sum), ("min", min), ("max", max)] {
+        if let Some(v) = value {
+            fields.push((name.to_string(), FieldValue::F64(v)));
+        }
+    }
+}
+
+/// The fields of a histogram data point.
+fn histogram_point(
+    p: &HistogramDataPoint,
+    metric: &str,
+) -> Result<Vec<(String, FieldValue)>, Error> {
+    let mut fields = vec![("count".to_string(), FieldValue::U64(p.count))];
+    optional_fields(&mut fields, p.sum, p.min, p.max);
+
+    if p.bucket_counts.is_empty() {
+        return Ok(fields);
+    }
+    if p.bucket_counts.len() != p.explicit_bounds.len() + 1 {
+        return Err(Error::InvalidHistogram {
+            metric: metric.to_string(),
+            counts: p.bucket_counts.len(),
+            bounds: p.explicit_bounds.len(),
+        });
+    }
+
+    let bounds = p
+        .explicit_bounds
+        .iter()
+        .map(|b| b.to_string())
+        .chain(std::iter::once("+Inf".to_string()));
+
+    let mut cumulative_count = 0;
+    for (le, count) in bounds.zip(&p.bucket_counts) {
+        cumulative_count += count;
+        fields.push((format!("le_{}", le), FieldValue::U64(cumulative_count)));
+    }
+
+    Ok(fields)
+}
+
+/// The fields of an exponential histogram data point.
+fn exponential_histogram_point(
+    p: &ExponentialHistogramDataPoint,
+    _metric: &str,
+) -> Result<Vec<(String, FieldValue)>, Error> {
+    // The index of the first populated bucket and the counts up to and
+    // including the last populated bucket, for each range.
+    let ranges = [("positive", &p.positive), ("negative", &p.negative)].map(|(prefix, buckets)| {
+        let range = buckets.as_ref().and_then(
+            |Buckets {
+                 offset,
+                 bucket_counts,
+             }| {
+                let first = bucket_counts.iter().position(|&c| c > 0)?;
+                let last = bucket_counts.iter().rposition(|&c| c > 0)?;
+                Some((*offset as i64 + first as i64, &bucket_counts[first..=last]))
+            },
+        );
+        (prefix, range)
+    });
+
+    // Downscale by merging pairs of adjacent buckets, halving the resolution,
+    // until both ranges fit in MAX_EXPONENTIAL_BUCKETS buckets. The bucket at
+    // index i becomes the bucket at index i >> shift.
+    let span = |offset: i64, len: usize, shift: u32| {
+        ((offset + len as i64 - 1) >> shift) - (offset >> shift) + 1
+    };
+    let fits = |shift| {
+        ranges.iter().all(|(_, range)| match range {
+            Some((offset, counts)) => span(*offset, counts.len(), shift) <= MAX_EXPONENTIAL_BUCKETS,
+            None => true,
+        })
+    };
+    let mut shift = 0;
+    while !fits(shift) {
+        shift += 1;
+    }
+
+    let mut fields = vec![
+        ("count".to_string(), FieldValue::U64(p.count)),
+        ("zero_count".to_string(), FieldValue::U64(p.zero_count)),
+        (
+            "scale".to_string(),
+            FieldValue::I64(p.scale as i64 - shift as i64),
+        ),
+    ];
+    optional_fields(&mut fields, p.sum, p.min, p.max);
+
+    for (prefix, range) in ranges {
+        let (offset, counts) = match range {
+            Some(v) => v,
+            None => continue,
+        };
+
+        let base = offset >> shift;
+        let mut merged = vec![0; span(offset, counts.len(), shift) as usize];
+        for (index, &count) in (offset..).zip(counts) {
+            merged[((index >> shift) - base) as usize] += count;
+        }
+
+        fields.push((format!("{}_offset", prefix), FieldValue::I64(base)));
+        for (i, count) in merged.into_iter().enumerate() {
+            if count > 0 {
+                fields.push((format!("{}_{}", prefix, i), FieldValue::U64(count)));
+            }
+        }
+    }
+
+    Ok(fields)
+}
+
+/// The fields of a summary data point.
+fn summary_point(p: &SummaryDataPoint, _metric: &str) -> Result<Vec<(String, FieldValue)>, Error> {
+    let mut fields = vec![
+        ("count".to_string(), FieldValue::U64(p.count)),
+        ("sum".to_string(), FieldValue::F64(p.sum)),
+    ];
+    for q in &p.quantile_values {
+        fields.push((format!("quantile_{}", q.quantile), FieldValue::F64(q.value)));
+    }
+
+    Ok(fields)
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_matches::assert_matches;
+    use generated_types::opentelemetry::proto::{
+        common::v1::{AnyValue, InstrumentationScope},
+        metrics::v1::{
+            summary_data_point::ValueAtQuantile, ExponentialHistogram, Gauge, Histogram, Metric,
+            ResourceMetrics, ScopeMetrics, Sum, Summary,
+        },
+        resource::v1::Resource,
+    };
+    use schema::Projection;
+
+    use super::*;
+
+    fn kv(key: &str, value: any_value::Value) -> KeyValue {
+        KeyValue {
+            key: key.to_string(),
+            value: Some(AnyValue { value: Some(value) }),
+        }
+    }
+
+    fn string_kv(key: &str, value: &str) -> KeyValue {
+        kv(key, any_value::Value::StringValue(value.to_string()))
+    }
+
+    fn number(attributes: Vec<KeyValue>, value: number_data_point::Value) -> NumberDataPoint {
+        NumberDataPoint {
+            attributes,
+            start_time_unix_nano: 0,
+            time_unix_nano: 1_000_000_000,
+            value: Some(value),
+            exemplars: vec![],
+            flags: 0,
+        }
+    }
+
+    fn metric(name: &str, data: Data) -> Metric {
+        Metric {
+            name: name.to_string(),
+            description: String::new(),
+            unit: String::new(),
+            data: Some(data),
+        }
+    }
+
+    fn request(metrics: Vec<Metric>) -> ExportMetricsServiceRequest {
+        ExportMetricsServiceRequest {
+            resource_metrics: vec![ResourceMetrics {
+                resource: Some(Resource {
+                    attributes: vec![
+                        string_kv("service.name", "bananas"),
+                        string_kv("region", "resource"),
+                        kv("ignored", any_value::Value::BytesValue(vec![42])),
+                    ],
+                    dropped_attributes_count: 0,
+                }),
+                scope_metrics: vec![ScopeMetrics {
+                    scope: Some(InstrumentationScope {
+                        name: "platanos".to_string(),
+                        version: String::new(),
+                        attributes: vec![string_kv("region", "scope")],
+                        dropped_attributes_count: 0,
+                    }),
+                    metrics,
+                    schema_url: String::new(),
+                }],
+                schema_url: String::new(),
+            }],
+        }
+    }
+
+    #[test]
+    fn test_gauge_and_sum() {
+        let req = request(vec![
+            metric(
+                "temperature",
+                Data::Gauge(Gauge {
+                    data_points: vec![
+                        number(
+                            vec![string_kv("region", "point")],
+                            number_data_point::Value::AsDouble(21.5),
+                        ),
+                        // Integer values are written as floats.
+                        number(
+                            vec![kv("core", any_value::Value::IntValue(2))],
+                            number_data_point::Value::AsInt(18),
+                        ),
+                    ],
+                }),
+            ),
+            metric(
+                "requests",
+                Data::Sum(Sum {
+                    data_points: vec![
+                        number(vec![], number_data_point::Value::AsInt(42)),
+                        NumberDataPoint {
+                            flags: DataPointFlags::FlagNoRecordedValue as u32,
+                            ..number(vec![], number_data_point::Value::AsInt(0))
+                        },
+                    ],
+                    aggregation_temporality: 2,
+                    is_monotonic: true,
+                }),
+            ),
+        ]);
+
+        let (batches, stats) = export_request_to_batches(&req).unwrap();
+        assert_eq!(
+            stats,
+            OtlpWriteStatistics {
+                num_data_points: 3,
+                num_fields: 3,
+            }
+        );
+
+        arrow_util::assert_batches_eq!(
+            [
+                "+------+--------+--------------+----------------------+-------+",
+                "| core | region | service.name | time                 | value |",
+                "+------+--------+--------------+----------------------+-------+",
+                "|      | point  | bananas      | 1970-01-01T00:00:01Z | 21.5  |",
+                "| 2    | scope  | bananas      | 1970-01-01T00:00:01Z | 18    |",
+                "+------+--------+--------------+----------------------+-------+",
+            ],
+            &[batches["temperature"].to_arrow(Projection::All).unwrap()]
+        );
+        arrow_util::assert_batches_eq!(
+            [
+                "+--------+--------------+----------------------+-------+",
+                "| region | service.name | time                 | value |",
+                "+--------+--------------+----------------------+-------+",
+                "| scope  | bananas      | 1970-01-01T00:00:01Z | 42    |",
+                "+--------+--------------+----------------------+-------+",
+            ],
+            &[batches["requests"].to_arrow(Projection::All).unwrap()]
+        );
+    }
+
+    #[test]
+    fn test_histograms() {
+        let req = request(vec![
+            metric(
+                "latency",
+                Data::Histogram(Histogram {
+                    data_points: vec![HistogramDataPoint {
+                        attributes: vec![],
+                        start_time_unix_nano: 0,
+                        time_unix_nano: 1_000_000_000,
+                        count: 6,
+                        sum: Some(12.5),
+                        bucket_counts: vec![1, 2, 3],
+                        explicit_bounds: vec![0.5, 5.0],
+                        exemplars: vec![],
+                        flags: 0,
+                        min: None,
+                        max: Some(9.0),
+                    }],
+                    aggregation_temporality: 2,
+                }),
+            ),
+            metric(
+                "size",
+                Data::ExponentialHistogram(ExponentialHistogram {
+                    data_points: vec![ExponentialHistogramDataPoint {
+                        attributes: vec![],
+                        start_time_unix_nano: 0,
+                        time_unix_nano: 1_000_000_000,
+                        count: 4,
+                        sum: None,
+                        scale: 1,
+                        zero_count: 1,
+                        positive: Some(Buckets {
+                            offset: -1,
+                            bucket_counts: vec![2, 0, 1],
+                        }),
+                        negative: None,
+                        flags: 0,
+                        exemplars: vec![],
+                        min: None,
+                        max: None,
+                    }],
+                    aggregation_temporality: 2,
+                }),
+            ),
+            metric(
+                "rtt",
+                Data::Summary(Summary {
+                    data_points: vec![SummaryDataPoint {
+                        attributes: vec![],
+                        start_time_unix_nano: 0,
+                        time_unix_nano: 1_000_000_000,
+                        count: 10,
+                        sum: 20.0,
+                        quantile_values: vec![ValueAtQuantile {
+                            quantile: 0.99,
+                            value: 4.0,
+                        }],
+                        flags: 0,
+                    }],
+                }),
+            ),
+        ]);
+
+        let (batches, stats) = export_request_to_batches(&req).unwrap();
+        assert_eq!(stats.num_data_points, 3);
+
+        let table = &batches["latency"];
+        assert_eq!(table.rows(), 1);
+        for col in ["count", "sum", "max", "le_0.5", "le_5", "le_+Inf"] {
+            assert!(table.column(col).is_ok(), "missing column {}", col);
+        }
+        assert!(table.column("min").is_err());
+
+        let table = &batches["size"];
+        for col in [
+            "count",
+            "zero_count",
+            "scale",
+            "positive_offset",
+            "positive_0",
+            "positive_2",
+        ] {
+            assert!(table.column(col).is_ok(), "missing column {}", col);
+        }
+        // Empty buckets are not written.
+        assert!(table.column("positive_1").is_err());
+        assert!(table.column("negative_offset").is_err());
+
+        let table = &batches["rtt"];
+        for col in ["count", "sum", "quantile_0.99"] {
+            assert!(table.column(col).is_ok(), "missing column {}", col);
+        }
+    }
+
+    #[test]
+    fn test_exponential_histogram_downscale() {
+        let req = request(vec![metric(
+            "size",
+            Data::ExponentialHistogram(ExponentialHistogram {
+                data_points: vec![ExponentialHistogramDataPoint {
+                    attributes: vec![],
+                    start_time_unix_nano: 0,
+                    time_unix_nano: 1_000_000_000,
+                    count: 401,
+                    sum: None,
+                    scale: 3,
+                    zero_count: 0,
+                    // Leading and trailing empty buckets do not count towards
+                    // the limit.
+                    positive: Some(Buckets {
+                        offset: -2,
+                        bucket_counts: [0, 0]
+                            .into_iter()
+                            .chain(std::iter::repeat(1).take(400))
+                            .chain([0])
+                            .collect(),
+                    }),
+                    negative: Some(Buckets {
+                        offset: -3,
+                        bucket_counts: vec![1],
+                    }),
+                    flags: 0,
+                    exemplars: vec![],
+                    min: None,
+                    max: None,
+                }],
+                aggregation_temporality: 2,
+            }),
+        )]);
+
+        let (batches, _) = export_request_to_batches(&req).unwrap();
+        let table = &batches["size"];
+
+        // 400 buckets need two halvings to fit in MAX_EXPONENTIAL_BUCKETS,
+        // leaving 100 buckets of 4 merged buckets each.
+        arrow_util::assert_batches_eq!(
+            [
+                "+-------+-----------------+------------+-------------+-----------------+------------+",
+                "| scale | positive_offset | positive_0 | positive_99 | negative_offset | negative_0 |",
+                "+-------+-----------------+------------+-------------+-----------------+------------+",
+                "| 1     | 0               | 4          | 4           | -1              | 1          |",
+                "+-------+-----------------+------------+-------------+-----------------+------------+",
+            ],
+            &[table
+                .to_arrow(Projection::Some(&[
+                    "scale",
+                    "positive_offset",
+                    "positive_0",
+                    "positive_99",
+                    "negative_offset",
+                    "negative_0",
+                ]))
+                .unwrap()]
+        );
+        assert!(table.column("positive_100").is_err());
+    }
+
+    #[test]
+    fn test_errors() {
+        let histogram = |bucket_counts, explicit_bounds| {
+            request(vec![metric(
+                "latency",
+                Data::Histogram(Histogram {
+                    data_points: vec![HistogramDataPoint {
+                        attributes: vec![],
+                        start_time_unix_nano: 0,
+                        time_unix_nano: 1,
+                        count: 1,
+                        sum: None,
+                        bucket_counts,
+                        explicit_bounds,
+                        exemplars: vec![],
+                        flags: 0,
+                        min: None,
+                        max: None,
+                    }],
+                    aggregation_temporality: 2,
+                }),
+            )])
+        };
+
+        assert_matches!(
+            export_request_to_batches(&histogram(vec![1, 0], vec![1.0, 2.0])),
+            Err(Error::InvalidHistogram {
+                counts: 2,
+                bounds: 2,
+                ..
+            })
+        );
+        assert_matches!(
+            export_request_to_batches(&histogram(vec![1, 0, 0], vec![1.0, 1.0])),
+            Err(Error::DuplicateField { field, .. }) => assert_eq!(field, "le_1")
+        );
+
+        let gauge = |name: &str, attributes, time_unix_nano| {
+            request(vec![metric(
+                name,
+                Data::Gauge(Gauge {
+                    data_points: vec![NumberDataPoint {
+                        time_unix_nano,
+                        ..number(attributes, number_data_point::Value::AsDouble(1.0))
+                    }],
+                }),
+            )])
+        };
+
+        assert_matches!(
+            export_request_to_batches(&gauge("", vec![], 1)),
+            Err(Error::MissingMetricName)
+        );
+        assert_matches!(
+            export_request_to_batches(&gauge("g", vec![], u64::MAX)),
+            Err(Error::TimestampOverflow(..))
+        );
+        assert_matches!(
+            export_request_to_batches(&gauge("g", vec![string_kv("value", "x")], 1)),
+            Err(Error::Write { metric, .. }) => assert_eq!(metric, "g")
+        );
+    }
+}
@@ -25,6 +25,11 @@ pub trait QueryNamespaceProvider: std::fmt::Debug + Send + Sync + 'static {
 
     /// Acquire concurrency-limiting sempahore
     async fn acquire_semaphore(&self, span: Option<Span>) -> InstrumentedAsyncOwnedSemaphorePermit;
+
+    /// Cancel the running query with the given ID, aborting its execution.
+    ///
+    /// Returns false if no such query is running.
+    fn cancel_query(&self, query_id: &str) -> bool;
 }
 
 pub use error::datafusion_error_to_tonic_code;
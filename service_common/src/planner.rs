@@ -11,6 +11,8 @@ use iox_query::{
 
 pub use datafusion::error::{DataFusionError as Error, Result};
 use iox_query::frontend::influxql::InfluxQLQueryPlanner;
+use iox_query::frontend::promql::PromQLQueryPlanner;
+use iox_query::plan::promql::EvalRange;
 use predicate::rpc_predicate::InfluxRpcPredicate;
 
 /// Query planner that plans queries on a separate threadpool.
@@ -60,6 +62,24 @@ impl Planner {
             .await
     }
 
+    /// Plan a PromQL expression evaluated at every step of `eval` against
+    /// the data in `database`, and return a DataFusion physical execution
+    /// plan.
+    pub async fn promql(
+        &self,
+        database: Arc<dyn QueryNamespace>,
+        query: impl Into<String> + Send,
+        eval: EvalRange,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        let planner = PromQLQueryPlanner::new();
+        let query = query.into();
+        let ctx = self.ctx.child_ctx("planner promql");
+
+        self.ctx
+            .run(async move { planner.query(database, &query, eval, &ctx).await })
+            .await
+    }
+
     /// Creates a plan as described on
     /// [`InfluxRpcPlanner::table_names`], on a separate threadpool
     pub async fn table_names<N>(
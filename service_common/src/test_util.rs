@@ -73,4 +73,8 @@ impl QueryNamespaceProvider for TestDatabaseStore {
             .await
             .unwrap()
     }
+
+    fn cancel_query(&self, _query_id: &str) -> bool {
+        false
+    }
 }
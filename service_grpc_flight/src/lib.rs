@@ -5,7 +5,12 @@ mod request;
 use arrow::error::ArrowError;
 use data_types::NamespaceNameError;
 use datafusion::{error::DataFusionError, physical_plan::ExecutionPlan};
-use futures::{ready, stream::BoxStream, Stream, StreamExt, TryStreamExt};
+use futures::{
+    future::AbortHandle,
+    ready,
+    stream::{Abortable, BoxStream},
+    Stream, StreamExt, TryStreamExt,
+};
 use generated_types::influxdata::iox::querier::v1 as proto;
 use iox_arrow_flight::{
     flight_descriptor::DescriptorType,
@@ -23,7 +28,16 @@ use prost::Message;
 use request::{IoxGetRequest, RunQuery};
 use service_common::{datafusion_error_to_tonic_code, planner::Planner, QueryNamespaceProvider};
 use snafu::{ResultExt, Snafu};
-use std::{fmt::Debug, pin::Pin, sync::Arc, task::Poll, time::Instant};
+use std::{
+    fmt::Debug,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    task::Poll,
+    time::Instant,
+};
 use tonic::{Request, Response, Streaming};
 use trace::{ctx::SpanContext, span::SpanExt};
 use trace_http::ctx::{RequestLogContext, RequestLogContextExt};
@@ -36,6 +50,12 @@ use tracker::InstrumentedAsyncOwnedSemaphorePermit;
 /// for discussion on adding support to FlightSQL itself.
 const IOX_FLIGHT_SQL_NAMESPACE_HEADER: &str = "iox-namespace-name";
 
+/// The `do_action` type used to cancel a running query.
+///
+/// The body of the action is the UTF-8 encoded ID of the query, as listed in
+/// the `system.queries` table.
+const CANCEL_QUERY_ACTION: &str = "cancel_query";
+
 #[allow(clippy::enum_variant_names)]
 #[derive(Debug, Snafu)]
 pub enum Error {
@@ -97,6 +117,18 @@ pub enum Error {
 
     #[snafu(display("Unsupported message type: {}", description))]
     UnsupportedMessageType { description: String },
+
+    #[snafu(display("Unsupported action type: {}", action_type))]
+    UnsupportedAction { action_type: String },
+
+    #[snafu(display("Invalid query ID: {}", source))]
+    InvalidQueryId { source: std::str::Utf8Error },
+
+    #[snafu(display("No running query with ID {}", query_id))]
+    QueryNotRunning { query_id: String },
+
+    #[snafu(display("Query was cancelled"))]
+    Cancelled,
 }
 pub type Result<T, E = Error> = std::result::Result<T, E>;
 
@@ -111,6 +143,9 @@ impl From<Error> for tonic::Status {
             Error::NamespaceNotFound { .. }
             | Error::InvalidTicket { .. }
             | Error::InvalidQuery { .. }
+            | Error::InvalidQueryId { .. }
+            | Error::QueryNotRunning { .. }
+            | Error::Cancelled
             // TODO(edd): this should be `debug`. Keeping at info while IOx in early development
             | Error::InvalidNamespaceName { .. } => info!(e=%err, msg),
             Error::Query { .. } => info!(e=%err, msg),
@@ -123,6 +158,7 @@ impl From<Error> for tonic::Status {
             | Error::DeserializationTypeKnown { .. }
             | Error::InternalCreatingTicket { .. }
                 | Error::UnsupportedMessageType { .. }
+                | Error::UnsupportedAction { .. }
             => {
                 warn!(e=%err, msg)
             }
@@ -138,8 +174,9 @@ impl Error {
         let msg = self.to_string();
 
         let code = match self {
-            Self::NamespaceNotFound { .. } => tonic::Code::NotFound,
+            Self::NamespaceNotFound { .. } | Self::QueryNotRunning { .. } => tonic::Code::NotFound,
             Self::InvalidTicket { .. }
+            | Self::InvalidQueryId { .. }
             | Self::InvalidQuery { .. }
             | Self::Serialization { .. }
             | Self::Deserialization { .. }
@@ -150,7 +187,10 @@ impl Error {
             Self::Planning { source, .. } | Self::Query { source, .. } => {
                 datafusion_error_to_tonic_code(&source)
             }
-            Self::UnsupportedMessageType { .. } => tonic::Code::Unimplemented,
+            Self::UnsupportedMessageType { .. } | Self::UnsupportedAction { .. } => {
+                tonic::Code::Unimplemented
+            }
+            Self::Cancelled => tonic::Code::Cancelled,
             Self::InternalCreatingTicket { .. } | Self::Optimize { .. } => tonic::Code::Internal,
         };
 
@@ -200,24 +240,29 @@ where
             .ok_or_else(|| tonic::Status::not_found(format!("Unknown namespace: {namespace}")))?;
 
         let ctx = db.new_query_context(span_ctx);
-        let (query_completed_token, physical_plan) = match query {
+        let (query_type, query_text, mut query_completed_token, physical_plan) = match query {
             RunQuery::Sql(sql_query) => {
                 let token = db.record_query(&ctx, "sql", Box::new(sql_query.clone()));
                 let plan = Planner::new(&ctx)
                     .sql(sql_query)
                     .await
                     .context(PlanningSnafu)?;
-                (token, plan)
+                ("sql", sql_query, token, plan)
             }
             RunQuery::InfluxQL(sql_query) => {
                 let token = db.record_query(&ctx, "influxql", Box::new(sql_query.clone()));
                 let plan = Planner::new(&ctx)
-                    .influxql(db, sql_query)
+                    .influxql(Arc::clone(&db) as _, sql_query)
                     .await
                     .context(PlanningSnafu)?;
-                (token, plan)
+                ("influxql", sql_query, token, plan)
             }
         };
+        let physical_plan = db
+            .cached_plan(&ctx, query_type, query_text, physical_plan)
+            .await
+            .context(PlanningSnafu)?;
+        query_completed_token.set_planned();
 
         let output =
             GetStream::new(ctx, physical_plan, namespace, query_completed_token, permit).await?;
@@ -356,16 +401,42 @@ where
 
     async fn do_action(
         &self,
-        _request: Request<Action>,
+        request: Request<Action>,
     ) -> Result<Response<Self::DoActionStream>, tonic::Status> {
-        Err(tonic::Status::unimplemented("Not yet implemented"))
+        let action = request.into_inner();
+
+        match action.r#type.as_str() {
+            CANCEL_QUERY_ACTION => {
+                let query_id = std::str::from_utf8(&action.body).context(InvalidQueryIdSnafu)?;
+                if !self.server.cancel_query(query_id) {
+                    return Err(Error::QueryNotRunning {
+                        query_id: query_id.to_string(),
+                    }
+                    .into());
+                }
+                info!(%query_id, "Cancelled query via flight do_action");
+
+                let output = futures::stream::empty();
+                Ok(Response::new(Box::pin(output) as Self::DoActionStream))
+            }
+            action_type => Err(Error::UnsupportedAction {
+                action_type: action_type.to_string(),
+            }
+            .into()),
+        }
     }
 
     async fn list_actions(
         &self,
         _request: Request<Empty>,
     ) -> Result<Response<Self::ListActionsStream>, tonic::Status> {
-        Err(tonic::Status::unimplemented("Not yet implemented"))
+        let actions = vec![Ok(ActionType {
+            r#type: CANCEL_QUERY_ACTION.to_string(),
+            description: "Cancel a running query. The body is the ID of the query".to_string(),
+        })];
+
+        let output = futures::stream::iter(actions);
+        Ok(Response::new(Box::pin(output) as Self::ListActionsStream))
     }
 
     async fn do_exchange(
@@ -461,11 +532,21 @@ where
 /// Wrapper over a FlightDataEncodeStream that adds IOx specfic
 /// metadata and records completion
 struct GetStream {
-    inner: BoxStream<'static, Result<FlightData, tonic::Status>>,
+    /// The encoded query results, or `None` once the query completed or was
+    /// cancelled (which releases all resources used by the execution).
+    inner: Option<Abortable<BoxStream<'static, Result<FlightData, tonic::Status>>>>,
+    ctx: IOxSessionContext,
     #[allow(dead_code)]
     permit: InstrumentedAsyncOwnedSemaphorePermit,
     query_completed_token: QueryCompletedToken,
-    done: bool,
+    output: Arc<OutputCounter>,
+}
+
+/// Number of rows and bytes of the record batches returned by a query.
+#[derive(Debug, Default)]
+struct OutputCounter {
+    rows: AtomicUsize,
+    bytes: AtomicUsize,
 }
 
 impl GetStream {
@@ -473,7 +554,7 @@ impl GetStream {
         ctx: IOxSessionContext,
         physical_plan: Arc<dyn ExecutionPlan>,
         namespace_name: String,
-        query_completed_token: QueryCompletedToken,
+        mut query_completed_token: QueryCompletedToken,
         permit: InstrumentedAsyncOwnedSemaphorePermit,
     ) -> Result<Self, tonic::Status> {
         let app_metadata = proto::AppMetadata {};
@@ -483,12 +564,25 @@ impl GetStream {
 
         let schema = physical_plan.schema();
 
+        let output = Arc::new(OutputCounter::default());
+        let output_captured = Arc::clone(&output);
         let query_results = ctx
             .execute_stream(Arc::clone(&physical_plan))
             .await
             .context(QuerySnafu {
                 namespace_name: namespace_name.clone(),
             })?
+            .inspect_ok(move |batch| {
+                output_captured
+                    .rows
+                    .fetch_add(batch.num_rows(), Ordering::Relaxed);
+                let bytes = batch
+                    .columns()
+                    .iter()
+                    .map(|col| col.get_array_memory_size())
+                    .sum();
+                output_captured.bytes.fetch_add(bytes, Ordering::Relaxed);
+            })
             // Convert from Arrow errors to tonic errors
             .map_err(move |e| {
                 Error::Query {
@@ -499,13 +593,18 @@ impl GetStream {
             })
             .boxed();
 
-        let inner = builder.build(schema, query_results);
+        // queries that can not be cancelled get a registration that is never aborted
+        let abort_registration = query_completed_token
+            .take_abort_registration()
+            .unwrap_or_else(|| AbortHandle::new_pair().1);
+        let inner = Abortable::new(builder.build(schema, query_results), abort_registration);
 
         Ok(Self {
-            inner,
+            inner: Some(inner),
+            ctx,
             permit,
             query_completed_token,
-            done: false,
+            output,
         })
     }
 }
@@ -517,30 +616,45 @@ impl Stream for GetStream {
         mut self: Pin<&mut Self>,
         cx: &mut std::task::Context<'_>,
     ) -> std::task::Poll<Option<Self::Item>> {
-        loop {
-            if self.done {
-                return Poll::Ready(None);
-            }
+        let Some(inner) = self.inner.as_mut() else {
+            return Poll::Ready(None);
+        };
 
-            let res = ready!(self.inner.poll_next_unpin(cx));
-            match res {
-                None => {
-                    self.done = true;
+        let res = ready!(inner.poll_next_unpin(cx));
+        match res {
+            None => {
+                let cancelled = inner.is_aborted();
+                // drop the query execution right away
+                self.inner = None;
+
+                if cancelled {
+                    Poll::Ready(Some(Err(Error::Cancelled.into())))
+                } else {
                     // if we get here, all is good
                     self.query_completed_token.set_success();
+                    Poll::Ready(None)
                 }
-                Some(Ok(data)) => {
-                    return Poll::Ready(Some(Ok(data)));
-                }
-                Some(Err(e)) => {
-                    self.done = true;
-                    return Poll::Ready(Some(Err(e)));
-                }
+            }
+            Some(Ok(data)) => Poll::Ready(Some(Ok(data))),
+            Some(Err(e)) => {
+                self.inner = None;
+                Poll::Ready(Some(Err(e)))
             }
         }
     }
 }
 
+impl Drop for GetStream {
+    fn drop(&mut self) {
+        // record statistics before the token reports the query as completed
+        let rows = self.output.rows.load(Ordering::Relaxed);
+        let bytes = self.output.bytes.load(Ordering::Relaxed);
+        self.query_completed_token.record_output(rows, bytes);
+        self.query_completed_token
+            .set_peak_memory(self.ctx.peak_memory());
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use futures::Future;
@@ -683,6 +797,103 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_cancel_query() {
+        let test_storage = Arc::new(TestDatabaseStore::new());
+        let db = test_storage.db_or_create("my_db").await;
+
+        let ctx = db.new_query_context(None);
+        let plan = ctx.prepare_sql("SELECT 1").await.unwrap();
+        let permit = test_storage.acquire_semaphore(None).await;
+
+        let completed = Arc::new(std::sync::Mutex::new(None));
+        let completed_captured = Arc::clone(&completed);
+        let (abort_handle, abort_registration) = AbortHandle::new_pair();
+        let token = QueryCompletedToken::new_with_stats(move |success, stats| {
+            *completed_captured.lock().unwrap() = Some((success, stats));
+        })
+        .with_abort_registration(abort_registration, || {});
+
+        let mut stream = GetStream::new(ctx, plan, "my_db".to_string(), token, permit)
+            .await
+            .unwrap();
+
+        abort_handle.abort();
+        let err = stream.next().await.unwrap().unwrap_err();
+        assert_eq!(err.code(), tonic::Code::Cancelled);
+        assert!(stream.next().await.is_none());
+
+        drop(stream);
+        let (success, stats) = completed.lock().unwrap().take().unwrap();
+        assert!(!success);
+        assert_eq!(stats.rows, 0);
+    }
+
+    #[tokio::test]
+    async fn test_query_stats() {
+        let test_storage = Arc::new(TestDatabaseStore::new());
+        let db = test_storage.db_or_create("my_db").await;
+
+        let ctx = db.new_query_context(None);
+        let plan = ctx.prepare_sql("SELECT 1").await.unwrap();
+        let permit = test_storage.acquire_semaphore(None).await;
+
+        let completed = Arc::new(std::sync::Mutex::new(None));
+        let completed_captured = Arc::clone(&completed);
+        let token = QueryCompletedToken::new_with_stats(move |success, stats| {
+            *completed_captured.lock().unwrap() = Some((success, stats));
+        });
+
+        let stream = GetStream::new(ctx, plan, "my_db".to_string(), token, permit)
+            .await
+            .unwrap();
+        stream.try_collect::<Vec<_>>().await.unwrap();
+
+        let (success, stats) = completed.lock().unwrap().take().unwrap();
+        assert!(success);
+        assert_eq!(stats.rows, 1);
+        assert!(stats.bytes > 0);
+        assert!(stats.peak_memory.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_do_action() {
+        let service = FlightService {
+            server: Arc::new(TestDatabaseStore::new()),
+        };
+
+        let actions: Vec<_> = service
+            .list_actions(tonic::Request::new(Empty {}))
+            .await
+            .unwrap()
+            .into_inner()
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(actions.len(), 1);
+        assert_eq!(actions[0].r#type, CANCEL_QUERY_ACTION);
+
+        let action = Action {
+            r#type: CANCEL_QUERY_ACTION.to_string(),
+            body: b"not-running".to_vec().into(),
+        };
+        let err = service
+            .do_action(tonic::Request::new(action))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), tonic::Code::NotFound);
+
+        let action = Action {
+            r#type: "explode".to_string(),
+            body: Default::default(),
+        };
+        let err = service
+            .do_action(tonic::Request::new(action))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), tonic::Code::Unimplemented);
+    }
+
     /// Assert that given future is pending.
     ///
     /// This will try to poll the future a bit to ensure that it is not stuck in tokios task preemption.
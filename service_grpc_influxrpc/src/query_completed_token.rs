@@ -3,15 +3,29 @@ use std::{
     task::{Context, Poll},
 };
 
-use futures::{ready, Stream, StreamExt};
+use futures::{future::AbortHandle, ready, stream::Abortable, Stream, StreamExt};
 use iox_query::QueryCompletedToken;
 
+/// The error yielded by a [`QueryCompletedTokenStream`] when its query is
+/// cancelled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QueryCancelled;
+
+impl From<QueryCancelled> for tonic::Status {
+    fn from(_: QueryCancelled) -> Self {
+        Self::cancelled("query cancelled")
+    }
+}
+
 /// Wraps an inner query stream, calling the `QueryCompletedToken::set_success` on success
+///
+/// The stream is aborted, yielding a [`QueryCancelled`] error, if the query is
+/// cancelled.
 pub struct QueryCompletedTokenStream<S, T, E>
 where
     S: Stream<Item = Result<T, E>> + Unpin,
 {
-    inner: S,
+    inner: Abortable<S>,
     token: QueryCompletedToken,
     found_err: bool,
 }
@@ -20,9 +34,14 @@ impl<S, T, E> QueryCompletedTokenStream<S, T, E>
 where
     S: Stream<Item = Result<T, E>> + Unpin,
 {
-    pub fn new(inner: S, token: QueryCompletedToken) -> Self {
+    pub fn new(inner: S, mut token: QueryCompletedToken) -> Self {
+        // queries that can not be cancelled get a registration that is never aborted
+        let abort_registration = token
+            .take_abort_registration()
+            .unwrap_or_else(|| AbortHandle::new_pair().1);
+
         Self {
-            inner,
+            inner: Abortable::new(inner, abort_registration),
             token,
             found_err: false,
         }
@@ -32,6 +51,7 @@ where
 impl<S, T, E> Stream for QueryCompletedTokenStream<S, T, E>
 where
     S: Stream<Item = Result<T, E>> + Unpin,
+    E: From<QueryCancelled>,
 {
     type Item = Result<T, E>;
 
@@ -39,6 +59,10 @@ where
         let this = &mut *self;
 
         match ready!(this.inner.poll_next_unpin(cx)) {
+            None if this.inner.is_aborted() && !this.found_err => {
+                this.found_err = true;
+                Poll::Ready(Some(Err(QueryCancelled.into())))
+            }
             None => {
                 if !this.found_err {
                     this.token.set_success();
@@ -66,11 +90,25 @@ mod tests {
 
     use super::*;
 
+    #[derive(Debug, PartialEq, Eq)]
+    enum TestError {
+        Query,
+        Cancelled,
+    }
+
+    impl From<QueryCancelled> for TestError {
+        fn from(_: QueryCancelled) -> Self {
+            Self::Cancelled
+        }
+    }
+
     #[tokio::test]
     async fn test_empty() {
         let (res, token) = token();
-        let stream =
-            QueryCompletedTokenStream::new(futures::stream::empty::<Result<(), ()>>(), token);
+        let stream = QueryCompletedTokenStream::new(
+            futures::stream::empty::<Result<(), TestError>>(),
+            token,
+        );
 
         assert_eq!(stream.collect::<Vec<_>>().await, vec![],);
         assert_eq!(*res.lock(), Some(true));
@@ -79,19 +117,39 @@ mod tests {
     #[tokio::test]
     async fn test_not_finished() {
         let (res, token) = token();
-        QueryCompletedTokenStream::new(futures::stream::empty::<Result<(), ()>>(), token);
+        QueryCompletedTokenStream::new(futures::stream::empty::<Result<(), TestError>>(), token);
         assert_eq!(*res.lock(), Some(false));
     }
 
     #[tokio::test]
     async fn test_err() {
         let (res, token) = token();
-        let stream =
-            QueryCompletedTokenStream::new(futures::stream::iter([Ok(()), Err(()), Ok(())]), token);
+        let stream = QueryCompletedTokenStream::new(
+            futures::stream::iter([Ok(()), Err(TestError::Query), Ok(())]),
+            token,
+        );
+
+        assert_eq!(
+            stream.collect::<Vec<_>>().await,
+            vec![Ok(()), Err(TestError::Query), Ok(())],
+        );
+        assert_eq!(*res.lock(), Some(false));
+    }
+
+    #[tokio::test]
+    async fn test_cancelled() {
+        let (res, token) = token();
+        let (abort_handle, abort_registration) = AbortHandle::new_pair();
+        let token = token.with_abort_registration(abort_registration, || {});
+        let stream = QueryCompletedTokenStream::new(
+            futures::stream::pending::<Result<(), TestError>>(),
+            token,
+        );
 
+        abort_handle.abort();
         assert_eq!(
             stream.collect::<Vec<_>>().await,
-            vec![Ok(()), Err(()), Ok(())],
+            vec![Err(TestError::Cancelled)],
         );
         assert_eq!(*res.lock(), Some(false));
     }
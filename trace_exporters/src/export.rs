@@ -24,6 +24,16 @@ const CHANNEL_SIZE: usize = 100_000;
 #[async_trait]
 pub trait AsyncExport: Send + 'static {
     async fn export(&mut self, span: Vec<Span>);
+
+    /// The maximum number of spans passed to a single call to
+    /// [`AsyncExport::export`].
+    ///
+    /// Spans already queued when the background worker is ready to export are
+    /// batched up to this limit. Defaults to 1, exporting each span
+    /// individually.
+    fn max_batch_size(&self) -> usize {
+        1
+    }
 }
 
 /// `AsyncExporter` wraps a `AsyncExport` and sinks spans to it
@@ -34,7 +44,8 @@ pub trait AsyncExport: Send + 'static {
 /// If this worker cannot keep up, and this queue fills up, spans will
 /// be dropped and warnings logged
 ///
-/// Note: Currently this does not batch spans (#2392)
+/// Spans waiting in the queue are batched up to the
+/// [`AsyncExport::max_batch_size`] of the exporter.
 #[derive(Debug)]
 pub struct AsyncExporter {
     join: Shared<BoxFuture<'static, Result<(), Arc<JoinError>>>>,
@@ -103,9 +114,11 @@ async fn background_worker<T: AsyncExport>(
     mut exporter: T,
     mut receiver: mpsc::Receiver<Option<Span>>,
 ) {
+    let max_batch_size = exporter.max_batch_size().max(1);
+
     loop {
-        match receiver.recv().await {
-            Some(Some(span)) => exporter.export(vec![span]).await,
+        let span = match receiver.recv().await {
+            Some(Some(span)) => span,
             Some(None) => {
                 info!("async exporter shut down");
                 break;
@@ -114,6 +127,27 @@ async fn background_worker<T: AsyncExport>(
                 error!("sender-side of async exporter dropped without waiting for shut down");
                 break;
             }
+        };
+
+        // Batch any other spans already waiting in the queue.
+        let mut batch = vec![span];
+        let mut shutdown = false;
+        while batch.len() < max_batch_size {
+            match receiver.try_recv() {
+                Ok(Some(span)) => batch.push(span),
+                Ok(None) => {
+                    shutdown = true;
+                    break;
+                }
+                Err(_) => break,
+            }
+        }
+
+        exporter.export(batch).await;
+
+        if shutdown {
+            info!("async exporter shut down");
+            break;
         }
     }
 }
@@ -182,4 +216,40 @@ mod tests {
         assert_eq!(s2.ctx.span_id.get(), r3.ctx.span_id.get());
         assert_eq!(s2.ctx.trace_id.get(), r3.ctx.trace_id.get());
     }
+
+    #[derive(Debug)]
+    struct BatchSizeExporter {
+        batches: Arc<std::sync::Mutex<Vec<usize>>>,
+    }
+
+    #[async_trait]
+    impl AsyncExport for BatchSizeExporter {
+        async fn export(&mut self, batch: Vec<Span>) {
+            self.batches.lock().unwrap().push(batch.len());
+        }
+
+        fn max_batch_size(&self) -> usize {
+            2
+        }
+    }
+
+    #[tokio::test]
+    async fn test_exporter_batching() {
+        let batches = Arc::new(std::sync::Mutex::new(vec![]));
+        let exporter = AsyncExporter::new(BatchSizeExporter {
+            batches: Arc::clone(&batches),
+        });
+
+        let root = SpanContext::new(Arc::new(trace::LogTraceCollector::new()));
+
+        // Queue all spans before the background worker runs.
+        for _ in 0..3 {
+            exporter.export(root.child("foo"));
+        }
+        exporter.drain().await.unwrap();
+
+        // Queued spans are batched up to the max batch size, and spans queued
+        // before the shutdown are still exported.
+        assert_eq!(*batches.lock().unwrap(), [2, 1]);
+    }
 }
@@ -11,6 +11,7 @@
 
 use crate::export::AsyncExporter;
 use crate::jaeger::JaegerAgentExporter;
+use crate::otlp::OtlpExporter;
 use iox_time::SystemProvider;
 use jaeger::JaegerTag;
 use snafu::Snafu;
@@ -20,8 +21,11 @@ use std::sync::Arc;
 pub mod export;
 
 mod jaeger;
+mod otlp;
 mod rate_limiter;
 
+pub use otlp::{OtlpProtocol, OtlpResourceAttribute};
+
 /// Auto-generated thrift code
 #[allow(
     dead_code,
@@ -47,7 +51,7 @@ pub const DEFAULT_JAEGER_TRACE_CONTEXT_HEADER_NAME: &str = "uber-trace-id";
 pub struct TracingConfig {
     /// Tracing: exporter type
     ///
-    /// Can be one of: none, jaeger
+    /// Can be one of: none, jaeger, otlp
     #[clap(
         long = "traces-exporter",
         env = "TRACES_EXPORTER",
@@ -138,6 +142,71 @@ pub struct TracingConfig {
         action
     )]
     pub traces_jaeger_max_msgs_per_second: NonZeroU64,
+
+    /// Tracing: OpenTelemetry collector endpoint.
+    ///
+    /// For the "http/protobuf" protocol, spans are sent to the `/v1/traces` path of this
+    /// endpoint.
+    ///
+    /// Only used if `--traces-exporter` is "otlp".
+    #[clap(
+        long = "traces-exporter-otlp-endpoint",
+        env = "TRACES_EXPORTER_OTLP_ENDPOINT",
+        default_value = "http://localhost:4317",
+        action
+    )]
+    pub traces_exporter_otlp_endpoint: String,
+
+    /// Tracing: OTLP transport protocol.
+    ///
+    /// Can be one of: grpc, http/protobuf
+    ///
+    /// Only used if `--traces-exporter` is "otlp".
+    #[clap(
+        long = "traces-exporter-otlp-protocol",
+        env = "TRACES_EXPORTER_OTLP_PROTOCOL",
+        default_value = "grpc",
+        action
+    )]
+    pub traces_exporter_otlp_protocol: OtlpProtocol,
+
+    /// Tracing: OTLP service name, sent as the `service.name` resource attribute.
+    ///
+    /// Only used if `--traces-exporter` is "otlp".
+    #[clap(
+        long = "traces-exporter-otlp-service-name",
+        env = "TRACES_EXPORTER_OTLP_SERVICE_NAME",
+        default_value = "iox-conductor",
+        action
+    )]
+    pub traces_exporter_otlp_service_name: String,
+
+    /// Tracing: set of key=value pairs describing this process, sent as OTLP resource
+    /// attributes.
+    ///
+    /// Use a comma-delimited string to set multiple pairs: env=prod,region=eu-1
+    ///
+    /// Only used if `--traces-exporter` is "otlp".
+    #[clap(
+        long = "traces-otlp-resource-attributes",
+        env = "TRACES_EXPORTER_OTLP_RESOURCE_ATTRIBUTES",
+        value_delimiter = ',',
+        action
+    )]
+    pub traces_otlp_resource_attributes: Option<Vec<OtlpResourceAttribute>>,
+
+    /// Tracing: Maximum number of export requests sent to an OTLP collector, per second.
+    ///
+    /// Each request contains a batch of spans.
+    ///
+    /// Only used if `--traces-exporter` is "otlp".
+    #[clap(
+        long = "traces-otlp-max-requests-per-second",
+        env = "TRACES_OTLP_MAX_REQUESTS_PER_SECOND",
+        default_value = "100",
+        action
+    )]
+    pub traces_otlp_max_requests_per_second: NonZeroU64,
 }
 
 impl TracingConfig {
@@ -145,6 +214,7 @@ impl TracingConfig {
         match self.traces_exporter {
             TracesExporter::None => Ok(None),
             TracesExporter::Jaeger => Ok(Some(jaeger_exporter(self)?)),
+            TracesExporter::Otlp => Ok(Some(otlp_exporter(self)?)),
         }
     }
 }
@@ -153,6 +223,7 @@ impl TracingConfig {
 pub enum TracesExporter {
     None,
     Jaeger,
+    Otlp,
 }
 
 impl std::str::FromStr for TracesExporter {
@@ -162,8 +233,9 @@ impl std::str::FromStr for TracesExporter {
         match s.to_ascii_lowercase().as_str() {
             "none" => Ok(Self::None),
             "jaeger" => Ok(Self::Jaeger),
+            "otlp" => Ok(Self::Otlp),
             _ => Err(format!(
-                "Invalid traces exporter '{}'. Valid options: none, jaeger",
+                "Invalid traces exporter '{}'. Valid options: none, jaeger, otlp",
                 s
             )),
         }
@@ -177,6 +249,15 @@ pub enum Error {
 
     #[snafu(context(false))]
     IOError { source: std::io::Error },
+
+    #[snafu(display("Invalid OTLP endpoint {}: {}", endpoint, source))]
+    InvalidEndpoint {
+        endpoint: String,
+        source: tonic::transport::Error,
+    },
+
+    #[snafu(display("Failed to create HTTP client: {}", source))]
+    HttpClient { source: reqwest::Error },
 }
 
 pub type Result<T, E = Error> = std::result::Result<T, E>;
@@ -203,3 +284,20 @@ fn jaeger_exporter(config: &TracingConfig) -> Result<Arc<AsyncExporter>> {
 
     Ok(Arc::new(AsyncExporter::new(jaeger)))
 }
+
+fn otlp_exporter(config: &TracingConfig) -> Result<Arc<AsyncExporter>> {
+    let mut otlp = OtlpExporter::new(
+        config.traces_exporter_otlp_protocol,
+        config.traces_exporter_otlp_endpoint.trim(),
+        config.traces_exporter_otlp_service_name.clone(),
+        Arc::new(SystemProvider::new()),
+        config.traces_otlp_max_requests_per_second,
+    )?;
+
+    // Use any specified static resource attributes.
+    if let Some(attributes) = &config.traces_otlp_resource_attributes {
+        otlp = otlp.with_resource_attributes(attributes);
+    }
+
+    Ok(Arc::new(AsyncExporter::new(otlp)))
+}
@@ -0,0 +1,501 @@
+use std::{num::NonZeroU64, str::FromStr, sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use generated_types::opentelemetry::proto::{
+    collector::trace::v1::{
+        trace_service_client::TraceServiceClient, ExportTraceServiceRequest,
+        ExportTraceServiceResponse,
+    },
+    common::v1::InstrumentationScope,
+    resource::v1::Resource,
+    trace::v1::{ResourceSpans, ScopeSpans},
+};
+use iox_time::TimeProvider;
+use observability_deps::tracing::*;
+use prost::Message;
+use snafu::ResultExt;
+use tonic::transport::{Channel, Endpoint};
+use trace::span::{MetaValue, Span};
+
+use crate::{export::AsyncExport, rate_limiter::RateLimiter};
+
+mod span;
+
+/// The path OTLP/HTTP collectors accept trace exports on.
+const HTTP_TRACES_PATH: &str = "/v1/traces";
+
+/// The maximum number of spans sent in a single export request.
+const MAX_BATCH_SIZE: usize = 512;
+
+/// The time after which an export request is abandoned.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The transport protocol used to send spans to an OTLP collector.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OtlpProtocol {
+    /// Protobuf-encoded spans sent over gRPC.
+    Grpc,
+    /// Protobuf-encoded spans sent in a HTTP POST request.
+    HttpProtobuf,
+}
+
+impl FromStr for OtlpProtocol {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "grpc" => Ok(Self::Grpc),
+            "http/protobuf" | "http" => Ok(Self::HttpProtobuf),
+            _ => Err(format!(
+                "Invalid OTLP protocol '{}'. Valid options: grpc, http/protobuf",
+                s
+            )),
+        }
+    }
+}
+
+/// A key=value pair describing the resource (process) emitting spans.
+#[derive(Debug, Clone)]
+pub struct OtlpResourceAttribute {
+    key: String,
+    value: String,
+}
+
+impl OtlpResourceAttribute {
+    /// Create a new resource attribute.
+    pub fn new(key: impl Into<String>, value: impl Into<String>) -> Self {
+        Self {
+            key: key.into(),
+            value: value.into(),
+        }
+    }
+}
+
+impl FromStr for OtlpResourceAttribute {
+    type Err = Box<dyn std::error::Error + Send + Sync + 'static>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts = s.split('=').collect::<Vec<_>>();
+        match *parts {
+            [key, value] if !key.is_empty() && !value.is_empty() => Ok(Self::new(key, value)),
+            _ => Err(format!("invalid key=value pair ({})", s).into()),
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Transport {
+    Grpc(TraceServiceClient<Channel>),
+    Http {
+        client: reqwest::Client,
+        url: String,
+    },
+}
+
+/// `OtlpExporter` receives span data and sends it to an OpenTelemetry
+/// collector using the OTLP protocol over gRPC or HTTP.
+///
+/// Export failures are logged and the spans dropped - they are not retried.
+#[derive(Debug)]
+pub struct OtlpExporter {
+    transport: Transport,
+
+    /// Describes this process, and is sent with every batch of spans.
+    resource: Resource,
+
+    /// Rate limiter
+    rate_limiter: RateLimiter,
+}
+
+impl OtlpExporter {
+    /// Create a new exporter sending spans to the collector at `endpoint`
+    /// (for example `http://localhost:4317`), identifying this process as
+    /// `service_name`.
+    ///
+    /// For OTLP/HTTP, spans are sent to the `/v1/traces` path of `endpoint`.
+    ///
+    /// The gRPC connection is established lazily when the first spans are
+    /// exported.
+    pub fn new(
+        protocol: OtlpProtocol,
+        endpoint: &str,
+        service_name: String,
+        time_provider: Arc<dyn TimeProvider>,
+        max_requests_per_second: NonZeroU64,
+    ) -> super::Result<Self> {
+        info!(%endpoint, %service_name, ?protocol, "Creating OTLP tracing exporter");
+
+        let transport = match protocol {
+            OtlpProtocol::Grpc => {
+                let channel = Endpoint::from_shared(endpoint.to_string())
+                    .context(super::InvalidEndpointSnafu { endpoint })?
+                    .timeout(REQUEST_TIMEOUT)
+                    .connect_lazy();
+                Transport::Grpc(TraceServiceClient::new(channel))
+            }
+            OtlpProtocol::HttpProtobuf => Transport::Http {
+                client: reqwest::Client::builder()
+                    .timeout(REQUEST_TIMEOUT)
+                    .build()
+                    .context(super::HttpClientSnafu)?,
+                url: format!("{}{}", endpoint.trim_end_matches('/'), HTTP_TRACES_PATH),
+            },
+        };
+
+        Ok(Self {
+            transport,
+            resource: Resource {
+                attributes: vec![span::key_value(
+                    "service.name".to_string(),
+                    MetaValue::String(service_name.into()),
+                )],
+                dropped_attributes_count: 0,
+            },
+            rate_limiter: RateLimiter::new(max_requests_per_second, time_provider),
+        })
+    }
+
+    /// Describe this process with the specified static resource attributes,
+    /// in addition to the service name.
+    pub fn with_resource_attributes(mut self, attributes: &[OtlpResourceAttribute]) -> Self {
+        debug!(?attributes, "setting OTLP resource attributes");
+        self.resource.attributes.extend(
+            attributes
+                .iter()
+                .cloned()
+                .map(|a| span::key_value(a.key, MetaValue::String(a.value.into()))),
+        );
+        self
+    }
+
+    fn make_request(&self, spans: Vec<Span>) -> ExportTraceServiceRequest {
+        ExportTraceServiceRequest {
+            resource_spans: vec![ResourceSpans {
+                resource: Some(self.resource.clone()),
+                scope_spans: vec![ScopeSpans {
+                    scope: Some(InstrumentationScope {
+                        name: "iox".to_string(),
+                        version: String::new(),
+                        attributes: vec![],
+                        dropped_attributes_count: 0,
+                    }),
+                    spans: spans.into_iter().map(Into::into).collect(),
+                    schema_url: String::new(),
+                }],
+                schema_url: String::new(),
+            }],
+        }
+    }
+
+    async fn send(
+        &mut self,
+        request: ExportTraceServiceRequest,
+    ) -> Result<ExportTraceServiceResponse, String> {
+        match &mut self.transport {
+            Transport::Grpc(client) => client
+                .export(request)
+                .await
+                .map(|r| r.into_inner())
+                .map_err(|e| e.to_string()),
+            Transport::Http { client, url } => {
+                let resp = client
+                    .post(url.as_str())
+                    .header(reqwest::header::CONTENT_TYPE, "application/x-protobuf")
+                    .body(request.encode_to_vec())
+                    .send()
+                    .await
+                    .and_then(|r| r.error_for_status())
+                    .map_err(|e| e.to_string())?;
+
+                let body = resp.bytes().await.map_err(|e| e.to_string())?;
+                ExportTraceServiceResponse::decode(body).map_err(|e| e.to_string())
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl AsyncExport for OtlpExporter {
+    async fn export(&mut self, spans: Vec<Span>) {
+        let n_spans = spans.len();
+        let request = self.make_request(spans);
+
+        // Bound the request rate to avoid overwhelming the collector when a
+        // large volume of spans is generated.
+        self.rate_limiter.send().await;
+
+        match self.send(request).await {
+            Ok(ExportTraceServiceResponse {
+                partial_success: Some(p),
+            }) if p.rejected_spans > 0 => {
+                warn!(
+                    rejected_spans = p.rejected_spans,
+                    error = %p.error_message,
+                    "OTLP collector rejected spans"
+                );
+            }
+            Ok(_) => {}
+            Err(e) => error!(%e, n_spans, "error exporting spans to OTLP collector"),
+        }
+    }
+
+    fn max_batch_size(&self) -> usize {
+        MAX_BATCH_SIZE
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+    use generated_types::opentelemetry::proto::{
+        collector::trace::v1::trace_service_server::{TraceService, TraceServiceServer},
+        common::v1::any_value,
+        trace::v1::{self as otlp, status::StatusCode},
+    };
+    use iox_time::SystemProvider;
+    use std::{convert::Infallible, net::SocketAddr, sync::Mutex};
+    use tokio::net::TcpListener;
+    use tokio_stream::wrappers::TcpListenerStream;
+    use trace::{
+        ctx::{SpanContext, SpanId, TraceId},
+        span::{SpanEvent, SpanStatus},
+    };
+
+    type Requests = Arc<Mutex<Vec<ExportTraceServiceRequest>>>;
+
+    /// A mock OTLP/gRPC collector recording all requests.
+    #[derive(Debug, Default)]
+    struct MockCollector {
+        requests: Requests,
+    }
+
+    #[tonic::async_trait]
+    impl TraceService for MockCollector {
+        async fn export(
+            &self,
+            request: tonic::Request<ExportTraceServiceRequest>,
+        ) -> Result<tonic::Response<ExportTraceServiceResponse>, tonic::Status> {
+            self.requests.lock().unwrap().push(request.into_inner());
+            Ok(tonic::Response::new(ExportTraceServiceResponse::default()))
+        }
+    }
+
+    async fn grpc_collector(requests: Requests) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        tokio::spawn(
+            tonic::transport::Server::builder()
+                .add_service(TraceServiceServer::new(MockCollector { requests }))
+                .serve_with_incoming(TcpListenerStream::new(listener)),
+        );
+
+        addr
+    }
+
+    /// A mock OTLP/HTTP collector recording all requests.
+    async fn http_collector(requests: Requests) -> SocketAddr {
+        use hyper::{
+            service::{make_service_fn, service_fn},
+            Body, Request, Response, Server, StatusCode,
+        };
+
+        let make_svc = make_service_fn(move |_| {
+            let requests = Arc::clone(&requests);
+            async move {
+                Ok::<_, Infallible>(service_fn(move |req: Request<Body>| {
+                    let requests = Arc::clone(&requests);
+                    async move {
+                        let content_type = req.headers().get(hyper::header::CONTENT_TYPE);
+                        if req.uri().path() != HTTP_TRACES_PATH
+                            || content_type.map_or(true, |v| v != "application/x-protobuf")
+                        {
+                            return Ok::<_, Infallible>(
+                                Response::builder()
+                                    .status(StatusCode::BAD_REQUEST)
+                                    .body(Body::empty())
+                                    .unwrap(),
+                            );
+                        }
+
+                        let body = hyper::body::to_bytes(req.into_body()).await.unwrap();
+                        let request = ExportTraceServiceRequest::decode(body).unwrap();
+                        requests.lock().unwrap().push(request);
+
+                        let resp = ExportTraceServiceResponse::default().encode_to_vec();
+                        Ok(Response::new(Body::from(resp)))
+                    }
+                }))
+            }
+        });
+
+        let server = Server::bind(&"127.0.0.1:0".parse().unwrap()).serve(make_svc);
+        let addr = server.local_addr();
+        tokio::spawn(server);
+        addr
+    }
+
+    fn test_span() -> Span {
+        let ctx = SpanContext {
+            trace_id: TraceId::new(43434).unwrap(),
+            parent_span_id: None,
+            span_id: SpanId::new(3495993).unwrap(),
+            links: vec![],
+            collector: None,
+            sampled: true,
+        };
+        let mut span = ctx.child("foo");
+        span.ctx.links = vec![(TraceId::new(12).unwrap(), SpanId::new(123).unwrap())];
+        span.status = SpanStatus::Err;
+        span.events = vec![SpanEvent {
+            time: Utc.timestamp_nanos(200000),
+            msg: "hello".into(),
+        }];
+        span.metadata.insert("rows".into(), MetaValue::Int(42));
+        span.start = Some(Utc.timestamp_nanos(100000));
+        span.end = Some(Utc.timestamp_nanos(300000));
+        span
+    }
+
+    fn new_exporter(protocol: OtlpProtocol, addr: SocketAddr) -> OtlpExporter {
+        OtlpExporter::new(
+            protocol,
+            &format!("http://{addr}"),
+            "service_name".to_string(),
+            Arc::new(SystemProvider::new()),
+            NonZeroU64::new(1_000).unwrap(),
+        )
+        .unwrap()
+        .with_resource_attributes(&[OtlpResourceAttribute::new("env", "test")])
+    }
+
+    fn assert_request(request: &ExportTraceServiceRequest, span: &Span, n_spans: usize) {
+        assert_eq!(request.resource_spans.len(), 1);
+        let resource_spans = &request.resource_spans[0];
+
+        let attributes = resource_spans
+            .resource
+            .as_ref()
+            .expect("no resource")
+            .attributes
+            .iter()
+            .map(|kv| {
+                let value = match kv.value.as_ref().unwrap().value.as_ref().unwrap() {
+                    any_value::Value::StringValue(v) => v.clone(),
+                    v => panic!("unexpected attribute value {v:?}"),
+                };
+                (kv.key.as_str(), value)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            attributes,
+            [
+                ("service.name", "service_name".to_string()),
+                ("env", "test".to_string())
+            ]
+        );
+
+        assert_eq!(resource_spans.scope_spans.len(), 1);
+        let spans = &resource_spans.scope_spans[0].spans;
+        assert_eq!(spans.len(), n_spans);
+
+        let got = &spans[0];
+        assert_eq!(got.trace_id, span.ctx.trace_id.get().to_be_bytes());
+        assert_eq!(got.span_id, span.ctx.span_id.get().to_be_bytes());
+        assert_eq!(
+            got.parent_span_id,
+            span.ctx.parent_span_id.unwrap().get().to_be_bytes()
+        );
+        assert_eq!(got.name, "foo");
+        assert_eq!(got.start_time_unix_nano, 100000);
+        assert_eq!(got.end_time_unix_nano, 300000);
+        assert_eq!(got.status.as_ref().unwrap().code, StatusCode::Error as i32);
+
+        assert_eq!(got.links.len(), 1);
+        assert_eq!(got.links[0].span_id, 123_u64.to_be_bytes());
+
+        assert_eq!(
+            got.events,
+            [otlp::span::Event {
+                time_unix_nano: 200000,
+                name: "hello".to_string(),
+                attributes: vec![],
+                dropped_attributes_count: 0,
+            }]
+        );
+
+        assert_eq!(got.attributes.len(), 1);
+        assert_eq!(got.attributes[0].key, "rows");
+        assert_eq!(
+            got.attributes[0].value.as_ref().unwrap().value,
+            Some(any_value::Value::IntValue(42))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_otlp_grpc() {
+        let requests = Requests::default();
+        let addr = grpc_collector(Arc::clone(&requests)).await;
+        let mut exporter = new_exporter(OtlpProtocol::Grpc, addr);
+
+        let span = test_span();
+        exporter.export(vec![span.clone(), span.clone()]).await;
+        exporter.export(vec![span.clone()]).await;
+
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 2);
+        assert_request(&requests[0], &span, 2);
+        assert_request(&requests[1], &span, 1);
+    }
+
+    #[tokio::test]
+    async fn test_otlp_http() {
+        let requests = Requests::default();
+        let addr = http_collector(Arc::clone(&requests)).await;
+        let mut exporter = new_exporter(OtlpProtocol::HttpProtobuf, addr);
+
+        let span = test_span();
+        exporter.export(vec![span.clone(), span.clone()]).await;
+        exporter.export(vec![span.clone()]).await;
+
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 2);
+        assert_request(&requests[0], &span, 2);
+        assert_request(&requests[1], &span, 1);
+    }
+
+    #[tokio::test]
+    async fn test_otlp_unreachable() {
+        // Bind and immediately drop a listener to obtain an unused port.
+        let addr = TcpListener::bind("127.0.0.1:0")
+            .await
+            .unwrap()
+            .local_addr()
+            .unwrap();
+
+        // Export failures must not panic.
+        for protocol in [OtlpProtocol::Grpc, OtlpProtocol::HttpProtobuf] {
+            new_exporter(protocol, addr).export(vec![test_span()]).await;
+        }
+    }
+
+    #[test]
+    fn test_protocol_from_str() {
+        assert_eq!("grpc".parse::<OtlpProtocol>().unwrap(), OtlpProtocol::Grpc);
+        assert_eq!(
+            "http/protobuf".parse::<OtlpProtocol>().unwrap(),
+            OtlpProtocol::HttpProtobuf
+        );
+        "http/json".parse::<OtlpProtocol>().unwrap_err();
+    }
+
+    #[test]
+    fn test_resource_attribute_from_str() {
+        "key=value"
+            .parse::<OtlpResourceAttribute>()
+            .expect("valid form should succeed");
+        "key".parse::<OtlpResourceAttribute>().unwrap_err();
+        "=value".parse::<OtlpResourceAttribute>().unwrap_err();
+    }
+}
@@ -0,0 +1,116 @@
+/// Contains the conversion logic from a `trace::span::Span` to an OTLP span
+use generated_types::opentelemetry::proto::{
+    common::v1::{any_value, AnyValue, KeyValue},
+    trace::v1::{self as otlp, span::SpanKind, status::StatusCode},
+};
+use trace::{
+    ctx::{SpanId, TraceId},
+    span::{MetaValue, Span, SpanEvent, SpanStatus},
+};
+
+/// OTLP encodes trace IDs as 16 big-endian bytes.
+fn trace_id_bytes(id: TraceId) -> Vec<u8> {
+    id.get().to_be_bytes().to_vec()
+}
+
+/// OTLP encodes span IDs as 8 big-endian bytes.
+fn span_id_bytes(id: SpanId) -> Vec<u8> {
+    id.get().to_be_bytes().to_vec()
+}
+
+/// Nanoseconds since the epoch, or 0 (unset) if not representable.
+fn unix_nanos(t: chrono::DateTime<chrono::Utc>) -> u64 {
+    u64::try_from(t.timestamp_nanos()).unwrap_or_default()
+}
+
+impl From<Span> for otlp::Span {
+    fn from(s: Span) -> Self {
+        let start_time_unix_nano = s.start.map(unix_nanos).unwrap_or_default();
+        let end_time_unix_nano = s.end.map(unix_nanos).unwrap_or(start_time_unix_nano);
+
+        let code = match s.status {
+            SpanStatus::Unknown => StatusCode::Unset,
+            SpanStatus::Ok => StatusCode::Ok,
+            SpanStatus::Err => StatusCode::Error,
+        };
+
+        Self {
+            trace_id: trace_id_bytes(s.ctx.trace_id),
+            span_id: span_id_bytes(s.ctx.span_id),
+            trace_state: String::new(),
+            parent_span_id: s.ctx.parent_span_id.map(span_id_bytes).unwrap_or_default(),
+            name: s.name.to_string(),
+            kind: SpanKind::Internal as i32,
+            start_time_unix_nano,
+            end_time_unix_nano,
+            attributes: s
+                .metadata
+                .into_iter()
+                .map(|(key, value)| key_value(key.to_string(), value))
+                .collect(),
+            dropped_attributes_count: 0,
+            events: s.events.into_iter().map(Into::into).collect(),
+            dropped_events_count: 0,
+            links: s
+                .ctx
+                .links
+                .into_iter()
+                .map(|(trace_id, span_id)| otlp::span::Link {
+                    trace_id: trace_id_bytes(trace_id),
+                    span_id: span_id_bytes(span_id),
+                    trace_state: String::new(),
+                    attributes: vec![],
+                    dropped_attributes_count: 0,
+                })
+                .collect(),
+            dropped_links_count: 0,
+            status: Some(otlp::Status {
+                message: String::new(),
+                code: code as i32,
+            }),
+        }
+    }
+}
+
+impl From<SpanEvent> for otlp::span::Event {
+    fn from(event: SpanEvent) -> Self {
+        Self {
+            time_unix_nano: unix_nanos(event.time),
+            name: event.msg.to_string(),
+            attributes: vec![],
+            dropped_attributes_count: 0,
+        }
+    }
+}
+
+/// Build an OTLP attribute from a span metadata entry.
+pub(super) fn key_value(key: String, value: MetaValue) -> KeyValue {
+    let value = match value {
+        MetaValue::String(v) => any_value::Value::StringValue(v.to_string()),
+        MetaValue::Float(v) => any_value::Value::DoubleValue(v),
+        MetaValue::Int(v) => any_value::Value::IntValue(v),
+        MetaValue::Bool(v) => any_value::Value::BoolValue(v),
+    };
+
+    KeyValue {
+        key,
+        value: Some(AnyValue { value: Some(value) }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_id_encoding() {
+        let trace_id = TraceId::new(0x0102030405060708090a0b0c0d0e0f10).unwrap();
+        assert_eq!(
+            trace_id_bytes(trace_id),
+            [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16]
+        );
+
+        let span_id = SpanId::new(0x0102030405060708).unwrap();
+        assert_eq!(span_id_bytes(span_id), [1, 2, 3, 4, 5, 6, 7, 8]);
+    }
+}
@@ -12,7 +12,14 @@ use std::{
 };
 
 #[derive(Debug)]
-pub struct ClosedSegmentFileReader<R>(R);
+pub struct ClosedSegmentFileReader<R> {
+    f: R,
+    /// The number of bytes at the start of the file that have been read and
+    /// validated.
+    valid_bytes: u64,
+    /// Set once a read fails, after which `valid_bytes` no longer advances.
+    corrupt: bool,
+}
 
 impl ClosedSegmentFileReader<BufReader<File>> {
     pub fn from_path(path: impl AsRef<Path>) -> Result<Self> {
@@ -28,34 +35,49 @@ where
     R: Read,
 {
     pub fn new(f: R) -> Self {
-        Self(f)
+        Self {
+            f,
+            valid_bytes: 0,
+            corrupt: false,
+        }
+    }
+
+    /// The number of bytes at the start of the file covering the header and
+    /// all entries successfully read so far.
+    ///
+    /// After a read error, this is the offset at which the first bad entry
+    /// starts.
+    pub fn valid_bytes(&self) -> u64 {
+        self.valid_bytes
     }
 
     fn read_array<const N: usize>(&mut self) -> Result<[u8; N]> {
         let mut data = [0u8; N];
-        self.0
+        self.f
             .read_exact(&mut data)
             .context(UnableToReadArraySnafu { length: N })?;
         Ok(data)
     }
 
     pub fn read_header(&mut self) -> Result<(FileTypeIdentifier, SegmentIdBytes)> {
-        Ok((self.read_array()?, self.read_array()?))
+        let header = (self.read_array()?, self.read_array()?);
+        self.valid_bytes += (header.0.len() + header.1.len()) as u64;
+        Ok(header)
     }
 
     fn one_entry(&mut self) -> Result<Option<SegmentEntry>> {
-        let expected_checksum = match self.0.read_u32::<BigEndian>() {
+        let expected_checksum = match self.f.read_u32::<BigEndian>() {
             Err(ref e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
             other => other.context(UnableToReadChecksumSnafu)?,
         };
 
         let expected_len = self
-            .0
+            .f
             .read_u32::<BigEndian>()
             .context(UnableToReadLengthSnafu)?
             .into();
 
-        let compressed_read = self.0.by_ref().take(expected_len);
+        let compressed_read = self.f.by_ref().take(expected_len);
         let hashing_read = CrcReader::new(compressed_read);
         let mut decompressing_read = FrameDecoder::new(hashing_read);
 
@@ -85,10 +107,19 @@ where
         Ok(Some(SegmentEntry {
             checksum: expected_checksum,
             data,
+            compressed_len: actual_compressed_len,
         }))
     }
 
     pub fn next_batch(&mut self) -> Result<Option<Vec<SequencedWalOp>>> {
+        let res = self.decode_batch();
+        if res.is_err() {
+            self.corrupt = true;
+        }
+        res
+    }
+
+    fn decode_batch(&mut self) -> Result<Option<Vec<SequencedWalOp>>> {
         if let Some(entry) = self.one_entry()? {
             let decoded =
                 ProtoWalOpBatch::decode(&*entry.data).context(UnableToDeserializeDataSnafu)?;
@@ -98,6 +129,11 @@ where
                 ops.push(op.try_into().context(InvalidMessageSnafu)?);
             }
 
+            // The checksum and length prefix, followed by the compressed data.
+            if !self.corrupt {
+                self.valid_bytes += 8 + entry.compressed_len;
+            }
+
             return Ok(Some(ops));
         }
 
@@ -419,6 +455,7 @@ mod tests {
             Self {
                 checksum: fake.checksum(),
                 data: fake.uncompressed_data.clone(),
+                compressed_len: fake.compressed_len().into(),
             }
         }
     }
@@ -120,6 +120,7 @@ impl OpenSegmentFileWriter {
             size: bytes_written
                 .try_into()
                 .expect("bytes_written did not fit in size type"),
+            sequence_numbers: Default::default(),
         })
     }
 }
@@ -16,6 +16,7 @@
 use crate::blocking::{
     ClosedSegmentFileReader as RawClosedSegmentFileReader, OpenSegmentFileWriter,
 };
+use data_types::{sequence_number_set::SequenceNumberSet, SequenceNumber};
 use generated_types::{
     google::{FieldViolation, OptionalField},
     influxdata::iox::wal::v1::{
@@ -104,6 +105,11 @@ pub enum Error {
         source: blocking::ReaderError,
     },
 
+    UnableToTruncateSegment {
+        source: io::Error,
+        path: PathBuf,
+    },
+
     InvalidId {
         filename: String,
         source: std::num::ParseIntError,
@@ -251,6 +257,7 @@ impl Wal {
                     id,
                     path: child.path(),
                     size: metadata.len(),
+                    sequence_numbers: SequenceNumberSet::default(),
                 };
                 closed_segments.insert(id, segment);
             }
@@ -274,6 +281,7 @@ impl Wal {
             segments: Mutex::new(Segments {
                 closed_segments,
                 open_segment,
+                open_segment_sequence_numbers: SequenceNumberSet::default(),
             }),
             next_id_source,
             buffer: Mutex::new(buffer),
@@ -316,7 +324,8 @@ impl Wal {
         let mut segments = self.segments.lock();
 
         let closed = std::mem::replace(&mut segments.open_segment, new_open_segment);
-        let closed = closed.close().expect("should convert to closed segmet");
+        let mut closed = closed.close().expect("should convert to closed segmet");
+        closed.sequence_numbers = std::mem::take(&mut segments.open_segment_sequence_numbers);
 
         let previous_value = segments.closed_segments.insert(closed.id(), closed.clone());
         assert!(
@@ -344,6 +353,11 @@ impl Wal {
             };
 
             // do the encoding while we're not holding any locks
+            let sequence_numbers: Vec<_> = filled_buffer
+                .ops
+                .iter()
+                .map(|op| SequenceNumber::new(op.sequence_number as i64))
+                .collect();
             let ops: Vec<_> = filled_buffer
                 .ops
                 .into_iter()
@@ -356,7 +370,15 @@ impl Wal {
             let res = {
                 let mut segments = self.segments.lock();
                 match segments.open_segment.write(&encoded) {
-                    Ok(summary) => WriteResult::Ok(summary),
+                    Ok(summary) => {
+                        // Record the ops as part of the open segment while
+                        // still holding the lock, so that a concurrent
+                        // rotation attributes them to the right segment.
+                        for n in sequence_numbers {
+                            segments.open_segment_sequence_numbers.add(n);
+                        }
+                        WriteResult::Ok(summary)
+                    }
                     Err(e) => WriteResult::Err(e.to_string()),
                 }
             };
@@ -392,6 +414,8 @@ impl std::fmt::Debug for Wal {
 struct Segments {
     closed_segments: BTreeMap<SegmentId, ClosedSegment>,
     open_segment: OpenSegmentFileWriter,
+    /// The sequence numbers of all ops written to `open_segment`.
+    open_segment_sequence_numbers: SequenceNumberSet,
 }
 
 struct WalBuffer {
@@ -456,6 +480,8 @@ pub struct SegmentEntry {
     checksum: u32,
     /// The uncompressed data
     pub data: Vec<u8>,
+    /// The length of the compressed data in the segment file
+    compressed_len: u64,
 }
 
 /// Result from a WAL flush and fsync. This needs to be cloneable which is why it doesn't
@@ -498,6 +524,16 @@ impl ClosedSegmentFileReader {
         self.id
     }
 
+    /// The number of bytes at the start of the segment file covering the header and all batches
+    /// read without error so far.
+    ///
+    /// After [`Self::next_batch`] returns an error, this is the offset of the first corrupt
+    /// entry - truncating the file to this length drops the corrupt tail, see
+    /// [`truncate_segment`].
+    pub fn valid_bytes(&self) -> u64 {
+        self.file.valid_bytes()
+    }
+
     /// Open the segment file and read its header, ensuring it is a segment file and reading its id.
     pub fn from_path(path: impl AsRef<Path>) -> Result<Self> {
         let path = path.as_ref();
@@ -525,6 +561,20 @@ impl std::fmt::Debug for ClosedSegmentFileReader {
     }
 }
 
+/// Truncate the closed segment file at `path` to `len` bytes, discarding all entries after it.
+///
+/// This must not be used on segments of a running [`Wal`].
+pub fn truncate_segment(path: impl AsRef<Path>, len: u64) -> Result<()> {
+    let path = path.as_ref();
+    let f = std::fs::OpenOptions::new()
+        .write(true)
+        .open(path)
+        .context(UnableToTruncateSegmentSnafu { path })?;
+    f.set_len(len)
+        .context(UnableToTruncateSegmentSnafu { path })?;
+    f.sync_all().context(UnableToTruncateSegmentSnafu { path })
+}
+
 /// Metadata for a WAL segment that is no longer accepting writes, but can be read for replay
 /// purposes.
 #[derive(Debug, Clone)]
@@ -532,6 +582,7 @@ pub struct ClosedSegment {
     id: SegmentId,
     path: PathBuf,
     size: u64,
+    sequence_numbers: SequenceNumberSet,
 }
 
 impl ClosedSegment {
@@ -542,6 +593,14 @@ impl ClosedSegment {
     pub fn size(&self) -> u64 {
         self.size
     }
+
+    /// The sequence numbers of the ops written to this segment.
+    ///
+    /// This is only known for segments closed by [`Wal::rotate()`] - segments
+    /// discovered on disk when opening the [`Wal`] return an empty set.
+    pub fn sequence_numbers(&self) -> &SequenceNumberSet {
+        &self.sequence_numbers
+    }
 }
 
 #[cfg(test)]
@@ -594,12 +653,66 @@ mod tests {
             ops.append(&mut batch);
         }
         assert_eq!(vec![op1, op2, op3, op4], ops);
+
+        // The closed segment records the sequence numbers it contains.
+        assert_eq!(
+            closed.sequence_numbers().iter().collect::<Vec<_>>(),
+            [0, 1, 2].map(SequenceNumber::new)
+        );
+
+        // Writes after the rotation are attributed to the next segment.
+        let op5 = SequencedWalOp {
+            sequence_number: 3,
+            op: WalOp::Write(test_data("m1,t=foo v=3i 3")),
+        };
+        wal.write_op(op5).changed().await.unwrap();
+        let closed = wal.rotate().unwrap();
+        assert_eq!(
+            closed.sequence_numbers().iter().collect::<Vec<_>>(),
+            [SequenceNumber::new(3)]
+        );
     }
 
     // open wal with files that aren't segments (should log and skip)
 
     // read segment works even if last entry is truncated
 
+    #[tokio::test]
+    async fn truncate_corrupt_tail() {
+        let dir = test_helpers::tmp_dir().unwrap();
+        let wal = Wal::new(&dir.path()).await.unwrap();
+
+        let op = SequencedWalOp {
+            sequence_number: 0,
+            op: WalOp::Write(test_data("m1,t=foo v=1i 1")),
+        };
+        wal.write_op(op.clone()).changed().await.unwrap();
+        let closed = wal.rotate().unwrap();
+        let path = build_segment_path(dir.path(), closed.id());
+
+        // Append a partially written entry.
+        let good_len = std::fs::metadata(&path).unwrap().len();
+        {
+            use std::io::Write;
+            let mut f = std::fs::OpenOptions::new()
+                .append(true)
+                .open(&path)
+                .unwrap();
+            f.write_all(&[0, 1, 2, 3, 0, 0, 0, 42, 7]).unwrap();
+        }
+
+        let mut reader = ClosedSegmentFileReader::from_path(&path).unwrap();
+        assert_eq!(reader.next_batch().unwrap().unwrap(), vec![op.clone()]);
+        assert!(reader.next_batch().is_err());
+        assert_eq!(reader.valid_bytes(), good_len);
+
+        truncate_segment(&path, reader.valid_bytes()).unwrap();
+
+        let mut reader = ClosedSegmentFileReader::from_path(&path).unwrap();
+        assert_eq!(reader.next_batch().unwrap().unwrap(), vec![op]);
+        assert!(reader.next_batch().unwrap().is_none());
+    }
+
     #[tokio::test]
     async fn rotate_without_writes() {
         let dir = test_helpers::tmp_dir().unwrap();
@@ -617,6 +730,7 @@ mod tests {
         // No writes, but rotating is totally fine
         let closed_segment_details = wal.rotate().unwrap();
         assert_eq!(closed_segment_details.size(), 16);
+        assert!(closed_segment_details.sequence_numbers().is_empty());
 
         // There's one closed segment
         let closed = wal.closed_segments();